@@ -0,0 +1,812 @@
+/// age v1 (age-encryption.org/v1) format, for interoperability with age/rage
+/// X25519 and scrypt recipients, binary and ASCII-armored encodings. Built on the
+/// primitives already in the tree (X25519, HKDF/HMAC-SHA256, ChaCha20-Poly1305);
+/// the random file key never leaves this module.
+use base64::{engine::general_purpose, Engine as _};
+use chacha20poly1305::aead::{Aead, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Nonce};
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use sha2::Sha256;
+use std::io::{BufRead, Read, Write};
+use x25519_dalek::{EphemeralSecret, PublicKey, StaticSecret};
+use zeroize::Zeroize;
+
+use crate::filecrypt::read_full;
+
+const VERSION_LINE: &str = "age-encryption.org/v1";
+
+pub const ARMOR_BEGIN: &str = "-----BEGIN AGE ENCRYPTED FILE-----";
+const ARMOR_END: &str = "-----END AGE ENCRYPTED FILE-----";
+
+/// Bech32 prefixes of recipients (`age1...`) and identities (`AGE-SECRET-KEY-1...`)
+const RECIPIENT_HRP: &str = "age";
+const IDENTITY_HRP: &str = "age-secret-key-";
+
+const X25519_INFO: &[u8] = b"age-encryption.org/v1/X25519";
+const SCRYPT_LABEL: &[u8] = b"age-encryption.org/v1/scrypt";
+
+/// scrypt work factor used when encrypting (2^18, age's default)
+const SCRYPT_LOG_N: u8 = 18;
+
+/// Highest scrypt work factor accepted when decrypting (2^20 needs 1 GiB)
+const MAX_SCRYPT_LOG_N: u8 = 20;
+
+/// Plaintext bytes per payload chunk
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Poly1305 tag appended to every chunk
+const TAG_LEN: usize = 16;
+
+/// Largest header accepted when decrypting
+const MAX_HEADER_SIZE: usize = 64 * 1024;
+
+/// Who can decrypt a new age file
+pub enum Recipients<'a> {
+    /// `age1...` public keys
+    Keys(Vec<PublicKey>),
+    /// A passphrase (scrypt recipient)
+    Passphrase(&'a str),
+}
+
+/// What is available to decrypt an age file
+pub enum Unlock<'a> {
+    Identities(&'a [AgeIdentity]),
+    Passphrase(&'a str),
+}
+
+/// X25519 identity (private key), zeroized on drop
+pub struct AgeIdentity {
+    secret: StaticSecret,
+}
+
+impl AgeIdentity {
+    pub fn generate() -> Self {
+        AgeIdentity {
+            secret: StaticSecret::random_from_rng(OsRng),
+        }
+    }
+
+    /// Identity from raw key material, e.g. a session subkey
+    pub fn from_bytes(key: &[u8; 32]) -> Self {
+        AgeIdentity {
+            secret: StaticSecret::from(*key),
+        }
+    }
+
+    /// Parse an `AGE-SECRET-KEY-1...` identity
+    pub fn parse(encoded: &str) -> Result<Self, String> {
+        let mut bytes =
+            bech32_decode(encoded.trim(), IDENTITY_HRP).ok_or("age: invalid identity.")?;
+        if bytes.len() != 32 {
+            bytes.zeroize();
+            return Err("age: invalid identity.".to_string());
+        }
+        let mut key = [0u8; 32];
+        key.copy_from_slice(&bytes);
+        bytes.zeroize();
+        let secret = StaticSecret::from(key);
+        key.zeroize();
+        Ok(AgeIdentity { secret })
+    }
+
+    /// `AGE-SECRET-KEY-1...` encoding; zeroize it after use
+    pub fn encode(&self) -> String {
+        let mut encoded = bech32_encode(IDENTITY_HRP, self.secret.as_bytes());
+        encoded.make_ascii_uppercase();
+        encoded
+    }
+
+    /// The matching `age1...` recipient
+    pub fn recipient(&self) -> String {
+        encode_recipient(&PublicKey::from(&self.secret))
+    }
+}
+
+/// `age1...` encoding of an X25519 public key
+pub fn encode_recipient(key: &PublicKey) -> String {
+    bech32_encode(RECIPIENT_HRP, key.as_bytes())
+}
+
+/// Parse an `age1...` recipient
+pub fn parse_recipient(encoded: &str) -> Result<PublicKey, String> {
+    bech32_decode(encoded, RECIPIENT_HRP)
+        .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+        .map(PublicKey::from)
+        .ok_or_else(|| format!("age: invalid recipient '{}'.", encoded))
+}
+
+/// Parse an identity file: one `AGE-SECRET-KEY-1...` per line, `#` comments
+pub fn parse_identities(text: &str) -> Result<Vec<AgeIdentity>, String> {
+    let identities = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(AgeIdentity::parse)
+        .collect::<Result<Vec<_>, _>>()?;
+    if identities.is_empty() {
+        return Err("age: no identities found.".to_string());
+    }
+    Ok(identities)
+}
+
+/// True if `data` starts like a binary or armored age file
+pub fn is_age(data: &[u8]) -> bool {
+    data.starts_with(VERSION_LINE.as_bytes())
+        || data.trim_ascii_start().starts_with(ARMOR_BEGIN.as_bytes())
+}
+
+/// Whether the (binary) age file read from `input` is encrypted to a passphrase
+pub fn is_passphrase_protected(input: &mut impl BufRead) -> Result<bool, String> {
+    let header = read_header(input)?;
+    Ok(header.stanzas.iter().any(|stanza| stanza.kind == "scrypt"))
+}
+
+/// Encrypt everything read from `input` to `recipients`, writing binary age to `output`
+pub fn encrypt(
+    recipients: &Recipients,
+    input: &mut impl Read,
+    output: &mut impl Write,
+) -> Result<(), String> {
+    let mut file_key = [0u8; 16];
+    OsRng.fill_bytes(&mut file_key);
+
+    let result = wrap_file_key(recipients, &file_key).and_then(|stanzas| {
+        let header = encode_header(&stanzas, &file_key)?;
+        let mut nonce = [0u8; 16];
+        OsRng.fill_bytes(&mut nonce);
+        output
+            .write_all(&header)
+            .and_then(|_| output.write_all(&nonce))
+            .map_err(|e| format!("age: {}", e))?;
+        encrypt_payload(payload_cipher(&file_key, &nonce)?, input, output)
+    });
+
+    file_key.zeroize();
+    result
+}
+
+/// Decrypt binary age read from `input`, writing the plaintext to `output`.
+/// Output written before a failure is unauthenticated and must be discarded.
+pub fn decrypt(
+    unlock: &Unlock,
+    input: &mut impl BufRead,
+    output: &mut impl Write,
+) -> Result<(), String> {
+    let header = read_header(input)?;
+    let mut file_key = unwrap_file_key(&header, unlock)?;
+
+    let result = verify_header_mac(&file_key, &header).and_then(|_| {
+        let mut nonce = [0u8; 16];
+        input
+            .read_exact(&mut nonce)
+            .map_err(|_| "age: truncated payload.".to_string())?;
+        decrypt_payload(payload_cipher(&file_key, &nonce)?, input, output)
+    });
+
+    file_key.zeroize();
+    result
+}
+
+/// ASCII armor (PEM-style, base64 in 64-column lines)
+pub fn armor(data: &[u8]) -> String {
+    let encoded = general_purpose::STANDARD.encode(data);
+    let mut armored = String::with_capacity(encoded.len() + encoded.len() / 64 + 72);
+    armored.push_str(ARMOR_BEGIN);
+    armored.push('\n');
+    let mut rest = encoded.as_str();
+    while !rest.is_empty() {
+        let (line, tail) = rest.split_at(rest.len().min(64));
+        armored.push_str(line);
+        armored.push('\n');
+        rest = tail;
+    }
+    armored.push_str(ARMOR_END);
+    armored.push('\n');
+    armored
+}
+
+/// Strip ASCII armor back to binary age
+pub fn dearmor(text: &str) -> Result<Vec<u8>, String> {
+    let body = text
+        .trim()
+        .strip_prefix(ARMOR_BEGIN)
+        .and_then(|rest| rest.strip_suffix(ARMOR_END))
+        .ok_or("age: malformed armor.")?;
+    let encoded: String = body.lines().map(str::trim).collect();
+    general_purpose::STANDARD
+        .decode(encoded)
+        .map_err(|_| "age: malformed armor.".to_string())
+}
+
+/// One recipient stanza: `-> <kind> <args...>` followed by a base64 body
+struct Stanza {
+    kind: String,
+    args: Vec<String>,
+    body: Vec<u8>,
+}
+
+struct Header {
+    stanzas: Vec<Stanza>,
+    mac: Vec<u8>,
+    /// Header bytes covered by the MAC (everything up to and including `---`)
+    covered: Vec<u8>,
+}
+
+fn malformed() -> String {
+    "age: malformed header.".to_string()
+}
+
+fn wrap_file_key(recipients: &Recipients, file_key: &[u8; 16]) -> Result<Vec<Stanza>, String> {
+    match recipients {
+        Recipients::Keys(keys) if keys.is_empty() => Err("age: no recipients.".to_string()),
+        Recipients::Keys(keys) => keys.iter().map(|key| x25519_wrap(key, file_key)).collect(),
+        Recipients::Passphrase(passphrase) => Ok(vec![scrypt_wrap(passphrase, file_key)?]),
+    }
+}
+
+fn x25519_wrap(recipient: &PublicKey, file_key: &[u8; 16]) -> Result<Stanza, String> {
+    let ephemeral = EphemeralSecret::random_from_rng(OsRng);
+    let share = PublicKey::from(&ephemeral);
+    let shared = ephemeral.diffie_hellman(recipient);
+    if !shared.was_contributory() {
+        return Err("age: invalid recipient key.".to_string());
+    }
+
+    let mut key = x25519_wrap_key(shared.as_bytes(), &share, recipient)?;
+    let body = seal_file_key(&key, file_key);
+    key.zeroize();
+
+    Ok(Stanza {
+        kind: "X25519".to_string(),
+        args: vec![general_purpose::STANDARD_NO_PAD.encode(share.as_bytes())],
+        body: body?,
+    })
+}
+
+fn x25519_unwrap(stanza: &Stanza, identity: &AgeIdentity) -> Result<Option<[u8; 16]>, String> {
+    if stanza.args.len() != 1 || stanza.body.len() != 32 {
+        return Err(malformed());
+    }
+    let share = general_purpose::STANDARD_NO_PAD
+        .decode(&stanza.args[0])
+        .ok()
+        .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+        .map(PublicKey::from)
+        .ok_or_else(malformed)?;
+
+    let shared = identity.secret.diffie_hellman(&share);
+    if !shared.was_contributory() {
+        return Err(malformed());
+    }
+
+    let mut key = x25519_wrap_key(
+        shared.as_bytes(),
+        &share,
+        &PublicKey::from(&identity.secret),
+    )?;
+    let file_key = open_file_key(&key, &stanza.body);
+    key.zeroize();
+    Ok(file_key)
+}
+
+/// HKDF-SHA256 with `ephemeral share | recipient` as salt
+fn x25519_wrap_key(
+    shared: &[u8; 32],
+    share: &PublicKey,
+    recipient: &PublicKey,
+) -> Result<[u8; 32], String> {
+    let mut salt = [0u8; 64];
+    salt[..32].copy_from_slice(share.as_bytes());
+    salt[32..].copy_from_slice(recipient.as_bytes());
+    hkdf_sha256(Some(&salt), shared, X25519_INFO)
+}
+
+fn scrypt_wrap(passphrase: &str, file_key: &[u8; 16]) -> Result<Stanza, String> {
+    let mut salt = [0u8; 16];
+    OsRng.fill_bytes(&mut salt);
+
+    let mut key = scrypt_wrap_key(passphrase, &salt, SCRYPT_LOG_N);
+    let body = seal_file_key(&key, file_key);
+    key.zeroize();
+
+    Ok(Stanza {
+        kind: "scrypt".to_string(),
+        args: vec![
+            general_purpose::STANDARD_NO_PAD.encode(salt),
+            SCRYPT_LOG_N.to_string(),
+        ],
+        body: body?,
+    })
+}
+
+fn scrypt_unwrap(stanza: &Stanza, passphrase: &str) -> Result<[u8; 16], String> {
+    if stanza.args.len() != 2 || stanza.body.len() != 32 {
+        return Err(malformed());
+    }
+    let salt = general_purpose::STANDARD_NO_PAD
+        .decode(&stanza.args[0])
+        .ok()
+        .filter(|salt| salt.len() == 16)
+        .ok_or_else(malformed)?;
+    let log_n = &stanza.args[1];
+    if !log_n.bytes().all(|b| b.is_ascii_digit()) || log_n.starts_with('0') {
+        return Err(malformed());
+    }
+    let log_n: u8 = log_n.parse().map_err(|_| malformed())?;
+    if log_n > MAX_SCRYPT_LOG_N {
+        return Err(format!(
+            "age: scrypt work factor 2^{} exceeds the limit of 2^{}.",
+            log_n, MAX_SCRYPT_LOG_N
+        ));
+    }
+
+    let mut key = scrypt_wrap_key(passphrase, &salt, log_n);
+    let file_key = open_file_key(&key, &stanza.body);
+    key.zeroize();
+    file_key.ok_or_else(|| "age: wrong passphrase.".to_string())
+}
+
+/// scrypt (r = 8, p = 1) over `age-encryption.org/v1/scrypt | salt`
+fn scrypt_wrap_key(passphrase: &str, salt: &[u8], log_n: u8) -> [u8; 32] {
+    let mut labelled_salt = SCRYPT_LABEL.to_vec();
+    labelled_salt.extend_from_slice(salt);
+    let mut key = [0u8; 32];
+    scrypt(passphrase.as_bytes(), &labelled_salt, log_n, &mut key);
+    key
+}
+
+fn unwrap_file_key(header: &Header, unlock: &Unlock) -> Result<[u8; 16], String> {
+    let passphrase_protected = header.stanzas.iter().any(|stanza| stanza.kind == "scrypt");
+    if passphrase_protected && header.stanzas.len() != 1 {
+        return Err("age: an scrypt recipient must be the only recipient.".to_string());
+    }
+
+    match unlock {
+        Unlock::Passphrase(passphrase) if passphrase_protected => {
+            scrypt_unwrap(&header.stanzas[0], passphrase)
+        }
+        Unlock::Passphrase(_) => {
+            Err("age: not passphrase-encrypted; an identity is needed.".to_string())
+        }
+        Unlock::Identities(_) if passphrase_protected => {
+            Err("age: passphrase-encrypted; a passphrase is needed.".to_string())
+        }
+        Unlock::Identities(identities) => {
+            for stanza in header.stanzas.iter().filter(|s| s.kind == "X25519") {
+                for identity in identities.iter() {
+                    if let Some(file_key) = x25519_unwrap(stanza, identity)? {
+                        return Ok(file_key);
+                    }
+                }
+            }
+            Err("age: no identity matches any recipient.".to_string())
+        }
+    }
+}
+
+/// Wrap the file key under a stanza key (the key is single-use, so the nonce is zero)
+fn seal_file_key(key: &[u8; 32], file_key: &[u8; 16]) -> Result<Vec<u8>, String> {
+    ChaCha20Poly1305::new(key.into())
+        .encrypt(&Nonce::default(), file_key.as_slice())
+        .map_err(|_| "age: encryption failed.".to_string())
+}
+
+fn open_file_key(key: &[u8; 32], body: &[u8]) -> Option<[u8; 16]> {
+    let mut opened = ChaCha20Poly1305::new(key.into())
+        .decrypt(&Nonce::default(), body)
+        .ok()?;
+    let file_key = <[u8; 16]>::try_from(opened.as_slice()).ok();
+    opened.zeroize();
+    file_key
+}
+
+fn hkdf_sha256(salt: Option<&[u8]>, ikm: &[u8], info: &[u8]) -> Result<[u8; 32], String> {
+    let mut key = [0u8; 32];
+    Hkdf::<Sha256>::new(salt, ikm)
+        .expand(info, &mut key)
+        .map_err(|_| "age: key derivation failed.".to_string())?;
+    Ok(key)
+}
+
+/// HMAC-SHA256 keyed by HKDF(file key, "header"), loaded with the covered header bytes
+fn header_mac(file_key: &[u8; 16], covered: &[u8]) -> Result<Hmac<Sha256>, String> {
+    let mut key = hkdf_sha256(None, file_key, b"header")?;
+    let mac = <Hmac<Sha256> as Mac>::new_from_slice(&key);
+    key.zeroize();
+    let mut mac = mac.map_err(|_| "age: key derivation failed.".to_string())?;
+    mac.update(covered);
+    Ok(mac)
+}
+
+fn verify_header_mac(file_key: &[u8; 16], header: &Header) -> Result<(), String> {
+    header_mac(file_key, &header.covered)?
+        .verify_slice(&header.mac)
+        .map_err(|_| "age: header authentication failed.".to_string())
+}
+
+fn encode_header(stanzas: &[Stanza], file_key: &[u8; 16]) -> Result<Vec<u8>, String> {
+    let mut header = String::new();
+    header.push_str(VERSION_LINE);
+    header.push('\n');
+    for stanza in stanzas {
+        header.push_str("-> ");
+        header.push_str(&stanza.kind);
+        for arg in &stanza.args {
+            header.push(' ');
+            header.push_str(arg);
+        }
+        header.push('\n');
+
+        // Full 64-column lines, always followed by a shorter (possibly empty) one
+        let body = general_purpose::STANDARD_NO_PAD.encode(&stanza.body);
+        let mut rest = body.as_str();
+        while rest.len() >= 64 {
+            header.push_str(&rest[..64]);
+            header.push('\n');
+            rest = &rest[64..];
+        }
+        header.push_str(rest);
+        header.push('\n');
+    }
+    header.push_str("---");
+
+    let mac = header_mac(file_key, header.as_bytes())?
+        .finalize()
+        .into_bytes();
+    header.push(' ');
+    header.push_str(&general_purpose::STANDARD_NO_PAD.encode(mac));
+    header.push('\n');
+    Ok(header.into_bytes())
+}
+
+fn read_header(input: &mut impl BufRead) -> Result<Header, String> {
+    let mut raw = Vec::new();
+    if next_line(input, &mut raw)? != VERSION_LINE {
+        return Err("age: not an age file, or an unsupported version.".to_string());
+    }
+
+    let mut stanzas = Vec::new();
+    loop {
+        let line_start = raw.len();
+        let line = next_line(input, &mut raw)?;
+        if let Some(mac) = line.strip_prefix("--- ") {
+            let mac = general_purpose::STANDARD_NO_PAD
+                .decode(mac)
+                .map_err(|_| malformed())?;
+            raw.truncate(line_start + 3);
+            return Ok(Header {
+                stanzas,
+                mac,
+                covered: raw,
+            });
+        }
+
+        let mut words = line.strip_prefix("-> ").ok_or_else(malformed)?.split(' ');
+        let kind = words.next().unwrap_or_default().to_string();
+        let args: Vec<String> = words.map(str::to_string).collect();
+        if kind.is_empty() || args.iter().any(String::is_empty) {
+            return Err(malformed());
+        }
+
+        let mut body = String::new();
+        loop {
+            let body_line = next_line(input, &mut raw)?;
+            if body_line.len() > 64 {
+                return Err(malformed());
+            }
+            body.push_str(&body_line);
+            if body_line.len() < 64 {
+                break;
+            }
+        }
+        let body = general_purpose::STANDARD_NO_PAD
+            .decode(body)
+            .map_err(|_| malformed())?;
+        stanzas.push(Stanza { kind, args, body });
+    }
+}
+
+/// Read one `\n`-terminated header line, appending its raw bytes to `raw`
+fn next_line(input: &mut impl BufRead, raw: &mut Vec<u8>) -> Result<String, String> {
+    let start = raw.len();
+    let limit = MAX_HEADER_SIZE.saturating_sub(start) as u64;
+    input
+        .by_ref()
+        .take(limit)
+        .read_until(b'\n', raw)
+        .map_err(|e| format!("age: {}", e))?;
+    if raw.len() == start || raw.last() != Some(&b'\n') {
+        return Err("age: truncated or oversized header.".to_string());
+    }
+    String::from_utf8(raw[start..raw.len() - 1].to_vec()).map_err(|_| malformed())
+}
+
+/// Payload key: HKDF(file key, salt = payload nonce, "payload")
+fn payload_cipher(file_key: &[u8; 16], nonce: &[u8; 16]) -> Result<ChaCha20Poly1305, String> {
+    let mut key = hkdf_sha256(Some(nonce), file_key, b"payload")?;
+    let cipher = ChaCha20Poly1305::new(&key.into());
+    key.zeroize();
+    Ok(cipher)
+}
+
+/// 11-byte big-endian chunk counter followed by the last-chunk flag
+fn chunk_nonce(counter: u64, last: bool) -> Nonce {
+    let mut nonce = [0u8; 12];
+    nonce[3..11].copy_from_slice(&counter.to_be_bytes());
+    nonce[11] = last as u8;
+    Nonce::from(nonce)
+}
+
+/// STREAM-encrypt `input`. One byte of lookahead decides whether a full chunk is the
+/// last one: age only allows an empty final chunk when the whole payload is empty.
+fn encrypt_payload(
+    cipher: ChaCha20Poly1305,
+    input: &mut impl Read,
+    output: &mut impl Write,
+) -> Result<(), String> {
+    let mut buffer = vec![0u8; CHUNK_SIZE + 1];
+    let mut filled = 0;
+    let mut counter = 0u64;
+
+    let result = loop {
+        match read_full(input, &mut buffer[filled..]) {
+            Ok(n) => filled += n,
+            Err(e) => break Err(format!("age: {}", e)),
+        }
+        let last = filled <= CHUNK_SIZE;
+        let chunk = match cipher.encrypt(
+            &chunk_nonce(counter, last),
+            &buffer[..filled.min(CHUNK_SIZE)],
+        ) {
+            Ok(chunk) => chunk,
+            Err(_) => break Err("age: encryption failed.".to_string()),
+        };
+        if let Err(e) = output.write_all(&chunk) {
+            break Err(format!("age: {}", e));
+        }
+        if last {
+            break Ok(());
+        }
+        buffer[0] = buffer[CHUNK_SIZE];
+        filled = 1;
+        counter += 1;
+    };
+
+    buffer.zeroize();
+    result
+}
+
+fn decrypt_payload(
+    cipher: ChaCha20Poly1305,
+    input: &mut impl Read,
+    output: &mut impl Write,
+) -> Result<(), String> {
+    const SEALED_CHUNK: usize = CHUNK_SIZE + TAG_LEN;
+    let mut buffer = vec![0u8; SEALED_CHUNK + 1];
+    let mut filled = 0;
+    let mut counter = 0u64;
+
+    loop {
+        filled += read_full(input, &mut buffer[filled..]).map_err(|e| format!("age: {}", e))?;
+        let last = filled <= SEALED_CHUNK;
+        let sealed = &buffer[..filled.min(SEALED_CHUNK)];
+        if last && counter > 0 && sealed.len() == TAG_LEN {
+            return Err("age: payload ends with an empty chunk.".to_string());
+        }
+
+        let mut chunk = cipher
+            .decrypt(&chunk_nonce(counter, last), sealed)
+            .map_err(|_| "age: payload authentication failed (corrupted or truncated).")?;
+        let written = output.write_all(&chunk);
+        chunk.zeroize();
+        written.map_err(|e| format!("age: {}", e))?;
+
+        if last {
+            return Ok(());
+        }
+        buffer[0] = buffer[SEALED_CHUNK];
+        filled = 1;
+        counter += 1;
+    }
+}
+
+const BECH32_CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+fn bech32_polymod(values: &[u8]) -> u32 {
+    const GENERATOR: [u32; 5] = [0x3b6a57b2, 0x26508e6d, 0x1ea119fa, 0x3d4233dd, 0x2a1462b3];
+    let mut checksum = 1u32;
+    for &value in values {
+        let top = checksum >> 25;
+        checksum = ((checksum & 0x1ffffff) << 5) ^ value as u32;
+        for (i, generator) in GENERATOR.iter().enumerate() {
+            if (top >> i) & 1 == 1 {
+                checksum ^= generator;
+            }
+        }
+    }
+    checksum
+}
+
+fn bech32_hrp_expand(hrp: &str) -> Vec<u8> {
+    hrp.bytes()
+        .map(|b| b >> 5)
+        .chain([0])
+        .chain(hrp.bytes().map(|b| b & 31))
+        .collect()
+}
+
+/// Regroup `data` from `from`-bit to `to`-bit values
+fn convert_bits(data: &[u8], from: u32, to: u32, pad: bool) -> Option<Vec<u8>> {
+    let max_value = (1u32 << to) - 1;
+    let max_acc = (1u32 << (from + to - 1)) - 1;
+    let mut acc = 0u32;
+    let mut bits = 0u32;
+    let mut out = Vec::with_capacity(data.len() * from as usize / to as usize + 1);
+    for &value in data {
+        acc = ((acc << from) | value as u32) & max_acc;
+        bits += from;
+        while bits >= to {
+            bits -= to;
+            out.push(((acc >> bits) & max_value) as u8);
+        }
+    }
+    if pad {
+        if bits > 0 {
+            out.push(((acc << (to - bits)) & max_value) as u8);
+        }
+    } else if bits >= from || ((acc << (to - bits)) & max_value) != 0 {
+        out.zeroize();
+        return None;
+    }
+    Some(out)
+}
+
+/// Lowercase Bech32 (BIP 173) encoding, without the 90-character limit
+fn bech32_encode(hrp: &str, data: &[u8]) -> String {
+    let mut values = convert_bits(data, 8, 5, true).unwrap_or_default();
+    let mut checked = bech32_hrp_expand(hrp);
+    checked.extend_from_slice(&values);
+    checked.extend_from_slice(&[0; 6]);
+    let checksum = bech32_polymod(&checked) ^ 1;
+    values.extend((0..6).map(|i| ((checksum >> (5 * (5 - i))) & 31) as u8));
+
+    let mut encoded = String::with_capacity(hrp.len() + 1 + values.len());
+    encoded.push_str(hrp);
+    encoded.push('1');
+    encoded.extend(values.iter().map(|&v| BECH32_CHARSET[v as usize] as char));
+    values.zeroize();
+    checked.zeroize();
+    encoded
+}
+
+/// Decode Bech32 with the expected (case-insensitive) human-readable part
+fn bech32_decode(encoded: &str, hrp: &str) -> Option<Vec<u8>> {
+    if encoded.bytes().any(|b| b.is_ascii_lowercase())
+        && encoded.bytes().any(|b| b.is_ascii_uppercase())
+    {
+        return None;
+    }
+    let (found_hrp, data) = encoded.rsplit_once('1')?;
+    if !found_hrp.eq_ignore_ascii_case(hrp) || data.len() < 6 {
+        return None;
+    }
+
+    let mut values = bech32_hrp_expand(hrp);
+    let data_start = values.len();
+    for c in data.bytes() {
+        let c = c.to_ascii_lowercase();
+        match BECH32_CHARSET.iter().position(|&x| x == c) {
+            Some(value) => values.push(value as u8),
+            None => {
+                values.zeroize();
+                return None;
+            }
+        }
+    }
+
+    let decoded = if bech32_polymod(&values) == 1 {
+        convert_bits(&values[data_start..values.len() - 6], 5, 8, false)
+    } else {
+        None
+    };
+    values.zeroize();
+    decoded
+}
+
+/// scrypt with r = 8, p = 1 (all age uses)
+fn scrypt(password: &[u8], salt: &[u8], log_n: u8, output: &mut [u8]) {
+    const R: usize = 8;
+    const BLOCK_WORDS: usize = 32 * R;
+    let n = 1usize << log_n;
+
+    let mut block = vec![0u8; 4 * BLOCK_WORDS];
+    pbkdf2_sha256(password, salt, &mut block);
+    let mut x: Vec<u32> = block
+        .chunks_exact(4)
+        .map(|w| u32::from_le_bytes([w[0], w[1], w[2], w[3]]))
+        .collect();
+    let mut scratch = vec![0u32; BLOCK_WORDS];
+
+    // ROMix
+    let mut v = vec![0u32; BLOCK_WORDS * n];
+    for i in 0..n {
+        v[i * BLOCK_WORDS..(i + 1) * BLOCK_WORDS].copy_from_slice(&x);
+        block_mix(&mut x, &mut scratch);
+    }
+    for _ in 0..n {
+        let j = x[BLOCK_WORDS - 16] as usize & (n - 1);
+        for (word, mixed) in x.iter_mut().zip(&v[j * BLOCK_WORDS..(j + 1) * BLOCK_WORDS]) {
+            *word ^= mixed;
+        }
+        block_mix(&mut x, &mut scratch);
+    }
+
+    for (bytes, word) in block.chunks_exact_mut(4).zip(&x) {
+        bytes.copy_from_slice(&word.to_le_bytes());
+    }
+    pbkdf2_sha256(password, &block, output);
+
+    v.zeroize();
+    x.zeroize();
+    scratch.zeroize();
+    block.zeroize();
+}
+
+/// scrypt BlockMix: Salsa20/8 chained over 64-byte blocks, even outputs first
+fn block_mix(b: &mut [u32], scratch: &mut [u32]) {
+    let blocks = b.len() / 16;
+    let mut x = [0u32; 16];
+    x.copy_from_slice(&b[(blocks - 1) * 16..]);
+    for i in 0..blocks {
+        for (word, input) in x.iter_mut().zip(&b[i * 16..(i + 1) * 16]) {
+            *word ^= input;
+        }
+        salsa20_8(&mut x);
+        let slot = if i % 2 == 0 {
+            i / 2
+        } else {
+            blocks / 2 + i / 2
+        };
+        scratch[slot * 16..(slot + 1) * 16].copy_from_slice(&x);
+    }
+    b.copy_from_slice(scratch);
+    x.zeroize();
+}
+
+fn salsa20_8(block: &mut [u32; 16]) {
+    fn quarter(x: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize) {
+        x[b] ^= x[a].wrapping_add(x[d]).rotate_left(7);
+        x[c] ^= x[b].wrapping_add(x[a]).rotate_left(9);
+        x[d] ^= x[c].wrapping_add(x[b]).rotate_left(13);
+        x[a] ^= x[d].wrapping_add(x[c]).rotate_left(18);
+    }
+
+    let mut x = *block;
+    for _ in 0..4 {
+        quarter(&mut x, 0, 4, 8, 12);
+        quarter(&mut x, 5, 9, 13, 1);
+        quarter(&mut x, 10, 14, 2, 6);
+        quarter(&mut x, 15, 3, 7, 11);
+        quarter(&mut x, 0, 1, 2, 3);
+        quarter(&mut x, 5, 6, 7, 4);
+        quarter(&mut x, 10, 11, 8, 9);
+        quarter(&mut x, 15, 12, 13, 14);
+    }
+    for (word, mixed) in block.iter_mut().zip(x) {
+        *word = word.wrapping_add(mixed);
+    }
+}
+
+/// PBKDF2-HMAC-SHA256 with a single iteration, as scrypt uses it
+fn pbkdf2_sha256(password: &[u8], salt: &[u8], output: &mut [u8]) {
+    for (i, chunk) in output.chunks_mut(32).enumerate() {
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(password)
+            .expect("HMAC accepts keys of any length");
+        mac.update(salt);
+        mac.update(&(i as u32 + 1).to_be_bytes());
+        let block = mac.finalize().into_bytes();
+        chunk.copy_from_slice(&block[..chunk.len()]);
+    }
+}
@@ -0,0 +1,196 @@
+/// Append-only encrypted audit log for `::audit on`
+/// Every command line run at the prompt is appended to `$XDG_DATA_HOME/gsh/audit.log`
+/// with its time and exit status, one record per line. A record is sealed with
+/// ChaCha20-Poly1305 under the logs subkey (reproducible only from the same
+/// `--master-passphrase`) and its plaintext carries its sequence number and the chain
+/// hash of the records before it, where each link is SHA-256(previous link ‖ record).
+/// Editing, removing, reordering or splicing in a record breaks the chain at that
+/// point; cutting records off the end is only visible against a head hash noted
+/// earlier, which is why `::audit` shows it.
+use base64::{engine::general_purpose, Engine as _};
+use sha2::{Digest, Sha256};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use zeroize::Zeroize;
+
+use crate::clipboard::{open_bytes, seal_bytes};
+use crate::config::data_file;
+use crate::hash::to_hex;
+use crate::keys::SubKey;
+
+/// Chain hash before the first record
+const GENESIS: [u8; 32] = [0; 32];
+
+/// One decrypted audit record
+pub struct AuditEntry {
+    pub seq: u64,
+    pub time: SystemTime,
+    pub status: Option<i32>,
+    pub command: String,
+    /// Chain hash up to and including this record
+    pub chain: [u8; 32],
+}
+
+impl Drop for AuditEntry {
+    fn drop(&mut self) {
+        self.command.zeroize();
+    }
+}
+
+/// The open log: where the next record goes and the chain it extends
+pub struct AuditLog {
+    path: PathBuf,
+    key: SubKey,
+    next_seq: u64,
+    head: [u8; 32],
+}
+
+/// `$XDG_DATA_HOME/gsh/audit.log`, falling back to `~/.local/share/gsh/audit.log`
+pub fn audit_path() -> Option<PathBuf> {
+    data_file("audit.log")
+}
+
+fn link(previous: &[u8; 32], record: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(previous);
+    hasher.update(record);
+    hasher.finalize().into()
+}
+
+/// Decrypt and check every record at `path`, oldest first. Fails at the first record
+/// that does not decrypt or does not continue the chain.
+pub fn read_log(path: &Path, key: &SubKey) -> Result<Vec<AuditEntry>, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let mut entries: Vec<AuditEntry> = Vec::new();
+    let mut head = GENESIS;
+    for (number, line) in text.lines().enumerate() {
+        let broken = |what: &str| format!("{}: record {}: {}", path.display(), number + 1, what);
+        let record = general_purpose::STANDARD
+            .decode(line.trim())
+            .ok()
+            .filter(|record| record.len() > 12)
+            .ok_or_else(|| broken("not a sealed record (edited?)"))?;
+        let (nonce, ciphertext) = record.split_at(12);
+        let plaintext = key
+            .expose(|key| open_bytes(key, nonce, ciphertext))
+            .map_err(|_| broken("does not decrypt (edited, or another passphrase)"))?;
+        let parsed = parse_record(plaintext.expose());
+        let (seq, previous, time, status, command) =
+            parsed.ok_or_else(|| broken("malformed record"))?;
+        if seq != entries.len() as u64 || previous != to_hex(&head) {
+            let mut command = command;
+            command.zeroize();
+            return Err(broken(
+                "chain broken (a record was removed, moved or inserted)",
+            ));
+        }
+        head = link(&head, &record);
+        entries.push(AuditEntry {
+            seq,
+            time,
+            status,
+            command,
+            chain: head,
+        });
+    }
+    Ok(entries)
+}
+
+/// `<seq>\t<previous chain hash>\t<unix seconds>\t<status or ->\t<command>`
+fn parse_record(plaintext: &[u8]) -> Option<(u64, String, SystemTime, Option<i32>, String)> {
+    let text = std::str::from_utf8(plaintext).ok()?;
+    let mut fields = text.splitn(5, '\t');
+    let seq = fields.next()?.parse().ok()?;
+    let previous = fields.next()?.to_string();
+    let time = UNIX_EPOCH + Duration::from_secs(fields.next()?.parse().ok()?);
+    let status = match fields.next()? {
+        "-" => None,
+        status => Some(status.parse().ok()?),
+    };
+    Some((seq, previous, time, status, fields.next()?.to_string()))
+}
+
+impl AuditLog {
+    /// Continue the log at `path`, verifying what is already there
+    pub fn open(path: PathBuf, key: SubKey) -> Result<Self, String> {
+        let (next_seq, head) = if path.exists() {
+            let entries = read_log(&path, &key)?;
+            (
+                entries.len() as u64,
+                entries.last().map_or(GENESIS, |entry| entry.chain),
+            )
+        } else {
+            (0, GENESIS)
+        };
+        Ok(AuditLog {
+            path,
+            key,
+            next_seq,
+            head,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Records written so far, by every session
+    pub fn records(&self) -> u64 {
+        self.next_seq
+    }
+
+    /// Chain hash of the newest record, as hex
+    pub fn head(&self) -> String {
+        to_hex(&self.head)
+    }
+
+    /// Seal one record and append it to the file
+    pub fn append(
+        &mut self,
+        time: SystemTime,
+        status: Option<i32>,
+        command: &str,
+    ) -> Result<(), String> {
+        let seconds = time
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or(0);
+        let status = status.map_or_else(|| "-".to_string(), |status| status.to_string());
+        let mut plaintext = format!(
+            "{}\t{}\t{}\t{}\t{}",
+            self.next_seq,
+            to_hex(&self.head),
+            seconds,
+            status,
+            command
+        );
+        let sealed = self.key.expose(|key| seal_bytes(key, plaintext.as_bytes()));
+        plaintext.zeroize();
+        let (nonce, ciphertext) = sealed?;
+        let mut record = nonce.to_vec();
+        record.extend_from_slice(&ciphertext);
+
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+        }
+        let mut options = OpenOptions::new();
+        options.append(true).create(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        options
+            .open(&self.path)
+            .and_then(|mut file| {
+                writeln!(file, "{}", general_purpose::STANDARD.encode(&record))?;
+                file.sync_data()
+            })
+            .map_err(|e| format!("{}: {}", self.path.display(), e))?;
+        self.head = link(&self.head, &record);
+        self.next_seq += 1;
+        Ok(())
+    }
+}
@@ -0,0 +1,598 @@
+/// In-process `ls`, `cat`, `head` and `grep`
+/// Inspecting files is the most common thing typed, and running it through `$SHELL -c`
+/// leaves a shell and a coreutils process in process accounting and audit logs, and
+/// may source rc files that log commands. Plain invocations of these four are served
+/// here instead, with words, quotes, variables and globs expanded as `::direct` does.
+/// Anything else falls through to the shell unchanged: pipes, redirections, options
+/// not listed below, and `grep` without a file (it would read stdin). `\ls`,
+/// `command ls` or `/bin/ls` always run the real tool.
+///
+/// - `ls [-a|-A] [-l] [-h] [-1] [path...]`, times in UTC
+/// - `cat [-n] <file...>`
+/// - `head [-n <lines>|-<lines>|-c <bytes>] <file...>`
+/// - `grep [-i] [-v] [-n] [-c] [-l] [-h|-H] [-r] [-s] [-q] [-x] [-E|-F] (-e <pattern>... | <pattern>) <file...>`;
+///   basic patterns using `+ ? | ( ) {` mean something else there, so those go to
+///   the real grep unless `-E` or `-F` is given. So do patterns the matcher in
+///   `regex` refuses or would run slowly.
+use crate::direct::words;
+use crate::regex::Regex;
+use crate::timestomp::format_datetime;
+use std::collections::HashMap;
+use std::fs::{self, Metadata};
+use std::io;
+use std::os::unix::fs::{FileTypeExt, MetadataExt, PermissionsExt};
+use std::path::Path;
+use std::time::{Duration, UNIX_EPOCH};
+
+/// Lines `head` prints by default
+const HEAD_LINES: usize = 10;
+
+/// Compiled patterns larger than this go to the real grep: the in-process matcher steps
+/// through every instruction per character, where grep builds a DFA
+const MAX_GREP_PROGRAM: usize = 2000;
+
+/// What a builtin printed and its exit status, as a child process would report them
+pub struct Captured {
+    pub stdout: String,
+    pub stderr: String,
+    pub status: i32,
+}
+
+impl Captured {
+    fn new() -> Self {
+        Captured {
+            stdout: String::new(),
+            stderr: String::new(),
+            status: 0,
+        }
+    }
+
+    /// Report a failure the way coreutils does: a message on stderr and status 2
+    /// (`ls`, `grep`) or 1 (`cat`, `head`)
+    fn fail(&mut self, message: String, status: i32) {
+        self.stderr.push_str(&message);
+        self.stderr.push('\n');
+        self.status = self.status.max(status);
+    }
+}
+
+/// Run `line` in-process when it is a plain `ls`, `cat`, `head` or `grep`; None when it
+/// needs the real shell
+pub fn run(line: &str) -> Option<Captured> {
+    let name = line.split_whitespace().next()?;
+    if !["ls", "cat", "head", "grep"].contains(&name) {
+        return None;
+    }
+    let words = words(line).ok()?;
+    let args = &words[1..];
+    match name {
+        "ls" => ls(args),
+        "cat" => cat(args),
+        "head" => head(args),
+        _ => grep(args),
+    }
+}
+
+/// An option letter and its value, if it takes one
+type Flag = (char, Option<String>);
+
+/// Split `args` into single-letter options and operands. `takes_value` options consume
+/// the rest of their cluster or the next argument. None on an option not in `known`.
+fn options(args: &[String], known: &str, takes_value: &str) -> Option<(Vec<Flag>, Vec<String>)> {
+    let mut options = Vec::new();
+    let mut operands = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            operands.extend(args.cloned());
+            break;
+        }
+        let Some(cluster) = arg.strip_prefix('-').filter(|cluster| !cluster.is_empty()) else {
+            operands.push(arg.clone());
+            continue;
+        };
+        for (i, option) in cluster.char_indices() {
+            if !known.contains(option) {
+                return None;
+            }
+            if takes_value.contains(option) {
+                let rest = &cluster[i + 1..];
+                let value = if rest.is_empty() {
+                    args.next()?.clone()
+                } else {
+                    rest.to_string()
+                };
+                options.push((option, Some(value)));
+                break;
+            }
+            options.push((option, None));
+        }
+    }
+    Some((options, operands))
+}
+
+fn has(options: &[Flag], option: char) -> bool {
+    options.iter().any(|(o, _)| *o == option)
+}
+
+/// An I/O error as coreutils words it, without Rust's `(os error N)`
+fn reason(e: &io::Error) -> String {
+    let text = e.to_string();
+    match text.find(" (os error") {
+        Some(end) => text[..end].to_string(),
+        None => text,
+    }
+}
+
+fn ls(args: &[String]) -> Option<Captured> {
+    let (options, mut operands) = options(args, "aAlh1", "")?;
+    let all = has(&options, 'a');
+    let almost_all = has(&options, 'A');
+    let long = has(&options, 'l');
+    let human = has(&options, 'h');
+    if operands.is_empty() {
+        operands.push(".".to_string());
+    }
+    operands.sort();
+
+    let mut out = Captured::new();
+    let mut names = Names::default();
+    let mut files = Vec::new();
+    let mut dirs = Vec::new();
+    for operand in &operands {
+        match fs::metadata(operand).or_else(|_| fs::symlink_metadata(operand)) {
+            Ok(metadata) if metadata.is_dir() => dirs.push(operand),
+            Ok(_) => files.push(operand.clone()),
+            Err(e) => out.fail(
+                format!("ls: cannot access '{}': {}", operand, reason(&e)),
+                2,
+            ),
+        }
+    }
+
+    let mut sections = Vec::new();
+    if !files.is_empty() {
+        let entries: Vec<(String, String)> = files
+            .iter()
+            .map(|file| (file.clone(), file.clone()))
+            .collect();
+        sections.push(listing(&entries, long, human, false, &mut names));
+    }
+    for dir in &dirs {
+        let mut entries = Vec::new();
+        if all {
+            entries.push((".".to_string(), format!("{}/.", dir)));
+            entries.push(("..".to_string(), format!("{}/..", dir)));
+        }
+        match fs::read_dir(dir) {
+            Ok(read) => {
+                for entry in read.flatten() {
+                    let name = entry.file_name().to_string_lossy().into_owned();
+                    if name.starts_with('.') && !all && !almost_all {
+                        continue;
+                    }
+                    let path = entry.path().to_string_lossy().into_owned();
+                    entries.push((name, path));
+                }
+            }
+            Err(e) => {
+                out.fail(
+                    format!("ls: cannot open directory '{}': {}", dir, reason(&e)),
+                    2,
+                );
+                continue;
+            }
+        }
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        let mut section = listing(&entries, long, human, long, &mut names);
+        if operands.len() > 1 {
+            section = format!("{}:\n{}", dir, section);
+        }
+        sections.push(section);
+    }
+    out.stdout = sections.join("\n");
+    Some(out)
+}
+
+/// `ls` output for `(shown name, path)` entries, one per line or in long format with
+/// aligned columns
+fn listing(
+    entries: &[(String, String)],
+    long: bool,
+    human: bool,
+    total: bool,
+    names: &mut Names,
+) -> String {
+    if !long {
+        return entries
+            .iter()
+            .map(|(name, _)| format!("{}\n", name))
+            .collect();
+    }
+    let rows: Vec<(Metadata, [String; 4], String)> = entries
+        .iter()
+        .filter_map(|(name, path)| {
+            let metadata = fs::symlink_metadata(path).ok()?;
+            let size = if human {
+                human_size(metadata.len())
+            } else {
+                metadata.len().to_string()
+            };
+            let shown = if metadata.file_type().is_symlink() {
+                match fs::read_link(path) {
+                    Ok(target) => format!("{} -> {}", name, target.display()),
+                    Err(_) => name.clone(),
+                }
+            } else {
+                name.clone()
+            };
+            let columns = [
+                metadata.nlink().to_string(),
+                names.user(metadata.uid()),
+                names.group(metadata.gid()),
+                size,
+            ];
+            Some((metadata, columns, shown))
+        })
+        .collect();
+    let widths: Vec<usize> = (0..4)
+        .map(|i| rows.iter().map(|row| row.1[i].len()).max().unwrap_or(0))
+        .collect();
+
+    let mut text = String::new();
+    if total {
+        // st_blocks counts 512-byte blocks; ls totals in KiB
+        let blocks: u64 = rows.iter().map(|row| row.0.blocks()).sum();
+        text.push_str(&format!("total {}\n", blocks.div_ceil(2)));
+    }
+    for (metadata, [links, user, group, size], shown) in &rows {
+        let modified = UNIX_EPOCH + Duration::from_secs(metadata.mtime().max(0) as u64);
+        text.push_str(&format!(
+            "{} {:>w0$} {:<w1$} {:<w2$} {:>w3$} {} {}\n",
+            mode_string(metadata),
+            links,
+            user,
+            group,
+            size,
+            &format_datetime(modified)[..16],
+            shown,
+            w0 = widths[0],
+            w1 = widths[1],
+            w2 = widths[2],
+            w3 = widths[3],
+        ));
+    }
+    text
+}
+
+/// `drwxr-xr-x`
+fn mode_string(metadata: &Metadata) -> String {
+    let file_type = metadata.file_type();
+    let kind = if file_type.is_dir() {
+        'd'
+    } else if file_type.is_symlink() {
+        'l'
+    } else if file_type.is_char_device() {
+        'c'
+    } else if file_type.is_block_device() {
+        'b'
+    } else if file_type.is_fifo() {
+        'p'
+    } else if file_type.is_socket() {
+        's'
+    } else {
+        '-'
+    };
+    let mode = metadata.permissions().mode();
+    let mut text = String::from(kind);
+    for (shift, special, special_char) in [(6, 0o4000, 's'), (3, 0o2000, 's'), (0, 0o1000, 't')] {
+        let bits = mode >> shift;
+        text.push(if bits & 4 != 0 { 'r' } else { '-' });
+        text.push(if bits & 2 != 0 { 'w' } else { '-' });
+        text.push(match (bits & 1 != 0, mode & special != 0) {
+            (true, true) => special_char,
+            (false, true) => special_char.to_ascii_uppercase(),
+            (true, false) => 'x',
+            (false, false) => '-',
+        });
+    }
+    text
+}
+
+/// `4.0K`, `12M`: one decimal below 10, like `ls -h`
+fn human_size(bytes: u64) -> String {
+    let mut size = bytes as f64;
+    for unit in ["", "K", "M", "G", "T", "P"] {
+        if size < 1024.0 || unit == "P" {
+            return match unit {
+                "" => bytes.to_string(),
+                _ if size < 10.0 => format!("{:.1}{}", (size * 10.0).ceil() / 10.0, unit),
+                _ => format!("{}{}", size.ceil(), unit),
+            };
+        }
+        size /= 1024.0;
+    }
+    bytes.to_string()
+}
+
+/// User and group names from /etc/passwd and /etc/group, read once per `ls`
+#[derive(Default)]
+struct Names {
+    users: Option<HashMap<u32, String>>,
+    groups: Option<HashMap<u32, String>>,
+}
+
+impl Names {
+    fn user(&mut self, uid: u32) -> String {
+        let users = self.users.get_or_insert_with(|| read_ids("/etc/passwd"));
+        users.get(&uid).cloned().unwrap_or_else(|| uid.to_string())
+    }
+
+    fn group(&mut self, gid: u32) -> String {
+        let groups = self.groups.get_or_insert_with(|| read_ids("/etc/group"));
+        groups.get(&gid).cloned().unwrap_or_else(|| gid.to_string())
+    }
+}
+
+/// `name:x:id:...` lines
+fn read_ids(path: &str) -> HashMap<u32, String> {
+    fs::read_to_string(path)
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split(':');
+            let name = fields.next()?;
+            let id = fields.nth(1)?.parse().ok()?;
+            Some((id, name.to_string()))
+        })
+        .collect()
+}
+
+/// A file's contents for `cat`, `head` and `grep`, or the message coreutils prints
+fn read_file(tool: &str, path: &str) -> Result<Vec<u8>, String> {
+    if Path::new(path).is_dir() {
+        return Err(format!("{}: {}: Is a directory", tool, path));
+    }
+    fs::read(path).map_err(|e| format!("{}: {}: {}", tool, path, reason(&e)))
+}
+
+fn cat(args: &[String]) -> Option<Captured> {
+    let (options, operands) = options(args, "n", "")?;
+    if operands.is_empty() || operands.iter().any(|operand| operand == "-") {
+        return None;
+    }
+    let number = has(&options, 'n');
+    let mut out = Captured::new();
+    let mut line_number = 0;
+    for operand in &operands {
+        match read_file("cat", operand) {
+            Ok(data) => {
+                let text = String::from_utf8_lossy(&data);
+                if number {
+                    for line in text.split_inclusive('\n') {
+                        line_number += 1;
+                        out.stdout
+                            .push_str(&format!("{:>6}\t{}", line_number, line));
+                    }
+                } else {
+                    out.stdout.push_str(&text);
+                }
+            }
+            Err(message) => out.fail(message, 1),
+        }
+    }
+    Some(out)
+}
+
+fn head(args: &[String]) -> Option<Captured> {
+    // `-5` is `-n 5`
+    let args: Vec<String> = args
+        .iter()
+        .map(|arg| match arg.strip_prefix('-') {
+            Some(count) if !count.is_empty() && count.chars().all(|c| c.is_ascii_digit()) => {
+                format!("-n{}", count)
+            }
+            _ => arg.clone(),
+        })
+        .collect();
+    let (options, operands) = options(&args, "nc", "nc")?;
+    if operands.is_empty() || operands.iter().any(|operand| operand == "-") {
+        return None;
+    }
+    // The last count given wins; `-n -3` (all but the last lines) is left to head
+    let (bytes, count) = match options.last() {
+        Some((option, Some(value))) => (*option == 'c', value.parse::<usize>().ok()?),
+        _ => (false, HEAD_LINES),
+    };
+
+    let mut out = Captured::new();
+    for (i, operand) in operands.iter().enumerate() {
+        let data = match read_file("head", operand) {
+            Ok(data) => data,
+            Err(message) => {
+                out.fail(message, 1);
+                continue;
+            }
+        };
+        if operands.len() > 1 {
+            out.stdout.push_str(&format!(
+                "{}==> {} <==\n",
+                if i > 0 { "\n" } else { "" },
+                operand
+            ));
+        }
+        let end = if bytes {
+            count.min(data.len())
+        } else {
+            data.iter()
+                .enumerate()
+                .filter(|(_, b)| **b == b'\n')
+                .nth(count.wrapping_sub(1))
+                .map_or(data.len(), |(at, _)| at + 1)
+        };
+        let end = if count == 0 { 0 } else { end };
+        out.stdout.push_str(&String::from_utf8_lossy(&data[..end]));
+    }
+    Some(out)
+}
+
+/// `text` matched literally
+fn escape(text: &str) -> String {
+    let mut escaped = String::new();
+    for c in text.chars() {
+        if "\\.^$|?*+()[]{}".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+fn grep(args: &[String]) -> Option<Captured> {
+    let (options, mut operands) = options(args, "ivnclhHrRsqxEFe", "e")?;
+    let mut patterns: Vec<String> = options
+        .iter()
+        .filter_map(|(o, value)| value.clone().filter(|_| *o == 'e'))
+        .collect();
+    if patterns.is_empty() {
+        if operands.is_empty() {
+            return None;
+        }
+        patterns.push(operands.remove(0));
+    }
+    let recursive = has(&options, 'r') || has(&options, 'R');
+    if operands.is_empty() {
+        if !recursive {
+            return None;
+        }
+        operands.push(".".to_string());
+    }
+    let fixed = has(&options, 'F');
+    // Basic regular expressions treat these as literals and `\(`, `\{` as operators;
+    // POSIX classes aren't supported here
+    if !fixed
+        && !has(&options, 'E')
+        && patterns.iter().any(|pattern| {
+            pattern.contains(['+', '?', '|', '(', ')', '{', '}']) || pattern.contains("[[")
+        })
+    {
+        return None;
+    }
+    let patterns: Vec<String> = patterns
+        .into_iter()
+        .map(|pattern| if fixed { escape(&pattern) } else { pattern })
+        .collect();
+    // Every `-e` pattern counts: a line matches when any of them does
+    let mut source = match patterns.as_slice() {
+        [pattern] => pattern.clone(),
+        patterns => format!("({})", patterns.join(")|(")),
+    };
+    if has(&options, 'x') {
+        source = format!("^({})$", source);
+    }
+    // A pattern the matcher refuses (unsupported syntax, nested too deep, too large) is
+    // left to the real grep, which reports its own errors
+    let regex = if has(&options, 'i') {
+        Regex::case_insensitive(&source)
+    } else {
+        Regex::new(&source)
+    }
+    .ok()
+    .filter(|regex| regex.size() <= MAX_GREP_PROGRAM)?;
+
+    let mut files = Vec::new();
+    let mut out = Captured::new();
+    let quiet_errors = has(&options, 's');
+    for operand in &operands {
+        if recursive && Path::new(operand).is_dir() {
+            walk(Path::new(operand), &mut files, &mut out, quiet_errors);
+        } else {
+            files.push(operand.clone());
+        }
+    }
+    let show_names = !has(&options, 'h') && (has(&options, 'H') || recursive || files.len() > 1);
+    let invert = has(&options, 'v');
+    let mut matched = false;
+    for file in &files {
+        let data = match read_file("grep", file) {
+            Ok(data) => data,
+            Err(message) => {
+                if !quiet_errors {
+                    out.stderr.push_str(&message);
+                    out.stderr.push('\n');
+                }
+                out.status = 2;
+                continue;
+            }
+        };
+        let binary = data.contains(&0);
+        let text = String::from_utf8_lossy(&data);
+        let mut count = 0;
+        for (i, line) in text.lines().enumerate() {
+            if regex.is_match(line) == invert {
+                continue;
+            }
+            count += 1;
+            if has(&options, 'q') || has(&options, 'l') || has(&options, 'c') || binary {
+                continue;
+            }
+            if show_names {
+                out.stdout.push_str(&format!("{}:", file));
+            }
+            if has(&options, 'n') {
+                out.stdout.push_str(&format!("{}:", i + 1));
+            }
+            out.stdout.push_str(line);
+            out.stdout.push('\n');
+        }
+        matched |= count > 0;
+        if has(&options, 'q') {
+            continue;
+        } else if has(&options, 'l') {
+            if count > 0 {
+                out.stdout.push_str(&format!("{}\n", file));
+            }
+        } else if has(&options, 'c') {
+            if show_names {
+                out.stdout.push_str(&format!("{}:", file));
+            }
+            out.stdout.push_str(&format!("{}\n", count));
+        } else if binary && count > 0 {
+            out.stderr
+                .push_str(&format!("grep: {}: binary file matches\n", file));
+        }
+    }
+    if has(&options, 'q') && matched {
+        out.status = 0;
+    } else if out.status == 0 && !matched {
+        out.status = 1;
+    }
+    Some(out)
+}
+
+/// Regular files under `dir` in name order, not following symlinked directories
+fn walk(dir: &Path, files: &mut Vec<String>, out: &mut Captured, quiet_errors: bool) {
+    let mut entries: Vec<_> = match fs::read_dir(dir) {
+        Ok(read) => read.flatten().collect(),
+        Err(e) => {
+            if !quiet_errors {
+                out.stderr
+                    .push_str(&format!("grep: {}: {}\n", dir.display(), reason(&e)));
+            }
+            out.status = 2;
+            return;
+        }
+    };
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let path = entry.path();
+        match entry.file_type() {
+            Ok(file_type) if file_type.is_dir() => walk(&path, files, out, quiet_errors),
+            Ok(file_type) if file_type.is_file() => {
+                let path = path.to_string_lossy();
+                files.push(path.strip_prefix("./").unwrap_or(&path).to_string());
+            }
+            _ => {}
+        }
+    }
+}
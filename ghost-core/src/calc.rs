@@ -0,0 +1,539 @@
+/// In-process calculator for `::calc`
+/// Integer and floating point arithmetic (`+ - * / % **`), bitwise operators
+/// (`& | ^ ~ << >>`) and parentheses over decimal, `0x`, `0o` and `0b` literals, with
+/// byte sizes (`B`, `KB`..`TB`, `KiB`..`TiB`) and durations (`ns`..`d`) as units:
+/// `4GiB / 512B`, `90min in h`, `0x7fff0000 + 16KiB in hex`. An IPv4 CIDR block
+/// (`10.0.0.0/22`) is broken down into its network, mask, broadcast and host range
+/// instead. Nothing is spawned, so quick sums leave no `bc` or `python` behind.
+use std::net::Ipv4Addr;
+
+/// Byte sizes, in bytes
+const SIZES: &[(&str, i128)] = &[
+    ("B", 1),
+    ("KB", 1_000),
+    ("MB", 1_000_000),
+    ("GB", 1_000_000_000),
+    ("TB", 1_000_000_000_000),
+    ("KiB", 1 << 10),
+    ("MiB", 1 << 20),
+    ("GiB", 1 << 30),
+    ("TiB", 1 << 40),
+];
+
+/// Durations, in nanoseconds
+const DURATIONS: &[(&str, i128)] = &[
+    ("ns", 1),
+    ("us", 1_000),
+    ("ms", 1_000_000),
+    ("s", 1_000_000_000),
+    ("min", 60_000_000_000),
+    ("h", 3_600_000_000_000),
+    ("d", 86_400_000_000_000),
+];
+
+/// What a quantity measures
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Dim {
+    Plain,
+    Bytes,
+    Time,
+}
+
+impl Dim {
+    fn name(self) -> &'static str {
+        match self {
+            Dim::Plain => "a plain number",
+            Dim::Bytes => "a size",
+            Dim::Time => "a duration",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Num {
+    Int(i128),
+    Float(f64),
+}
+
+impl Num {
+    fn float(self) -> f64 {
+        match self {
+            Num::Int(n) => n as f64,
+            Num::Float(f) => f,
+        }
+    }
+
+    fn int(self, what: &str) -> Result<i128, String> {
+        match self {
+            Num::Int(n) => Ok(n),
+            Num::Float(_) => Err(format!("{} needs whole numbers", what)),
+        }
+    }
+}
+
+/// A number and what it measures. Sizes are in bytes, durations in nanoseconds.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Quantity {
+    num: Num,
+    dim: Dim,
+}
+
+fn unit(name: &str) -> Option<(i128, Dim)> {
+    SIZES
+        .iter()
+        .find(|(unit, _)| *unit == name)
+        .map(|(_, factor)| (*factor, Dim::Bytes))
+        .or_else(|| {
+            DURATIONS
+                .iter()
+                .find(|(unit, _)| *unit == name)
+                .map(|(_, factor)| (*factor, Dim::Time))
+        })
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Num(Num),
+    Unit(String),
+    Op(&'static str),
+    Open,
+    Close,
+}
+
+const OPERATORS: &[&str] = &[
+    "**", "<<", ">>", "+", "-", "*", "/", "%", "&", "|", "^", "~",
+];
+
+fn tokenize(expr: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = expr.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c == '(' {
+            tokens.push(Token::Open);
+            i += 1;
+        } else if c == ')' {
+            tokens.push(Token::Close);
+            i += 1;
+        } else if c.is_ascii_digit() || c == '.' {
+            // A unit glued to the number (`4GiB`) is left for the next token
+            let start = i;
+            // `0B` is zero bytes, not a binary prefix
+            let radix = chars.get(i + 1).copied();
+            if c == '0'
+                && matches!(radix, Some('x' | 'X' | 'o' | 'b'))
+                && chars.get(i + 2).is_some_and(char::is_ascii_hexdigit)
+            {
+                i += 2;
+                let hex = matches!(radix, Some('x' | 'X'));
+                while i < chars.len()
+                    && (chars[i] == '_'
+                        || if hex {
+                            chars[i].is_ascii_hexdigit()
+                        } else {
+                            chars[i].is_ascii_digit()
+                        })
+                {
+                    i += 1;
+                }
+            } else {
+                while i < chars.len() {
+                    let exponent = matches!(chars[i], 'e' | 'E')
+                        && match chars.get(i + 1) {
+                            Some('-' | '+') => chars.get(i + 2).is_some_and(char::is_ascii_digit),
+                            next => next.is_some_and(char::is_ascii_digit),
+                        };
+                    if exponent {
+                        i += 2;
+                    } else if chars[i].is_ascii_digit() || matches!(chars[i], '.' | '_') {
+                        i += 1;
+                    } else {
+                        break;
+                    }
+                }
+            }
+            let text: String = chars[start..i].iter().collect();
+            tokens.push(Token::Num(parse_number(&text)?));
+        } else if c.is_ascii_alphabetic() {
+            let start = i;
+            while i < chars.len() && chars[i].is_ascii_alphabetic() {
+                i += 1;
+            }
+            tokens.push(Token::Unit(chars[start..i].iter().collect()));
+        } else {
+            let rest: String = chars[i..].iter().take(2).collect();
+            let op = OPERATORS
+                .iter()
+                .find(|op| rest.starts_with(**op))
+                .ok_or_else(|| format!("Unexpected '{}'", c))?;
+            tokens.push(Token::Op(op));
+            i += op.len();
+        }
+    }
+    Ok(tokens)
+}
+
+fn parse_number(text: &str) -> Result<Num, String> {
+    let digits = text.replace('_', "");
+    let lower = digits.to_ascii_lowercase();
+    let radix = [("0x", 16), ("0o", 8), ("0b", 2)]
+        .iter()
+        .find_map(|(prefix, radix)| lower.strip_prefix(prefix).map(|rest| (rest, *radix)));
+    let parsed = match radix {
+        Some((rest, radix)) => i128::from_str_radix(rest, radix).ok().map(Num::Int),
+        None if lower.contains(['.', 'e']) => lower.parse::<f64>().ok().map(Num::Float),
+        None => lower.parse::<i128>().ok().map(Num::Int),
+    };
+    parsed.ok_or_else(|| format!("Invalid number '{}'", text))
+}
+
+/// Recursive descent over the tokens, loosest binding first:
+/// `|`, `^`, `&`, shifts, `+ -`, `* / %`, unary `- + ~`, `**`, then numbers with an
+/// optional unit and parentheses
+struct Parser {
+    tokens: Vec<Token>,
+    at: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.at)
+    }
+
+    fn eat(&mut self, ops: &[&'static str]) -> Option<&'static str> {
+        match self.peek() {
+            Some(Token::Op(op)) if ops.contains(op) => {
+                let op = *op;
+                self.at += 1;
+                Some(op)
+            }
+            _ => None,
+        }
+    }
+
+    fn binary(&mut self, level: usize, levels: &[&[&'static str]]) -> Result<Quantity, String> {
+        let Some(ops) = levels.get(level) else {
+            return self.unary();
+        };
+        let mut left = self.binary(level + 1, levels)?;
+        while let Some(op) = self.eat(ops) {
+            let right = self.binary(level + 1, levels)?;
+            left = apply(op, left, right)?;
+        }
+        Ok(left)
+    }
+
+    fn expression(&mut self) -> Result<Quantity, String> {
+        self.binary(
+            0,
+            &[
+                &["|"],
+                &["^"],
+                &["&"],
+                &["<<", ">>"],
+                &["+", "-"],
+                &["*", "/", "%"],
+            ],
+        )
+    }
+
+    fn unary(&mut self) -> Result<Quantity, String> {
+        match self.eat(&["-", "+", "~"]) {
+            Some(op) => {
+                let value = self.unary()?;
+                let num = match (op, value.num) {
+                    ("-", Num::Int(n)) => Num::Int(n.checked_neg().ok_or("Overflow")?),
+                    ("-", Num::Float(f)) => Num::Float(-f),
+                    ("~", num) => Num::Int(!num.int("~")?),
+                    (_, num) => num,
+                };
+                Ok(Quantity { num, ..value })
+            }
+            None => self.power(),
+        }
+    }
+
+    fn power(&mut self) -> Result<Quantity, String> {
+        let base = self.primary()?;
+        if self.eat(&["**"]).is_none() {
+            return Ok(base);
+        }
+        let exponent = self.unary()?;
+        apply("**", base, exponent)
+    }
+
+    fn primary(&mut self) -> Result<Quantity, String> {
+        let value = match self.peek().cloned() {
+            Some(Token::Num(num)) => {
+                self.at += 1;
+                Quantity {
+                    num,
+                    dim: Dim::Plain,
+                }
+            }
+            Some(Token::Open) => {
+                self.at += 1;
+                let value = self.expression()?;
+                if self.peek() != Some(&Token::Close) {
+                    return Err("Missing ')'".to_string());
+                }
+                self.at += 1;
+                value
+            }
+            Some(Token::Unit(name)) => return Err(format!("Unknown name '{}'", name)),
+            Some(Token::Close) => return Err("Unexpected ')'".to_string()),
+            Some(Token::Op(op)) => return Err(format!("Unexpected '{}'", op)),
+            None => return Err("Incomplete expression".to_string()),
+        };
+        // A unit after a number or a parenthesized plain expression scales it
+        if let Some(Token::Unit(name)) = self.peek().cloned() {
+            let (factor, dim) = unit(&name).ok_or_else(|| format!("Unknown unit '{}'", name))?;
+            if value.dim != Dim::Plain {
+                return Err(format!("'{}' applied to {}", name, value.dim.name()));
+            }
+            self.at += 1;
+            return apply(
+                "*",
+                value,
+                Quantity {
+                    num: Num::Int(factor),
+                    dim,
+                },
+            );
+        }
+        Ok(value)
+    }
+}
+
+fn apply(op: &str, left: Quantity, right: Quantity) -> Result<Quantity, String> {
+    let dim = match op {
+        "+" | "-" | "%" => match (left.dim, right.dim) {
+            (a, b) if a == b => a,
+            (Dim::Plain, b) => b,
+            (a, Dim::Plain) => a,
+            (a, b) => return Err(format!("Can't combine {} and {}", a.name(), b.name())),
+        },
+        "*" => match (left.dim, right.dim) {
+            (Dim::Plain, b) => b,
+            (a, Dim::Plain) => a,
+            (a, b) => return Err(format!("Can't multiply {} by {}", a.name(), b.name())),
+        },
+        "/" => match (left.dim, right.dim) {
+            (a, Dim::Plain) => a,
+            (a, b) if a == b => Dim::Plain,
+            (a, b) => return Err(format!("Can't divide {} by {}", a.name(), b.name())),
+        },
+        "**" if right.dim != Dim::Plain || left.dim != Dim::Plain => {
+            return Err("Powers take plain numbers".to_string())
+        }
+        _ => left.dim,
+    };
+    let overflow = || "Overflow".to_string();
+    let num = match (left.num, right.num) {
+        (Num::Int(a), Num::Int(b)) => match op {
+            "+" => Num::Int(a.checked_add(b).ok_or_else(overflow)?),
+            "-" => Num::Int(a.checked_sub(b).ok_or_else(overflow)?),
+            "*" => Num::Int(a.checked_mul(b).ok_or_else(overflow)?),
+            "/" | "%" if b == 0 => return Err("Division by zero".to_string()),
+            "/" if a.checked_rem(b).ok_or_else(overflow)? == 0 => {
+                Num::Int(a.checked_div(b).ok_or_else(overflow)?)
+            }
+            "/" => Num::Float(a as f64 / b as f64),
+            "%" => Num::Int(a.checked_rem_euclid(b).ok_or_else(overflow)?),
+            "**" if (0..=u32::MAX as i128).contains(&b) => {
+                Num::Int(a.checked_pow(b as u32).ok_or_else(overflow)?)
+            }
+            "**" => Num::Float((a as f64).powf(b as f64)),
+            "&" => Num::Int(a & b),
+            "|" => Num::Int(a | b),
+            "^" => Num::Int(a ^ b),
+            "<<" | ">>" if !(0..128).contains(&b) => {
+                return Err("Shifts take 0 to 127 bits".to_string())
+            }
+            "<<" => Num::Int(a.checked_shl(b as u32).ok_or_else(overflow)?),
+            ">>" => Num::Int(a >> b),
+            _ => return Err(format!("Unknown operator '{}'", op)),
+        },
+        (a, b) => {
+            let (a, b) = (a.float(), b.float());
+            match op {
+                "+" => Num::Float(a + b),
+                "-" => Num::Float(a - b),
+                "*" => Num::Float(a * b),
+                "/" | "%" if b == 0.0 => return Err("Division by zero".to_string()),
+                "/" => Num::Float(a / b),
+                "%" => Num::Float(a.rem_euclid(b)),
+                "**" => Num::Float(a.powf(b)),
+                _ => return Err(format!("'{}' needs whole numbers", op)),
+            }
+        }
+    };
+    if let Num::Float(f) = num {
+        if !f.is_finite() {
+            return Err("Result out of range".to_string());
+        }
+    }
+    Ok(Quantity { num, dim })
+}
+
+/// Up to 10 decimals, trailing zeros dropped
+fn format_float(f: f64) -> String {
+    let text = format!("{:.10}", f);
+    text.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+fn format_num(num: Num) -> String {
+    match num {
+        Num::Int(n) => n.to_string(),
+        Num::Float(f) => format_float(f),
+    }
+}
+
+/// `0x1000 · 0o10000 · 0b1_0000_0000_0000`; negative numbers in 64-bit two's complement
+fn radixes(n: i128) -> String {
+    let bits = if n < 0 {
+        match i64::try_from(n) {
+            Ok(n) => n as u64 as u128,
+            Err(_) => return String::new(),
+        }
+    } else {
+        n as u128
+    };
+    let binary = format!("{:b}", bits);
+    let grouped: Vec<String> = binary
+        .as_bytes()
+        .rchunks(4)
+        .rev()
+        .map(|chunk| String::from_utf8_lossy(chunk).into_owned())
+        .collect();
+    format!(
+        "hex 0x{:x} · oct 0o{:o} · bin 0b{}{}",
+        bits,
+        bits,
+        grouped.join("_"),
+        if n < 0 { " (64-bit)" } else { "" }
+    )
+}
+
+/// `value` in the largest unit of `units` it reaches
+fn human(value: Num, units: &[(&str, i128)]) -> String {
+    let magnitude = value.float().abs();
+    let (name, factor) = units
+        .iter()
+        .rev()
+        .find(|(_, factor)| magnitude >= *factor as f64)
+        .unwrap_or(&units[0]);
+    format!("{} {}", format_float(value.float() / *factor as f64), name)
+}
+
+/// Evaluate `::calc` input: an expression, optionally followed by `in <unit>` (or
+/// `in hex|oct|bin|dec`), or an IPv4 CIDR block
+pub fn calculate(input: &str) -> Result<String, String> {
+    let input = input.trim();
+    if let Some(block) = cidr(input) {
+        return block;
+    }
+    let (expr, target) = match input
+        .rsplit_once(" in ")
+        .or_else(|| input.rsplit_once(" to "))
+    {
+        Some((expr, target)) => (expr, Some(target.trim())),
+        None => (input, None),
+    };
+    let mut parser = Parser {
+        tokens: tokenize(expr)?,
+        at: 0,
+    };
+    let value = parser.expression()?;
+    if let Some(token) = parser.peek() {
+        return Err(format!("Unexpected {:?} after the expression", token));
+    }
+
+    match target {
+        None => Ok(match (value.dim, value.num) {
+            (Dim::Plain, Num::Int(n)) => format!("= {}\r\n  {}", n, radixes(n)),
+            (Dim::Plain, num) => format!("= {}", format_num(num)),
+            (Dim::Bytes, num) => {
+                // `B` then the decimal units, and `B` then the binary ones
+                let decimal = &SIZES[..5];
+                let binary: Vec<_> = SIZES[..1].iter().chain(&SIZES[5..]).copied().collect();
+                let mut lines = vec![format!("= {} bytes", format_num(num))];
+                if num.float().abs() >= 1000.0 {
+                    lines.push(format!(
+                        "  {} · {}",
+                        human(num, &binary),
+                        human(num, decimal)
+                    ));
+                }
+                if let Num::Int(n) = num {
+                    lines.push(format!("  {}", radixes(n)));
+                }
+                lines.join("\r\n")
+            }
+            (Dim::Time, num) => {
+                let seconds = format!("= {} s", format_float(num.float() / 1e9));
+                let readable = human(num, DURATIONS);
+                if readable.ends_with(" s") {
+                    seconds
+                } else {
+                    format!("{}\r\n  {}", seconds, readable)
+                }
+            }
+        }),
+        Some("hex" | "oct" | "bin" | "dec") => {
+            let n = value.num.int("Radix conversion")?;
+            let target = target.unwrap_or_default();
+            Ok(match target {
+                "dec" => format!("= {}", n),
+                _ => radixes(n)
+                    .split(" · ")
+                    .find(|part| part.starts_with(target))
+                    .map(|part| format!("= {}", part.trim_start_matches(target).trim()))
+                    .unwrap_or_else(|| "Negative beyond 64 bits".to_string()),
+            })
+        }
+        Some(name) => {
+            let (factor, dim) = unit(name).ok_or_else(|| format!("Unknown unit '{}'", name))?;
+            if dim != value.dim {
+                return Err(format!("Can't express {} in {}", value.dim.name(), name));
+            }
+            let converted = match value.num {
+                Num::Int(n) if n % factor == 0 => Num::Int(n / factor),
+                num => Num::Float(num.float() / factor as f64),
+            };
+            Ok(format!("= {} {}", format_num(converted), name))
+        }
+    }
+}
+
+/// `a.b.c.d/len` broken down; None when `input` isn't one
+fn cidr(input: &str) -> Option<Result<String, String>> {
+    let (address, length) = input.split_once('/')?;
+    let address: Ipv4Addr = address.trim().parse().ok()?;
+    let length: u32 = match length.trim().parse() {
+        Ok(length) if length <= 32 => length,
+        _ => return Some(Err("Prefix length must be 0 to 32".to_string())),
+    };
+    let mask = u32::MAX.checked_shl(32 - length).unwrap_or(0);
+    let network = u32::from(address) & mask;
+    let broadcast = network | !mask;
+    let (first, last, hosts) = match length {
+        32 => (network, network, 1u64),
+        31 => (network, broadcast, 2),
+        _ => (network + 1, broadcast - 1, (1u64 << (32 - length)) - 2),
+    };
+    Some(Ok(format!(
+        "Network:    {}/{}\r\nNetmask:    {}\r\nWildcard:   {}\r\nBroadcast:  {}\r\nHosts:      {} - {} ({})",
+        Ipv4Addr::from(network),
+        length,
+        Ipv4Addr::from(mask),
+        Ipv4Addr::from(!mask),
+        Ipv4Addr::from(broadcast),
+        Ipv4Addr::from(first),
+        Ipv4Addr::from(last),
+        hosts
+    )))
+}
@@ -0,0 +1,1521 @@
+/// Encrypted clipboard module
+/// Provides ephemeral, encrypted clipboard operations
+use arboard::{Clipboard, ImageData};
+use argon2::Argon2;
+use base64::{engine::general_purpose, Engine as _};
+use chacha20poly1305::{
+    aead::{Aead, KeyInit, OsRng},
+    ChaCha20Poly1305, Nonce,
+};
+use rand::RngCore;
+use std::borrow::Cow;
+use std::collections::hash_map::RandomState;
+use std::collections::{BTreeMap, VecDeque};
+use std::env;
+use std::fs::{File, OpenOptions};
+use std::hash::BuildHasher;
+use std::io::{self, Read, Write};
+use std::ops::Range;
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use zeroize::Zeroize;
+
+use crate::age::{self, Recipients, Unlock};
+use crate::gpg;
+use crate::keys::SubKey;
+use crate::meminfo::region;
+use crate::secret::{SecretBytes, SecretKey, SecretString};
+
+/// Marker that prefixes every encrypted clipboard payload
+const ENCRYPTED_PREFIX: &str = "GHOST_ENCRYPTED:";
+
+/// Marker for encrypted binary/image containers (see `ClipboardData`)
+const ENCRYPTED_BINARY_PREFIX: &str = "GHOST_ENCRYPTED_BIN:";
+
+/// Salt length for passphrase-derived keys
+const SALT_LEN: usize = 16;
+
+/// Largest base64 payload sent via OSC 52 (xterm and most emulators cap around 100 KB)
+const OSC52_MAX_PAYLOAD: usize = 100_000;
+
+/// Largest file `::cpf` will load into the clipboard pipeline
+pub const MAX_FILE_COPY_SIZE: u64 = 64 * 1024;
+
+/// Largest binary file or image taken into the clipboard pipeline
+pub const MAX_BINARY_COPY_SIZE: u64 = 32 * 1024 * 1024;
+
+/// Container tags for `ClipboardData`
+const CONTAINER_BYTES: u8 = 1;
+const CONTAINER_IMAGE: u8 = 2;
+
+/// Number of recently copied items kept in the encrypted history ring
+pub const CLIPBOARD_HISTORY_SIZE: usize = 10;
+
+/// How often the tamper monitor samples the clipboard
+const TAMPER_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// How often the timer worker checks on one-time clipboard servers
+const SERVE_ONCE_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How a pending auto-clear empties the clipboard
+enum ClearAction {
+    /// Clear through the backend that made the copy
+    Backend {
+        clipboard: Arc<Mutex<ClipboardBackend>>,
+        /// Tamper watch to end first, so our own clear isn't reported
+        watch: Option<(ClipboardMonitor, u64)>,
+    },
+    /// Kill the one-time clipboard server (ends early on its own once pasted)
+    ServeOnce(Child),
+}
+
+impl ClearAction {
+    fn name(&self) -> &'static str {
+        match self {
+            ClearAction::Backend { .. } => "clear",
+            ClearAction::ServeOnce(_) => "one-time",
+        }
+    }
+
+    /// False once a one-time server has been pasted and exited
+    fn is_pending(&mut self) -> bool {
+        match self {
+            ClearAction::Backend { .. } => true,
+            ClearAction::ServeOnce(child) => matches!(child.try_wait(), Ok(None)),
+        }
+    }
+
+    fn fire(self) {
+        match self {
+            ClearAction::Backend { clipboard, watch } => {
+                if let Some((monitor, generation)) = watch {
+                    monitor.release(generation);
+                }
+                if let Ok(mut cb) = clipboard.lock() {
+                    let _ = cb.clear();
+                }
+            }
+            ClearAction::ServeOnce(mut child) => {
+                let _ = child.kill();
+                let _ = child.wait();
+            }
+        }
+    }
+}
+
+/// A scheduled auto-clear
+struct PendingClear {
+    id: u64,
+    /// None when cancelled (one-time servers keep serving until pasted)
+    deadline: Option<Instant>,
+    action: ClearAction,
+}
+
+/// Every pending auto-clear, driven by a single worker thread
+struct TimerRegistry {
+    pending: Vec<PendingClear>,
+    next_id: u64,
+    worker_running: bool,
+}
+
+static TIMERS: Mutex<TimerRegistry> = Mutex::new(TimerRegistry {
+    pending: Vec::new(),
+    next_id: 1,
+    worker_running: false,
+});
+
+/// Signalled whenever the registry changes so the worker recomputes its wait
+static TIMERS_CHANGED: Condvar = Condvar::new();
+
+/// Register the clear for a new copy. Earlier pending clears are superseded
+/// (the new content replaced what they would have cleared), so they can't race it.
+fn schedule_clear(action: ClearAction, timeout_secs: u64) {
+    let mut registry = TIMERS.lock().unwrap();
+
+    for superseded in registry.pending.drain(..) {
+        if let ClearAction::ServeOnce(mut child) = superseded.action {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+
+    let deadline = (timeout_secs > 0).then(|| Instant::now() + Duration::from_secs(timeout_secs));
+    if deadline.is_some() || matches!(action, ClearAction::ServeOnce(_)) {
+        let id = registry.next_id;
+        registry.next_id += 1;
+        registry.pending.push(PendingClear {
+            id,
+            deadline,
+            action,
+        });
+    }
+
+    if !registry.worker_running {
+        registry.worker_running = true;
+        thread::spawn(run_timers);
+    }
+    TIMERS_CHANGED.notify_all();
+}
+
+/// Timer worker: fire expired clears, reap pasted one-time servers, then sleep
+/// until the nearest deadline or the next registry change
+fn run_timers() {
+    let mut registry = TIMERS.lock().unwrap();
+    loop {
+        let now = Instant::now();
+        let (expired, mut pending): (Vec<_>, Vec<_>) = registry
+            .pending
+            .drain(..)
+            .partition(|timer| timer.deadline.is_some_and(|deadline| deadline <= now));
+        pending.retain_mut(|timer| timer.action.is_pending());
+        registry.pending = pending;
+
+        if !expired.is_empty() {
+            // Clearing can block (wl-copy); don't hold the registry meanwhile
+            drop(registry);
+            for timer in expired {
+                timer.action.fire();
+            }
+            registry = TIMERS.lock().unwrap();
+            continue;
+        }
+
+        let mut wait = registry
+            .pending
+            .iter()
+            .filter_map(|timer| timer.deadline)
+            .min()
+            .map(|deadline| deadline.saturating_duration_since(now));
+        if registry
+            .pending
+            .iter()
+            .any(|timer| matches!(timer.action, ClearAction::ServeOnce(_)))
+        {
+            wait = Some(wait.map_or(SERVE_ONCE_POLL_INTERVAL, |w| {
+                w.min(SERVE_ONCE_POLL_INTERVAL)
+            }));
+        }
+
+        registry = match wait {
+            Some(wait) => TIMERS_CHANGED.wait_timeout(registry, wait).unwrap().0,
+            None => TIMERS_CHANGED.wait(registry).unwrap(),
+        };
+    }
+}
+
+/// Run every pending auto-clear immediately (e.g. kill one-time servers)
+fn fire_clear_timers() {
+    let pending: Vec<_> = TIMERS.lock().unwrap().pending.drain(..).collect();
+    TIMERS_CHANGED.notify_all();
+    for timer in pending {
+        timer.action.fire();
+    }
+}
+
+/// Describe when a copy will be cleared
+fn auto_clear_note(timeout_secs: u64) -> String {
+    if timeout_secs > 0 {
+        format!("AUTO-CLEAR IN {}s", timeout_secs)
+    } else {
+        "NO AUTO-CLEAR (::cp --now-clear to clear)".to_string()
+    }
+}
+
+/// Whole seconds left before the clipboard is auto-cleared, if a clear is pending
+pub fn clear_countdown() -> Option<u64> {
+    let registry = TIMERS.lock().unwrap();
+    let deadline = registry
+        .pending
+        .iter()
+        .filter_map(|timer| timer.deadline)
+        .min()?;
+    let remaining = deadline.checked_duration_since(Instant::now())?;
+    // Round up so the countdown reads 30..1, never 0
+    Some(remaining.as_millis().div_ceil(1000) as u64)
+}
+
+/// Describe the pending auto-clears
+pub fn list_clear_timers() -> Vec<String> {
+    let registry = TIMERS.lock().unwrap();
+    let now = Instant::now();
+    registry
+        .pending
+        .iter()
+        .map(|timer| match timer.deadline {
+            Some(deadline) => format!(
+                "  #{}: {} in {}s",
+                timer.id,
+                timer.action.name(),
+                deadline.saturating_duration_since(now).as_secs()
+            ),
+            None => format!("  #{}: {} until pasted", timer.id, timer.action.name()),
+        })
+        .collect()
+}
+
+/// Cancel the pending auto-clears, leaving the clipboard as it is.
+/// One-time servers keep serving until pasted. Returns how many were cancelled.
+pub fn cancel_clear_timers() -> usize {
+    let mut registry = TIMERS.lock().unwrap();
+    let count = registry
+        .pending
+        .iter()
+        .filter(|timer| timer.deadline.is_some())
+        .count();
+    registry
+        .pending
+        .retain(|timer| matches!(timer.action, ClearAction::ServeOnce(_)));
+    for timer in registry.pending.iter_mut() {
+        timer.deadline = None;
+    }
+    TIMERS_CHANGED.notify_all();
+    count
+}
+
+/// Push the pending auto-clears back by `secs`. Returns how many were extended.
+pub fn extend_clear_timers(secs: u64) -> usize {
+    let mut registry = TIMERS.lock().unwrap();
+    let mut count = 0;
+    for timer in registry.pending.iter_mut() {
+        if let Some(deadline) = timer.deadline.as_mut() {
+            *deadline += Duration::from_secs(secs);
+            count += 1;
+        }
+    }
+    TIMERS_CHANGED.notify_all();
+    count
+}
+
+/// Where clipboard data actually goes
+enum ClipboardBackend {
+    /// arboard: X11, macOS, Windows and Wayland via wlr-data-control
+    Native(Clipboard),
+    /// wl-clipboard command-line tools, for Wayland sessions arboard can't reach
+    WlClipboard,
+    /// OSC 52 escape sequences: the local terminal emulator owns the clipboard
+    /// (used over SSH, where there is no local clipboard to talk to)
+    Osc52,
+}
+
+impl ClipboardBackend {
+    /// Pick a backend at runtime: OSC 52 for SSH sessions, otherwise arboard,
+    /// then wl-copy/wl-paste on Wayland
+    fn detect() -> Result<Self, String> {
+        if is_ssh_session() {
+            return Ok(ClipboardBackend::Osc52);
+        }
+
+        match Clipboard::new() {
+            Ok(clipboard) => Ok(ClipboardBackend::Native(clipboard)),
+            Err(e) => {
+                let on_wayland = env::var_os("WAYLAND_DISPLAY").is_some();
+                if on_wayland && crate::sandbox::find_in_path("wl-copy").is_some() {
+                    Ok(ClipboardBackend::WlClipboard)
+                } else {
+                    Err(e.to_string())
+                }
+            }
+        }
+    }
+
+    fn set_text(&mut self, text: &str) -> Result<(), String> {
+        match self {
+            ClipboardBackend::Native(cb) => cb.set_text(text).map_err(|e| e.to_string()),
+            ClipboardBackend::WlClipboard => {
+                // Content goes through stdin, never argv (visible in /proc)
+                let mut child = Command::new("wl-copy")
+                    .stdin(Stdio::piped())
+                    .stdout(Stdio::null())
+                    .stderr(Stdio::null())
+                    .spawn()
+                    .map_err(|e| e.to_string())?;
+                if let Some(mut stdin) = child.stdin.take() {
+                    stdin
+                        .write_all(text.as_bytes())
+                        .map_err(|e| e.to_string())?;
+                }
+                let status = child.wait().map_err(|e| e.to_string())?;
+                if status.success() {
+                    Ok(())
+                } else {
+                    Err(format!("wl-copy exited with {}", status))
+                }
+            }
+            ClipboardBackend::Osc52 => {
+                let encoded = general_purpose::STANDARD.encode(text.as_bytes());
+                if encoded.len() > OSC52_MAX_PAYLOAD {
+                    return Err(format!(
+                        "payload too large for OSC 52 ({} bytes, limit {})",
+                        encoded.len(),
+                        OSC52_MAX_PAYLOAD
+                    ));
+                }
+                write_osc52(&encoded).map_err(|e| e.to_string())
+            }
+        }
+    }
+
+    fn get_text(&mut self) -> Result<String, String> {
+        match self {
+            ClipboardBackend::Native(cb) => cb.get_text().map_err(|e| e.to_string()),
+            ClipboardBackend::WlClipboard => {
+                let output = Command::new("wl-paste")
+                    .args(["--no-newline", "--type", "text/plain"])
+                    .stderr(Stdio::null())
+                    .output()
+                    .map_err(|e| e.to_string())?;
+                if !output.status.success() {
+                    return Err("clipboard is empty".to_string());
+                }
+                String::from_utf8(output.stdout).map_err(|e| e.to_string())
+            }
+            ClipboardBackend::Osc52 => {
+                Err("the remote terminal clipboard can't be read over OSC 52".to_string())
+            }
+        }
+    }
+
+    fn set_image(&mut self, width: usize, height: usize, rgba: &[u8]) -> Result<(), String> {
+        match self {
+            ClipboardBackend::Native(cb) => cb
+                .set_image(ImageData {
+                    width,
+                    height,
+                    bytes: Cow::Borrowed(rgba),
+                })
+                .map_err(|e| e.to_string()),
+            _ => Err(IMAGES_NEED_NATIVE.to_string()),
+        }
+    }
+
+    fn get_image(&mut self) -> Result<(usize, usize, Vec<u8>), String> {
+        match self {
+            ClipboardBackend::Native(cb) => cb
+                .get_image()
+                .map(|image| (image.width, image.height, image.bytes.into_owned()))
+                .map_err(|e| e.to_string()),
+            _ => Err(IMAGES_NEED_NATIVE.to_string()),
+        }
+    }
+
+    fn clear(&mut self) -> Result<(), String> {
+        match self {
+            ClipboardBackend::Native(cb) => cb.clear().map_err(|e| e.to_string()),
+            ClipboardBackend::WlClipboard => Command::new("wl-copy")
+                .arg("--clear")
+                .status()
+                .map_err(|e| e.to_string())
+                .map(|_| ()),
+            // An empty payload clears the selection
+            ClipboardBackend::Osc52 => write_osc52("").map_err(|e| e.to_string()),
+        }
+    }
+}
+
+const IMAGES_NEED_NATIVE: &str =
+    "images need the native clipboard (X11, macOS, Windows or wlr-data-control)";
+
+/// True when the shell is running inside an SSH session
+fn is_ssh_session() -> bool {
+    crate::remote::ssh_session().is_some()
+}
+
+/// Emit an OSC 52 "set clipboard" sequence, wrapped for tmux passthrough when needed
+fn write_osc52(encoded: &str) -> io::Result<()> {
+    let sequence = format!("\x1b]52;c;{}\x07", encoded);
+    let mut stdout = crate::term::stdout();
+    if env::var_os("TMUX").is_some() {
+        write!(stdout, "\x1bPtmux;\x1b{}\x1b\\", sequence)?;
+    } else {
+        write!(stdout, "{}", sequence)?;
+    }
+    stdout.flush()
+}
+
+/// Hand `content` to a clipboard tool that serves exactly one paste and then
+/// drops the selection: `wl-copy --paste-once` on Wayland, `xclip -loops 1` on X11.
+/// If nobody pastes within `timeout_secs` the timer registry kills the tool, which
+/// clears it too.
+fn serve_once(content: &str, timeout_secs: u64) -> Result<(), String> {
+    if is_ssh_session() {
+        return Err(
+            "One-time clipboard is unavailable over SSH (OSC 52 can't report pastes).".to_string(),
+        );
+    }
+
+    let on_wayland = env::var_os("WAYLAND_DISPLAY").is_some();
+    let on_x11 = env::var_os("DISPLAY").is_some();
+    let mut command = if on_wayland && crate::sandbox::find_in_path("wl-copy").is_some() {
+        let mut command = Command::new("wl-copy");
+        command.args(["--paste-once", "--foreground"]);
+        command
+    } else if on_x11 && crate::sandbox::find_in_path("xclip").is_some() {
+        let mut command = Command::new("xclip");
+        command.args(["-selection", "clipboard", "-loops", "1", "-quiet"]);
+        command
+    } else {
+        return Err("One-time clipboard needs wl-copy (Wayland) or xclip (X11).".to_string());
+    };
+
+    // Content goes through stdin, never argv (visible in /proc)
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("Clipboard error: {}", e))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        if let Err(e) = stdin.write_all(content.as_bytes()) {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(format!("Clipboard error: {}", e));
+        }
+    }
+
+    schedule_clear(ClearAction::ServeOnce(child), timeout_secs);
+    Ok(())
+}
+
+/// Binary clipboard content. Serialized into a container before encryption:
+/// `0x01 | bytes` or `0x02 | width (u32 BE) | height (u32 BE) | RGBA pixels`
+pub enum ClipboardData {
+    Bytes(Vec<u8>),
+    Image {
+        width: usize,
+        height: usize,
+        rgba: Vec<u8>,
+    },
+}
+
+impl ClipboardData {
+    /// Short description for status messages
+    pub fn describe(&self) -> String {
+        match self {
+            ClipboardData::Bytes(bytes) => format!("{} BYTES", bytes.len()),
+            ClipboardData::Image { width, height, .. } => format!("{}x{} IMAGE", width, height),
+        }
+    }
+
+    fn to_container(&self) -> Result<Vec<u8>, String> {
+        match self {
+            ClipboardData::Bytes(bytes) => {
+                let mut container = Vec::with_capacity(1 + bytes.len());
+                container.push(CONTAINER_BYTES);
+                container.extend_from_slice(bytes);
+                Ok(container)
+            }
+            ClipboardData::Image {
+                width,
+                height,
+                rgba,
+            } => {
+                let (width, height) = u32::try_from(*width)
+                    .and_then(|w| u32::try_from(*height).map(|h| (w, h)))
+                    .map_err(|_| "Image too large.".to_string())?;
+                let mut container = Vec::with_capacity(9 + rgba.len());
+                container.push(CONTAINER_IMAGE);
+                container.extend_from_slice(&width.to_be_bytes());
+                container.extend_from_slice(&height.to_be_bytes());
+                container.extend_from_slice(rgba);
+                Ok(container)
+            }
+        }
+    }
+
+    fn from_container(mut container: Vec<u8>) -> Result<Self, String> {
+        match container.first() {
+            Some(&CONTAINER_BYTES) => {
+                container.remove(0);
+                Ok(ClipboardData::Bytes(container))
+            }
+            Some(&CONTAINER_IMAGE) if container.len() >= 9 => {
+                let width =
+                    u32::from_be_bytes([container[1], container[2], container[3], container[4]])
+                        as usize;
+                let height =
+                    u32::from_be_bytes([container[5], container[6], container[7], container[8]])
+                        as usize;
+                if width.checked_mul(height).and_then(|px| px.checked_mul(4))
+                    != Some(container.len() - 9)
+                {
+                    container.zeroize();
+                    return Err("Invalid image container.".to_string());
+                }
+                container.drain(..9);
+                Ok(ClipboardData::Image {
+                    width,
+                    height,
+                    rgba: container,
+                })
+            }
+            _ => {
+                container.zeroize();
+                Err("Invalid binary container.".to_string())
+            }
+        }
+    }
+}
+
+impl Drop for ClipboardData {
+    fn drop(&mut self) {
+        match self {
+            ClipboardData::Bytes(bytes) => bytes.zeroize(),
+            ClipboardData::Image { rgba, .. } => rgba.zeroize(),
+        }
+    }
+}
+
+/// What the system clipboard currently holds
+pub enum ClipboardPayload {
+    /// Not a Ghost Shell payload
+    Plain(String),
+    /// GHOST_ENCRYPTED_BIN binary or image container (key-protected)
+    Binary,
+    /// GHOST_ENCRYPTED under a random key (printed by ::cp)
+    KeyProtected,
+    /// GHOST_ENCRYPTED under a passphrase-derived key
+    PassphraseProtected,
+    /// ASCII-armored age, to an X25519 identity or a passphrase (scrypt)
+    Age { passphrase: bool },
+    /// ASCII-armored OpenPGP message (decrypted by gpg)
+    Gpg,
+}
+
+/// What to do when clipboard content set by Ghost Shell is replaced
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum TamperResponse {
+    /// Don't monitor the clipboard
+    Off,
+    /// Raise an alert
+    Alert,
+    /// Raise an alert and purge the clipboard history entry that was replaced
+    Purge,
+}
+
+impl TamperResponse {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "off" => Some(TamperResponse::Off),
+            "alert" => Some(TamperResponse::Alert),
+            "purge" => Some(TamperResponse::Purge),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            TamperResponse::Off => "off",
+            TamperResponse::Alert => "alert",
+            TamperResponse::Purge => "purge",
+        }
+    }
+}
+
+/// Ghost Shell content replaced on the clipboard by another application
+pub struct TamperAlert {
+    /// Clipboard history entry the replaced content came from
+    pub history_id: Option<u64>,
+}
+
+struct MonitorState {
+    response: TamperResponse,
+    /// Bumped whenever the watched content changes hands; stale watchers exit
+    generation: u64,
+    alert: Option<TamperAlert>,
+}
+
+/// Watches the clipboard after each copy and flags content that another
+/// application replaced before the auto-clear (a possible clipboard hijacker).
+/// Only a keyed fingerprint of the copied content is kept.
+#[derive(Clone)]
+pub struct ClipboardMonitor {
+    state: Arc<Mutex<MonitorState>>,
+    fingerprint_key: RandomState,
+}
+
+impl Default for ClipboardMonitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ClipboardMonitor {
+    pub fn new() -> Self {
+        ClipboardMonitor {
+            state: Arc::new(Mutex::new(MonitorState {
+                response: TamperResponse::Alert,
+                generation: 0,
+                alert: None,
+            })),
+            fingerprint_key: RandomState::new(),
+        }
+    }
+
+    pub fn response(&self) -> TamperResponse {
+        self.state.lock().unwrap().response
+    }
+
+    /// Change the response; `Off` also stops the running watch
+    pub fn set_response(&self, response: TamperResponse) {
+        let mut state = self.state.lock().unwrap();
+        state.response = response;
+        if response == TamperResponse::Off {
+            state.generation += 1;
+        }
+    }
+
+    /// Take the pending tamper alert, if any
+    pub fn take_alert(&self) -> Option<TamperAlert> {
+        self.state.lock().unwrap().alert.take()
+    }
+
+    /// Start watching `content`, superseding any earlier watch.
+    /// Must be called before `content` is placed on the clipboard.
+    fn watch(
+        &self,
+        clipboard: Arc<Mutex<ClipboardBackend>>,
+        content: &str,
+        history_id: Option<u64>,
+    ) -> u64 {
+        let generation = {
+            let mut state = self.state.lock().unwrap();
+            state.generation += 1;
+            if state.response == TamperResponse::Off {
+                return state.generation;
+            }
+            state.generation
+        };
+
+        let fingerprint = self.fingerprint_key.hash_one(content);
+        let monitor = self.clone();
+        thread::spawn(move || loop {
+            thread::sleep(TAMPER_POLL_INTERVAL);
+
+            let replaced = {
+                let mut cb = match clipboard.lock() {
+                    Ok(cb) => cb,
+                    Err(_) => return,
+                };
+                if !monitor.is_current(generation) {
+                    return;
+                }
+                match cb.get_text() {
+                    Ok(mut text) => {
+                        let replaced = monitor.fingerprint_key.hash_one(&text) != fingerprint;
+                        text.zeroize();
+                        replaced
+                    }
+                    // Emptied, or unreadable (OSC 52): nothing left to watch
+                    Err(_) => return,
+                }
+            };
+
+            if replaced {
+                let mut state = monitor.state.lock().unwrap();
+                // Our own clear or a newer copy may have raced the read
+                if state.generation == generation {
+                    state.generation += 1;
+                    state.alert = Some(TamperAlert { history_id });
+                }
+                return;
+            }
+        });
+
+        generation
+    }
+
+    fn is_current(&self, generation: u64) -> bool {
+        self.state.lock().unwrap().generation == generation
+    }
+
+    /// End watch `generation` if it is still the active one
+    fn release(&self, generation: u64) {
+        let mut state = self.state.lock().unwrap();
+        if state.generation == generation {
+            state.generation += 1;
+        }
+    }
+}
+
+/// Encrypted clipboard manager
+#[derive(Clone)]
+pub struct SecureClipboard {
+    clipboard: Arc<Mutex<ClipboardBackend>>,
+    encryption_enabled: bool,
+    /// Tamper monitor and the history entry being copied
+    monitor: Option<(ClipboardMonitor, Option<u64>)>,
+    /// Clear the clipboard as soon as it has been pasted once
+    once: bool,
+}
+
+impl SecureClipboard {
+    pub fn new(encryption_enabled: bool) -> Result<Self, String> {
+        match ClipboardBackend::detect() {
+            Ok(clipboard) => Ok(SecureClipboard {
+                clipboard: Arc::new(Mutex::new(clipboard)),
+                encryption_enabled,
+                monitor: None,
+                once: false,
+            }),
+            Err(e) => Err(format!("Failed to access clipboard: {}", e)),
+        }
+    }
+
+    /// The same clipboard, copying with encryption on or off. Clones share the
+    /// backend, so one session needs only one `new`.
+    pub fn encrypted(mut self, encryption_enabled: bool) -> Self {
+        self.encryption_enabled = encryption_enabled;
+        self
+    }
+
+    /// Watch what this instance copies for replacement by another application,
+    /// attributing it to clipboard history entry `history_id`
+    pub fn monitored(mut self, monitor: &ClipboardMonitor, history_id: Option<u64>) -> Self {
+        self.monitor = Some((monitor.clone(), history_id));
+        self
+    }
+
+    /// Serve copies for exactly one paste. Drops tamper monitoring, since
+    /// reading the clipboard ourselves would use up that paste.
+    pub fn one_time(mut self) -> Self {
+        self.once = true;
+        self.monitor = None;
+        self
+    }
+
+    /// Copy text to clipboard with optional encryption and auto-clear
+    pub fn copy_with_timeout(
+        &self,
+        text: impl Into<SecretString>,
+        timeout_secs: u64,
+    ) -> Result<String, String> {
+        let text = text.into();
+        let result = if self.encryption_enabled {
+            self.copy_encrypted(text.expose(), timeout_secs)
+        } else {
+            self.copy_plain(text.expose(), timeout_secs)
+        };
+        result.map(|msg| self.describe_once(msg))
+    }
+
+    /// Copy text encrypted under a key derived from `passphrase` (Argon2id).
+    /// The salt travels inside the payload, so nothing but the passphrase is
+    /// needed to decrypt and no key is ever printed.
+    pub fn copy_with_passphrase(
+        &self,
+        text: impl Into<SecretString>,
+        passphrase: &str,
+        timeout_secs: u64,
+    ) -> Result<String, String> {
+        let text = text.into();
+        let mut salt = [0u8; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+
+        let result = derive_passphrase_key(passphrase, &salt).and_then(|key| {
+            let (nonce_b64, encrypted_b64) = seal(key.expose(), text.expose().as_bytes())?;
+            let salt_b64 = general_purpose::STANDARD.encode(salt);
+
+            // Format: GHOST_ENCRYPTED:<salt>:<nonce>:<ciphertext>
+            let clipboard_content =
+                format!("{ENCRYPTED_PREFIX}{salt_b64}:{nonce_b64}:{encrypted_b64}");
+            self.place_on_clipboard(&clipboard_content, timeout_secs)?;
+
+            Ok(format!(
+                "ENCRYPTED DATA INJECTED (PASSPHRASE-DERIVED KEY).\r\n{}.\r\nUse ::decrypt -p to recover.",
+                auto_clear_note(timeout_secs)
+            ))
+        });
+        result.map(|msg| self.describe_once(msg))
+    }
+
+    fn describe_once(&self, msg: String) -> String {
+        if self.once {
+            format!("{msg}\r\nONE-TIME READ: cleared after the first paste.")
+        } else {
+            msg
+        }
+    }
+
+    /// Copy plain text with auto-clear
+    fn copy_plain(&self, text: &str, timeout_secs: u64) -> Result<String, String> {
+        self.place_on_clipboard(text, timeout_secs)?;
+
+        if timeout_secs > 0 {
+            Ok(format!(
+                "DATA INJECTED TO CLIPBOARD. AUTO-CLEAR IN {}s.",
+                timeout_secs
+            ))
+        } else {
+            Ok("DATA INJECTED TO CLIPBOARD. TRACES REMOVED.".to_string())
+        }
+    }
+
+    /// Copy encrypted text with auto-clear
+    fn copy_encrypted(&self, text: &str, timeout_secs: u64) -> Result<String, String> {
+        let (clipboard_content, key_b64) = encrypt_payload(text)?;
+        self.place_on_clipboard(&clipboard_content, timeout_secs)?;
+
+        // The key is meant to be shown: it is the only way to decrypt
+        Ok(format!(
+            "ENCRYPTED DATA INJECTED. KEY: {}\r\n{}.\r\nUse ::decrypt to recover.",
+            key_b64.expose(),
+            auto_clear_note(timeout_secs)
+        ))
+    }
+
+    /// Put `content` on the system clipboard and schedule the auto-clear
+    fn place_on_clipboard(&self, content: &str, timeout_secs: u64) -> Result<(), String> {
+        if self.once {
+            return serve_once(content, timeout_secs);
+        }
+
+        let clipboard = Arc::clone(&self.clipboard);
+
+        let watch = self.monitor.as_ref().map(|(monitor, history_id)| {
+            let generation = monitor.watch(Arc::clone(&clipboard), content, *history_id);
+            (monitor.clone(), generation)
+        });
+
+        // Copy to clipboard
+        {
+            let mut cb = clipboard.lock().unwrap();
+            if let Err(e) = cb.set_text(content) {
+                if let Some((monitor, generation)) = &watch {
+                    monitor.release(*generation);
+                }
+                return Err(format!("Clipboard error: {e}"));
+            }
+        }
+
+        // Schedule auto-clear
+        schedule_clear(ClearAction::Backend { clipboard, watch }, timeout_secs);
+        Ok(())
+    }
+
+    /// Read the clipboard and split a GHOST_ENCRYPTED payload into its fields
+    fn read_encrypted_payload(&self) -> Result<Vec<String>, String> {
+        let clipboard_text = self.get_text()?;
+
+        match clipboard_text.strip_prefix(ENCRYPTED_PREFIX) {
+            Some(payload) => Ok(payload.split(':').map(str::to_string).collect()),
+            None => Err("Clipboard does not contain encrypted Ghost Shell data.".to_string()),
+        }
+    }
+
+    /// Read the clipboard as-is
+    pub fn get_text(&self) -> Result<String, String> {
+        let mut cb = self.clipboard.lock().unwrap();
+        cb.get_text()
+            .map_err(|e| format!("Failed to read clipboard: {}", e))
+    }
+
+    /// Read the clipboard and classify its content
+    pub fn inspect(&self) -> Result<ClipboardPayload, String> {
+        let clipboard_text = self.get_text()?;
+
+        if clipboard_text.starts_with(ENCRYPTED_BINARY_PREFIX) {
+            return Ok(ClipboardPayload::Binary);
+        }
+        if clipboard_text.trim_start().starts_with(age::ARMOR_BEGIN) {
+            let passphrase = age::dearmor(&clipboard_text)
+                .and_then(|binary| age::is_passphrase_protected(&mut binary.as_slice()))?;
+            return Ok(ClipboardPayload::Age { passphrase });
+        }
+        if clipboard_text
+            .trim_start()
+            .starts_with(gpg::PGP_ARMOR_BEGIN)
+        {
+            return Ok(ClipboardPayload::Gpg);
+        }
+        match clipboard_text.strip_prefix(ENCRYPTED_PREFIX) {
+            Some(payload) if payload.split(':').count() == 3 => {
+                Ok(ClipboardPayload::PassphraseProtected)
+            }
+            Some(_) => Ok(ClipboardPayload::KeyProtected),
+            None => Ok(ClipboardPayload::Plain(clipboard_text)),
+        }
+    }
+
+    /// Decrypt clipboard content
+    pub fn decrypt_clipboard(&self, key_b64: &str) -> Result<SecretString, String> {
+        let parts = self.read_encrypted_payload()?;
+
+        if parts.len() == 3 {
+            return Err("Payload is passphrase-protected. Use ::decrypt -p.".to_string());
+        }
+        if parts.len() != 2 {
+            return Err("Invalid encrypted format.".to_string());
+        }
+
+        let key_bytes = SecretBytes::new(
+            general_purpose::STANDARD
+                .decode(key_b64)
+                .map_err(|_| "Invalid key format.")?,
+        );
+        open(key_bytes.expose(), &parts[0], &parts[1])
+    }
+
+    /// Decrypt a passphrase-protected clipboard payload
+    pub fn decrypt_clipboard_with_passphrase(
+        &self,
+        passphrase: &str,
+    ) -> Result<SecretString, String> {
+        let parts = self.read_encrypted_payload()?;
+
+        if parts.len() == 2 {
+            return Err("Payload uses a random key. Use ::decrypt <key>.".to_string());
+        }
+        if parts.len() != 3 {
+            return Err("Invalid encrypted format.".to_string());
+        }
+
+        let salt = general_purpose::STANDARD
+            .decode(&parts[0])
+            .map_err(|_| "Invalid salt format.")?;
+
+        let key = derive_passphrase_key(passphrase, &salt)?;
+        open(key.expose(), &parts[1], &parts[2])
+    }
+
+    /// Encrypt text to age recipients (or an age passphrase) and put the
+    /// ASCII-armored result on the clipboard, readable by standard age tooling
+    pub fn copy_age(
+        &self,
+        text: impl Into<SecretString>,
+        recipients: &Recipients,
+        timeout_secs: u64,
+    ) -> Result<String, String> {
+        let text = text.into();
+        let mut binary = Vec::new();
+        let result =
+            age::encrypt(recipients, &mut text.expose().as_bytes(), &mut binary).and_then(|_| {
+                self.place_on_clipboard(&age::armor(&binary), timeout_secs)?;
+                Ok(format!(
+                    "AGE-ENCRYPTED DATA INJECTED.\r\n{}.\r\nRecover with ::paste or `age -d`.",
+                    auto_clear_note(timeout_secs)
+                ))
+            });
+        result.map(|msg| self.describe_once(msg))
+    }
+
+    /// Encrypt text to an OpenPGP `recipient` with gpg and put the armored
+    /// message on the clipboard
+    pub fn copy_gpg(
+        &self,
+        text: impl Into<SecretString>,
+        recipient: &str,
+        timeout_secs: u64,
+    ) -> Result<String, String> {
+        let text = text.into();
+        let result = gpg::encrypt(recipient, text.expose().as_bytes()).and_then(|armored| {
+            self.place_on_clipboard(&armored, timeout_secs)?;
+            Ok(format!(
+                "GPG-ENCRYPTED DATA INJECTED FOR '{}'.\r\n{}.\r\nRecover with ::paste or `gpg -d`.",
+                recipient,
+                auto_clear_note(timeout_secs)
+            ))
+        });
+        result.map(|msg| self.describe_once(msg))
+    }
+
+    /// Decrypt an ASCII-armored OpenPGP message on the clipboard with gpg
+    pub fn decrypt_clipboard_gpg(&self) -> Result<SecretString, String> {
+        SecretBytes::new(gpg::decrypt(self.get_text()?.as_bytes())?).into_text()
+    }
+
+    /// Decrypt an ASCII-armored age payload on the clipboard
+    pub fn decrypt_clipboard_age(&self, unlock: &Unlock) -> Result<SecretString, String> {
+        let binary = age::dearmor(&self.get_text()?)?;
+        let mut plaintext = SecretBytes::new(Vec::new());
+        age::decrypt(unlock, &mut binary.as_slice(), plaintext.expose_mut())?;
+        plaintext.into_text()
+    }
+
+    /// Encrypt binary content or an image under a fresh key and put the
+    /// container on the clipboard as text, with auto-clear
+    pub fn copy_data_with_timeout(
+        &self,
+        data: ClipboardData,
+        timeout_secs: u64,
+    ) -> Result<String, String> {
+        let mut container = data.to_container()?;
+        let encrypted = encrypt_with_prefix(ENCRYPTED_BINARY_PREFIX, &container);
+        container.zeroize();
+        let (clipboard_content, key_b64) = encrypted?;
+        self.place_on_clipboard(&clipboard_content, timeout_secs)?;
+
+        let output = format!(
+            "ENCRYPTED {} INJECTED. KEY: {}\r\n{}.\r\nUse ::decrypt to recover.",
+            data.describe(),
+            key_b64.expose(),
+            auto_clear_note(timeout_secs)
+        );
+        Ok(self.describe_once(output))
+    }
+
+    /// Read the image currently on the clipboard (e.g. a screenshot)
+    pub fn get_image(&self) -> Result<ClipboardData, String> {
+        let (width, height, rgba) = {
+            let mut cb = self.clipboard.lock().unwrap();
+            cb.get_image()
+                .map_err(|e| format!("Failed to read clipboard image: {}", e))?
+        };
+        let image = ClipboardData::Image {
+            width,
+            height,
+            rgba,
+        };
+        if let ClipboardData::Image { rgba, .. } = &image {
+            if rgba.len() as u64 > MAX_BINARY_COPY_SIZE {
+                return Err(format!(
+                    "Clipboard image too large ({} bytes, limit {}).",
+                    rgba.len(),
+                    MAX_BINARY_COPY_SIZE
+                ));
+            }
+        }
+        Ok(image)
+    }
+
+    /// Put a decrypted image on the clipboard, with auto-clear
+    pub fn restore_image(
+        &self,
+        image: &ClipboardData,
+        timeout_secs: u64,
+    ) -> Result<String, String> {
+        let ClipboardData::Image {
+            width,
+            height,
+            rgba,
+        } = image
+        else {
+            return Err("Not an image.".to_string());
+        };
+
+        {
+            let mut cb = self.clipboard.lock().unwrap();
+            cb.set_image(*width, *height, rgba)
+                .map_err(|e| format!("Clipboard error: {e}"))?;
+        }
+        let clipboard = Arc::clone(&self.clipboard);
+        schedule_clear(
+            ClearAction::Backend {
+                clipboard,
+                watch: None,
+            },
+            timeout_secs,
+        );
+
+        Ok(format!(
+            "{} RESTORED TO CLIPBOARD. {}.",
+            image.describe(),
+            auto_clear_note(timeout_secs)
+        ))
+    }
+
+    /// Decrypt a GHOST_ENCRYPTED_BIN container from the clipboard
+    pub fn decrypt_clipboard_data(&self, key_b64: &str) -> Result<ClipboardData, String> {
+        let clipboard_text = self.get_text()?;
+        let parts: Vec<&str> = match clipboard_text.strip_prefix(ENCRYPTED_BINARY_PREFIX) {
+            Some(payload) => payload.split(':').collect(),
+            None => {
+                return Err("Clipboard does not contain an encrypted binary payload.".to_string())
+            }
+        };
+        if parts.len() != 2 {
+            return Err("Invalid encrypted format.".to_string());
+        }
+
+        let key_bytes = SecretBytes::new(
+            general_purpose::STANDARD
+                .decode(key_b64)
+                .map_err(|_| "Invalid key format.")?,
+        );
+        let nonce = general_purpose::STANDARD
+            .decode(parts[0])
+            .map_err(|_| "Invalid nonce format.")?;
+        let ciphertext = general_purpose::STANDARD
+            .decode(parts[1])
+            .map_err(|_| "Invalid ciphertext format.")?;
+        let container = open_bytes(key_bytes.expose(), &nonce, &ciphertext)?;
+
+        ClipboardData::from_container(container.into_exposed())
+    }
+
+    /// Clear clipboard immediately, firing every pending auto-clear now
+    pub fn clear(&self) -> Result<(), String> {
+        fire_clear_timers();
+        let mut cb = self.clipboard.lock().unwrap();
+        cb.clear()
+            .map_err(|e| format!("Failed to clear clipboard: {}", e))
+    }
+}
+
+/// Read a (small) text file for the clipboard pipeline, refusing anything over
+/// `MAX_FILE_COPY_SIZE` and zeroizing the buffer if it isn't valid UTF-8
+pub fn read_file_for_copy(path: &Path) -> Result<String, String> {
+    let buffer = read_file_bytes(path, MAX_FILE_COPY_SIZE)?;
+
+    String::from_utf8(buffer).map_err(|e| {
+        let mut bytes = e.into_bytes();
+        bytes.zeroize();
+        format!("{}: not a UTF-8 text file.", path.display())
+    })
+}
+
+/// Read any file (up to `MAX_BINARY_COPY_SIZE`) for the binary clipboard pipeline
+pub fn read_binary_file_for_copy(path: &Path) -> Result<ClipboardData, String> {
+    read_file_bytes(path, MAX_BINARY_COPY_SIZE).map(ClipboardData::Bytes)
+}
+
+/// Write decrypted bytes to a new file readable only by the owner
+pub fn write_private_file(path: &Path, bytes: &[u8]) -> Result<(), String> {
+    create_private_file(path)?
+        .write_all(bytes)
+        .map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// Create a new file readable only by the owner, refusing to overwrite
+pub fn create_private_file(path: &Path) -> Result<File, String> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options
+        .open(path)
+        .map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// Read a whole file, refusing anything over `limit` bytes
+fn read_file_bytes(path: &Path, limit: u64) -> Result<Vec<u8>, String> {
+    let file = File::open(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+
+    let size = file
+        .metadata()
+        .map_err(|e| format!("{}: {}", path.display(), e))?
+        .len();
+    if size > limit {
+        return Err(format!(
+            "{}: file too large ({} bytes, limit {}).",
+            path.display(),
+            size,
+            limit
+        ));
+    }
+
+    // Reserve up front so reading doesn't leave reallocated copies behind;
+    // take() guards against files that grow while we read them
+    let mut buffer = Vec::with_capacity(size as usize + 1);
+    if let Err(e) = file.take(limit + 1).read_to_end(&mut buffer) {
+        buffer.zeroize();
+        return Err(format!("{}: {}", path.display(), e));
+    }
+    if buffer.len() as u64 > limit {
+        buffer.zeroize();
+        return Err(format!("{}: file too large.", path.display()));
+    }
+
+    Ok(buffer)
+}
+
+/// Encrypt `text` under a fresh random key without touching any clipboard.
+/// Returns the `GHOST_ENCRYPTED:<nonce>:<ciphertext>` payload and the base64 key.
+pub fn encrypt_payload(text: &str) -> Result<(String, SecretString), String> {
+    encrypt_with_prefix(ENCRYPTED_PREFIX, text.as_bytes())
+}
+
+/// Encrypt `plaintext` under a fresh random key as `<prefix><nonce>:<ciphertext>`
+fn encrypt_with_prefix(prefix: &str, plaintext: &[u8]) -> Result<(String, SecretString), String> {
+    // Generate random key
+    let mut key_bytes = SecretKey::new([0u8; 32]);
+    OsRng.fill_bytes(key_bytes.expose_mut());
+
+    let (nonce_b64, encrypted_b64) = seal(key_bytes.expose(), plaintext)?;
+    let key_b64 = SecretString::new(general_purpose::STANDARD.encode(key_bytes.expose()));
+
+    // Format: GHOST_ENCRYPTED:<nonce>:<ciphertext>
+    Ok((format!("{prefix}{nonce_b64}:{encrypted_b64}"), key_b64))
+}
+
+/// Derive a 256-bit key from a passphrase with Argon2id (default parameters)
+pub fn derive_passphrase_key(passphrase: &str, salt: &[u8]) -> Result<SecretKey, String> {
+    let mut key = SecretKey::new([0u8; 32]);
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, key.expose_mut())
+        .map_err(|e| format!("Key derivation failed: {}", e))?;
+    Ok(key)
+}
+
+/// Encrypt `plaintext` under `key` with a fresh random nonce.
+/// Returns the raw nonce and ciphertext.
+pub fn seal_bytes(key: &[u8; 32], plaintext: &[u8]) -> Result<([u8; 12], Vec<u8>), String> {
+    let mut nonce_bytes = [0u8; 12];
+    OsRng.fill_bytes(&mut nonce_bytes);
+
+    let cipher = ChaCha20Poly1305::new(key.into());
+    let nonce = Nonce::from_slice(&nonce_bytes);
+
+    let ciphertext = cipher
+        .encrypt(nonce, plaintext)
+        .map_err(|e| format!("Encryption failed: {}", e))?;
+
+    Ok((nonce_bytes, ciphertext))
+}
+
+/// Decrypt a raw nonce/ciphertext pair produced by `seal_bytes`
+pub fn open_bytes(
+    key: &[u8],
+    nonce_bytes: &[u8],
+    ciphertext: &[u8],
+) -> Result<SecretBytes, String> {
+    if key.len() != 32 || nonce_bytes.len() != 12 {
+        return Err("Invalid key or nonce length.".to_string());
+    }
+
+    let cipher = ChaCha20Poly1305::new(key.into());
+    let nonce = Nonce::from_slice(nonce_bytes);
+
+    cipher
+        .decrypt(nonce, ciphertext)
+        .map(SecretBytes::new)
+        .map_err(|_| "Decryption failed. Wrong key or corrupted data.".to_string())
+}
+
+/// Encrypt `plaintext` under `key` with a fresh random nonce.
+/// Returns the base64 nonce and ciphertext.
+fn seal(key: &[u8; 32], plaintext: &[u8]) -> Result<(String, String), String> {
+    let (mut nonce_bytes, ciphertext) = seal_bytes(key, plaintext)?;
+
+    // Encode as base64
+    let encrypted_b64 = general_purpose::STANDARD.encode(ciphertext);
+    let nonce_b64 = general_purpose::STANDARD.encode(nonce_bytes);
+    nonce_bytes.zeroize();
+
+    Ok((nonce_b64, encrypted_b64))
+}
+
+/// Decrypt a base64 nonce/ciphertext pair produced by `seal`
+fn open(key: &[u8], nonce_b64: &str, ciphertext_b64: &str) -> Result<SecretString, String> {
+    let nonce_bytes = general_purpose::STANDARD
+        .decode(nonce_b64)
+        .map_err(|_| "Invalid nonce format.")?;
+
+    let ciphertext = general_purpose::STANDARD
+        .decode(ciphertext_b64)
+        .map_err(|_| "Invalid ciphertext format.")?;
+
+    open_bytes(key, &nonce_bytes, &ciphertext)?.into_text()
+}
+
+/// One encrypted entry of the clipboard history ring or a named slot
+struct SealedItem {
+    nonce: [u8; 12],
+    ciphertext: Vec<u8>,
+    length: usize,
+    copied_at: Instant,
+}
+
+/// Encrypted in-memory ring of recently copied items.
+/// Entries never touch the system clipboard until explicitly restored.
+pub struct ClipboardHistory {
+    key: SubKey,
+    items: VecDeque<(u64, SealedItem)>,
+    next_id: u64,
+}
+
+impl ClipboardHistory {
+    pub fn new(key: SubKey) -> Self {
+        ClipboardHistory {
+            key,
+            items: VecDeque::with_capacity(CLIPBOARD_HISTORY_SIZE),
+            next_id: 0,
+        }
+    }
+
+    /// Encrypt and record a copied item, evicting the oldest when full.
+    /// Returns the entry's stable id.
+    pub fn push(&mut self, text: &str) -> Result<u64, String> {
+        let (nonce, ciphertext) = self.key.expose(|key| seal_bytes(key, text.as_bytes()))?;
+
+        if self.items.len() == CLIPBOARD_HISTORY_SIZE {
+            if let Some((_, mut oldest)) = self.items.pop_back() {
+                oldest.ciphertext.zeroize();
+            }
+        }
+
+        let id = self.next_id;
+        self.next_id += 1;
+        self.items.push_front((
+            id,
+            SealedItem {
+                nonce,
+                ciphertext,
+                length: text.chars().count(),
+                copied_at: Instant::now(),
+            },
+        ));
+        Ok(id)
+    }
+
+    /// Describe the stored items (1 = most recent) without decrypting them
+    pub fn list(&self) -> Vec<String> {
+        self.items
+            .iter()
+            .enumerate()
+            .map(|(i, (_, item))| {
+                format!(
+                    "  {}: {} chars, copied {}s ago",
+                    i + 1,
+                    item.length,
+                    item.copied_at.elapsed().as_secs()
+                )
+            })
+            .collect()
+    }
+
+    /// Stable id of item `n` (1 = most recent)
+    pub fn id(&self, n: usize) -> Option<u64> {
+        n.checked_sub(1)
+            .and_then(|i| self.items.get(i))
+            .map(|(id, _)| *id)
+    }
+
+    /// Decrypt item `n` (1 = most recent)
+    pub fn get(&self, n: usize) -> Result<SecretString, String> {
+        let (_, item) = n
+            .checked_sub(1)
+            .and_then(|i| self.items.get(i))
+            .ok_or_else(|| format!("No clipboard history entry #{}.", n))?;
+
+        self.key
+            .expose(|key| open_bytes(key, &item.nonce, &item.ciphertext))?
+            .into_text()
+    }
+
+    /// Zeroize and drop the entry with stable id `id`, if still present
+    pub fn remove(&mut self, id: u64) -> bool {
+        match self.items.iter().position(|(item_id, _)| *item_id == id) {
+            Some(index) => {
+                if let Some((_, mut item)) = self.items.remove(index) {
+                    item.ciphertext.zeroize();
+                    item.nonce.zeroize();
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Where the encrypted items sit in memory, for ::meminfo
+    pub fn regions(&self) -> Vec<Range<usize>> {
+        self.items
+            .iter()
+            .map(|(_, item)| region(&item.ciphertext))
+            .collect()
+    }
+
+    /// Zeroize and drop every entry, returning how many were purged
+    pub fn purge(&mut self) -> usize {
+        let count = self.items.len();
+        for (_, item) in self.items.iter_mut() {
+            item.ciphertext.zeroize();
+            item.nonce.zeroize();
+        }
+        self.items.clear();
+        count
+    }
+}
+
+impl Drop for ClipboardHistory {
+    fn drop(&mut self) {
+        self.purge();
+    }
+}
+
+/// Named, independently encrypted in-memory slots for staging several secrets
+pub struct ClipboardSlots {
+    key: SubKey,
+    slots: BTreeMap<String, SealedItem>,
+}
+
+impl ClipboardSlots {
+    pub fn new(key: SubKey) -> Self {
+        ClipboardSlots {
+            key,
+            slots: BTreeMap::new(),
+        }
+    }
+
+    /// Encrypt `text` into slot `name`, replacing (and zeroizing) any previous content
+    pub fn store(&mut self, name: &str, text: &str) -> Result<(), String> {
+        let (nonce, ciphertext) = self.key.expose(|key| seal_bytes(key, text.as_bytes()))?;
+        let item = SealedItem {
+            nonce,
+            ciphertext,
+            length: text.chars().count(),
+            copied_at: Instant::now(),
+        };
+        if let Some(mut previous) = self.slots.insert(name.to_string(), item) {
+            previous.ciphertext.zeroize();
+        }
+        Ok(())
+    }
+
+    /// Decrypt the content of slot `name`
+    pub fn get(&self, name: &str) -> Result<SecretString, String> {
+        let item = self
+            .slots
+            .get(name)
+            .ok_or_else(|| format!("No clipboard slot named '{}'.", name))?;
+
+        self.key
+            .expose(|key| open_bytes(key, &item.nonce, &item.ciphertext))?
+            .into_text()
+    }
+
+    /// Describe the stored slots without decrypting them
+    pub fn list(&self) -> Vec<String> {
+        self.slots
+            .iter()
+            .map(|(name, item)| {
+                format!(
+                    "  {}: {} chars, staged {}s ago",
+                    name,
+                    item.length,
+                    item.copied_at.elapsed().as_secs()
+                )
+            })
+            .collect()
+    }
+
+    /// Where the encrypted slots sit in memory, for ::meminfo
+    pub fn regions(&self) -> Vec<Range<usize>> {
+        self.slots
+            .values()
+            .map(|item| region(&item.ciphertext))
+            .collect()
+    }
+
+    /// Zeroize and drop every slot
+    pub fn purge(&mut self) -> usize {
+        let count = self.slots.len();
+        for item in self.slots.values_mut() {
+            item.ciphertext.zeroize();
+            item.nonce.zeroize();
+        }
+        self.slots.clear();
+        count
+    }
+}
+
+impl Drop for ClipboardSlots {
+    fn drop(&mut self) {
+        self.purge();
+    }
+}
@@ -0,0 +1,658 @@
+/// Registry of the `::` commands, for ::help, Tab completion and typo suggestions
+/// Every ghost command handled in `SecureBuffer::process_command` has one entry here:
+/// its group, a one-line summary and its usage lines. Plugins are not listed; they are
+/// discovered at run time (::plugins).
+pub const COMMANDS: &[CommandSpec] = &[
+    // --- SESSION ---
+    spec(
+        "help",
+        Group::Session,
+        "List commands, or show one command's syntax",
+        &["::help", "::help <command>"],
+    ),
+    spec(
+        "status",
+        Group::Session,
+        "Current security status of the shell",
+        &["::status"],
+    ),
+    spec(
+        "history",
+        Group::Session,
+        "Command history with times and exit status, searchable, archived with age or kept across sessions in an encrypted file",
+        &[
+            "::history",
+            "::history export [--age <age1...[,...]>] <file>",
+            "::history import [-i <identity file>] <file>",
+            "::history persist [on|off]",
+            "::history search [-i] <regex>",
+        ],
+    ),
+    spec(
+        "last",
+        Group::Session,
+        "Show the last command's output again, or grep it",
+        &["::last", "::last grep [-i] <regex>"],
+    ),
+    spec(
+        "calc",
+        Group::Session,
+        "Arithmetic, bitwise, size, duration and subnet math without spawning a tool",
+        &["::calc <expr> [in <unit>|hex|oct|bin|dec]", "::calc <ipv4>/<prefix>"],
+    ),
+    spec(
+        "purge-history",
+        Group::Session,
+        "Zeroize and clear the command history",
+        &["::purge-history"],
+    ),
+    spec(
+        "clear",
+        Group::Session,
+        "Clear the screen and scrollback",
+        &["::clear"],
+    ),
+    spec(
+        "config",
+        Group::Session,
+        "Settings loaded from the config file",
+        &["::config"],
+    ),
+    spec(
+        "detach",
+        Group::Session,
+        "Keep the session running without the terminal; ghost-shell --attach reattaches",
+        &["::detach", "::detach on-hangup|off|status"],
+    ),
+    spec(
+        "tab",
+        Group::Session,
+        "Independent tabs with their own line, history and directory (Alt+1..9)",
+        &["::tab", "::tab <1-9>|new|close"],
+    ),
+    spec("exit", Group::Session, "Secure shutdown", &["::exit"]),
+    spec(
+        "panic",
+        Group::Session,
+        "Fake kernel panic and immediate exit",
+        &["::panic"],
+    ),
+    // --- CLIPBOARD ---
+    spec(
+        "cp",
+        Group::Clipboard,
+        "Encrypted copy to the clipboard (auto-clears)",
+        &[
+            "::cp <text>",
+            "::cp -p <text>                      (passphrase instead of a key)",
+            "::cp -t <secs> <text>               (custom auto-clear, 0 = never)",
+            "::cp --once <text>                  (cleared after one paste)",
+            "::cp -s <slot> <text>               (stage in an encrypted slot)",
+            "::cp --age <age1...[,age1...]|-p> <text>",
+            "::cp --to <contact[,contact...]> <text>",
+            "::cp --gpg <recipient> <text>",
+            "::cp --qr <text>",
+            "::cp timers | cancel | extend <secs> | --now-clear",
+        ],
+    ),
+    spec(
+        "paste",
+        Group::Clipboard,
+        "Decrypt the clipboard, or push a staged slot",
+        &[
+            "::paste [key]",
+            "::paste -i [key]                    (into the input line)",
+            "::paste [key] -- <command>          (to a command's stdin)",
+            "::paste -s [slot]",
+        ],
+    ),
+    spec(
+        "cpf",
+        Group::Clipboard,
+        "Encrypt a file's contents onto the clipboard",
+        &["::cpf <file>", "::cpf --bin <file>"],
+    ),
+    spec(
+        "cp-image",
+        Group::Clipboard,
+        "Replace the clipboard image with its encrypted container",
+        &["::cp-image"],
+    ),
+    spec(
+        "cp-last",
+        Group::Clipboard,
+        "Copy the last command's output to the encrypted clipboard",
+        &["::cp-last"],
+    ),
+    spec(
+        "cp-history",
+        Group::Clipboard,
+        "Encrypted ring of the last copied items",
+        &["::cp-history list|restore <n>|purge"],
+    ),
+    spec(
+        "cp-timeout",
+        Group::Clipboard,
+        "Show or set the default auto-clear timeout",
+        &["::cp-timeout [secs]                 (0 = never clear)"],
+    ),
+    spec(
+        "cp-guard",
+        Group::Clipboard,
+        "Alert when another app replaces copied content",
+        &["::cp-guard off|alert|purge"],
+    ),
+    spec(
+        "decrypt",
+        Group::Clipboard,
+        "Decrypt clipboard content with a key or passphrase",
+        &[
+            "::decrypt <key>",
+            "::decrypt -p",
+            "::decrypt <key> -o <file>",
+        ],
+    ),
+    spec(
+        "type",
+        Group::Clipboard,
+        "Type a secret into the focused window",
+        &["::type <slot|text>"],
+    ),
+    spec(
+        "qr",
+        Group::Clipboard,
+        "Render text as a terminal QR code",
+        &["::qr <text>"],
+    ),
+    // --- SECRETS ---
+    spec(
+        "vault",
+        Group::Secrets,
+        "Named secrets encrypted in RAM",
+        &["::vault add <name> [secret] | get <name> | list | rm <name>"],
+    ),
+    spec(
+        "genpass",
+        Group::Secrets,
+        "Generate a password onto the encrypted clipboard",
+        &["::genpass [-l <len>] [-c aA0!] [-p | -d [-w <wordlist>]] [--show]"],
+    ),
+    spec(
+        "genphrase",
+        Group::Secrets,
+        "Generate a diceware passphrase",
+        &["::genphrase [words] [-w <wordlist>] [--show]"],
+    ),
+    spec(
+        "totp",
+        Group::Secrets,
+        "Store TOTP secrets and copy current codes",
+        &["::totp add <name>", "::totp <name>"],
+    ),
+    spec(
+        "note",
+        Group::Secrets,
+        "Encrypted in-RAM scratchpad",
+        &["::note | ::note show | ::note burn"],
+    ),
+    spec(
+        "ssh-add",
+        Group::Secrets,
+        "Built-in SSH agent holding keys in locked RAM",
+        &["::ssh-add <key file|vault signing key> | ::ssh-add -l | ::ssh-add -D"],
+    ),
+    spec(
+        "split",
+        Group::Secrets,
+        "Shamir-split a secret into k-of-n shares",
+        &["::split <k>/<n> [--qr] [-v <vault-key> | <secret>]"],
+    ),
+    spec(
+        "combine",
+        Group::Secrets,
+        "Recover a secret from Shamir shares",
+        &["::combine [-v <vault-key>] [share...]"],
+    ),
+    spec(
+        "contacts",
+        Group::Secrets,
+        "Public keys for ::cp --to",
+        &["::contacts [list] | ::contacts add <name> <age1...|base64 key> | ::contacts rm <name>"],
+    ),
+    spec(
+        "age-keygen",
+        Group::Secrets,
+        "Create an age identity in the vault",
+        &["::age-keygen [-o <file>]"],
+    ),
+    // --- FILES ---
+    spec(
+        "encrypt-file",
+        Group::Files,
+        "Encrypt a file (.ghost, age or OpenPGP)",
+        &["::encrypt-file [--shred] [--age <age1...[,age1...]|-p> | --gpg <recipient>] <path>"],
+    ),
+    spec(
+        "decrypt-file",
+        Group::Files,
+        "Decrypt a .ghost, age or OpenPGP file",
+        &["::decrypt-file [-i <identity file>] <path>"],
+    ),
+    spec(
+        "out",
+        Group::Files,
+        "Run a command, keeping only an encrypted copy of its output",
+        &[
+            "::out [--age <age1...[,age1...]|-p>] <file> -- <command>",
+            "<command> ::> <file>",
+        ],
+    ),
+    spec(
+        "scrub",
+        Group::Files,
+        "Strip metadata from images, PDFs and Office files",
+        &["::scrub <file> [file...]"],
+    ),
+    spec(
+        "timestomp",
+        Group::Files,
+        "Set a file's timestamps",
+        &["::timestomp <target> <YYYY-MM-DD[ HH:MM[:SS]] | @unix seconds> (UTC)"],
+    ),
+    spec(
+        "touch-match",
+        Group::Files,
+        "Copy timestamps from another file",
+        &["::touch-match <reference> <target>"],
+    ),
+    spec(
+        "wipe-free",
+        Group::Files,
+        "Overwrite a filesystem's free space",
+        &["::wipe-free [--rate <MiB/s>] <mountpoint>"],
+    ),
+    spec(
+        "workspace",
+        Group::Files,
+        "Scratch directory on tmpfs, shredded on exit",
+        &["::workspace create [dir] | ::workspace | ::workspace wipe"],
+    ),
+    spec(
+        "ephemeral",
+        Group::Files,
+        "Child commands get a tmpfs HOME and XDG dirs, shredded on exit",
+        &["::ephemeral on|off"],
+    ),
+    // --- CRYPTO ---
+    spec(
+        "hash",
+        Group::Crypto,
+        "SHA-256/SHA-512/BLAKE3 of a file or text",
+        &[
+            "::hash sha256|sha512|blake3 <file|text>",
+            "::hash verify <algo> <expected> <file|text>",
+        ],
+    ),
+    spec(
+        "hmac",
+        Group::Crypto,
+        "HMAC-SHA256 keyed by a vault secret",
+        &[
+            "::hmac <vault-key> <file|text>",
+            "::hmac verify <vault-key> <expected> <file|text>",
+        ],
+    ),
+    spec(
+        "sign",
+        Group::Crypto,
+        "Ed25519 signing keys and detached signatures",
+        &["::sign keygen <name> | ::sign pubkey <name> | ::sign <name> <file|text>"],
+    ),
+    spec(
+        "verify",
+        Group::Crypto,
+        "Check an Ed25519 signature",
+        &["::verify <pubkey|vault-key> <signature> <file|text>"],
+    ),
+    // --- NETWORK ---
+    spec(
+        "share",
+        Group::Network,
+        "Send the clipboard to another gsh, end-to-end encrypted",
+        &[
+            "::share send <host[:port]|socket> [slot]",
+            "::share recv [addr:port|:port|socket]",
+        ],
+    ),
+    spec(
+        "send",
+        Group::Network,
+        "Offer a file under a one-time code",
+        &["::send <file>"],
+    ),
+    spec(
+        "recv",
+        Group::Network,
+        "Receive a file sent with ::send",
+        &["::recv <code> [host[:port]]"],
+    ),
+    spec(
+        "resolve",
+        Group::Network,
+        "DNS lookup over DoH/DoT, bypassing the system resolver",
+        &["::resolve <name|ip> [--doh https://host/dns-query|tls://host[:853]]"],
+    ),
+    spec(
+        "proxy",
+        Group::Network,
+        "Route child commands through SOCKS5/HTTP or Tor",
+        &["::proxy set <url> | ::proxy tor [host:port] | ::proxy check | ::proxy off"],
+    ),
+    spec(
+        "torcheck",
+        Group::Network,
+        "Check the Tor SOCKS port and exit IP",
+        &["::torcheck [host:port]"],
+    ),
+    spec(
+        "net-audit",
+        Group::Network,
+        "Sockets held by the shell process itself",
+        &["::net-audit"],
+    ),
+    // --- SECURITY ---
+    spec(
+        "security-status",
+        Group::Security,
+        "Detailed threat analysis",
+        &["::security-status [--json]"],
+    ),
+    spec(
+        "meminfo",
+        Group::Security,
+        "Sensitive bytes held in memory, mlock state and expiry",
+        &["::meminfo"],
+    ),
+    spec(
+        "watch-security",
+        Group::Security,
+        "Live full-screen security dashboard",
+        &["::watch-security"],
+    ),
+    spec(
+        "anti-debug",
+        Group::Security,
+        "Check for an attached debugger",
+        &["::anti-debug"],
+    ),
+    spec(
+        "paranoid",
+        Group::Security,
+        "Auto-panic on threats, frequent checks",
+        &["::paranoid on|off"],
+    ),
+    spec(
+        "decoy",
+        Group::Security,
+        "Routine-looking background activity to blend the session in",
+        &["::decoy start|stop"],
+    ),
+    spec(
+        "honeypot",
+        Group::Security,
+        "Fake shell for launches with a wrong master passphrase",
+        &["::honeypot", "::honeypot arm [attempts]", "::honeypot disarm", "::honeypot log", "::honeypot clear"],
+    ),
+    spec(
+        "readonly",
+        Group::Security,
+        "Refuse commands that write, delete, install or push",
+        &["::readonly on|off"],
+    ),
+    spec(
+        "direct",
+        Group::Security,
+        "Exec commands directly instead of through $SHELL -c (no rc files or shell hooks)",
+        &["::direct on|off"],
+    ),
+    spec(
+        "audit",
+        Group::Security,
+        "Append-only, hash-chained encrypted log of every command run",
+        &[
+            "::audit [on|off]",
+            "::audit verify",
+            "::audit export [--age <age1...[,...]>] <file>",
+        ],
+    ),
+    spec(
+        "profile",
+        Group::Security,
+        "Apply a bundle of security settings",
+        &["::profile standard|paranoid|stealth"],
+    ),
+    spec(
+        "sandbox",
+        Group::Security,
+        "Wrap child commands in bubblewrap/firejail",
+        &["::sandbox profile off|standard|strict | ::sandbox run <command>"],
+    ),
+    spec(
+        "isolate",
+        Group::Security,
+        "Run a command in fresh namespaces without network",
+        &["::isolate <command>"],
+    ),
+    spec(
+        "trace",
+        Group::Security,
+        "Run a command and list the files it changed",
+        &["::trace <command>"],
+    ),
+    // --- CUSTOMIZATION ---
+    spec(
+        "theme",
+        Group::Customization,
+        "Switch the color theme",
+        &["::theme [default|matrix|mono|high-contrast]"],
+    ),
+    spec(
+        "run",
+        Group::Customization,
+        "Run a Rhai script against the shell",
+        &["::run <script.rhai>"],
+    ),
+    spec(
+        "plugins",
+        Group::Customization,
+        "List installed gsh-plugin-* executables",
+        &["::plugins"],
+    ),
+];
+
+/// Section a command is listed under in ::help
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Group {
+    Session,
+    Clipboard,
+    Secrets,
+    Files,
+    Crypto,
+    Network,
+    Security,
+    Customization,
+}
+
+impl Group {
+    pub const ALL: [Group; 8] = [
+        Group::Session,
+        Group::Clipboard,
+        Group::Secrets,
+        Group::Files,
+        Group::Crypto,
+        Group::Network,
+        Group::Security,
+        Group::Customization,
+    ];
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            Group::Session => "SESSION",
+            Group::Clipboard => "CLIPBOARD",
+            Group::Secrets => "SECRETS",
+            Group::Files => "FILES",
+            Group::Crypto => "CRYPTO",
+            Group::Network => "NETWORK",
+            Group::Security => "SECURITY",
+            Group::Customization => "CUSTOMIZATION",
+        }
+    }
+}
+
+/// One ghost command
+#[derive(Debug, Clone, Copy)]
+pub struct CommandSpec {
+    pub name: &'static str,
+    pub group: Group,
+    pub summary: &'static str,
+    pub usage: &'static [&'static str],
+}
+
+const fn spec(
+    name: &'static str,
+    group: Group,
+    summary: &'static str,
+    usage: &'static [&'static str],
+) -> CommandSpec {
+    CommandSpec {
+        name,
+        group,
+        summary,
+        usage,
+    }
+}
+
+/// Registry entry for `name` (with or without the `::` prefix)
+pub fn find(name: &str) -> Option<&'static CommandSpec> {
+    let name = name.trim().trim_start_matches("::");
+    COMMANDS.iter().find(|command| command.name == name)
+}
+
+/// Command names starting with `prefix`
+pub fn completions(prefix: &str) -> Vec<&'static str> {
+    COMMANDS
+        .iter()
+        .map(|command| command.name)
+        .filter(|name| name.starts_with(prefix))
+        .collect()
+}
+
+/// Most suggestions offered for one typo
+const MAX_SUGGESTIONS: usize = 3;
+
+/// Names closest to a mistyped command, best first. `extra` adds names known only at
+/// run time (plugins). A typo may be a couple of edits away, or the start of a name.
+pub fn suggest(typed: &str, extra: &[String]) -> Vec<String> {
+    let typed = typed.trim_start_matches("::").to_ascii_lowercase();
+    if typed.is_empty() {
+        return Vec::new();
+    }
+    let mut candidates: Vec<(usize, String)> = COMMANDS
+        .iter()
+        .map(|command| command.name.to_string())
+        .chain(extra.iter().cloned())
+        .filter_map(|name| {
+            let distance = if typed.len() >= 3 && name.starts_with(&typed) {
+                1
+            } else {
+                edit_distance(&typed, &name)
+            };
+            // Short names tolerate one edit, longer ones two
+            let limit = if name.len() <= 4 { 1 } else { 2 };
+            (distance <= limit).then_some((distance, name))
+        })
+        .collect();
+    candidates.sort();
+    candidates.dedup_by(|a, b| a.1 == b.1);
+    let best = candidates.first().map(|(distance, _)| *distance);
+    candidates
+        .into_iter()
+        .filter(|(distance, _)| Some(*distance) == best)
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, name)| name)
+        .collect()
+}
+
+/// "Did you mean ::a?" / "Did you mean ::a or ::b?", empty without suggestions
+pub fn did_you_mean(suggestions: &[String]) -> String {
+    let names: Vec<String> = suggestions
+        .iter()
+        .map(|name| format!("::{}", name))
+        .collect();
+    match names.as_slice() {
+        [] => String::new(),
+        [only] => format!("Did you mean {}?", only),
+        [rest @ .., last] => format!("Did you mean {} or {}?", rest.join(", "), last),
+    }
+}
+
+/// ::help: every command by group
+pub fn overview() -> String {
+    let width = COMMANDS
+        .iter()
+        .map(|command| command.name.len())
+        .max()
+        .unwrap_or(0)
+        + 2;
+    let mut lines = vec!["GHOST COMMANDS (::help <command> for syntax)".to_string()];
+    for group in Group::ALL {
+        lines.push(String::new());
+        lines.push(group.title().to_string());
+        for command in COMMANDS.iter().filter(|command| command.group == group) {
+            lines.push(format!(
+                "  ::{:<width$} {}",
+                command.name,
+                command.summary,
+                width = width
+            ));
+        }
+    }
+    lines.push(String::new());
+    lines.push("Anything else runs as a normal shell command. Plugins: ::plugins".to_string());
+    lines.join("\r\n")
+}
+
+/// ::help <command>
+pub fn usage(command: &CommandSpec) -> String {
+    let mut lines = vec![
+        format!("::{} - {}", command.name, command.summary),
+        "Usage:".to_string(),
+    ];
+    lines.extend(command.usage.iter().map(|line| format!("  {}", line)));
+    lines.join("\r\n")
+}
+
+/// Optimal string alignment distance: Levenshtein plus adjacent transpositions, so
+/// `hlep` is one edit from `help`
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut rows = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    rows[0] = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = (rows[i - 1][j] + 1)
+                .min(rows[i][j - 1] + 1)
+                .min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = distance;
+        }
+    }
+    rows[a.len()][b.len()]
+}
@@ -0,0 +1,424 @@
+/// Encrypted configuration file (`~/.config/gsh/config.age`)
+/// An age file protected by a passphrase (create it with `age -p` or
+/// `::encrypt-file --age -p`), decrypted at startup with the session passphrase and
+/// never written back. The plaintext is one `key = value` per line; `#` starts a comment:
+///
+///   clipboard_timeout = 45
+///   paranoid = true
+///   audit = true
+///   prompt = ghost {dir}$
+///   title = htop
+///   theme = matrix
+///   bind.ctrl-k = ::vault list
+///   env_scrub = AWS_SECRET_ACCESS_KEY, GITHUB_TOKEN, *_TOKEN
+///   history.max = 500
+///   history.dedupe = true
+///   history.ignore = ::vault *
+///   hook.pre = if command.contains("rm -rf") { throw "rm -rf is denied" }
+///   hook.post = output.replace(vault_get("api-token"), "********")
+///   policy.deny = *rm -rf /
+///   policy.confirm = *curl *|*sh
+///   panic.style = silent
+///   panic.exit_code = 0
+///   panic.shred = ~/engagement/notes.txt
+///   ssh.refuse_plaintext = true
+///   exec.direct = true
+///   env.git = GIT_TERMINAL_PROMPT=0, GIT_ASKPASS=/bin/false, -SSH_ASKPASS
+///   trace.allow = ~/.cache/*
+///
+/// Hooks are Rhai snippets (see script.rs) run around every command typed at the prompt:
+/// `hook.pre` may rewrite `command` or refuse it by throwing, `hook.post` may rewrite
+/// `output`. A key may repeat; the snippets run in order.
+///
+/// `history.ignore` patterns (`*` matches anything, repeat the key for more) keep
+/// matching lines out of the history; so does a leading space unless
+/// `history.ignore_space = false`. `history.dedupe` drops earlier copies of a command
+/// rather than only a repeat of the last one.
+///
+/// `policy.deny` and `policy.confirm` rules (see policy.rs) refuse matching command
+/// lines or ask before running them.
+///
+/// `panic.*` keys override the active profile's ::panic routine: `style` (kernel or
+/// silent), `exit_code`, `clear_clipboard`, `purge_vault`, `kill_children`, and
+/// `shred` (repeat it) for files destroyed on the way out.
+///
+/// `env.<command>` entries change the environment of command lines that run that program
+/// (by name, also behind sudo or env and anywhere in a pipeline): `NAME=value` sets a
+/// variable, `-NAME` removes it (`*` matches any run of characters). Repeat the key to
+/// add more; they apply in order.
+///
+/// `trace.allow` patterns (`*` matches anything, repeat the key for more) name paths
+/// ::trace does not report when a command changes them.
+///
+/// `ssh.refuse_plaintext` makes `::decrypt` refuse to print plaintext when the shell
+/// runs over SSH (see remote.rs), where the display is on the other end of the network.
+use std::env;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use zeroize::Zeroize;
+
+use crate::age::Unlock;
+use crate::filecrypt::read_age_file;
+use crate::policy::{PolicyAction, PolicyRule};
+use crate::profile::PanicStyle;
+use crate::theme::Theme;
+
+/// Placeholder in `prompt` replaced by the current directory name
+pub const PROMPT_DIR: &str = "{dir}";
+
+/// Ctrl+<key> combinations the shell keeps for itself
+const RESERVED_BINDINGS: &[char] = &['c', 'l', 'o', 't'];
+
+/// Settings read from the config file; anything absent keeps its built-in default
+#[derive(Default)]
+pub struct Config {
+    pub clipboard_timeout: Option<u64>,
+    pub paranoid: Option<bool>,
+    pub audit: Option<bool>,
+    pub prompt: Option<String>,
+    pub title: Option<String>,
+    pub theme: Option<Theme>,
+    pub bindings: Vec<(char, String)>,
+    pub env_scrub: Vec<String>,
+    pub history_max: Option<usize>,
+    pub history_dedupe: Option<bool>,
+    pub history_ignore_space: Option<bool>,
+    pub history_ignore: Vec<String>,
+    pub pre_hooks: Vec<String>,
+    pub post_hooks: Vec<String>,
+    pub policy: Vec<PolicyRule>,
+    pub panic_style: Option<PanicStyle>,
+    pub panic_exit_code: Option<i32>,
+    pub panic_clear_clipboard: Option<bool>,
+    pub panic_purge_vault: Option<bool>,
+    pub panic_kill_children: Option<bool>,
+    pub ssh_refuse_plaintext: Option<bool>,
+    pub exec_direct: Option<bool>,
+    pub env_overrides: Vec<(String, EnvOverride)>,
+    pub trace_allow: Vec<String>,
+    pub panic_shred: Vec<PathBuf>,
+}
+
+/// `$XDG_CONFIG_HOME/gsh/config.age`, falling back to `~/.config/gsh/config.age`
+pub fn config_path() -> Option<PathBuf> {
+    let base = env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(base.join("gsh").join("config.age"))
+}
+
+/// `$XDG_DATA_HOME/gsh/<name>`, falling back to `~/.local/share/gsh/<name>`: where the
+/// history, audit log and honeypot files live
+pub fn data_file(name: &str) -> Option<PathBuf> {
+    let base = env::var_os("XDG_DATA_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| {
+            env::var_os("HOME").map(|home| PathBuf::from(home).join(".local").join("share"))
+        })?;
+    Some(base.join("gsh").join(name))
+}
+
+/// Decrypt and parse the config file at `path`
+pub fn load_config(path: &Path, passphrase: &str) -> Result<Config, String> {
+    let mut plaintext = read_age_file(path, &Unlock::Passphrase(passphrase))?;
+    let parsed = std::str::from_utf8(&plaintext)
+        .map_err(|_| format!("{}: not UTF-8 text.", path.display()))
+        .and_then(|text| parse_config(text).map_err(|e| format!("{}: {}", path.display(), e)));
+    plaintext.zeroize();
+    parsed
+}
+
+fn parse_config(text: &str) -> Result<Config, String> {
+    let mut config = Config::default();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let invalid = |what: &str| format!("line {}: {}", number + 1, what);
+        let (key, value) = line
+            .split_once('=')
+            .map(|(key, value)| (key.trim(), value.trim()))
+            .ok_or_else(|| invalid("expected key = value"))?;
+
+        match key {
+            "clipboard_timeout" => {
+                config.clipboard_timeout = Some(
+                    value
+                        .parse()
+                        .map_err(|_| invalid("clipboard_timeout must be seconds"))?,
+                )
+            }
+            "paranoid" => {
+                config.paranoid = Some(
+                    parse_bool(value).ok_or_else(|| invalid("paranoid must be true or false"))?,
+                )
+            }
+            "audit" => {
+                config.audit =
+                    Some(parse_bool(value).ok_or_else(|| invalid("audit must be true or false"))?)
+            }
+            "prompt" => config.prompt = Some(unquote(value).to_string()),
+            "title" => match unquote(value) {
+                "" => return Err(invalid("title must not be empty")),
+                title => config.title = Some(title.to_string()),
+            },
+            "theme" => {
+                config.theme = Some(Theme::parse(value).ok_or_else(|| {
+                    invalid("theme must be default, matrix, mono or high-contrast")
+                })?)
+            }
+            "env_scrub" => config.env_scrub.extend(
+                value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|name| !name.is_empty())
+                    .map(str::to_string),
+            ),
+            "history.max" => {
+                config.history_max = Some(
+                    value
+                        .parse()
+                        .map_err(|_| invalid("history.max must be a number of entries"))?,
+                )
+            }
+            "history.dedupe" => {
+                config.history_dedupe = Some(
+                    parse_bool(value)
+                        .ok_or_else(|| invalid("history.dedupe must be true or false"))?,
+                )
+            }
+            "history.ignore_space" => {
+                config.history_ignore_space = Some(
+                    parse_bool(value)
+                        .ok_or_else(|| invalid("history.ignore_space must be true or false"))?,
+                )
+            }
+            "history.ignore" => config.history_ignore.push(unquote(value).to_string()),
+            "hook.pre" => config.pre_hooks.push(value.to_string()),
+            "hook.post" => config.post_hooks.push(value.to_string()),
+            "panic.style" => {
+                config.panic_style = Some(
+                    PanicStyle::parse(value)
+                        .ok_or_else(|| invalid("panic.style must be kernel or silent"))?,
+                )
+            }
+            "panic.exit_code" => {
+                config.panic_exit_code = Some(
+                    value
+                        .parse()
+                        .ok()
+                        .filter(|code| (0..=255).contains(code))
+                        .ok_or_else(|| invalid("panic.exit_code must be 0 to 255"))?,
+                )
+            }
+            "panic.clear_clipboard" => {
+                config.panic_clear_clipboard = Some(
+                    parse_bool(value)
+                        .ok_or_else(|| invalid("panic.clear_clipboard must be true or false"))?,
+                )
+            }
+            "panic.purge_vault" => {
+                config.panic_purge_vault = Some(
+                    parse_bool(value)
+                        .ok_or_else(|| invalid("panic.purge_vault must be true or false"))?,
+                )
+            }
+            "panic.kill_children" => {
+                config.panic_kill_children = Some(
+                    parse_bool(value)
+                        .ok_or_else(|| invalid("panic.kill_children must be true or false"))?,
+                )
+            }
+            "panic.shred" => config.panic_shred.push(expand_home(unquote(value))),
+            "ssh.refuse_plaintext" => {
+                config.ssh_refuse_plaintext = Some(
+                    parse_bool(value)
+                        .ok_or_else(|| invalid("ssh.refuse_plaintext must be true or false"))?,
+                )
+            }
+            "exec.direct" => {
+                config.exec_direct = Some(
+                    parse_bool(value)
+                        .ok_or_else(|| invalid("exec.direct must be true or false"))?,
+                )
+            }
+            key if key.starts_with("env.") => {
+                let program = &key["env.".len()..];
+                if program.is_empty() || program.contains('/') {
+                    return Err(invalid("env.<command> needs a command name"));
+                }
+                for entry in value.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+                    let entry = EnvOverride::parse(entry).ok_or_else(|| {
+                        invalid(&format!("{}: expected NAME=value or -NAME", key))
+                    })?;
+                    config.env_overrides.push((program.to_string(), entry));
+                }
+            }
+            "trace.allow" => config
+                .trace_allow
+                .push(expand_home(unquote(value)).to_string_lossy().into_owned()),
+            "policy.deny" | "policy.confirm" => {
+                if unquote(value).trim().is_empty() {
+                    return Err(invalid("policy rules need a command pattern"));
+                }
+                config.policy.push(PolicyRule {
+                    action: if key == "policy.deny" {
+                        PolicyAction::Deny
+                    } else {
+                        PolicyAction::Confirm
+                    },
+                    pattern: unquote(value).to_string(),
+                })
+            }
+            _ => match key.strip_prefix("bind.ctrl-") {
+                Some(name) => {
+                    let mut chars = name.chars();
+                    let (Some(key), None) = (chars.next(), chars.next()) else {
+                        return Err(invalid("bindings look like bind.ctrl-<letter>"));
+                    };
+                    let key = key.to_ascii_lowercase();
+                    if !key.is_ascii_alphabetic() {
+                        return Err(invalid("bindings look like bind.ctrl-<letter>"));
+                    }
+                    if RESERVED_BINDINGS.contains(&key) {
+                        return Err(invalid(
+                            "Ctrl+C, Ctrl+L, Ctrl+O and Ctrl+T cannot be rebound",
+                        ));
+                    }
+                    config.bindings.retain(|(bound, _)| *bound != key);
+                    config.bindings.push((key, value.to_string()));
+                }
+                None => return Err(invalid(&format!("unknown setting '{}'", key))),
+            },
+        }
+    }
+    Ok(config)
+}
+
+/// One `env.<command>` change to a child's environment
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EnvOverride {
+    Set(String, String),
+    /// Variables matching the pattern are removed
+    Unset(String),
+}
+
+impl EnvOverride {
+    fn parse(entry: &str) -> Option<Self> {
+        let valid_name = |name: &str, wildcard: bool| {
+            !name.is_empty()
+                && name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_' || (wildcard && c == '*'))
+        };
+        match entry.strip_prefix('-') {
+            Some(pattern) if valid_name(pattern, true) => {
+                Some(EnvOverride::Unset(pattern.to_string()))
+            }
+            Some(_) => None,
+            None => {
+                let (name, value) = entry.split_once('=')?;
+                valid_name(name, false)
+                    .then(|| EnvOverride::Set(name.to_string(), unquote(value).to_string()))
+            }
+        }
+    }
+
+    /// `NAME=value` / `-NAME`
+    pub fn describe(&self) -> String {
+        match self {
+            EnvOverride::Set(name, value) => format!("{}={}", name, value),
+            EnvOverride::Unset(pattern) => format!("-{}", pattern),
+        }
+    }
+
+    /// Apply to `command`'s environment, which starts as this process's
+    pub fn apply(&self, command: &mut Command) {
+        match self {
+            EnvOverride::Set(name, value) => {
+                command.env(name, value);
+            }
+            EnvOverride::Unset(pattern) => {
+                // This process's variables, and any an earlier entry set
+                let names: Vec<OsString> = env::vars_os()
+                    .map(|(name, _)| name)
+                    .chain(command.get_envs().map(|(name, _)| name.to_os_string()))
+                    .collect();
+                for name in names {
+                    if name
+                        .to_str()
+                        .is_some_and(|name| matches_pattern(pattern, name))
+                    {
+                        command.env_remove(name);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Remove every variable matching an `env_scrub` pattern (`*` matches any run of
+/// characters) from this process, and so from every child it starts. Returns the
+/// names removed.
+pub fn scrub_environment(patterns: &[impl AsRef<str>]) -> Vec<String> {
+    let mut removed: Vec<String> = env::vars_os()
+        .filter_map(|(name, _)| name.into_string().ok())
+        .filter(|name| {
+            patterns
+                .iter()
+                .any(|pattern| matches_pattern(pattern.as_ref(), name))
+        })
+        .collect();
+    removed.sort();
+    for name in &removed {
+        env::remove_var(name);
+    }
+    removed
+}
+
+/// Glob match supporting only `*`
+pub fn matches_pattern(pattern: &str, name: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = name.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+/// `~/x` → `$HOME/x`
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), env::var_os("HOME")) {
+        (Some(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match value {
+        "true" | "on" | "yes" => Some(true),
+        "false" | "off" | "no" => Some(false),
+        _ => None,
+    }
+}
+
+fn unquote(value: &str) -> &str {
+    value
+        .strip_prefix('"')
+        .and_then(|inner| inner.strip_suffix('"'))
+        .unwrap_or(value)
+}
@@ -0,0 +1,74 @@
+/// Address book for ::contacts and ::cp --to: names mapped to X25519 public keys
+/// Only public keys are stored; payloads sealed to a contact are age-encrypted so
+/// nothing symmetric has to be passed along out of band.
+use base64::{engine::general_purpose, Engine as _};
+use std::collections::BTreeMap;
+use x25519_dalek::PublicKey;
+
+use crate::age::{encode_recipient, parse_recipient, Recipients};
+
+pub struct Contacts {
+    keys: BTreeMap<String, PublicKey>,
+}
+
+impl Default for Contacts {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Contacts {
+    pub fn new() -> Self {
+        Contacts {
+            keys: BTreeMap::new(),
+        }
+    }
+
+    /// Add or replace `name`. Returns true if an existing contact was replaced.
+    pub fn add(&mut self, name: &str, public_key: &str) -> Result<bool, String> {
+        let key = parse_public_key(public_key)?;
+        Ok(self.keys.insert(name.to_string(), key).is_some())
+    }
+
+    pub fn remove(&mut self, name: &str) -> Result<(), String> {
+        self.keys
+            .remove(name)
+            .map(|_| ())
+            .ok_or_else(|| format!("No contact named '{}'.", name))
+    }
+
+    /// `name: age1...` for each contact
+    pub fn list(&self) -> Vec<String> {
+        self.keys
+            .iter()
+            .map(|(name, key)| format!("  {}: {}", name, encode_recipient(key)))
+            .collect()
+    }
+
+    /// Recipients for a comma-separated list of contact names
+    pub fn recipients(&self, names: &str) -> Result<Recipients<'static>, String> {
+        names
+            .split(',')
+            .map(|name| {
+                self.keys
+                    .get(name)
+                    .copied()
+                    .ok_or_else(|| format!("No contact named '{}'.", name))
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map(Recipients::Keys)
+    }
+}
+
+/// An `age1...` recipient or a base64 raw X25519 public key
+fn parse_public_key(encoded: &str) -> Result<PublicKey, String> {
+    if encoded.starts_with("age1") {
+        return parse_recipient(encoded);
+    }
+    general_purpose::STANDARD
+        .decode(encoded)
+        .ok()
+        .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+        .map(PublicKey::from)
+        .ok_or_else(|| format!("Invalid X25519 public key '{}'.", encoded))
+}
@@ -0,0 +1,151 @@
+/// Full-screen security dashboard for `::watch-security`
+/// The sections are sampled again every refresh interval and drawn on the alternate
+/// screen, so nothing stays in the scrollback. Lines starting with a status mark are
+/// coloured by it. q, Esc or Ctrl+C leave; a resize redraws straight away.
+use crossterm::{
+    cursor::{Hide, MoveTo, Show},
+    event::{Event, KeyCode, KeyEvent, KeyModifiers},
+    execute, queue,
+    style::Print,
+    terminal::{Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use std::io::{self, Write};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::shell::RESET_SCROLL_REGION;
+use crate::term::{self, Output};
+use crate::theme::{Role, Theme, ALERT_PREFIX};
+
+/// A titled block of status lines
+pub struct Section {
+    pub title: &'static str,
+    pub lines: Vec<String>,
+}
+
+/// Wall-clock time of day as HH:MM:SS UTC
+fn clock() -> String {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs() % 86_400);
+    format!(
+        "{:02}:{:02}:{:02} UTC",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+/// `line` cut to `width` columns and coloured by its leading mark
+fn render_line(theme: Theme, line: &str, width: usize) -> String {
+    let line: String = line
+        .chars()
+        .map(|c| if c.is_control() { '?' } else { c })
+        .take(width)
+        .collect();
+    let trimmed = line.trim_start();
+    let role = if trimmed.starts_with('✓') {
+        Role::Success
+    } else if trimmed.starts_with('✗') {
+        Role::Error
+    } else if trimmed.starts_with('⚠') {
+        Role::Warning
+    } else if trimmed.starts_with(ALERT_PREFIX.trim_end()) {
+        Role::Alert
+    } else {
+        return line;
+    };
+    theme.paint(role, &line).to_string()
+}
+
+/// Draw the header and as many section lines as fit
+fn draw(
+    stdout: &mut Output,
+    theme: Theme,
+    title: &str,
+    sections: &[Section],
+    refreshed: &str,
+    interval: Duration,
+) -> io::Result<()> {
+    let (width, rows) = term::size().unwrap_or((80, 24));
+    let width = width as usize;
+    let mut lines = Vec::new();
+    for section in sections {
+        lines.push(String::new());
+        lines.push(theme.paint(Role::Prompt, section.title).to_string());
+        lines.extend(
+            section
+                .lines
+                .iter()
+                .map(|line| render_line(theme, line, width)),
+        );
+    }
+    let title: String = title.chars().take(width).collect();
+    let status: String = format!(
+        "  refreshed {} every {}s   q to quit",
+        refreshed,
+        interval.as_secs()
+    )
+    .chars()
+    .take(width.saturating_sub(title.chars().count()))
+    .collect();
+    queue!(
+        stdout,
+        Clear(ClearType::All),
+        MoveTo(0, 0),
+        Print(theme.paint(Role::Prompt, &title)),
+        Print(theme.paint(Role::Countdown, &status))
+    )?;
+    for (row, line) in lines
+        .iter()
+        .take(rows.saturating_sub(1) as usize)
+        .enumerate()
+    {
+        queue!(stdout, MoveTo(0, row as u16 + 1), Print(line))?;
+    }
+    stdout.flush()
+}
+
+/// Show the sections `sample` returns under `title`, sampling again every
+/// `interval`, until the user quits
+pub fn watch(
+    theme: Theme,
+    title: &str,
+    interval: Duration,
+    mut sample: impl FnMut() -> Vec<Section>,
+) -> io::Result<()> {
+    let mut stdout = term::stdout();
+    execute!(
+        stdout,
+        EnterAlternateScreen,
+        Print(RESET_SCROLL_REGION),
+        Hide
+    )?;
+
+    let result = (|| loop {
+        let sections = sample();
+        let refreshed = clock();
+        let next = Instant::now() + interval;
+        loop {
+            draw(&mut stdout, theme, title, &sections, &refreshed, interval)?;
+            let remaining = next.saturating_duration_since(Instant::now());
+            if remaining.is_zero() || !term::poll_event(remaining)? {
+                break;
+            }
+            // Anything else, a resize included, just redraws
+            if let Event::Key(KeyEvent {
+                code, modifiers, ..
+            }) = term::read_event()?
+            {
+                let ctrl = modifiers.contains(KeyModifiers::CONTROL);
+                match code {
+                    KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                    KeyCode::Char('c') if ctrl => return Ok(()),
+                    _ => {}
+                }
+            }
+        }
+    })();
+
+    execute!(stdout, Show, LeaveAlternateScreen)?;
+    result
+}
@@ -0,0 +1,164 @@
+/// Decoy background activity for `::decoy start`
+/// While it runs, the shell keeps a log-tailing child alive and starts one routine
+/// inspection command (uptime, df, ps, ...) at random intervals with its output
+/// discarded, so process listings and the rhythm of the session look like everyday
+/// sysadmin work. Decoy commands run directly, never through the sandbox, the proxy or
+/// the audit log, and only read.
+use rand::Rng;
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// Logs worth tailing, first readable one wins
+const LOG_FILES: &[&str] = &[
+    "/var/log/syslog",
+    "/var/log/messages",
+    "/var/log/auth.log",
+    "/var/log/kern.log",
+    "/var/log/dpkg.log",
+];
+
+/// What a sysadmin runs without thinking; missing tools are skipped
+const ROUTINE: &[&[&str]] = &[
+    &["uptime"],
+    &["df", "-h"],
+    &["free", "-m"],
+    &["ps", "aux"],
+    &["ls", "-la", "/var/log"],
+    &["w"],
+    &["who"],
+    &["ss", "-tln"],
+    &["ip", "addr"],
+    &["cat", "/proc/loadavg"],
+    &["journalctl", "-n", "20", "--no-pager"],
+    &["systemctl", "list-units", "--failed", "--no-pager"],
+    &["top", "-bn1"],
+];
+
+/// Seconds between routine commands, picked at random in this range
+pub const INTERVAL_SECS: (u64, u64) = (20, 90);
+
+/// How often the decoy thread checks whether it should stop
+const POLL: Duration = Duration::from_millis(200);
+
+pub struct Decoy {
+    running: Option<(Arc<AtomicBool>, JoinHandle<()>)>,
+    tail: Option<(Child, &'static str)>,
+    runs: Arc<AtomicUsize>,
+    started: Option<Instant>,
+}
+
+impl Decoy {
+    pub fn new() -> Self {
+        Decoy {
+            running: None,
+            tail: None,
+            runs: Arc::new(AtomicUsize::new(0)),
+            started: None,
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running.is_some()
+    }
+
+    /// Start tailing a log and running routine commands. Returns the log being tailed.
+    pub fn start(&mut self) -> Option<&'static str> {
+        if self.is_running() {
+            return self.tail.as_ref().map(|(_, log)| *log);
+        }
+        self.tail = LOG_FILES
+            .iter()
+            .find(|log| std::fs::File::open(log).is_ok())
+            .and_then(|log| {
+                Command::new("tail")
+                    .args(["-n", "50", "-f", log])
+                    .stdin(Stdio::null())
+                    .stdout(Stdio::null())
+                    .stderr(Stdio::null())
+                    .spawn()
+                    .ok()
+                    .map(|child| (child, *log))
+            });
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let stop_flag = Arc::clone(&stop);
+        let runs = Arc::clone(&self.runs);
+        let handle = thread::spawn(move || {
+            let mut rng = rand::thread_rng();
+            while !stop_flag.load(Ordering::SeqCst) {
+                let wait = Duration::from_secs(rng.gen_range(INTERVAL_SECS.0..=INTERVAL_SECS.1));
+                let due = Instant::now() + wait;
+                while Instant::now() < due {
+                    if stop_flag.load(Ordering::SeqCst) {
+                        return;
+                    }
+                    thread::sleep(POLL);
+                }
+                let routine = ROUTINE[rng.gen_range(0..ROUTINE.len())];
+                let ran = Command::new(routine[0])
+                    .args(&routine[1..])
+                    .stdin(Stdio::null())
+                    .stdout(Stdio::null())
+                    .stderr(Stdio::null())
+                    .status();
+                if ran.is_ok() {
+                    runs.fetch_add(1, Ordering::SeqCst);
+                }
+            }
+        });
+        self.running = Some((stop, handle));
+        self.started = Some(Instant::now());
+        self.tail.as_ref().map(|(_, log)| *log)
+    }
+
+    /// Stop everything; returns how many routine commands ran
+    pub fn stop(&mut self) -> usize {
+        if let Some((stop, handle)) = self.running.take() {
+            stop.store(true, Ordering::SeqCst);
+            let _ = handle.join();
+        }
+        if let Some((mut child, _)) = self.tail.take() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+        self.started = None;
+        self.runs.swap(0, Ordering::SeqCst)
+    }
+
+    /// `running for 5m, 7 commands, tailing /var/log/syslog`
+    pub fn describe(&self) -> String {
+        match self.started {
+            Some(started) => format!(
+                "running for {}m, {} commands, {}",
+                started.elapsed().as_secs() / 60,
+                self.runs.load(Ordering::SeqCst),
+                match &self.tail {
+                    Some((_, log)) => format!("tailing {}", log),
+                    None => "no readable log to tail".to_string(),
+                }
+            ),
+            None => "off".to_string(),
+        }
+    }
+}
+
+impl Default for Decoy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for Decoy {
+    fn drop(&mut self) {
+        if let Some((stop, _)) = self.running.take() {
+            stop.store(true, Ordering::SeqCst);
+        }
+        if let Some((mut child, _)) = self.tail.take() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
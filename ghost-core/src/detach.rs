@@ -0,0 +1,478 @@
+/// Detached sessions: `::detach` and `ghost-shell --attach`
+/// A detached session stays in its own process, so its keys, vault, history and
+/// background work stay in locked memory. The terminal is let go (stdio on /dev/null,
+/// hangup and keyboard signals ignored, no parent-death signal) and the shell serves a
+/// Unix socket in a private per-user directory instead. `ghost-shell --attach` proves
+/// the passphrase chosen at detach time, then relays keys as headless script lines one
+/// way and terminal output the other. Output produced while nobody is attached lands
+/// on an in-memory screen that is repainted for the next client.
+use chacha20poly1305::aead::OsRng;
+use crossterm::event::{self, DisableBracketedPaste, EnableBracketedPaste, Event};
+use crossterm::execute;
+use crossterm::terminal::{disable_raw_mode, enable_raw_mode};
+use rand::RngCore;
+use std::collections::VecDeque;
+use std::env;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::os::unix::fs::{DirBuilderExt, MetadataExt, PermissionsExt};
+use std::os::unix::io::AsRawFd;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+use subtle::ConstantTimeEq;
+use zeroize::Zeroize;
+
+use crate::clipboard::derive_passphrase_key;
+use crate::secret::SecretKey;
+use crate::shell::read_secret;
+use crate::term::{encode_event, parse_script, Screen, Step, HEADLESS_SIZE};
+
+/// How long a connecting client has to send its passphrase (typed before connecting)
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Longest handshake accepted: `auth <passphrase>` and `resize <cols> <rows>`
+const MAX_HANDSHAKE: usize = 1024;
+
+/// Pause before answering a wrong passphrase
+const DENIED_DELAY: Duration = Duration::from_secs(2);
+
+/// How long a blocked client may hold up output before it is dropped
+const WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Wait between checks of the socket while nothing arrives
+const IDLE: Duration = Duration::from_millis(20);
+
+/// Argon2id check of the reattach passphrase; only the salt and the derived key are
+/// kept
+pub struct Verifier {
+    salt: [u8; 16],
+    key: SecretKey,
+}
+
+impl Verifier {
+    pub fn new(passphrase: &str) -> Result<Self, String> {
+        let mut salt = [0u8; 16];
+        OsRng.fill_bytes(&mut salt);
+        Ok(Verifier {
+            key: derive_passphrase_key(passphrase, &salt)?,
+            salt,
+        })
+    }
+
+    fn accepts(&self, passphrase: &str) -> bool {
+        match derive_passphrase_key(passphrase, &self.salt) {
+            Ok(key) => key.expose().ct_eq(self.key.expose()).into(),
+            Err(_) => false,
+        }
+    }
+}
+
+/// `$XDG_RUNTIME_DIR/gsh-sessions`, or `gsh-sessions-<uid>` in the temp directory.
+/// Created mode 700; an existing one must be ours and private.
+fn socket_dir() -> Result<PathBuf, String> {
+    let uid = unsafe { libc::getuid() };
+    let dir = match env::var_os("XDG_RUNTIME_DIR").map(PathBuf::from) {
+        Some(base) if base.is_dir() => base.join("gsh-sessions"),
+        _ => env::temp_dir().join(format!("gsh-sessions-{}", uid)),
+    };
+    match fs::symlink_metadata(&dir) {
+        Ok(meta) if meta.is_dir() && meta.uid() == uid && meta.mode() & 0o077 == 0 => Ok(dir),
+        Ok(_) => Err(format!(
+            "{}: not a private directory owned by you.",
+            dir.display()
+        )),
+        Err(_) => fs::DirBuilder::new()
+            .mode(0o700)
+            .create(&dir)
+            .map(|_| dir.clone())
+            .map_err(|e| format!("{}: {}", dir.display(), e)),
+    }
+}
+
+fn socket_path(pid: u32) -> Result<PathBuf, String> {
+    Ok(socket_dir()?.join(format!("{}.sock", pid)))
+}
+
+/// Process ids of the detached sessions waiting for a client
+pub fn sessions() -> Vec<u32> {
+    let Ok(entries) = socket_dir().and_then(|dir| fs::read_dir(dir).map_err(|e| e.to_string()))
+    else {
+        return Vec::new();
+    };
+    let mut pids: Vec<u32> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            let pid: u32 = name.strip_suffix(".sock")?.parse().ok()?;
+            // A socket left by a killed session
+            if unsafe { libc::kill(pid as libc::pid_t, 0) } != 0 {
+                let _ = fs::remove_file(entry.path());
+                return None;
+            }
+            Some(pid)
+        })
+        .collect();
+    pids.sort_unstable();
+    pids
+}
+
+/// Let go of the terminal: stdio on /dev/null, and neither hanging up nor keys typed
+/// into the old terminal (Ctrl+C, Ctrl+Z, Ctrl+\) reach the session any more
+pub fn release_terminal() {
+    unsafe {
+        for signal in [
+            libc::SIGHUP,
+            libc::SIGINT,
+            libc::SIGQUIT,
+            libc::SIGTSTP,
+            libc::SIGTTIN,
+            libc::SIGTTOU,
+        ] {
+            libc::signal(signal, libc::SIG_IGN);
+        }
+        let null = libc::open(c"/dev/null".as_ptr(), libc::O_RDWR);
+        if null >= 0 {
+            for fd in 0..=2 {
+                libc::dup2(null, fd);
+            }
+            if null > 2 {
+                libc::close(null);
+            }
+        }
+    }
+}
+
+/// The uid at the other end of a connection (Linux); elsewhere the private socket
+/// directory is the only gate
+fn peer_is_us(stream: &UnixStream) -> bool {
+    #[cfg(target_os = "linux")]
+    {
+        let mut credentials = libc::ucred {
+            pid: 0,
+            uid: u32::MAX,
+            gid: 0,
+        };
+        let mut length = std::mem::size_of::<libc::ucred>() as libc::socklen_t;
+        let found = unsafe {
+            libc::getsockopt(
+                stream.as_raw_fd(),
+                libc::SOL_SOCKET,
+                libc::SO_PEERCRED,
+                &mut credentials as *mut libc::ucred as *mut libc::c_void,
+                &mut length,
+            )
+        } == 0;
+        found && credentials.uid == unsafe { libc::getuid() }
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = stream;
+        true
+    }
+}
+
+/// The detached side: serves the socket in place of the terminal (see `term`)
+pub struct Relay {
+    listener: UnixListener,
+    path: PathBuf,
+    verifier: Verifier,
+    client: Option<Client>,
+    screen: Screen,
+    events: VecDeque<Event>,
+}
+
+struct Client {
+    stream: UnixStream,
+    /// Bytes of an event line not complete yet
+    pending: Vec<u8>,
+}
+
+impl Relay {
+    /// Bind this process's socket. Output is kept on a `cols` x `rows` screen until a
+    /// client brings its own size.
+    pub fn listen(verifier: Verifier, (cols, rows): (u16, u16)) -> Result<Self, String> {
+        let path = socket_path(std::process::id())?;
+        let _ = fs::remove_file(&path);
+        let listener =
+            UnixListener::bind(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let _ = fs::set_permissions(&path, fs::Permissions::from_mode(0o600));
+        listener
+            .set_nonblocking(true)
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+        Ok(Relay {
+            listener,
+            path,
+            verifier,
+            client: None,
+            screen: Screen::new(cols as usize, rows as usize),
+            events: VecDeque::new(),
+        })
+    }
+
+    pub fn is_attached(&self) -> bool {
+        self.client.is_some()
+    }
+
+    pub fn size(&self) -> (u16, u16) {
+        let (cols, rows) = self.screen.size();
+        (cols as u16, rows as u16)
+    }
+
+    /// Whether an event arrives within `timeout`; clients are admitted meanwhile
+    pub fn poll(&mut self, timeout: Duration) -> io::Result<bool> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            self.accept();
+            self.receive();
+            if !self.events.is_empty() {
+                return Ok(true);
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return Ok(false);
+            }
+            thread::sleep(IDLE.min(deadline - now));
+        }
+    }
+
+    /// Next event from a client, if one arrives within `timeout`
+    pub fn next_event(&mut self, timeout: Duration) -> io::Result<Option<Event>> {
+        if !self.poll(timeout)? {
+            return Ok(None);
+        }
+        let event = self.events.pop_front();
+        if let Some(Event::Resize(cols, rows)) = event {
+            self.screen.resize(cols as usize, rows as usize);
+        }
+        Ok(event)
+    }
+
+    /// Output goes to the screen kept for reattaching and to the client, if any
+    pub fn write(&mut self, bytes: &[u8]) {
+        self.screen.feed(bytes);
+        if let Some(client) = self.client.as_mut() {
+            if client.stream.write_all(bytes).is_err() {
+                self.client = None;
+            }
+        }
+    }
+
+    /// Send the attached client away; the session keeps waiting for the next one
+    pub fn drop_client(&mut self) {
+        self.client = None;
+    }
+
+    /// Take a waiting connection: one client at a time, only with the passphrase
+    fn accept(&mut self) {
+        let Ok((mut stream, _)) = self.listener.accept() else {
+            return;
+        };
+        if self.client.is_some() {
+            let _ = stream.write_all(b"busy\n");
+            return;
+        }
+        if !peer_is_us(&stream) || stream.set_nonblocking(false).is_err() {
+            return;
+        }
+        let _ = stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT));
+        let _ = stream.set_write_timeout(Some(WRITE_TIMEOUT));
+
+        let mut handshake = Vec::with_capacity(MAX_HANDSHAKE);
+        let mut byte = [0u8; 1];
+        while handshake.iter().filter(|&&b| b == b'\n').count() < 2
+            && handshake.len() < MAX_HANDSHAKE
+        {
+            match stream.read(&mut byte) {
+                Ok(1) => handshake.push(byte[0]),
+                _ => break,
+            }
+        }
+        let text = String::from_utf8_lossy(&handshake).into_owned();
+        handshake.zeroize();
+        let mut lines = text.lines();
+        let accepted = lines
+            .next()
+            .and_then(|line| line.strip_prefix("auth "))
+            .is_some_and(|passphrase| self.verifier.accepts(passphrase));
+        let size = match lines.next().map(parse_script) {
+            Some(Ok(steps)) => match steps.as_slice() {
+                [Step::Event(Event::Resize(cols, rows))] => Some((*cols, *rows)),
+                _ => None,
+            },
+            _ => None,
+        };
+        let mut text = text;
+        text.zeroize();
+
+        match (accepted, size) {
+            (true, Some((cols, rows))) => {
+                // Event lines are read a little at a time from now on
+                let _ = stream.set_read_timeout(Some(Duration::from_millis(1)));
+                if stream.write_all(b"ok\n").is_err() {
+                    return;
+                }
+                self.screen.resize(cols as usize, rows as usize);
+                let repaint = self.screen.repaint();
+                self.client = Some(Client {
+                    stream,
+                    pending: Vec::new(),
+                });
+                self.write(repaint.as_bytes());
+                // The prompt loop redraws its line and status bar on a resize
+                self.events.push_back(Event::Resize(cols, rows));
+            }
+            _ => {
+                thread::sleep(DENIED_DELAY);
+                let _ = stream.write_all(b"denied\n");
+            }
+        }
+    }
+
+    /// Turn complete lines from the client into events; a closed connection detaches it
+    fn receive(&mut self) {
+        let Some(client) = self.client.as_mut() else {
+            return;
+        };
+        let mut chunk = [0u8; 1024];
+        loop {
+            match client.stream.read(&mut chunk) {
+                Ok(0) => {
+                    self.client = None;
+                    return;
+                }
+                Ok(read) => client.pending.extend_from_slice(&chunk[..read]),
+                Err(e)
+                    if matches!(
+                        e.kind(),
+                        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                    ) =>
+                {
+                    break
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(_) => {
+                    self.client = None;
+                    return;
+                }
+            }
+        }
+        chunk.zeroize();
+        while let Some(end) = client.pending.iter().position(|&b| b == b'\n') {
+            let mut line: Vec<u8> = client.pending.drain(..=end).collect();
+            if let Ok(steps) = parse_script(&String::from_utf8_lossy(&line)) {
+                self.events
+                    .extend(steps.into_iter().filter_map(|step| match step {
+                        Step::Event(event) => Some(event),
+                        Step::Snapshot => None,
+                    }));
+            }
+            line.zeroize();
+        }
+    }
+}
+
+impl Drop for Relay {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// `ghost-shell --attach [pid]`: connect this terminal to a detached session until it
+/// ends or detaches this client again
+pub fn attach(pid: Option<u32>) -> Result<(), String> {
+    let pid = match (pid, sessions().as_slice()) {
+        (Some(pid), _) => pid,
+        (None, [pid]) => *pid,
+        (None, []) => return Err("No detached session to attach to.".to_string()),
+        (None, pids) => {
+            return Err(format!(
+                "Several detached sessions: {}. Pick one: ghost-shell --attach <pid>",
+                pids.iter()
+                    .map(u32::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ))
+        }
+    };
+    let path = socket_path(pid)?;
+    enable_raw_mode().map_err(|e| format!("Terminal: {}", e))?;
+    let attached = relay_terminal(pid, &path);
+    let mut stdout = io::stdout();
+    let _ = execute!(stdout, DisableBracketedPaste);
+    let _ = disable_raw_mode();
+    println!();
+    attached
+}
+
+/// The passphrase is asked before connecting: the session admits one client at a
+/// time and waits for it
+fn relay_terminal(pid: u32, path: &Path) -> Result<(), String> {
+    let failed = |e: io::Error| format!("Session {}: {}", pid, e);
+    let Some(passphrase) =
+        read_secret(&format!("Passphrase for session {}: ", pid)).map_err(failed)?
+    else {
+        return Err("Cancelled.".to_string());
+    };
+    let mut stream = match UnixStream::connect(path) {
+        Ok(stream) => stream,
+        Err(e) => return Err(failed(e)),
+    };
+    // A terminal that reports no size gets the headless one
+    let (cols, rows) = match crossterm::terminal::size().map_err(failed)? {
+        (0, _) | (_, 0) => HEADLESS_SIZE,
+        size => size,
+    };
+    let mut handshake = format!("auth {}\nresize {} {}\n", passphrase.expose(), cols, rows);
+    drop(passphrase);
+    let sent = stream.write_all(handshake.as_bytes());
+    handshake.zeroize();
+    sent.map_err(failed)?;
+
+    let mut answer = Vec::new();
+    let mut byte = [0u8; 1];
+    while stream.read(&mut byte).map_err(failed)? == 1 && byte[0] != b'\n' {
+        answer.push(byte[0]);
+    }
+    match answer.as_slice() {
+        b"ok" => {}
+        b"busy" => return Err(format!("Session {} already has a client attached.", pid)),
+        _ => return Err("Wrong passphrase.".to_string()),
+    }
+
+    let mut stdout = io::stdout();
+    execute!(stdout, EnableBracketedPaste).map_err(failed)?;
+    let ended = Arc::new(AtomicBool::new(false));
+    {
+        let mut output = stream.try_clone().map_err(failed)?;
+        let ended = Arc::clone(&ended);
+        thread::spawn(move || {
+            let mut stdout = io::stdout();
+            let mut chunk = [0u8; 4096];
+            while let Ok(read @ 1..) = output.read(&mut chunk) {
+                if stdout.write_all(&chunk[..read]).is_err() || stdout.flush().is_err() {
+                    break;
+                }
+            }
+            ended.store(true, Ordering::SeqCst);
+        });
+    }
+    while !ended.load(Ordering::SeqCst) {
+        if !event::poll(Duration::from_millis(100)).map_err(failed)? {
+            continue;
+        }
+        let mut line = match encode_event(&event::read().map_err(failed)?) {
+            Some(line) => line + "\n",
+            None => continue,
+        };
+        let sent = stream.write_all(line.as_bytes());
+        line.zeroize();
+        if sent.is_err() {
+            break;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,307 @@
+/// Command lines parsed without a shell, for `::direct on`
+/// The line is split into words the way `sh` would for a simple command: single and
+/// double quotes, backslash escapes, `~/` and `$NAME`/`${NAME}` expansion (never split
+/// into more words), `*`, `?` and `[...]` globs (left as typed when nothing matches),
+/// leading `NAME=value` assignments and `#` comments. The program is then exec'd
+/// straight away, so no bash or zsh starts: no rc file runs, no PROMPT_COMMAND or
+/// preexec hook audits the line, nothing reaches a shell history. Pipes, redirections,
+/// `;`, `&&`, subshells, command substitution and brace expansion need a shell and are
+/// refused rather than passed along.
+use std::env;
+use std::fs;
+use std::path::Path;
+
+/// A parsed simple command
+pub struct Argv {
+    /// `NAME=value` words before the program
+    pub env: Vec<(String, String)>,
+    /// The program and its arguments
+    pub args: Vec<String>,
+}
+
+/// Parse `line` into a program, its arguments and environment assignments
+pub fn parse(line: &str) -> Result<Argv, String> {
+    let mut words = words(line)?.into_iter().peekable();
+    let mut assignments = Vec::new();
+    while let Some(word) = words.next_if(|word| assignment(word).is_some()) {
+        assignments.extend(assignment(&word));
+    }
+    let args: Vec<String> = words.collect();
+    if args.is_empty() {
+        return Err("Nothing to run.".to_string());
+    }
+    Ok(Argv {
+        env: assignments,
+        args,
+    })
+}
+
+/// `NAME=value` as a pair
+fn assignment(word: &str) -> Option<(String, String)> {
+    let (name, value) = word.split_once('=')?;
+    let valid = name
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    valid.then(|| (name.to_string(), value.to_string()))
+}
+
+/// A character of a word and whether it was quoted or escaped (so never a glob)
+type Quoted = (char, bool);
+
+/// Split `line` into words with quotes removed and `~`, variables and globs expanded
+pub fn words(line: &str) -> Result<Vec<String>, String> {
+    let needs_shell = |what: &str| Err(format!("'{}' needs a shell (::direct off).", what));
+    let mut words = Vec::new();
+    let mut word: Vec<Quoted> = Vec::new();
+    let mut started = false; // The current word had quotes, so it exists even if empty
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => {
+                started = true;
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => word.push((c, true)),
+                        None => return Err("Unterminated ' quote.".to_string()),
+                    }
+                }
+            }
+            '"' => {
+                started = true;
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('$') => expand_variable(&mut chars, &mut word)?,
+                        Some('`') => return needs_shell("`"),
+                        Some('\\') if matches!(chars.peek(), Some('"' | '\\' | '$' | '`')) => {
+                            word.extend(chars.next().map(|c| (c, true)))
+                        }
+                        Some(c) => word.push((c, true)),
+                        None => return Err("Unterminated \" quote.".to_string()),
+                    }
+                }
+            }
+            '\\' => match chars.next() {
+                Some(c) => word.push((c, true)),
+                None => return needs_shell("\\"),
+            },
+            '$' => expand_variable(&mut chars, &mut word)?,
+            '~' if word.is_empty() && !started => {
+                if !matches!(chars.peek(), None | Some('/' | ' ')) {
+                    return needs_shell("~user");
+                }
+                let home = env::var("HOME").map_err(|_| "HOME is not set.".to_string())?;
+                word.extend(home.chars().map(|c| (c, true)));
+            }
+            '#' if word.is_empty() && !started => break,
+            c if c.is_whitespace() => {
+                if !word.is_empty() || started {
+                    words.extend(expand_glob(&word));
+                    word.clear();
+                }
+                started = false;
+            }
+            '|' | '&' | ';' | '<' | '>' | '(' | ')' | '`' => return needs_shell(&c.to_string()),
+            '{' | '}' if !(c == '{' && chars.peek() == Some(&'}')) => {
+                return needs_shell(&c.to_string())
+            }
+            '{' => {
+                chars.next();
+                word.extend([('{', true), ('}', true)]);
+            }
+            c => word.push((c, false)),
+        }
+    }
+    if !word.is_empty() || started {
+        words.extend(expand_glob(&word));
+    }
+    Ok(words)
+}
+
+/// `$NAME` or `${NAME}` after the `$`; unset variables expand to nothing. A lone `$`
+/// stays literal.
+fn expand_variable(
+    chars: &mut std::iter::Peekable<std::str::Chars>,
+    word: &mut Vec<Quoted>,
+) -> Result<(), String> {
+    let braced = chars.next_if_eq(&'{').is_some();
+    let mut name = String::new();
+    while let Some(c) = chars.next_if(|c| c.is_ascii_alphanumeric() || *c == '_') {
+        name.push(c);
+    }
+    if braced && chars.next_if_eq(&'}').is_none() {
+        return Err("Only ${NAME} expansion works without a shell (::direct off).".to_string());
+    }
+    if name.is_empty() && !braced && matches!(chars.peek(), None | Some(' ' | '"')) {
+        word.push(('$', true));
+        return Ok(());
+    }
+    if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+        return Err("Only $NAME expansion works without a shell (::direct off).".to_string());
+    }
+    // Expanded text is never globbed or split, as if it had been quoted
+    word.extend(
+        env::var(&name)
+            .unwrap_or_default()
+            .chars()
+            .map(|c| (c, true)),
+    );
+    Ok(())
+}
+
+/// One glob pattern element
+#[derive(Debug)]
+enum Glob {
+    Literal(char),
+    Any,
+    Star,
+    Class(Vec<(char, char)>, bool), // Ranges, and whether the class is negated
+}
+
+/// Parse a path component into glob elements; None when it has no glob characters
+fn compile(component: &[Quoted]) -> Option<Vec<Glob>> {
+    let mut pattern = Vec::new();
+    let mut globbing = false;
+    let mut i = 0;
+    while i < component.len() {
+        let (c, quoted) = component[i];
+        i += 1;
+        match c {
+            '*' if !quoted => {
+                globbing = true;
+                pattern.push(Glob::Star);
+            }
+            '?' if !quoted => {
+                globbing = true;
+                pattern.push(Glob::Any);
+            }
+            '[' if !quoted => {
+                // An unclosed bracket is a literal `[`
+                let Some(end) = (i + 1..component.len()).find(|&j| component[j].0 == ']') else {
+                    pattern.push(Glob::Literal('['));
+                    continue;
+                };
+                let negated = matches!(component[i].0, '!' | '^');
+                let body: Vec<char> = component[i + usize::from(negated)..end]
+                    .iter()
+                    .map(|(c, _)| *c)
+                    .collect();
+                let mut ranges = Vec::new();
+                let mut j = 0;
+                while j < body.len() {
+                    if j + 2 < body.len() && body[j + 1] == '-' {
+                        ranges.push((body[j], body[j + 2]));
+                        j += 3;
+                    } else {
+                        ranges.push((body[j], body[j]));
+                        j += 1;
+                    }
+                }
+                globbing = true;
+                pattern.push(Glob::Class(ranges, negated));
+                i = end + 1;
+            }
+            c => pattern.push(Glob::Literal(c)),
+        }
+    }
+    globbing.then_some(pattern)
+}
+
+/// Whether `name` matches `pattern`; a leading `.` must be matched literally
+fn glob_match(pattern: &[Glob], name: &str) -> bool {
+    let name: Vec<char> = name.chars().collect();
+    if name.first() == Some(&'.') && !matches!(pattern.first(), Some(Glob::Literal('.'))) {
+        return false;
+    }
+    // Greedy matching that backtracks to the last `*` only, which is enough since a
+    // `*` matches any run
+    let (mut p, mut n) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        let single = match pattern.get(p) {
+            Some(Glob::Literal(c)) => *c == name[n],
+            Some(Glob::Any) => true,
+            Some(Glob::Class(ranges, negated)) => {
+                ranges
+                    .iter()
+                    .any(|(low, high)| (*low..=*high).contains(&name[n]))
+                    != *negated
+            }
+            Some(Glob::Star) => {
+                star = Some((p, n));
+                p += 1;
+                continue;
+            }
+            None => false,
+        };
+        if single {
+            p += 1;
+            n += 1;
+        } else if let Some((star_p, star_n)) = star {
+            p = star_p + 1;
+            n = star_n + 1;
+            star = Some((star_p, star_n + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|glob| matches!(glob, Glob::Star))
+}
+
+/// The paths `word` matches in name order, or the word itself when it has no glob or
+/// nothing matches
+fn expand_glob(word: &[Quoted]) -> Vec<String> {
+    let literal: String = word.iter().map(|(c, _)| *c).collect();
+    let components: Vec<&[Quoted]> = word.split(|(c, _)| *c == '/').collect();
+    if components
+        .iter()
+        .all(|component| compile(component).is_none())
+    {
+        return vec![literal];
+    }
+    // Each candidate is the path typed so far; the first component is empty for `/x`
+    let mut candidates = vec![String::new()];
+    for (i, component) in components.iter().enumerate() {
+        let separator = if i == 0 { "" } else { "/" };
+        let mut next = Vec::new();
+        match compile(component) {
+            None => {
+                let text: String = component.iter().map(|(c, _)| *c).collect();
+                for candidate in &candidates {
+                    next.push(format!("{}{}{}", candidate, separator, text));
+                }
+            }
+            Some(pattern) => {
+                for candidate in &candidates {
+                    let dir = match (i, candidate.as_str()) {
+                        (0, _) => ".",
+                        (_, "") => "/",
+                        (_, dir) => dir,
+                    };
+                    let Ok(entries) = fs::read_dir(dir) else {
+                        continue;
+                    };
+                    let mut names: Vec<String> = entries
+                        .flatten()
+                        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+                        .filter(|name| glob_match(&pattern, name))
+                        .collect();
+                    names.sort();
+                    for name in names {
+                        next.push(format!("{}{}{}", candidate, separator, name));
+                    }
+                }
+            }
+        }
+        candidates = next;
+    }
+    candidates.retain(|path| Path::new(path).symlink_metadata().is_ok());
+    if candidates.is_empty() {
+        vec![literal]
+    } else {
+        candidates
+    }
+}
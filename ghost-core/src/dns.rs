@@ -0,0 +1,459 @@
+/// Encrypted DNS lookups for ::resolve
+/// Queries go straight to a DNS-over-HTTPS (RFC 8484) or DNS-over-TLS (RFC 7858)
+/// server from this process, so they never reach the system resolver, its cache or
+/// its query log. The default server is addressed by IP, so not even its own name
+/// has to be looked up; with a ::proxy set the connection is tunnelled through it.
+use rand::RngCore;
+use rustls::pki_types::ServerName;
+use rustls::{ClientConfig, ClientConnection, RootCertStore, StreamOwned};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpStream, ToSocketAddrs};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::proxy::Proxy;
+
+/// Cloudflare's resolver; its certificate covers the IP address
+pub const DEFAULT_SERVER: &str = "https://1.1.1.1/dns-query";
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Largest DNS message over TCP/HTTPS
+const MAX_MESSAGE: usize = 65535;
+
+const TYPE_A: u16 = 1;
+const TYPE_NS: u16 = 2;
+const TYPE_CNAME: u16 = 5;
+const TYPE_PTR: u16 = 12;
+const TYPE_MX: u16 = 15;
+const TYPE_TXT: u16 = 16;
+const TYPE_AAAA: u16 = 28;
+
+/// Where queries are sent
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Server {
+    /// `https://host[:port]/path`
+    Https {
+        host: String,
+        port: u16,
+        path: String,
+    },
+    /// `tls://host[:port]`
+    Tls { host: String, port: u16 },
+}
+
+/// One answer record
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Record {
+    pub kind: &'static str,
+    pub value: String,
+    pub ttl: u32,
+}
+
+impl Server {
+    /// Parse `https://host[:port][/path]` (DoH) or `tls://host[:port]` (DoT)
+    pub fn parse(url: &str) -> Result<Self, String> {
+        let invalid = || {
+            format!(
+                "Invalid resolver: {} (expected https://host/dns-query or tls://host[:853])",
+                url
+            )
+        };
+        let (scheme, rest) = url.trim().split_once("://").ok_or_else(invalid)?;
+        let (authority, path) = match rest.find('/') {
+            Some(slash) => (&rest[..slash], &rest[slash..]),
+            None => (rest, ""),
+        };
+        let default_port = match scheme.to_ascii_lowercase().as_str() {
+            "https" => 443,
+            "tls" => 853,
+            _ => return Err(invalid()),
+        };
+        let (host, port) = split_authority(authority, default_port).ok_or_else(invalid)?;
+        Ok(if default_port == 443 {
+            Server::Https {
+                host,
+                port,
+                path: if path.is_empty() {
+                    "/dns-query".to_string()
+                } else {
+                    path.to_string()
+                },
+            }
+        } else {
+            Server::Tls { host, port }
+        })
+    }
+
+    fn host(&self) -> &str {
+        match self {
+            Server::Https { host, .. } | Server::Tls { host, .. } => host,
+        }
+    }
+
+    fn port(&self) -> u16 {
+        match self {
+            Server::Https { port, .. } | Server::Tls { port, .. } => *port,
+        }
+    }
+
+    /// `DoH` or `DoT`
+    pub fn protocol(&self) -> &'static str {
+        match self {
+            Server::Https { .. } => "DoH",
+            Server::Tls { .. } => "DoT",
+        }
+    }
+}
+
+/// `host` and `port` from `host`, `host:port`, `[v6]` or `[v6]:port`
+fn split_authority(authority: &str, default_port: u16) -> Option<(String, u16)> {
+    let (host, port) = if let Some(rest) = authority.strip_prefix('[') {
+        let (host, after) = rest.split_once(']')?;
+        (host, after.strip_prefix(':'))
+    } else {
+        match authority.split_once(':') {
+            Some((host, port)) => (host, Some(port)),
+            None => (authority, None),
+        }
+    };
+    let port = match port {
+        Some(port) => port.parse().ok().filter(|port| *port != 0)?,
+        None => default_port,
+    };
+    (!host.is_empty()).then(|| (host.to_string(), port))
+}
+
+/// Records for `name` from `server`: A and AAAA (with any CNAMEs), or PTR when
+/// `name` is an IP address
+pub fn resolve(name: &str, server: &Server, proxy: Option<&Proxy>) -> Result<Vec<Record>, String> {
+    let queries: Vec<(String, u16)> = match name.parse::<IpAddr>() {
+        Ok(address) => vec![(reverse_name(&address), TYPE_PTR)],
+        Err(_) => {
+            let name = name.trim_end_matches('.');
+            vec![(name.to_string(), TYPE_A), (name.to_string(), TYPE_AAAA)]
+        }
+    };
+    let mut stream = connect(server, proxy)?;
+    let mut records: Vec<Record> = Vec::new();
+    for (i, (qname, qtype)) in queries.iter().enumerate() {
+        let (id, query) = build_query(qname, *qtype)?;
+        let last = i + 1 == queries.len();
+        let response = match server {
+            Server::Https { host, path, .. } => {
+                doh_exchange(&mut stream, host, path, &query, last)?
+            }
+            Server::Tls { .. } => dot_exchange(&mut stream, &query)?,
+        };
+        for record in parse_response(&response, id)? {
+            // The CNAME chain comes back with both A and AAAA answers
+            if !records.contains(&record) {
+                records.push(record);
+            }
+        }
+    }
+    Ok(records)
+}
+
+type TlsStream = StreamOwned<ClientConnection, TcpStream>;
+
+fn connect(server: &Server, proxy: Option<&Proxy>) -> Result<TlsStream, String> {
+    let host = server.host();
+    let tcp = match proxy {
+        Some(proxy) => proxy.tunnel(host, server.port())?,
+        None => {
+            let address = match host.parse::<IpAddr>() {
+                Ok(ip) => SocketAddr::new(ip, server.port()),
+                // Only the resolver's own name goes to the system resolver
+                Err(_) => (host, server.port())
+                    .to_socket_addrs()
+                    .ok()
+                    .and_then(|mut addresses| addresses.next())
+                    .ok_or_else(|| format!("resolve: cannot reach {}", host))?,
+            };
+            TcpStream::connect_timeout(&address, TIMEOUT)
+                .map_err(|e| format!("resolve: {}: {}", host, e))?
+        }
+    };
+    tcp.set_read_timeout(Some(TIMEOUT))
+        .and_then(|_| tcp.set_write_timeout(Some(TIMEOUT)))
+        .map_err(|e| format!("resolve: {}", e))?;
+
+    let roots = RootCertStore {
+        roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+    };
+    let mut config =
+        ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .map_err(|e| format!("resolve: {}", e))?
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+    if matches!(server, Server::Https { .. }) {
+        config.alpn_protocols = vec![b"http/1.1".to_vec()];
+    }
+    let name = ServerName::try_from(host.to_string())
+        .map_err(|_| format!("resolve: invalid server name {}", host))?;
+    let connection =
+        ClientConnection::new(Arc::new(config), name).map_err(|e| format!("resolve: {}", e))?;
+    Ok(StreamOwned::new(connection, tcp))
+}
+
+/// RFC 8484 POST on a kept-alive HTTP/1.1 connection
+fn doh_exchange(
+    stream: &mut TlsStream,
+    host: &str,
+    path: &str,
+    query: &[u8],
+    last: bool,
+) -> Result<Vec<u8>, String> {
+    let tls_error = |e: std::io::Error| format!("resolve: {}: {}", host, e);
+    let host_header = if host.contains(':') {
+        format!("[{}]", host)
+    } else {
+        host.to_string()
+    };
+    let request = format!(
+        "POST {} HTTP/1.1\r\nHost: {}\r\nAccept: application/dns-message\r\n\
+        Content-Type: application/dns-message\r\nContent-Length: {}\r\nConnection: {}\r\n\r\n",
+        path,
+        host_header,
+        query.len(),
+        if last { "close" } else { "keep-alive" }
+    );
+    let mut message = request.into_bytes();
+    message.extend_from_slice(query);
+    stream.write_all(&message).map_err(tls_error)?;
+    stream.flush().map_err(tls_error)?;
+
+    // The reader only borrows the stream; the body is read exactly so the next
+    // response stays in the TLS buffer
+    let mut reader = BufReader::with_capacity(1, &mut *stream);
+    let mut status = String::new();
+    reader.read_line(&mut status).map_err(tls_error)?;
+    let mut length: Option<usize> = None;
+    let mut chunked = false;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).map_err(tls_error)? == 0 {
+            return Err("resolve: connection closed mid-response.".to_string());
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((key, value)) = line.split_once(':') {
+            let value = value.trim();
+            match key.trim().to_ascii_lowercase().as_str() {
+                "content-length" => length = value.parse().ok(),
+                "transfer-encoding" => chunked = value.eq_ignore_ascii_case("chunked"),
+                _ => {}
+            }
+        }
+    }
+    let body = if chunked {
+        read_chunked(&mut reader).map_err(tls_error)?
+    } else {
+        let length = length
+            .filter(|length| *length <= MAX_MESSAGE)
+            .ok_or("resolve: server sent no usable Content-Length.")?;
+        let mut body = vec![0u8; length];
+        reader.read_exact(&mut body).map_err(tls_error)?;
+        body
+    };
+    match status.split_whitespace().nth(1) {
+        Some("200") => Ok(body),
+        _ => Err(format!("resolve: server answered {}", status.trim())),
+    }
+}
+
+fn read_chunked(reader: &mut impl BufRead) -> std::io::Result<Vec<u8>> {
+    let mut body = Vec::new();
+    loop {
+        let mut size = String::new();
+        reader.read_line(&mut size)?;
+        let size = usize::from_str_radix(size.trim().split(';').next().unwrap_or(""), 16)
+            .map_err(|_| std::io::Error::other("malformed chunk"))?;
+        if body.len() + size > MAX_MESSAGE {
+            return Err(std::io::Error::other("response too large"));
+        }
+        let start = body.len();
+        body.resize(start + size, 0);
+        reader.read_exact(&mut body[start..])?;
+        let mut crlf = String::new();
+        reader.read_line(&mut crlf)?;
+        if size == 0 {
+            return Ok(body);
+        }
+    }
+}
+
+/// RFC 7858: the TCP framing (RFC 1035 4.2.2) inside TLS
+fn dot_exchange(stream: &mut TlsStream, query: &[u8]) -> Result<Vec<u8>, String> {
+    let tls_error = |e: std::io::Error| format!("resolve: {}", e);
+    let mut message = (query.len() as u16).to_be_bytes().to_vec();
+    message.extend_from_slice(query);
+    stream.write_all(&message).map_err(tls_error)?;
+    stream.flush().map_err(tls_error)?;
+    let mut length = [0u8; 2];
+    stream.read_exact(&mut length).map_err(tls_error)?;
+    let mut response = vec![0u8; u16::from_be_bytes(length) as usize];
+    stream.read_exact(&mut response).map_err(tls_error)?;
+    Ok(response)
+}
+
+/// `4.3.2.1.in-addr.arpa` / nibble-reversed `ip6.arpa`
+fn reverse_name(address: &IpAddr) -> String {
+    match address {
+        IpAddr::V4(v4) => {
+            let [a, b, c, d] = v4.octets();
+            format!("{}.{}.{}.{}.in-addr.arpa", d, c, b, a)
+        }
+        IpAddr::V6(v6) => {
+            let mut name = String::new();
+            for byte in v6.octets().iter().rev() {
+                name.push_str(&format!("{:x}.{:x}.", byte & 0xf, byte >> 4));
+            }
+            name + "ip6.arpa"
+        }
+    }
+}
+
+/// A recursive query for `name`; returns its ID with the message
+fn build_query(name: &str, qtype: u16) -> Result<(u16, Vec<u8>), String> {
+    let invalid = || format!("Invalid domain name: {}", name);
+    if name.is_empty() || name.len() > 253 || !name.is_ascii() {
+        return Err(invalid());
+    }
+    let id = rand::thread_rng().next_u32() as u16;
+    let mut message = Vec::with_capacity(name.len() + 18);
+    message.extend_from_slice(&id.to_be_bytes());
+    message.extend_from_slice(&[0x01, 0x00]); // RD
+    message.extend_from_slice(&[0, 1, 0, 0, 0, 0, 0, 0]); // QDCOUNT 1
+    for label in name.split('.') {
+        if label.is_empty() || label.len() > 63 {
+            return Err(invalid());
+        }
+        message.push(label.len() as u8);
+        message.extend_from_slice(label.as_bytes());
+    }
+    message.push(0);
+    message.extend_from_slice(&qtype.to_be_bytes());
+    message.extend_from_slice(&[0, 1]); // IN
+    Ok((id, message))
+}
+
+fn parse_response(message: &[u8], id: u16) -> Result<Vec<Record>, String> {
+    let malformed = || "resolve: malformed DNS response.".to_string();
+    if message.len() < 12 || u16::from_be_bytes([message[0], message[1]]) != id {
+        return Err(malformed());
+    }
+    match message[3] & 0x0f {
+        0 => {}
+        2 => return Err("resolve: server failure (SERVFAIL).".to_string()),
+        3 => return Err("resolve: no such domain (NXDOMAIN).".to_string()),
+        5 => return Err("resolve: query refused.".to_string()),
+        code => return Err(format!("resolve: server error (rcode {}).", code)),
+    }
+    let questions = u16::from_be_bytes([message[4], message[5]]);
+    let answers = u16::from_be_bytes([message[6], message[7]]);
+
+    let mut position = 12;
+    for _ in 0..questions {
+        position = read_name(message, position).ok_or_else(malformed)?.1 + 4;
+    }
+    let mut records = Vec::new();
+    for _ in 0..answers {
+        let (_, after_name) = read_name(message, position).ok_or_else(malformed)?;
+        let fixed = message
+            .get(after_name..after_name + 10)
+            .ok_or_else(malformed)?;
+        let rtype = u16::from_be_bytes([fixed[0], fixed[1]]);
+        let ttl = u32::from_be_bytes([fixed[4], fixed[5], fixed[6], fixed[7]]);
+        let length = u16::from_be_bytes([fixed[8], fixed[9]]) as usize;
+        let start = after_name + 10;
+        let data = message.get(start..start + length).ok_or_else(malformed)?;
+        position = start + length;
+
+        let name_at = |offset: usize| read_name(message, offset).map(|(name, _)| name);
+        let (kind, value) = match rtype {
+            TYPE_A if length == 4 => (
+                "A",
+                Ipv4Addr::new(data[0], data[1], data[2], data[3]).to_string(),
+            ),
+            TYPE_AAAA if length == 16 => {
+                let mut octets = [0u8; 16];
+                octets.copy_from_slice(data);
+                ("AAAA", Ipv6Addr::from(octets).to_string())
+            }
+            TYPE_CNAME => ("CNAME", name_at(start).ok_or_else(malformed)?),
+            TYPE_PTR => ("PTR", name_at(start).ok_or_else(malformed)?),
+            TYPE_NS => ("NS", name_at(start).ok_or_else(malformed)?),
+            TYPE_MX if length > 2 => {
+                let preference = u16::from_be_bytes([data[0], data[1]]);
+                let exchange = name_at(start + 2).ok_or_else(malformed)?;
+                ("MX", format!("{} {}", preference, exchange))
+            }
+            TYPE_TXT => ("TXT", txt_strings(data).ok_or_else(malformed)?),
+            // DNSSEC signatures and the like
+            _ => continue,
+        };
+        records.push(Record {
+            kind,
+            value: sanitize(&value),
+            ttl,
+        });
+    }
+    Ok(records)
+}
+
+/// Name at `offset`, following compression pointers, and the offset after it
+fn read_name(message: &[u8], offset: usize) -> Option<(String, usize)> {
+    let mut labels: Vec<String> = Vec::new();
+    let mut position = offset;
+    let mut end = None;
+    // Bounds pointer loops
+    for _ in 0..128 {
+        let length = *message.get(position)? as usize;
+        match length {
+            0 => {
+                let name = if labels.is_empty() {
+                    ".".to_string()
+                } else {
+                    labels.join(".") + "."
+                };
+                return Some((name, end.unwrap_or(position + 1)));
+            }
+            l if l & 0xc0 == 0xc0 => {
+                let pointer = ((l & 0x3f) << 8) | *message.get(position + 1)? as usize;
+                end.get_or_insert(position + 2);
+                position = pointer;
+            }
+            l if l <= 63 => {
+                let label = message.get(position + 1..position + 1 + l)?;
+                labels.push(String::from_utf8_lossy(label).into_owned());
+                position += 1 + l;
+            }
+            _ => return None,
+        }
+    }
+    None
+}
+
+/// The length-prefixed character-strings of a TXT record, joined
+fn txt_strings(data: &[u8]) -> Option<String> {
+    let mut text = Vec::new();
+    let mut rest = data;
+    while let Some((&length, tail)) = rest.split_first() {
+        text.extend_from_slice(tail.get(..length as usize)?);
+        rest = &tail[length as usize..];
+    }
+    Some(String::from_utf8_lossy(&text).into_owned())
+}
+
+/// Answers are attacker-controlled: no escape sequences into the terminal
+fn sanitize(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| if c.is_control() { '?' } else { c })
+        .collect()
+}
@@ -0,0 +1,401 @@
+/// Passphrase-based file encryption for ::encrypt-file / ::decrypt-file
+/// (age files are handed to the age module, OpenPGP files to gpg)
+/// Files are streamed through ChaCha20-Poly1305 in the STREAM construction (64 KiB
+/// chunks, big-endian counter, last-chunk flag) so size is bounded only by disk.
+/// Layout: `GHOSTF01 | salt (16) | nonce prefix (7) | chunks...`, with the header
+/// authenticated as associated data of every chunk.
+use chacha20poly1305::aead::stream::{DecryptorBE32, EncryptorBE32};
+use chacha20poly1305::aead::{KeyInit, OsRng, Payload};
+use chacha20poly1305::ChaCha20Poly1305;
+use rand::RngCore;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use zeroize::Zeroize;
+
+use crate::age::{self, Recipients, Unlock};
+use crate::clipboard::{create_private_file, derive_passphrase_key};
+use crate::gpg;
+
+/// File signature and format version
+const MAGIC: &[u8; 8] = b"GHOSTF01";
+
+const SALT_LEN: usize = 16;
+
+/// STREAM nonce prefix: 12-byte nonce minus the 4-byte counter and 1-byte last flag
+const NONCE_PREFIX_LEN: usize = 7;
+
+const HEADER_LEN: usize = MAGIC.len() + SALT_LEN + NONCE_PREFIX_LEN;
+
+/// Plaintext bytes per chunk
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Poly1305 tag appended to every chunk
+const TAG_LEN: usize = 16;
+
+/// Extension added by `::encrypt-file`
+pub const ENCRYPTED_EXTENSION: &str = "ghost";
+
+/// Extension added by `::encrypt-file --age`
+pub const AGE_EXTENSION: &str = "age";
+
+/// Extension added by `::encrypt-file --gpg`
+pub const GPG_EXTENSION: &str = "gpg";
+
+/// Encrypted file formats understood by `::decrypt-file`
+pub enum FileFormat {
+    Ghost,
+    Age {
+        passphrase: bool,
+    },
+    /// OpenPGP, handed to gpg
+    Gpg,
+}
+
+/// Identify an encrypted file from its header
+pub fn detect_format(path: &Path) -> Result<FileFormat, String> {
+    let mut input = File::open(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let mut prefix = [0u8; 64];
+    let n = read_full(&mut input, &mut prefix).map_err(|e| format!("{}: {}", path.display(), e))?;
+
+    if prefix[..n].starts_with(MAGIC) {
+        Ok(FileFormat::Ghost)
+    } else if age::is_age(&prefix[..n]) {
+        let passphrase =
+            with_age_input(path, |mut input| age::is_passphrase_protected(&mut input))?;
+        Ok(FileFormat::Age { passphrase })
+    } else if gpg::is_pgp(&prefix[..n]) {
+        Ok(FileFormat::Gpg)
+    } else {
+        Err(format!(
+            "{}: not a Ghost Shell, age or OpenPGP encrypted file.",
+            path.display()
+        ))
+    }
+}
+
+/// Encrypt `path` to `<path>.ghost` under `passphrase`. Returns the new path.
+pub fn encrypt_file(path: &Path, passphrase: &str) -> Result<PathBuf, String> {
+    let mut input = File::open(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+
+    let mut header = [0u8; HEADER_LEN];
+    header[..MAGIC.len()].copy_from_slice(MAGIC);
+    OsRng.fill_bytes(&mut header[MAGIC.len()..]);
+
+    let key = derive_passphrase_key(passphrase, &header[MAGIC.len()..][..SALT_LEN])?;
+    let cipher = ChaCha20Poly1305::new(key.expose().into());
+
+    let output_path = with_extension_appended(path, ENCRYPTED_EXTENSION);
+    write_new_file(&output_path, |output| {
+        output
+            .write_all(&header)
+            .map_err(|e| format!("{}: {}", output_path.display(), e))?;
+        encrypt_stream(cipher, &header, &mut input, output, path, &output_path)
+    })
+}
+
+/// Encrypt in-memory `data` under `passphrase` to a new `.ghost`-format file at `path` (0600)
+pub fn write_ghost_file(path: &Path, passphrase: &str, data: &[u8]) -> Result<(), String> {
+    let mut header = [0u8; HEADER_LEN];
+    header[..MAGIC.len()].copy_from_slice(MAGIC);
+    OsRng.fill_bytes(&mut header[MAGIC.len()..]);
+
+    let key = derive_passphrase_key(passphrase, &header[MAGIC.len()..][..SALT_LEN])?;
+    let cipher = ChaCha20Poly1305::new(key.expose().into());
+
+    write_new_file(path, |output| {
+        output
+            .write_all(&header)
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+        encrypt_stream(cipher, &header, &mut &data[..], output, path, path)
+    })
+    .map(|_| ())
+}
+
+/// Decrypt a `.ghost` file next to it (extension stripped, or `.dec` appended).
+/// Returns the new path; nothing is left behind if authentication fails.
+pub fn decrypt_file(path: &Path, passphrase: &str) -> Result<PathBuf, String> {
+    let mut input = File::open(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+
+    let mut header = [0u8; HEADER_LEN];
+    if read_full(&mut input, &mut header).map_err(|e| format!("{}: {}", path.display(), e))?
+        < HEADER_LEN
+        || &header[..MAGIC.len()] != MAGIC
+    {
+        return Err(format!(
+            "{}: not a Ghost Shell encrypted file.",
+            path.display()
+        ));
+    }
+
+    let key = derive_passphrase_key(passphrase, &header[MAGIC.len()..][..SALT_LEN])?;
+    let cipher = ChaCha20Poly1305::new(key.expose().into());
+
+    let output_path = decrypted_path(path, ENCRYPTED_EXTENSION);
+    write_new_file(&output_path, |output| {
+        decrypt_stream(cipher, &header, &mut input, output, path, &output_path)
+    })
+}
+
+/// Encrypt `path` to `<path>.age` (binary age format). Returns the new path.
+pub fn encrypt_file_age(path: &Path, recipients: &Recipients) -> Result<PathBuf, String> {
+    let mut input = File::open(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let output_path = with_extension_appended(path, AGE_EXTENSION);
+    write_new_file(&output_path, |output| {
+        age::encrypt(recipients, &mut input, output)
+    })
+}
+
+/// Decrypt a binary or armored age file next to it (`.age` stripped, or `.dec` appended)
+pub fn decrypt_file_age(path: &Path, unlock: &Unlock) -> Result<PathBuf, String> {
+    let output_path = decrypted_path(path, AGE_EXTENSION);
+    write_new_file(&output_path, |output| {
+        with_age_input(path, |mut input| age::decrypt(unlock, &mut input, output))
+    })
+}
+
+/// Encrypt in-memory `data` to a new age file at `path` (0600)
+pub fn write_age_file(path: &Path, recipients: &Recipients, data: &[u8]) -> Result<(), String> {
+    write_new_file(path, |output| {
+        age::encrypt(recipients, &mut &data[..], output)
+    })
+    .map(|_| ())
+}
+
+/// Decrypt a whole age file (binary or armored) into memory
+pub fn read_age_file(path: &Path, unlock: &Unlock) -> Result<Vec<u8>, String> {
+    let mut plaintext = Vec::new();
+    match with_age_input(path, |mut input| {
+        age::decrypt(unlock, &mut input, &mut plaintext)
+    }) {
+        Ok(()) => Ok(plaintext),
+        Err(e) => {
+            plaintext.zeroize();
+            Err(e)
+        }
+    }
+}
+
+/// Encrypt `path` to `<path>.gpg` for `recipient` with gpg. Returns the new path.
+pub fn encrypt_file_gpg(path: &Path, recipient: &str) -> Result<PathBuf, String> {
+    let input = File::open(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let output_path = with_extension_appended(path, GPG_EXTENSION);
+    write_new_file(&output_path, |output| {
+        gpg::encrypt_file(recipient, input, output)
+    })
+}
+
+/// Decrypt an OpenPGP file next to it with gpg (`.gpg`/`.pgp`/`.asc` stripped, or `.dec` appended)
+pub fn decrypt_file_gpg(path: &Path) -> Result<PathBuf, String> {
+    let input = File::open(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let output_path = ["pgp", "asc"]
+        .into_iter()
+        .find(|ext| path.extension().is_some_and(|e| e == *ext))
+        .map_or_else(
+            || decrypted_path(path, GPG_EXTENSION),
+            |_| path.with_extension(""),
+        );
+    write_new_file(&output_path, |output| gpg::decrypt_file(input, output))
+}
+
+/// Run `read` over the binary age stream of `path`, removing ASCII armor if present
+fn with_age_input<T>(
+    path: &Path,
+    read: impl FnOnce(&mut dyn BufRead) -> Result<T, String>,
+) -> Result<T, String> {
+    let mut input =
+        BufReader::new(File::open(path).map_err(|e| format!("{}: {}", path.display(), e))?);
+    let armored = input
+        .fill_buf()
+        .map_err(|e| format!("{}: {}", path.display(), e))?
+        .trim_ascii_start()
+        .starts_with(age::ARMOR_BEGIN.as_bytes());
+
+    if armored {
+        let mut text = String::new();
+        input
+            .read_to_string(&mut text)
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+        let binary = age::dearmor(&text);
+        text.zeroize();
+        read(&mut binary?.as_slice())
+    } else {
+        read(&mut input)
+    }
+}
+
+/// `<path>.<extension>`
+fn with_extension_appended(path: &Path, extension: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".");
+    name.push(extension);
+    PathBuf::from(name)
+}
+
+/// `path` without `extension`, or `<path>.dec` if it has another one
+fn decrypted_path(path: &Path, extension: &str) -> PathBuf {
+    if path.extension().is_some_and(|ext| ext == extension) {
+        path.with_extension("")
+    } else {
+        with_extension_appended(path, "dec")
+    }
+}
+
+/// Create `output_path` (0600, never overwriting), fill it with `write` and flush it
+/// to disk. On failure the partial output, possibly unauthenticated plaintext, is shredded.
+fn write_new_file(
+    output_path: &Path,
+    write: impl FnOnce(&mut File) -> Result<(), String>,
+) -> Result<PathBuf, String> {
+    let mut output = create_private_file(output_path)?;
+    let result = write(&mut output).and_then(|_| {
+        output
+            .sync_all()
+            .map_err(|e| format!("{}: {}", output_path.display(), e))
+    });
+    drop(output);
+
+    match result {
+        Ok(()) => Ok(output_path.to_path_buf()),
+        Err(e) => {
+            let _ = shred_file(output_path);
+            Err(e)
+        }
+    }
+}
+
+/// Overwrite `path` with random data, flush it to disk and unlink it.
+/// On SSDs and copy-on-write filesystems old blocks may survive; this is best effort.
+pub fn shred_file(path: &Path) -> Result<(), String> {
+    let size = fs::metadata(path)
+        .map_err(|e| format!("{}: {}", path.display(), e))?
+        .len();
+    let mut file = OpenOptions::new()
+        .write(true)
+        .open(path)
+        .map_err(|e| format!("{}: {}", path.display(), e))?;
+
+    let mut noise = vec![0u8; CHUNK_SIZE];
+    let mut remaining = size;
+    while remaining > 0 {
+        let n = remaining.min(CHUNK_SIZE as u64) as usize;
+        OsRng.fill_bytes(&mut noise[..n]);
+        file.write_all(&noise[..n])
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+        remaining -= n as u64;
+    }
+    file.sync_all()
+        .map_err(|e| format!("{}: {}", path.display(), e))?;
+    drop(file);
+
+    fs::remove_file(path).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// Encrypt `input` chunk by chunk. A chunk shorter than `CHUNK_SIZE` (possibly
+/// empty) is always written last, so truncation is detected on decryption.
+fn encrypt_stream(
+    cipher: ChaCha20Poly1305,
+    header: &[u8; HEADER_LEN],
+    input: &mut impl Read,
+    output: &mut File,
+    input_path: &Path,
+    output_path: &Path,
+) -> Result<(), String> {
+    let mut encryptor = EncryptorBE32::from_aead(cipher, header[MAGIC.len() + SALT_LEN..].into());
+    let mut buffer = vec![0u8; CHUNK_SIZE];
+
+    let result = loop {
+        let n = match read_full(input, &mut buffer) {
+            Ok(n) => n,
+            Err(e) => break Err(format!("{}: {}", input_path.display(), e)),
+        };
+        let payload = Payload {
+            msg: &buffer[..n],
+            aad: header,
+        };
+        if n < CHUNK_SIZE {
+            break encryptor
+                .encrypt_last(payload)
+                .map_err(|e| format!("Encryption failed: {}", e))
+                .and_then(|chunk| write_chunk(output, &chunk, output_path));
+        }
+        if let Err(e) = encryptor
+            .encrypt_next(payload)
+            .map_err(|e| format!("Encryption failed: {}", e))
+            .and_then(|chunk| write_chunk(output, &chunk, output_path))
+        {
+            break Err(e);
+        }
+    };
+
+    buffer.zeroize();
+    result
+}
+
+/// Decrypt `input` chunk by chunk, stopping at the first chunk that fails to authenticate
+fn decrypt_stream(
+    cipher: ChaCha20Poly1305,
+    header: &[u8; HEADER_LEN],
+    input: &mut File,
+    output: &mut File,
+    input_path: &Path,
+    output_path: &Path,
+) -> Result<(), String> {
+    let mut decryptor = DecryptorBE32::from_aead(cipher, header[MAGIC.len() + SALT_LEN..].into());
+    let mut buffer = vec![0u8; CHUNK_SIZE + TAG_LEN];
+
+    loop {
+        let n = read_full(input, &mut buffer)
+            .map_err(|e| format!("{}: {}", input_path.display(), e))?;
+        let payload = Payload {
+            msg: &buffer[..n],
+            aad: header,
+        };
+        if n < buffer.len() {
+            let chunk = decryptor.decrypt_last(payload);
+            return write_plaintext(output, chunk, input_path, output_path);
+        }
+        let chunk = decryptor.decrypt_next(payload);
+        write_plaintext(output, chunk, input_path, output_path)?;
+    }
+}
+
+/// Write one decrypted chunk, or report the authentication failure
+fn write_plaintext(
+    output: &mut File,
+    decrypted: chacha20poly1305::aead::Result<Vec<u8>>,
+    input_path: &Path,
+    output_path: &Path,
+) -> Result<(), String> {
+    let mut chunk = decrypted.map_err(|_| {
+        format!(
+            "{}: decryption failed (wrong passphrase or corrupted/truncated file).",
+            input_path.display()
+        )
+    })?;
+    let written = output
+        .write_all(&chunk)
+        .map_err(|e| format!("{}: {}", output_path.display(), e));
+    chunk.zeroize();
+    written
+}
+
+fn write_chunk(output: &mut File, chunk: &[u8], path: &Path) -> Result<(), String> {
+    output
+        .write_all(chunk)
+        .map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// Fill `buffer` as far as possible; returns fewer bytes only at end of file
+pub fn read_full(reader: &mut impl Read, buffer: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match reader.read(&mut buffer[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
@@ -0,0 +1,267 @@
+/// Cryptographically secure password generation for ::genpass and ::genphrase
+/// Random charset passwords, pronounceable syllable passwords and diceware passphrases,
+/// all drawn uniformly from the OS RNG
+use chacha20poly1305::aead::OsRng;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use zeroize::Zeroize;
+
+/// Default length of charset passwords
+const DEFAULT_LENGTH: usize = 24;
+
+/// Default number of diceware words: about 77 bits from the built-in list, the length
+/// its authors recommend
+const DEFAULT_WORDS: usize = 6;
+
+/// Most words accepted by ::genphrase
+const MAX_WORDS: usize = 64;
+
+/// Built-in diceware wordlist (EFF large list: 7776 words, about 12.9 bits each)
+pub const EMBEDDED_WORDLIST: &str = include_str!("../assets/eff_large_wordlist.txt");
+
+/// Fewest usable words accepted from a wordlist
+const MIN_WORDLIST_SIZE: usize = 1024;
+
+const LOWER: &[u8] = b"abcdefghijklmnopqrstuvwxyz";
+const UPPER: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+const DIGITS: &[u8] = b"0123456789";
+const SYMBOLS: &[u8] = b"!#$%&()*+,-./:;<=>?@[]^_{}~";
+
+const CONSONANTS: &[u8] = b"bcdfghjklmnprstvwxz";
+const VOWELS: &[u8] = b"aeiou";
+
+/// What kind of secret to generate
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GenpassMode {
+    /// Random characters from the selected classes
+    Charset,
+    /// Alternating consonant/vowel syllables
+    Pronounceable,
+    /// Words drawn from a wordlist
+    Diceware,
+}
+
+/// Parsed `::genpass` options
+pub struct GenpassOptions {
+    pub mode: GenpassMode,
+    /// Characters, or words for diceware
+    pub length: usize,
+    /// Character classes for charset mode: `a` lower, `A` upper, `0` digits, `!` symbols
+    pub classes: String,
+    /// Diceware wordlist file; the embedded list when None
+    pub wordlist: Option<PathBuf>,
+    /// Print instead of sending to the encrypted clipboard
+    pub show: bool,
+}
+
+impl GenpassOptions {
+    pub fn parse(args: &str) -> Result<Self, String> {
+        let mut options = GenpassOptions {
+            mode: GenpassMode::Charset,
+            length: 0,
+            classes: "aA0!".to_string(),
+            wordlist: None,
+            show: false,
+        };
+
+        let mut words = args.split_whitespace();
+        while let Some(word) = words.next() {
+            match word {
+                "-l" | "--length" => {
+                    options.length = words
+                        .next()
+                        .and_then(|n| n.parse().ok())
+                        .filter(|n| (4..=1024).contains(n))
+                        .ok_or("genpass: -l expects a length between 4 and 1024.")?;
+                }
+                "-c" | "--charset" => {
+                    let classes = words.next().unwrap_or("");
+                    if classes.is_empty() || !classes.chars().all(|c| "aA0!".contains(c)) {
+                        return Err(
+                            "genpass: -c expects classes from 'a' (lower), 'A' (upper), '0' (digits), '!' (symbols)."
+                                .to_string(),
+                        );
+                    }
+                    options.classes = classes.to_string();
+                }
+                "-p" | "--pronounceable" => options.mode = GenpassMode::Pronounceable,
+                "-d" | "--diceware" => options.mode = GenpassMode::Diceware,
+                "-w" | "--words" => {
+                    options.wordlist = Some(
+                        words
+                            .next()
+                            .map(PathBuf::from)
+                            .ok_or("genpass: -w expects a wordlist file.")?,
+                    );
+                }
+                "--show" => options.show = true,
+                other => return Err(format!("genpass: unknown option '{}'.", other)),
+            }
+        }
+
+        if options.length == 0 {
+            options.length = match options.mode {
+                GenpassMode::Diceware => DEFAULT_WORDS,
+                _ => DEFAULT_LENGTH,
+            };
+        }
+        Ok(options)
+    }
+
+    /// Options for `::genphrase [words] [-w <wordlist>] [--show]`
+    pub fn parse_phrase(args: &str) -> Result<Self, String> {
+        let mut options = GenpassOptions {
+            mode: GenpassMode::Diceware,
+            length: DEFAULT_WORDS,
+            classes: String::new(),
+            wordlist: None,
+            show: false,
+        };
+
+        let mut words = args.split_whitespace();
+        while let Some(word) = words.next() {
+            match word {
+                "-w" | "--words" => {
+                    options.wordlist = Some(
+                        words
+                            .next()
+                            .map(PathBuf::from)
+                            .ok_or("genphrase: -w expects a wordlist file.")?,
+                    );
+                }
+                "--show" => options.show = true,
+                count => {
+                    options.length = count
+                        .parse()
+                        .ok()
+                        .filter(|n| (3..=MAX_WORDS).contains(n))
+                        .ok_or_else(|| {
+                            format!(
+                                "genphrase: expected a word count between 3 and {}.",
+                                MAX_WORDS
+                            )
+                        })?;
+                }
+            }
+        }
+        Ok(options)
+    }
+}
+
+/// Generate a secret. Returns it with its entropy in bits.
+pub fn generate(options: &GenpassOptions) -> Result<(String, f64), String> {
+    match options.mode {
+        GenpassMode::Charset => Ok(charset_password(options.length, &options.classes)),
+        GenpassMode::Pronounceable => Ok(pronounceable_password(options.length)),
+        GenpassMode::Diceware => diceware_passphrase(options),
+    }
+}
+
+/// Uniform random characters, with at least one from every selected class
+fn charset_password(length: usize, classes: &str) -> (String, f64) {
+    let sets: Vec<&[u8]> = classes
+        .chars()
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .map(|class| match class {
+            'a' => LOWER,
+            'A' => UPPER,
+            '0' => DIGITS,
+            _ => SYMBOLS,
+        })
+        .collect();
+    let alphabet: Vec<u8> = sets.concat();
+
+    let mut rng = OsRng;
+    // Rejection sampling keeps every accepted password uniformly distributed
+    let password = loop {
+        let mut candidate: String = (0..length)
+            .map(|_| *alphabet.choose(&mut rng).unwrap() as char)
+            .collect();
+        if length < sets.len()
+            || sets
+                .iter()
+                .all(|set| candidate.bytes().any(|b| set.contains(&b)))
+        {
+            break candidate;
+        }
+        candidate.zeroize();
+    };
+
+    let entropy = length as f64 * (alphabet.len() as f64).log2();
+    (password, entropy)
+}
+
+/// Alternating consonants and vowels, with a capital and a digit spliced in
+fn pronounceable_password(length: usize) -> (String, f64) {
+    let mut rng = OsRng;
+    let mut bytes: Vec<u8> = (0..length)
+        .map(|i| {
+            let set = if i % 2 == 0 { CONSONANTS } else { VOWELS };
+            *set.choose(&mut rng).unwrap()
+        })
+        .collect();
+
+    let capital = rng.gen_range(0..length);
+    bytes[capital] = bytes[capital].to_ascii_uppercase();
+    let digit = (capital + 1 + rng.gen_range(0..length - 1)) % length;
+    bytes[digit] = *DIGITS.choose(&mut rng).unwrap();
+
+    let consonants = length.div_ceil(2) as f64;
+    let vowels = (length / 2) as f64;
+    let entropy = consonants * (CONSONANTS.len() as f64).log2()
+        + vowels * (VOWELS.len() as f64).log2()
+        + (length as f64).log2()
+        + ((length - 1) as f64).log2()
+        + (DIGITS.len() as f64).log2();
+
+    let password = String::from_utf8(bytes).expect("ASCII only");
+    (password, entropy)
+}
+
+/// The words of a wordlist that can be joined with `-`: lowercase ASCII, 3-9 letters,
+/// deduplicated. Lines may carry dice numbers in front of the word, as in the EFF lists.
+pub fn usable_words(contents: &str) -> Vec<&str> {
+    contents
+        .lines()
+        .filter_map(|line| line.split_whitespace().last())
+        .filter(|w| (3..=9).contains(&w.len()) && w.bytes().all(|b| b.is_ascii_lowercase()))
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect()
+}
+
+/// Words drawn uniformly from a wordlist (see `usable_words`)
+fn diceware_passphrase(options: &GenpassOptions) -> Result<(String, f64), String> {
+    let contents = match &options.wordlist {
+        Some(path) => {
+            fs::read_to_string(path).map_err(|e| format!("genpass: {}: {}", path.display(), e))?
+        }
+        None => EMBEDDED_WORDLIST.to_string(),
+    };
+    let words = usable_words(&contents);
+    if words.len() < MIN_WORDLIST_SIZE {
+        return Err(format!(
+            "genpass: {}: only {} usable words (need {}).",
+            options
+                .wordlist
+                .as_deref()
+                .unwrap_or(Path::new("built-in wordlist"))
+                .display(),
+            words.len(),
+            MIN_WORDLIST_SIZE
+        ));
+    }
+
+    let mut rng = OsRng;
+    let passphrase = (0..options.length)
+        .map(|_| *words.choose(&mut rng).unwrap())
+        .collect::<Vec<_>>()
+        .join("-");
+
+    let entropy = options.length as f64 * (words.len() as f64).log2();
+    Ok((passphrase, entropy))
+}
@@ -0,0 +1,171 @@
+/// Opt-in OpenPGP backend (::cp --gpg, ::encrypt-file --gpg) that spawns gpg
+/// The child gets a sanitized environment: only what gpg and gpg-agent need is passed
+/// through (no LD_PRELOAD, proxies, or the rest of the shell's variables), and
+/// plaintext only ever travels over pipes, never argv or temp files.
+use std::env;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use zeroize::Zeroize;
+
+use crate::sandbox::find_in_path;
+
+/// Armor header of an encrypted OpenPGP message
+pub const PGP_ARMOR_BEGIN: &str = "-----BEGIN PGP MESSAGE-----";
+
+/// Variables passed through to gpg: key ring location, agent socket, pinentry display
+const PASSTHROUGH_ENV: &[&str] = &[
+    "HOME",
+    "GNUPGHOME",
+    "GPG_TTY",
+    "TERM",
+    "DISPLAY",
+    "WAYLAND_DISPLAY",
+    "XAUTHORITY",
+    "XDG_RUNTIME_DIR",
+    "DBUS_SESSION_BUS_ADDRESS",
+];
+
+/// PATH given to gpg (it spawns gpg-agent and pinentry)
+const SAFE_PATH: &str = "/usr/local/bin:/usr/bin:/bin";
+
+/// True if `data` starts like an encrypted OpenPGP message (armored, or a binary
+/// public-key / symmetric-key encrypted session key packet)
+pub fn is_pgp(data: &[u8]) -> bool {
+    data.trim_ascii_start()
+        .starts_with(PGP_ARMOR_BEGIN.as_bytes())
+        || matches!(data.first(), Some(0x84..=0x87 | 0x8c..=0x8f | 0xc1 | 0xc3))
+}
+
+/// Encrypt `plaintext` to `recipient` (key ID, fingerprint or user ID) as ASCII armor
+pub fn encrypt(recipient: &str, plaintext: &[u8]) -> Result<String, String> {
+    let mut command = gpg_command(&["--armor", "--encrypt", "--recipient", recipient])?;
+    let armored = run_piped(&mut command, plaintext)?;
+    String::from_utf8(armored).map_err(|_| "gpg: unexpected non-ASCII output.".to_string())
+}
+
+/// Decrypt an OpenPGP message (armored or binary); gpg-agent asks for the passphrase
+pub fn decrypt(ciphertext: &[u8]) -> Result<Vec<u8>, String> {
+    let mut command = gpg_command(&["--decrypt"])?;
+    run_piped(&mut command, ciphertext)
+}
+
+/// Encrypt the file `input` to `recipient`, streaming gpg's binary output into `output`
+pub fn encrypt_file(recipient: &str, input: File, output: &mut File) -> Result<(), String> {
+    let command = gpg_command(&["--encrypt", "--recipient", recipient])?;
+    run_streamed(command, input, output)
+}
+
+/// Decrypt the file `input`, streaming the plaintext into `output`
+pub fn decrypt_file(input: File, output: &mut File) -> Result<(), String> {
+    let command = gpg_command(&["--decrypt"])?;
+    run_streamed(command, input, output)
+}
+
+/// gpg in batch mode with a whitelisted environment
+fn gpg_command(args: &[&str]) -> Result<Command, String> {
+    // Resolve the binary with the user's PATH before it is replaced
+    let gpg = find_in_path("gpg")
+        .or_else(|| find_in_path("gpg2"))
+        .ok_or("gpg: not installed.")?;
+
+    let mut command = Command::new(gpg);
+    command.env_clear();
+    for name in PASSTHROUGH_ENV {
+        if let Some(value) = env::var_os(name) {
+            command.env(name, value);
+        }
+    }
+    // The agent needs the terminal to show pinentry
+    if env::var_os("GPG_TTY").is_none() {
+        if let Some(tty) = stdin_tty() {
+            command.env("GPG_TTY", tty);
+        }
+    }
+    command
+        .env("PATH", SAFE_PATH)
+        .env("LC_ALL", "C")
+        .args([
+            "--batch",
+            "--no-tty",
+            "--quiet",
+            // Never look keys up over the network (WKD/keyservers leak who you write to)
+            "--no-auto-key-retrieve",
+            "--auto-key-locate",
+            "local",
+            "--output",
+            "-",
+        ])
+        .args(args);
+    Ok(command)
+}
+
+/// The terminal on our stdin, if any
+fn stdin_tty() -> Option<PathBuf> {
+    fs::read_link("/proc/self/fd/0")
+        .ok()
+        .filter(|path| path.starts_with("/dev/pts") || path.starts_with("/dev/tty"))
+}
+
+/// Feed `input` through gpg and collect its stdout
+fn run_piped(command: &mut Command, input: &[u8]) -> Result<Vec<u8>, String> {
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("gpg: {}", e))?;
+
+    // Write from a separate thread so a full stdout pipe can't deadlock us
+    let mut stdin = child.stdin.take().ok_or("gpg: no stdin.")?;
+    let mut pending = input.to_vec();
+    let writer = thread::spawn(move || {
+        let _ = stdin.write_all(&pending);
+        pending.zeroize();
+    });
+
+    let mut output = Vec::new();
+    let read = child
+        .stdout
+        .take()
+        .map(|mut stdout| stdout.read_to_end(&mut output));
+    let _ = writer.join();
+
+    if let Err(e) = finish(child).and(read.transpose().map_err(|e| format!("gpg: {}", e))) {
+        output.zeroize();
+        return Err(e);
+    }
+    Ok(output)
+}
+
+/// Run gpg with a file on stdin and `output` as stdout
+fn run_streamed(mut command: Command, input: File, output: &mut File) -> Result<(), String> {
+    let stdout = output.try_clone().map_err(|e| format!("gpg: {}", e))?;
+    let child = command
+        .stdin(Stdio::from(input))
+        .stdout(Stdio::from(stdout))
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("gpg: {}", e))?;
+    finish(child)
+}
+
+/// Wait for gpg, turning a failure into its (first lines of) stderr
+fn finish(child: Child) -> Result<(), String> {
+    let result = child
+        .wait_with_output()
+        .map_err(|e| format!("gpg: {}", e))?;
+    if result.status.success() {
+        return Ok(());
+    }
+
+    let stderr = String::from_utf8_lossy(&result.stderr);
+    let reason: Vec<&str> = stderr.lines().filter(|l| !l.is_empty()).take(3).collect();
+    if reason.is_empty() {
+        Err(format!("gpg exited with {}", result.status))
+    } else {
+        Err(reason.join("\r\n"))
+    }
+}
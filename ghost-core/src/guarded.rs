@@ -0,0 +1,162 @@
+/// Guard-page protected, mlocked allocations for secrets kept all session
+/// Each buffer gets pages of its own from memsec (libsodium's `sodium_malloc` scheme):
+/// a PROT_NONE guard page on either side, a canary checked when it is freed, and an
+/// mlock so it never reaches swap; on Linux it is also left out of core dumps. Outside
+/// `expose` the buffer's own pages are PROT_NONE too, so a stray read, an overflow
+/// from a neighbouring allocation or a use after free faults instead of leaking. A
+/// buffer costs four pages of address space and one locked page, which suits keys and
+/// history lines, not bulk data.
+use memsec::Prot;
+use std::ops::Range;
+use std::ptr::NonNull;
+use std::sync::Mutex;
+use zeroize::Zeroize;
+
+#[cfg(target_os = "linux")]
+use crate::security::disable_core_dump;
+
+/// Bytes in a guarded allocation, readable only inside `expose`
+pub struct GuardedBytes {
+    ptr: NonNull<[u8]>,
+    /// Open `expose` calls; the pages are readable while this is nonzero
+    readers: Mutex<usize>,
+}
+
+// The pointer is owned, and protection changes are serialized by `readers`
+unsafe impl Send for GuardedBytes {}
+unsafe impl Sync for GuardedBytes {}
+
+impl GuardedBytes {
+    /// Copy `bytes` into a fresh guarded allocation
+    pub fn new(bytes: &[u8]) -> Self {
+        let mut ptr = unsafe { memsec::malloc_sized(bytes.len()) }
+            .expect("guarded allocation failed: out of memory");
+        unsafe {
+            ptr.as_mut().copy_from_slice(bytes);
+            memsec::mprotect(ptr, Prot::NoAccess);
+        }
+        let guarded = GuardedBytes {
+            ptr,
+            readers: Mutex::new(0),
+        };
+        // madvise wants a page-aligned start; the contents end on the last data page
+        #[cfg(target_os = "linux")]
+        {
+            let page = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as usize;
+            let region = guarded.region();
+            let start = region.start & !(page - 1);
+            let _ = disable_core_dump(start as *const u8, region.end.max(start + 1) - start);
+        }
+        guarded
+    }
+
+    pub fn len(&self) -> usize {
+        self.ptr.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Address range of the contents, for ::meminfo; the pages are not touched
+    pub fn region(&self) -> Range<usize> {
+        let start = self.ptr.as_ptr() as *const u8 as usize;
+        start..start + self.len()
+    }
+
+    /// Run `f` with the contents readable; they are protected again afterwards,
+    /// even if `f` panics
+    pub fn expose<R>(&self, f: impl FnOnce(&[u8]) -> R) -> R {
+        let _open = Exposed::new(self);
+        f(unsafe { self.ptr.as_ref() })
+    }
+
+    fn set_protection(&self, opening: bool) {
+        let mut readers = self.readers.lock().unwrap_or_else(|e| e.into_inner());
+        if opening {
+            *readers += 1;
+            if *readers == 1 {
+                unsafe { memsec::mprotect(self.ptr, Prot::ReadOnly) };
+            }
+        } else {
+            *readers -= 1;
+            if *readers == 0 {
+                unsafe { memsec::mprotect(self.ptr, Prot::NoAccess) };
+            }
+        }
+    }
+}
+
+impl Clone for GuardedBytes {
+    fn clone(&self) -> Self {
+        self.expose(GuardedBytes::new)
+    }
+}
+
+impl Drop for GuardedBytes {
+    fn drop(&mut self) {
+        unsafe {
+            memsec::mprotect(self.ptr, Prot::ReadWrite);
+            self.ptr.as_mut().zeroize();
+            memsec::free(self.ptr);
+        }
+    }
+}
+
+/// Keeps a buffer readable for as long as it lives
+struct Exposed<'a>(&'a GuardedBytes);
+
+impl<'a> Exposed<'a> {
+    fn new(bytes: &'a GuardedBytes) -> Self {
+        bytes.set_protection(true);
+        Exposed(bytes)
+    }
+}
+
+impl Drop for Exposed<'_> {
+    fn drop(&mut self) {
+        self.0.set_protection(false);
+    }
+}
+
+/// UTF-8 text in a guarded allocation
+#[derive(Clone)]
+pub struct GuardedString(GuardedBytes);
+
+impl GuardedString {
+    /// Move `text` into a guarded allocation, zeroizing the original
+    pub fn new(mut text: String) -> Self {
+        let guarded = GuardedString(GuardedBytes::new(text.as_bytes()));
+        text.zeroize();
+        guarded
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn region(&self) -> Range<usize> {
+        self.0.region()
+    }
+
+    /// Run `f` with the text readable
+    pub fn expose<R>(&self, f: impl FnOnce(&str) -> R) -> R {
+        self.0
+            .expose(|bytes| f(std::str::from_utf8(bytes).unwrap_or_default()))
+    }
+
+    /// An ordinary copy of the text, for code that needs to own it
+    pub fn to_plain(&self) -> String {
+        self.expose(str::to_string)
+    }
+}
+
+impl PartialEq for GuardedString {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.expose(|a| other.expose(|b| a == b))
+    }
+}
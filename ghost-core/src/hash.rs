@@ -0,0 +1,125 @@
+/// In-process hashing for ::hash (no sha256sum/b3sum process shows up in audit logs)
+/// Files are streamed from disk; expected digests are compared in constant time
+use sha2::{Digest, Sha256, Sha512};
+use std::fs::File;
+use std::path::Path;
+use subtle::ConstantTimeEq;
+
+use crate::filecrypt::read_full;
+
+/// Bytes read from a file per update
+const READ_SIZE: usize = 64 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashAlgorithm {
+    Sha256,
+    Sha512,
+    Blake3,
+}
+
+impl HashAlgorithm {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "sha256" => Some(HashAlgorithm::Sha256),
+            "sha512" => Some(HashAlgorithm::Sha512),
+            "blake3" => Some(HashAlgorithm::Blake3),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            HashAlgorithm::Sha256 => "SHA256",
+            HashAlgorithm::Sha512 => "SHA512",
+            HashAlgorithm::Blake3 => "BLAKE3",
+        }
+    }
+}
+
+/// Incremental hasher for any supported algorithm
+enum Hasher {
+    Sha256(Sha256),
+    Sha512(Sha512),
+    Blake3(Box<blake3::Hasher>),
+}
+
+impl Hasher {
+    fn new(algorithm: HashAlgorithm) -> Self {
+        match algorithm {
+            HashAlgorithm::Sha256 => Hasher::Sha256(Sha256::new()),
+            HashAlgorithm::Sha512 => Hasher::Sha512(Sha512::new()),
+            HashAlgorithm::Blake3 => Hasher::Blake3(Box::new(blake3::Hasher::new())),
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        match self {
+            Hasher::Sha256(hasher) => hasher.update(data),
+            Hasher::Sha512(hasher) => hasher.update(data),
+            Hasher::Blake3(hasher) => {
+                hasher.update(data);
+            }
+        }
+    }
+
+    fn finalize(self) -> Vec<u8> {
+        match self {
+            Hasher::Sha256(hasher) => hasher.finalize().to_vec(),
+            Hasher::Sha512(hasher) => hasher.finalize().to_vec(),
+            Hasher::Blake3(hasher) => hasher.finalize().as_bytes().to_vec(),
+        }
+    }
+}
+
+/// Hash `target`: the file at that path if there is one, otherwise the text itself.
+/// Returns the digest and a label for display.
+pub fn digest_target(algorithm: HashAlgorithm, target: &str) -> Result<(Vec<u8>, String), String> {
+    let mut hasher = Hasher::new(algorithm);
+    let label = feed_target(target, |data| hasher.update(data))?;
+    Ok((hasher.finalize(), label))
+}
+
+/// Stream `target` into `update`: the file at that path if there is one, otherwise
+/// the text itself. Returns a label for display.
+pub fn feed_target(target: &str, mut update: impl FnMut(&[u8])) -> Result<String, String> {
+    let path = Path::new(target);
+    if !path.is_file() {
+        update(target.as_bytes());
+        return Ok(format!("(text, {} bytes)", target.len()));
+    }
+
+    let mut file = File::open(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let mut buffer = vec![0u8; READ_SIZE];
+    loop {
+        let n =
+            read_full(&mut file, &mut buffer).map_err(|e| format!("{}: {}", path.display(), e))?;
+        update(&buffer[..n]);
+        if n < READ_SIZE {
+            return Ok(target.to_string());
+        }
+    }
+}
+
+/// Compare a digest against an expected hex digest in constant time
+pub fn digest_matches(digest: &[u8], expected_hex: &str) -> Result<bool, String> {
+    let expected = from_hex(expected_hex).ok_or("Expected digest is not valid hex.")?;
+    Ok(bool::from(digest.ct_eq(&expected)))
+}
+
+/// Lowercase hex encoding
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| {
+            hex.get(i..i + 2)
+                .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+        })
+        .collect()
+}
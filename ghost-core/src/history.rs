@@ -0,0 +1,217 @@
+/// History entries and the opt-in encrypted history file for `::history persist on`
+/// The history is RAM-only unless the user asks otherwise. When they do, every change is
+/// written to `$XDG_DATA_HOME/gsh/history.enc` sealed with ChaCha20-Poly1305 under the
+/// history-file subkey, which is only reproducible from the same `--master-passphrase`.
+/// The file is restored at the next launch; its decrypted contents are mlocked while
+/// they are parsed. Every entry lives in a guarded allocation (see `guarded`), mlocked
+/// and unreadable outside `expose`. `expand` does the bash-style
+/// `!!`, `!n`, `!-n` and `!prefix` history expansion of a line before it runs.
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use zeroize::Zeroize;
+
+use crate::clipboard::{create_private_file, open_bytes, seal_bytes};
+use crate::config::data_file;
+use crate::guarded::GuardedString;
+use crate::keys::SubKey;
+use crate::security::{disable_core_dump, lock_memory};
+
+/// File format tag, followed by the nonce and the ciphertext
+const MAGIC: &[u8; 8] = b"GSHHIST1";
+
+/// One command line in the history, with when it ran and how it ended.
+/// Time and status are unknown for entries from `::history import`.
+pub struct HistoryEntry {
+    pub command: GuardedString,
+    pub time: Option<SystemTime>,
+    /// The child's exit status for external commands, 0 or 1 for ghost commands
+    pub status: Option<i32>,
+}
+
+impl HistoryEntry {
+    pub fn new(command: String, time: Option<SystemTime>, status: Option<i32>) -> Self {
+        HistoryEntry {
+            command: GuardedString::new(command),
+            time,
+            status,
+        }
+    }
+
+    /// `<unix seconds>\t<status>\t<command>`, `-` for an unknown field: the line stored
+    /// in the history file
+    fn serialize(&self) -> String {
+        let seconds = self
+            .time
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map_or_else(|| "-".to_string(), |elapsed| elapsed.as_secs().to_string());
+        let status = self
+            .status
+            .map_or_else(|| "-".to_string(), |status| status.to_string());
+        self.command
+            .expose(|command| format!("{}\t{}\t{}", seconds, status, command))
+    }
+
+    /// Inverse of `serialize`; a line without the two fields is a bare command
+    fn parse(line: &str) -> Self {
+        fn field<T: std::str::FromStr>(text: &str) -> Result<Option<T>, T::Err> {
+            match text {
+                "-" => Ok(None),
+                text => text.parse().map(Some),
+            }
+        }
+        let mut fields = line.splitn(3, '\t');
+        if let (Some(seconds), Some(status), Some(command)) =
+            (fields.next(), fields.next(), fields.next())
+        {
+            if let (Ok(seconds), Ok(status)) = (field::<u64>(seconds), field::<i32>(status)) {
+                let time = seconds.map(|seconds| UNIX_EPOCH + Duration::from_secs(seconds));
+                return HistoryEntry::new(command.to_string(), time, status);
+            }
+        }
+        HistoryEntry::new(line.to_string(), None, None)
+    }
+}
+
+/// `$XDG_DATA_HOME/gsh/history.enc`, falling back to `~/.local/share/gsh/history.enc`
+pub fn history_path() -> Option<PathBuf> {
+    data_file("history.enc")
+}
+
+/// Encrypt `entries` to `path`, replacing the previous file atomically
+pub fn save_history(path: &Path, key: &SubKey, entries: &[HistoryEntry]) -> Result<(), String> {
+    let mut lines: Vec<String> = entries.iter().map(HistoryEntry::serialize).collect();
+    let mut plaintext = lines.join("\n").into_bytes();
+    lines.iter_mut().for_each(Zeroize::zeroize);
+    let sealed = key.expose(|key| seal_bytes(key, &plaintext));
+    plaintext.zeroize();
+    let (nonce, ciphertext) = sealed?;
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+    }
+    let partial = path.with_extension("enc.tmp");
+    let _ = fs::remove_file(&partial);
+    let mut bytes = Vec::with_capacity(MAGIC.len() + nonce.len() + ciphertext.len());
+    bytes.extend_from_slice(MAGIC);
+    bytes.extend_from_slice(&nonce);
+    bytes.extend_from_slice(&ciphertext);
+    std::io::Write::write_all(&mut create_private_file(&partial)?, &bytes)
+        .and_then(|_| fs::rename(&partial, path))
+        .map_err(|e| {
+            let _ = fs::remove_file(&partial);
+            format!("{}: {}", path.display(), e)
+        })
+}
+
+/// Decrypt the history at `path`, oldest entry first
+pub fn load_history(path: &Path, key: &SubKey) -> Result<Vec<HistoryEntry>, String> {
+    let bytes = fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let body = bytes
+        .strip_prefix(MAGIC.as_slice())
+        .filter(|body| body.len() > 12)
+        .ok_or_else(|| format!("{}: not a Ghost Shell history file.", path.display()))?;
+    let (nonce, ciphertext) = body.split_at(12);
+    let plaintext = key
+        .expose(|key| open_bytes(key, nonce, ciphertext))
+        .map_err(|_| {
+            format!(
+                "{}: cannot decrypt; was it saved under another passphrase?",
+                path.display()
+            )
+        })?;
+    let bytes = plaintext.expose();
+    let _ = lock_memory(bytes.as_ptr(), bytes.len());
+    let _ = disable_core_dump(bytes.as_ptr(), bytes.len());
+
+    match std::str::from_utf8(bytes) {
+        Ok(text) => Ok(text
+            .split('\n')
+            .filter(|line| !line.trim().is_empty())
+            .map(HistoryEntry::parse)
+            .collect()),
+        Err(_) => Err(format!("{}: history is not valid UTF-8.", path.display())),
+    }
+}
+
+/// Characters that end a `!prefix` designator
+const DESIGNATOR_END: &[char] = &[';', '|', '&', '(', ')', '<', '>', '"', '\''];
+
+/// Expand history references in `line`: `!!` (the last command), `!n` (entry n, as
+/// `::history` numbers them), `!-n` (n commands back) and `!prefix` (the latest command
+/// starting with prefix). A `!` inside single quotes, after a backslash, or followed by
+/// a blank, `=` or `(` stays as is. None when the line has no reference.
+pub fn expand(line: &str, history: &[HistoryEntry]) -> Result<Option<String>, String> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut expanded = String::with_capacity(line.len());
+    let mut quoted = false;
+    let mut changed = false;
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '\\' if i + 1 < chars.len() => {
+                expanded.push(c);
+                expanded.push(chars[i + 1]);
+                i += 2;
+                continue;
+            }
+            '\'' => quoted = !quoted,
+            '!' if !quoted => {
+                let rest = &chars[i + 1..];
+                let (entry, length) = match rest.first() {
+                    None | Some(' ' | '\t' | '=' | '(' | '"') => (None, 0),
+                    Some('!') => (Some(history.last()), 1),
+                    // `a!&&b`, `hi!|cat`: nothing to designate, so the `!` is literal
+                    Some(c) if c.is_whitespace() || DESIGNATOR_END.contains(c) => (None, 0),
+                    Some(_) => {
+                        let length = rest
+                            .iter()
+                            .position(|c| c.is_whitespace() || DESIGNATOR_END.contains(c))
+                            .unwrap_or(rest.len());
+                        let designator: String = rest[..length].iter().collect();
+                        let entry = match designator.parse::<i64>() {
+                            Ok(n) if n > 0 => history.get(n as usize - 1),
+                            Ok(n) if n < 0 => history
+                                .len()
+                                .checked_sub(n.unsigned_abs() as usize)
+                                .and_then(|index| history.get(index)),
+                            Ok(_) => None,
+                            Err(_) => history.iter().rev().find(|entry| {
+                                entry
+                                    .command
+                                    .expose(|command| command.trim_start().starts_with(&designator))
+                            }),
+                        };
+                        (Some(entry), length)
+                    }
+                };
+                match entry {
+                    None => expanded.push(c),
+                    Some(Some(entry)) => {
+                        entry
+                            .command
+                            .expose(|command| expanded.push_str(command.trim()));
+                        changed = true;
+                    }
+                    Some(None) => {
+                        let designator: String = rest[..length].iter().collect();
+                        expanded.zeroize();
+                        return Err(format!("!{}: event not found", designator));
+                    }
+                }
+                i += 1 + length;
+                continue;
+            }
+            _ => {}
+        }
+        expanded.push(c);
+        i += 1;
+    }
+    if changed {
+        Ok(Some(expanded))
+    } else {
+        expanded.zeroize();
+        Ok(None)
+    }
+}
@@ -0,0 +1,440 @@
+/// Honeypot for unauthorized launches, armed with `::honeypot arm`
+/// Arming writes `$XDG_DATA_HOME/gsh/honeypot`: the number of passphrase attempts
+/// allowed at startup and an age recipient whose identity is derived from the master
+/// passphrase. Every later launch must then give that passphrase; after the last wrong
+/// attempt the launch drops into a fake shell that looks like a fresh session, answers
+/// from a made-up home directory and runs nothing. Each command typed there is sealed
+/// to the recipient and appended to `honeypot.log`, readable only in a session started
+/// with the real passphrase. Wrong passphrases themselves are never recorded, since
+/// they are as likely the owner's typos as an intruder's guesses.
+use base64::{engine::general_purpose, Engine as _};
+use crossterm::{
+    cursor::MoveTo,
+    event::{DisableBracketedPaste, Event, KeyCode, KeyEvent, KeyModifiers},
+    execute, queue,
+    terminal::{disable_raw_mode, Clear, ClearType},
+};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use subtle::ConstantTimeEq;
+use zeroize::Zeroize;
+
+use crate::age::{self, parse_recipient, AgeIdentity, Recipients, Unlock};
+use crate::config::data_file;
+use crate::keys::{KeyPurpose, SessionKeys};
+use crate::shell::{restore_title, sanitize_screen};
+use crate::term;
+
+/// Passphrase attempts before the fake shell, unless `::honeypot arm <n>` says otherwise
+pub const DEFAULT_ATTEMPTS: u32 = 3;
+
+/// Home directory the fake shell pretends to be in
+const FAKE_HOME: &str = "/home/user";
+
+/// Made-up home directory contents: (name, is a directory)
+const FAKE_FILES: &[(&str, bool)] = &[
+    ("Documents", true),
+    ("Downloads", true),
+    ("projects", true),
+    (".bashrc", false),
+    (".profile", false),
+    ("notes.txt", false),
+];
+
+/// An armed honeypot: attempts allowed and who can read the captures
+pub struct Honeypot {
+    attempts: u32,
+    recipient: String,
+}
+
+/// Where the arming file lives
+pub fn honeypot_path() -> Option<PathBuf> {
+    data_file("honeypot")
+}
+
+/// Where captured intruder sessions are appended
+pub fn capture_path() -> Option<PathBuf> {
+    data_file("honeypot.log")
+}
+
+/// The capture identity of a passphrase-derived session
+fn identity(keys: &SessionKeys) -> AgeIdentity {
+    keys.derive(KeyPurpose::Honeypot)
+        .expose(AgeIdentity::from_bytes)
+}
+
+/// Create `path` (mode 600 on Unix) or open it for appending
+fn open_private(path: &Path, append: bool) -> io::Result<fs::File> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut options = OpenOptions::new();
+    options.create(true);
+    if append {
+        options.append(true);
+    } else {
+        options.write(true).truncate(true);
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(path)
+}
+
+impl Honeypot {
+    /// The armed honeypot, if there is one
+    pub fn load() -> Result<Option<Self>, String> {
+        let Some(path) = honeypot_path().filter(|path| path.is_file()) else {
+            return Ok(None);
+        };
+        let text = fs::read_to_string(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let mut attempts = None;
+        let mut recipient = None;
+        for line in text.lines().map(str::trim) {
+            match line.split_once(' ') {
+                Some(("attempts", n)) => attempts = n.trim().parse().ok().filter(|n| *n > 0),
+                Some(("recipient", key)) => recipient = Some(key.trim().to_string()),
+                _ => {}
+            }
+        }
+        match (attempts, recipient) {
+            (Some(attempts), Some(recipient)) if parse_recipient(&recipient).is_ok() => {
+                Ok(Some(Honeypot {
+                    attempts,
+                    recipient,
+                }))
+            }
+            _ => Err(format!("{}: malformed honeypot file.", path.display())),
+        }
+    }
+
+    /// Arm the honeypot for `keys`, which must come from the master passphrase
+    pub fn arm(keys: &SessionKeys, attempts: u32) -> Result<PathBuf, String> {
+        let path = honeypot_path().ok_or("No home directory for the honeypot.")?;
+        let recipient = identity(keys).recipient();
+        open_private(&path, false)
+            .and_then(|mut file| {
+                write!(file, "attempts {}\nrecipient {}\n", attempts, recipient)?;
+                file.sync_all()
+            })
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+        Ok(path)
+    }
+
+    /// Remove the arming file; captures are kept. False if it was not armed.
+    pub fn disarm() -> Result<bool, String> {
+        match honeypot_path().filter(|path| path.is_file()) {
+            Some(path) => fs::remove_file(&path)
+                .map(|_| true)
+                .map_err(|e| format!("{}: {}", path.display(), e)),
+            None => Ok(false),
+        }
+    }
+
+    pub fn attempts(&self) -> u32 {
+        self.attempts
+    }
+
+    /// Whether `keys` were derived from the passphrase the honeypot was armed with
+    pub fn accepts(&self, keys: &SessionKeys) -> bool {
+        let recipient = identity(keys).recipient();
+        bool::from(recipient.as_bytes().ct_eq(self.recipient.as_bytes()))
+    }
+
+    /// Seal one event to the recipient and append it to the capture log
+    fn record(&self, event: &str) {
+        let Some(path) = capture_path() else {
+            return;
+        };
+        let Ok(key) = parse_recipient(&self.recipient) else {
+            return;
+        };
+        let seconds = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or(0);
+        let mut plaintext = format!("{}\t{}", seconds, event);
+        let mut sealed = Vec::new();
+        let result = age::encrypt(
+            &Recipients::Keys(vec![key]),
+            &mut plaintext.as_bytes(),
+            &mut sealed,
+        );
+        plaintext.zeroize();
+        if result.is_ok() {
+            let _ = open_private(&path, true).and_then(|mut file| {
+                writeln!(file, "{}", general_purpose::STANDARD.encode(&sealed))?;
+                file.sync_data()
+            });
+        }
+    }
+}
+
+/// Every captured event, oldest first, for a session with the arming passphrase
+pub fn read_captures(keys: &SessionKeys) -> Result<Vec<(SystemTime, String)>, String> {
+    let path = capture_path()
+        .filter(|path| path.is_file())
+        .ok_or("No honeypot captures.")?;
+    let text = fs::read_to_string(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let identities = [identity(keys)];
+    let mut events = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let broken = |what: &str| format!("{}: record {}: {}", path.display(), number + 1, what);
+        let sealed = general_purpose::STANDARD
+            .decode(line.trim())
+            .map_err(|_| broken("not a sealed record"))?;
+        let mut plaintext = Vec::new();
+        age::decrypt(
+            &Unlock::Identities(&identities),
+            &mut sealed.as_slice(),
+            &mut plaintext,
+        )
+        .map_err(|_| broken("does not decrypt (another passphrase?)"))?;
+        let event = String::from_utf8(plaintext)
+            .ok()
+            .and_then(|mut text| {
+                let (seconds, event) = text.split_once('\t')?;
+                let parsed = (
+                    UNIX_EPOCH + Duration::from_secs(seconds.parse().ok()?),
+                    event.to_string(),
+                );
+                text.zeroize();
+                Some(parsed)
+            })
+            .ok_or_else(|| broken("malformed record"))?;
+        events.push(event);
+    }
+    Ok(events)
+}
+
+/// Run the fake shell until the intruder leaves, then exit as a real session would
+pub fn trap(honeypot: &Honeypot) -> ! {
+    honeypot.record(&format!(
+        "launch: {} wrong passphrases, fake shell started",
+        honeypot.attempts
+    ));
+    let _ = fake_shell(honeypot);
+    honeypot.record("session ended");
+
+    let mut stdout = term::stdout();
+    let _ = execute!(stdout, DisableBracketedPaste);
+    let _ = sanitize_screen(&mut stdout);
+    let _ = restore_title(&mut stdout);
+    if !term::is_headless() {
+        let _ = disable_raw_mode();
+    }
+    let _ = write!(
+        stdout,
+        "\r\n[!] INITIATING SECURE SHUTDOWN...\r\n\
+        [*] Overwriting memory buffers... DONE.\r\n\
+        [*] All systems clear. Ghost Shell terminated.\r\n"
+    );
+    let _ = stdout.flush();
+    term::exit(0)
+}
+
+/// Read lines like the real prompt does and answer each from the made-up filesystem
+fn fake_shell(honeypot: &Honeypot) -> io::Result<()> {
+    let mut stdout = term::stdout();
+    let mut cwd = FAKE_HOME.to_string();
+    let mut history: Vec<String> = Vec::new();
+    let mut line = String::new();
+    let prompt = |cwd: &str| {
+        let dir = cwd.rsplit('/').find(|part| !part.is_empty()).unwrap_or("/");
+        format!("[A] gsh {}>> ", dir)
+    };
+    write!(stdout, "{}", prompt(&cwd))?;
+    stdout.flush()?;
+    loop {
+        let event = match term::read_event() {
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+            event => event?,
+        };
+        let Event::Key(KeyEvent {
+            code, modifiers, ..
+        }) = event
+        else {
+            continue;
+        };
+        match code {
+            KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => {
+                line.clear();
+                write!(stdout, "^C\r\n{}", prompt(&cwd))?;
+            }
+            KeyCode::Char('l') if modifiers.contains(KeyModifiers::CONTROL) => {
+                execute!(stdout, Clear(ClearType::All), MoveTo(0, 0))?;
+                write!(stdout, "{}{}", prompt(&cwd), line)?;
+            }
+            KeyCode::Char('d') if modifiers.contains(KeyModifiers::CONTROL) => return Ok(()),
+            KeyCode::Char(c) => {
+                line.push(c);
+                write!(stdout, "{}", c)?;
+            }
+            KeyCode::Backspace if line.pop().is_some() => write!(stdout, "\x08 \x08")?,
+            KeyCode::Enter => {
+                write!(stdout, "\r\n")?;
+                let command = line.trim().to_string();
+                line.clear();
+                if !command.is_empty() {
+                    honeypot.record(&command);
+                    if matches!(command.as_str(), "exit" | "quit" | "::exit" | "::quit") {
+                        return Ok(());
+                    }
+                    if command == "clear" || command == "::clear" {
+                        queue!(stdout, Clear(ClearType::All), MoveTo(0, 0))?;
+                        history.push(command);
+                        write!(stdout, "{}", prompt(&cwd))?;
+                        stdout.flush()?;
+                        continue;
+                    }
+                    history.push(command.clone());
+                    if let Some(output) = respond(&command, &mut cwd, &history) {
+                        write!(stdout, "{}\r\n", output)?;
+                    }
+                }
+                write!(stdout, "{}", prompt(&cwd))?;
+            }
+            _ => {}
+        }
+        stdout.flush()?;
+    }
+}
+
+/// What the fake shell prints for `command`; None for silent success
+fn respond(command: &str, cwd: &mut String, history: &[String]) -> Option<String> {
+    let words: Vec<&str> = command.split_whitespace().collect();
+    let args = &words[1..];
+    let operands: Vec<&str> = args
+        .iter()
+        .copied()
+        .filter(|arg| !arg.starts_with('-'))
+        .collect();
+    let name = words[0];
+    if name.starts_with("::") {
+        return Some(match name {
+            "::help" => crate::commands::overview(),
+            "::security-status" => {
+                "Security grade: A\r\n✓ No debugger attached\r\n✓ No monitoring tools found"
+                    .to_string()
+            }
+            "::vault" => "Vault is empty.".to_string(),
+            "::history" => list_history(history),
+            _ => format!("Unknown command: {}", name),
+        });
+    }
+    let in_home = cwd.as_str() == FAKE_HOME;
+    match name {
+        "pwd" => Some(cwd.clone()),
+        "cd" => {
+            match operands.first().copied() {
+                None | Some("~") => *cwd = FAKE_HOME.to_string(),
+                Some("..") => *cwd = parent(cwd),
+                Some(dir) if dir.starts_with('/') && dir != "/" => *cwd = dir.trim_end_matches('/').to_string(),
+                Some("/") => *cwd = "/".to_string(),
+                Some(dir) if in_home && FAKE_FILES.contains(&(dir.trim_end_matches('/'), true)) => {
+                    *cwd = format!("{}/{}", FAKE_HOME, dir.trim_end_matches('/'))
+                }
+                Some(dir) => return Some(format!("sh: 1: cd: can't cd to {}", dir)),
+            }
+            None
+        }
+        "ls" | "ll" | "dir" => {
+            let all = name == "ll" || args.iter().any(|arg| arg.starts_with('-') && arg.contains('a'));
+            let long = name == "ll" || args.iter().any(|arg| arg.starts_with('-') && arg.contains('l'));
+            let entries: Vec<(&str, bool)> = if in_home && operands.is_empty() {
+                FAKE_FILES
+                    .iter()
+                    .copied()
+                    .filter(|(name, _)| all || !name.starts_with('.'))
+                    .collect()
+            } else {
+                Vec::new()
+            };
+            if let Some(path) = operands.first() {
+                return match FAKE_FILES.iter().find(|(name, _)| name == path) {
+                    Some((_, true)) if in_home => None,
+                    Some(_) if in_home => Some(path.to_string()),
+                    _ => Some(format!(
+                        "ls: cannot access '{}': No such file or directory",
+                        path
+                    )),
+                };
+            }
+            if long {
+                let mut lines = vec![format!("total {}", entries.len() * 4)];
+                lines.extend(entries.iter().map(|(name, is_dir)| {
+                    let (mode, size) = if *is_dir {
+                        ("drwxr-xr-x", 4096)
+                    } else {
+                        ("-rw-r--r--", 220 + name.len() * 37)
+                    };
+                    format!("{} 2 user user {:>5} Mar  3 09:12 {}", mode, size, name)
+                }));
+                Some(lines.join("\r\n"))
+            } else if entries.is_empty() {
+                None
+            } else {
+                Some(
+                    entries
+                        .iter()
+                        .map(|(name, _)| *name)
+                        .collect::<Vec<_>>()
+                        .join("  "),
+                )
+            }
+        }
+        "cat" | "less" | "more" | "head" | "tail" | "vi" | "vim" | "nano" => {
+            let path = operands.first().copied().unwrap_or_default();
+            Some(match FAKE_FILES.iter().find(|(name, _)| *name == path) {
+                Some((_, true)) if in_home => format!("{}: {}: Is a directory", name, path),
+                Some((".bashrc" | ".profile", _)) if in_home => {
+                    "# ~/.bashrc: executed by bash(1) for non-login shells.\r\n\
+                    [ -z \"$PS1\" ] && return\r\n\
+                    HISTCONTROL=ignoreboth\r\n\
+                    alias ll='ls -alF'"
+                        .to_string()
+                }
+                Some(_) if in_home => format!("{}: {}: Permission denied", name, path),
+                _ => format!("{}: {}: No such file or directory", name, path),
+            })
+        }
+        "whoami" => Some("user".to_string()),
+        "id" => Some("uid=1000(user) gid=1000(user) groups=1000(user)".to_string()),
+        "hostname" => Some("localhost".to_string()),
+        "uname" if args.is_empty() => Some("Linux".to_string()),
+        "uname" => Some(
+            "Linux localhost 6.1.0-18-amd64 #1 SMP PREEMPT_DYNAMIC Debian 6.1.76-1 (2024-02-01) x86_64 GNU/Linux"
+                .to_string(),
+        ),
+        "echo" => Some(args.join(" ")),
+        "history" => Some(list_history(history)),
+        "uptime" => Some(" 09:41:07 up 3 days,  2:17,  1 user,  load average: 0.08, 0.03, 0.01".to_string()),
+        "ps" => Some(
+            "    PID TTY          TIME CMD\r\n   2231 pts/0    00:00:00 bash\r\n   2290 pts/0    00:00:00 ps"
+                .to_string(),
+        ),
+        "sudo" | "su" => Some("user is not in the sudoers file.  This incident will be reported.".to_string()),
+        _ => Some(format!("sh: 1: {}: not found", name)),
+    }
+}
+
+/// `/home/user/projects` -> `/home/user`
+fn parent(path: &str) -> String {
+    match path.rsplit_once('/') {
+        Some(("", _)) | None => "/".to_string(),
+        Some((parent, _)) => parent.to_string(),
+    }
+}
+
+fn list_history(history: &[String]) -> String {
+    history
+        .iter()
+        .enumerate()
+        .map(|(i, command)| format!("{:>5}  {}", i + 1, command))
+        .collect::<Vec<_>>()
+        .join("\r\n")
+}
@@ -0,0 +1,133 @@
+/// Session key hierarchy: one master key per session, HKDF-SHA256 subkeys per purpose
+/// The master key is random by default, or derived from a passphrase with Argon2id so
+/// that data kept at rest can be reopened in a later session. Features never generate
+/// their own long-lived keys; they ask for a subkey bound to their purpose.
+use chacha20poly1305::aead::OsRng;
+use hkdf::Hkdf;
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use std::env;
+use zeroize::Zeroize;
+
+use crate::clipboard::derive_passphrase_key;
+use crate::guarded::GuardedBytes;
+use crate::secret::SecretKey;
+
+/// Domain separation prefix for the passphrase salt and subkey labels
+const KEY_DOMAIN: &str = "ghost-shell/keys/v1";
+
+/// What a subkey is used for; each purpose gets an independent key
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyPurpose {
+    Clipboard,   // Named clipboard slots
+    Vault,       // Session vault entries
+    History,     // Clipboard history ring
+    HistoryFile, // ::history persist file
+    Note,        // ::note scratchpad
+    #[allow(dead_code)]
+    Config, // Encrypted configuration
+    Logs,        // ::audit log records
+    Honeypot,    // ::honeypot capture identity
+}
+
+impl KeyPurpose {
+    pub fn name(&self) -> &'static str {
+        match self {
+            KeyPurpose::Clipboard => "clipboard",
+            KeyPurpose::Vault => "vault",
+            KeyPurpose::History => "history",
+            KeyPurpose::HistoryFile => "history-file",
+            KeyPurpose::Note => "note",
+            KeyPurpose::Config => "config",
+            KeyPurpose::Logs => "logs",
+            KeyPurpose::Honeypot => "honeypot",
+        }
+    }
+}
+
+/// Where the session master key came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeySource {
+    Random,
+    Passphrase,
+}
+
+impl KeySource {
+    pub fn name(&self) -> &'static str {
+        match self {
+            KeySource::Random => "random (this session only)",
+            KeySource::Passphrase => "passphrase (Argon2id)",
+        }
+    }
+}
+
+/// 256-bit key in a guarded allocation, zeroized when dropped
+pub struct SubKey(GuardedBytes);
+
+impl SubKey {
+    /// Run `f` with the key readable
+    pub fn expose<R>(&self, f: impl FnOnce(&[u8; 32]) -> R) -> R {
+        self.0
+            .expose(|bytes| f(bytes.try_into().expect("subkeys are 32 bytes")))
+    }
+}
+
+/// The session master key
+pub struct SessionKeys {
+    master: SecretKey,
+    source: KeySource,
+}
+
+impl SessionKeys {
+    /// Fresh random master key; everything derived from it dies with the session
+    pub fn random() -> Self {
+        let mut master = SecretKey::new([0u8; 32]);
+        OsRng.fill_bytes(master.expose_mut());
+        SessionKeys {
+            master,
+            source: KeySource::Random,
+        }
+    }
+
+    /// Master key derived from a passphrase. The salt is bound to the local user so
+    /// the same passphrase gives the same keys across sessions.
+    pub fn from_passphrase(passphrase: &str) -> Result<Self, String> {
+        let user = env::var("USER")
+            .or_else(|_| env::var("USERNAME"))
+            .unwrap_or_default();
+        let digest = Sha256::new()
+            .chain_update(KEY_DOMAIN)
+            .chain_update([0])
+            .chain_update(user)
+            .finalize();
+
+        Ok(SessionKeys {
+            master: derive_passphrase_key(passphrase, &digest[..16])?,
+            source: KeySource::Passphrase,
+        })
+    }
+
+    /// Another handle on the same master key, for a second tab of the session
+    pub fn duplicate(&self) -> Self {
+        SessionKeys {
+            master: SecretKey::new(*self.master.expose()),
+            source: self.source,
+        }
+    }
+
+    pub fn source(&self) -> KeySource {
+        self.source
+    }
+
+    /// Subkey for `purpose`: HKDF-SHA256 expand of the master key
+    pub fn derive(&self, purpose: KeyPurpose) -> SubKey {
+        let hkdf = Hkdf::<Sha256>::new(None, self.master.expose());
+        let mut key = [0u8; 32];
+        let info = format!("{}/{}", KEY_DOMAIN, purpose.name());
+        hkdf.expand(info.as_bytes(), &mut key)
+            .expect("32 bytes is a valid HKDF-SHA256 output length");
+        let subkey = SubKey(GuardedBytes::new(&key));
+        key.zeroize();
+        subkey
+    }
+}
@@ -0,0 +1,85 @@
+/// Type-out mode: emits secrets as simulated keystrokes into the focused window
+/// For password fields that block paste; nothing ever touches the clipboard
+use std::env;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::Duration;
+
+use crate::sandbox::find_in_path;
+
+/// Seconds given to focus the target window before typing starts
+pub const TYPE_DELAY_SECS: u64 = 3;
+
+/// External tool that injects the keystrokes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeystrokeBackend {
+    /// Wayland virtual-keyboard protocol
+    Wtype,
+    /// X11 XTest extension
+    Xdotool,
+    /// Kernel uinput device (any display server, needs ydotoold)
+    Ydotool,
+}
+
+impl KeystrokeBackend {
+    pub fn name(&self) -> &'static str {
+        match self {
+            KeystrokeBackend::Wtype => "wtype",
+            KeystrokeBackend::Xdotool => "xdotool",
+            KeystrokeBackend::Ydotool => "ydotool",
+        }
+    }
+}
+
+/// Pick an installed tool that can reach the current display server
+pub fn detect_keystroke_backend() -> Option<KeystrokeBackend> {
+    let candidates: &[KeystrokeBackend] = if env::var_os("WAYLAND_DISPLAY").is_some() {
+        &[KeystrokeBackend::Wtype, KeystrokeBackend::Ydotool]
+    } else if env::var_os("DISPLAY").is_some() {
+        &[KeystrokeBackend::Xdotool, KeystrokeBackend::Ydotool]
+    } else {
+        &[KeystrokeBackend::Ydotool]
+    };
+
+    candidates
+        .iter()
+        .copied()
+        .find(|backend| find_in_path(backend.name()).is_some())
+}
+
+/// Wait `TYPE_DELAY_SECS` for the user to focus the target window, then type `text`
+/// into it. The text goes through the tool's stdin, never argv (visible in /proc).
+pub fn type_text(backend: KeystrokeBackend, text: &str) -> Result<(), String> {
+    thread::sleep(Duration::from_secs(TYPE_DELAY_SECS));
+
+    let mut command = Command::new(backend.name());
+    match backend {
+        KeystrokeBackend::Wtype => command.arg("-"),
+        KeystrokeBackend::Xdotool => command.args(["type", "--clearmodifiers", "--file", "-"]),
+        KeystrokeBackend::Ydotool => command.args(["type", "--file", "-"]),
+    };
+
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("type: {}: {}", backend.name(), e))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        if let Err(e) = stdin.write_all(text.as_bytes()) {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(format!("type: {}: {}", backend.name(), e));
+        }
+    }
+
+    let status = child
+        .wait()
+        .map_err(|e| format!("type: {}: {}", backend.name(), e))?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(format!("type: {} exited with {}", backend.name(), status))
+    }
+}
@@ -0,0 +1,90 @@
+//! Ghost Shell core: the encrypted clipboard, anti-monitoring checks and shell state
+//! behind the `gsh` binary, usable from other tools.
+//!
+//! - [`shell::SecureBuffer`] is the whole interactive shell minus the terminal: feed it
+//!   keystrokes, call [`shell::SecureBuffer::execute_line`] and show the result.
+//!   It is built around a [`shell::GhostShell`], the session's one clipboard and its
+//!   security scan.
+//! - [`clipboard::SecureClipboard`] copies with ChaCha20-Poly1305 encryption and
+//!   auto-clear timers.
+//! - [`security`] detects debuggers, tracers and monitoring tools, locks memory and
+//!   audits the process memory maps.
+//! - [`vault`], [`filecrypt`], [`age`] and [`keys`] hold the session key hierarchy and the
+//!   file and secret formats.
+//!
+//! ```no_run
+//! use ghost_core::clipboard::SecureClipboard;
+//! use ghost_core::security::initialize_security;
+//!
+//! let status = initialize_security();
+//! print!("{}", status.report());
+//!
+//! let clipboard = SecureClipboard::new(true)?;
+//! clipboard.copy_with_timeout("s3cret".to_string(), 30)?;
+//! # Ok::<(), String>(())
+//! ```
+//!
+//! `::run` scripts and config hooks only zero their freed memory when the program
+//! installs [`script::ScrubbingAllocator`] as its `#[global_allocator]`.
+
+pub mod age;
+pub mod audit;
+#[cfg(unix)]
+pub mod builtins;
+pub mod calc;
+pub mod clipboard;
+pub mod commands;
+pub mod config;
+pub mod contacts;
+pub mod dashboard;
+pub mod decoy;
+#[cfg(unix)]
+pub mod detach;
+pub mod direct;
+pub mod dns;
+pub mod filecrypt;
+pub mod genpass;
+pub mod gpg;
+pub mod guarded;
+pub mod hash;
+pub mod history;
+pub mod honeypot;
+pub mod keys;
+pub mod keystrokes;
+pub mod linebuf;
+pub mod meminfo;
+pub mod netaudit;
+pub mod note;
+pub mod picker;
+pub mod plugin;
+pub mod policy;
+pub mod posture;
+pub mod profile;
+pub mod proxy;
+pub mod qr;
+pub mod readonly;
+pub mod regex;
+pub mod remote;
+pub mod sandbox;
+pub mod script;
+pub mod scrub;
+pub mod secret;
+pub mod security;
+pub mod sensitive;
+pub mod shamir;
+pub mod share;
+pub mod shell;
+pub mod signing;
+pub mod sshagent;
+pub mod term;
+pub mod theme;
+pub mod timestomp;
+pub mod totp;
+pub mod trace;
+pub mod vault;
+pub mod watchdog;
+pub mod wipefree;
+pub mod workspace;
+pub mod wormhole;
+#[cfg(target_os = "linux")]
+pub mod x11;
@@ -0,0 +1,257 @@
+/// Gap buffer for the input line
+/// The text before the cursor sits at the start of the allocation and the text after it
+/// at the end, with the free space (the gap) in between, so typing or deleting at the
+/// cursor moves nothing else and only moving the cursor shifts the characters it
+/// passes. The allocation is grown by hand so no stale copy is left behind unzeroized;
+/// the gap is kept zeroed, and everything is wiped on clear and drop.
+///
+/// The buffer also keeps what the prompt needs to redraw cheaply: the first character
+/// changed since the line was last drawn, and which part of a line wider than the
+/// screen is shown.
+use zeroize::Zeroize;
+
+use crate::secret::SecretString;
+
+/// Room a new line starts with; most lines never grow it
+const INITIAL_CAPACITY: usize = 256;
+
+pub struct LineBuffer {
+    /// UTF-8 text before the gap, the gap (all zero), then the text after it
+    bytes: Vec<u8>,
+    gap_start: usize,
+    gap_end: usize,
+    /// Characters before the cursor, i.e. before the gap
+    cursor: usize,
+    /// Characters in the line
+    chars: usize,
+    /// First character changed since the last `take_changed`
+    changed: Option<usize>,
+    /// First character shown when the line is wider than the screen
+    scroll: usize,
+}
+
+impl LineBuffer {
+    pub fn new() -> Self {
+        LineBuffer {
+            bytes: vec![0; INITIAL_CAPACITY],
+            gap_start: 0,
+            gap_end: INITIAL_CAPACITY,
+            cursor: 0,
+            chars: 0,
+            changed: None,
+            scroll: 0,
+        }
+    }
+
+    /// Length in characters
+    pub fn len(&self) -> usize {
+        self.chars
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chars == 0
+    }
+
+    /// Cursor position, in characters
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// Text before the cursor
+    fn before(&self) -> &str {
+        std::str::from_utf8(&self.bytes[..self.gap_start]).unwrap_or_default()
+    }
+
+    /// Text after the cursor
+    fn after(&self) -> &str {
+        std::str::from_utf8(&self.bytes[self.gap_end..]).unwrap_or_default()
+    }
+
+    /// Insert `c` at the cursor
+    pub fn insert(&mut self, c: char) {
+        let mut encoded = [0u8; 4];
+        self.insert_str(c.encode_utf8(&mut encoded));
+    }
+
+    /// Insert `text` at the cursor, leaving the cursor after it
+    pub fn insert_str(&mut self, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        self.reserve(text.len());
+        self.bytes[self.gap_start..self.gap_start + text.len()].copy_from_slice(text.as_bytes());
+        self.gap_start += text.len();
+        self.mark_changed(self.cursor);
+        let count = text.chars().count();
+        self.cursor += count;
+        self.chars += count;
+    }
+
+    /// Bytes in the character just before the gap
+    fn length_before(&self) -> Option<usize> {
+        let mut start = self.gap_start.checked_sub(1)?;
+        while start > 0 && self.bytes[start] & 0xC0 == 0x80 {
+            start -= 1;
+        }
+        Some(self.gap_start - start)
+    }
+
+    /// Bytes in the character just after the gap, from its leading byte
+    fn length_after(&self) -> Option<usize> {
+        self.bytes.get(self.gap_end).map(|&lead| match lead {
+            0xF0.. => 4,
+            0xE0.. => 3,
+            0xC0.. => 2,
+            _ => 1,
+        })
+    }
+
+    /// Delete the character before the cursor. Returns whether there was one.
+    pub fn backspace(&mut self) -> bool {
+        let Some(length) = self.length_before() else {
+            return false;
+        };
+        let start = self.gap_start - length;
+        self.bytes[start..self.gap_start].zeroize();
+        self.gap_start = start;
+        self.cursor -= 1;
+        self.chars -= 1;
+        self.mark_changed(self.cursor);
+        true
+    }
+
+    /// Move the cursor one character left. Returns whether it moved.
+    pub fn move_left(&mut self) -> bool {
+        let Some(length) = self.length_before() else {
+            return false;
+        };
+        self.bytes.copy_within(
+            self.gap_start - length..self.gap_start,
+            self.gap_end - length,
+        );
+        self.gap_start -= length;
+        self.gap_end -= length;
+        // What was copied out now lies in the gap
+        let overlap = self.gap_end.min(self.gap_start + length);
+        self.bytes[self.gap_start..overlap].zeroize();
+        self.cursor -= 1;
+        true
+    }
+
+    /// Move the cursor one character right. Returns whether it moved.
+    pub fn move_right(&mut self) -> bool {
+        let Some(length) = self.length_after() else {
+            return false;
+        };
+        self.bytes
+            .copy_within(self.gap_end..self.gap_end + length, self.gap_start);
+        self.gap_start += length;
+        self.gap_end += length;
+        let overlap = self.gap_start.max(self.gap_end - length);
+        self.bytes[overlap..self.gap_end].zeroize();
+        self.cursor += 1;
+        true
+    }
+
+    /// Replace the line with `text`, cursor at the end
+    pub fn set(&mut self, text: &str) {
+        self.clear();
+        self.insert_str(text);
+    }
+
+    /// Zeroize and empty the line
+    pub fn clear(&mut self) {
+        self.bytes.as_mut_slice().zeroize();
+        self.gap_start = 0;
+        self.gap_end = self.bytes.len();
+        self.cursor = 0;
+        self.chars = 0;
+        self.mark_changed(0);
+    }
+
+    /// A copy of the whole line
+    pub fn text(&self) -> SecretString {
+        let mut text = String::with_capacity(self.gap_start + self.bytes.len() - self.gap_end);
+        text.push_str(self.before());
+        text.push_str(self.after());
+        SecretString::new(text)
+    }
+
+    /// Move the line out, leaving it empty
+    pub fn take(&mut self) -> String {
+        let text = self.text().into_exposed();
+        self.clear();
+        text
+    }
+
+    /// Characters `from..from + count` of the line (clamped to its end), as the parts
+    /// before and after the cursor
+    pub fn span(&self, from: usize, count: usize) -> (&str, &str) {
+        let to = from.saturating_add(count).min(self.chars);
+        let from = from.min(to);
+        let before = self.before();
+        let head = &before
+            [byte_offset(before, from.min(self.cursor))..byte_offset(before, to.min(self.cursor))];
+        let after = self.after();
+        let tail = &after[byte_offset(after, from.max(self.cursor) - self.cursor)
+            ..byte_offset(after, to.max(self.cursor) - self.cursor)];
+        (head, tail)
+    }
+
+    /// The first character changed since the last call, if any
+    pub fn take_changed(&mut self) -> Option<usize> {
+        self.changed.take()
+    }
+
+    /// Scroll so the cursor is inside a window `width` characters wide, showing as much
+    /// of the line as fits. Returns the first character shown and whether it moved.
+    pub fn scroll(&mut self, width: usize) -> (usize, bool) {
+        let width = width.max(1);
+        let scroll = self
+            .scroll
+            .min(self.cursor)
+            .max((self.cursor + 1).saturating_sub(width))
+            .min((self.chars + 1).saturating_sub(width));
+        let moved = scroll != self.scroll;
+        self.scroll = scroll;
+        (scroll, moved)
+    }
+
+    fn mark_changed(&mut self, at: usize) {
+        self.changed = Some(self.changed.map_or(at, |changed| changed.min(at)));
+    }
+
+    /// Make the gap at least `needed` bytes wide
+    fn reserve(&mut self, needed: usize) {
+        if self.gap_end - self.gap_start >= needed {
+            return;
+        }
+        let after = self.bytes.len() - self.gap_end;
+        let length = (self.bytes.len() * 2).max(self.gap_start + after + needed);
+        let mut bytes = vec![0; length];
+        bytes[..self.gap_start].copy_from_slice(&self.bytes[..self.gap_start]);
+        bytes[length - after..].copy_from_slice(&self.bytes[self.gap_end..]);
+        self.bytes.zeroize();
+        self.bytes = bytes;
+        self.gap_end = length - after;
+    }
+}
+
+/// Byte offset of character `n` in `text`, or its length past the end
+fn byte_offset(text: &str, n: usize) -> usize {
+    text.char_indices()
+        .nth(n)
+        .map_or(text.len(), |(offset, _)| offset)
+}
+
+impl Default for LineBuffer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for LineBuffer {
+    fn drop(&mut self) {
+        self.bytes.zeroize();
+    }
+}
@@ -0,0 +1,138 @@
+/// Sensitive-memory accounting for ::meminfo
+/// Each store hands over where its buffers sit; their sizes are summed and checked
+/// against the mappings the kernel has locked into RAM (the `lo` flag in
+/// /proc/self/smaps), so the report shows what an attacker reading swap or a core
+/// dump could still find, not what the code meant to lock.
+use std::ops::Range;
+
+/// What one store holds and how long it keeps it
+pub struct Holding {
+    pub store: &'static str,
+    pub items: usize,
+    pub bytes: usize,
+    /// Bytes inside mlocked mappings, None where that can't be checked
+    pub locked: Option<usize>,
+    pub encrypted: bool,
+    pub expiry: String,
+}
+
+impl Holding {
+    pub fn new(
+        store: &'static str,
+        regions: &[Range<usize>],
+        encrypted: bool,
+        expiry: String,
+        locked: Option<&LockedRanges>,
+    ) -> Self {
+        Holding {
+            store,
+            items: regions.len(),
+            bytes: regions.iter().map(|region| region.len()).sum(),
+            locked: locked.map(|ranges| regions.iter().map(|region| ranges.overlap(region)).sum()),
+            encrypted,
+            expiry,
+        }
+    }
+
+    /// The locked column: all, none or the locked byte count
+    fn describe_locked(&self) -> String {
+        match self.locked {
+            _ if self.bytes == 0 => "-".to_string(),
+            None => "?".to_string(),
+            Some(0) => "✗ none".to_string(),
+            Some(locked) if locked == self.bytes => "✓ all".to_string(),
+            Some(locked) => format!("⚠ {} B", locked),
+        }
+    }
+}
+
+/// Address ranges of the mappings locked into RAM
+pub struct LockedRanges(Vec<Range<usize>>);
+
+impl LockedRanges {
+    /// Read the locked mappings from /proc/self/smaps
+    #[cfg(target_os = "linux")]
+    pub fn capture() -> Option<Self> {
+        let smaps = std::fs::read_to_string("/proc/self/smaps").ok()?;
+        let mut ranges = Vec::new();
+        let mut current = None;
+        for line in smaps.lines() {
+            if let Some(flags) = line.strip_prefix("VmFlags:") {
+                if flags.split_whitespace().any(|flag| flag == "lo") {
+                    ranges.extend(current.take());
+                }
+            } else if let Some(range) = parse_mapping(line) {
+                current = Some(range);
+            }
+        }
+        Some(LockedRanges(ranges))
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn capture() -> Option<Self> {
+        None
+    }
+
+    /// Bytes of `region` inside a locked mapping
+    fn overlap(&self, region: &Range<usize>) -> usize {
+        self.0
+            .iter()
+            .map(|range| {
+                region
+                    .end
+                    .min(range.end)
+                    .saturating_sub(region.start.max(range.start))
+            })
+            .sum()
+    }
+}
+
+/// The addresses `bytes` occupies
+pub fn region(bytes: &[u8]) -> Range<usize> {
+    let range = bytes.as_ptr_range();
+    range.start as usize..range.end as usize
+}
+
+/// The address range of an smaps mapping header (`start-end perms offset ...`)
+#[cfg(target_os = "linux")]
+fn parse_mapping(line: &str) -> Option<Range<usize>> {
+    let (start, end) = line.split_whitespace().next()?.split_once('-')?;
+    Some(usize::from_str_radix(start, 16).ok()?..usize::from_str_radix(end, 16).ok()?)
+}
+
+/// The ::meminfo table: one row per store, then the totals
+pub fn report(holdings: &[Holding]) -> String {
+    let mut report = String::from("=== SENSITIVE MEMORY ===\r\n");
+    report.push_str(&format!(
+        "{:<15} {:>5} {:>6}  {:<6} {:<9}  {}\r\n",
+        "Store", "Items", "Bytes", "Locked", "Form", "Zeroized"
+    ));
+    for holding in holdings {
+        report.push_str(&format!(
+            "{:<15} {:>5} {:>6}  {:<6} {:<9}  {}\r\n",
+            holding.store,
+            holding.items,
+            holding.bytes,
+            holding.describe_locked(),
+            if holding.encrypted {
+                "encrypted"
+            } else {
+                "plaintext"
+            },
+            holding.expiry
+        ));
+    }
+    let bytes: usize = holdings.iter().map(|holding| holding.bytes).sum();
+    let locked: Option<usize> = holdings.iter().map(|holding| holding.locked).sum();
+    report.push_str(&format!(
+        "{:<15} {:>5} {:>6}  {}",
+        "Total",
+        holdings.iter().map(|holding| holding.items).sum::<usize>(),
+        bytes,
+        match locked {
+            Some(locked) => format!("{} of {} bytes mlocked", locked, bytes),
+            None => "mlock state unknown on this platform".to_string(),
+        }
+    ));
+    report
+}
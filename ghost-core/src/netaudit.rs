@@ -0,0 +1,244 @@
+/// Audit of the shell's own network sockets (::net-audit, paranoid watcher)
+/// Between commands the shell process itself has no business holding an inet socket:
+/// ::share, ::send, ::resolve and the like open theirs and close them before they
+/// return, and external commands are separate processes. So any TCP/UDP socket found
+/// among our own file descriptors is something else phoning home, e.g. a compromised
+/// dependency. Sockets are matched by inode between /proc/self/fd and /proc/self/net.
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// How often the paranoid-mode watcher looks
+pub const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Commands currently allowed to use the network
+static NETWORK_EXPECTED: AtomicUsize = AtomicUsize::new(0);
+
+/// Held while a command legitimately talks to the network, so the watcher keeps quiet
+pub struct ExpectNetwork(());
+
+impl ExpectNetwork {
+    pub fn new() -> Self {
+        NETWORK_EXPECTED.fetch_add(1, Ordering::SeqCst);
+        ExpectNetwork(())
+    }
+}
+
+impl Default for ExpectNetwork {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for ExpectNetwork {
+    fn drop(&mut self) {
+        NETWORK_EXPECTED.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// One inet socket owned by this process
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OwnSocket {
+    pub protocol: &'static str,
+    pub local: String,
+    pub remote: Option<String>,
+    pub state: &'static str,
+}
+
+impl OwnSocket {
+    pub fn describe(&self) -> String {
+        match &self.remote {
+            Some(remote) => format!(
+                "{} {} -> {} ({})",
+                self.protocol, self.local, remote, self.state
+            ),
+            None => format!("{} {} ({})", self.protocol, self.local, self.state),
+        }
+    }
+}
+
+/// Inet sockets held by this process right now
+#[cfg(target_os = "linux")]
+pub fn own_sockets() -> Vec<OwnSocket> {
+    use std::collections::HashSet;
+    use std::fs;
+
+    let inodes: HashSet<String> = fs::read_dir("/proc/self/fd")
+        .map(|entries| {
+            entries
+                .filter_map(|entry| fs::read_link(entry.ok()?.path()).ok())
+                .filter_map(|target| {
+                    let target = target.to_string_lossy().into_owned();
+                    target
+                        .strip_prefix("socket:[")
+                        .and_then(|rest| rest.strip_suffix(']'))
+                        .map(str::to_string)
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    // Unix sockets (clipboard, ssh-agent) are fds too; nothing to look up without any
+    if inodes.is_empty() {
+        return Vec::new();
+    }
+
+    let mut sockets = Vec::new();
+    for (file, protocol) in [
+        ("tcp", "TCP"),
+        ("tcp6", "TCP6"),
+        ("udp", "UDP"),
+        ("udp6", "UDP6"),
+    ] {
+        let Ok(table) = fs::read_to_string(format!("/proc/self/net/{}", file)) else {
+            continue;
+        };
+        for line in table.lines().skip(1) {
+            // sl local_address rem_address st tx:rx tr:when retrnsmt uid timeout inode
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() < 10 || !inodes.contains(fields[9]) {
+                continue;
+            }
+            let (Some(local), Some(remote)) = (parse_address(fields[1]), parse_address(fields[2]))
+            else {
+                continue;
+            };
+            let tcp = protocol.starts_with("TCP");
+            let state = if tcp {
+                tcp_state(fields[3])
+            } else if remote.ends_with(":0") {
+                "unconnected"
+            } else {
+                "connected"
+            };
+            let remote = (!remote.ends_with(":0")).then_some(remote);
+            sockets.push(OwnSocket {
+                protocol,
+                local,
+                remote,
+                state,
+            });
+        }
+    }
+    sockets
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn own_sockets() -> Vec<OwnSocket> {
+    Vec::new()
+}
+
+/// Whether this platform can enumerate its own sockets
+pub fn supported() -> bool {
+    cfg!(target_os = "linux")
+}
+
+/// Threat descriptions for sockets nobody asked for
+pub fn audit_own_sockets() -> Vec<String> {
+    if NETWORK_EXPECTED.load(Ordering::SeqCst) > 0 {
+        return Vec::new();
+    }
+    own_sockets()
+        .iter()
+        .map(|socket| {
+            format!(
+                "Unexpected network socket in the shell: {}",
+                socket.describe()
+            )
+        })
+        .collect()
+}
+
+/// `0100007F:0035` (IPv4) or the 32-hex-digit IPv6 form, in kernel byte order
+#[cfg(target_os = "linux")]
+fn parse_address(field: &str) -> Option<String> {
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    let (address, port) = field.split_once(':')?;
+    let port = u16::from_str_radix(port, 16).ok()?;
+    match address.len() {
+        8 => {
+            let ip = Ipv4Addr::from(u32::from_str_radix(address, 16).ok()?.to_le_bytes());
+            Some(format!("{}:{}", ip, port))
+        }
+        32 => {
+            // Four 32-bit words, each in host (little-endian) order
+            let mut octets = [0u8; 16];
+            for (i, word) in octets.chunks_mut(4).enumerate() {
+                let value = u32::from_str_radix(&address[i * 8..i * 8 + 8], 16).ok()?;
+                word.copy_from_slice(&value.to_le_bytes());
+            }
+            Some(format!("[{}]:{}", Ipv6Addr::from(octets), port))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn tcp_state(code: &str) -> &'static str {
+    match code {
+        "01" => "ESTABLISHED",
+        "02" => "SYN_SENT",
+        "03" => "SYN_RECV",
+        "04" => "FIN_WAIT1",
+        "05" => "FIN_WAIT2",
+        "06" => "TIME_WAIT",
+        "07" => "CLOSE",
+        "08" => "CLOSE_WAIT",
+        "09" => "LAST_ACK",
+        "0A" => "LISTEN",
+        "0B" => "CLOSING",
+        _ => "UNKNOWN",
+    }
+}
+
+/// Background thread polling `audit_own_sockets` while paranoid mode is on
+#[derive(Default)]
+pub struct SocketWatcher {
+    running: Option<(Arc<AtomicBool>, JoinHandle<()>)>,
+}
+
+impl SocketWatcher {
+    pub fn new() -> Self {
+        SocketWatcher { running: None }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running.is_some()
+    }
+
+    /// Start watching; `on_alert` runs on the watcher thread with the threats found
+    pub fn start(&mut self, on_alert: impl Fn(Vec<String>) + Send + 'static) {
+        if self.is_running() || !supported() {
+            return;
+        }
+        let stop = Arc::new(AtomicBool::new(false));
+        let stop_flag = Arc::clone(&stop);
+        let handle = thread::spawn(move || {
+            while !stop_flag.load(Ordering::SeqCst) {
+                let threats = audit_own_sockets();
+                if !threats.is_empty() {
+                    on_alert(threats);
+                    return;
+                }
+                thread::sleep(WATCH_INTERVAL);
+            }
+        });
+        self.running = Some((stop, handle));
+    }
+
+    pub fn stop(&mut self) {
+        if let Some((stop, handle)) = self.running.take() {
+            stop.store(true, Ordering::SeqCst);
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Drop for SocketWatcher {
+    fn drop(&mut self) {
+        if let Some((stop, _)) = self.running.take() {
+            stop.store(true, Ordering::SeqCst);
+        }
+    }
+}
@@ -0,0 +1,96 @@
+/// Ephemeral scratchpad for ::note: one multi-line note kept encrypted in RAM
+/// Only decrypted while appending or for ::note show; burned on ::note burn and on exit
+use std::ops::Range;
+use std::time::Instant;
+use zeroize::Zeroize;
+
+use crate::clipboard::{open_bytes, seal_bytes};
+use crate::keys::SubKey;
+use crate::meminfo::region;
+use crate::secret::SecretString;
+
+/// Sealed note contents
+struct SealedNote {
+    nonce: [u8; 12],
+    ciphertext: Vec<u8>,
+    lines: usize,
+    updated_at: Instant,
+}
+
+/// The session scratchpad, encrypted under the note subkey
+pub struct Note {
+    key: SubKey,
+    sealed: Option<SealedNote>,
+}
+
+impl Note {
+    pub fn new(key: SubKey) -> Self {
+        Note { key, sealed: None }
+    }
+
+    /// Append `text` as new lines. Returns the total number of lines.
+    pub fn append(&mut self, text: &str) -> Result<usize, String> {
+        let mut contents = self.read()?.unwrap_or_default();
+        let buffer = contents.expose_mut();
+        if !buffer.is_empty() {
+            buffer.push('\n');
+        }
+        buffer.push_str(text);
+
+        let (nonce, ciphertext) = self.key.expose(|key| seal_bytes(key, buffer.as_bytes()))?;
+        let lines = buffer.lines().count();
+
+        self.burn();
+        self.sealed = Some(SealedNote {
+            nonce,
+            ciphertext,
+            lines,
+            updated_at: Instant::now(),
+        });
+        Ok(lines)
+    }
+
+    /// Decrypt the note, or None if nothing has been written
+    pub fn read(&self) -> Result<Option<SecretString>, String> {
+        let Some(sealed) = &self.sealed else {
+            return Ok(None);
+        };
+        self.key
+            .expose(|key| open_bytes(key, &sealed.nonce, &sealed.ciphertext))?
+            .into_text()
+            .map(Some)
+    }
+
+    /// Line count and seconds since the last change, without decrypting
+    pub fn describe(&self) -> Option<(usize, u64)> {
+        self.sealed
+            .as_ref()
+            .map(|sealed| (sealed.lines, sealed.updated_at.elapsed().as_secs()))
+    }
+
+    /// Where the encrypted note sits in memory, for ::meminfo
+    pub fn regions(&self) -> Vec<Range<usize>> {
+        self.sealed
+            .iter()
+            .map(|sealed| region(&sealed.ciphertext))
+            .collect()
+    }
+
+    /// Zeroize and drop the note. Returns true if there was one.
+    pub fn burn(&mut self) -> bool {
+        match self.sealed.take() {
+            Some(mut sealed) => {
+                sealed.ciphertext.zeroize();
+                sealed.nonce.zeroize();
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl Drop for Note {
+    fn drop(&mut self) {
+        self.burn();
+    }
+}
@@ -0,0 +1,248 @@
+/// Full-screen fuzzy finder for Ctrl+T, over the history and the ghost commands
+/// Matching is fzf-style: the query's characters must appear in order, and tighter
+/// matches, matches at word starts and shorter lines rank first. A query with an
+/// uppercase letter is case-sensitive. The finder draws on the alternate screen, so
+/// nothing it shows stays in the scrollback, and its buffers are zeroized on exit.
+use crossterm::{
+    cursor::MoveTo,
+    event::{Event, KeyCode, KeyEvent, KeyModifiers},
+    execute, queue,
+    style::{Print, Stylize},
+    terminal::{Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use std::io::{self, Write};
+use zeroize::Zeroize;
+
+use crate::shell::RESET_SCROLL_REGION;
+use crate::term::{self, Output};
+use crate::theme::{Role, Theme};
+
+/// Score for each matched character
+const SCORE_MATCH: i64 = 16;
+/// Extra score when a match directly follows the previous one
+const BONUS_CONSECUTIVE: i64 = 8;
+/// Extra score for a match at the start of a word
+const BONUS_BOUNDARY: i64 = 8;
+/// Rows above the list: the query line and the match counter
+const HEADER_ROWS: u16 = 2;
+
+/// Score of `candidate` for `query` and the char positions that matched, or None if
+/// the query is not a subsequence of it
+pub fn fuzzy_match(query: &str, candidate: &str) -> Option<(i64, Vec<usize>)> {
+    let case_sensitive = query.chars().any(char::is_uppercase);
+    let fold = |c: char| {
+        if case_sensitive {
+            c
+        } else {
+            c.to_lowercase().next().unwrap_or(c)
+        }
+    };
+    let query: Vec<char> = query.chars().map(fold).collect();
+    let text: Vec<char> = candidate.chars().collect();
+    if query.is_empty() {
+        return Some((0, Vec::new()));
+    }
+
+    // Leftmost end of a match, then walk back from it for the shortest window
+    let mut next = 0;
+    let mut end = None;
+    for (i, c) in text.iter().enumerate() {
+        if fold(*c) == query[next] {
+            next += 1;
+            if next == query.len() {
+                end = Some(i);
+                break;
+            }
+        }
+    }
+    let end = end?;
+    let mut positions = Vec::with_capacity(query.len());
+    let mut wanted = query.iter().rev().peekable();
+    for i in (0..=end).rev() {
+        if wanted.peek().is_some_and(|c| **c == fold(text[i])) {
+            positions.push(i);
+            wanted.next();
+            if wanted.peek().is_none() {
+                break;
+            }
+        }
+    }
+    positions.reverse();
+
+    let mut score = 0;
+    for (n, &i) in positions.iter().enumerate() {
+        score += SCORE_MATCH;
+        if n > 0 && positions[n - 1] + 1 == i {
+            score += BONUS_CONSECUTIVE;
+        }
+        if i == 0 || matches!(text[i - 1], ' ' | ':' | '/' | '-' | '_' | '.' | '=') {
+            score += BONUS_BOUNDARY;
+        }
+    }
+    // Gaps inside the match and a longer line both cost a little
+    let span = positions[positions.len() - 1] - positions[0] + 1;
+    score -= (span - positions.len()) as i64;
+    score -= (text.len() / 8) as i64;
+    Some((score, positions))
+}
+
+/// Indices of the candidates matching `query`, best first; ties keep the given order
+fn rank(query: &str, candidates: &[String]) -> Vec<(usize, Vec<usize>)> {
+    let mut ranked: Vec<(i64, usize, Vec<usize>)> = candidates
+        .iter()
+        .enumerate()
+        .filter_map(|(i, candidate)| {
+            fuzzy_match(query, candidate).map(|(score, positions)| (score, i, positions))
+        })
+        .collect();
+    ranked.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+    ranked
+        .into_iter()
+        .map(|(_, i, positions)| (i, positions))
+        .collect()
+}
+
+/// One list row: control characters shown as `?`, matched characters in bold, cut
+/// to `width` columns
+fn render_row(candidate: &str, positions: &[usize], selected: bool, width: usize) -> String {
+    let mut row = String::from(if selected { "> " } else { "  " });
+    for (i, c) in candidate.chars().take(width.saturating_sub(2)).enumerate() {
+        let c = if c.is_control() { '?' } else { c };
+        if positions.contains(&i) {
+            row.push_str(&c.to_string().bold().to_string());
+        } else {
+            row.push(c);
+        }
+    }
+    if selected {
+        row = row.reverse().to_string();
+    }
+    row
+}
+
+/// Draw the query, the counter and the visible part of the ranked list
+fn draw(
+    stdout: &mut Output,
+    theme: Theme,
+    query: &str,
+    candidates: &[String],
+    ranked: &[(usize, Vec<usize>)],
+    selected: usize,
+    offset: usize,
+) -> io::Result<()> {
+    let (width, rows) = term::size().unwrap_or((80, 24));
+    let mut frame = String::new();
+    for (row, (i, positions)) in ranked
+        .iter()
+        .skip(offset)
+        .take(rows.saturating_sub(HEADER_ROWS) as usize)
+        .enumerate()
+    {
+        frame.push_str(&format!("\x1b[{};1H", row + HEADER_ROWS as usize + 1));
+        frame.push_str(&render_row(
+            &candidates[*i],
+            positions,
+            offset + row == selected,
+            width as usize,
+        ));
+    }
+    let counter = format!("  {}/{}", ranked.len(), candidates.len());
+    queue!(
+        stdout,
+        Clear(ClearType::All),
+        MoveTo(0, 1),
+        Print(theme.paint(Role::Countdown, &counter)),
+        Print(&frame),
+        MoveTo(0, 0),
+        Print(theme.paint(Role::Prompt, "> ")),
+        Print(query)
+    )?;
+    frame.zeroize();
+    stdout.flush()
+}
+
+/// Let the user pick one of `candidates`, starting from `query`. Enter returns the
+/// selection; Esc, Ctrl+C, Ctrl+G or Ctrl+T again cancel. Every candidate and the
+/// query are zeroized before returning.
+pub fn pick(theme: Theme, mut candidates: Vec<String>, query: &str) -> io::Result<Option<String>> {
+    let mut stdout = term::stdout();
+    execute!(stdout, EnterAlternateScreen, Print(RESET_SCROLL_REGION))?;
+
+    let mut query = query.to_string();
+    let mut selected = 0;
+    let mut offset = 0;
+    let choice = loop {
+        let ranked = rank(&query, &candidates);
+        let visible = term::size()
+            .map(|(_, rows)| rows.saturating_sub(HEADER_ROWS).max(1) as usize)
+            .unwrap_or(1);
+        selected = selected.min(ranked.len().saturating_sub(1));
+        offset = offset
+            .min(selected)
+            .max((selected + 1).saturating_sub(visible));
+        if let Err(e) = draw(
+            &mut stdout,
+            theme,
+            &query,
+            &candidates,
+            &ranked,
+            selected,
+            offset,
+        ) {
+            break Err(e);
+        }
+
+        let event = match term::read_event() {
+            Ok(event) => event,
+            Err(e) => break Err(e),
+        };
+        let (code, modifiers) = match event {
+            Event::Key(KeyEvent {
+                code, modifiers, ..
+            }) => (code, modifiers),
+            Event::Paste(mut text) => {
+                // Only the first line of a paste goes into the query
+                query.extend(
+                    text.lines()
+                        .next()
+                        .unwrap_or_default()
+                        .chars()
+                        .filter(|c| !c.is_control()),
+                );
+                text.zeroize();
+                continue;
+            }
+            _ => continue, // Resizes redraw on the next pass
+        };
+        let ctrl = modifiers.contains(KeyModifiers::CONTROL);
+        match code {
+            KeyCode::Esc => break Ok(None),
+            KeyCode::Char('c' | 'g' | 't') if ctrl => break Ok(None),
+            KeyCode::Enter => {
+                break Ok(ranked.get(selected).map(|(i, _)| candidates[*i].clone()));
+            }
+            KeyCode::Up => selected = selected.saturating_sub(1),
+            KeyCode::Char('p' | 'k') if ctrl => selected = selected.saturating_sub(1),
+            KeyCode::Down => selected += 1,
+            KeyCode::Char('n' | 'j') if ctrl => selected += 1,
+            KeyCode::Char('u') if ctrl => {
+                query.zeroize();
+                selected = 0;
+            }
+            KeyCode::Backspace => {
+                query.pop();
+                selected = 0;
+            }
+            KeyCode::Char(c) if !ctrl => {
+                query.push(c);
+                selected = 0;
+            }
+            _ => {}
+        }
+    };
+
+    query.zeroize();
+    candidates.iter_mut().for_each(Zeroize::zeroize);
+    execute!(stdout, LeaveAlternateScreen)?;
+    choice
+}
@@ -0,0 +1,222 @@
+/// External plugins for custom `::` commands
+/// A plugin is any executable named `gsh-plugin-<name>` in `~/.config/gsh/plugins/` or on
+/// PATH; `::<name> args...` runs it. It never sees shell state directly: it talks to a
+/// narrow host interface over its stdin/stdout, one line per message.
+///
+///   plugin -> host   any other line          printed as command output
+///                    @gsh copy <base64>      copy text to the auto-clearing clipboard
+///                    @gsh vault-get <name>   read a vault entry (the user must approve)
+///                    @gsh vault-put <name> <base64>   store a vault entry
+///   host -> plugin   ok [<base64>] | err <message>    reply to every @gsh request
+use base64::{engine::general_purpose, Engine as _};
+use std::env;
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use zeroize::Zeroize;
+
+use crate::sandbox::find_in_path;
+
+pub const PLUGIN_PREFIX: &str = "gsh-plugin-";
+
+/// Marks a host request in plugin output
+const REQUEST_PREFIX: &str = "@gsh ";
+
+/// Output kept from one plugin run
+const MAX_PLUGIN_OUTPUT: usize = 4 * 1024 * 1024;
+
+/// What a plugin may ask of the shell
+pub trait PluginHost {
+    /// Copy to the clipboard; returns a status line for the output
+    fn copy(&mut self, plugin: &str, text: String) -> Result<String, String>;
+    /// Read a vault entry, subject to the user's approval
+    fn vault_get(&mut self, plugin: &str, name: &str) -> Result<String, String>;
+    fn vault_put(&mut self, plugin: &str, name: &str, secret: &str) -> Result<(), String>;
+}
+
+/// `~/.config/gsh/plugins`, searched before PATH
+fn plugin_dir() -> Option<PathBuf> {
+    crate::config::config_path().and_then(|path| path.parent().map(|dir| dir.join("plugins")))
+}
+
+/// Plugin names are restricted so `::<name>` can't reach outside the prefix
+fn valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Executable for `::<name>`, if one is installed
+pub fn find_plugin(name: &str) -> Option<PathBuf> {
+    if !valid_name(name) {
+        return None;
+    }
+    let file_name = format!("{}{}", PLUGIN_PREFIX, name);
+    plugin_dir()
+        .map(|dir| dir.join(&file_name))
+        .filter(|path| is_executable(path))
+        .or_else(|| find_in_path(&file_name).filter(|path| is_executable(path)))
+}
+
+/// Every installed plugin as (name, path); the plugin directory shadows PATH
+pub fn list_plugins() -> Vec<(String, PathBuf)> {
+    let mut dirs: Vec<PathBuf> = plugin_dir().into_iter().collect();
+    if let Some(path) = env::var_os("PATH") {
+        dirs.extend(env::split_paths(&path));
+    }
+
+    let mut plugins: Vec<(String, PathBuf)> = Vec::new();
+    for dir in dirs {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let file_name = entry.file_name().to_string_lossy().into_owned();
+            let Some(name) = file_name.strip_prefix(PLUGIN_PREFIX) else {
+                continue;
+            };
+            if valid_name(name)
+                && is_executable(&entry.path())
+                && !plugins.iter().any(|(existing, _)| existing == name)
+            {
+                plugins.push((name.to_string(), entry.path()));
+            }
+        }
+    }
+    plugins.sort();
+    plugins
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    fs::metadata(path).is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+/// Run plugin `name` with `args`, serving its host requests. Returns its output.
+pub fn run_plugin(
+    name: &str,
+    path: &Path,
+    args: &str,
+    host: &mut dyn PluginHost,
+) -> Result<String, String> {
+    let mut child = Command::new(path)
+        .args(args.split_whitespace())
+        .env("GSH_PLUGIN_API", "1")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("{}: {}", path.display(), e))?;
+
+    let (Some(mut stdin), Some(stdout)) = (child.stdin.take(), child.stdout.take()) else {
+        let _ = child.kill();
+        return Err(format!("{}: no pipes to the plugin.", name));
+    };
+
+    let mut output: Vec<String> = Vec::new();
+    let mut output_len = 0;
+    let mut reader = BufReader::new(stdout);
+    let mut line = String::new();
+    loop {
+        line.zeroize();
+        line.clear();
+        match reader.read_line(&mut line) {
+            Ok(0) => break,
+            Ok(_) => {}
+            Err(e) => {
+                let _ = child.kill();
+                return Err(format!("{}: {}", name, e));
+            }
+        }
+        let text = line.trim_end_matches(['\r', '\n']);
+
+        let Some(request) = text.strip_prefix(REQUEST_PREFIX) else {
+            output_len += text.len();
+            if output_len > MAX_PLUGIN_OUTPUT {
+                let _ = child.kill();
+                return Err(format!("{}: output too large.", name));
+            }
+            output.push(text.to_string());
+            continue;
+        };
+
+        let mut reply = match handle_request(name, request, host) {
+            Ok((Some(mut value), status)) => {
+                output.extend(status);
+                let encoded = format!("ok {}\n", general_purpose::STANDARD.encode(&value));
+                value.zeroize();
+                encoded
+            }
+            Ok((None, status)) => {
+                output.extend(status);
+                "ok\n".to_string()
+            }
+            Err(e) => format!("err {}\n", e.replace('\n', " ")),
+        };
+        let written = stdin
+            .write_all(reply.as_bytes())
+            .and_then(|_| stdin.flush());
+        reply.zeroize();
+        if written.is_err() {
+            break;
+        }
+    }
+    line.zeroize();
+    drop(stdin);
+
+    let result = child
+        .wait_with_output()
+        .map_err(|e| format!("{}: {}", name, e))?;
+    let stderr = String::from_utf8_lossy(&result.stderr);
+    if !stderr.trim().is_empty() {
+        output.push("STDERR:".to_string());
+        output.extend(stderr.lines().map(str::to_string));
+    }
+    if !result.status.success() {
+        output.push(format!("{} exited with {}", name, result.status));
+    }
+    Ok(output.join("\r\n"))
+}
+
+/// Serve one request: the reply value (if any) and status lines for the output
+fn handle_request(
+    plugin: &str,
+    request: &str,
+    host: &mut dyn PluginHost,
+) -> Result<(Option<String>, Option<String>), String> {
+    let decode = |encoded: &str| {
+        general_purpose::STANDARD
+            .decode(encoded)
+            .ok()
+            .and_then(|bytes| String::from_utf8(bytes).ok())
+            .ok_or_else(|| "invalid base64 text".to_string())
+    };
+    let mut words = request.splitn(3, ' ');
+    match (words.next(), words.next(), words.next()) {
+        (Some("copy"), Some(encoded), None) => {
+            let status = host.copy(plugin, decode(encoded)?)?;
+            Ok((None, Some(status)))
+        }
+        (Some("vault-get"), Some(name), None) => Ok((Some(host.vault_get(plugin, name)?), None)),
+        (Some("vault-put"), Some(name), Some(encoded)) => {
+            let mut secret = decode(encoded)?;
+            let stored = host.vault_put(plugin, name, &secret);
+            secret.zeroize();
+            stored?;
+            Ok((
+                None,
+                Some(format!("VAULT ENTRY '{}' STORED BY {}.", name, plugin)),
+            ))
+        }
+        _ => Err(format!("unknown request: {}", request)),
+    }
+}
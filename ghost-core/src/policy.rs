@@ -0,0 +1,51 @@
+/// Command policy from the config file, for work under rules of engagement
+/// `policy.deny` rules refuse matching command lines unless the operator presses
+/// Ctrl+O at the refusal; `policy.confirm` rules ask first. Patterns are globs over the
+/// whole line (`*` matches anything) with runs of whitespace read as one space, so
+/// `*rm -rf /` catches `sudo rm  -rf /` but not `rm -rf /tmp/x`. Deny rules are
+/// checked before confirm rules.
+use zeroize::Zeroize;
+
+use crate::config::matches_pattern;
+
+/// What a policy rule does with a matching command line
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PolicyAction {
+    Deny,
+    Confirm,
+}
+
+impl PolicyAction {
+    pub fn name(self) -> &'static str {
+        match self {
+            PolicyAction::Deny => "deny",
+            PolicyAction::Confirm => "confirm",
+        }
+    }
+}
+
+/// One `policy.deny` or `policy.confirm` line
+#[derive(Clone, Debug)]
+pub struct PolicyRule {
+    pub action: PolicyAction,
+    pub pattern: String,
+}
+
+/// The first rule matching `line`, deny rules first
+pub fn check<'a>(rules: &'a [PolicyRule], line: &str) -> Option<&'a PolicyRule> {
+    let mut line = normalize(line);
+    let rule = [PolicyAction::Deny, PolicyAction::Confirm]
+        .into_iter()
+        .find_map(|action| {
+            rules.iter().find(|rule| {
+                rule.action == action && matches_pattern(&normalize(&rule.pattern), &line)
+            })
+        });
+    line.zeroize();
+    rule
+}
+
+/// `text` with each run of whitespace replaced by one space
+fn normalize(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
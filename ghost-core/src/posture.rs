@@ -0,0 +1,86 @@
+/// Background security posture monitor feeding the status bar
+/// The debugger and monitoring checks in process_command only run between commands, so a
+/// tracer attached while the user sits at the prompt goes unnoticed until the next Enter.
+/// This thread repeats them on a fixed interval and keeps the latest result for display.
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::security::{detect_monitoring, is_debugger_present};
+
+/// How often the debugger and monitoring checks are repeated
+pub const MONITOR_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Granularity at which the monitor thread notices it should stop
+const STOP_POLL: Duration = Duration::from_millis(100);
+
+/// Latest result of the background checks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Posture {
+    /// False until the first round of checks has finished
+    pub checked: bool,
+    pub debugger: bool,
+    /// Number of monitoring indicators found (ptrace, LD_PRELOAD, tracers, ...)
+    pub monitors: usize,
+}
+
+/// Run one round of checks
+pub fn check_posture() -> Posture {
+    Posture {
+        checked: true,
+        debugger: is_debugger_present(),
+        monitors: detect_monitoring().len(),
+    }
+}
+
+/// Background thread refreshing a shared `Posture` every `MONITOR_INTERVAL`
+pub struct PostureMonitor {
+    posture: Arc<Mutex<Posture>>,
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl PostureMonitor {
+    pub fn start() -> Self {
+        let posture = Arc::new(Mutex::new(Posture::default()));
+        let stop = Arc::new(AtomicBool::new(false));
+        let (shared, stop_flag) = (Arc::clone(&posture), Arc::clone(&stop));
+        let handle = thread::spawn(move || {
+            while !stop_flag.load(Ordering::SeqCst) {
+                let current = check_posture();
+                if let Ok(mut posture) = shared.lock() {
+                    *posture = current;
+                }
+                let mut waited = Duration::ZERO;
+                while waited < MONITOR_INTERVAL && !stop_flag.load(Ordering::SeqCst) {
+                    thread::sleep(STOP_POLL);
+                    waited += STOP_POLL;
+                }
+            }
+        });
+        PostureMonitor {
+            posture,
+            stop,
+            handle: Some(handle),
+        }
+    }
+
+    /// Most recent posture; unchecked until the first round completes
+    pub fn posture(&self) -> Posture {
+        self.posture.lock().map(|p| *p).unwrap_or_default()
+    }
+
+    pub fn stop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Drop for PostureMonitor {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+    }
+}
@@ -0,0 +1,222 @@
+/// Named security profiles for `--profile` and ::profile
+/// A profile bundles the settings that otherwise have to be tuned one by one: paranoid
+/// mode and how often the periodic checks run, the name the process hides behind, the
+/// clipboard policy, which environment variables are scrubbed, the child sandbox and
+/// what ::panic does on the way out.
+use crate::clipboard::TamperResponse;
+use crate::sandbox::SandboxProfile;
+
+/// Credential-looking variables removed by the paranoid and stealth profiles
+const SECRET_ENV_PATTERNS: &[&str] = &[
+    "*_TOKEN",
+    "*_SECRET",
+    "*_SECRET_*",
+    "*_PASSWORD",
+    "*_API_KEY",
+    "AWS_ACCESS_KEY_ID",
+    "AWS_SECRET_ACCESS_KEY",
+    "AWS_SESSION_TOKEN",
+];
+
+/// Variables that make shells and tools write history files; stealth drops them too
+const HISTORY_ENV_PATTERNS: &[&str] = &["HISTFILE", "LESSHISTFILE", "PYTHONSTARTUP"];
+
+/// What ::panic shows before the shell disappears
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PanicStyle {
+    /// A fake kernel panic and core dump message
+    Kernel,
+    /// Nothing: the screen is wiped and the shell is gone
+    Silent,
+}
+
+impl PanicStyle {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "kernel" => Some(PanicStyle::Kernel),
+            "silent" => Some(PanicStyle::Silent),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PanicStyle::Kernel => "kernel",
+            PanicStyle::Silent => "silent",
+        }
+    }
+}
+
+/// The ::panic routine: how it looks, how the process ends and what it cleans up first
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PanicSettings {
+    pub style: PanicStyle,
+    pub exit_code: i32,
+    pub clear_clipboard: bool,
+    pub purge_vault: bool,
+    pub kill_children: bool,
+}
+
+impl PanicSettings {
+    /// One line for ::profile and ::config
+    pub fn describe(&self) -> String {
+        let mut actions = Vec::new();
+        if self.kill_children {
+            actions.push("kills children");
+        }
+        if self.clear_clipboard {
+            actions.push("clears clipboard");
+        }
+        if self.purge_vault {
+            actions.push("purges vault");
+        }
+        format!(
+            "{} message, exit {}{}{}",
+            self.style.name(),
+            self.exit_code,
+            if actions.is_empty() { "" } else { ", " },
+            actions.join(", ")
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SecurityProfile {
+    Standard,
+    Paranoid,
+    Stealth,
+}
+
+/// Everything a profile sets
+pub struct ProfileSettings {
+    pub paranoid: bool,
+    pub check_interval: usize, // Commands between periodic debugger / memory map checks
+    pub process_name: &'static str, // Linux process name (comm)
+    pub console_title: &'static str, // Windows console title
+    pub clipboard_timeout: u64, // Auto-clear in seconds
+    pub tamper_response: TamperResponse,
+    pub env_scrub: Vec<&'static str>,
+    pub sandbox: SandboxProfile,
+    pub panic: PanicSettings,
+}
+
+impl SecurityProfile {
+    pub const ALL: [SecurityProfile; 3] = [
+        SecurityProfile::Standard,
+        SecurityProfile::Paranoid,
+        SecurityProfile::Stealth,
+    ];
+
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "standard" => Some(SecurityProfile::Standard),
+            "paranoid" => Some(SecurityProfile::Paranoid),
+            "stealth" => Some(SecurityProfile::Stealth),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SecurityProfile::Standard => "standard",
+            SecurityProfile::Paranoid => "paranoid",
+            SecurityProfile::Stealth => "stealth",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            SecurityProfile::Standard => "defaults: checks every 5 commands, 30s clipboard",
+            SecurityProfile::Paranoid => {
+                "auto-panic, checks every command, 10s clipboard, sandboxed children"
+            }
+            SecurityProfile::Stealth => {
+                "kernel-thread process name, no history variables, 15s clipboard"
+            }
+        }
+    }
+
+    pub fn settings(&self) -> ProfileSettings {
+        match self {
+            SecurityProfile::Standard => ProfileSettings {
+                paranoid: false,
+                check_interval: 5,
+                process_name: "systemd-journald",
+                console_title: "Windows PowerShell",
+                clipboard_timeout: 30,
+                tamper_response: TamperResponse::Alert,
+                env_scrub: Vec::new(),
+                sandbox: SandboxProfile::Off,
+                panic: PanicSettings {
+                    style: PanicStyle::Kernel,
+                    exit_code: 137,
+                    clear_clipboard: true,
+                    purge_vault: true,
+                    kill_children: true,
+                },
+            },
+            SecurityProfile::Paranoid => ProfileSettings {
+                paranoid: true,
+                check_interval: 1,
+                process_name: "systemd-journald",
+                console_title: "Windows PowerShell",
+                clipboard_timeout: 10,
+                tamper_response: TamperResponse::Purge,
+                env_scrub: SECRET_ENV_PATTERNS.to_vec(),
+                sandbox: SandboxProfile::Standard,
+                panic: PanicSettings {
+                    style: PanicStyle::Kernel,
+                    exit_code: 137,
+                    clear_clipboard: true,
+                    purge_vault: true,
+                    kill_children: true,
+                },
+            },
+            SecurityProfile::Stealth => ProfileSettings {
+                paranoid: false,
+                check_interval: 5,
+                process_name: "kworker/u8:1",
+                console_title: "Administrator: Command Prompt",
+                clipboard_timeout: 15,
+                tamper_response: TamperResponse::Purge,
+                env_scrub: [SECRET_ENV_PATTERNS, HISTORY_ENV_PATTERNS].concat(),
+                sandbox: SandboxProfile::Off,
+                // A shell that just ends draws less attention than a kernel panic
+                panic: PanicSettings {
+                    style: PanicStyle::Silent,
+                    exit_code: 0,
+                    clear_clipboard: true,
+                    purge_vault: true,
+                    kill_children: true,
+                },
+            },
+        }
+    }
+}
+
+impl ProfileSettings {
+    /// Name the process currently shows under: comm on Linux, the console title on Windows
+    pub fn mask_name(&self) -> Option<&'static str> {
+        if cfg!(target_os = "linux") {
+            Some(self.process_name)
+        } else if cfg!(windows) {
+            Some(self.console_title)
+        } else {
+            None
+        }
+    }
+}
+
+/// Rename the process (Linux) or the console window (Windows)
+pub fn mask_process(settings: &ProfileSettings) {
+    #[cfg(target_os = "linux")]
+    {
+        let _ = prctl::set_name(settings.process_name);
+    }
+    #[cfg(windows)]
+    {
+        crate::security::mask_console_title(settings.console_title);
+    }
+    #[cfg(not(any(target_os = "linux", windows)))]
+    let _ = settings;
+}
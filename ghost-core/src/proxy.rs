@@ -0,0 +1,357 @@
+/// SOCKS5 / Tor routing for child commands (::proxy)
+/// Every spawned command gets the proxy in the variables proxy-aware tools read
+/// (ALL_PROXY, HTTPS_PROXY, ...), and on Linux also an LD_PRELOAD of torsocks (for Tor)
+/// or proxychains-ng (any SOCKS5 proxy) so tools that ignore those variables are
+/// forced through it too. Both are set per command, never in the shell's own
+/// environment. `::proxy check` compares the proxy's exit address with what a child
+/// actually reaches the network as.
+use std::env;
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::PathBuf;
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+/// Tor's default SOCKS port
+pub const TOR_SOCKS: &str = "127.0.0.1:9050";
+
+/// Tor Browser's bundled tor
+pub const TOR_BROWSER_SOCKS: &str = "127.0.0.1:9150";
+
+/// Answers with the caller's address as `{"IsTor":..,"IP":".."}`
+const CHECK_HOST: &str = "check.torproject.org";
+const CHECK_PATH: &str = "/api/ip";
+
+const CHECK_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// Variables proxy-aware tools read; both spellings since tools disagree
+const PROXY_VARS: &[&str] = &[
+    "ALL_PROXY",
+    "all_proxy",
+    "HTTPS_PROXY",
+    "https_proxy",
+    "HTTP_PROXY",
+    "http_proxy",
+    "FTP_PROXY",
+    "ftp_proxy",
+    "RSYNC_PROXY",
+];
+
+/// Where distributions install the preload libraries
+const LIBRARY_DIRS: &[&str] = &["/usr/lib", "/usr/lib64", "/usr/local/lib"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProxyScheme {
+    Socks5,
+    Http,
+}
+
+/// Proxy for child commands
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Proxy {
+    pub scheme: ProxyScheme,
+    pub host: String,
+    pub port: u16,
+    pub tor: bool, // Preload torsocks instead of proxychains
+}
+
+/// LD_PRELOAD library that forces a child's sockets through the proxy
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Preload {
+    Torsocks,
+    Proxychains,
+}
+
+impl Preload {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Preload::Torsocks => "torsocks",
+            Preload::Proxychains => "proxychains-ng",
+        }
+    }
+
+    /// File names across distributions
+    fn libraries(&self) -> &'static [&'static str] {
+        match self {
+            Preload::Torsocks => &["libtorsocks.so"],
+            Preload::Proxychains => &["libproxychains4.so", "libproxychains.so.4"],
+        }
+    }
+}
+
+impl Proxy {
+    /// Parse `socks5://host:port`, `socks5h://host:port` or `http://host:port`
+    pub fn parse(url: &str) -> Result<Self, String> {
+        let invalid = || {
+            format!(
+                "Invalid proxy: {} (expected socks5://host:port or http://host:port)",
+                url
+            )
+        };
+        let (scheme, address) = url.trim().split_once("://").ok_or_else(invalid)?;
+        let scheme = match scheme.to_ascii_lowercase().as_str() {
+            "socks5" | "socks5h" => ProxyScheme::Socks5,
+            "http" => ProxyScheme::Http,
+            _ => return Err(invalid()),
+        };
+        let address = address.trim_end_matches('/');
+        let (host, port) = address.rsplit_once(':').ok_or_else(invalid)?;
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        let port: u16 = port.parse().map_err(|_| invalid())?;
+        if host.is_empty() || port == 0 {
+            return Err(invalid());
+        }
+        Ok(Proxy {
+            scheme,
+            host: host.to_string(),
+            port,
+            tor: false,
+        })
+    }
+
+    /// A local Tor daemon's SOCKS port, `address` defaulting to 127.0.0.1:9050
+    pub fn tor(address: Option<&str>) -> Result<Self, String> {
+        let mut proxy = Proxy::parse(&format!("socks5://{}", address.unwrap_or(TOR_SOCKS)))?;
+        proxy.tor = true;
+        Ok(proxy)
+    }
+
+    /// URL for the proxy variables; `socks5h` so tools resolve names through the proxy
+    pub fn url(&self) -> String {
+        let host = if self.host.contains(':') {
+            format!("[{}]", self.host)
+        } else {
+            self.host.clone()
+        };
+        match self.scheme {
+            ProxyScheme::Socks5 => format!("socks5h://{}:{}", host, self.port),
+            ProxyScheme::Http => format!("http://{}:{}", host, self.port),
+        }
+    }
+
+    /// Preload library for this proxy, if one is installed
+    pub fn preload(&self) -> Option<(Preload, PathBuf)> {
+        if !cfg!(target_os = "linux") {
+            return None;
+        }
+        let preload = match (self.tor, self.scheme) {
+            (true, _) => Preload::Torsocks,
+            (false, ProxyScheme::Socks5) => Preload::Proxychains,
+            // proxychains only takes HTTP proxies from its config file
+            (false, ProxyScheme::Http) => return None,
+        };
+        preload
+            .libraries()
+            .iter()
+            .find_map(|name| find_library(name))
+            .map(|path| (preload, path))
+    }
+
+    /// Route `command` through the proxy
+    pub fn apply(&self, command: &mut Command) {
+        let url = self.url();
+        for var in PROXY_VARS {
+            command.env(var, &url);
+        }
+        command.env_remove("NO_PROXY").env_remove("no_proxy");
+
+        let Some((preload, library)) = self.preload() else {
+            return;
+        };
+        let mut preloads = library.to_string_lossy().into_owned();
+        if let Some(existing) = env::var_os("LD_PRELOAD").filter(|value| !value.is_empty()) {
+            preloads.push(' ');
+            preloads.push_str(&existing.to_string_lossy());
+        }
+        command.env("LD_PRELOAD", preloads);
+        let port = self.port.to_string();
+        match preload {
+            Preload::Torsocks => {
+                command
+                    .env("TORSOCKS_TOR_ADDRESS", &self.host)
+                    .env("TORSOCKS_TOR_PORT", &port);
+            }
+            Preload::Proxychains => {
+                command
+                    .env("PROXYCHAINS_SOCKS5_HOST", &self.host)
+                    .env("PROXYCHAINS_SOCKS5_PORT", &port)
+                    .env("PROXYCHAINS_QUIET_MODE", "1");
+            }
+        }
+    }
+
+    /// The address the proxy's traffic leaves from, and whether it is a Tor exit
+    pub fn exit_address(&self) -> Result<(String, bool), String> {
+        let mut stream = self.connect(CHECK_HOST, 80)?;
+        let target = match self.scheme {
+            ProxyScheme::Socks5 => CHECK_PATH.to_string(),
+            ProxyScheme::Http => format!("http://{}{}", CHECK_HOST, CHECK_PATH),
+        };
+        let request = format!(
+            "GET {} HTTP/1.1\r\nHost: {}\r\nUser-Agent: curl/8.5.0\r\nConnection: close\r\n\r\n",
+            target, CHECK_HOST
+        );
+        stream
+            .write_all(request.as_bytes())
+            .map_err(|e| format!("proxy: {}", e))?;
+        let mut response = Vec::new();
+        stream
+            .read_to_end(&mut response)
+            .map_err(|e| format!("proxy: {}", e))?;
+        parse_check_response(&String::from_utf8_lossy(&response))
+    }
+
+    /// Raw TCP tunnel to `host:port` (SOCKS5 CONNECT or HTTP CONNECT), e.g. for TLS
+    pub fn tunnel(&self, host: &str, port: u16) -> Result<TcpStream, String> {
+        let mut stream = self.connect(host, port)?;
+        if self.scheme == ProxyScheme::Http {
+            http_connect(&mut stream, host, port)?;
+        }
+        Ok(stream)
+    }
+
+    /// Check that a SOCKS5 server answers on the proxy port; returns the round trip
+    pub fn handshake(&self) -> Result<Duration, String> {
+        let started = Instant::now();
+        let mut stream = self.connect_proxy()?;
+        socks5_greeting(&mut stream)?;
+        Ok(started.elapsed())
+    }
+
+    fn connect_proxy(&self) -> Result<TcpStream, String> {
+        let proxy_address = (self.host.as_str(), self.port)
+            .to_socket_addrs()
+            .ok()
+            .and_then(|mut addresses| addresses.next())
+            .ok_or_else(|| format!("proxy: cannot resolve {}", self.host))?;
+        let stream = TcpStream::connect_timeout(&proxy_address, CHECK_TIMEOUT)
+            .map_err(|e| format!("proxy {}: {}", self.url(), e))?;
+        let _ = stream.set_read_timeout(Some(CHECK_TIMEOUT));
+        let _ = stream.set_write_timeout(Some(CHECK_TIMEOUT));
+        Ok(stream)
+    }
+
+    /// TCP stream to `host:port` through the proxy; names are resolved by the proxy
+    fn connect(&self, host: &str, port: u16) -> Result<TcpStream, String> {
+        let mut stream = self.connect_proxy()?;
+        if self.scheme == ProxyScheme::Socks5 {
+            socks5_connect(&mut stream, host, port)?;
+        }
+        Ok(stream)
+    }
+}
+
+/// SOCKS5 method negotiation (RFC 1928): no authentication
+fn socks5_greeting(stream: &mut TcpStream) -> Result<(), String> {
+    let io_error = |e: std::io::Error| format!("SOCKS5: {}", e);
+    stream.write_all(&[5, 1, 0]).map_err(io_error)?;
+    let mut reply = [0u8; 2];
+    stream.read_exact(&mut reply).map_err(io_error)?;
+    match reply {
+        [5, 0] => Ok(()),
+        [5, _] => Err("SOCKS5: proxy requires authentication.".to_string()),
+        _ => Err("SOCKS5: not a SOCKS5 server.".to_string()),
+    }
+}
+
+/// SOCKS5 handshake: greeting, then CONNECT by domain name
+fn socks5_connect(stream: &mut TcpStream, host: &str, port: u16) -> Result<(), String> {
+    let io_error = |e: std::io::Error| format!("SOCKS5: {}", e);
+    socks5_greeting(stream)?;
+
+    let host = host.as_bytes();
+    let length = u8::try_from(host.len()).map_err(|_| "SOCKS5: host name too long.")?;
+    let mut request = vec![5, 1, 0, 3, length];
+    request.extend_from_slice(host);
+    request.extend_from_slice(&port.to_be_bytes());
+    stream.write_all(&request).map_err(io_error)?;
+
+    let mut header = [0u8; 4];
+    stream.read_exact(&mut header).map_err(io_error)?;
+    if header[1] != 0 {
+        return Err(format!(
+            "SOCKS5: connection refused by proxy (code {}).",
+            header[1]
+        ));
+    }
+    // Skip the bound address
+    let address_len = match header[3] {
+        1 => 4,
+        4 => 16,
+        3 => {
+            let mut length = [0u8; 1];
+            stream.read_exact(&mut length).map_err(io_error)?;
+            length[0] as usize
+        }
+        _ => return Err("SOCKS5: malformed reply.".to_string()),
+    };
+    let mut bound = vec![0u8; address_len + 2];
+    stream.read_exact(&mut bound).map_err(io_error)
+}
+
+/// HTTP CONNECT (RFC 9110 9.3.6); the reply headers are consumed byte by byte so
+/// nothing of the tunnelled stream is read
+fn http_connect(stream: &mut TcpStream, host: &str, port: u16) -> Result<(), String> {
+    let io_error = |e: std::io::Error| format!("HTTP proxy: {}", e);
+    let authority = if host.contains(':') {
+        format!("[{}]:{}", host, port)
+    } else {
+        format!("{}:{}", host, port)
+    };
+    let request = format!(
+        "CONNECT {} HTTP/1.1\r\nHost: {}\r\n\r\n",
+        authority, authority
+    );
+    stream.write_all(request.as_bytes()).map_err(io_error)?;
+    let mut reply = Vec::new();
+    let mut byte = [0u8; 1];
+    while !reply.ends_with(b"\r\n\r\n") {
+        if reply.len() > 8192 {
+            return Err("HTTP proxy: malformed reply.".to_string());
+        }
+        stream.read_exact(&mut byte).map_err(io_error)?;
+        reply.push(byte[0]);
+    }
+    let reply = String::from_utf8_lossy(&reply);
+    let status = reply.lines().next().unwrap_or_default();
+    match status.split_whitespace().nth(1) {
+        Some(code) if code.starts_with('2') => Ok(()),
+        _ => Err(format!("HTTP proxy: tunnel refused ({}).", status.trim())),
+    }
+}
+
+/// `IP` and `IsTor` from a check.torproject.org reply (headers included)
+pub fn parse_check_response(response: &str) -> Result<(String, bool), String> {
+    let body = response
+        .split_once("\r\n\r\n")
+        .map(|(_, body)| body)
+        .unwrap_or(response);
+    let ip = body
+        .split_once("\"IP\":\"")
+        .and_then(|(_, rest)| rest.split_once('"'))
+        .map(|(ip, _)| ip.to_string())
+        .ok_or_else(|| "unexpected answer from the address check.".to_string())?;
+    Ok((ip, body.contains("\"IsTor\":true")))
+}
+
+/// Command a child runs for the leak check
+pub fn check_command() -> Option<String> {
+    crate::sandbox::find_in_path("curl").map(|_| {
+        format!(
+            "curl -s --max-time {} http://{}{}",
+            CHECK_TIMEOUT.as_secs(),
+            CHECK_HOST,
+            CHECK_PATH
+        )
+    })
+}
+
+fn find_library(name: &str) -> Option<PathBuf> {
+    let arch_dir = PathBuf::from(format!("/usr/lib/{}-linux-gnu", env::consts::ARCH));
+    LIBRARY_DIRS
+        .iter()
+        .map(PathBuf::from)
+        .chain([arch_dir])
+        .flat_map(|dir| [dir.join(name), dir.join("torsocks").join(name)])
+        .find(|candidate| candidate.is_file())
+}
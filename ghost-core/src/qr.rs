@@ -0,0 +1,19 @@
+/// Terminal QR code rendering
+/// Lets secrets cross an air gap to a phone camera without ever touching the clipboard
+use qrcode::render::unicode;
+use qrcode::QrCode;
+
+/// Render `data` as a QR code made of half-block characters, ready for the raw-mode terminal
+pub fn render_qr(data: &str) -> Result<String, String> {
+    let code = QrCode::new(data.as_bytes()).map_err(|e| format!("QR encoding failed: {}", e))?;
+
+    // Inverted colors read correctly on the usual dark terminal background
+    let rendered = code
+        .render::<unicode::Dense1x2>()
+        .dark_color(unicode::Dense1x2::Light)
+        .light_color(unicode::Dense1x2::Dark)
+        .quiet_zone(true)
+        .build();
+
+    Ok(rendered.replace('\n', "\r\n"))
+}
@@ -0,0 +1,894 @@
+/// Classifying command lines for `::readonly on`, which lets inspection through and
+/// stops anything that would change the system
+/// A line counts as mutating when any command in it (after `;`, `&&`, `||`, `|` or `&`)
+/// writes, deletes, installs, signals processes or pushes over the network, when it
+/// redirects output into a file, or when it is a ghost command that writes files or
+/// sends data. Commands nested in `$(…)`, backticks, `<(…)`, `>(…)`, `( )` subshells
+/// and `{ }` groups are judged the same way. What can't be judged is refused: a command
+/// name built by substitution, code handed to an interpreter (`sh -c`, `python -c`,
+/// `perl -e`), commands run by `find -exec` or `xargs`, and plugin `::` commands.
+/// Commands are judged by name and a few well-known subcommands and options, so this
+/// guards against slips rather than sandboxing: an interpreter running a script file
+/// can still change anything.
+use crate::commands;
+use std::iter::Peekable;
+use std::str::Chars;
+use zeroize::Zeroize;
+
+/// Commands that change files, processes or system state whatever their arguments
+const MUTATING: &[&str] = &[
+    "rm",
+    "rmdir",
+    "unlink",
+    "mv",
+    "cp",
+    "dd",
+    "mkdir",
+    "mknod",
+    "mkfifo",
+    "touch",
+    "chmod",
+    "chown",
+    "chgrp",
+    "chattr",
+    "setfacl",
+    "ln",
+    "truncate",
+    "shred",
+    "install",
+    "patch",
+    "mount",
+    "umount",
+    "swapon",
+    "swapoff",
+    "mkfs",
+    "mkswap",
+    "fdisk",
+    "parted",
+    "kill",
+    "pkill",
+    "killall",
+    "reboot",
+    "shutdown",
+    "poweroff",
+    "halt",
+    "useradd",
+    "userdel",
+    "usermod",
+    "groupadd",
+    "groupdel",
+    "passwd",
+    "chpasswd",
+    "visudo",
+    "vi",
+    "vim",
+    "nvim",
+    "nano",
+    "emacs",
+    "ed",
+    "scp",
+    "sftp",
+    "ftp",
+    "rsync",
+    "wget",
+    "dpkg",
+    "rpm",
+    "insmod",
+    "rmmod",
+    "modprobe",
+    "setenforce",
+    "ssh-keygen",
+];
+
+/// Commands that change nothing unless their first argument is not one of these
+const READ_ONLY_SUBCOMMANDS: &[(&str, &[&str])] = &[
+    (
+        "git",
+        &[
+            "status",
+            "log",
+            "show",
+            "diff",
+            "blame",
+            "grep",
+            "ls-files",
+            "ls-tree",
+            "ls-remote",
+            "rev-parse",
+            "rev-list",
+            "describe",
+            "shortlog",
+            "cat-file",
+            "show-ref",
+            "whatchanged",
+            "count-objects",
+            "help",
+            "version",
+            "--version",
+        ],
+    ),
+    (
+        "systemctl",
+        &[
+            "status",
+            "show",
+            "cat",
+            "list-units",
+            "list-unit-files",
+            "list-timers",
+            "list-sockets",
+            "list-dependencies",
+            "is-active",
+            "is-enabled",
+            "is-failed",
+            "--version",
+        ],
+    ),
+    (
+        "docker",
+        &[
+            "ps", "images", "inspect", "logs", "version", "info", "top", "stats", "history",
+            "diff", "events", "port", "search",
+        ],
+    ),
+    (
+        "podman",
+        &[
+            "ps", "images", "inspect", "logs", "version", "info", "top", "stats", "history",
+            "diff", "events", "port", "search",
+        ],
+    ),
+    (
+        "kubectl",
+        &[
+            "get",
+            "describe",
+            "logs",
+            "top",
+            "explain",
+            "version",
+            "api-resources",
+            "api-versions",
+            "cluster-info",
+            "auth",
+            "diff",
+        ],
+    ),
+];
+
+/// Package managers and the subcommands that install, remove or publish
+const PACKAGE_MANAGERS: &[(&str, &[&str])] = &[
+    (
+        "apt",
+        &[
+            "install",
+            "remove",
+            "purge",
+            "upgrade",
+            "full-upgrade",
+            "autoremove",
+            "update",
+        ],
+    ),
+    (
+        "apt-get",
+        &[
+            "install",
+            "remove",
+            "purge",
+            "upgrade",
+            "dist-upgrade",
+            "autoremove",
+            "update",
+        ],
+    ),
+    (
+        "dnf",
+        &[
+            "install",
+            "remove",
+            "erase",
+            "upgrade",
+            "update",
+            "autoremove",
+            "downgrade",
+        ],
+    ),
+    (
+        "yum",
+        &[
+            "install",
+            "remove",
+            "erase",
+            "upgrade",
+            "update",
+            "autoremove",
+            "downgrade",
+        ],
+    ),
+    ("apk", &["add", "del", "upgrade", "update"]),
+    ("pip", &["install", "uninstall", "download"]),
+    ("pip3", &["install", "uninstall", "download"]),
+    (
+        "npm",
+        &["install", "i", "uninstall", "update", "publish", "link"],
+    ),
+    (
+        "cargo",
+        &[
+            "install",
+            "uninstall",
+            "publish",
+            "build",
+            "update",
+            "new",
+            "init",
+        ],
+    ),
+    ("gem", &["install", "uninstall", "update", "push"]),
+    (
+        "brew",
+        &[
+            "install",
+            "uninstall",
+            "upgrade",
+            "update",
+            "link",
+            "unlink",
+        ],
+    ),
+    ("snap", &["install", "remove", "refresh"]),
+    ("flatpak", &["install", "uninstall", "update"]),
+];
+
+/// Shell keywords that may come before a command (`if rm x; then rm y; fi`)
+const KEYWORDS: &[&str] = &[
+    "{", "!", "if", "then", "elif", "else", "while", "until", "do",
+];
+
+/// Interpreters and the options that hand them code on the command line
+const INTERPRETERS: &[(&str, &[&str])] = &[
+    ("sh", &["-c"]),
+    ("bash", &["-c"]),
+    ("zsh", &["-c"]),
+    ("dash", &["-c"]),
+    ("ksh", &["-c"]),
+    ("fish", &["-c", "--command"]),
+    ("python", &["-c"]),
+    ("python2", &["-c"]),
+    ("python3", &["-c"]),
+    ("perl", &["-e", "-E"]),
+    ("ruby", &["-e"]),
+    ("node", &["-e", "--eval", "-p", "--print"]),
+    ("php", &["-r"]),
+    ("lua", &["-e"]),
+];
+
+/// Commands and wrappers nested more deeply than this are refused unchecked
+const MAX_NESTING: usize = 16;
+
+/// Stands in a word for text a substitution will produce
+const SUBSTITUTED: char = '\u{0}';
+
+/// Prefixes that run the next word as the command
+const WRAPPERS: &[&str] = &[
+    "sudo", "doas", "env", "nohup", "nice", "ionice", "time", "timeout", "stdbuf", "command",
+    "exec", "xargs", "watch", "strace", "ltrace",
+];
+
+/// Ghost commands that write files or send data, whatever their arguments
+const MUTATING_GHOST: &[&str] = &[
+    "encrypt-file",
+    "decrypt-file",
+    "out",
+    "scrub",
+    "timestomp",
+    "touch-match",
+    "wipe-free",
+    "share",
+    "send",
+    "recv",
+];
+
+/// Redirection targets that are not files
+const HARMLESS_TARGETS: &[&str] = &["/dev/null", "/dev/stdout", "/dev/stderr", "/dev/tty"];
+
+enum Token {
+    Word(String),
+    /// `;`, `&`, `|`, `&&` or `||`: the next word starts a new command
+    Separator,
+    /// `>` or `>>` writing to the next word
+    Redirect,
+    /// The commands inside `$(…)`, backticks, `<(…)`, `>(…)` or a `( )` subshell
+    Nested(String),
+}
+
+impl Drop for Token {
+    fn drop(&mut self) {
+        if let Token::Word(text) | Token::Nested(text) = self {
+            text.zeroize();
+        }
+    }
+}
+
+/// The text up to the `)` matching one just read, which is consumed too. Quotes and
+/// escapes are kept as written so the text can be split again.
+fn enclosed(chars: &mut Peekable<Chars>) -> String {
+    let mut text = String::new();
+    let mut depth = 0;
+    while let Some(c) = chars.next() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 0 => break,
+            ')' => depth -= 1,
+            '\\' => {
+                text.push(c);
+                text.extend(chars.next());
+                continue;
+            }
+            '\'' => {
+                text.push(c);
+                text.extend(chars.by_ref().take_while(|c| *c != '\''));
+                text.push(c);
+                continue;
+            }
+            '"' => {
+                text.push(c);
+                while let Some(c) = chars.next() {
+                    text.push(c);
+                    match c {
+                        '"' => break,
+                        '\\' => text.extend(chars.next()),
+                        _ => {}
+                    }
+                }
+                continue;
+            }
+            _ => {}
+        }
+        text.push(c);
+    }
+    text
+}
+
+/// The text up to the closing backtick, which is consumed too, with `\``, `\$` and
+/// `\\` unescaped as the shell does
+fn backquoted(chars: &mut Peekable<Chars>) -> String {
+    let mut text = String::new();
+    while let Some(c) = chars.next() {
+        match c {
+            '`' => break,
+            '\\' => match chars.next() {
+                Some(next @ ('`' | '$' | '\\')) => text.push(next),
+                Some(next) => {
+                    text.push(c);
+                    text.push(next);
+                }
+                None => text.push(c),
+            },
+            c => text.push(c),
+        }
+    }
+    text
+}
+
+/// Split a line into words, separators, output redirections and nested commands, with
+/// quotes removed. A substitution leaves `SUBSTITUTED` in its word; `2>&1`-style
+/// descriptor copies are dropped.
+fn tokenize(line: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut word = String::new();
+    let mut quoted = false; // The current word had quotes, so it exists even if empty
+    let mut chars = line.chars().peekable();
+    let end_word = |word: &mut String, quoted: &mut bool, tokens: &mut Vec<Token>| {
+        if !word.is_empty() || *quoted {
+            tokens.push(Token::Word(std::mem::take(word)));
+        }
+        *quoted = false;
+    };
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => {
+                quoted = true;
+                word.extend(chars.by_ref().take_while(|c| *c != '\''));
+            }
+            '"' => {
+                quoted = true;
+                while let Some(c) = chars.next() {
+                    match c {
+                        '"' => break,
+                        '\\' => word.extend(chars.next()),
+                        '$' if chars.peek() == Some(&'(') => {
+                            chars.next();
+                            tokens.push(Token::Nested(enclosed(&mut chars)));
+                            word.push(SUBSTITUTED);
+                        }
+                        '`' => {
+                            tokens.push(Token::Nested(backquoted(&mut chars)));
+                            word.push(SUBSTITUTED);
+                        }
+                        c => word.push(c),
+                    }
+                }
+            }
+            '\\' => word.extend(chars.next()),
+            '$' | '<' | '>' if chars.peek() == Some(&'(') => {
+                chars.next();
+                tokens.push(Token::Nested(enclosed(&mut chars)));
+                word.push(SUBSTITUTED);
+            }
+            '`' => {
+                tokens.push(Token::Nested(backquoted(&mut chars)));
+                word.push(SUBSTITUTED);
+            }
+            // A subshell
+            '(' if word.is_empty() && !quoted => tokens.push(Token::Nested(enclosed(&mut chars))),
+            ';' | '|' | '&' | '\n' => {
+                // `&>` redirects both streams
+                if c == '&' && chars.peek() == Some(&'>') {
+                    end_word(&mut word, &mut quoted, &mut tokens);
+                    continue;
+                }
+                end_word(&mut word, &mut quoted, &mut tokens);
+                if matches!(c, '|' | '&') && chars.peek() == Some(&c) {
+                    chars.next();
+                }
+                tokens.push(Token::Separator);
+            }
+            '>' => {
+                // A descriptor number belongs to the redirection, not the command
+                if !quoted && !word.is_empty() && word.chars().all(|c| c.is_ascii_digit()) {
+                    word.zeroize();
+                }
+                end_word(&mut word, &mut quoted, &mut tokens);
+                if chars.peek() == Some(&'>') {
+                    chars.next();
+                }
+                if chars.peek() == Some(&'&') {
+                    // `>&2`: a copy of another descriptor
+                    chars.next();
+                    while chars
+                        .peek()
+                        .is_some_and(|c| c.is_ascii_digit() || *c == '-')
+                    {
+                        chars.next();
+                    }
+                } else {
+                    tokens.push(Token::Redirect);
+                }
+            }
+            c if c.is_whitespace() => end_word(&mut word, &mut quoted, &mut tokens),
+            c => word.push(c),
+        }
+    }
+    end_word(&mut word, &mut quoted, &mut tokens);
+    tokens
+}
+
+/// Why the external command line `line` would change the system, or None when it
+/// only inspects
+pub fn mutation(line: &str) -> Option<String> {
+    if line.contains(" ::> ") {
+        return Some("`::>` writes an encrypted file".to_string());
+    }
+    nested_mutation(line, 0)
+}
+
+/// `mutation` for commands nested `depth` levels deep
+fn nested_mutation(line: &str, depth: usize) -> Option<String> {
+    if depth > MAX_NESTING {
+        return Some("commands nested too deeply to check".to_string());
+    }
+    let tokens = tokenize(line);
+    let mut command: Vec<&str> = Vec::new();
+    let mut redirect = false;
+    for token in tokens.iter().chain([&Token::Separator]) {
+        match token {
+            Token::Word(word) if redirect => {
+                redirect = false;
+                if word.contains(SUBSTITUTED) {
+                    return Some("output redirected into a substituted file name".to_string());
+                }
+                if !HARMLESS_TARGETS.contains(&word.as_str()) {
+                    return Some(format!("output redirected into {}", word));
+                }
+            }
+            Token::Word(word) => command.push(word),
+            Token::Redirect => redirect = true,
+            Token::Nested(text) => {
+                if let Some(reason) = nested_mutation(text, depth + 1) {
+                    return Some(reason);
+                }
+            }
+            Token::Separator => {
+                if let Some(reason) = command_mutation(&command) {
+                    return Some(reason);
+                }
+                command.clear();
+            }
+        }
+    }
+    None
+}
+
+/// The programs the simple commands of `line` run, wrappers like sudo included
+/// (`/usr/bin/git` is `git`)
+pub fn programs(line: &str) -> Vec<String> {
+    nested_programs(line, 0)
+}
+
+/// `programs` for commands nested `depth` levels deep
+fn nested_programs(line: &str, depth: usize) -> Vec<String> {
+    if depth > MAX_NESTING {
+        return Vec::new();
+    }
+    let tokens = tokenize(line);
+    let mut programs = Vec::new();
+    let mut command: Vec<&str> = Vec::new();
+    let mut redirect = false;
+    for token in tokens.iter().chain([&Token::Separator]) {
+        match token {
+            Token::Word(_) if redirect => redirect = false,
+            Token::Word(word) => command.push(word),
+            Token::Redirect => redirect = true,
+            Token::Nested(text) => programs.extend(nested_programs(text, depth + 1)),
+            Token::Separator => {
+                // Wrappers count too: `sudo git` runs both
+                let program = unwrap_command(&command);
+                let wrappers = &command[..command.len() - program.len()];
+                programs.extend(
+                    wrappers
+                        .iter()
+                        .map(|word| base_name(word))
+                        .filter(|name| WRAPPERS.contains(name))
+                        .map(str::to_string),
+                );
+                if let Some(first) = program.first() {
+                    programs.push(base_name(first).to_string());
+                }
+                command.clear();
+            }
+        }
+    }
+    programs
+}
+
+/// A simple command's words from the program on: leading `NAME=value` assignments,
+/// shell keywords and wrappers like sudo come before it
+fn unwrap_command<'a>(words: &'a [&'a str]) -> &'a [&'a str] {
+    let mut words = words;
+    loop {
+        match words.split_first() {
+            Some((first, rest)) if is_assignment(first) || KEYWORDS.contains(first) => words = rest,
+            Some((first, rest)) if WRAPPERS.contains(&base_name(first)) => {
+                // Their own options (and timeout's duration) come first
+                let skip = rest
+                    .iter()
+                    .take_while(|word| {
+                        word.starts_with('-')
+                            || is_assignment(word)
+                            || word.chars().next().is_some_and(|c| c.is_ascii_digit())
+                    })
+                    .count();
+                words = &rest[skip..];
+            }
+            _ => return words,
+        }
+    }
+}
+
+/// Why one simple command (its words, variable assignments included) is mutating
+fn command_mutation(words: &[&str]) -> Option<String> {
+    let program = unwrap_command(words);
+    let wrappers = &words[..words.len() - program.len()];
+    if wrappers.iter().any(|word| base_name(word) == "xargs") {
+        return Some("`xargs` runs commands built from its input".to_string());
+    }
+    let (first, args) = program.split_first()?;
+    if first.contains(SUBSTITUTED) {
+        return Some("the command name comes from a substitution".to_string());
+    }
+    let name = base_name(first);
+    let subcommand = args
+        .iter()
+        .find(|arg| !arg.starts_with('-'))
+        .copied()
+        .unwrap_or_default();
+    let has = |options: &[&str]| {
+        args.iter().any(|arg| {
+            options
+                .iter()
+                .any(|option| arg == option || arg.starts_with(&format!("{}=", option)))
+        })
+    };
+
+    if MUTATING.contains(&name) || name.starts_with("mkfs.") {
+        return Some(format!("`{}` changes the system", name));
+    }
+    if let Some((_, read_only)) = READ_ONLY_SUBCOMMANDS.iter().find(|(n, _)| *n == name) {
+        let first_arg = args.first().copied().unwrap_or_default();
+        // A bare `git branch`/`git tag`/`git remote` only lists
+        let listing = name == "git"
+            && matches!(first_arg, "branch" | "tag" | "remote" | "stash")
+            && args[1..]
+                .iter()
+                .all(|arg| matches!(*arg, "-a" | "-r" | "-v" | "-vv" | "-l" | "--list" | "list"));
+        if !first_arg.is_empty() && !read_only.contains(&first_arg) && !listing {
+            return Some(format!("`{} {}` changes the system", name, first_arg));
+        }
+        return None;
+    }
+    if let Some((_, options)) = INTERPRETERS.iter().find(|(n, _)| *n == name) {
+        // Also clustered, as in `bash -lc` or `perl -ne`
+        let inline = args.iter().any(|arg| {
+            options.iter().any(|option| {
+                arg == option
+                    || (option.len() == 2
+                        && !arg.starts_with("--")
+                        && arg.starts_with('-')
+                        && arg.ends_with(&option[1..])
+                        && arg[1..].chars().all(|c| c.is_ascii_alphabetic()))
+            })
+        });
+        if inline {
+            return Some(format!("`{}` runs code that isn't checked", name));
+        }
+    }
+    if let Some((_, mutating)) = PACKAGE_MANAGERS.iter().find(|(n, _)| *n == name) {
+        return mutating
+            .contains(&subcommand)
+            .then(|| format!("`{} {}` changes installed software", name, subcommand));
+    }
+    let changes = |what: &str| Some(format!("`{}` {}", name, what));
+    match name {
+        "pacman"
+            if args.iter().any(|arg| {
+                arg.starts_with("-S") || arg.starts_with("-R") || arg.starts_with("-U")
+            }) =>
+        {
+            changes("changes installed software")
+        }
+        "sed" | "perl" | "ruby"
+            if args.iter().any(|arg| {
+                arg.starts_with("--in-place")
+                    || (arg.starts_with('-')
+                        && !arg.starts_with("--")
+                        && !arg.starts_with("-M")
+                        && !arg.starts_with("-I")
+                        && arg.contains('i'))
+            }) =>
+        {
+            changes("-i edits files in place")
+        }
+        "tee"
+            if args
+                .iter()
+                .any(|arg| !arg.starts_with('-') && !HARMLESS_TARGETS.contains(arg)) =>
+        {
+            changes("writes files")
+        }
+        "curl"
+            if has(&[
+                "-d",
+                "--data",
+                "--data-binary",
+                "--data-raw",
+                "--data-urlencode",
+                "--json",
+                "-F",
+                "--form",
+                "-T",
+                "--upload-file",
+            ]) || args.windows(2).any(|pair| {
+                matches!(pair[0], "-X" | "--request") && !matches!(pair[1], "GET" | "HEAD")
+            }) =>
+        {
+            changes("sends data")
+        }
+        "curl" if has(&["-o", "--output", "-O", "--remote-name"]) => changes("writes files"),
+        "crontab" if !has(&["-l"]) => changes("changes scheduled jobs"),
+        "sysctl"
+            if has(&["-w", "--write", "-p", "--load"])
+                || args.iter().any(|arg| arg.contains('=')) =>
+        {
+            changes("changes kernel settings")
+        }
+        "hostname" if !subcommand.is_empty() => changes("with an argument sets it"),
+        // `date +%F` only formats
+        "date"
+            if has(&["-s", "--set"]) || !(subcommand.is_empty() || subcommand.starts_with('+')) =>
+        {
+            changes("with an argument sets it")
+        }
+        "find" if has(&["-delete"]) => changes("-delete removes files"),
+        "find" if has(&["-exec", "-execdir", "-ok", "-okdir"]) => {
+            changes("-exec runs commands that aren't checked")
+        }
+        "find" if has(&["-fprint", "-fprint0", "-fprintf", "-fls"]) => changes("writes files"),
+        "awk" | "gawk" | "mawk" | "nawk" if args.iter().any(|arg| awk_side_effects(arg)) => {
+            changes("can run commands or write files")
+        }
+        _ => None,
+    }
+}
+
+/// Why the ghost command `cmd` with `args` would write files or send data
+pub fn ghost_mutation(cmd: &str, args: &str) -> Option<String> {
+    let words: Vec<&str> = args.split_whitespace().collect();
+    let first = words.first().copied().unwrap_or_default();
+    let writes = match cmd {
+        "workspace" => !first.is_empty(),
+        "decrypt" => words.contains(&"-o"),
+        "age-keygen" => words.contains(&"-o"),
+        "history" => first == "export" || (first == "persist" && words.get(1) == Some(&"on")),
+        "honeypot" => matches!(first, "arm" | "disarm" | "clear"),
+        "isolate" | "trace" => return mutation(args),
+        // ::paste [key] -- <command> pipes the clipboard into <command>
+        "paste" => match args
+            .strip_prefix("--")
+            .or(args.split_once(" --").map(|(_, rest)| rest))
+        {
+            Some(feed_command) => return mutation(feed_command),
+            None => false,
+        },
+        // Plugins are executables the shell knows nothing about
+        cmd if commands::find(cmd).is_none() => {
+            return Some(format!(
+                "`::{}` is not built in, so it can't be checked",
+                cmd
+            ))
+        }
+        "sandbox" if first == "run" => {
+            return mutation(args.trim_start().trim_start_matches("run"))
+        }
+        cmd => MUTATING_GHOST.contains(&cmd),
+    };
+    writes.then(|| format!("`::{}` writes files or sends data", cmd))
+}
+
+/// Whether an awk program runs commands (`system()`, a pipe) or prints into a named
+/// file. A comparison like `$3 > 100` doesn't count.
+fn awk_side_effects(program: &str) -> bool {
+    let redirects = program.match_indices('>').any(|(i, _)| {
+        let rest = program[i + 1..].trim_start();
+        rest.starts_with('"') || rest.starts_with('>')
+    });
+    program.contains("system") || program.replace("||", "").contains('|') || redirects
+}
+
+fn is_assignment(word: &str) -> bool {
+    word.split_once('=').is_some_and(|(name, _)| {
+        !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    })
+}
+
+/// `/usr/bin/rm` → `rm`
+fn base_name(word: &str) -> &str {
+    word.rsplit('/').next().unwrap_or(word)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn refused(line: &str) -> bool {
+        mutation(line).is_some()
+    }
+
+    #[test]
+    fn inspection_runs() {
+        for line in [
+            "ls -la /etc",
+            "cat /etc/passwd | grep root",
+            "git log --oneline",
+            "echo $(date +%Y) \"$(ls | wc -l)\" `pwd`",
+            "diff <(ls a) <(ls b)",
+            "(cd /tmp && ls) | sort",
+            "ls 2>/dev/null",
+            "awk '$3 > 100 { print $1 }' data",
+            "for f in *; do wc -l \"$f\"; done",
+            "grep -r '$(rm x)' .",
+            "X=$(date) env",
+        ] {
+            assert_eq!(mutation(line), None, "{}", line);
+        }
+    }
+
+    #[test]
+    fn plain_writes_are_refused() {
+        for line in [
+            "rm x",
+            "ls > out",
+            "sudo rm -rf /tmp/x",
+            "git push",
+            "sed -i s/a/b/ f",
+            "date -s 2020-01-01",
+            "date --set=2020-01-01",
+        ] {
+            assert!(refused(line), "{}", line);
+        }
+    }
+
+    #[test]
+    fn nested_commands_are_checked() {
+        for line in [
+            "echo $(rm x)",
+            "echo `touch q`",
+            "echo \"$(rm x)\"",
+            "echo \"`touch q`\"",
+            "echo a$(rm x)b",
+            "cat <(rm x)",
+            "tee >(rm x) < f",
+            "(rm x)",
+            "ls; (cd /tmp && rm x)",
+            "{ rm x; }",
+            "if true; then rm x; fi",
+            "while false; do touch q; done",
+            "! rm x",
+            "echo $(echo $(echo $(rm x)))",
+            "echo $(echo ')' ; rm x)",
+            "$(echo rm) x",
+            "sudo `echo rm` x",
+            "ls > $(echo out)",
+        ] {
+            assert!(refused(line), "{}", line);
+        }
+        let deep = format!("{}rm x{}", "$(echo ".repeat(100), ")".repeat(100));
+        assert!(refused(&deep));
+    }
+
+    #[test]
+    fn unchecked_code_is_refused() {
+        for line in [
+            "sh -c 'rm x'",
+            "bash -c ls",
+            "bash -lc 'rm x'",
+            "python3 -c 'import os; os.remove(\"x\")'",
+            "python -Ic 'print(1)'",
+            "perl -e 'unlink \"x\"'",
+            "perl -ne print f",
+            "ruby -e 'File.delete(\"x\")'",
+            "node -e 'require(\"fs\").unlinkSync(\"x\")'",
+            "find . -delete",
+            "find . -name '*.log' -exec rm {} +",
+            "find . -execdir cat {} ;",
+            "find . -fprint out",
+            "echo x | xargs rm",
+            "xargs -a list cat",
+            "awk 'BEGIN { system(\"rm x\") }'",
+            "awk '{ print > \"out\" }' f",
+            "awk '{ print | \"sh\" }' f",
+        ] {
+            assert!(refused(line), "{}", line);
+        }
+    }
+
+    #[test]
+    fn nested_programs_are_listed() {
+        let programs = programs("echo $(git log) | sudo `which curl`");
+        for program in ["echo", "git", "sudo", "which"] {
+            assert!(programs.iter().any(|p| p == program), "{}", program);
+        }
+    }
+
+    #[test]
+    fn ghost_commands_are_checked() {
+        for (cmd, args) in [
+            ("status", ""),
+            ("paste", ""),
+            ("paste", "work"),
+            ("readonly", "off"),
+        ] {
+            assert_eq!(ghost_mutation(cmd, args), None, "::{} {}", cmd, args);
+        }
+        for (cmd, args) in [
+            ("paste", "-- rm -rf x"),
+            ("paste", "work -- tee out"),
+            ("paste", "--cat > out"),
+            ("share", "file"),
+            ("my-plugin", ""),
+        ] {
+            assert!(ghost_mutation(cmd, args).is_some(), "::{} {}", cmd, args);
+        }
+    }
+}
@@ -0,0 +1,597 @@
+/// Small regular expression matcher for `::history search` and sensitive-input detection
+/// Supports literals, `.`, `^`, `$`, classes (`[a-z]`, `[^0-9]`), the escapes `\d \w \s`
+/// (and their negations), groups, alternation and the quantifiers `* + ? {n} {n,} {n,m}`.
+/// Patterns compile to a Thompson NFA run as a Pike VM, so matching stays linear in the
+/// input however the pattern is written. Only "does it match anywhere" is answered.
+#[derive(Debug, Clone)]
+pub struct Regex {
+    program: Vec<Inst>,
+    ignore_case: bool,
+}
+
+/// Repetition counts above this are refused (each one copies the repeated program)
+const MAX_REPEAT: u32 = 1000;
+
+/// Compiled programs beyond this many instructions are refused (nested counted repeats)
+const MAX_PROGRAM: usize = 100_000;
+
+/// Groups and stacked quantifiers nested deeper than this are refused (parsing and
+/// compiling recurse per level)
+const MAX_NESTING: usize = 100;
+
+#[derive(Debug, Clone)]
+enum ClassItem {
+    Range(char, char),
+    Digit(bool), // \d, or \D when false
+    Word(bool),  // \w / \W
+    Space(bool), // \s / \S
+}
+
+impl ClassItem {
+    fn matches(&self, c: char) -> bool {
+        match *self {
+            ClassItem::Range(low, high) => low <= c && c <= high,
+            ClassItem::Digit(yes) => c.is_ascii_digit() == yes,
+            ClassItem::Word(yes) => (c.is_alphanumeric() || c == '_') == yes,
+            ClassItem::Space(yes) => c.is_whitespace() == yes,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Class {
+    negated: bool,
+    items: Vec<ClassItem>,
+}
+
+impl Class {
+    fn matches(&self, c: char, ignore_case: bool) -> bool {
+        let hit = |c: char| self.items.iter().any(|item| item.matches(c));
+        let found = if ignore_case {
+            c.to_lowercase().any(hit) || c.to_uppercase().any(hit)
+        } else {
+            hit(c)
+        };
+        found != self.negated
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Empty,
+    Char(char),
+    Any,
+    Class(Class),
+    Start,
+    End,
+    Concat(Vec<Node>),
+    Alternate(Vec<Node>),
+    Repeat {
+        node: Box<Node>,
+        min: u32,
+        max: Option<u32>,
+    },
+}
+
+#[derive(Debug, Clone)]
+enum Inst {
+    Char(char),
+    Any,
+    Class(Class),
+    Start,
+    End,
+    Split(usize, usize),
+    Jump(usize),
+    Match,
+}
+
+impl Regex {
+    pub fn new(pattern: &str) -> Result<Self, String> {
+        Self::build(pattern, false)
+    }
+
+    /// Letters match regardless of case
+    pub fn case_insensitive(pattern: &str) -> Result<Self, String> {
+        Self::build(pattern, true)
+    }
+
+    fn build(pattern: &str, ignore_case: bool) -> Result<Self, String> {
+        let mut parser = Parser {
+            chars: pattern.chars().collect(),
+            pos: 0,
+            depth: 0,
+        };
+        let node = parser.alternation()?;
+        if parser.pos < parser.chars.len() {
+            return Err(format!("Invalid pattern '{}': unmatched ')'.", pattern));
+        }
+        let mut program = Vec::new();
+        compile(&node, &mut program, ignore_case);
+        if program.len() > MAX_PROGRAM {
+            return Err(format!("Invalid pattern '{}': too large.", pattern));
+        }
+        program.push(Inst::Match);
+        Ok(Regex {
+            program,
+            ignore_case,
+        })
+    }
+
+    /// Instructions in the compiled program; matching costs up to this much per character
+    pub fn size(&self) -> usize {
+        self.program.len()
+    }
+
+    /// Whether the pattern matches anywhere in `text`
+    pub fn is_match(&self, text: &str) -> bool {
+        let chars: Vec<char> = text.chars().collect();
+        let len = chars.len();
+        let mut current = Vec::new();
+        let mut seen = vec![false; self.program.len()];
+        if self.add(&mut current, &mut seen, 0, 0, len) {
+            return true;
+        }
+        for (at, &c) in chars.iter().enumerate() {
+            let mut next = Vec::new();
+            seen.iter_mut().for_each(|s| *s = false);
+            for &pc in &current {
+                let consumed = match &self.program[pc] {
+                    Inst::Char(expected) => {
+                        *expected == c || (self.ignore_case && fold(c) == *expected)
+                    }
+                    Inst::Any => true,
+                    Inst::Class(class) => class.matches(c, self.ignore_case),
+                    _ => false,
+                };
+                if consumed && self.add(&mut next, &mut seen, pc + 1, at + 1, len) {
+                    return true;
+                }
+            }
+            // Unanchored: a match may also start at the next character
+            if self.add(&mut next, &mut seen, 0, at + 1, len) {
+                return true;
+            }
+            current = next;
+        }
+        false
+    }
+
+    /// Follow jumps and assertions from `pc` at position `at`, queueing the consuming
+    /// instructions reached. Returns true when `Match` is reachable. Walks an explicit
+    /// stack, since a large program can chain more jumps than the call stack holds.
+    fn add(
+        &self,
+        list: &mut Vec<usize>,
+        seen: &mut [bool],
+        pc: usize,
+        at: usize,
+        len: usize,
+    ) -> bool {
+        let mut pending = vec![pc];
+        while let Some(pc) = pending.pop() {
+            if seen[pc] {
+                continue;
+            }
+            seen[pc] = true;
+            match self.program[pc] {
+                Inst::Split(a, b) => {
+                    pending.push(b);
+                    pending.push(a);
+                }
+                Inst::Jump(target) => pending.push(target),
+                Inst::Start if at == 0 => pending.push(pc + 1),
+                Inst::End if at == len => pending.push(pc + 1),
+                Inst::Start | Inst::End => {}
+                Inst::Match => return true,
+                _ => list.push(pc),
+            }
+        }
+        false
+    }
+}
+
+/// Lowercase form used for case-insensitive literals
+fn fold(c: char) -> char {
+    c.to_lowercase().next().unwrap_or(c)
+}
+
+fn compile(node: &Node, program: &mut Vec<Inst>, ignore_case: bool) {
+    // Already refused; stop copying
+    if program.len() > MAX_PROGRAM {
+        return;
+    }
+    match node {
+        Node::Empty => {}
+        Node::Char(c) => program.push(Inst::Char(if ignore_case { fold(*c) } else { *c })),
+        Node::Any => program.push(Inst::Any),
+        Node::Class(class) => program.push(Inst::Class(class.clone())),
+        Node::Start => program.push(Inst::Start),
+        Node::End => program.push(Inst::End),
+        Node::Concat(nodes) => {
+            for node in nodes {
+                compile(node, program, ignore_case);
+            }
+        }
+        Node::Alternate(branches) => {
+            // Split(first, rest) chains, every branch jumping past the last one
+            let mut jumps = Vec::new();
+            for (i, branch) in branches.iter().enumerate() {
+                if i + 1 < branches.len() {
+                    let split = program.len();
+                    program.push(Inst::Split(split + 1, 0));
+                    compile(branch, program, ignore_case);
+                    jumps.push(program.len());
+                    program.push(Inst::Jump(0));
+                    let next = program.len();
+                    program[split] = Inst::Split(split + 1, next);
+                } else {
+                    compile(branch, program, ignore_case);
+                }
+            }
+            let end = program.len();
+            for jump in jumps {
+                program[jump] = Inst::Jump(end);
+            }
+        }
+        Node::Repeat { node, min, max } => {
+            for _ in 0..*min {
+                compile(node, program, ignore_case);
+            }
+            match max {
+                None => {
+                    // node*: loop back to the split after each pass
+                    let split = program.len();
+                    program.push(Inst::Split(split + 1, 0));
+                    compile(node, program, ignore_case);
+                    program.push(Inst::Jump(split));
+                    let end = program.len();
+                    program[split] = Inst::Split(split + 1, end);
+                }
+                Some(max) => {
+                    // Each optional copy may skip to the end
+                    let mut splits = Vec::new();
+                    for _ in *min..*max {
+                        splits.push(program.len());
+                        program.push(Inst::Split(program.len() + 1, 0));
+                        compile(node, program, ignore_case);
+                    }
+                    let end = program.len();
+                    for split in splits {
+                        program[split] = Inst::Split(split + 1, end);
+                    }
+                }
+            }
+        }
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    /// Groups open at the cursor
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn error(&self, what: &str) -> String {
+        let pattern: String = self.chars.iter().collect();
+        format!("Invalid pattern '{}': {}.", pattern, what)
+    }
+
+    /// Enter one more level of groups or quantifiers
+    fn nest(&mut self) -> Result<(), String> {
+        if self.depth >= MAX_NESTING {
+            return Err(self.error(&format!("nested more than {} deep", MAX_NESTING)));
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    fn alternation(&mut self) -> Result<Node, String> {
+        let mut branches = vec![self.concatenation()?];
+        while self.peek() == Some('|') {
+            self.pos += 1;
+            branches.push(self.concatenation()?);
+        }
+        Ok(if branches.len() == 1 {
+            branches.pop().unwrap_or(Node::Empty)
+        } else {
+            Node::Alternate(branches)
+        })
+    }
+
+    fn concatenation(&mut self) -> Result<Node, String> {
+        let mut nodes = Vec::new();
+        while let Some(c) = self.peek() {
+            if c == '|' || c == ')' {
+                break;
+            }
+            nodes.push(self.repetition()?);
+        }
+        Ok(match nodes.len() {
+            0 => Node::Empty,
+            1 => nodes.pop().unwrap_or(Node::Empty),
+            _ => Node::Concat(nodes),
+        })
+    }
+
+    fn repetition(&mut self) -> Result<Node, String> {
+        let mut node = self.atom()?;
+        // Stacked quantifiers (`a**`) nest like groups do
+        let depth = self.depth;
+        loop {
+            let (min, max) = match self.peek() {
+                Some('*') => (0, None),
+                Some('+') => (1, None),
+                Some('?') => (0, Some(1)),
+                Some('{') => match self.counts()? {
+                    Some(counts) => counts,
+                    None => break,
+                },
+                _ => break,
+            };
+            if matches!(node, Node::Start | Node::End) {
+                return Err(self.error("nothing to repeat"));
+            }
+            self.nest()?;
+            self.pos += 1;
+            // Lazy quantifiers match the same lines; accept and ignore the `?`
+            if self.peek() == Some('?') {
+                self.pos += 1;
+            }
+            node = Node::Repeat {
+                node: Box::new(node),
+                min,
+                max,
+            };
+        }
+        self.depth = depth;
+        Ok(node)
+    }
+
+    /// `{n}`, `{n,}` or `{n,m}` at the cursor, leaving it on the closing brace.
+    /// Anything else is not a quantifier and `{` stays a literal.
+    fn counts(&mut self) -> Result<Option<(u32, Option<u32>)>, String> {
+        let Some(close) = self.chars[self.pos..].iter().position(|&c| c == '}') else {
+            return Ok(None);
+        };
+        let body: String = self.chars[self.pos + 1..self.pos + close].iter().collect();
+        let number = |text: &str| text.parse::<u32>().ok();
+        let (min, max) = match body.split_once(',') {
+            None => match number(&body) {
+                Some(n) => (n, Some(n)),
+                None => return Ok(None),
+            },
+            Some((low, "")) => match number(low) {
+                Some(n) => (n, None),
+                None => return Ok(None),
+            },
+            Some((low, high)) => match (number(low), number(high)) {
+                (Some(low), Some(high)) => (low, Some(high)),
+                _ => return Ok(None),
+            },
+        };
+        if max.is_some_and(|max| max < min) {
+            return Err(self.error("repeat range is backwards"));
+        }
+        if max.unwrap_or(min) > MAX_REPEAT {
+            return Err(self.error(&format!("repeat counts are limited to {}", MAX_REPEAT)));
+        }
+        self.pos += close;
+        Ok(Some((min, max)))
+    }
+
+    fn atom(&mut self) -> Result<Node, String> {
+        let Some(c) = self.peek() else {
+            return Ok(Node::Empty);
+        };
+        self.pos += 1;
+        Ok(match c {
+            '(' => {
+                if self.chars[self.pos..].starts_with(&['?', ':']) {
+                    self.pos += 2;
+                }
+                self.nest()?;
+                let node = self.alternation()?;
+                self.depth -= 1;
+                if self.peek() != Some(')') {
+                    return Err(self.error("unclosed '('"));
+                }
+                self.pos += 1;
+                node
+            }
+            '[' => Node::Class(self.class()?),
+            '.' => Node::Any,
+            '^' => Node::Start,
+            '$' => Node::End,
+            '*' | '+' | '?' => return Err(self.error("nothing to repeat")),
+            '\\' => match self.escape()? {
+                Escaped::Char(c) => Node::Char(c),
+                Escaped::Item(item) => Node::Class(Class {
+                    negated: false,
+                    items: vec![item],
+                }),
+            },
+            c => Node::Char(c),
+        })
+    }
+
+    fn escape(&mut self) -> Result<Escaped, String> {
+        let c = self
+            .peek()
+            .ok_or_else(|| self.error("trailing backslash"))?;
+        self.pos += 1;
+        Ok(match c {
+            'd' => Escaped::Item(ClassItem::Digit(true)),
+            'D' => Escaped::Item(ClassItem::Digit(false)),
+            'w' => Escaped::Item(ClassItem::Word(true)),
+            'W' => Escaped::Item(ClassItem::Word(false)),
+            's' => Escaped::Item(ClassItem::Space(true)),
+            'S' => Escaped::Item(ClassItem::Space(false)),
+            't' => Escaped::Char('\t'),
+            'n' => Escaped::Char('\n'),
+            c if c.is_ascii_alphanumeric() => {
+                return Err(self.error(&format!("unsupported escape \\{}", c)))
+            }
+            c => Escaped::Char(c),
+        })
+    }
+
+    /// Bracket expression after the `[`
+    fn class(&mut self) -> Result<Class, String> {
+        let negated = self.peek() == Some('^');
+        if negated {
+            self.pos += 1;
+        }
+        let mut items = Vec::new();
+        let mut first = true;
+        loop {
+            let c = self.peek().ok_or_else(|| self.error("unclosed '['"))?;
+            self.pos += 1;
+            // A leading `]` is a literal
+            if c == ']' && !first {
+                break;
+            }
+            first = false;
+            let low = match c {
+                '\\' => match self.escape()? {
+                    Escaped::Char(c) => c,
+                    Escaped::Item(item) => {
+                        items.push(item);
+                        continue;
+                    }
+                },
+                c => c,
+            };
+            let is_range = self.peek() == Some('-')
+                && self
+                    .chars
+                    .get(self.pos + 1)
+                    .is_some_and(|&next| next != ']');
+            if !is_range {
+                items.push(ClassItem::Range(low, low));
+                continue;
+            }
+            self.pos += 1;
+            let high = match self.peek() {
+                Some('\\') => {
+                    self.pos += 1;
+                    match self.escape()? {
+                        Escaped::Char(c) => c,
+                        Escaped::Item(_) => return Err(self.error("class shorthand in a range")),
+                    }
+                }
+                Some(c) => {
+                    self.pos += 1;
+                    c
+                }
+                None => return Err(self.error("unclosed '['")),
+            };
+            if high < low {
+                return Err(self.error("character range is backwards"));
+            }
+            items.push(ClassItem::Range(low, high));
+        }
+        Ok(Class { negated, items })
+    }
+}
+
+enum Escaped {
+    Char(char),
+    Item(ClassItem),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(pattern: &str, text: &str) -> bool {
+        Regex::new(pattern)
+            .expect("pattern compiles")
+            .is_match(text)
+    }
+
+    #[test]
+    fn repetition() {
+        assert!(matches("^a{2,3}$", "aa"));
+        assert!(matches("^a{2,3}$", "aaa"));
+        assert!(!matches("^a{2,3}$", "a"));
+        assert!(!matches("^a{2,3}$", "aaaa"));
+        assert!(matches("^a{2}$", "aa"));
+        assert!(matches("^a{2,}$", "aaaaa"));
+        assert!(matches("^ab+c?$", "abbb"));
+        assert!(!matches("^ab+c$", "ac"));
+        assert!(matches("^(ab|cd)*$", "abcdab"));
+        assert!(matches("^a+?$", "aaa"));
+        // Not a quantifier, so a literal brace
+        assert!(matches("a{x}", "a{x}"));
+    }
+
+    #[test]
+    fn empty_loops() {
+        assert!(matches("^(a*)*$", ""));
+        assert!(matches("^(a*)*b$", "aaab"));
+        assert!(!matches("^(a*)*b$", "aaa"));
+        assert!(matches("^(a?)*$", "aaaa"));
+        assert!(matches("^()*$", ""));
+        assert!(matches("x()*y", "xy"));
+        assert!(matches("^(a|)+$", "aa"));
+    }
+
+    #[test]
+    fn deep_programs_do_not_recurse() {
+        let regex = Regex::new("((a?){1000}){45}b").expect("pattern compiles");
+        assert!(regex.is_match("aab"));
+        assert!(!regex.is_match("aac"));
+    }
+
+    #[test]
+    fn anchors() {
+        assert!(matches("^abc$", "abc"));
+        assert!(!matches("^abc$", "xabc"));
+        assert!(matches("b$", "ab"));
+        assert!(!matches("^b", "ab"));
+        assert!(matches("bc", "abcd"));
+        assert!(matches("^$", ""));
+        assert!(matches("a|^b", "cb a"));
+        assert!(!matches("x|^b", "cb"));
+    }
+
+    #[test]
+    fn classes() {
+        assert!(matches("^[a-c]+$", "abcab"));
+        assert!(!matches("^[a-c]+$", "abd"));
+        assert!(matches("^[^0-9]$", "x"));
+        assert!(!matches("^[^0-9]$", "5"));
+        assert!(matches(r"^\d\d$", "42"));
+        assert!(matches(r"^[\w-]+$", "api-key_2"));
+        assert!(matches(r"^\S+\s\S+$", "a b"));
+        assert!(matches("^[]a]+$", "]a"));
+        assert!(matches("^[a-]+$", "a-"));
+        assert!(matches("^.$", "é"));
+        let regex = Regex::case_insensitive("^Pass[A-Z]+$").expect("pattern compiles");
+        assert!(regex.is_match("PASSword"));
+    }
+
+    #[test]
+    fn invalid_patterns() {
+        for pattern in ["(a", "a)", "[a", "*a", "a{3,2}", r"\q", "[z-a]", "^*"] {
+            assert!(Regex::new(pattern).is_err(), "{}", pattern);
+        }
+    }
+
+    #[test]
+    fn size_caps() {
+        assert!(Regex::new("a{1001}").is_err());
+        assert!(Regex::new("(a{1000}){1000}").is_err());
+        let nested = format!("{}a{}", "(".repeat(10_000), ")".repeat(10_000));
+        assert!(Regex::new(&nested).is_err());
+        let stacked = format!("a{}", "*".repeat(10_000));
+        assert!(Regex::new(&stacked).is_err());
+        assert!(Regex::new(&format!("{}a{}", "(".repeat(50), ")".repeat(50))).is_ok());
+    }
+}
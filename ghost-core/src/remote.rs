@@ -0,0 +1,86 @@
+/// Detection of a shell running over SSH
+/// The session is remote when sshd set SSH_CONNECTION, SSH_CLIENT or SSH_TTY, or when an
+/// sshd process is among the shell's ancestors, which still holds after `sudo -i` or
+/// `su -` scrubbed the environment. Everything printed then travels over the network
+/// to the client, and the clipboard worth writing to is the client's, reachable only
+/// through OSC 52. Checked once per session.
+use std::env;
+use std::sync::OnceLock;
+
+#[cfg(target_os = "linux")]
+/// sshd process names: the listener, and the per-session child of OpenSSH 9.8+
+const SSHD_NAMES: [&str; 2] = ["sshd", "sshd-session"];
+
+#[cfg(target_os = "linux")]
+/// Ancestors looked at before giving up
+const MAX_ANCESTORS: usize = 32;
+
+/// Where an SSH session comes from
+pub struct SshSession {
+    /// Client address and port, when sshd's environment survived
+    pub client: Option<String>,
+}
+
+impl SshSession {
+    /// `SSH from 10.0.0.5:51234`
+    pub fn describe(&self) -> String {
+        match &self.client {
+            Some(client) => format!("SSH from {}", client),
+            None => "SSH".to_string(),
+        }
+    }
+}
+
+/// The SSH session this shell runs in, if any
+pub fn ssh_session() -> Option<&'static SshSession> {
+    static SESSION: OnceLock<Option<SshSession>> = OnceLock::new();
+    SESSION.get_or_init(detect).as_ref()
+}
+
+fn detect() -> Option<SshSession> {
+    // `client_ip client_port server_ip server_port` / `client_ip client_port server_port`
+    let client = ["SSH_CONNECTION", "SSH_CLIENT"].iter().find_map(|name| {
+        let value = env::var(name).ok()?;
+        let mut fields = value.split_whitespace();
+        let (ip, port) = (fields.next()?, fields.next()?);
+        Some(if ip.contains(':') {
+            format!("[{}]:{}", ip, port)
+        } else {
+            format!("{}:{}", ip, port)
+        })
+    });
+    if client.is_some() || env::var_os("SSH_TTY").is_some() || sshd_ancestor() {
+        Some(SshSession { client })
+    } else {
+        None
+    }
+}
+
+/// Whether an sshd process started this one, however far up (Linux, from /proc)
+fn sshd_ancestor() -> bool {
+    #[cfg(target_os = "linux")]
+    {
+        let mut pid = std::process::id();
+        for _ in 0..MAX_ANCESTORS {
+            let Ok(stat) = std::fs::read_to_string(format!("/proc/{}/stat", pid)) else {
+                return false;
+            };
+            // `pid (comm) state ppid ...`; comm may itself contain spaces or parentheses
+            let (Some(open), Some(close)) = (stat.find('('), stat.rfind(')')) else {
+                return false;
+            };
+            if SSHD_NAMES.contains(&&stat[open + 1..close]) {
+                return true;
+            }
+            match stat[close + 1..]
+                .split_whitespace()
+                .nth(1)
+                .and_then(|ppid| ppid.parse::<u32>().ok())
+            {
+                Some(parent) if parent > 1 => pid = parent,
+                _ => return false,
+            }
+        }
+    }
+    false
+}
@@ -0,0 +1,232 @@
+/// Child process isolation for Ghost Shell
+/// Runs commands in throwaway namespaces or bwrap/firejail sandboxes so they can't reach
+/// the network or leave dotfile traces
+use std::env;
+#[cfg(target_os = "linux")]
+use std::ffi::{CStr, CString};
+use std::io;
+#[cfg(target_os = "linux")]
+use std::os::unix::process::CommandExt;
+use std::path::PathBuf;
+use std::process::Command;
+
+/// External sandbox tool used to wrap child commands
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SandboxBackend {
+    Bubblewrap,
+    Firejail,
+}
+
+impl SandboxBackend {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SandboxBackend::Bubblewrap => "bwrap",
+            SandboxBackend::Firejail => "firejail",
+        }
+    }
+}
+
+/// How tightly child commands are confined
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SandboxProfile {
+    /// Commands run directly
+    Off,
+    /// Read-only system, private /tmp (and HOME under bwrap), writable cwd, network allowed
+    Standard,
+    /// Read-only everything, private /tmp and HOME, no network
+    Strict,
+}
+
+impl SandboxProfile {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "off" => Some(SandboxProfile::Off),
+            "standard" => Some(SandboxProfile::Standard),
+            "strict" => Some(SandboxProfile::Strict),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SandboxProfile::Off => "off",
+            SandboxProfile::Standard => "standard",
+            SandboxProfile::Strict => "strict",
+        }
+    }
+}
+
+/// Locate an executable on PATH
+pub fn find_in_path(name: &str) -> Option<PathBuf> {
+    let path = env::var_os("PATH")?;
+    env::split_paths(&path)
+        .map(|dir| dir.join(name))
+        .find(|candidate| candidate.is_file())
+}
+
+/// Pick the first installed sandbox tool, preferring bubblewrap
+pub fn detect_backend() -> Option<SandboxBackend> {
+    [SandboxBackend::Bubblewrap, SandboxBackend::Firejail]
+        .into_iter()
+        .find(|backend| find_in_path(backend.name()).is_some())
+}
+
+/// Build a command that runs `argv` wrapped by `backend` according to `profile`
+pub fn sandboxed_command(
+    backend: SandboxBackend,
+    profile: SandboxProfile,
+    argv: &[&str],
+) -> Command {
+    let home = env::var("HOME").unwrap_or_else(|_| "/tmp".to_string());
+    let cwd = env::current_dir()
+        .map(|dir| dir.to_string_lossy().to_string())
+        .unwrap_or_else(|_| "/".to_string());
+
+    let mut command = Command::new(backend.name());
+
+    match backend {
+        SandboxBackend::Bubblewrap => {
+            command.args(["--ro-bind", "/", "/"]);
+            command.args(["--dev", "/dev", "--proc", "/proc", "--tmpfs", "/tmp"]);
+            command.args(["--tmpfs", home.as_str()]);
+            match profile {
+                SandboxProfile::Strict => {
+                    command.args(["--unshare-all", "--new-session"]);
+                    command.args(["--ro-bind", cwd.as_str(), cwd.as_str()]);
+                }
+                _ => {
+                    command.args(["--unshare-all", "--share-net"]);
+                    command.args(["--bind", cwd.as_str(), cwd.as_str()]);
+                }
+            }
+            command.args(["--chdir", cwd.as_str(), "--die-with-parent", "--"]);
+        }
+        SandboxBackend::Firejail => {
+            command.args(["--quiet", "--noprofile", "--private-tmp", "--private-dev"]);
+            command.args(["--caps.drop=all", "--nonewprivs", "--nogroups"]);
+            if profile == SandboxProfile::Strict {
+                command.args(["--private", "--net=none", "--seccomp"]);
+            }
+            command.arg("--");
+        }
+    }
+
+    command.args(argv);
+    command
+}
+
+/// Build a command that runs `command_line` through `shell` inside fresh
+/// user, mount, network and PID namespaces, with a private tmpfs mounted over HOME
+#[cfg(target_os = "linux")]
+pub fn isolated_command(shell: &str, command_line: &str) -> io::Result<Command> {
+    // Everything the child needs is prepared up front: pre_exec runs after
+    // fork() and must not allocate
+    let uid = unsafe { libc::getuid() };
+    let gid = unsafe { libc::getgid() };
+    let uid_map = format!("{uid} {uid} 1");
+    let gid_map = format!("{gid} {gid} 1");
+    let home = CString::new(env::var("HOME").unwrap_or_else(|_| "/tmp".to_string()))?;
+
+    let mut command = Command::new(shell);
+    command.arg("-c").arg(command_line);
+
+    unsafe {
+        command.pre_exec(move || enter_isolation(&uid_map, &gid_map, &home));
+    }
+
+    Ok(command)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn isolated_command(_shell: &str, _command_line: &str) -> io::Result<Command> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "namespace isolation is only available on Linux",
+    ))
+}
+
+/// Runs in the forked child just before exec
+#[cfg(target_os = "linux")]
+fn enter_isolation(uid_map: &str, gid_map: &str, home: &CStr) -> io::Result<()> {
+    // A non-dumpable process's /proc files belong to root, uid_map included
+    unsafe { libc::prctl(libc::PR_SET_DUMPABLE, 1 as libc::c_ulong) };
+    let flags = libc::CLONE_NEWUSER | libc::CLONE_NEWNS | libc::CLONE_NEWNET | libc::CLONE_NEWPID;
+    if unsafe { libc::unshare(flags) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    // Map our own uid/gid into the new user namespace
+    write_proc_file(c"/proc/self/setgroups", b"deny")?;
+    write_proc_file(c"/proc/self/uid_map", uid_map.as_bytes())?;
+    write_proc_file(c"/proc/self/gid_map", gid_map.as_bytes())?;
+
+    unsafe {
+        // Keep our mounts from propagating back to the host
+        if libc::mount(
+            std::ptr::null(),
+            c"/".as_ptr(),
+            std::ptr::null(),
+            libc::MS_REC | libc::MS_PRIVATE,
+            std::ptr::null(),
+        ) != 0
+        {
+            return Err(io::Error::last_os_error());
+        }
+
+        // Private, empty HOME: dotfiles written by the child vanish with it
+        if libc::mount(
+            c"tmpfs".as_ptr(),
+            home.as_ptr(),
+            c"tmpfs".as_ptr(),
+            libc::MS_NOSUID | libc::MS_NODEV,
+            c"mode=0700".as_ptr() as *const libc::c_void,
+        ) != 0
+        {
+            return Err(io::Error::last_os_error());
+        }
+
+        // A new PID namespace only applies to children, so fork once more and
+        // let the grandchild (PID 1 in the namespace) exec the command
+        match libc::fork() {
+            -1 => Err(io::Error::last_os_error()),
+            0 => {
+                // Best effort: a fresh /proc hides host processes
+                libc::mount(
+                    c"proc".as_ptr(),
+                    c"/proc".as_ptr(),
+                    c"proc".as_ptr(),
+                    libc::MS_NOSUID | libc::MS_NODEV | libc::MS_NOEXEC,
+                    std::ptr::null(),
+                );
+                Ok(())
+            }
+            pid => {
+                let mut status = 0;
+                libc::waitpid(pid, &mut status, 0);
+                let code = if libc::WIFEXITED(status) {
+                    libc::WEXITSTATUS(status)
+                } else {
+                    128 + libc::WTERMSIG(status)
+                };
+                libc::_exit(code);
+            }
+        }
+    }
+}
+
+/// Write `data` to a /proc file using raw syscalls (no allocation after fork)
+#[cfg(target_os = "linux")]
+fn write_proc_file(path: &CStr, data: &[u8]) -> io::Result<()> {
+    unsafe {
+        let fd = libc::open(path.as_ptr(), libc::O_WRONLY | libc::O_CLOEXEC);
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let written = libc::write(fd, data.as_ptr() as *const libc::c_void, data.len());
+        libc::close(fd);
+        if written < 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,236 @@
+/// Rhai scripting for ::run
+/// A script automates a multi-step workflow through the functions below; it runs on its
+/// own thread and reaches shell state only through requests the shell thread serves:
+///
+///   run(line)              run a shell line (external or `::` command), returns its output
+///   copy(text)             copy to the auto-clearing clipboard
+///   vault_get(name)        read a vault entry
+///   vault_put(name, text)  store a vault entry
+///   print(value)           show a line of output
+///
+/// Config hooks (`hook.pre` / `hook.post`) are snippets run the same way, with the
+/// command line (and its output) in scope as `command` and `output`.
+///
+/// Every allocation the interpreter frees on that thread is zeroed first, so the engine,
+/// the script source and the values it held (secrets included) are scrubbed once it ends.
+use rhai::{Engine, EvalAltResult, Scope};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::fs;
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use zeroize::Zeroize;
+
+/// What a script may ask of the shell
+pub trait ScriptHost {
+    /// Run one input line as if typed at the prompt; returns its output
+    fn run_line(&mut self, line: &str) -> Result<String, String>;
+    /// Copy to the clipboard; returns a status line
+    fn copy(&mut self, text: String) -> Result<String, String>;
+    fn vault_get(&mut self, name: &str) -> Result<String, String>;
+    fn vault_put(&mut self, name: &str, secret: &str) -> Result<(), String>;
+    /// Show output while the script is still running
+    fn print(&mut self, text: &str);
+}
+
+/// A request from the script thread
+enum Request {
+    Run(String),
+    Copy(String),
+    VaultGet(String),
+    VaultPut(String, String),
+    Print(String),
+    /// The script finished: its final variables, or its error
+    Done(Result<Variables, String>),
+}
+
+/// Wraps the system allocator; on threads that opted in, freed memory is zeroed first.
+/// Scrubbing only happens when the program installs it with `#[global_allocator]`.
+pub struct ScrubbingAllocator;
+
+thread_local! {
+    static SCRUB_ON_FREE: Cell<bool> = const { Cell::new(false) };
+}
+
+fn scrubbing() -> bool {
+    SCRUB_ON_FREE.try_with(Cell::get).unwrap_or(false)
+}
+
+unsafe impl GlobalAlloc for ScrubbingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        System.alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        System.alloc_zeroed(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        if scrubbing() {
+            std::slice::from_raw_parts_mut(ptr, layout.size()).zeroize();
+        }
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        if !scrubbing() {
+            return System.realloc(ptr, layout, new_size);
+        }
+        // Move by hand so the old block is scrubbed instead of released as-is
+        let new_layout = Layout::from_size_align_unchecked(new_size, layout.align());
+        let new_ptr = System.alloc(new_layout);
+        if !new_ptr.is_null() {
+            std::ptr::copy_nonoverlapping(ptr, new_ptr, layout.size().min(new_size));
+            self.dealloc(ptr, layout);
+        }
+        new_ptr
+    }
+}
+
+/// Variables a snippet starts with and whatever values they hold when it ends
+pub type Variables = Vec<(&'static str, String)>;
+
+/// Run the script at `path`, serving its requests through `host`
+pub fn run_script(path: &Path, host: &mut dyn ScriptHost) -> Result<(), String> {
+    let source = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    execute(path.display().to_string(), source, Vec::new(), host).map(|_| ())
+}
+
+/// Run a hook snippet with `vars` in scope; their values are updated from the snippet
+pub fn run_hook(
+    name: &str,
+    snippet: &str,
+    vars: &mut Variables,
+    host: &mut dyn ScriptHost,
+) -> Result<(), String> {
+    *vars = execute(
+        name.to_string(),
+        snippet.to_string(),
+        std::mem::take(vars),
+        host,
+    )?;
+    Ok(())
+}
+
+/// Evaluate `source` on a scrubbing thread while this one serves its requests
+fn execute(
+    name: String,
+    mut source: String,
+    vars: Variables,
+    host: &mut dyn ScriptHost,
+) -> Result<Variables, String> {
+    let (request_tx, request_rx) = mpsc::channel();
+    let (reply_tx, reply_rx) = mpsc::channel();
+    let script = thread::spawn(move || {
+        SCRUB_ON_FREE.with(|scrub| scrub.set(true));
+        let result =
+            evaluate(&source, vars, &request_tx, reply_rx).map_err(|e| format!("{}: {}", name, e));
+        source.zeroize();
+        let _ = request_tx.send(Request::Done(result));
+    });
+
+    let result = serve(host, &request_rx, &reply_tx);
+    drop(reply_tx);
+    let _ = script.join();
+    result
+}
+
+/// Answer script requests until it is done
+fn serve(
+    host: &mut dyn ScriptHost,
+    requests: &Receiver<Request>,
+    replies: &Sender<Result<String, String>>,
+) -> Result<Variables, String> {
+    loop {
+        let reply = match requests.recv() {
+            Ok(Request::Run(mut line)) => {
+                let output = host.run_line(&line);
+                line.zeroize();
+                output
+            }
+            Ok(Request::Copy(text)) => host.copy(text),
+            Ok(Request::VaultGet(name)) => host.vault_get(&name),
+            Ok(Request::VaultPut(name, mut secret)) => {
+                let stored = host.vault_put(&name, &secret);
+                secret.zeroize();
+                stored.map(|_| String::new())
+            }
+            Ok(Request::Print(mut text)) => {
+                host.print(&text);
+                text.zeroize();
+                continue;
+            }
+            Ok(Request::Done(result)) => return result,
+            Err(_) => return Err("script thread stopped unexpectedly.".to_string()),
+        };
+        if replies.send(reply).is_err() {
+            return Err("script thread stopped unexpectedly.".to_string());
+        }
+    }
+}
+
+/// Build the engine with the host functions and evaluate `source` (script thread)
+fn evaluate(
+    source: &str,
+    vars: Variables,
+    requests: &Sender<Request>,
+    replies: Receiver<Result<String, String>>,
+) -> Result<Variables, String> {
+    let replies = std::rc::Rc::new(replies);
+    let call = {
+        let requests = requests.clone();
+        move |request: Request| -> Result<String, Box<EvalAltResult>> {
+            requests
+                .send(request)
+                .map_err(|_| "shell is gone".to_string())?;
+            match replies.recv() {
+                Ok(reply) => reply.map_err(Into::into),
+                Err(_) => Err("shell is gone".into()),
+            }
+        }
+    };
+
+    let mut engine = Engine::new();
+    let print = requests.clone();
+    engine.on_print(move |text| {
+        let _ = print.send(Request::Print(text.to_string()));
+    });
+    let debug = requests.clone();
+    engine.on_debug(move |text, _, _| {
+        let _ = debug.send(Request::Print(text.to_string()));
+    });
+
+    let run = call.clone();
+    engine.register_fn("run", move |line: &str| run(Request::Run(line.to_string())));
+    let copy = call.clone();
+    engine.register_fn("copy", move |text: &str| {
+        copy(Request::Copy(text.to_string())).map(|_| ())
+    });
+    let vault_get = call.clone();
+    engine.register_fn("vault_get", move |name: &str| {
+        vault_get(Request::VaultGet(name.to_string()))
+    });
+    engine.register_fn("vault_put", move |name: &str, secret: &str| {
+        call(Request::VaultPut(name.to_string(), secret.to_string())).map(|_| ())
+    });
+
+    let mut scope = Scope::new();
+    for (name, value) in &vars {
+        scope.push(*name, value.clone());
+    }
+    engine
+        .run_with_scope(&mut scope, source)
+        .map_err(|e| e.to_string())?;
+    Ok(vars
+        .into_iter()
+        .map(|(name, mut value)| {
+            value.zeroize();
+            let current = scope
+                .get(name)
+                .map(|value| value.to_string())
+                .unwrap_or_default();
+            (name, current)
+        })
+        .collect())
+}
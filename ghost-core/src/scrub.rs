@@ -0,0 +1,321 @@
+/// In-process metadata removal for ::scrub
+/// JPEG, PNG and WebP files lose EXIF, XMP, IPTC, comments and text chunks while pixel
+/// data, ICC profiles and colour information are kept byte for byte. PDFs lose the Info
+/// dictionary and XMP streams; Office (OOXML) and OpenDocument files get blank property
+/// parts and archive timestamps reset to 1980-01-01.
+use lopdf::{Document, Object};
+use std::fs::{self, OpenOptions};
+use std::io::{Cursor, Read, Write};
+use std::path::Path;
+use zip::write::SimpleFileOptions;
+use zip::{DateTime, ZipArchive, ZipWriter};
+
+/// Largest file ::scrub loads into memory (also caps each unpacked archive entry)
+const MAX_SCRUB_SIZE: u64 = 256 * 1024 * 1024;
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+// Replacement document property parts: valid, but carrying nothing
+const OOXML_CORE: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<cp:coreProperties xmlns:cp="http://schemas.openxmlformats.org/package/2006/metadata/core-properties" xmlns:dc="http://purl.org/dc/elements/1.1/" xmlns:dcterms="http://purl.org/dc/terms/" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance"/>"#;
+const OOXML_APP: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Properties xmlns="http://schemas.openxmlformats.org/officeDocument/2006/extended-properties"/>"#;
+const OOXML_CUSTOM: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Properties xmlns="http://schemas.openxmlformats.org/officeDocument/2006/custom-properties"/>"#;
+const ODF_META: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<office:document-meta xmlns:office="urn:oasis:names:tc:opendocument:xmlns:office:1.0"><office:meta/></office:document-meta>"#;
+
+/// What ::scrub did to one file
+pub struct ScrubReport {
+    pub format: &'static str,
+    pub removed: Vec<String>,
+    pub before: usize,
+    pub after: usize,
+}
+
+/// Strip metadata from `path` in place. The cleaned copy is written next to it and
+/// renamed over the original, keeping its permissions.
+pub fn scrub_file(path: &Path) -> Result<ScrubReport, String> {
+    let metadata = fs::metadata(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    if !metadata.is_file() {
+        return Err(format!("{}: not a regular file.", path.display()));
+    }
+    if metadata.len() > MAX_SCRUB_SIZE {
+        return Err(format!(
+            "{}: larger than {} MiB.",
+            path.display(),
+            MAX_SCRUB_SIZE / 1024 / 1024
+        ));
+    }
+    let data = fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+
+    let (format, removed, cleaned) =
+        scrub_bytes(&data).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let report = ScrubReport {
+        format,
+        removed,
+        before: data.len(),
+        after: cleaned.len(),
+    };
+    if report.removed.is_empty() {
+        return Ok(report);
+    }
+
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let temp = path.with_file_name(format!(".{}.scrub", file_name));
+    let result = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&temp)
+        .and_then(|mut file| {
+            file.write_all(&cleaned)?;
+            file.set_permissions(metadata.permissions())?;
+            file.sync_all()
+        })
+        .and_then(|_| fs::rename(&temp, path));
+    if let Err(e) = result {
+        let _ = fs::remove_file(&temp);
+        return Err(format!("{}: {}", path.display(), e));
+    }
+    Ok(report)
+}
+
+/// Format name, removed items and cleaned contents
+fn scrub_bytes(data: &[u8]) -> Result<(&'static str, Vec<String>, Vec<u8>), String> {
+    let mut removed = Vec::new();
+    let (format, cleaned) = if data.starts_with(b"\xff\xd8\xff") {
+        ("JPEG", scrub_jpeg(data, &mut removed)?)
+    } else if data.starts_with(PNG_SIGNATURE) {
+        ("PNG", scrub_png(data, &mut removed)?)
+    } else if data.len() >= 12 && data.starts_with(b"RIFF") && &data[8..12] == b"WEBP" {
+        ("WebP", scrub_webp(data, &mut removed)?)
+    } else if data.starts_with(b"%PDF-") {
+        ("PDF", scrub_pdf(data, &mut removed)?)
+    } else if data.starts_with(b"PK\x03\x04") {
+        scrub_office(data, &mut removed)?
+    } else {
+        return Err("unsupported format (JPEG, PNG, WebP, PDF, Office/OpenDocument).".to_string());
+    };
+    removed.sort();
+    removed.dedup();
+    Ok((format, removed, cleaned))
+}
+
+/// Drop APP1 (EXIF/XMP), APP13 (IPTC), comments and unknown APPn segments before the scan
+fn scrub_jpeg(data: &[u8], removed: &mut Vec<String>) -> Result<Vec<u8>, String> {
+    let truncated = || "truncated JPEG.".to_string();
+    let mut cleaned = Vec::with_capacity(data.len());
+    cleaned.extend_from_slice(&data[..2]);
+    let mut pos = 2;
+    loop {
+        // Markers may be preceded by any number of 0xFF fill bytes
+        while data.get(pos) == Some(&0xff) && data.get(pos + 1) == Some(&0xff) {
+            pos += 1;
+        }
+        let (&0xff, Some(&marker)) = (data.get(pos).ok_or_else(truncated)?, data.get(pos + 1))
+        else {
+            return Err("malformed JPEG marker.".to_string());
+        };
+        // Start of scan: entropy-coded data and everything after it is kept as is
+        if marker == 0xda || marker == 0xd9 {
+            cleaned.extend_from_slice(&data[pos..]);
+            return Ok(cleaned);
+        }
+        if matches!(marker, 0x01 | 0xd0..=0xd7) {
+            cleaned.extend_from_slice(&data[pos..pos + 2]);
+            pos += 2;
+            continue;
+        }
+
+        let length = data
+            .get(pos + 2..pos + 4)
+            .map(|len| u16::from_be_bytes([len[0], len[1]]) as usize)
+            .filter(|&len| len >= 2)
+            .ok_or_else(truncated)?;
+        let end = pos + 2 + length;
+        let payload = data.get(pos + 4..end).ok_or_else(truncated)?;
+        let label = match marker {
+            0xe0 => None,                                          // JFIF
+            0xe2 if payload.starts_with(b"ICC_PROFILE\0") => None, // colour profile
+            0xee if payload.starts_with(b"Adobe") => None,         // colour transform
+            0xe1 if payload.starts_with(b"Exif\0") => Some("EXIF".to_string()),
+            0xe1 if payload.starts_with(b"http://ns.adobe.com/xap/") => Some("XMP".to_string()),
+            0xed => Some("IPTC".to_string()),
+            0xfe => Some("comment".to_string()),
+            0xe1..=0xef => Some(format!("APP{}", marker - 0xe0)),
+            _ => None,
+        };
+        match label {
+            Some(label) => removed.push(label),
+            None => cleaned.extend_from_slice(&data[pos..end]),
+        }
+        pos = end;
+    }
+}
+
+/// Drop text, EXIF and timestamp chunks; image and colour chunks are kept
+fn scrub_png(data: &[u8], removed: &mut Vec<String>) -> Result<Vec<u8>, String> {
+    let mut cleaned = Vec::with_capacity(data.len());
+    cleaned.extend_from_slice(PNG_SIGNATURE);
+    let mut pos = PNG_SIGNATURE.len();
+    while pos < data.len() {
+        let header = data.get(pos..pos + 8).ok_or("truncated PNG.")?;
+        let length = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+        let kind = &header[4..8];
+        let end = pos + 12 + length;
+        let chunk = data.get(pos..end).ok_or("truncated PNG.")?;
+        let label = match kind {
+            b"iTXt" if chunk[8..].starts_with(b"XML:com.adobe.xmp\0") => Some("XMP"),
+            b"tEXt" | b"zTXt" | b"iTXt" => Some("text"),
+            b"eXIf" => Some("EXIF"),
+            b"tIME" => Some("timestamp"),
+            _ => None,
+        };
+        match label {
+            Some(label) => removed.push(label.to_string()),
+            None => cleaned.extend_from_slice(chunk),
+        }
+        pos = end;
+        if kind == b"IEND" {
+            break;
+        }
+    }
+    Ok(cleaned)
+}
+
+/// Drop EXIF and XMP chunks and clear their flags in the VP8X header
+fn scrub_webp(data: &[u8], removed: &mut Vec<String>) -> Result<Vec<u8>, String> {
+    let mut cleaned = Vec::with_capacity(data.len());
+    cleaned.extend_from_slice(&data[..12]);
+    let mut pos = 12;
+    while pos + 8 <= data.len() {
+        let kind = &data[pos..pos + 4];
+        let length =
+            u32::from_le_bytes([data[pos + 4], data[pos + 5], data[pos + 6], data[pos + 7]])
+                as usize;
+        // Chunks are padded to an even length
+        let end = (pos + 8 + length + (length & 1)).min(data.len());
+        if pos + 8 + length > data.len() {
+            return Err("truncated WebP.".to_string());
+        }
+        match kind {
+            b"EXIF" => removed.push("EXIF".to_string()),
+            b"XMP " => removed.push("XMP".to_string()),
+            _ => {
+                let start = cleaned.len();
+                cleaned.extend_from_slice(&data[pos..end]);
+                if kind == b"VP8X" && length > 0 {
+                    // Flags byte: bit 3 = EXIF, bit 2 = XMP
+                    cleaned[start + 8] &= !0x0c;
+                }
+            }
+        }
+        pos = end;
+    }
+    let riff_size = (cleaned.len() - 8) as u32;
+    cleaned[4..8].copy_from_slice(&riff_size.to_le_bytes());
+    Ok(cleaned)
+}
+
+/// Drop the trailer Info dictionary and every XMP / application data stream, then write
+/// a fresh file so earlier incremental revisions are gone too
+fn scrub_pdf(data: &[u8], removed: &mut Vec<String>) -> Result<Vec<u8>, String> {
+    let mut document = Document::load_mem(data).map_err(|e| format!("PDF: {}", e))?;
+    if document.is_encrypted() {
+        return Err("encrypted PDFs are not supported.".to_string());
+    }
+
+    if document.trailer.remove(b"Info").is_some() {
+        removed.push("Info dictionary".to_string());
+    }
+    for object in document.objects.values_mut() {
+        let dictionary = match object {
+            Object::Dictionary(dictionary) => dictionary,
+            Object::Stream(stream) => &mut stream.dict,
+            _ => continue,
+        };
+        if dictionary.remove(b"Metadata").is_some() {
+            removed.push("XMP".to_string());
+        }
+        if dictionary.remove(b"PieceInfo").is_some() {
+            removed.push("application data".to_string());
+        }
+    }
+    document.prune_objects();
+    document.renumber_objects();
+
+    let mut cleaned = Vec::with_capacity(data.len());
+    document
+        .save_to(&mut cleaned)
+        .map_err(|e| format!("PDF: {}", e))?;
+    Ok(cleaned)
+}
+
+/// Rebuild an OOXML or OpenDocument archive with blank property parts and fixed
+/// entry timestamps; every other part is copied with its original compression
+fn scrub_office(data: &[u8], removed: &mut Vec<String>) -> Result<(&'static str, Vec<u8>), String> {
+    let mut archive = ZipArchive::new(Cursor::new(data)).map_err(|e| format!("zip: {}", e))?;
+    let format = if archive.index_for_name("[Content_Types].xml").is_some() {
+        "Office Open XML"
+    } else if archive.index_for_name("mimetype").is_some() {
+        "OpenDocument"
+    } else {
+        return Err("zip archive is not an Office or OpenDocument file.".to_string());
+    };
+    if !archive.comment().is_empty() {
+        removed.push("archive comment".to_string());
+    }
+
+    let mut writer = ZipWriter::new(Cursor::new(Vec::with_capacity(data.len())));
+    for index in 0..archive.len() {
+        let mut entry = archive.by_index(index).map_err(|e| format!("zip: {}", e))?;
+        let name = entry.name().to_string();
+        let options = SimpleFileOptions::default()
+            .compression_method(entry.compression())
+            .last_modified_time(DateTime::default());
+        if entry.is_dir() {
+            writer
+                .add_directory(name, options)
+                .map_err(|e| format!("zip: {}", e))?;
+            continue;
+        }
+
+        let replacement = match name.as_str() {
+            "docProps/core.xml" => Some((OOXML_CORE, "document properties")),
+            "docProps/app.xml" => Some((OOXML_APP, "application properties")),
+            "docProps/custom.xml" => Some((OOXML_CUSTOM, "custom properties")),
+            "meta.xml" => Some((ODF_META, "document properties")),
+            _ => None,
+        };
+        let contents = match replacement {
+            Some((blank, label)) => {
+                removed.push(label.to_string());
+                blank.as_bytes().to_vec()
+            }
+            None => {
+                if entry.size() > MAX_SCRUB_SIZE {
+                    return Err(format!("zip entry {} is too large.", name));
+                }
+                let mut contents = Vec::with_capacity(entry.size() as usize);
+                entry
+                    .read_to_end(&mut contents)
+                    .map_err(|e| format!("zip: {}: {}", name, e))?;
+                contents
+            }
+        };
+        writer
+            .start_file(name, options)
+            .and_then(|_| writer.write_all(&contents).map_err(Into::into))
+            .map_err(|e| format!("zip: {}", e))?;
+    }
+    removed.push("archive timestamps".to_string());
+
+    let cleaned = writer
+        .finish()
+        .map_err(|e| format!("zip: {}", e))?
+        .into_inner();
+    Ok((format, cleaned))
+}
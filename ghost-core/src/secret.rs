@@ -0,0 +1,70 @@
+/// Secrets that can't be printed by accident
+/// `Secret<T>` owns a key, passphrase or decrypted payload and zeroizes it when dropped.
+/// It implements neither `Debug` nor `Display`, so formatting one with `{}` or `{:?}`
+/// (or deriving `Debug` on a struct that holds one) fails to compile. The value is only
+/// reached through `expose`, which keeps every place a secret is used easy to find.
+use zeroize::Zeroize;
+
+/// An owned secret, zeroized on drop
+pub struct Secret<T: Zeroize>(T);
+
+/// A passphrase or decrypted text
+pub type SecretString = Secret<String>;
+/// Decrypted bytes
+pub type SecretBytes = Secret<Vec<u8>>;
+/// A 256-bit symmetric key
+pub type SecretKey = Secret<[u8; 32]>;
+
+impl<T: Zeroize> Secret<T> {
+    pub fn new(value: T) -> Self {
+        Secret(value)
+    }
+
+    /// Borrow the secret value
+    pub fn expose(&self) -> &T {
+        &self.0
+    }
+
+    /// Borrow the secret value to change it in place
+    pub fn expose_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<T: Zeroize + Default> Secret<T> {
+    /// Hand the value to code that takes ownership; zeroizing it becomes its job
+    pub fn into_exposed(mut self) -> T {
+        std::mem::take(&mut self.0)
+    }
+}
+
+impl SecretBytes {
+    /// The bytes as text; invalid UTF-8 is zeroized and reported
+    pub fn into_text(self) -> Result<SecretString, String> {
+        String::from_utf8(self.into_exposed())
+            .map(Secret)
+            .map_err(|e| {
+                let mut bytes = e.into_bytes();
+                bytes.zeroize();
+                "Decrypted data is not valid UTF-8.".to_string()
+            })
+    }
+}
+
+impl<T: Zeroize + Default> Default for Secret<T> {
+    fn default() -> Self {
+        Secret(T::default())
+    }
+}
+
+impl<T: Zeroize> From<T> for Secret<T> {
+    fn from(value: T) -> Self {
+        Secret(value)
+    }
+}
+
+impl<T: Zeroize> Drop for Secret<T> {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
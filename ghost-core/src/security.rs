@@ -0,0 +1,1502 @@
+/// Advanced security module for Ghost Shell
+/// Provides memory protection, anti-forensics, and monitoring detection
+use std::io;
+
+#[cfg(target_os = "linux")]
+use libc::{c_void, madvise, mlock, MADV_DONTDUMP};
+#[cfg(target_os = "linux")]
+use std::collections::BTreeSet;
+#[cfg(target_os = "linux")]
+use std::fs;
+#[cfg(target_os = "linux")]
+use std::sync::Mutex;
+
+#[cfg(target_os = "macos")]
+use libc::{c_int, c_void, mlock};
+#[cfg(target_os = "macos")]
+use std::ffi::CStr;
+
+#[cfg(windows)]
+use std::os::windows::ffi::OsStrExt;
+#[cfg(windows)]
+use windows_sys::Win32::Foundation::{CloseHandle, INVALID_HANDLE_VALUE};
+#[cfg(windows)]
+use windows_sys::Win32::System::Threading::GetCurrentProcess;
+
+#[cfg(any(target_os = "openbsd", target_os = "freebsd"))]
+use std::sync::OnceLock;
+
+// dyld image enumeration (deprecated in libc, declared directly)
+#[cfg(target_os = "macos")]
+extern "C" {
+    fn _dyld_image_count() -> u32;
+    fn _dyld_get_image_name(image_index: u32) -> *const libc::c_char;
+}
+
+/// Concrete threat level posed by the Linux audit subsystem
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditThreat {
+    /// auditd is not running
+    None,
+    /// auditd is running but no exec/tty rules were found
+    Low,
+    /// auditd is running but its rules could not be read
+    Unknown,
+    /// Every execve is logged (full command lines)
+    High,
+    /// TTY input is logged (keystrokes, including secrets)
+    Critical,
+}
+
+/// Result of inspecting the loaded audit configuration
+#[derive(Debug, Clone)]
+pub struct AuditInspection {
+    pub daemon_running: bool,
+    pub rules_readable: bool,
+    pub execve_logged: bool,
+    pub tty_logged: bool,
+}
+
+impl Default for AuditInspection {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AuditInspection {
+    pub fn new() -> Self {
+        AuditInspection {
+            daemon_running: false,
+            rules_readable: false,
+            execve_logged: false,
+            tty_logged: false,
+        }
+    }
+
+    pub fn threat_level(&self) -> AuditThreat {
+        if self.tty_logged {
+            AuditThreat::Critical
+        } else if self.execve_logged {
+            AuditThreat::High
+        } else if !self.daemon_running {
+            AuditThreat::None
+        } else if !self.rules_readable {
+            AuditThreat::Unknown
+        } else {
+            AuditThreat::Low
+        }
+    }
+
+    /// `none`, `low`, `unknown`, `high` or `critical`
+    pub fn level_name(&self) -> &'static str {
+        match self.threat_level() {
+            AuditThreat::None => "none",
+            AuditThreat::Low => "low",
+            AuditThreat::Unknown => "unknown",
+            AuditThreat::High => "high",
+            AuditThreat::Critical => "critical",
+        }
+    }
+
+    fn describe(&self) -> &'static str {
+        match self.threat_level() {
+            AuditThreat::None => "✓ NONE (auditd not running)",
+            AuditThreat::Low => "✓ LOW (no execve/tty rules loaded)",
+            AuditThreat::Unknown => "⚠ UNKNOWN (auditd running, rules unreadable)",
+            AuditThreat::High => "⚠ HIGH (execve logging enabled)",
+            AuditThreat::Critical => "✗ CRITICAL (TTY keystroke logging enabled)",
+        }
+    }
+}
+
+/// Security status of the shell
+#[derive(Debug, Clone)]
+pub struct SecurityStatus {
+    pub memory_locked: bool,
+    pub swap_disabled: bool,
+    pub core_dumps_disabled: bool,
+    pub ptrace_blocked: bool,
+    pub ptrace_scope: Option<u8>,
+    pub kernel: Vec<KernelSetting>,
+    pub monitoring_detected: bool,
+    pub audit: AuditInspection,
+    pub platform_sandbox: Option<String>,
+    pub threats_detected: Vec<String>,
+}
+
+/// Overall security posture, A (best) to F
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Grade {
+    A,
+    B,
+    C,
+    D,
+    F,
+}
+
+impl Grade {
+    pub fn letter(&self) -> char {
+        match self {
+            Grade::A => 'A',
+            Grade::B => 'B',
+            Grade::C => 'C',
+            Grade::D => 'D',
+            Grade::F => 'F',
+        }
+    }
+}
+
+impl Default for SecurityStatus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SecurityStatus {
+    pub fn new() -> Self {
+        SecurityStatus {
+            memory_locked: false,
+            swap_disabled: false,
+            core_dumps_disabled: false,
+            ptrace_blocked: false,
+            ptrace_scope: None,
+            kernel: Vec::new(),
+            monitoring_detected: false,
+            audit: AuditInspection::new(),
+            platform_sandbox: None,
+            threats_detected: Vec::new(),
+        }
+    }
+
+    /// Generate a status report string
+    /// What lowers the posture grade: memory that can't be locked, swap, core dumps,
+    /// debuggers free to attach
+    pub fn weaknesses(&self) -> Vec<&'static str> {
+        let mut weaknesses = Vec::new();
+        if !self.memory_locked {
+            weaknesses.push("memory not lockable");
+        }
+        if !self.swap_disabled {
+            weaknesses.push("swap enabled");
+        }
+        if !self.core_dumps_disabled {
+            weaknesses.push("core dumps allowed");
+        }
+        if !self.ptrace_blocked {
+            weaknesses.push("ptrace attach allowed");
+        }
+        weaknesses
+    }
+
+    /// A with nothing to fix, one letter lower per weakness, F whenever monitoring
+    /// was detected
+    pub fn grade(&self) -> Grade {
+        if self.monitoring_detected {
+            return Grade::F;
+        }
+        match self.weaknesses().len() {
+            0 => Grade::A,
+            1 => Grade::B,
+            2 => Grade::C,
+            _ => Grade::D,
+        }
+    }
+
+    pub fn report(&self) -> String {
+        let mut report = String::from("=== GHOST SHELL SECURITY STATUS ===\r\n");
+
+        report.push_str(&format!(
+            "Memory Locked:       {}\r\n",
+            if self.memory_locked {
+                "✓ YES"
+            } else {
+                "✗ NO"
+            }
+        ));
+
+        report.push_str(&format!(
+            "Swap Disabled:       {}\r\n",
+            if self.swap_disabled {
+                "✓ YES"
+            } else {
+                "⚠ NO (RISK: Memory may be swapped to disk)"
+            }
+        ));
+
+        report.push_str(&format!(
+            "Core Dumps Blocked:  {}\r\n",
+            if self.core_dumps_disabled {
+                "✓ YES"
+            } else {
+                "✗ NO"
+            }
+        ));
+
+        let yama = match self.ptrace_scope {
+            Some(scope) => format!("Yama scope {}: {}", scope, describe_ptrace_scope(scope)),
+            None => "no Yama".to_string(),
+        };
+        report.push_str(&format!(
+            "Ptrace Attach:       {} ({})\r\n",
+            if self.ptrace_blocked {
+                "✓ REFUSED"
+            } else {
+                "✗ ALLOWED"
+            },
+            yama
+        ));
+
+        report.push_str(&format!(
+            "Monitoring Detected: {}\r\n",
+            if self.monitoring_detected {
+                "⚠ YES (Potential surveillance)"
+            } else {
+                "✓ NO"
+            }
+        ));
+
+        report.push_str(&format!(
+            "Audit Logging:       {}\r\n",
+            self.audit.describe()
+        ));
+
+        if !self.kernel.is_empty() {
+            report.push_str("Kernel Hardening:\r\n");
+            for setting in &self.kernel {
+                report.push_str(&match setting.value {
+                    Some(value) if setting.is_weak() => {
+                        format!("  ✗ {:<28} {} ({})\r\n", setting.name, value, setting.risk)
+                    }
+                    Some(value) => format!("  ✓ {:<28} {}\r\n", setting.name, value),
+                    None => format!("  ⚠ {:<28} not available\r\n", setting.name),
+                });
+            }
+            let weak: Vec<&KernelSetting> = self
+                .kernel
+                .iter()
+                .filter(|setting| setting.is_weak())
+                .collect();
+            if !weak.is_empty() {
+                report.push_str("  Suggested (as root; add to /etc/sysctl.d/ to keep):\r\n");
+                for setting in weak {
+                    report.push_str(&format!(
+                        "    sysctl -w {}={}\r\n",
+                        setting.name, setting.suggested
+                    ));
+                }
+            }
+        }
+
+        let grade = self.grade();
+        let reasons = match grade {
+            Grade::A => "nothing to fix".to_string(),
+            Grade::F => "monitoring detected".to_string(),
+            _ => self.weaknesses().join(", "),
+        };
+        report.push_str(&format!(
+            "Posture Grade:       {} ({})\r\n",
+            grade.letter(),
+            reasons
+        ));
+
+        if !self.threats_detected.is_empty() {
+            report.push_str("\r\n⚠ THREATS DETECTED:\r\n");
+            for threat in &self.threats_detected {
+                report.push_str(&format!("  - {}\r\n", threat));
+            }
+        }
+
+        report.push_str("\r\n");
+        report
+    }
+
+    /// The status as one line of JSON for scripts and dashboards. `extra` members,
+    /// already encoded as JSON values, are appended after the built-in ones.
+    pub fn to_json(&self, extra: &[(&str, String)]) -> String {
+        let strings = |items: &[&str]| {
+            let items: Vec<String> = items.iter().map(|item| json_string(item)).collect();
+            format!("[{}]", items.join(","))
+        };
+        let kernel: Vec<String> = self
+            .kernel
+            .iter()
+            .map(|setting| {
+                format!(
+                    "{{\"name\":{},\"value\":{},\"minimum\":{},\"suggested\":{},\"weak\":{}}}",
+                    json_string(setting.name),
+                    setting
+                        .value
+                        .map_or("null".to_string(), |value| value.to_string()),
+                    setting.minimum,
+                    setting.suggested,
+                    setting.is_weak()
+                )
+            })
+            .collect();
+        let threats: Vec<&str> = self.threats_detected.iter().map(String::as_str).collect();
+        let mut members = vec![
+            ("memory_locked", self.memory_locked.to_string()),
+            ("swap_disabled", self.swap_disabled.to_string()),
+            ("core_dumps_disabled", self.core_dumps_disabled.to_string()),
+            ("ptrace_blocked", self.ptrace_blocked.to_string()),
+            (
+                "ptrace_scope",
+                self.ptrace_scope
+                    .map_or("null".to_string(), |scope| scope.to_string()),
+            ),
+            ("monitoring_detected", self.monitoring_detected.to_string()),
+            (
+                "audit",
+                format!(
+                    "{{\"level\":{},\"daemon_running\":{},\"rules_readable\":{},\"execve_logged\":{},\"tty_logged\":{}}}",
+                    json_string(self.audit.level_name()),
+                    self.audit.daemon_running,
+                    self.audit.rules_readable,
+                    self.audit.execve_logged,
+                    self.audit.tty_logged
+                ),
+            ),
+            (
+                "platform_sandbox",
+                self.platform_sandbox
+                    .as_deref()
+                    .map_or("null".to_string(), json_string),
+            ),
+            ("kernel", format!("[{}]", kernel.join(","))),
+            ("threats", strings(&threats)),
+            ("grade", json_string(&self.grade().letter().to_string())),
+            ("weaknesses", strings(&self.weaknesses())),
+        ];
+        members.extend(extra.iter().map(|(key, value)| (*key, value.clone())));
+        let members: Vec<String> = members
+            .iter()
+            .map(|(key, value)| format!("{}:{}", json_string(key), value))
+            .collect();
+        format!("{{{}}}", members.join(","))
+    }
+}
+
+/// `text` as a JSON string literal
+pub fn json_string(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Lock memory pages to prevent swapping to disk
+#[allow(dead_code)]
+#[cfg(target_os = "linux")]
+pub fn lock_memory(ptr: *const u8, len: usize) -> io::Result<()> {
+    unsafe {
+        if mlock(ptr as *const c_void, len) == 0 {
+            Ok(())
+        } else {
+            Err(io::Error::last_os_error())
+        }
+    }
+}
+
+#[allow(dead_code)]
+#[cfg(target_os = "macos")]
+pub fn lock_memory(ptr: *const u8, len: usize) -> io::Result<()> {
+    unsafe {
+        if mlock(ptr as *const c_void, len) == 0 {
+            Ok(())
+        } else {
+            Err(io::Error::last_os_error())
+        }
+    }
+}
+
+/// Lock memory pages into the working set (VirtualLock)
+#[allow(dead_code)]
+#[cfg(windows)]
+pub fn lock_memory(ptr: *const u8, len: usize) -> io::Result<()> {
+    use windows_sys::Win32::System::Memory::VirtualLock;
+
+    if unsafe { VirtualLock(ptr as *const std::ffi::c_void, len) } != 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+#[allow(dead_code)]
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+pub fn lock_memory(_ptr: *const u8, _len: usize) -> io::Result<()> {
+    // Not supported on this platform
+    Ok(())
+}
+
+/// Whether secrets can be pinned in RAM: an mlock of a probe page succeeds
+#[cfg(target_os = "linux")]
+fn can_lock_memory() -> bool {
+    let probe = [0u8; 4096];
+    let locked = lock_memory(probe.as_ptr(), probe.len()).is_ok();
+    if locked {
+        unsafe { libc::munlock(probe.as_ptr() as *const c_void, probe.len()) };
+    }
+    locked
+}
+
+/// Whether the kernel won't write a core dump of this process: a zero core size
+/// limit, or the process is marked non-dumpable
+#[cfg(target_os = "linux")]
+fn core_dumps_blocked() -> bool {
+    let mut limit = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    let no_core_file =
+        unsafe { libc::getrlimit(libc::RLIMIT_CORE, &mut limit) } == 0 && limit.rlim_cur == 0;
+    no_core_file || unsafe { libc::prctl(libc::PR_GET_DUMPABLE) } == 0
+}
+
+/// Yama's ptrace restriction (/proc/sys/kernel/yama/ptrace_scope), None without Yama
+#[cfg(target_os = "linux")]
+pub fn ptrace_scope() -> Option<u8> {
+    read_sysctl("kernel.yama.ptrace_scope").and_then(|scope| u8::try_from(scope).ok())
+}
+
+/// Who Yama lets attach with ptrace at `scope`
+pub fn describe_ptrace_scope(scope: u8) -> &'static str {
+    match scope {
+        0 => "any process of the same user",
+        1 => "parent processes only",
+        2 => "CAP_SYS_PTRACE only",
+        _ => "nobody",
+    }
+}
+
+/// A sysctl that limits what other local users and processes can learn about this one
+#[derive(Debug, Clone, Copy)]
+pub struct KernelSetting {
+    pub name: &'static str,
+    /// None when the kernel doesn't have it
+    pub value: Option<i64>,
+    /// Lowest value that isn't flagged
+    pub minimum: i64,
+    pub suggested: i64,
+    /// What a lower value exposes
+    pub risk: &'static str,
+}
+
+impl KernelSetting {
+    pub fn is_weak(&self) -> bool {
+        self.value.is_some_and(|value| value < self.minimum)
+    }
+}
+
+/// `(sysctl, minimum, suggested, risk)` for the settings `::security-status` checks
+#[cfg(target_os = "linux")]
+const KERNEL_SETTINGS: [(&str, i64, i64, &str); 4] = [
+    (
+        "kernel.yama.ptrace_scope",
+        1,
+        1,
+        "any process of the same user may attach",
+    ),
+    (
+        "kernel.kptr_restrict",
+        1,
+        2,
+        "kernel addresses readable by every user",
+    ),
+    (
+        "kernel.dmesg_restrict",
+        1,
+        1,
+        "kernel log readable by every user",
+    ),
+    (
+        "kernel.perf_event_paranoid",
+        2,
+        2,
+        "unprivileged users may profile the kernel",
+    ),
+];
+
+/// A numeric sysctl such as `kernel.kptr_restrict`, from /proc/sys
+#[cfg(target_os = "linux")]
+fn read_sysctl(name: &str) -> Option<i64> {
+    fs::read_to_string(format!("/proc/sys/{}", name.replace('.', "/")))
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// Current values of the hardening sysctls
+#[cfg(target_os = "linux")]
+pub fn kernel_hardening() -> Vec<KernelSetting> {
+    KERNEL_SETTINGS
+        .iter()
+        .map(|&(name, minimum, suggested, risk)| KernelSetting {
+            name,
+            value: read_sysctl(name),
+            minimum,
+            suggested,
+            risk,
+        })
+        .collect()
+}
+
+/// Have the kernel refuse debuggers attaching from now on rather than only noticing
+/// them: drop any PR_SET_PTRACER exception (a crash handler may have named a tracer,
+/// or allowed any), then mark the process non-dumpable, which denies ptrace to every
+/// process without CAP_SYS_PTRACE whatever Yama allows, parents included. Children
+/// are dumpable again once they exec.
+#[cfg(target_os = "linux")]
+pub fn block_ptrace_attach() {
+    unsafe {
+        // EINVAL without Yama, where there is no exception to drop
+        libc::prctl(libc::PR_SET_PTRACER, 0 as libc::c_ulong);
+        libc::prctl(libc::PR_SET_DUMPABLE, 0 as libc::c_ulong);
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn block_ptrace_attach() {}
+
+/// Whether an unprivileged ptrace attach would be refused: the process is
+/// non-dumpable, or Yama only lets CAP_SYS_PTRACE attach
+#[cfg(target_os = "linux")]
+fn ptrace_attach_blocked() -> bool {
+    let dumpable = unsafe { libc::prctl(libc::PR_GET_DUMPABLE) };
+    dumpable == 0 || ptrace_scope().is_some_and(|scope| scope >= 2)
+}
+
+/// Prevent memory region from being included in core dumps
+#[allow(dead_code)]
+#[cfg(target_os = "linux")]
+pub fn disable_core_dump(ptr: *const u8, len: usize) -> io::Result<()> {
+    unsafe {
+        if madvise(ptr as *mut c_void, len, MADV_DONTDUMP) == 0 {
+            Ok(())
+        } else {
+            Err(io::Error::last_os_error())
+        }
+    }
+}
+
+#[allow(dead_code)]
+#[cfg(not(target_os = "linux"))]
+pub fn disable_core_dump(_ptr: *const u8, _len: usize) -> io::Result<()> {
+    Ok(())
+}
+
+/// Keep Windows Error Reporting from collecting crash dumps of this process:
+/// suppress the fault dialog and exclude our executable from WER
+#[cfg(windows)]
+pub fn suppress_crash_dumps() -> bool {
+    use windows_sys::Win32::System::Diagnostics::Debug::{SetErrorMode, SEM_NOGPFAULTERRORBOX};
+    use windows_sys::Win32::System::ErrorReporting::WerAddExcludedApplication;
+
+    unsafe {
+        SetErrorMode(SEM_NOGPFAULTERRORBOX);
+    }
+
+    let exe_name = match std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.file_name().map(|name| name.to_os_string()))
+    {
+        Some(name) => name,
+        None => return false,
+    };
+    let wide: Vec<u16> = exe_name.encode_wide().chain(std::iter::once(0)).collect();
+
+    // S_OK == 0; per-user exclusion (no admin rights required)
+    unsafe { WerAddExcludedApplication(wide.as_ptr(), 0) == 0 }
+}
+
+/// Mask the console window title (Windows can't rename a running process image)
+#[cfg(windows)]
+pub fn mask_console_title(title: &str) {
+    use windows_sys::Win32::System::Console::SetConsoleTitleW;
+
+    let wide: Vec<u16> = title.encode_utf16().chain(std::iter::once(0)).collect();
+    unsafe {
+        SetConsoleTitleW(wide.as_ptr());
+    }
+}
+
+/// Outcome of `apply_platform_sandbox`, reported by security-status
+#[cfg(any(target_os = "openbsd", target_os = "freebsd"))]
+static PLATFORM_SANDBOX: OnceLock<String> = OnceLock::new();
+
+/// Shrink the shell's own attack surface: unveil the filesystem read/exec-only
+/// (except /dev and /tmp), then pledge the syscalls an interactive shell needs.
+/// Both are reset on exec, so child commands are unaffected.
+#[cfg(target_os = "openbsd")]
+pub fn apply_platform_sandbox() {
+    let unveiled = unsafe {
+        libc::unveil(c"/".as_ptr(), c"rx".as_ptr()) == 0
+            && libc::unveil(c"/dev".as_ptr(), c"rw".as_ptr()) == 0
+            && libc::unveil(c"/tmp".as_ptr(), c"rwc".as_ptr()) == 0
+            && libc::unveil(std::ptr::null(), std::ptr::null()) == 0
+    };
+
+    let promises = c"stdio rpath wpath cpath fattr tty proc exec getpw unix inet dns";
+    let pledged = unsafe { libc::pledge(promises.as_ptr(), std::ptr::null()) == 0 };
+
+    let result = match (pledged, unveiled) {
+        (true, true) => "✓ pledge + unveil active".to_string(),
+        (true, false) => "⚠ pledge active, unveil FAILED".to_string(),
+        (false, true) => "⚠ unveil active, pledge FAILED".to_string(),
+        (false, false) => format!("✗ FAILED ({})", io::Error::last_os_error()),
+    };
+    let _ = PLATFORM_SANDBOX.set(result);
+}
+
+/// Capsicum: the shell has to exec arbitrary commands, so it can't enter
+/// capability mode itself; instead the rights on its stdio descriptors are
+/// limited to what a terminal needs. Children get fresh pipes, not these fds.
+#[cfg(target_os = "freebsd")]
+pub fn apply_platform_sandbox() {
+    let mut rights: libc::cap_rights_t = unsafe { std::mem::zeroed() };
+    unsafe {
+        libc::__cap_rights_init(
+            libc::CAP_RIGHTS_VERSION,
+            &mut rights,
+            libc::CAP_READ,
+            libc::CAP_WRITE,
+            libc::CAP_SEEK,
+            libc::CAP_IOCTL,
+            libc::CAP_FSTAT,
+            libc::CAP_EVENT,
+            libc::CAP_FCNTL,
+            0u64,
+        );
+    }
+
+    let limited = (0..=2).all(|fd| unsafe { libc::cap_rights_limit(fd, &rights) } == 0);
+    let result = if limited {
+        "✓ capsicum rights limited on stdio".to_string()
+    } else {
+        format!("✗ capsicum FAILED ({})", io::Error::last_os_error())
+    };
+    let _ = PLATFORM_SANDBOX.set(result);
+}
+
+#[cfg(not(any(target_os = "openbsd", target_os = "freebsd")))]
+pub fn apply_platform_sandbox() {}
+
+/// Description of the active OS-level sandbox, if this platform has one
+#[cfg(any(target_os = "openbsd", target_os = "freebsd"))]
+pub fn platform_sandbox_status() -> Option<String> {
+    Some(
+        PLATFORM_SANDBOX
+            .get()
+            .cloned()
+            .unwrap_or_else(|| "✗ NOT APPLIED".to_string()),
+    )
+}
+
+#[cfg(not(any(target_os = "openbsd", target_os = "freebsd")))]
+pub fn platform_sandbox_status() -> Option<String> {
+    None
+}
+
+/// Check if swap is enabled on the system
+#[cfg(target_os = "linux")]
+pub fn is_swap_enabled() -> bool {
+    if let Ok(meminfo) = fs::read_to_string("/proc/meminfo") {
+        for line in meminfo.lines() {
+            if line.starts_with("SwapTotal:") {
+                if let Some(value) = line.split_whitespace().nth(1) {
+                    if let Ok(swap_kb) = value.parse::<u64>() {
+                        return swap_kb > 0;
+                    }
+                }
+            }
+        }
+    }
+    false
+}
+
+#[cfg(target_os = "macos")]
+pub fn is_swap_enabled() -> bool {
+    let mut usage: libc::xsw_usage = unsafe { std::mem::zeroed() };
+    let mut size = std::mem::size_of::<libc::xsw_usage>();
+    let rc = unsafe {
+        libc::sysctlbyname(
+            c"vm.swapusage".as_ptr(),
+            &mut usage as *mut libc::xsw_usage as *mut c_void,
+            &mut size,
+            std::ptr::null_mut(),
+            0,
+        )
+    };
+    rc == 0 && usage.xsu_total > 0
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+pub fn is_swap_enabled() -> bool {
+    false
+}
+
+/// Detect if we're being traced/monitored
+#[cfg(target_os = "linux")]
+pub fn detect_monitoring() -> Vec<String> {
+    let mut threats = Vec::new();
+
+    // Check if we're being ptraced
+    if let Ok(status) = fs::read_to_string("/proc/self/status") {
+        for line in status.lines() {
+            if line.starts_with("TracerPid:") {
+                if let Some(pid) = line.split_whitespace().nth(1) {
+                    if pid != "0" {
+                        threats.push(format!("ptrace detected (PID: {})", pid));
+                    }
+                }
+            }
+        }
+    }
+
+    // Check for common monitoring tools
+    let monitoring_tools = vec![
+        "strace",
+        "ltrace",
+        "gdb",
+        "auditd",
+        "sysdig",
+        "bpftrace",
+        "perf",
+        "systemtap",
+    ];
+
+    if let Ok(processes) = fs::read_dir("/proc") {
+        for entry in processes.flatten() {
+            if let Ok(file_name) = entry.file_name().into_string() {
+                if file_name.chars().all(|c| c.is_ascii_digit()) {
+                    // ::trace runs strace under this shell on purpose
+                    let parent = fs::read_to_string(format!("/proc/{}/stat", file_name))
+                        .ok()
+                        .and_then(|stat| {
+                            let (_, rest) = stat.rsplit_once(')')?;
+                            rest.split_whitespace().nth(1)?.parse::<u32>().ok()
+                        });
+                    if parent == Some(std::process::id()) {
+                        continue;
+                    }
+                    let cmdline_path = format!("/proc/{}/cmdline", file_name);
+                    if let Ok(cmdline) = fs::read_to_string(&cmdline_path) {
+                        for tool in &monitoring_tools {
+                            if cmdline.contains(tool) {
+                                threats.push(format!("Monitoring tool detected: {}", tool));
+                                break;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    threats.extend(detect_ebpf_tracing());
+    threats.extend(detect_screen_capture());
+
+    threats
+}
+
+/// Screen recorders and screenshot daemons, by executable name
+#[cfg(target_os = "linux")]
+const SCREEN_CAPTURE_TOOLS: [&str; 14] = [
+    "obs",
+    "wf-recorder",
+    "wl-screenrec",
+    "gpu-screen-recorder",
+    "simplescreenrecorder",
+    "recordmydesktop",
+    "vokoscreenNG",
+    "kazam",
+    "peek",
+    "kooha",
+    "byzanz-record",
+    "flameshot",
+    "spectacle",
+    "gnome-screenshot",
+];
+
+/// Media tools that record the screen when given one of `SCREEN_GRAB_SOURCES`
+#[cfg(target_os = "linux")]
+const SCREEN_GRABBERS: [&str; 3] = ["ffmpeg", "avconv", "gst-launch-1.0"];
+
+/// ffmpeg input formats and GStreamer sources that read the screen
+#[cfg(target_os = "linux")]
+const SCREEN_GRAB_SOURCES: [&str; 4] = ["x11grab", "kmsgrab", "ximagesrc", "pipewiresrc"];
+
+/// D-Bus service of GNOME's built-in screen recorder, present while it records
+#[cfg(target_os = "linux")]
+const GNOME_SCREENCAST: &str = "org.gnome.Shell.Screencast";
+
+/// Detect active screen recording or screenshot tools: on-screen keys and output are
+/// as exposed to them as memory is to a debugger. Looks for recorder processes,
+/// ffmpeg/GStreamer grabbing the display, and a running GNOME screencast service.
+#[cfg(target_os = "linux")]
+pub fn detect_screen_capture() -> Vec<String> {
+    let mut threats = Vec::new();
+    let mut gnome_screencast = false;
+
+    if let Ok(processes) = fs::read_dir("/proc") {
+        for entry in processes.flatten() {
+            let pid = match entry.file_name().into_string() {
+                Ok(pid) if pid.chars().all(|c| c.is_ascii_digit()) => pid,
+                _ => continue,
+            };
+            let Ok(cmdline) = fs::read(format!("/proc/{}/cmdline", pid)) else {
+                continue;
+            };
+            let args: Vec<String> = cmdline
+                .split(|&b| b == 0)
+                .filter(|arg| !arg.is_empty())
+                .map(|arg| String::from_utf8_lossy(arg).into_owned())
+                .collect();
+            let Some(program) = args
+                .first()
+                .map(|arg| arg.rsplit('/').next().unwrap_or(arg))
+            else {
+                continue;
+            };
+            if SCREEN_CAPTURE_TOOLS.contains(&program) {
+                threats.push(format!(
+                    "Screen capture tool running: {} (PID: {})",
+                    program, pid
+                ));
+            } else if let Some(source) = SCREEN_GRAB_SOURCES
+                .iter()
+                .filter(|_| SCREEN_GRABBERS.contains(&program))
+                .find(|source| args[1..].iter().any(|arg| arg.contains(*source)))
+            {
+                threats.push(format!(
+                    "Screen capture detected: {} {} (PID: {})",
+                    program, source, pid
+                ));
+            } else if args.iter().any(|arg| arg.ends_with(GNOME_SCREENCAST)) {
+                gnome_screencast = true;
+                threats.push(format!("GNOME screencast session active (PID: {})", pid));
+            }
+        }
+    }
+
+    // Older GNOME runs the recorder inside gnome-shell; its bus name is owned while
+    // it records (activatable names show `-` for the PID until then)
+    if !gnome_screencast {
+        if let Ok(output) = std::process::Command::new("busctl")
+            .args(["--user", "--no-pager", "--no-legend", "list"])
+            .stderr(std::process::Stdio::null())
+            .output()
+        {
+            if output.status.success() {
+                let owned = String::from_utf8_lossy(&output.stdout).lines().any(|line| {
+                    let mut columns = line.split_whitespace();
+                    columns.next() == Some(GNOME_SCREENCAST)
+                        && columns.next().is_some_and(|pid| pid != "-")
+                });
+                if owned {
+                    threats.push("GNOME screencast session active (D-Bus)".to_string());
+                }
+            }
+        }
+    }
+
+    threats
+}
+
+/// BPF program types that can observe syscalls and tty activity
+/// (kprobe, tracepoint, perf_event, raw_tracepoint, raw_tracepoint_writable, tracing, lsm)
+#[cfg(target_os = "linux")]
+const BPF_TRACING_PROG_TYPES: [u32; 7] = [2, 5, 7, 17, 24, 26, 29];
+
+/// Attach points that reveal interest in process execution or terminal input
+#[cfg(target_os = "linux")]
+const BPF_SENSITIVE_HOOKS: [&str; 6] = [
+    "execve",
+    "execveat",
+    "sched_process_exec",
+    "bprm",
+    "tty",
+    "pty",
+];
+
+/// Detect eBPF programs that may be tracing execve or tty reads.
+/// Modern EDRs hook the kernel with eBPF instead of ptrace, so this looks at
+/// BPF program fds held by other processes, kprobe_events and `bpftool perf`.
+#[cfg(target_os = "linux")]
+pub fn detect_ebpf_tracing() -> Vec<String> {
+    let mut threats = Vec::new();
+    let own_pid = std::process::id().to_string();
+
+    // 1. Processes holding tracing-type BPF program fds (visible in fdinfo)
+    if let Ok(processes) = fs::read_dir("/proc") {
+        for entry in processes.flatten() {
+            let pid = match entry.file_name().into_string() {
+                Ok(pid) if pid.chars().all(|c| c.is_ascii_digit()) && pid != own_pid => pid,
+                _ => continue,
+            };
+
+            let fdinfo_dir = format!("/proc/{}/fdinfo", pid);
+            let fds = match fs::read_dir(&fdinfo_dir) {
+                Ok(fds) => fds,
+                Err(_) => continue,
+            };
+
+            let holds_tracing_prog = fds.flatten().any(|fd| {
+                fs::read_to_string(fd.path())
+                    .ok()
+                    .and_then(|info| {
+                        info.lines()
+                            .find_map(|line| line.strip_prefix("prog_type:"))
+                            .and_then(|t| t.trim().parse::<u32>().ok())
+                    })
+                    .is_some_and(|prog_type| BPF_TRACING_PROG_TYPES.contains(&prog_type))
+            });
+
+            if holds_tracing_prog {
+                let comm = fs::read_to_string(format!("/proc/{}/comm", pid))
+                    .unwrap_or_default()
+                    .trim()
+                    .to_string();
+                threats.push(format!(
+                    "eBPF tracing program held by {} (PID: {})",
+                    comm, pid
+                ));
+            }
+        }
+    }
+
+    // 2. Dynamic kprobes registered through tracefs
+    for path in [
+        "/sys/kernel/tracing/kprobe_events",
+        "/sys/kernel/debug/tracing/kprobe_events",
+    ] {
+        if let Ok(events) = fs::read_to_string(path) {
+            for line in events.lines() {
+                if BPF_SENSITIVE_HOOKS.iter().any(|hook| line.contains(hook)) {
+                    threats.push(format!("kprobe on sensitive hook: {}", line.trim()));
+                }
+            }
+            break;
+        }
+    }
+
+    // 3. Attachments reported by bpftool (usually requires root)
+    if let Ok(output) = std::process::Command::new("bpftool")
+        .args(["perf", "list"])
+        .stderr(std::process::Stdio::null())
+        .output()
+    {
+        if output.status.success() {
+            for line in String::from_utf8_lossy(&output.stdout).lines() {
+                if BPF_SENSITIVE_HOOKS.iter().any(|hook| line.contains(hook)) {
+                    threats.push(format!("eBPF attachment: {}", line.trim()));
+                }
+            }
+        }
+    }
+
+    threats
+}
+
+/// Detect if we're being traced/monitored (macOS: P_TRACED, dylib injection, process scan)
+#[cfg(target_os = "macos")]
+pub fn detect_monitoring() -> Vec<String> {
+    let mut threats = Vec::new();
+
+    if is_debugger_present() {
+        threats.push("Debugger attached (P_TRACED set)".to_string());
+    }
+
+    // DYLD_INSERT_LIBRARIES is the macOS equivalent of LD_PRELOAD
+    if let Some(inserted) = std::env::var_os("DYLD_INSERT_LIBRARIES") {
+        threats.push(format!(
+            "DYLD_INSERT_LIBRARIES set: {}",
+            inserted.to_string_lossy()
+        ));
+    }
+
+    // Images loaded from outside the system library paths
+    let exe = std::env::current_exe().ok();
+    let image_count = unsafe { _dyld_image_count() };
+    for index in 0..image_count {
+        let name_ptr = unsafe { _dyld_get_image_name(index) };
+        if name_ptr.is_null() {
+            continue;
+        }
+        let name = unsafe { CStr::from_ptr(name_ptr) }
+            .to_string_lossy()
+            .to_string();
+        let is_system = name.starts_with("/usr/lib/") || name.starts_with("/System/");
+        let is_self = exe
+            .as_ref()
+            .is_some_and(|exe| exe.as_os_str() == name.as_str());
+        if !is_system && !is_self {
+            threats.push(format!("Unexpected dylib loaded: {}", name));
+        }
+    }
+
+    // Check for common monitoring tools
+    let monitoring_tools = [
+        "dtrace",
+        "dtruss",
+        "lldb",
+        "gdb",
+        "fs_usage",
+        "sc_usage",
+        "opensnoop",
+        "execsnoop",
+        "eslogger",
+        "praudit",
+        "osqueryd",
+    ];
+
+    let capacity = unsafe { libc::proc_listallpids(std::ptr::null_mut(), 0) };
+    if capacity > 0 {
+        let mut pids: Vec<c_int> = vec![0; capacity as usize + 32];
+        let count = unsafe {
+            libc::proc_listallpids(
+                pids.as_mut_ptr() as *mut c_void,
+                (pids.len() * std::mem::size_of::<c_int>()) as c_int,
+            )
+        };
+        for &pid in pids.iter().take(count.max(0) as usize) {
+            let mut name = [0u8; 64];
+            let len = unsafe {
+                libc::proc_name(pid, name.as_mut_ptr() as *mut c_void, name.len() as u32)
+            };
+            if len <= 0 {
+                continue;
+            }
+            let name = String::from_utf8_lossy(&name[..len as usize]);
+            if let Some(tool) = monitoring_tools.iter().find(|tool| name == **tool) {
+                threats.push(format!("Monitoring tool detected: {}", tool));
+            }
+        }
+    }
+
+    threats
+}
+
+/// Detect if we're being debugged/monitored (Windows: debugger APIs, process scan)
+#[cfg(windows)]
+pub fn detect_monitoring() -> Vec<String> {
+    use windows_sys::Win32::System::Diagnostics::ToolHelp::{
+        CreateToolhelp32Snapshot, Process32FirstW, Process32NextW, PROCESSENTRY32W,
+        TH32CS_SNAPPROCESS,
+    };
+
+    let mut threats = Vec::new();
+
+    if is_debugger_present() {
+        threats.push("Debugger attached (IsDebuggerPresent/ProcessDebugPort)".to_string());
+    }
+
+    // Check for common monitoring tools
+    let monitoring_tools = [
+        "procmon",
+        "procexp",
+        "processhacker",
+        "x64dbg",
+        "x32dbg",
+        "ollydbg",
+        "windbg",
+        "ida",
+        "wireshark",
+        "sysmon",
+        "apimonitor",
+    ];
+
+    unsafe {
+        let snapshot = CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0);
+        if snapshot != INVALID_HANDLE_VALUE {
+            let mut entry: PROCESSENTRY32W = std::mem::zeroed();
+            entry.dwSize = std::mem::size_of::<PROCESSENTRY32W>() as u32;
+            let mut more = Process32FirstW(snapshot, &mut entry) != 0;
+            while more {
+                let len = entry
+                    .szExeFile
+                    .iter()
+                    .position(|&c| c == 0)
+                    .unwrap_or(entry.szExeFile.len());
+                let name = String::from_utf16_lossy(&entry.szExeFile[..len]).to_lowercase();
+                if let Some(tool) = monitoring_tools
+                    .iter()
+                    .find(|tool| name.starts_with(**tool))
+                {
+                    threats.push(format!("Monitoring tool detected: {}", tool));
+                }
+                more = Process32NextW(snapshot, &mut entry) != 0;
+            }
+            CloseHandle(snapshot);
+        }
+    }
+
+    threats
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+pub fn detect_monitoring() -> Vec<String> {
+    Vec::new()
+}
+
+/// Inspect the audit subsystem: is auditd running, and do its rules log
+/// execve calls or TTY input? Rules are read from `auditctl -l` when
+/// permitted, falling back to the on-disk rule files.
+#[cfg(target_os = "linux")]
+pub fn inspect_audit_rules() -> AuditInspection {
+    let mut inspection = AuditInspection::new();
+
+    if let Ok(processes) = fs::read_dir("/proc") {
+        inspection.daemon_running = processes.flatten().any(|entry| {
+            fs::read_to_string(entry.path().join("comm")).is_ok_and(|comm| comm.trim() == "auditd")
+        });
+    }
+
+    let mut rules = String::new();
+    if let Ok(output) = std::process::Command::new("auditctl")
+        .arg("-l")
+        .stderr(std::process::Stdio::null())
+        .output()
+    {
+        if output.status.success() {
+            rules.push_str(&String::from_utf8_lossy(&output.stdout));
+            inspection.rules_readable = true;
+        }
+    }
+
+    if !inspection.rules_readable {
+        let mut rule_files = vec![std::path::PathBuf::from("/etc/audit/audit.rules")];
+        if let Ok(entries) = fs::read_dir("/etc/audit/rules.d") {
+            rule_files.extend(entries.flatten().map(|e| e.path()));
+        }
+        for path in rule_files {
+            if let Ok(content) = fs::read_to_string(&path) {
+                rules.push_str(&content);
+                rules.push('\n');
+                inspection.rules_readable = true;
+            }
+        }
+    }
+
+    inspection.execve_logged = rules
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.starts_with('#'))
+        .any(|line| line.contains("-S") && line.contains("execve"));
+
+    // TTY auditing is switched on per-session by pam_tty_audit
+    if let Ok(entries) = fs::read_dir("/etc/pam.d") {
+        inspection.tty_logged = entries.flatten().any(|entry| {
+            fs::read_to_string(entry.path()).is_ok_and(|content| {
+                content.lines().map(str::trim).any(|line| {
+                    !line.starts_with('#')
+                        && line.contains("pam_tty_audit.so")
+                        && line.contains("enable=")
+                })
+            })
+        });
+    }
+
+    inspection
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn inspect_audit_rules() -> AuditInspection {
+    AuditInspection::new()
+}
+
+/// Snapshot of the executable regions and shared objects mapped into our address space
+#[cfg(target_os = "linux")]
+#[derive(Debug, Clone, Default)]
+pub struct MapsSnapshot {
+    exec_regions: BTreeSet<String>,
+    libraries: BTreeSet<String>,
+}
+
+#[cfg(target_os = "linux")]
+impl MapsSnapshot {
+    /// Read /proc/self/maps and record every executable mapping and loaded library
+    pub fn capture() -> Self {
+        let mut snapshot = MapsSnapshot::default();
+
+        if let Ok(maps) = fs::read_to_string("/proc/self/maps") {
+            for line in maps.lines() {
+                // Format: address perms offset dev inode [path]
+                let fields: Vec<&str> = line.split_whitespace().collect();
+                if fields.len() < 5 {
+                    continue;
+                }
+                let range = fields[0];
+                let perms = fields[1];
+                let path = if fields.len() > 5 {
+                    fields[5..].join(" ")
+                } else {
+                    String::new()
+                };
+
+                if path.contains(".so") {
+                    snapshot.libraries.insert(path.clone());
+                }
+
+                if perms.contains('x') {
+                    let label = if path.is_empty() {
+                        "[anonymous]".to_string()
+                    } else {
+                        path
+                    };
+                    snapshot.exec_regions.insert(format!("{} {}", range, label));
+                }
+            }
+        }
+
+        snapshot
+    }
+
+    /// List everything present in `self` that was not in `baseline`. Libraries the
+    /// loader maps from the system directories later on (NSS modules pulled in by the
+    /// first name lookup, for instance) are expected; anonymous, deleted or
+    /// out-of-tree code is not.
+    pub fn diff(&self, baseline: &MapsSnapshot) -> Vec<String> {
+        let mut anomalies = Vec::new();
+
+        for lib in self.libraries.difference(&baseline.libraries) {
+            if !system_library(lib) {
+                anomalies.push(format!("Unexpected library loaded: {}", lib));
+            }
+        }
+
+        for region in self.exec_regions.difference(&baseline.exec_regions) {
+            let label = region.split_once(' ').map_or("", |(_, label)| label);
+            if !system_library(label) {
+                anomalies.push(format!("New executable region: {}", region));
+            }
+        }
+
+        anomalies
+    }
+}
+
+/// Directories ld.so loads shared objects from; only root can write there
+#[cfg(target_os = "linux")]
+const SYSTEM_LIBRARY_DIRS: &[&str] = &["/lib/", "/lib64/", "/usr/lib/", "/usr/lib64/"];
+
+/// Whether `path` is a shared object still on disk in a system library directory
+#[cfg(target_os = "linux")]
+fn system_library(path: &str) -> bool {
+    path.contains(".so")
+        && !path.ends_with("(deleted)")
+        && !path.contains("/../")
+        && SYSTEM_LIBRARY_DIRS.iter().any(|dir| path.starts_with(dir))
+}
+
+#[cfg(target_os = "linux")]
+static MAPS_BASELINE: Mutex<Option<MapsSnapshot>> = Mutex::new(None);
+
+/// Record the current memory map as the trusted baseline for later audits
+#[cfg(target_os = "linux")]
+pub fn record_maps_baseline() {
+    if let Ok(mut baseline) = MAPS_BASELINE.lock() {
+        *baseline = Some(MapsSnapshot::capture());
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn record_maps_baseline() {}
+
+/// Compare the live memory map against the baseline to spot runtime code injection
+#[cfg(target_os = "linux")]
+pub fn audit_memory_maps() -> Vec<String> {
+    match MAPS_BASELINE.lock() {
+        Ok(baseline) => match baseline.as_ref() {
+            Some(baseline) => MapsSnapshot::capture().diff(baseline),
+            None => Vec::new(),
+        },
+        Err(_) => Vec::new(),
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn audit_memory_maps() -> Vec<String> {
+    Vec::new()
+}
+
+/// Initialize security measures
+pub fn initialize_security() -> SecurityStatus {
+    let mut status = SecurityStatus::new();
+
+    // Check swap, and whether secrets can be locked in RAM and kept out of core dumps
+    status.swap_disabled = !is_swap_enabled();
+    #[cfg(target_os = "linux")]
+    {
+        status.memory_locked = can_lock_memory();
+        status.core_dumps_disabled = core_dumps_blocked();
+        status.ptrace_blocked = ptrace_attach_blocked();
+        status.ptrace_scope = ptrace_scope();
+        status.kernel = kernel_hardening();
+    }
+    status.platform_sandbox = platform_sandbox_status();
+
+    #[cfg(windows)]
+    {
+        status.core_dumps_disabled = suppress_crash_dumps();
+    }
+
+    // Detect monitoring, runtime code injection and anything phoning home
+    let mut threats = detect_monitoring();
+    threats.extend(audit_memory_maps());
+    threats.extend(crate::netaudit::audit_own_sockets());
+    #[cfg(target_os = "linux")]
+    threats.extend(crate::x11::detect_keyboard_interception());
+
+    // Inspect audit rules for command/keystroke logging
+    status.audit = inspect_audit_rules();
+    if status.audit.execve_logged {
+        threats.push("auditd is logging execve (command lines recorded)".to_string());
+    }
+    if status.audit.tty_logged {
+        threats.push("pam_tty_audit is logging TTY input (keystrokes recorded)".to_string());
+    }
+    status.monitoring_detected = !threats.is_empty();
+    status.threats_detected = threats;
+
+    status
+}
+
+/// Anti-debugging: Check if debugger is attached
+#[cfg(target_os = "linux")]
+pub fn is_debugger_present() -> bool {
+    if let Ok(status) = fs::read_to_string("/proc/self/status") {
+        for line in status.lines() {
+            if line.starts_with("TracerPid:") {
+                if let Some(pid) = line.split_whitespace().nth(1) {
+                    return pid != "0";
+                }
+            }
+        }
+    }
+    false
+}
+
+/// Size of `struct kinfo_proc` and offset of `kp_proc.p_flag` (libc doesn't bind it on Apple)
+#[cfg(target_os = "macos")]
+const KINFO_PROC_SIZE: usize = 648;
+#[cfg(target_os = "macos")]
+const P_FLAG_OFFSET: usize = 32;
+#[cfg(target_os = "macos")]
+const P_TRACED: i32 = 0x0000_0800;
+
+/// Anti-debugging: Check the P_TRACED flag via sysctl(KERN_PROC_PID)
+#[cfg(target_os = "macos")]
+pub fn is_debugger_present() -> bool {
+    let mut mib: [c_int; 4] = [
+        libc::CTL_KERN,
+        libc::KERN_PROC,
+        libc::KERN_PROC_PID,
+        std::process::id() as c_int,
+    ];
+    let mut info = [0u8; KINFO_PROC_SIZE];
+    let mut size = info.len();
+
+    let rc = unsafe {
+        libc::sysctl(
+            mib.as_mut_ptr(),
+            mib.len() as libc::c_uint,
+            info.as_mut_ptr() as *mut c_void,
+            &mut size,
+            std::ptr::null_mut(),
+            0,
+        )
+    };
+    if rc != 0 || size < P_FLAG_OFFSET + 4 {
+        return false;
+    }
+
+    let mut flag = [0u8; 4];
+    flag.copy_from_slice(&info[P_FLAG_OFFSET..P_FLAG_OFFSET + 4]);
+    i32::from_ne_bytes(flag) & P_TRACED != 0
+}
+
+/// Anti-debugging: IsDebuggerPresent, CheckRemoteDebuggerPresent and the
+/// ProcessDebugPort info class of NtQueryInformationProcess
+#[cfg(windows)]
+pub fn is_debugger_present() -> bool {
+    use windows_sys::Wdk::System::Threading::{NtQueryInformationProcess, ProcessDebugPort};
+    use windows_sys::Win32::System::Diagnostics::Debug::{
+        CheckRemoteDebuggerPresent, IsDebuggerPresent,
+    };
+
+    unsafe {
+        if IsDebuggerPresent() != 0 {
+            return true;
+        }
+
+        let process = GetCurrentProcess();
+        let mut remote = 0;
+        if CheckRemoteDebuggerPresent(process, &mut remote) != 0 && remote != 0 {
+            return true;
+        }
+
+        let mut debug_port: isize = 0;
+        let status = NtQueryInformationProcess(
+            process,
+            ProcessDebugPort,
+            &mut debug_port as *mut isize as *mut std::ffi::c_void,
+            std::mem::size_of::<isize>() as u32,
+            std::ptr::null_mut(),
+        );
+        status == 0 && debug_port != 0
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+pub fn is_debugger_present() -> bool {
+    false
+}
+
+/// A `Name:` field of /proc/self/status, without the name
+#[cfg(target_os = "linux")]
+fn self_status_field(name: &str) -> Option<String> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix(name)?.strip_prefix(':'))
+        .map(|value| value.trim().to_string())
+}
+
+/// Pid of the process tracing this one, 0 when none
+#[cfg(target_os = "linux")]
+pub fn tracer_pid() -> Option<u32> {
+    self_status_field("TracerPid")?.parse().ok()
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn tracer_pid() -> Option<u32> {
+    None
+}
+
+/// Memory this process has locked into RAM, in kB
+#[cfg(target_os = "linux")]
+pub fn locked_memory_kb() -> Option<u64> {
+    self_status_field("VmLck")?
+        .trim_end_matches("kB")
+        .trim()
+        .parse()
+        .ok()
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn locked_memory_kb() -> Option<u64> {
+    None
+}
@@ -0,0 +1,67 @@
+/// Spotting secrets typed as command arguments, for the warning shown while typing
+/// A password, token or key passed on the command line is readable by anyone who can
+/// run `ps` or read `/proc/<pid>/cmdline`, and lands in execve audit logs. The
+/// patterns are deliberately narrow: a warning that fires on ordinary commands would
+/// soon be ignored.
+use std::sync::OnceLock;
+
+use crate::regex::Regex;
+
+/// What each pattern spots, the pattern, and whether case is ignored
+const PATTERNS: &[(&str, &str, bool)] = &[
+    ("Authorization header", r"authorization:\s*\S", true),
+    ("bearer token", r"bearer\s+[A-Za-z0-9._~+/=-]{8,}", true),
+    (
+        "password variable",
+        r"(^|\s)[A-Za-z_]*(pass|passwd|password|secret|token|api_?key)[A-Za-z0-9_]*=[^\s]",
+        true,
+    ),
+    (
+        "password option",
+        r"(^|\s)--?(pass|passwd|password|token|secret|api-key)(=|\s+)[^\s-]",
+        true,
+    ),
+    ("credentials in a URL", r"://[^/\s:@]+:[^/\s@]+@", false),
+    (
+        "user:password option",
+        r"(^|\s)(curl|wget)\s(.*\s)?(-u|--user)(\s*|=)[^\s:]+:[^\s]",
+        false,
+    ),
+    (
+        "database password",
+        r"(^|\s)(mysql|mysqldump|mariadb)\s(.*\s)?-p[^\s]",
+        false,
+    ),
+    ("sshpass password", r"sshpass\s+-p\s*[^\s]", false),
+    (
+        "access token",
+        r"(ghp_|gho_|ghs_|github_pat_|glpat-|sk_live_)[A-Za-z0-9_-]{10,}|xox[abprs]-[A-Za-z0-9-]{10,}|AKIA[0-9A-Z]{16}",
+        false,
+    ),
+    ("private key", r"-----BEGIN [A-Z ]*PRIVATE KEY", false),
+];
+
+fn patterns() -> &'static [(&'static str, Regex)] {
+    static COMPILED: OnceLock<Vec<(&'static str, Regex)>> = OnceLock::new();
+    COMPILED.get_or_init(|| {
+        PATTERNS
+            .iter()
+            .filter_map(|(what, pattern, ignore_case)| {
+                let regex = if *ignore_case {
+                    Regex::case_insensitive(pattern)
+                } else {
+                    Regex::new(pattern)
+                };
+                regex.ok().map(|regex| (*what, regex))
+            })
+            .collect()
+    })
+}
+
+/// What kind of secret `line` seems to pass as an argument, if any
+pub fn detect_secret(line: &str) -> Option<&'static str> {
+    patterns()
+        .iter()
+        .find(|(_, regex)| regex.is_match(line))
+        .map(|(what, _)| *what)
+}
@@ -0,0 +1,193 @@
+/// Shamir secret sharing over GF(256) for ::split and ::combine
+/// Each byte of `secret || SHA256(secret)[..4]` is the constant term of its own random
+/// polynomial of degree k-1; share x holds the evaluations at x. The checksum lets
+/// ::combine tell a wrong or mismatched share set from a recovered secret.
+/// Shares read `ghost-sss-v1:<set id>:<k>:<x>:<base64url data>`.
+use base64::{engine::general_purpose, Engine as _};
+use chacha20poly1305::aead::OsRng;
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use zeroize::Zeroize;
+
+const SHARE_PREFIX: &str = "ghost-sss-v1";
+
+/// Bytes of SHA-256 appended to the secret before splitting
+const CHECKSUM_LEN: usize = 4;
+
+/// Split `secret` into `n` shares, any `k` of which recover it
+pub fn split(secret: &[u8], k: u8, n: u8) -> Result<Vec<String>, String> {
+    if k < 2 || k > n {
+        return Err("split: need 2 <= k <= n <= 255.".to_string());
+    }
+    if secret.is_empty() {
+        return Err("split: empty secret.".to_string());
+    }
+
+    let mut set_id = [0u8; 4];
+    OsRng.fill_bytes(&mut set_id);
+    let set_id: String = set_id.iter().map(|b| format!("{:02x}", b)).collect();
+
+    let mut data = secret.to_vec();
+    data.extend_from_slice(&Sha256::digest(secret)[..CHECKSUM_LEN]);
+
+    let mut shares: Vec<Vec<u8>> = vec![Vec::with_capacity(data.len()); n as usize];
+    let mut coefficients = vec![0u8; k as usize];
+    for &byte in &data {
+        coefficients[0] = byte;
+        OsRng.fill_bytes(&mut coefficients[1..]);
+        for (i, share) in shares.iter_mut().enumerate() {
+            share.push(evaluate(&coefficients, i as u8 + 1));
+        }
+    }
+    coefficients.zeroize();
+    data.zeroize();
+
+    Ok(shares
+        .iter_mut()
+        .enumerate()
+        .map(|(i, share)| {
+            let encoded = format!(
+                "{}:{}:{}:{}:{}",
+                SHARE_PREFIX,
+                set_id,
+                k,
+                i + 1,
+                general_purpose::URL_SAFE_NO_PAD.encode(&share)
+            );
+            share.zeroize();
+            encoded
+        })
+        .collect())
+}
+
+/// Recover the secret from at least k shares of the same set
+pub fn combine(shares: &[&str]) -> Result<Vec<u8>, String> {
+    let mut parsed = Vec::with_capacity(shares.len());
+    for share in shares {
+        parsed.push(parse_share(share)?);
+    }
+
+    let (set_id, k, _, _) = parsed.first().ok_or("combine: no shares given.")?;
+    let (set_id, k) = (set_id.clone(), *k);
+    let mut points: Vec<(u8, Vec<u8>)> = Vec::with_capacity(parsed.len());
+    for (id, share_k, x, data) in parsed {
+        if id != set_id || share_k != k {
+            return Err("combine: shares come from different splits.".to_string());
+        }
+        if points.iter().any(|(existing, _)| *existing == x) {
+            continue;
+        }
+        if points
+            .first()
+            .is_some_and(|(_, first)| first.len() != data.len())
+        {
+            return Err("combine: shares have different lengths.".to_string());
+        }
+        points.push((x, data));
+    }
+    if points.len() < k as usize {
+        return Err(format!(
+            "combine: need {} distinct shares, got {}.",
+            k,
+            points.len()
+        ));
+    }
+    points.truncate(k as usize);
+
+    let length = points[0].1.len();
+    let mut data: Vec<u8> = (0..length)
+        .map(|i| interpolate_at_zero(&points, i))
+        .collect();
+    for (_, share) in points.iter_mut() {
+        share.zeroize();
+    }
+
+    if length <= CHECKSUM_LEN {
+        data.zeroize();
+        return Err("combine: share data too short.".to_string());
+    }
+    let secret_len = length - CHECKSUM_LEN;
+    let valid = Sha256::digest(&data[..secret_len])[..CHECKSUM_LEN] == data[secret_len..];
+    data[secret_len..].zeroize();
+    data.truncate(secret_len);
+    if !valid {
+        data.zeroize();
+        return Err("combine: checksum mismatch (wrong or corrupted shares).".to_string());
+    }
+    Ok(data)
+}
+
+/// Set id, k, x and data of one share
+fn parse_share(share: &str) -> Result<(String, u8, u8, Vec<u8>), String> {
+    let invalid = || format!("combine: not a valid share: {}", share);
+    let mut fields = share.trim().split(':');
+    if fields.next() != Some(SHARE_PREFIX) {
+        return Err(invalid());
+    }
+    let (Some(set_id), Some(k), Some(x), Some(data), None) = (
+        fields.next(),
+        fields.next().and_then(|k| k.parse::<u8>().ok()),
+        fields.next().and_then(|x| x.parse::<u8>().ok()),
+        fields
+            .next()
+            .and_then(|data| general_purpose::URL_SAFE_NO_PAD.decode(data).ok()),
+        fields.next(),
+    ) else {
+        return Err(invalid());
+    };
+    if x == 0 || k < 2 {
+        return Err(invalid());
+    }
+    Ok((set_id.to_string(), k, x, data))
+}
+
+/// Polynomial with `coefficients` (constant term first) evaluated at `x`
+fn evaluate(coefficients: &[u8], x: u8) -> u8 {
+    coefficients
+        .iter()
+        .rev()
+        .fold(0, |acc, &coefficient| gf_mul(acc, x) ^ coefficient)
+}
+
+/// Lagrange interpolation at x = 0 of byte `index` across `points`
+fn interpolate_at_zero(points: &[(u8, Vec<u8>)], index: usize) -> u8 {
+    let mut value = 0;
+    for (i, (xi, yi)) in points.iter().enumerate() {
+        let mut basis = 1;
+        for (j, (xj, _)) in points.iter().enumerate() {
+            if i != j {
+                // (0 - xj) / (xi - xj); subtraction is XOR in GF(256)
+                basis = gf_mul(basis, gf_mul(*xj, gf_inverse(xi ^ xj)));
+            }
+        }
+        value ^= gf_mul(yi[index], basis);
+    }
+    value
+}
+
+/// Multiplication in GF(2^8) modulo x^8 + x^4 + x^3 + x + 1, without data-dependent branches
+fn gf_mul(mut a: u8, mut b: u8) -> u8 {
+    let mut product = 0;
+    for _ in 0..8 {
+        product ^= a & (b & 1).wrapping_neg();
+        let carry = (a >> 7).wrapping_neg();
+        a = (a << 1) ^ (0x1b & carry);
+        b >>= 1;
+    }
+    product
+}
+
+/// Multiplicative inverse (a^254); only called with non-zero x differences
+fn gf_inverse(a: u8) -> u8 {
+    let mut result = 1;
+    let mut power = a;
+    let mut exponent = 254u8;
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            result = gf_mul(result, power);
+        }
+        power = gf_mul(power, power);
+        exponent >>= 1;
+    }
+    result
+}
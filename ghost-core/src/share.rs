@@ -0,0 +1,249 @@
+/// Encrypted clipboard transfer between two Ghost Shell instances
+/// Both ends send an ephemeral X25519 public key, derive a ChaCha20-Poly1305 key with
+/// HKDF-SHA256, and the sender writes `nonce (12) | length (u32 BE) | ciphertext`.
+/// A short verification code derived from the same secret is shown on both ends so
+/// a man in the middle can be ruled out.
+use chacha20poly1305::aead::OsRng;
+use hkdf::Hkdf;
+use sha2::Sha256;
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::thread;
+use std::time::{Duration, Instant};
+use x25519_dalek::{EphemeralSecret, PublicKey};
+use zeroize::Zeroize;
+
+use crate::clipboard::{open_bytes, seal_bytes};
+use crate::secret::SecretString;
+
+/// Default TCP port for `::share`
+pub const SHARE_PORT: u16 = 47290;
+
+/// Largest payload accepted from a peer
+const MAX_SHARE_SIZE: usize = 1024 * 1024;
+
+/// How long `::share recv` waits for a peer
+pub const ACCEPT_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Connect/read/write timeout once a peer is involved
+const IO_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// HKDF context strings, versioned with the wire format
+const KEY_INFO: &[u8] = b"ghost-shell share v1 key";
+const CODE_INFO: &[u8] = b"ghost-shell share v1 verification";
+
+/// Keys agreed for one transfer
+struct SessionKeys {
+    key: [u8; 32],
+    /// Short code both users compare to detect a man in the middle
+    code: String,
+}
+
+impl Drop for SessionKeys {
+    fn drop(&mut self) {
+        self.key.zeroize();
+    }
+}
+
+/// Send `payload` to the `::share recv` listening at `target`
+/// (`host`, `host:port`, or a Unix socket path). Returns the verification code.
+pub fn send(target: &str, payload: &str) -> Result<String, String> {
+    #[cfg(unix)]
+    if target.contains('/') {
+        let mut stream =
+            UnixStream::connect(target).map_err(|e| format!("share: {}: {}", target, e))?;
+        set_timeouts_unix(&stream)?;
+        return send_over(&mut stream, payload);
+    }
+
+    let addr = with_default_port(target)
+        .to_socket_addrs()
+        .map_err(|e| format!("share: {}: {}", target, e))?
+        .next()
+        .ok_or_else(|| format!("share: {}: no address found", target))?;
+    let mut stream = TcpStream::connect_timeout(&addr, IO_TIMEOUT)
+        .map_err(|e| format!("share: {}: {}", target, e))?;
+    stream
+        .set_read_timeout(Some(IO_TIMEOUT))
+        .and_then(|_| stream.set_write_timeout(Some(IO_TIMEOUT)))
+        .map_err(|e| format!("share: {}", e))?;
+    send_over(&mut stream, payload)
+}
+
+/// Wait up to `ACCEPT_TIMEOUT` for one `::share send` on `bind`
+/// (`addr:port`, `:port`, or a Unix socket path).
+/// Returns the received payload and the verification code.
+pub fn receive(bind: &str) -> Result<(SecretString, String), String> {
+    #[cfg(unix)]
+    if bind.contains('/') {
+        let listener = UnixListener::bind(bind).map_err(|e| format!("share: {}: {}", bind, e))?;
+        if let Err(e) = listener.set_nonblocking(true) {
+            let _ = std::fs::remove_file(bind);
+            return Err(format!("share: {}", e));
+        }
+        let accepted = accept_with_timeout(|| listener.accept().map(|(stream, _)| stream));
+        let _ = std::fs::remove_file(bind);
+        let mut stream = accepted?;
+        stream
+            .set_nonblocking(false)
+            .map_err(|e| format!("share: {}", e))?;
+        set_timeouts_unix(&stream)?;
+        return receive_over(&mut stream);
+    }
+
+    let bind = if bind.starts_with(':') {
+        format!("0.0.0.0{}", bind)
+    } else {
+        with_default_port(bind)
+    };
+    let listener = TcpListener::bind(&bind).map_err(|e| format!("share: {}: {}", bind, e))?;
+    listener
+        .set_nonblocking(true)
+        .map_err(|e| format!("share: {}", e))?;
+    let mut stream = accept_with_timeout(|| listener.accept().map(|(stream, _)| stream))?;
+    stream
+        .set_nonblocking(false)
+        .and_then(|_| stream.set_read_timeout(Some(IO_TIMEOUT)))
+        .and_then(|_| stream.set_write_timeout(Some(IO_TIMEOUT)))
+        .map_err(|e| format!("share: {}", e))?;
+    receive_over(&mut stream)
+}
+
+/// Append the default port unless `target` already names one
+fn with_default_port(target: &str) -> String {
+    if target
+        .rsplit_once(':')
+        .is_some_and(|(_, port)| port.parse::<u16>().is_ok())
+    {
+        target.to_string()
+    } else {
+        format!("{}:{}", target, SHARE_PORT)
+    }
+}
+
+#[cfg(unix)]
+fn set_timeouts_unix(stream: &UnixStream) -> Result<(), String> {
+    stream
+        .set_read_timeout(Some(IO_TIMEOUT))
+        .and_then(|_| stream.set_write_timeout(Some(IO_TIMEOUT)))
+        .map_err(|e| format!("share: {}", e))
+}
+
+/// Poll a non-blocking `accept` until a peer connects or `ACCEPT_TIMEOUT` passes
+fn accept_with_timeout<S>(mut accept: impl FnMut() -> io::Result<S>) -> Result<S, String> {
+    let deadline = Instant::now() + ACCEPT_TIMEOUT;
+    loop {
+        match accept() {
+            Ok(stream) => return Ok(stream),
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                if Instant::now() >= deadline {
+                    return Err("share: no peer connected in time.".to_string());
+                }
+                thread::sleep(Duration::from_millis(100));
+            }
+            Err(e) => return Err(format!("share: {}", e)),
+        }
+    }
+}
+
+fn send_over<S: Read + Write>(stream: &mut S, payload: &str) -> Result<String, String> {
+    if payload.len() > MAX_SHARE_SIZE {
+        return Err(format!(
+            "share: payload too large ({} bytes, limit {}).",
+            payload.len(),
+            MAX_SHARE_SIZE
+        ));
+    }
+
+    let keys = handshake(stream, true)?;
+    let (nonce, ciphertext) = seal_bytes(&keys.key, payload.as_bytes())?;
+
+    let mut frame = Vec::with_capacity(16 + ciphertext.len());
+    frame.extend_from_slice(&nonce);
+    frame.extend_from_slice(&(ciphertext.len() as u32).to_be_bytes());
+    frame.extend_from_slice(&ciphertext);
+    stream
+        .write_all(&frame)
+        .and_then(|_| stream.flush())
+        .map_err(|e| format!("share: {}", e))?;
+
+    Ok(keys.code.clone())
+}
+
+fn receive_over<S: Read + Write>(stream: &mut S) -> Result<(SecretString, String), String> {
+    let keys = handshake(stream, false)?;
+
+    let mut header = [0u8; 16];
+    stream
+        .read_exact(&mut header)
+        .map_err(|e| format!("share: {}", e))?;
+    let length = u32::from_be_bytes([header[12], header[13], header[14], header[15]]) as usize;
+    // Poly1305 tag adds 16 bytes to the plaintext
+    if length > MAX_SHARE_SIZE + 16 {
+        return Err("share: peer sent an oversized payload.".to_string());
+    }
+
+    let mut ciphertext = vec![0u8; length];
+    stream
+        .read_exact(&mut ciphertext)
+        .map_err(|e| format!("share: {}", e))?;
+
+    let payload = open_bytes(&keys.key, &header[..12], &ciphertext)?
+        .into_text()
+        .map_err(|_| "share: received data is not valid UTF-8.".to_string())?;
+
+    Ok((payload, keys.code.clone()))
+}
+
+/// Exchange ephemeral public keys and derive the session keys.
+/// The initiator (sender) writes first; both sides salt HKDF with
+/// `sender public key | receiver public key`.
+fn handshake<S: Read + Write>(stream: &mut S, initiator: bool) -> Result<SessionKeys, String> {
+    let secret = EphemeralSecret::random_from_rng(OsRng);
+    let public = PublicKey::from(&secret);
+
+    let mut peer_bytes = [0u8; 32];
+    let io_result = if initiator {
+        stream
+            .write_all(public.as_bytes())
+            .and_then(|_| stream.flush())
+            .and_then(|_| stream.read_exact(&mut peer_bytes))
+    } else {
+        stream.read_exact(&mut peer_bytes).and_then(|_| {
+            stream
+                .write_all(public.as_bytes())
+                .and_then(|_| stream.flush())
+        })
+    };
+    io_result.map_err(|e| format!("share: handshake failed: {}", e))?;
+    let peer = PublicKey::from(peer_bytes);
+
+    let shared = secret.diffie_hellman(&peer);
+    if !shared.was_contributory() {
+        return Err("share: handshake failed: peer sent a weak public key.".to_string());
+    }
+
+    let mut salt = [0u8; 64];
+    let (first, second) = if initiator {
+        (&public, &peer)
+    } else {
+        (&peer, &public)
+    };
+    salt[..32].copy_from_slice(first.as_bytes());
+    salt[32..].copy_from_slice(second.as_bytes());
+
+    let hkdf = Hkdf::<Sha256>::new(Some(&salt), shared.as_bytes());
+    let mut key = [0u8; 32];
+    let mut code_bytes = [0u8; 4];
+    hkdf.expand(KEY_INFO, &mut key)
+        .and_then(|_| hkdf.expand(CODE_INFO, &mut code_bytes))
+        .map_err(|_| "share: key derivation failed.".to_string())?;
+
+    let code = format!(
+        "{:02X}{:02X}-{:02X}{:02X}",
+        code_bytes[0], code_bytes[1], code_bytes[2], code_bytes[3]
+    );
+    Ok(SessionKeys { key, code })
+}
@@ -0,0 +1,5117 @@
+/// Interactive shell state: the input line, RAM-only history, session settings and
+/// the `::` command set. A front end feeds keystrokes into [`SecureBuffer`], calls
+/// [`SecureBuffer::execute_line`] on Enter and displays the [`CommandResult`].
+use crossterm::{
+    cursor::MoveTo,
+    event::{DisableBracketedPaste, Event, KeyCode, KeyEvent, KeyModifiers},
+    execute,
+    style::Print,
+    terminal::{Clear, ClearType, SetTitle},
+};
+use std::cell::Cell;
+use std::env;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus, Output, Stdio};
+use std::time::{Duration, SystemTime};
+use zeroize::Zeroize;
+
+use crate::age::{parse_identities, parse_recipient, AgeIdentity, Recipients, Unlock};
+use crate::audit::{audit_path, read_log, AuditEntry, AuditLog};
+#[cfg(unix)]
+use crate::builtins;
+use crate::calc::calculate;
+use crate::clipboard::{
+    cancel_clear_timers, clear_countdown, encrypt_payload, extend_clear_timers, list_clear_timers,
+    read_binary_file_for_copy, read_file_for_copy, write_private_file, ClipboardData,
+    ClipboardHistory, ClipboardMonitor, ClipboardPayload, ClipboardSlots, SecureClipboard,
+    TamperResponse, CLIPBOARD_HISTORY_SIZE,
+};
+use crate::commands::{did_you_mean, suggest, COMMANDS};
+use crate::config::{config_path, matches_pattern, scrub_environment, Config, EnvOverride};
+use crate::contacts::Contacts;
+use crate::dashboard::{watch, Section};
+use crate::decoy::Decoy;
+#[cfg(unix)]
+use crate::detach::Verifier;
+use crate::direct::parse as parse_direct;
+use crate::filecrypt::{
+    decrypt_file, decrypt_file_age, decrypt_file_gpg, detect_format, encrypt_file,
+    encrypt_file_age, encrypt_file_gpg, read_age_file, shred_file, write_age_file,
+    write_ghost_file, FileFormat,
+};
+use crate::genpass::{generate, GenpassOptions};
+use crate::hash::{digest_matches, digest_target, to_hex, HashAlgorithm};
+use crate::history::{
+    expand as expand_history, history_path, load_history, save_history, HistoryEntry,
+};
+use crate::honeypot::{capture_path, read_captures, Honeypot, DEFAULT_ATTEMPTS};
+use crate::keys::{KeyPurpose, KeySource, SessionKeys};
+use crate::keystrokes::{detect_keystroke_backend, type_text, TYPE_DELAY_SECS};
+use crate::linebuf::LineBuffer;
+use crate::meminfo::{self, Holding, LockedRanges};
+use crate::netaudit::{audit_own_sockets, own_sockets, ExpectNetwork, SocketWatcher};
+use crate::note::Note;
+use crate::picker::pick;
+use crate::plugin::{find_plugin, list_plugins, run_plugin, PluginHost};
+use crate::policy::{self, PolicyAction, PolicyRule};
+use crate::posture::Posture;
+use crate::profile::{mask_process, PanicSettings, PanicStyle, SecurityProfile};
+use crate::proxy::{check_command, parse_check_response, Proxy, TOR_BROWSER_SOCKS, TOR_SOCKS};
+use crate::qr::render_qr;
+use crate::readonly::{ghost_mutation, mutation, programs};
+use crate::regex::Regex;
+use crate::remote::ssh_session;
+use crate::sandbox::{detect_backend, isolated_command, sandboxed_command, SandboxProfile};
+use crate::script::{run_hook, run_script, ScriptHost};
+use crate::scrub::scrub_file;
+use crate::secret::{SecretBytes, SecretString};
+use crate::security::{
+    audit_memory_maps, initialize_security, is_debugger_present, json_string, locked_memory_kb,
+    tracer_pid, Grade, SecurityStatus,
+};
+use crate::sensitive::detect_secret;
+use crate::shamir::{combine, split};
+use crate::signing::{
+    generate_signing_key, hmac_target, hmac_verify, public_key, sign_target, signing_key,
+    verify_target,
+};
+use crate::sshagent::{SshAgent, SshKey};
+use crate::term::{self, Output as TermOutput};
+use crate::theme::{Role, Theme, ALERT_PREFIX};
+use crate::timestomp::{apply, format_datetime, parse_datetime, Timestamps};
+use crate::totp::{current_code, encode_entry};
+use crate::trace::{self, Change, TraceBackend};
+use crate::vault::Vault;
+use crate::wipefree::wipe_free;
+use crate::workspace::{EphemeralHome, Workspace};
+
+// --- CONSTANTS ---
+const GHOST_COMMAND_PREFIX: &str = "::";
+const DEFAULT_CLIPBOARD_TIMEOUT: u64 = 30; // seconds before copies are auto-cleared
+const AGE_IDENTITY_ENTRY: &str = "age-identity"; // vault entry written by ::age-keygen
+
+pub const RESET_SCROLL_REGION: &str = "\x1b[r"; // DECSTBM without margins: the whole screen scrolls
+const DEFAULT_HISTORY_SIZE: usize = 1000; // entries kept before the oldest are zeroized
+const DEFAULT_PROMPT: &str = "gsh {dir}>> "; // `{dir}` is the current directory name
+pub const DEFAULT_TITLE: &str = "journalctl"; // Window title while the shell runs (config `title`)
+const PUSH_TITLE: &str = "\x1b[22;0t"; // Save the window title on the xterm title stack
+const POP_TITLE: &str = "\x1b[23;0t"; // Restore it
+const MAX_TABS: usize = 9; // One per Alt+<digit>
+const WATCH_INTERVAL: Duration = Duration::from_secs(2); // ::watch-security refresh
+
+// --- ENUMS ---
+
+/// Result of command execution
+pub enum CommandResult {
+    /// No output, continue normally
+    NoOp,
+    /// Command produced output
+    Output(String),
+    /// Command failed; the message is shown styled as an error
+    Error(String),
+    /// Text to place on the input line for editing (not executed)
+    Insert(String),
+    /// Exit the shell
+    Exit,
+}
+
+// --- STRUCTURES ---
+
+/// Session-wide clipboard and security state. main() creates the one instance at
+/// startup and hands it to the SecureBuffer that processes commands, so every copy,
+/// paste and clear goes through a single clipboard backend.
+#[derive(Clone)]
+pub struct GhostShell {
+    security_status: SecurityStatus, // Latest full scan: startup, then ::security-status
+    clipboard: Result<SecureClipboard, String>, // Opened once; clones share the backend
+    clipboard_timeout: u64,          // Auto-clear default in seconds (0 = never)
+}
+
+impl GhostShell {
+    /// Run the startup security scan and open the clipboard
+    pub fn new() -> Self {
+        GhostShell {
+            security_status: initialize_security(),
+            clipboard: SecureClipboard::new(false),
+            clipboard_timeout: DEFAULT_CLIPBOARD_TIMEOUT,
+        }
+    }
+
+    /// The latest full security scan
+    pub fn security_status(&self) -> &SecurityStatus {
+        &self.security_status
+    }
+
+    /// Scan again, keeping the result for ::status
+    fn rescan(&mut self) -> &SecurityStatus {
+        self.security_status = initialize_security();
+        &self.security_status
+    }
+
+    /// A handle on the session clipboard (plain copies unless `encrypted` is applied).
+    /// A clipboard that was unavailable at startup is looked for again.
+    fn clipboard(&self) -> Result<SecureClipboard, String> {
+        match &self.clipboard {
+            Ok(clipboard) => Ok(clipboard.clone()),
+            Err(_) => SecureClipboard::new(false),
+        }
+    }
+}
+
+impl Default for GhostShell {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// SecureBuffer holds command input and history
+/// Note: We implement Drop manually to ensure history is zeroized
+pub struct SecureBuffer {
+    line: LineBuffer, // The line being typed
+    content: String,  // The line being run, taken from `line` on Enter
+    history: Vec<HistoryEntry>,
+    history_index: usize, // Points to index in history. history.len() = new line.
+    history_max: usize,   // Oldest entries are zeroized beyond this (config `history.max`)
+    history_dedupe: bool, // Drop earlier copies, not just a repeat of the last entry
+    history_ignore_space: bool, // Lines typed with a leading space are never stored
+    history_ignore: Vec<String>, // Config `history.ignore` patterns
+    history_file: Option<PathBuf>, // Encrypted copy of the history (::history persist on)
+    audit: Option<AuditLog>, // ::audit on: every line run is appended here
+    command_started: SystemTime, // When the line being executed was submitted
+    last_status: Cell<Option<i32>>, // Exit status of the last child process run
+    command_count: usize, // Track number of commands executed
+    paranoid_mode: bool,  // Auto-panic on threat detection
+    readonly: bool,       // ::readonly on: refuse commands that change the system
+    refuse_remote_plaintext: bool, // Config `ssh.refuse_plaintext`: no ::decrypt output over SSH
+    direct_exec: bool,    // ::direct: exec commands without $SHELL -c
+    env_overrides: Vec<(String, EnvOverride)>, // Config `env.<command>`, by program name
+    trace_allow: Vec<String>, // Config `trace.allow` patterns ::trace leaves out
+    profile: SecurityProfile, // Last applied ::profile bundle
+    panic: PanicSettings, // What ::panic does (profile, then config `panic.*`)
+    panic_shred: Vec<PathBuf>, // Config `panic.shred` files destroyed by ::panic
+    check_interval: usize, // Commands between periodic background checks
+    sandbox_profile: SandboxProfile, // Global sandbox wrapper for child commands
+    proxy: Option<Proxy>, // SOCKS5/HTTP proxy for child commands (::proxy)
+    clipboard_history: ClipboardHistory, // Encrypted ring of recently copied items
+    clipboard_slots: ClipboardSlots, // Named encrypted staging slots
+    clipboard_monitor: ClipboardMonitor, // Detects copied content replaced by other apps
+    clipboard_tampered: bool, // Status bar alert until the next command
+    shell: GhostShell,    // Clipboard and security state created by main()
+    vault: Vault,         // Named secrets encrypted under the vault subkey
+    contacts: Contacts,   // Public keys for ::cp --to
+    note: Note,           // ::note scratchpad, encrypted under the note subkey
+    ssh_agent: Option<SshAgent>, // In-memory agent started by the first ::ssh-add
+    workspace: Option<Workspace>, // tmpfs scratch directory from ::workspace create
+    ephemeral: Option<EphemeralHome>, // ::ephemeral on: children's HOME/XDG dirs on tmpfs
+    keys: SessionKeys,    // Session master key every feature key is derived from
+    prompt: String,       // Prompt template (config `prompt`)
+    title: String,        // Window title shown instead of the real one (config `title`)
+    bindings: Vec<(char, String)>, // Ctrl+<key> command bindings from the config file
+    config_status: String, // What the config file set, shown by ::config
+    pre_hooks: Vec<String>, // Config `hook.pre` snippets
+    post_hooks: Vec<String>, // Config `hook.post` snippets
+    policy: Vec<PolicyRule>, // Config `policy.deny` / `policy.confirm` rules
+    theme: Theme,         // Colors for the prompt, warnings, alerts and countdown
+    socket_watcher: SocketWatcher, // Paranoid-mode watch on the shell's own sockets
+    decoy: Decoy,         // ::decoy background activity
+    #[cfg(unix)]
+    detach: Option<Verifier>, // Reattach passphrase chosen by ::detach
+    detach_on_hangup: bool, // ::detach on-hangup: a hangup detaches instead of wiping
+    detach_requested: bool, // ::detach ran; the prompt loop detaches after its output
+    tab: usize,           // Number of this tab (Alt+1..9)
+    tabs: Vec<SecureBuffer>, // The session's other tabs, parked while this one is shown
+    tab_request: Option<usize>, // ::tab <n>|close ran; 0 closes. Applied after its output
+    cwd: Option<PathBuf>, // Working directory of a parked tab
+    last_output: String,  // Output of the last system command (::last), zeroized when replaced
+}
+
+/// Custom Drop implementation to securely zeroize all sensitive data
+impl Drop for SecureBuffer {
+    fn drop(&mut self) {
+        // Zeroize the current command buffer (the line being typed wipes itself)
+        self.content.zeroize();
+        self.last_output.zeroize();
+
+        // Zeroize each command in history
+        self.history.clear(); // Entries zeroize their command when dropped
+
+        // Reset counters (not sensitive, but good hygiene)
+        self.history_index = 0;
+        self.command_count = 0;
+        self.set_paranoid(false);
+        self.sandbox_profile = SandboxProfile::Off;
+        self.clipboard_history.purge();
+        self.clipboard_slots.purge();
+        self.vault.purge();
+        self.note.burn();
+        self.ssh_agent = None;
+        self.workspace = None;
+        self.ephemeral = None;
+    }
+}
+
+impl SecureBuffer {
+    /// Fresh shell state around the session's `shell`; every feature key is derived
+    /// from `keys`
+    pub fn new(keys: SessionKeys, shell: GhostShell) -> Self {
+        SecureBuffer {
+            line: LineBuffer::new(),
+            content: String::new(),
+            history: Vec::new(),
+            history_index: 0,
+            history_max: DEFAULT_HISTORY_SIZE,
+            history_dedupe: false,
+            history_ignore_space: true,
+            history_ignore: Vec::new(),
+            history_file: None,
+            audit: None,
+            command_started: SystemTime::now(),
+            last_status: Cell::new(None),
+            command_count: 0,
+            paranoid_mode: false, // Can be enabled with ::paranoid command
+            readonly: false,
+            refuse_remote_plaintext: false,
+            direct_exec: false,
+            env_overrides: Vec::new(),
+            trace_allow: Vec::new(),
+            profile: SecurityProfile::Standard,
+            panic: SecurityProfile::Standard.settings().panic,
+            panic_shred: Vec::new(),
+            check_interval: SecurityProfile::Standard.settings().check_interval,
+            sandbox_profile: SandboxProfile::Off,
+            proxy: None,
+            clipboard_history: ClipboardHistory::new(keys.derive(KeyPurpose::History)),
+            clipboard_slots: ClipboardSlots::new(keys.derive(KeyPurpose::Clipboard)),
+            clipboard_monitor: ClipboardMonitor::new(),
+            clipboard_tampered: false,
+            shell,
+            vault: Vault::new(keys.derive(KeyPurpose::Vault)),
+            contacts: Contacts::new(),
+            note: Note::new(keys.derive(KeyPurpose::Note)),
+            ssh_agent: None,
+            workspace: None,
+            ephemeral: None,
+            keys,
+            prompt: DEFAULT_PROMPT.to_string(),
+            title: DEFAULT_TITLE.to_string(),
+            bindings: Vec::new(),
+            config_status: "No config file loaded.".to_string(),
+            theme: Theme::initial(),
+            pre_hooks: Vec::new(),
+            post_hooks: Vec::new(),
+            policy: Vec::new(),
+            socket_watcher: SocketWatcher::new(),
+            decoy: Decoy::new(),
+            #[cfg(unix)]
+            detach: None,
+            detach_on_hangup: false,
+            detach_requested: false,
+            tab: 1,
+            tabs: Vec::new(),
+            tab_request: None,
+            cwd: None,
+            last_output: String::new(),
+        }
+    }
+
+    /// A new tab: fresh line, history, vault and note on the same session key and
+    /// clipboard, with the settings the config file and profile gave this one.
+    /// `::audit`, `::history persist` and `::ephemeral` stay with the tab that turned
+    /// them on.
+    fn new_tab(&self, number: usize) -> SecureBuffer {
+        let mut tab = SecureBuffer::new(self.keys.duplicate(), self.shell.clone());
+        tab.tab = number;
+        tab.history_max = self.history_max;
+        tab.history_dedupe = self.history_dedupe;
+        tab.history_ignore_space = self.history_ignore_space;
+        tab.history_ignore = self.history_ignore.clone();
+        tab.readonly = self.readonly;
+        tab.proxy = self.proxy.clone();
+        tab.refuse_remote_plaintext = self.refuse_remote_plaintext;
+        tab.direct_exec = self.direct_exec;
+        tab.env_overrides = self.env_overrides.clone();
+        tab.trace_allow = self.trace_allow.clone();
+        tab.profile = self.profile;
+        tab.panic = self.panic;
+        tab.panic_shred = self.panic_shred.clone();
+        tab.check_interval = self.check_interval;
+        tab.sandbox_profile = self.sandbox_profile;
+        tab.prompt = self.prompt.clone();
+        tab.title = self.title.clone();
+        tab.bindings = self.bindings.clone();
+        tab.config_status = self.config_status.clone();
+        tab.pre_hooks = self.pre_hooks.clone();
+        tab.post_hooks = self.post_hooks.clone();
+        tab.policy = self.policy.clone();
+        tab.theme = self.theme;
+        tab.set_paranoid(self.paranoid_mode);
+        tab
+    }
+
+    /// This tab's number and how many tabs are open
+    pub fn tab(&self) -> (usize, usize) {
+        (self.tab, self.tabs.len() + 1)
+    }
+
+    /// Show tab `number` (1-9), opening it if it isn't open yet. The current tab is
+    /// parked with its line, history and working directory.
+    pub fn switch_tab(&mut self, number: usize) -> Result<(), String> {
+        if !(1..=MAX_TABS).contains(&number) {
+            return Err(format!("Tabs are numbered 1 to {}.", MAX_TABS));
+        }
+        if number == self.tab {
+            return Ok(());
+        }
+        let mut tabs = std::mem::take(&mut self.tabs);
+        let target = match tabs.iter().position(|tab| tab.tab == number) {
+            Some(index) => tabs.remove(index),
+            None => self.new_tab(number),
+        };
+        let cwd = env::current_dir().ok();
+        let mut parked = std::mem::replace(self, target);
+        parked.cwd = cwd;
+        tabs.push(parked);
+        tabs.sort_by_key(|tab| tab.tab);
+        self.tabs = tabs;
+        self.enter_cwd()
+    }
+
+    /// Close this tab, zeroizing it, and show the lowest-numbered other one
+    pub fn close_tab(&mut self) -> Result<usize, String> {
+        if self.tabs.is_empty() {
+            return Err("This is the only tab; ::exit ends the session.".to_string());
+        }
+        let mut tabs = std::mem::take(&mut self.tabs);
+        let closed = std::mem::replace(self, tabs.remove(0));
+        drop(closed);
+        self.tabs = tabs;
+        self.enter_cwd()?;
+        Ok(self.tab)
+    }
+
+    /// Back in the working directory a tab was parked in
+    fn enter_cwd(&mut self) -> Result<(), String> {
+        match self.cwd.take() {
+            Some(dir) => env::set_current_dir(&dir)
+                .map_err(|e| format!("Tab {}: {}: {}", self.tab, dir.display(), e)),
+            None => Ok(()),
+        }
+    }
+
+    /// Apply a `::tab` switch or close once its output is shown; returns the tab line
+    /// to show then
+    pub fn take_tab_request(&mut self) -> Option<Result<String, String>> {
+        let result = match self.tab_request.take()? {
+            0 => self.close_tab().map(|_| ()),
+            number => self.switch_tab(number),
+        };
+        Some(result.map(|_| self.tab_banner()))
+    }
+
+    /// `── tab 2/3 · /home/user/src ──`, shown after switching
+    pub fn tab_banner(&self) -> String {
+        let (number, count) = self.tab();
+        format!(
+            "── tab {}/{} · {} ──",
+            number,
+            count,
+            env::current_dir()
+                .map(|dir| dir.display().to_string())
+                .unwrap_or_default()
+        )
+    }
+
+    /// `::tab [<n>|new|close]`
+    fn tab_command(&mut self, args: &str) -> CommandResult {
+        let open: Vec<usize> = {
+            let mut open: Vec<usize> = self.tabs.iter().map(|tab| tab.tab).collect();
+            open.push(self.tab);
+            open.sort_unstable();
+            open
+        };
+        match args {
+            "" => {
+                let mut lines = Vec::new();
+                for number in &open {
+                    let (marker, cwd, commands) = if *number == self.tab {
+                        ("*", env::current_dir().ok(), self.history.len())
+                    } else {
+                        let Some(tab) = self.tabs.iter().find(|tab| tab.tab == *number) else {
+                            continue;
+                        };
+                        (" ", tab.cwd.clone(), tab.history.len())
+                    };
+                    lines.push(format!(
+                        "{} {}  {:<40} {} commands",
+                        marker,
+                        number,
+                        cwd.map(|dir| dir.display().to_string()).unwrap_or_default(),
+                        commands
+                    ));
+                }
+                lines.push("Alt+1..9 or ::tab <n> switches (opening new tabs), ::tab close closes this one".to_string());
+                CommandResult::Output(lines.join("\r\n"))
+            }
+            "new" => match (1..=MAX_TABS).find(|number| !open.contains(number)) {
+                Some(number) => {
+                    self.tab_request = Some(number);
+                    CommandResult::NoOp
+                }
+                None => CommandResult::Error(format!("All {} tabs are open.", MAX_TABS)),
+            },
+            "close" if self.tabs.is_empty() => {
+                CommandResult::Error("This is the only tab; ::exit ends the session.".to_string())
+            }
+            "close" => {
+                self.tab_request = Some(0);
+                CommandResult::Output(format!("TAB {} CLOSED AND ZEROIZED.", self.tab))
+            }
+            number => match number.parse::<usize>() {
+                Ok(number) if (1..=MAX_TABS).contains(&number) => {
+                    self.tab_request = Some(number);
+                    CommandResult::NoOp
+                }
+                _ => CommandResult::Output("Usage: ::tab [<1-9>|new|close]".to_string()),
+            },
+        }
+    }
+
+    // --- MANIPULATION ---
+
+    /// Insert `c` at the cursor
+    pub fn insert(&mut self, c: char) {
+        self.line.insert(c);
+    }
+
+    /// Delete the character before the cursor
+    pub fn backspace(&mut self) {
+        self.line.backspace();
+    }
+
+    pub fn move_left(&mut self) {
+        self.line.move_left();
+    }
+
+    pub fn move_right(&mut self) {
+        self.line.move_right();
+    }
+
+    // --- HISTORY ---
+
+    /// Recall the previous history entry
+    pub fn history_up(&mut self) {
+        if self.history_index > 0 {
+            self.history_index -= 1;
+            if let Some(entry) = self.history.get(self.history_index) {
+                entry.command.expose(|command| self.line.set(command));
+            }
+        }
+    }
+
+    /// Recall the next history entry, or return to an empty line
+    pub fn history_down(&mut self) {
+        if self.history_index < self.history.len() {
+            self.history_index += 1;
+            if self.history_index == self.history.len() {
+                self.line.clear();
+            } else if let Some(entry) = self.history.get(self.history_index) {
+                entry.command.expose(|command| self.line.set(command));
+            }
+        }
+    }
+
+    /// Append the current line to the RAM-only history, with when it was run and how
+    /// it ended
+    pub fn commit_history(&mut self) {
+        let entry = HistoryEntry::new(
+            self.content.clone(),
+            Some(self.command_started),
+            self.last_status.get(),
+        );
+        if self.remember(entry) {
+            let _ = self.save_history_file();
+        }
+        self.history_index = self.history.len();
+    }
+
+    /// Store `entry` in the history unless an ignore rule matches, dropping duplicates
+    /// and the oldest entries beyond `history_max`. Returns whether it was stored.
+    /// Entries zeroize their command when dropped.
+    fn remember(&mut self, entry: HistoryEntry) -> bool {
+        let ignored = entry.command.expose(|line| {
+            line.trim().is_empty()
+                || self.history_max == 0
+                || (self.history_ignore_space && line.starts_with(' '))
+                || self
+                    .history_ignore
+                    .iter()
+                    .any(|pattern| matches_pattern(pattern, line.trim()))
+        });
+        if ignored {
+            return false;
+        }
+        if self.history_dedupe {
+            // A repeated command moves to the end
+            self.history
+                .retain(|earlier| earlier.command != entry.command);
+        } else if let Some(last) = self.history.last_mut() {
+            if last.command == entry.command {
+                // Same command again: keep one entry with the latest run
+                last.time = entry.time;
+                last.status = entry.status;
+                return false;
+            }
+        }
+        self.history.push(entry);
+        let excess = self.history.len().saturating_sub(self.history_max);
+        self.history.drain(..excess);
+        true
+    }
+
+    // --- AUTOCOMPLETE ---
+    /// Complete the `::` command under the cursor
+    pub fn autocomplete(&mut self) {
+        let text = self.line.text();
+        // A lone `::name` completes from the command registry
+        if let Some(prefix) = text.expose().strip_prefix(GHOST_COMMAND_PREFIX) {
+            if !prefix.contains(' ') && self.line.cursor() == self.line.len() {
+                let matches = crate::commands::completions(prefix);
+                let common = match matches.split_first() {
+                    Some((first, rest)) => rest.iter().fold(first.len(), |length, name| {
+                        first
+                            .bytes()
+                            .zip(name.bytes())
+                            .take(length)
+                            .take_while(|(a, b)| a == b)
+                            .count()
+                    }),
+                    None => return,
+                };
+                let mut completion = matches[0][prefix.len()..common].to_string();
+                if matches.len() == 1 {
+                    completion.push(' ');
+                }
+                for c in completion.chars() {
+                    self.insert(c);
+                }
+                return;
+            }
+        }
+
+        // Otherwise complete files in current dir based on last word
+        let parts: Vec<&str> = text.expose().split_whitespace().collect();
+        if let Some(last_word) = parts.last() {
+            let path_to_check = if last_word.contains('/') {
+                Path::new(last_word).parent().unwrap_or(Path::new("."))
+            } else {
+                Path::new(".")
+            };
+
+            let prefix = Path::new(last_word)
+                .file_name()
+                .and_then(|s| s.to_str())
+                .unwrap_or("");
+
+            if let Ok(entries) = fs::read_dir(path_to_check) {
+                let matches: Vec<String> = entries
+                    .filter_map(|e| e.ok())
+                    .map(|e| e.file_name().to_string_lossy().to_string())
+                    .filter(|name| name.starts_with(prefix))
+                    .collect();
+
+                if matches.len() == 1 {
+                    let completion = &matches[0][prefix.len()..];
+                    for c in completion.chars() {
+                        self.insert(c);
+                    }
+                } else if matches.len() > 1 {
+                    // TODO: Show possibilities? For now, just cycle or do nothing.
+                }
+            }
+        }
+    }
+
+    /// Turn paranoid mode on or off, along with the watcher on the shell's own sockets
+    fn set_paranoid(&mut self, enabled: bool) {
+        self.paranoid_mode = enabled;
+        if !enabled {
+            self.socket_watcher.stop();
+            return;
+        }
+        let theme = self.theme;
+        self.socket_watcher.start(move |threats| {
+            let alerts: Vec<String> = threats
+                .iter()
+                .map(|threat| format!("NETWORK ALERT: {}", threat))
+                .collect();
+            emergency_shutdown(theme, &alerts);
+        });
+    }
+
+    /// Switch every setting bundled in `profile`, returning a summary
+    pub fn apply_profile(&mut self, profile: SecurityProfile) -> String {
+        let settings = profile.settings();
+        self.profile = profile;
+        self.set_paranoid(settings.paranoid);
+        self.check_interval = settings.check_interval;
+        self.shell.clipboard_timeout = settings.clipboard_timeout;
+        self.clipboard_monitor
+            .set_response(settings.tamper_response);
+        self.sandbox_profile = settings.sandbox;
+        self.panic = settings.panic;
+        mask_process(&settings);
+        let removed = scrub_environment(&settings.env_scrub);
+
+        format!(
+            "SECURITY PROFILE: {}\r\n\
+            - Paranoid mode: {} (checks every {} commands)\r\n\
+            - Process name: {}\r\n\
+            - Clipboard: auto-clear {}s, tamper guard {}\r\n\
+            - Sandbox: {}\r\n\
+            - Panic: {}\r\n\
+            - Environment scrubbed: {}",
+            profile.name().to_uppercase(),
+            if settings.paranoid { "on" } else { "off" },
+            settings.check_interval,
+            settings.process_name,
+            settings.clipboard_timeout,
+            settings.tamper_response.name(),
+            settings.sandbox.name(),
+            settings.panic.describe(),
+            if removed.is_empty() {
+                "none".to_string()
+            } else {
+                removed.join(", ")
+            }
+        )
+    }
+
+    /// Apply settings from the config file, scrubbing listed variables from the environment
+    pub fn apply_config(&mut self, path: &Path, config: Config) {
+        let mut lines = vec![format!("Config: {}", path.display())];
+        if let Some(timeout) = config.clipboard_timeout {
+            self.shell.clipboard_timeout = timeout;
+            lines.push(format!("  clipboard timeout  {}s", timeout));
+        }
+        if let Some(paranoid) = config.paranoid {
+            self.set_paranoid(paranoid);
+            lines.push(format!(
+                "  paranoid mode      {}",
+                if paranoid { "on" } else { "off" }
+            ));
+        }
+        let panic = self.panic;
+        self.panic = PanicSettings {
+            style: config.panic_style.unwrap_or(panic.style),
+            exit_code: config.panic_exit_code.unwrap_or(panic.exit_code),
+            clear_clipboard: config
+                .panic_clear_clipboard
+                .unwrap_or(panic.clear_clipboard),
+            purge_vault: config.panic_purge_vault.unwrap_or(panic.purge_vault),
+            kill_children: config.panic_kill_children.unwrap_or(panic.kill_children),
+        };
+        if self.panic != panic {
+            lines.push(format!("  panic              {}", self.panic.describe()));
+        }
+        for path in &config.panic_shred {
+            lines.push(format!("  panic shred        {}", path.display()));
+        }
+        self.panic_shred = config.panic_shred;
+        if config.audit == Some(true) {
+            lines.push(match self.start_audit() {
+                Ok(path) => format!("  audit log          {}", path.display()),
+                Err(e) => format!("  audit log          not started: {}", e),
+            });
+        }
+        if let Some(direct) = config.exec_direct {
+            self.direct_exec = direct;
+            lines.push(format!(
+                "  direct exec        {}",
+                if direct { "on" } else { "off" }
+            ));
+        }
+        for (program, entry) in &config.env_overrides {
+            lines.push(format!("  env {:<14} {}", program, entry.describe()));
+        }
+        self.env_overrides = config.env_overrides;
+        for pattern in &config.trace_allow {
+            lines.push(format!("  trace allow        {}", pattern));
+        }
+        self.trace_allow = config.trace_allow;
+        if let Some(refuse) = config.ssh_refuse_plaintext {
+            self.refuse_remote_plaintext = refuse;
+            lines.push(format!(
+                "  ssh plaintext      {}",
+                if refuse { "refused" } else { "allowed" }
+            ));
+        }
+        if let Some(prompt) = config.prompt {
+            lines.push(format!("  prompt             {}", prompt));
+            self.prompt = prompt;
+        }
+        if let Some(title) = config.title {
+            lines.push(format!("  title              {}", title));
+            let _ = execute!(term::stdout(), SetTitle(&title));
+            self.title = title;
+        }
+        for (key, command) in &config.bindings {
+            lines.push(format!(
+                "  Ctrl+{}             {}",
+                key.to_ascii_uppercase(),
+                command
+            ));
+        }
+        self.bindings = config.bindings;
+        for (label, hooks) in [
+            ("hook.pre", &config.pre_hooks),
+            ("hook.post", &config.post_hooks),
+        ] {
+            for hook in hooks {
+                lines.push(format!("  {:<18} {}", label, hook));
+            }
+        }
+        for rule in &config.policy {
+            lines.push(format!(
+                "  policy {:<11} {}",
+                rule.action.name(),
+                rule.pattern
+            ));
+        }
+        self.policy = config.policy;
+        if let Some(max) = config.history_max {
+            self.history_max = max;
+            lines.push(format!("  history max        {}", max));
+        }
+        if let Some(dedupe) = config.history_dedupe {
+            self.history_dedupe = dedupe;
+            lines.push(format!(
+                "  history dedupe     {}",
+                if dedupe { "on" } else { "off" }
+            ));
+        }
+        if let Some(ignore_space) = config.history_ignore_space {
+            self.history_ignore_space = ignore_space;
+            lines.push(format!(
+                "  history ignore     leading space {}",
+                if ignore_space { "on" } else { "off" }
+            ));
+        }
+        for pattern in &config.history_ignore {
+            lines.push(format!("  history ignore     {}", pattern));
+        }
+        self.history_ignore = config.history_ignore;
+        if let Some(theme) = config.theme {
+            self.theme = theme;
+            lines.push(format!("  theme              {}", theme.name()));
+        }
+        self.pre_hooks = config.pre_hooks;
+        self.post_hooks = config.post_hooks;
+        if !config.env_scrub.is_empty() {
+            let removed = scrub_environment(&config.env_scrub);
+            lines.push(format!(
+                "  env scrubbed       {}",
+                if removed.is_empty() {
+                    "(no matching variables)".to_string()
+                } else {
+                    removed.join(", ")
+                }
+            ));
+        }
+        self.config_status = lines.join("\r\n");
+    }
+
+    /// Command bound to Ctrl+`key` in the config file
+    pub fn binding(&self, key: char) -> Option<&str> {
+        self.bindings
+            .iter()
+            .find(|(bound, _)| *bound == key)
+            .map(|(_, command)| command.as_str())
+    }
+
+    /// Ctrl+T: choose a history line (newest first) or a ghost command in the fuzzy
+    /// finder, starting from what is typed, and put it on the input line to edit
+    pub fn fuzzy_pick(&mut self) -> io::Result<()> {
+        let mut candidates: Vec<String> = Vec::new();
+        for entry in self.history.iter().rev() {
+            let mut command = entry.command.to_plain();
+            if candidates.contains(&command) {
+                command.zeroize();
+            } else {
+                candidates.push(command);
+            }
+        }
+        candidates.extend(COMMANDS.iter().map(|command| format!("::{}", command.name)));
+        if let Some(mut choice) = pick(self.theme, candidates, self.line.text().expose())? {
+            self.line.set(&choice);
+            choice.zeroize();
+            self.history_index = self.history.len();
+        }
+        Ok(())
+    }
+
+    /// Reset the input line and the history position
+    pub fn clear_state(&mut self) {
+        self.line.clear();
+        self.content.zeroize();
+        self.history_index = self.history.len();
+    }
+
+    /// Drop the line being typed (Ctrl+C)
+    pub fn discard_line(&mut self) {
+        self.line.clear();
+    }
+
+    /// The line being typed
+    pub fn line(&self) -> &LineBuffer {
+        &self.line
+    }
+
+    /// The line being typed, for the redraw bookkeeping it carries
+    pub fn line_mut(&mut self) -> &mut LineBuffer {
+        &mut self.line
+    }
+
+    /// Prompt template; `{dir}` stands for the current directory name
+    pub fn prompt(&self) -> &str {
+        &self.prompt
+    }
+
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    /// Inline warning while typing a line that seems to pass a secret as an argument.
+    /// Ghost commands are exempt: their arguments never reach another process.
+    pub fn secret_warning(&self) -> Option<String> {
+        let text = self.line.text();
+        if text.expose().starts_with(GHOST_COMMAND_PREFIX) {
+            return None;
+        }
+        detect_secret(text.expose()).map(|what| {
+            format!(
+                "⚠ {} in the arguments (visible to ps): use ::vault or stdin",
+                what
+            )
+        })
+    }
+
+    /// `[A] ` to `[F] ` for the prompt: the posture grade of the latest security scan,
+    /// colored by how good it is. Returns the painted badge and its width.
+    pub fn grade_badge(&self) -> (String, usize) {
+        let grade = self.shell.security_status().grade();
+        let role = match grade {
+            Grade::A | Grade::B => Role::Success,
+            Grade::C | Grade::D => Role::Warning,
+            Grade::F => Role::Alert,
+        };
+        let badge = format!("[{}]", grade.letter());
+        let width = badge.chars().count() + 1;
+        (format!("{} ", self.theme.paint(role, &badge)), width)
+    }
+
+    /// Securely purge command history from memory
+    fn purge_history(&mut self) {
+        // Entries zeroize their command when dropped
+        self.history.clear();
+        self.history_index = 0;
+        let _ = self.save_history_file();
+    }
+
+    /// Rewrite the encrypted history file, if persistence is on
+    fn save_history_file(&self) -> Result<(), String> {
+        match &self.history_file {
+            Some(path) => save_history(
+                path,
+                &self.keys.derive(KeyPurpose::HistoryFile),
+                &self.history,
+            ),
+            None => Ok(()),
+        }
+    }
+
+    /// Load the history file an earlier session left behind, returning what happened.
+    /// Only a passphrase-derived session key can open it.
+    pub fn restore_history(&mut self) -> Option<String> {
+        let path = history_path().filter(|path| path.is_file())?;
+        if self.keys.source() != KeySource::Passphrase {
+            return Some(format!(
+                "Encrypted history at {} not restored: start with --master-passphrase to load it.",
+                path.display()
+            ));
+        }
+        match load_history(&path, &self.keys.derive(KeyPurpose::HistoryFile)) {
+            Ok(entries) => {
+                let count = entries.len();
+                for entry in entries {
+                    self.remember(entry);
+                }
+                self.history_index = self.history.len();
+                let message = format!(
+                    "HISTORY RESTORED: {} COMMANDS FROM {}.",
+                    count,
+                    path.display()
+                );
+                self.history_file = Some(path);
+                Some(message)
+            }
+            Err(e) => Some(format!("{} Persistent history stays off.", e)),
+        }
+    }
+
+    /// ::panic: wipe the screen, run the cleanup the profile and config ask for, zeroize
+    /// the history and the other in-memory secrets, and exit
+    fn panic(&mut self) -> ! {
+        let settings = self.panic;
+        let mut stdout = term::stdout();
+        let _ = execute!(stdout, DisableBracketedPaste);
+        let _ = sanitize_screen(&mut stdout);
+        let _ = restore_title(&mut stdout);
+        if settings.style == PanicStyle::Kernel {
+            let _ = write!(
+                stdout,
+                "KERNEL PANIC - MEMORY CORRUPTION DETECTED at 0xDEADBEEF\r\nDumping core to /dev/null...\r\n"
+            );
+        }
+        let _ = stdout.flush();
+        let started = std::time::Instant::now();
+
+        if settings.kill_children {
+            kill_children();
+        }
+        for path in &self.panic_shred {
+            let _ = shred_file(path);
+        }
+        self.secure_wipe(settings.clear_clipboard, settings.purge_vault);
+
+        if settings.style == PanicStyle::Kernel {
+            // The fake core dump takes a moment, as a real one would
+            let pause = std::time::Duration::from_millis(1500);
+            std::thread::sleep(pause.saturating_sub(started.elapsed()));
+        }
+        term::exit(settings.exit_code);
+    }
+
+    /// The terminal closed or the parent process died (see `watchdog`): nobody is
+    /// left to see output, so wipe everything, clear the clipboard and exit with the
+    /// status of a hangup
+    pub fn disconnect(&mut self) -> ! {
+        self.secure_wipe(true, true);
+        term::exit(129); // 128 + SIGHUP, as shells report a hangup
+    }
+
+    /// The reattach check once the session should detach: after `::detach`, or on a
+    /// hangup when `::detach on-hangup` armed it
+    #[cfg(unix)]
+    pub fn take_detach(&mut self, hangup: bool) -> Option<Verifier> {
+        let requested = if hangup {
+            self.detach_on_hangup
+        } else {
+            std::mem::take(&mut self.detach_requested)
+        };
+        if requested {
+            self.detach_on_hangup = false;
+            self.detach.take()
+        } else {
+            None
+        }
+    }
+
+    /// What an abrupt exit must do by hand, since exit() skips destructors
+    fn secure_wipe(&mut self, clear_clipboard: bool, purge_vault: bool) {
+        if clear_clipboard {
+            let _ = self
+                .shell
+                .clipboard()
+                .and_then(|clipboard| clipboard.clear());
+        }
+        self.wipe_tab(clear_clipboard, purge_vault);
+        for tab in &mut self.tabs {
+            tab.wipe_tab(clear_clipboard, purge_vault);
+        }
+    }
+
+    /// One tab's share of `secure_wipe`; the system clipboard is shared, so it is
+    /// cleared once by the caller, but each tab has its own encrypted ring and slots
+    fn wipe_tab(&mut self, purge_clipboards: bool, purge_vault: bool) {
+        if purge_clipboards {
+            self.clipboard_history.purge();
+            self.clipboard_slots.purge();
+        }
+        if purge_vault {
+            self.vault.purge();
+        }
+        self.history.clear(); // Entries zeroize their command when dropped
+        self.line.clear();
+        self.content.zeroize();
+        self.last_output.zeroize();
+        self.note.burn();
+        self.ssh_agent = None;
+        self.workspace = None; // Shreds the tmpfs scratch directory
+        self.ephemeral = None;
+    }
+
+    /// Start appending to the audit log, continuing (and first verifying) an existing
+    /// one. Needs a passphrase-derived session key so later sessions can read it.
+    fn start_audit(&mut self) -> Result<PathBuf, String> {
+        if self.keys.source() != KeySource::Passphrase {
+            return Err(
+                "the audit log needs a passphrase-derived session key: restart with --master-passphrase."
+                    .to_string(),
+            );
+        }
+        let path = audit_path().ok_or("No home directory for the audit log.")?;
+        let log = AuditLog::open(path.clone(), self.keys.derive(KeyPurpose::Logs))?;
+        self.audit = Some(log);
+        Ok(path)
+    }
+
+    /// `::audit [on|off|verify|export [--age <recipients>] <file>]`
+    fn audit_command(&mut self, args: &str) -> CommandResult {
+        match args {
+            "" => CommandResult::Output(match &self.audit {
+                Some(log) => format!(
+                    "Audit log: on ({})\r\nRecords:   {}\r\nHead:      {}",
+                    log.path().display(),
+                    log.records(),
+                    log.head()
+                ),
+                None => "Audit log: off (::audit on to start)".to_string(),
+            }),
+            "on" => match self.start_audit() {
+                Ok(path) => CommandResult::Output(format!(
+                    "AUDIT LOG ON: EVERY COMMAND IS APPENDED TO {}\r\n\
+                    Records are encrypted and hash-chained; read them back with the same passphrase.",
+                    path.display()
+                )),
+                Err(e) => CommandResult::Error(e),
+            },
+            "off" => match self.audit.take() {
+                Some(mut log) => {
+                    // The log records that it was stopped
+                    let _ = log.append(self.command_started, Some(0), self.content.trim());
+                    CommandResult::Output(format!(
+                        "AUDIT LOG OFF. {} kept with {} records.",
+                        log.path().display(),
+                        log.records()
+                    ))
+                }
+                None => CommandResult::Output("Audit log is already off.".to_string()),
+            },
+            "verify" => match self.read_audit() {
+                Ok((path, entries)) => CommandResult::Output(format!(
+                    "✓ AUDIT LOG INTACT: {} records in {}\r\nHead: {}",
+                    entries.len(),
+                    path.display(),
+                    entries
+                        .last()
+                        .map_or_else(|| "(empty)".to_string(), |entry| to_hex(&entry.chain))
+                )),
+                Err(e) => CommandResult::Error(format!("✗ {}", e)),
+            },
+            _ => match strip_flag(args, "export") {
+                Some(rest) => self.audit_export(rest),
+                None => CommandResult::Output(
+                    "Usage: ::audit [on|off|verify] | ::audit export [--age <age1...[,...]>] <file>"
+                        .to_string(),
+                ),
+            },
+        }
+    }
+
+    /// Every record of the audit log, checked against the chain
+    fn read_audit(&self) -> Result<(PathBuf, Vec<AuditEntry>), String> {
+        if self.keys.source() != KeySource::Passphrase {
+            return Err(
+                "Reading the audit log needs the session started with --master-passphrase."
+                    .to_string(),
+            );
+        }
+        let path = self
+            .audit
+            .as_ref()
+            .map(|log| log.path().to_path_buf())
+            .or_else(audit_path)
+            .filter(|path| path.is_file())
+            .ok_or("No audit log yet.")?;
+        let entries = read_log(&path, &self.keys.derive(KeyPurpose::Logs))?;
+        Ok((path, entries))
+    }
+
+    /// `::audit export [--age <recipients>] <file>`: the verified records as an age
+    /// archive, one tab-separated line each, passphrase-protected unless recipients
+    /// are given
+    fn audit_export(&self, args: &str) -> CommandResult {
+        let (recipients, path) = match strip_flag(args, "--age") {
+            Some(rest) => match rest.split_once(' ') {
+                Some((spec, path)) => (Some(spec), path.trim_start()),
+                None => (None, ""),
+            },
+            None => (None, args),
+        };
+        if path.is_empty() {
+            return CommandResult::Output(
+                "Usage: ::audit export [--age <age1...[,...]>] <file>".to_string(),
+            );
+        }
+        let entries = match self.read_audit() {
+            Ok((_, entries)) => entries,
+            Err(e) => return CommandResult::Error(e),
+        };
+
+        let mut archive = String::from("# seq\ttime (UTC)\tstatus\tchain\tcommand\n");
+        for entry in &entries {
+            archive.push_str(&format!(
+                "{}\t{}\t{}\t{}\t{}\n",
+                entry.seq,
+                format_datetime(entry.time),
+                entry
+                    .status
+                    .map_or_else(|| "-".to_string(), |status| status.to_string()),
+                to_hex(&entry.chain),
+                entry.command
+            ));
+        }
+        let result = match recipients {
+            Some(spec) => parse_age_recipients(spec).and_then(|recipients| {
+                write_age_file(Path::new(path), &recipients, archive.as_bytes())
+            }),
+            None => prompt_new_passphrase().and_then(|passphrase| {
+                write_age_file(
+                    Path::new(path),
+                    &Recipients::Passphrase(passphrase.expose()),
+                    archive.as_bytes(),
+                )
+            }),
+        };
+        archive.zeroize();
+        match result {
+            Ok(()) => CommandResult::Output(format!(
+                "AUDIT LOG EXPORTED: {} VERIFIED RECORDS ENCRYPTED TO {}",
+                entries.len(),
+                path
+            )),
+            Err(e) => CommandResult::Error(e),
+        }
+    }
+
+    /// `::detach [on-hangup|off|status]`
+    #[cfg(unix)]
+    fn detach_command(&mut self, args: &str) -> CommandResult {
+        let pid = std::process::id();
+        let reattach = format!(
+            "Reattach from any terminal with: ghost-shell --attach {}",
+            pid
+        );
+        match args {
+            "" if term::is_relayed() => {
+                let _ = write!(
+                    term::stdout(),
+                    "CLIENT DETACHED: session {} keeps running.\r\n",
+                    pid
+                );
+                term::drop_client();
+                CommandResult::NoOp
+            }
+            "" | "on-hangup" if term::is_headless() => {
+                CommandResult::Error("::detach needs a terminal.".to_string())
+            }
+            "" | "on-hangup" => {
+                if self.detach.is_none() {
+                    let verifier = prompt_new_passphrase()
+                        .and_then(|passphrase| Verifier::new(passphrase.expose()));
+                    match verifier {
+                        Ok(verifier) => self.detach = Some(verifier),
+                        Err(e) => return CommandResult::Error(e),
+                    }
+                }
+                if args.is_empty() {
+                    self.detach_requested = true;
+                    CommandResult::Output(format!(
+                        "DETACHING SESSION {}: it keeps running, with its memory, without this terminal.\r\n\
+                        This terminal can be closed. {}",
+                        pid, reattach
+                    ))
+                } else {
+                    self.detach_on_hangup = true;
+                    CommandResult::Output(format!(
+                        "DETACH ON HANGUP ARMED: a closed terminal or dropped connection detaches the session instead of wiping it.\r\n{}",
+                        reattach
+                    ))
+                }
+            }
+            "off" => {
+                self.detach_on_hangup = false;
+                self.detach = None;
+                CommandResult::Output(
+                    "DETACH ON HANGUP DISARMED: a hangup wipes the session again.".to_string(),
+                )
+            }
+            "status" => CommandResult::Output(format!(
+                "Session:           {} (PID {})\r\nOn hangup:         {}\r\nDetached sessions: {}",
+                if term::is_relayed() {
+                    "detached"
+                } else {
+                    "attached to its terminal"
+                },
+                pid,
+                if self.detach_on_hangup {
+                    "detach"
+                } else {
+                    "wipe and exit"
+                },
+                match crate::detach::sessions().as_slice() {
+                    [] => "none".to_string(),
+                    pids => pids
+                        .iter()
+                        .map(u32::to_string)
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                }
+            )),
+            _ => CommandResult::Output("Usage: ::detach [on-hangup|off|status]".to_string()),
+        }
+    }
+
+    /// `::honeypot [arm [attempts]|disarm|log|clear]`
+    fn honeypot_command(&self, args: &str) -> CommandResult {
+        let needs_passphrase = || {
+            CommandResult::Error(
+                "The honeypot needs a passphrase-derived session key: restart with --master-passphrase."
+                    .to_string(),
+            )
+        };
+        let (action, rest) = args.split_once(' ').unwrap_or((args, ""));
+        match action {
+            "" => {
+                let state = match Honeypot::load() {
+                    Ok(Some(honeypot)) => format!(
+                        "armed, {} master passphrase attempts at startup",
+                        honeypot.attempts()
+                    ),
+                    Ok(None) => "off (::honeypot arm [attempts] to arm)".to_string(),
+                    Err(e) => format!("✗ {}", e),
+                };
+                let captures = capture_path()
+                    .filter(|path| path.is_file())
+                    .and_then(|path| fs::read_to_string(path).ok())
+                    .map_or(0, |text| text.lines().count());
+                CommandResult::Output(format!(
+                    "Honeypot: {}\r\nCaptured events: {}\r\nUsage: ::honeypot [arm [attempts]|disarm|log|clear]",
+                    state, captures
+                ))
+            }
+            "arm" => {
+                if self.keys.source() != KeySource::Passphrase {
+                    return needs_passphrase();
+                }
+                let attempts = match rest.trim() {
+                    "" => DEFAULT_ATTEMPTS,
+                    n => match n.parse::<u32>() {
+                        Ok(n) if n > 0 => n,
+                        _ => return CommandResult::Error(format!("Invalid attempt count: {}", n)),
+                    },
+                };
+                match Honeypot::arm(&self.keys, attempts) {
+                    Ok(path) => CommandResult::Output(format!(
+                        "HONEYPOT ARMED: {} WRONG MASTER PASSPHRASES AT STARTUP OPEN A FAKE SHELL\r\n\
+                        - Every launch now asks for the master passphrase, with or without --master-passphrase\r\n\
+                        - Commands typed in the fake shell are sealed to this passphrase in {}\r\n\
+                        - ::honeypot log reads them; ::honeypot disarm removes {}",
+                        attempts,
+                        capture_path().unwrap_or_default().display(),
+                        path.display()
+                    )),
+                    Err(e) => CommandResult::Error(e),
+                }
+            }
+            "disarm" => match Honeypot::disarm() {
+                Ok(true) => CommandResult::Output(
+                    "HONEYPOT DISARMED. Captures are kept; ::honeypot clear removes them."
+                        .to_string(),
+                ),
+                Ok(false) => CommandResult::Output("The honeypot is not armed.".to_string()),
+                Err(e) => CommandResult::Error(e),
+            },
+            "log" => {
+                if self.keys.source() != KeySource::Passphrase {
+                    return needs_passphrase();
+                }
+                match read_captures(&self.keys) {
+                    Ok(events) => CommandResult::Output(
+                        events
+                            .iter()
+                            .map(|(time, event)| format!("{}  {}", format_datetime(*time), event))
+                            .collect::<Vec<_>>()
+                            .join("\r\n"),
+                    ),
+                    Err(e) => CommandResult::Error(e),
+                }
+            }
+            "clear" => match capture_path().filter(|path| path.is_file()) {
+                Some(path) => match fs::remove_file(&path) {
+                    Ok(()) => CommandResult::Output("HONEYPOT CAPTURES DELETED.".to_string()),
+                    Err(e) => CommandResult::Error(format!("{}: {}", path.display(), e)),
+                },
+                None => CommandResult::Output("No honeypot captures.".to_string()),
+            },
+            _ => CommandResult::Output(
+                "Usage: ::honeypot [arm [attempts]|disarm|log|clear]".to_string(),
+            ),
+        }
+    }
+
+    /// `::last [grep [-i] <regex>]`: the last command's output again, or its matching lines
+    fn last_command(&self, args: &str) -> CommandResult {
+        if self.last_output.is_empty() {
+            return CommandResult::Output("No command output kept yet.".to_string());
+        }
+        let Some(args) = strip_flag(args, "grep") else {
+            return match args {
+                "" => CommandResult::Output(self.last_output.clone()),
+                _ => CommandResult::Output("Usage: ::last [grep [-i] <regex>]".to_string()),
+            };
+        };
+        let (pattern, regex) = match strip_flag(args, "-i") {
+            Some(pattern) => (pattern, Regex::case_insensitive(pattern)),
+            None => (args, Regex::new(args)),
+        };
+        if pattern.is_empty() {
+            return CommandResult::Output("Usage: ::last grep [-i] <regex>".to_string());
+        }
+        let regex = match regex {
+            Ok(regex) => regex,
+            Err(e) => return CommandResult::Error(e),
+        };
+        let matches: Vec<&str> = self
+            .last_output
+            .split("\r\n")
+            .filter(|line| regex.is_match(line))
+            .collect();
+        if matches.is_empty() {
+            CommandResult::Output(format!("No lines of the last output match '{}'.", pattern))
+        } else {
+            CommandResult::Output(matches.join("\r\n"))
+        }
+    }
+
+    /// `::cp-last`: the last command's output to the encrypted, auto-clearing clipboard
+    fn copy_last(&mut self) -> CommandResult {
+        if self.last_output.is_empty() {
+            return CommandResult::Output("No command output kept yet.".to_string());
+        }
+        // Like `$(...)`, without the trailing line break
+        let mut text = self
+            .last_output
+            .trim_end_matches(['\r', '\n'])
+            .replace("\r\n", "\n");
+        let copied = self
+            .clipboard_history
+            .push(&text)
+            .and_then(|history_id| self.secure_clipboard(true, Some(history_id)))
+            .and_then(|clipboard| {
+                clipboard.copy_with_timeout(std::mem::take(&mut text), self.shell.clipboard_timeout)
+            });
+        text.zeroize();
+        match copied {
+            Ok(msg) => CommandResult::Output(msg),
+            Err(e) => CommandResult::Error(e),
+        }
+    }
+
+    /// `::history search [-i] <regex>`: list the entries whose command matches
+    fn history_search(&self, args: &str) -> CommandResult {
+        let (pattern, regex) = match strip_flag(args, "-i") {
+            Some(pattern) => (pattern, Regex::case_insensitive(pattern)),
+            None => (args, Regex::new(args)),
+        };
+        if pattern.is_empty() {
+            return CommandResult::Output("Usage: ::history search [-i] <regex>".to_string());
+        }
+        let regex = match regex {
+            Ok(regex) => regex,
+            Err(e) => return CommandResult::Error(e),
+        };
+        let matches: String = self
+            .history
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| entry.command.expose(|command| regex.is_match(command)))
+            .map(|(i, entry)| history_line(i, entry))
+            .collect();
+        if matches.is_empty() {
+            CommandResult::Output(format!("No history entries match '{}'.", pattern))
+        } else {
+            CommandResult::Output(format!("History matching '{}':\r\n{}", pattern, matches))
+        }
+    }
+
+    /// `::history persist [on|off]`: keep the history in an encrypted file across sessions
+    fn history_persist(&mut self, args: &str) -> CommandResult {
+        match args {
+            "on" => {
+                if self.keys.source() != KeySource::Passphrase {
+                    return CommandResult::Error(
+                        "Persistent history needs a passphrase-derived session key: restart with --master-passphrase."
+                            .to_string(),
+                    );
+                }
+                let Some(path) = history_path() else {
+                    return CommandResult::Error(
+                        "No home directory for the history file.".to_string(),
+                    );
+                };
+                if self.history_file.is_none() && path.exists() {
+                    return CommandResult::Error(format!(
+                        "{} exists but was not restored (saved under another passphrase?). \
+                        ::history persist off shreds it.",
+                        path.display()
+                    ));
+                }
+                self.history_file = Some(path.clone());
+                match self.save_history_file() {
+                    Ok(()) => CommandResult::Output(format!(
+                        "HISTORY PERSISTENCE ON: {} COMMANDS ENCRYPTED TO {}\r\n\
+                        Restored at the next launch with the same passphrase.",
+                        self.history.len(),
+                        path.display()
+                    )),
+                    Err(e) => {
+                        self.history_file = None;
+                        CommandResult::Error(e)
+                    }
+                }
+            }
+            "off" => {
+                let path = self
+                    .history_file
+                    .take()
+                    .or_else(history_path)
+                    .filter(|path| path.exists());
+                match path {
+                    Some(path) => match shred_file(&path) {
+                        Ok(()) => CommandResult::Output(format!(
+                            "HISTORY PERSISTENCE OFF: {} SHREDDED. History is RAM only.",
+                            path.display()
+                        )),
+                        Err(e) => CommandResult::Error(e),
+                    },
+                    None => CommandResult::Output(
+                        "HISTORY PERSISTENCE OFF. History is RAM only.".to_string(),
+                    ),
+                }
+            }
+            "" => CommandResult::Output(match &self.history_file {
+                Some(path) => format!("History persistence: on ({})", path.display()),
+                None => "History persistence: off (RAM only)".to_string(),
+            }),
+            _ => CommandResult::Output("Usage: ::history persist [on|off]".to_string()),
+        }
+    }
+
+    /// `::history export [--age <recipients>] <file>` / `::history import [-i <identity file>] <file>`:
+    /// the history as an age archive, passphrase-protected unless recipients are given
+    fn history_archive(&mut self, args: &str) -> CommandResult {
+        if let Some(rest) = strip_flag(args, "persist") {
+            return self.history_persist(rest);
+        }
+        if let Some(rest) = strip_flag(args, "search") {
+            return self.history_search(rest);
+        }
+        if let Some(rest) = strip_flag(args, "export") {
+            let (recipients, path) = match strip_flag(rest, "--age") {
+                Some(rest) => match rest.split_once(' ') {
+                    Some((spec, path)) => (Some(spec), path.trim_start()),
+                    None => (None, ""),
+                },
+                None => (None, rest),
+            };
+            if path.is_empty() {
+                return CommandResult::Output(
+                    "Usage: ::history export [--age <age1...[,...]>] <file>".to_string(),
+                );
+            }
+
+            let mut lines: Vec<String> = self
+                .history
+                .iter()
+                .map(|entry| entry.command.to_plain())
+                .collect();
+            let mut archive = lines.join("\n");
+            lines.iter_mut().for_each(Zeroize::zeroize);
+            let result = match recipients {
+                Some(spec) => parse_age_recipients(spec).and_then(|recipients| {
+                    write_age_file(Path::new(path), &recipients, archive.as_bytes())
+                }),
+                None => prompt_new_passphrase().and_then(|passphrase| {
+                    write_age_file(
+                        Path::new(path),
+                        &Recipients::Passphrase(passphrase.expose()),
+                        archive.as_bytes(),
+                    )
+                }),
+            };
+            archive.zeroize();
+            return match result {
+                Ok(()) => CommandResult::Output(format!(
+                    "HISTORY EXPORTED: {} COMMANDS ENCRYPTED TO {}",
+                    self.history.len(),
+                    path
+                )),
+                Err(e) => CommandResult::Error(e),
+            };
+        }
+
+        if let Some(rest) = strip_flag(args, "import") {
+            let (identity_file, path) = match strip_flag(rest, "-i") {
+                Some(rest) => match rest.split_once(' ') {
+                    Some((file, path)) => (Some(Path::new(file)), path.trim_start()),
+                    None => (None, ""),
+                },
+                None => (None, rest),
+            };
+            if path.is_empty() {
+                return CommandResult::Output(
+                    "Usage: ::history import [-i <identity file>] <file>".to_string(),
+                );
+            }
+
+            let path = Path::new(path);
+            let archive = match detect_format(path) {
+                Ok(FileFormat::Age { passphrase: false }) => {
+                    age_identities(&self.vault, identity_file).and_then(|identities| {
+                        read_age_file(path, &Unlock::Identities(&identities))
+                    })
+                }
+                Ok(FileFormat::Age { passphrase: true }) => match read_secret("Passphrase: ") {
+                    Ok(Some(passphrase)) => {
+                        read_age_file(path, &Unlock::Passphrase(passphrase.expose()))
+                    }
+                    Ok(None) => Err("Cancelled.".to_string()),
+                    Err(e) => Err(format!("Input error: {}", e)),
+                },
+                Ok(_) => Err(format!("{}: not an age history archive.", path.display())),
+                Err(e) => Err(e),
+            };
+            let mut archive = match archive.and_then(|bytes| {
+                String::from_utf8(bytes).map_err(|e| {
+                    e.into_bytes().zeroize();
+                    "History archive is not valid UTF-8.".to_string()
+                })
+            }) {
+                Ok(archive) => archive,
+                Err(e) => return CommandResult::Error(e),
+            };
+
+            let mut imported = 0;
+            for line in archive.lines() {
+                if self.remember(HistoryEntry::new(line.to_string(), None, None)) {
+                    imported += 1;
+                }
+            }
+            archive.zeroize();
+            self.history_index = self.history.len();
+            let _ = self.save_history_file();
+            return CommandResult::Output(format!("HISTORY IMPORTED: {} COMMANDS.", imported));
+        }
+
+        CommandResult::Output(
+            "Usage: ::history | ::history export [--age <recipients>] <file> | ::history import [-i <identity file>] <file> | ::history persist [on|off] | ::history search [-i] <regex>"
+                .to_string(),
+        )
+    }
+
+    // --- CLIPBOARD ---
+
+    /// Open the system clipboard with tamper monitoring attached
+    fn secure_clipboard(
+        &self,
+        encryption_enabled: bool,
+        history_id: Option<u64>,
+    ) -> Result<SecureClipboard, String> {
+        self.shell.clipboard().map(|clipboard| {
+            clipboard
+                .encrypted(encryption_enabled)
+                .monitored(&self.clipboard_monitor, history_id)
+        })
+    }
+
+    /// A fresh scan laid out for `::watch-security`
+    fn security_sections(&mut self) -> Vec<Section> {
+        let mark = |good: bool| if good { "✓" } else { "✗" };
+        let status = self.shell.rescan();
+        let tracer = match tracer_pid() {
+            Some(0) => "✓ TracerPid:          0 (not traced)".to_string(),
+            Some(pid) => format!("✗ TracerPid:          {} (being traced)", pid),
+            None => "  TracerPid:          not available".to_string(),
+        };
+        let locked = match locked_memory_kb() {
+            Some(kb) => format!(" ({} kB locked)", kb),
+            None => String::new(),
+        };
+        let process = vec![
+            format!("  Pid:                {}", std::process::id()),
+            tracer,
+            format!(
+                "{} Ptrace Attach:      {}",
+                mark(status.ptrace_blocked),
+                if status.ptrace_blocked {
+                    "REFUSED"
+                } else {
+                    "ALLOWED"
+                }
+            ),
+            format!(
+                "{} Memory Locked:      {}{}",
+                mark(status.memory_locked),
+                if status.memory_locked { "YES" } else { "NO" },
+                locked
+            ),
+            format!(
+                "{} Core Dumps Blocked: {}",
+                mark(status.core_dumps_disabled),
+                if status.core_dumps_disabled {
+                    "YES"
+                } else {
+                    "NO"
+                }
+            ),
+            if status.swap_disabled {
+                "✓ Swap Disabled:      YES".to_string()
+            } else {
+                "⚠ Swap Disabled:      NO".to_string()
+            },
+            format!("  Posture Grade:      {}", status.grade().letter()),
+        ];
+        let threats = if status.threats_detected.is_empty() {
+            vec!["✓ None detected".to_string()]
+        } else {
+            status
+                .threats_detected
+                .iter()
+                .map(|threat| format!("{}{}", ALERT_PREFIX, threat))
+                .collect()
+        };
+
+        let mut clipboard = vec![if self.clipboard_tampered {
+            format!(
+                "{}Tamper Guard:       content replaced by another application",
+                ALERT_PREFIX
+            )
+        } else {
+            format!(
+                "✓ Tamper Guard:       {} (no tampering seen)",
+                self.clipboard_monitor.response().name()
+            )
+        }];
+        let timers = list_clear_timers();
+        if timers.is_empty() {
+            clipboard.push("  Pending Clears:     none".to_string());
+        } else {
+            clipboard.push(format!(
+                "⚠ Pending Clears:     {} (next in {})",
+                timers.len(),
+                clear_countdown().map_or("-".to_string(), |secs| format!("{}s", secs))
+            ));
+            clipboard.extend(timers);
+        }
+        clipboard.push(format!(
+            "  History:            {} item(s), {} slot(s)",
+            self.clipboard_history.list().len(),
+            self.clipboard_slots.list().len()
+        ));
+
+        vec![
+            Section {
+                title: "PROCESS",
+                lines: process,
+            },
+            Section {
+                title: "CLIPBOARD",
+                lines: clipboard,
+            },
+            Section {
+                title: "THREATS",
+                lines: threats,
+            },
+        ]
+    }
+
+    /// Handle a pending clipboard tamper alert, returning the message to show
+    pub fn check_clipboard_tamper(&mut self) -> Option<String> {
+        let alert = self.clipboard_monitor.take_alert()?;
+        self.clipboard_tampered = true;
+
+        let purged = self.clipboard_monitor.response() == TamperResponse::Purge
+            && alert
+                .history_id
+                .is_some_and(|id| self.clipboard_history.remove(id));
+        Some(if purged {
+            "⚠ CLIPBOARD ALERT: copied content was replaced by another application. \
+            History entry purged."
+                .to_string()
+        } else {
+            "⚠ CLIPBOARD ALERT: copied content was replaced by another application.".to_string()
+        })
+    }
+
+    /// Bottom status line for `width` columns: paranoid state, the background monitor's
+    /// debugger and monitoring findings, the clipboard countdown and the process mask.
+    /// Segments are dropped from the right when the terminal is too narrow.
+    pub fn status_bar(&self, posture: Posture, width: usize) -> String {
+        let mut segments: Vec<(Option<Role>, String)> = Vec::new();
+        if !self.tabs.is_empty() {
+            segments.push((None, format!("TAB {}/{}", self.tab, self.tabs.len() + 1)));
+        }
+        segments.push(if self.paranoid_mode {
+            (Some(Role::Warning), "PARANOID ON".to_string())
+        } else {
+            (None, "paranoid off".to_string())
+        });
+        if self.readonly {
+            segments.push((Some(Role::Warning), "READ-ONLY".to_string()));
+        }
+        if self.direct_exec {
+            segments.push((None, "DIRECT".to_string()));
+        }
+        if self.ephemeral.is_some() {
+            segments.push((None, "EPHEMERAL".to_string()));
+        }
+        if ssh_session().is_some() {
+            segments.push((Some(Role::Warning), "SSH".to_string()));
+        }
+        segments.push(match posture {
+            Posture { checked: false, .. } => (None, "DBG …".to_string()),
+            Posture { debugger: true, .. } => {
+                (Some(Role::Alert), format!("{}DEBUGGER", ALERT_PREFIX))
+            }
+            _ => (None, "DBG ✓".to_string()),
+        });
+        segments.push(match posture {
+            Posture { checked: false, .. } => (None, "MON …".to_string()),
+            Posture { monitors: 0, .. } => (None, "MON ✓".to_string()),
+            Posture { monitors, .. } => (
+                Some(Role::Alert),
+                format!("{}MON {}", ALERT_PREFIX, monitors),
+            ),
+        });
+        if self.clipboard_tampered {
+            segments.push((Some(Role::Alert), format!("{}CLIP TAMPERED", ALERT_PREFIX)));
+        } else if let Some(secs) = clear_countdown() {
+            segments.push((Some(Role::Countdown), format!("CLIP {}s", secs)));
+        } else {
+            segments.push((None, "CLIP -".to_string()));
+        }
+        if let Some(mask) = self.profile.settings().mask_name() {
+            segments.push((None, format!("MASK {}", mask)));
+        }
+
+        const SEPARATOR: &str = " │ ";
+        let visible = |segments: &[(Option<Role>, String)]| {
+            segments
+                .iter()
+                .map(|(_, text)| text.chars().count())
+                .sum::<usize>()
+                + SEPARATOR.chars().count() * segments.len().saturating_sub(1)
+        };
+        while segments.len() > 1 && visible(&segments) > width {
+            segments.pop();
+        }
+        segments
+            .iter()
+            .map(|(role, text)| match role {
+                Some(role) => self.theme.paint(*role, text),
+                None => text.clone(),
+            })
+            .collect::<Vec<_>>()
+            .join(SEPARATOR)
+    }
+
+    /// Current color theme
+    pub fn theme(&self) -> Theme {
+        self.theme
+    }
+
+    // --- EXECUTION ---
+
+    /// Run the input line through the config hooks: `hook.pre` snippets may rewrite or
+    /// refuse it, `hook.post` snippets see and may rewrite its output
+    pub fn execute_line(&mut self) -> CommandResult {
+        self.content.zeroize();
+        self.content = self.line.take();
+        if self.content.trim().is_empty() {
+            return CommandResult::NoOp;
+        }
+
+        // History expansion, shown before it runs. Ghost command lines are left alone:
+        // their arguments are often secrets with a `!` in them.
+        if !self.content.trim_start().starts_with(GHOST_COMMAND_PREFIX) {
+            match expand_history(&self.content, &self.history) {
+                Ok(Some(expanded)) => {
+                    let _ = write!(term::stdout(), "{}\r\n", expanded);
+                    self.content.zeroize();
+                    self.content = expanded;
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    self.last_status.set(Some(1));
+                    return CommandResult::Error(e);
+                }
+            }
+        }
+
+        if !self.pre_hooks.is_empty() {
+            let mut vars = vec![("command", self.content.trim().to_string())];
+            for hook in self.pre_hooks.clone() {
+                if let Err(e) = run_hook("hook.pre", &hook, &mut vars, self) {
+                    return CommandResult::Output(format!("BLOCKED BY {}", e));
+                }
+            }
+            if let Some((_, command)) = vars.pop() {
+                self.content.zeroize();
+                self.content = command;
+            }
+        }
+
+        self.command_started = SystemTime::now();
+        self.last_status.set(None);
+        let result = self.process_command();
+        if self.last_status.get().is_some() {
+            // A child ran: its output is what ::last shows
+            if let CommandResult::Output(output) | CommandResult::Error(output) = &result {
+                self.last_output.zeroize();
+                self.last_output = output.clone();
+            }
+        }
+        // Ghost commands have no child to report a status; success is whether they failed
+        if self.last_status.get().is_none() {
+            let failed = matches!(result, CommandResult::Error(_));
+            self.last_status.set(Some(i32::from(failed)));
+        }
+        if let Some(audit) = &mut self.audit {
+            if let Err(e) = audit.append(
+                self.command_started,
+                self.last_status.get(),
+                self.content.trim(),
+            ) {
+                let alert = format!("AUDIT LOG ALERT: {}", e);
+                let _ = write!(term::stdout(), "{}\r\n", self.theme.alert(&alert));
+            }
+        }
+        if self.post_hooks.is_empty() {
+            return result;
+        }
+        let failed = matches!(result, CommandResult::Error(_));
+        let mut output = match result {
+            CommandResult::Output(output) | CommandResult::Error(output) => output,
+            CommandResult::NoOp => String::new(),
+            other => return other,
+        };
+        let mut vars = vec![
+            ("command", self.content.trim().to_string()),
+            ("output", std::mem::take(&mut output)),
+        ];
+        for hook in self.post_hooks.clone() {
+            if let Err(e) = run_hook("hook.post", &hook, &mut vars, self) {
+                vars.push(("error", e));
+                break;
+            }
+        }
+        let mut lines: Vec<String> = Vec::new();
+        for (name, mut value) in vars {
+            match name {
+                "output" if !value.is_empty() => lines.push(value),
+                "error" => lines.push(format!("Hook error: {}", value)),
+                _ => value.zeroize(),
+            }
+        }
+        if lines.is_empty() {
+            CommandResult::NoOp
+        } else if failed {
+            CommandResult::Error(lines.join("\r\n"))
+        } else {
+            CommandResult::Output(lines.join("\r\n"))
+        }
+    }
+
+    /// Run the input line without hooks
+    pub fn process_command(&mut self) -> CommandResult {
+        let trimmed_command = self.content.trim();
+
+        if trimmed_command.is_empty() {
+            return CommandResult::NoOp;
+        }
+
+        // Rules of engagement from the config file, before anything else happens
+        if let Some(rule) = policy::check(&self.policy, trimmed_command).cloned() {
+            if let Some(refusal) = enforce_policy(&rule) {
+                return refusal;
+            }
+        }
+
+        // ::readonly on: only commands that leave the system as it is get through
+        if self.readonly {
+            let reason = match trimmed_command.strip_prefix(GHOST_COMMAND_PREFIX) {
+                Some(ghost_cmd) => {
+                    let (cmd, args) = ghost_cmd.split_once(' ').unwrap_or((ghost_cmd, ""));
+                    ghost_mutation(cmd, args)
+                }
+                None => mutation(trimmed_command),
+            };
+            if let Some(reason) = reason {
+                return CommandResult::Error(format!(
+                    "✗ READ-ONLY MODE: {}. ::readonly off allows changes.",
+                    reason
+                ));
+            }
+        }
+
+        // Running a command acknowledges a clipboard tamper alert
+        self.clipboard_tampered = false;
+
+        // Increment command counter
+        self.command_count += 1;
+
+        let periodic_check = self.command_count.is_multiple_of(self.check_interval);
+
+        // Periodic security check in paranoid mode (every `check_interval` commands)
+        if self.paranoid_mode && periodic_check && is_debugger_present() {
+            emergency_shutdown(
+                self.theme,
+                &["PERIODIC CHECK: DEBUGGER DETECTED".to_string()],
+            );
+        }
+
+        // Periodic memory map audit: new executable regions signal code injection
+        if periodic_check {
+            let anomalies = audit_memory_maps();
+            if !anomalies.is_empty() {
+                if self.paranoid_mode {
+                    emergency_shutdown(
+                        self.theme,
+                        &["PERIODIC CHECK: MEMORY MAP TAMPERING DETECTED".to_string()],
+                    );
+                }
+                let mut stdout = term::stdout();
+                for anomaly in &anomalies {
+                    let alert = format!("MEMORY MAP ALERT: {}", anomaly);
+                    let _ = write!(stdout, "{}\r\n", self.theme.alert(&alert));
+                }
+            }
+        }
+
+        // Periodic own-socket audit: nothing in the shell itself should be connected now
+        if periodic_check {
+            let threats = audit_own_sockets();
+            if !threats.is_empty() {
+                if self.paranoid_mode {
+                    emergency_shutdown(
+                        self.theme,
+                        &["PERIODIC CHECK: UNEXPECTED NETWORK CONNECTION DETECTED".to_string()],
+                    );
+                }
+                let mut stdout = term::stdout();
+                for threat in &threats {
+                    let alert = format!("NETWORK ALERT: {}", threat);
+                    let _ = write!(stdout, "{}\r\n", self.theme.alert(&alert));
+                }
+            }
+        }
+
+        if let Some(ghost_cmd) = trimmed_command.strip_prefix(GHOST_COMMAND_PREFIX) {
+            let parts: Vec<&str> = ghost_cmd.splitn(2, ' ').collect();
+            let cmd = parts[0];
+            let args = if parts.len() > 1 { parts[1] } else { "" };
+            // Commands that open their own connections; the socket watcher holds off meanwhile
+            let _network = matches!(
+                cmd,
+                "share" | "send" | "recv" | "resolve" | "proxy" | "torcheck"
+            )
+            .then(ExpectNetwork::new);
+
+            match cmd {
+                "panic" => self.panic(), // NUCLEAR OPTION
+                "help" if args.is_empty() => CommandResult::Output(crate::commands::overview()),
+                "help" => CommandResult::Output(match crate::commands::find(args) {
+                    Some(command) => crate::commands::usage(command),
+                    None => match did_you_mean(&suggest(args, &[])) {
+                        hint if hint.is_empty() => {
+                            format!("No command '{}'. Type ::help for the list.", args)
+                        }
+                        hint => format!("No command '{}'. {}", args, hint),
+                    },
+                }),
+                "config" => CommandResult::Output(match config_path() {
+                    Some(path) => format!(
+                        "{}\r\nConfig file: {}{}",
+                        self.config_status,
+                        path.display(),
+                        if path.exists() { "" } else { " (not present)" }
+                    ),
+                    None => "No config location ($HOME unset).".to_string(),
+                }),
+                "status" => CommandResult::Output(
+                    match self.shell.security_status().threats_detected.len() {
+                        0 => "GHOST MODE ACTIVE. MEMORY SECURE. TRACE: NONE.".to_string(),
+                        n => format!(
+                            "⚠ GHOST MODE ACTIVE. {} THREAT(S) AT THE LAST SCAN (::security-status for details).",
+                            n
+                        ),
+                    },
+                ),
+                "security-status" if args == "--json" => {
+                    let extra = [
+                        ("session_keys", json_string(self.keys.source().name())),
+                        ("profile", json_string(self.profile.name())),
+                        (
+                            "remote_session",
+                            ssh_session().map_or("null".to_string(), |session| {
+                                json_string(&session.describe())
+                            }),
+                        ),
+                    ];
+                    CommandResult::Output(self.shell.rescan().to_json(&extra))
+                }
+                "security-status" => {
+                    let status = self.shell.rescan();
+                    CommandResult::Output(format!(
+                        "{}Session Keys:        {}\r\nSecurity Profile:    {}\r\nRemote Session:      {}",
+                        status.report(),
+                        self.keys.source().name(),
+                        self.profile.name(),
+                        match ssh_session() {
+                            Some(session) => format!(
+                                "⚠ {} (output crosses the network; clipboard via OSC 52)",
+                                session.describe()
+                            ),
+                            None => "✓ LOCAL".to_string(),
+                        }
+                    ))
+                }
+                "meminfo" => {
+                    let locked = LockedRanges::capture();
+                    let locked = locked.as_ref();
+                    let history: Vec<_> = self
+                        .history
+                        .iter()
+                        .map(|entry| entry.command.region())
+                        .collect();
+                    let holdings = [
+                        Holding::new(
+                            "History",
+                            &history,
+                            false,
+                            format!(
+                                "past {} entries; exit{}",
+                                self.history_max,
+                                if self.history_file.is_some() {
+                                    " (saved)"
+                                } else {
+                                    ""
+                                }
+                            ),
+                            locked,
+                        ),
+                        Holding::new(
+                            "Vault",
+                            &self.vault.regions(),
+                            true,
+                            "::vault rm; exit".to_string(),
+                            locked,
+                        ),
+                        Holding::new(
+                            "Clipboard ring",
+                            &self.clipboard_history.regions(),
+                            true,
+                            format!(
+                                "past {} items; purge; exit",
+                                CLIPBOARD_HISTORY_SIZE
+                            ),
+                            locked,
+                        ),
+                        Holding::new(
+                            "Clipboard slots",
+                            &self.clipboard_slots.regions(),
+                            true,
+                            "exit".to_string(),
+                            locked,
+                        ),
+                        Holding::new(
+                            "Note",
+                            &self.note.regions(),
+                            true,
+                            "::note burn; exit".to_string(),
+                            locked,
+                        ),
+                    ];
+                    CommandResult::Output(format!(
+                        "{}\r\n\r\nSystem clipboard: {}\r\nLocked in RAM:    {}",
+                        meminfo::report(&holdings),
+                        match clear_countdown() {
+                            Some(secs) => format!("⚠ our copy clears in {}s", secs),
+                            None if list_clear_timers().is_empty() => {
+                                "nothing of ours pending a clear".to_string()
+                            }
+                            None => "⚠ our copy clears once pasted".to_string(),
+                        },
+                        match locked_memory_kb() {
+                            Some(kb) => format!("{} kB in total (VmLck)", kb),
+                            None => "not available".to_string(),
+                        }
+                    ))
+                }
+                "watch-security" => {
+                    let mut alerts = Vec::new();
+                    let watched = watch(
+                        self.theme,
+                        "=== GHOST SHELL SECURITY WATCH ===",
+                        WATCH_INTERVAL,
+                        || {
+                            alerts.extend(self.check_clipboard_tamper());
+                            self.security_sections()
+                        },
+                    );
+                    match watched {
+                        Ok(()) if alerts.is_empty() => CommandResult::NoOp,
+                        Ok(()) => CommandResult::Output(alerts.join("\r\n")),
+                        Err(e) => CommandResult::Error(format!("Dashboard failed: {}", e)),
+                    }
+                }
+                "decoy" => match args {
+                    "start" if self.decoy.is_running() => CommandResult::Output(format!(
+                        "Decoy activity already {}.",
+                        self.decoy.describe()
+                    )),
+                    "start" => {
+                        let tailing = match self.decoy.start() {
+                            Some(log) => format!("Tailing {}", log),
+                            None => "No readable log to tail".to_string(),
+                        };
+                        CommandResult::Output(format!(
+                            "DECOY ACTIVITY STARTED\r\n\
+                            - {}\r\n\
+                            - A routine command (uptime, df, ps, ...) every {}-{}s, output discarded\r\n\
+                            - ::decoy stop ends it",
+                            tailing,
+                            crate::decoy::INTERVAL_SECS.0,
+                            crate::decoy::INTERVAL_SECS.1
+                        ))
+                    }
+                    "stop" if self.decoy.is_running() => CommandResult::Output(format!(
+                        "DECOY ACTIVITY STOPPED after {} routine commands.",
+                        self.decoy.stop()
+                    )),
+                    "stop" => CommandResult::Output("Decoy activity is not running.".to_string()),
+                    _ => CommandResult::Output(format!(
+                        "Decoy activity: {}\r\nUsage: ::decoy start|stop",
+                        self.decoy.describe()
+                    )),
+                },
+                "net-audit" => {
+                    if !crate::netaudit::supported() {
+                        return CommandResult::Output(
+                            "Socket audit needs /proc (Linux only).".to_string(),
+                        );
+                    }
+                    let sockets = own_sockets();
+                    let mut output = String::from("=== SHELL NETWORK SOCKETS ===\r\n");
+                    if sockets.is_empty() {
+                        output.push_str("✓ No TCP/UDP sockets held by the shell process.\r\n");
+                    } else {
+                        output.push_str("⚠ UNEXPECTED SOCKETS (nothing should be connected between commands):\r\n");
+                        for socket in &sockets {
+                            output.push_str(&format!("  - {}\r\n", socket.describe()));
+                        }
+                    }
+                    output.push_str(&format!(
+                        "Watcher:             {}",
+                        if self.socket_watcher.is_running() {
+                            format!(
+                                "ACTIVE (every {}ms, paranoid mode)",
+                                crate::netaudit::WATCH_INTERVAL.as_millis()
+                            )
+                        } else {
+                            "off (::paranoid on starts it)".to_string()
+                        }
+                    ));
+                    CommandResult::Output(output)
+                }
+                "exit" => CommandResult::Exit,
+                "clear" => {
+                    let _ = sanitize_screen(&mut term::stdout());
+                    let _ = execute!(term::stdout(), SetTitle(&self.title));
+                    CommandResult::NoOp
+                }
+                // `args` borrows the input line; copy it so the handler can take &mut self
+                "history" if !args.is_empty() => {
+                    let args = args.to_string();
+                    self.history_archive(&args)
+                }
+                "history" => {
+                    if self.history.is_empty() {
+                        CommandResult::Output("No commands in history.".to_string())
+                    } else {
+                        let mut output = match &self.history_file {
+                            Some(path) => format!("Command History (persisted to {}):\r\n", path.display()),
+                            None => String::from("Command History (RAM only):\r\n"),
+                        };
+                        for (i, entry) in self.history.iter().enumerate() {
+                            output.push_str(&history_line(i, entry));
+                        }
+                        CommandResult::Output(output)
+                    }
+                }
+                // `args` borrows the input line; copy it so the handler can take &mut self
+                "audit" => {
+                    let args = args.to_string();
+                    self.audit_command(&args)
+                }
+                #[cfg(unix)]
+                "detach" => {
+                    let args = args.to_string();
+                    self.detach_command(&args)
+                }
+                #[cfg(not(unix))]
+                "detach" => CommandResult::Error(
+                    "::detach is not supported on this platform.".to_string(),
+                ),
+                "honeypot" => self.honeypot_command(args),
+                "tab" => {
+                    let args = args.to_string();
+                    self.tab_command(&args)
+                }
+                "last" => self.last_command(args),
+                "cp-last" => self.copy_last(),
+                "calc" if args.is_empty() => CommandResult::Output(
+                    "Usage: ::calc <expr> [in <unit>|hex|oct|bin|dec] | ::calc <ipv4>/<prefix>"
+                        .to_string(),
+                ),
+                "calc" => match calculate(args) {
+                    Ok(result) => CommandResult::Output(result),
+                    Err(e) => CommandResult::Error(e),
+                },
+                "purge-history" => {
+                    let count = self.history.len();
+                    self.purge_history();
+                    CommandResult::Output(format!(
+                        "HISTORY PURGED. {} COMMANDS ZEROIZED FROM MEMORY.",
+                        count
+                    ))
+                }
+                "cp" => {
+                    // --once: the clipboard is cleared right after the first paste
+                    // -t <secs>: auto-clear timeout for this copy only
+                    let mut once = false;
+                    let mut timeout = self.shell.clipboard_timeout;
+                    let mut args = args;
+                    let mut options = false;
+                    loop {
+                        if let Some(rest) = strip_flag(args, "--once") {
+                            once = true;
+                            options = true;
+                            args = rest;
+                        } else if let Some(rest) = strip_flag(args, "-t") {
+                            let (secs, rest) = rest.split_once(' ').unwrap_or((rest, ""));
+                            match secs.parse::<u64>() {
+                                Ok(secs) => timeout = secs,
+                                Err(_) => {
+                                    return CommandResult::Output(
+                                        "Usage: ::cp -t <secs> <text>".to_string(),
+                                    )
+                                }
+                            }
+                            args = rest.trim_start();
+                            options = true;
+                        } else {
+                            break;
+                        }
+                    }
+
+                    if args == "--now-clear" && !options {
+                        match self.shell.clipboard().and_then(|clipboard| clipboard.clear()) {
+                            Ok(()) => CommandResult::Output("CLIPBOARD CLEARED.".to_string()),
+                            Err(e) => CommandResult::Error(e),
+                        }
+                    } else if args == "cancel" && !once {
+                        CommandResult::Output(match cancel_clear_timers() {
+                            0 => "No pending clipboard clear.".to_string(),
+                            n => format!("{} PENDING CLEAR(S) CANCELLED. Clipboard left as is.", n),
+                        })
+                    } else if args == "timers" && !options {
+                        let timers = list_clear_timers();
+                        if timers.is_empty() {
+                            CommandResult::Output("No pending clipboard clear.".to_string())
+                        } else {
+                            CommandResult::Output(format!(
+                                "Pending clipboard clears:\r\n{}",
+                                timers.join("\r\n")
+                            ))
+                        }
+                    } else if let Some(secs) = args.strip_prefix("extend ").filter(|_| !options) {
+                        match secs.trim().parse::<u64>() {
+                            Ok(secs) => CommandResult::Output(match extend_clear_timers(secs) {
+                                0 => "No pending clipboard clear.".to_string(),
+                                n => format!("{} PENDING CLEAR(S) EXTENDED BY {}s.", n, secs),
+                            }),
+                            Err(_) => {
+                                CommandResult::Output("Usage: ::cp extend <secs>".to_string())
+                            }
+                        }
+                    } else if args.is_empty()
+                        || args == "-p"
+                        || args == "--qr"
+                        || args == "--age"
+                        || args == "--to"
+                        || args == "--gpg"
+                    {
+                        CommandResult::Error("Error: No content to copy.".to_string())
+                    } else if let Some(text) = args.strip_prefix("--qr ") {
+                        // Air-gapped: ciphertext and key as two QR codes, clipboard untouched
+                        match encrypt_payload(text) {
+                            Ok((payload, key_b64)) => {
+                                let rendered = render_qr(&payload).and_then(|payload_qr| {
+                                    render_qr(key_b64.expose()).map(|key_qr| {
+                                        format!(
+                                            "ENCRYPTED PAYLOAD:\r\n{}\r\nKEY (scan separately):\r\n{}",
+                                            payload_qr, key_qr
+                                        )
+                                    })
+                                });
+                                match rendered {
+                                    Ok(output) => CommandResult::Output(output),
+                                    Err(e) => CommandResult::Error(e),
+                                }
+                            }
+                            Err(e) => CommandResult::Error(e),
+                        }
+                    } else if let Some((to_contacts, age_args)) = strip_flag(args, "--age")
+                        .map(|rest| (false, rest))
+                        .or_else(|| strip_flag(args, "--to").map(|rest| (true, rest)))
+                    {
+                        // ASCII-armored age, readable by standard age/rage tooling
+                        let (spec, text) = match age_args.split_once(' ') {
+                            Some((spec, text)) if !text.is_empty() => (spec, text),
+                            _ if to_contacts => {
+                                return CommandResult::Output(
+                                    "Usage: ::cp --to <contact[,contact...]> <text>".to_string(),
+                                )
+                            }
+                            _ => {
+                                return CommandResult::Output(
+                                    "Usage: ::cp --age <age1...[,age1...]|-p> <text>".to_string(),
+                                )
+                            }
+                        };
+                        let passphrase;
+                        let recipients = if to_contacts {
+                            match self.contacts.recipients(spec) {
+                                Ok(recipients) => recipients,
+                                Err(e) => return CommandResult::Error(e),
+                            }
+                        } else if spec == "-p" {
+                            match prompt_new_passphrase() {
+                                Ok(p) => passphrase = p,
+                                Err(e) => return CommandResult::Error(e),
+                            }
+                            Recipients::Passphrase(passphrase.expose())
+                        } else {
+                            match parse_age_recipients(spec) {
+                                Ok(recipients) => recipients,
+                                Err(e) => return CommandResult::Error(e),
+                            }
+                        };
+                        let result = self
+                            .clipboard_history
+                            .push(text)
+                            .and_then(|history_id| self.secure_clipboard(true, Some(history_id)))
+                            .map(|clipboard| {
+                                if once {
+                                    clipboard.one_time()
+                                } else {
+                                    clipboard
+                                }
+                            })
+                            .and_then(|clipboard| {
+                                clipboard.copy_age(text.to_string(), &recipients, timeout)
+                            });
+                        match result {
+                            Ok(msg) => CommandResult::Output(msg),
+                            Err(e) => CommandResult::Error(e),
+                        }
+                    } else if let Some(gpg_args) = strip_flag(args, "--gpg") {
+                        // Opt-in OpenPGP: armored message for an existing public key
+                        match gpg_args.split_once(' ') {
+                            Some((recipient, text)) if !text.is_empty() => {
+                                let result = self
+                                    .clipboard_history
+                                    .push(text)
+                                    .and_then(|history_id| {
+                                        self.secure_clipboard(true, Some(history_id))
+                                    })
+                                    .map(|clipboard| {
+                                        if once {
+                                            clipboard.one_time()
+                                        } else {
+                                            clipboard
+                                        }
+                                    })
+                                    .and_then(|clipboard| {
+                                        clipboard.copy_gpg(text.to_string(), recipient, timeout)
+                                    });
+                                match result {
+                                    Ok(msg) => CommandResult::Output(msg),
+                                    Err(e) => CommandResult::Error(e),
+                                }
+                            }
+                            _ => CommandResult::Output(
+                                "Usage: ::cp --gpg <recipient> <text>".to_string(),
+                            ),
+                        }
+                    } else if let Some(slot_args) = args.strip_prefix("-s ") {
+                        // Stage into a named slot; the system clipboard is untouched
+                        let slot_parts: Vec<&str> = slot_args.trim_start().splitn(2, ' ').collect();
+                        match slot_parts.as_slice() {
+                            [name, text] if !text.is_empty() => {
+                                match self.clipboard_slots.store(name, text) {
+                                    Ok(()) => CommandResult::Output(format!(
+                                        "STAGED IN ENCRYPTED SLOT '{}'. Use ::paste -s {} to push it.",
+                                        name, name
+                                    )),
+                                    Err(e) => CommandResult::Error(e),
+                                }
+                            }
+                            _ => CommandResult::Output("Usage: ::cp -s <slot> <text>".to_string()),
+                        }
+                    } else if let Some(text) = args.strip_prefix("-p ") {
+                        let history_id = match self.clipboard_history.push(text) {
+                            Ok(id) => id,
+                            Err(e) => return CommandResult::Error(e),
+                        };
+                        // Passphrase-derived key: nothing secret is printed
+                        match prompt_new_passphrase() {
+                            Ok(passphrase) => {
+                                let result = self
+                                    .secure_clipboard(true, Some(history_id))
+                                    .map(|clipboard| {
+                                        if once {
+                                            clipboard.one_time()
+                                        } else {
+                                            clipboard
+                                        }
+                                    })
+                                    .and_then(|clipboard| {
+                                        clipboard.copy_with_passphrase(
+                                            text.to_string(),
+                                            passphrase.expose(),
+                                            timeout,
+                                        )
+                                    });
+                                match result {
+                                    Ok(msg) => CommandResult::Output(msg),
+                                    Err(e) => CommandResult::Error(e),
+                                }
+                            }
+                            Err(e) => CommandResult::Error(e),
+                        }
+                    } else {
+                        let history_id = match self.clipboard_history.push(args) {
+                            Ok(id) => id,
+                            Err(e) => return CommandResult::Error(e),
+                        };
+                        let clipboard =
+                            self.secure_clipboard(true, Some(history_id))
+                                .map(|clipboard| {
+                                    if once {
+                                        clipboard.one_time()
+                                    } else {
+                                        clipboard
+                                    }
+                                });
+                        match clipboard {
+                            Ok(clipboard) => {
+                                match clipboard.copy_with_timeout(args.to_string(), timeout) {
+                                    Ok(msg) => CommandResult::Output(msg),
+                                    Err(e) => CommandResult::Error(e),
+                                }
+                            }
+                            Err(e) => CommandResult::Error(e),
+                        }
+                    }
+                }
+                "paste" => {
+                    let paste_parts: Vec<&str> = args.split_whitespace().collect();
+                    match paste_parts.as_slice() {
+                        ["-s"] => {
+                            let slots = self.clipboard_slots.list();
+                            if slots.is_empty() {
+                                CommandResult::Output("No clipboard slots staged.".to_string())
+                            } else {
+                                CommandResult::Output(format!(
+                                    "Clipboard Slots (encrypted):\r\n{}",
+                                    slots.join("\r\n")
+                                ))
+                            }
+                        }
+                        ["-s", name] => match self.clipboard_slots.get(name) {
+                            // Pushed in plaintext so it can be pasted, but still auto-cleared
+                            Ok(text) => match self.secure_clipboard(false, None) {
+                                Ok(clipboard) => match clipboard
+                                    .copy_with_timeout(text, self.shell.clipboard_timeout)
+                                {
+                                    Ok(msg) => CommandResult::Output(format!(
+                                        "SLOT '{}' PUSHED. {}",
+                                        name, msg
+                                    )),
+                                    Err(e) => CommandResult::Error(e),
+                                },
+                                Err(e) => CommandResult::Error(e),
+                            },
+                            Err(e) => CommandResult::Error(e),
+                        },
+                        ["-s", ..] => CommandResult::Output("Usage: ::paste -s [slot]".to_string()),
+                        _ => {
+                            // ::paste [-i] [key] [-- command]
+                            let (options, feed_command) =
+                                if let Some(rest) = args.strip_prefix("--") {
+                                    ("", Some(rest.trim()))
+                                } else if let Some((options, rest)) = args.split_once(" --") {
+                                    (options, Some(rest.trim()))
+                                } else {
+                                    (args, None)
+                                };
+                            let mut insert = false;
+                            let mut key = None;
+                            for option in options.split_whitespace() {
+                                match option {
+                                    "-i" => insert = true,
+                                    other => key = Some(other),
+                                }
+                            }
+
+                            let plaintext = match paste_plaintext(self.shell.clipboard(), key, &self.vault) {
+                                Ok(plaintext) => plaintext,
+                                Err(e) => return CommandResult::Error(e),
+                            };
+
+                            if insert {
+                                // The line editor owns it now and zeroizes it with the line
+                                CommandResult::Insert(plaintext.into_exposed())
+                            } else if let Some(command_line) = feed_command {
+                                if command_line.is_empty() {
+                                    return CommandResult::Output(
+                                        "Usage: ::paste [key] -- <command>".to_string(),
+                                    );
+                                }
+                                self.run_with_stdin(command_line, plaintext.expose().as_bytes())
+                            } else {
+                                CommandResult::Output(format!("Decrypted: {}", plaintext.expose()))
+                            }
+                        }
+                    }
+                }
+                "cpf" => {
+                    if args.is_empty() || args == "--bin" {
+                        CommandResult::Output("Usage: ::cpf [--bin] <file>".to_string())
+                    } else if let Some(path) = args.strip_prefix("--bin ") {
+                        // Any file (key files, archives): encrypted container, not in history
+                        match read_binary_file_for_copy(Path::new(path.trim_start())) {
+                            Ok(data) => match self.secure_clipboard(true, None) {
+                                Ok(clipboard) => {
+                                    match clipboard
+                                        .copy_data_with_timeout(data, self.shell.clipboard_timeout)
+                                    {
+                                        Ok(msg) => CommandResult::Output(msg),
+                                        Err(e) => CommandResult::Error(e),
+                                    }
+                                }
+                                Err(e) => CommandResult::Error(e),
+                            },
+                            Err(e) => CommandResult::Error(e),
+                        }
+                    } else {
+                        match read_file_for_copy(Path::new(args)) {
+                            Ok(mut contents) => {
+                                let history_id = match self.clipboard_history.push(&contents) {
+                                    Ok(id) => id,
+                                    Err(e) => {
+                                        contents.zeroize();
+                                        return CommandResult::Error(e);
+                                    }
+                                };
+                                // copy_with_timeout zeroizes the contents it consumes
+                                match self.secure_clipboard(true, Some(history_id)) {
+                                    Ok(clipboard) => {
+                                        match clipboard
+                                            .copy_with_timeout(contents, self.shell.clipboard_timeout)
+                                        {
+                                            Ok(msg) => CommandResult::Output(msg),
+                                            Err(e) => CommandResult::Error(e),
+                                        }
+                                    }
+                                    Err(e) => {
+                                        contents.zeroize();
+                                        CommandResult::Error(e)
+                                    }
+                                }
+                            }
+                            Err(e) => CommandResult::Error(e),
+                        }
+                    }
+                }
+                "cp-image" => {
+                    // Replace the image on the clipboard (e.g. a screenshot) with its
+                    // encrypted container, so the plaintext pixels leave the clipboard
+                    match self.secure_clipboard(true, None) {
+                        Ok(clipboard) => match clipboard.get_image() {
+                            Ok(image) => {
+                                match clipboard
+                                    .copy_data_with_timeout(image, self.shell.clipboard_timeout)
+                                {
+                                    Ok(msg) => CommandResult::Output(msg),
+                                    Err(e) => CommandResult::Error(e),
+                                }
+                            }
+                            Err(e) => CommandResult::Error(e),
+                        },
+                        Err(e) => CommandResult::Error(e),
+                    }
+                }
+                "qr" => {
+                    if args.is_empty() {
+                        CommandResult::Output("Usage: ::qr <text>".to_string())
+                    } else {
+                        match render_qr(args) {
+                            Ok(output) => CommandResult::Output(output),
+                            Err(e) => CommandResult::Error(e),
+                        }
+                    }
+                }
+                "cp-history" => {
+                    let sub_parts: Vec<&str> = args.split_whitespace().collect();
+                    match sub_parts.as_slice() {
+                        ["list"] | [] => {
+                            let entries = self.clipboard_history.list();
+                            if entries.is_empty() {
+                                CommandResult::Output("Clipboard history is empty.".to_string())
+                            } else {
+                                CommandResult::Output(format!(
+                                    "Clipboard History (encrypted, last {}):\r\n{}",
+                                    CLIPBOARD_HISTORY_SIZE,
+                                    entries.join("\r\n")
+                                ))
+                            }
+                        }
+                        ["restore", n] => match n.parse::<usize>() {
+                            Ok(n) => match self.clipboard_history.get(n) {
+                                Ok(text) => match self
+                                    .secure_clipboard(true, self.clipboard_history.id(n))
+                                {
+                                    Ok(clipboard) => match clipboard
+                                        .copy_with_timeout(text, self.shell.clipboard_timeout)
+                                    {
+                                        Ok(msg) => CommandResult::Output(msg),
+                                        Err(e) => CommandResult::Error(e),
+                                    },
+                                    Err(e) => CommandResult::Error(e),
+                                },
+                                Err(e) => CommandResult::Error(e),
+                            },
+                            Err(_) => {
+                                CommandResult::Output("Usage: ::cp-history restore <n>".to_string())
+                            }
+                        },
+                        ["purge"] => {
+                            let count = self.clipboard_history.purge();
+                            CommandResult::Output(format!(
+                                "CLIPBOARD HISTORY PURGED. {} ENTRIES ZEROIZED.",
+                                count
+                            ))
+                        }
+                        _ => CommandResult::Output(
+                            "Usage: ::cp-history list|restore <n>|purge".to_string(),
+                        ),
+                    }
+                }
+                "cp-timeout" => {
+                    if args.is_empty() {
+                        CommandResult::Output(format!(
+                            "Clipboard auto-clear: {}\r\nUsage: ::cp-timeout <secs> (0 = never clear)",
+                            match self.shell.clipboard_timeout {
+                                0 => "never".to_string(),
+                                secs => format!("{}s", secs),
+                            }
+                        ))
+                    } else {
+                        match args.parse::<u64>() {
+                            Ok(secs) => {
+                                self.shell.clipboard_timeout = secs;
+                                CommandResult::Output(match secs {
+                                    0 => "CLIPBOARD AUTO-CLEAR DISABLED. Use ::cp --now-clear to clear."
+                                        .to_string(),
+                                    secs => format!("CLIPBOARD AUTO-CLEAR SET TO {}s.", secs),
+                                })
+                            }
+                            Err(_) => CommandResult::Output(
+                                "Usage: ::cp-timeout <secs> (0 = never clear)".to_string(),
+                            ),
+                        }
+                    }
+                }
+                "cp-guard" => {
+                    if args.is_empty() {
+                        CommandResult::Output(format!(
+                            "Clipboard tamper guard: {}\r\nUsage: ::cp-guard off|alert|purge",
+                            self.clipboard_monitor.response().name()
+                        ))
+                    } else {
+                        match TamperResponse::parse(args) {
+                            Some(response) => {
+                                self.clipboard_monitor.set_response(response);
+                                CommandResult::Output(format!(
+                                    "CLIPBOARD TAMPER GUARD: {}",
+                                    response.name().to_uppercase()
+                                ))
+                            }
+                            None => CommandResult::Output(
+                                "Usage: ::cp-guard off|alert|purge".to_string(),
+                            ),
+                        }
+                    }
+                }
+                "genpass" | "genphrase" => match if cmd == "genphrase" {
+                    GenpassOptions::parse_phrase(args)
+                } else {
+                    GenpassOptions::parse(args)
+                }
+                .and_then(|options| generate(&options).map(|generated| (options.show, generated)))
+                {
+                    Ok((true, (mut password, entropy))) => {
+                        let output = format!("{}\r\n({:.0} bits of entropy)", password, entropy);
+                        password.zeroize();
+                        CommandResult::Output(output)
+                    }
+                    // Default: straight to the encrypted clipboard, never on screen
+                    Ok((false, (mut password, entropy))) => {
+                        let history_id = match self.clipboard_history.push(&password) {
+                            Ok(id) => id,
+                            Err(e) => {
+                                password.zeroize();
+                                return CommandResult::Error(e);
+                            }
+                        };
+                        match self.secure_clipboard(true, Some(history_id)) {
+                            Ok(clipboard) => {
+                                match clipboard.copy_with_timeout(password, self.shell.clipboard_timeout) {
+                                    Ok(msg) => CommandResult::Output(format!(
+                                        "{} GENERATED ({:.0} BITS).\r\n{}",
+                                        if cmd == "genphrase" { "PASSPHRASE" } else { "PASSWORD" },
+                                        entropy,
+                                        msg
+                                    )),
+                                    Err(e) => CommandResult::Error(e),
+                                }
+                            }
+                            Err(e) => {
+                                password.zeroize();
+                                CommandResult::Error(e)
+                            }
+                        }
+                    }
+                    Err(e) if cmd == "genphrase" => CommandResult::Output(format!(
+                        "{}\r\nUsage: ::genphrase [words] [-w <wordlist>] [--show]",
+                        e
+                    )),
+                    Err(e) => CommandResult::Error(format!(
+                        "{}\r\nUsage: ::genpass [-l <len>] [-c aA0!] [-p | -d [-w <wordlist>]] [--show]",
+                        e
+                    )),
+                },
+                "vault" => {
+                    let vault_parts: Vec<&str> = args.splitn(3, ' ').collect();
+                    match vault_parts.as_slice() {
+                        ["add", name, secret] if !secret.is_empty() => {
+                            vault_add(&mut self.vault, name, secret)
+                        }
+                        // Prompt without echo, keeping the secret out of command history
+                        ["add", name] if !name.is_empty() => match read_secret("Secret: ") {
+                            Ok(Some(secret)) => vault_add(&mut self.vault, name, secret.expose()),
+                            Ok(None) => CommandResult::Output("Cancelled.".to_string()),
+                            Err(e) => CommandResult::Error(format!("Input error: {}", e)),
+                        },
+                        ["get", name] => match self.vault.get(name) {
+                            Ok(secret) => match self.secure_clipboard(false, None) {
+                                Ok(clipboard) => {
+                                    match clipboard
+                                        .copy_with_timeout(secret, self.shell.clipboard_timeout)
+                                    {
+                                        Ok(msg) => CommandResult::Output(format!(
+                                            "VAULT '{}' PUSHED. {}",
+                                            name, msg
+                                        )),
+                                        Err(e) => CommandResult::Error(e),
+                                    }
+                                }
+                                Err(e) => CommandResult::Error(e),
+                            },
+                            Err(e) => CommandResult::Error(e),
+                        },
+                        ["list"] | [""] => {
+                            let entries = self.vault.list();
+                            if entries.is_empty() {
+                                CommandResult::Output("Vault is empty.".to_string())
+                            } else {
+                                CommandResult::Output(format!(
+                                    "Vault (encrypted):\r\n{}",
+                                    entries.join("\r\n")
+                                ))
+                            }
+                        }
+                        ["rm", name] => match self.vault.remove(name) {
+                            Ok(()) => {
+                                CommandResult::Output(format!("VAULT ENTRY '{}' ZEROIZED.", name))
+                            }
+                            Err(e) => CommandResult::Error(e),
+                        },
+                        _ => CommandResult::Output(
+                            "Usage: ::vault add <name> [secret] | get <name> | list | rm <name>"
+                                .to_string(),
+                        ),
+                    }
+                }
+                "note" => match args {
+                    "" => match read_note() {
+                        Ok(Some(mut text)) => {
+                            let result = self.note.append(&text);
+                            text.zeroize();
+                            match result {
+                                Ok(lines) => {
+                                    CommandResult::Output(format!("NOTE SAVED ({} lines).", lines))
+                                }
+                                Err(e) => CommandResult::Error(e),
+                            }
+                        }
+                        Ok(None) => CommandResult::Output("Note discarded.".to_string()),
+                        Err(e) => CommandResult::Error(format!("Input error: {}", e)),
+                    },
+                    "show" => match self.note.read() {
+                        Ok(Some(text)) => {
+                            let (lines, age) = self.note.describe().unwrap_or_default();
+                            CommandResult::Output(format!(
+                                "Note ({} lines, updated {}s ago):\r\n{}",
+                                lines,
+                                age,
+                                text.expose().replace('\n', "\r\n")
+                            ))
+                        }
+                        Ok(None) => CommandResult::Output("Note is empty.".to_string()),
+                        Err(e) => CommandResult::Error(e),
+                    },
+                    "burn" => {
+                        if self.note.burn() {
+                            CommandResult::Output("NOTE BURNED.".to_string())
+                        } else {
+                            CommandResult::Output("Note is empty.".to_string())
+                        }
+                    }
+                    _ => CommandResult::Output("Usage: ::note | ::note show | ::note burn".to_string()),
+                },
+                "totp" => match args.split_once(' ') {
+                    Some(("add", name)) if !name.is_empty() && !name.contains(' ') => {
+                        match read_secret("TOTP secret or otpauth:// URI: ") {
+                            Ok(Some(secret)) => {
+                                match encode_entry(secret.expose()) {
+                                    Ok(mut entry) => {
+                                        let result = vault_add(&mut self.vault, name, &entry);
+                                        entry.zeroize();
+                                        result
+                                    }
+                                    Err(e) => CommandResult::Error(e),
+                                }
+                            }
+                            Ok(None) => CommandResult::Output("Cancelled.".to_string()),
+                            Err(e) => CommandResult::Error(format!("Input error: {}", e)),
+                        }
+                    }
+                    None if !args.is_empty() && args != "add" => {
+                        let code = self.vault.get(args).and_then(|entry| current_code(entry.expose()));
+                        match code.and_then(|(code, remaining)| {
+                            self.secure_clipboard(false, None)?
+                                .copy_with_timeout(code, self.shell.clipboard_timeout)
+                                .map(|msg| (remaining, msg))
+                        }) {
+                            Ok((remaining, msg)) => CommandResult::Output(format!(
+                                "TOTP '{}' COPIED (valid {}s). {}",
+                                args, remaining, msg
+                            )),
+                            Err(e) => CommandResult::Error(e),
+                        }
+                    }
+                    _ => CommandResult::Output("Usage: ::totp add <name> | ::totp <name>".to_string()),
+                },
+                "ssh-add" => match args {
+                    "-l" => match &self.ssh_agent {
+                        Some(agent) if !agent.list().is_empty() => CommandResult::Output(format!(
+                            "Agent identities ({}):\r\n{}",
+                            agent.socket_path().display(),
+                            agent.list().join("\r\n")
+                        )),
+                        _ => CommandResult::Output("The agent has no identities.".to_string()),
+                    },
+                    "-D" => {
+                        let count = self.ssh_agent.as_ref().map_or(0, |agent| agent.remove_all());
+                        CommandResult::Output(format!("{} IDENTITIES ZEROIZED.", count))
+                    }
+                    "" => CommandResult::Output(
+                        "Usage: ::ssh-add <key file|vault signing key> | ::ssh-add -l | ::ssh-add -D"
+                            .to_string(),
+                    ),
+                    target => {
+                        let path = Path::new(target);
+                        let key = if path.is_file() {
+                            SshKey::from_file(path, || {
+                                match read_secret(&format!("Enter passphrase for {}: ", target)) {
+                                    Ok(Some(passphrase)) => Ok(passphrase),
+                                    Ok(None) => Err("Cancelled.".to_string()),
+                                    Err(e) => Err(format!("Input error: {}", e)),
+                                }
+                            })
+                        } else {
+                            signing_key(&self.vault, target)
+                                .map(|key| SshKey::from_seed(&mut key.to_bytes(), target))
+                        };
+                        let key = match key {
+                            Ok(key) => key,
+                            Err(e) => return CommandResult::Error(e),
+                        };
+
+                        if self.ssh_agent.is_none() {
+                            match SshAgent::start() {
+                                Ok(agent) => self.ssh_agent = Some(agent),
+                                Err(e) => return CommandResult::Error(e),
+                            }
+                        }
+                        match &self.ssh_agent {
+                            Some(agent) => {
+                                let public_line = key.public_line();
+                                let fingerprint = agent.add(key);
+                                CommandResult::Output(format!(
+                                    "IDENTITY ADDED: {}\r\n{}\r\nSSH_AUTH_SOCK={}",
+                                    fingerprint,
+                                    public_line,
+                                    agent.socket_path().display()
+                                ))
+                            }
+                            None => CommandResult::NoOp,
+                        }
+                    }
+                },
+                "touch-match" | "timestomp" => {
+                    let usage = if cmd == "timestomp" {
+                        "Usage: ::timestomp <target> <YYYY-MM-DD[ HH:MM[:SS]] | @unix seconds> (UTC)"
+                    } else {
+                        "Usage: ::touch-match <reference> <target>"
+                    };
+                    let Some((target, rest)) = args.split_once(' ') else {
+                        return CommandResult::Output(usage.to_string());
+                    };
+                    let (target, times) = if cmd == "timestomp" {
+                        (target, parse_datetime(rest).map(Timestamps::at))
+                    } else {
+                        (rest.trim(), Timestamps::of(Path::new(target)))
+                    };
+                    let times = match times {
+                        Ok(times) => times,
+                        Err(e) => return CommandResult::Error(e),
+                    };
+                    match apply(Path::new(target), &times) {
+                        Ok(birth_time) => CommandResult::Output(format!(
+                            "TIMESTAMPS SET: {}\r\n  accessed  {}\r\n  modified  {}\r\n  birth     {}\r\nNote: ctime (inode change) cannot be set and now reads the current time.",
+                            target,
+                            format_datetime(times.accessed),
+                            format_datetime(times.modified),
+                            match times.created {
+                                Some(created) if birth_time => format_datetime(created),
+                                _ => "unchanged (not settable on this platform)".to_string(),
+                            }
+                        )),
+                        Err(e) => CommandResult::Error(e),
+                    }
+                }
+                "out" => match args.split_once(" -- ") {
+                    // ::out [--age <age1...|-p>] <file> -- <command>
+                    Some((target, command_line)) if !command_line.trim().is_empty() => {
+                        self.capture_encrypted(command_line.trim(), target)
+                    }
+                    _ => CommandResult::Output(
+                        "Usage: ::out [--age <age1...[,age1...]|-p>] <file> -- <command>  (or: <command> ::> <file>)"
+                            .to_string(),
+                    ),
+                },
+                "wipe-free" => {
+                    // ::wipe-free [--rate <MiB/s>] <mountpoint>
+                    let usage = "Usage: ::wipe-free [--rate <MiB/s>] <mountpoint>";
+                    let (rate, target) = match strip_flag(args, "--rate") {
+                        Some(rest) => match rest.split_once(' ') {
+                            Some((rate, target)) => match rate.parse::<u64>() {
+                                Ok(rate) if rate > 0 => (Some(rate * 1024 * 1024), target.trim()),
+                                _ => return CommandResult::Output(usage.to_string()),
+                            },
+                            None => return CommandResult::Output(usage.to_string()),
+                        },
+                        None => (None, args),
+                    };
+                    if target.is_empty() {
+                        return CommandResult::Output(usage.to_string());
+                    }
+
+                    let mut stdout = term::stdout();
+                    let mut last_update: Option<std::time::Instant> = None;
+                    let result = wipe_free(Path::new(target), rate, |written, total| {
+                        if last_update.is_some_and(|at| at.elapsed().as_millis() < 250) {
+                            return true;
+                        }
+                        last_update = Some(std::time::Instant::now());
+                        let _ = write!(
+                            stdout,
+                            "\rWiping free space: {:3}% ({} / {} MiB)  Esc cancels ",
+                            written * 100 / total.max(1),
+                            written / 1024 / 1024,
+                            total / 1024 / 1024
+                        );
+                        let _ = stdout.flush();
+                        // Esc or Ctrl+C stops the wipe; the filler is removed either way
+                        !cancel_pressed()
+                    });
+                    let _ = write!(stdout, "\r\n");
+                    match result {
+                        Ok(summary) => CommandResult::Output(format!(
+                            "{}: {} MiB of random data written and deleted in {}s.",
+                            if summary.cancelled {
+                                "FREE-SPACE WIPE CANCELLED"
+                            } else {
+                                "FREE SPACE WIPED"
+                            },
+                            summary.written / 1024 / 1024,
+                            summary.elapsed.as_secs()
+                        )),
+                        Err(e) => CommandResult::Error(e),
+                    }
+                }
+                "scrub" => {
+                    if args.is_empty() {
+                        return CommandResult::Output(
+                            "Usage: ::scrub <file> [file...] (JPEG, PNG, WebP, PDF, Office/OpenDocument)"
+                                .to_string(),
+                        );
+                    }
+                    let lines: Vec<String> = args
+                        .split_whitespace()
+                        .map(|file| match scrub_file(Path::new(file)) {
+                            Ok(report) if report.removed.is_empty() => {
+                                format!("✓ {} ({}): no metadata found.", file, report.format)
+                            }
+                            Ok(report) => format!(
+                                "✓ {} ({}): removed {} ({} -> {} bytes)",
+                                file,
+                                report.format,
+                                report.removed.join(", "),
+                                report.before,
+                                report.after
+                            ),
+                            Err(e) => format!("✗ {}", e),
+                        })
+                        .collect();
+                    CommandResult::Output(lines.join("\r\n"))
+                }
+                "ephemeral" => match args {
+                    "on" if self.ephemeral.is_some() => {
+                        CommandResult::Output("Ephemeral home already active.".to_string())
+                    }
+                    "on" => match EphemeralHome::create() {
+                        Ok(ephemeral) => {
+                            let message = format!(
+                                "EPHEMERAL HOME ENABLED: {}\r\n\
+                                - Child commands get HOME, XDG_CACHE_HOME, XDG_DATA_HOME and XDG_STATE_HOME here\r\n\
+                                - Everything inside is shredded on ::ephemeral off or exit",
+                                ephemeral.path().display()
+                            );
+                            self.ephemeral = Some(ephemeral);
+                            CommandResult::Output(message)
+                        }
+                        Err(e) => CommandResult::Error(e),
+                    },
+                    "off" => match self.ephemeral.take() {
+                        Some(ephemeral) => CommandResult::Output(format!(
+                            "EPHEMERAL HOME WIPED: {} files shredded.",
+                            ephemeral.destroy()
+                        )),
+                        None => CommandResult::Output("No ephemeral home active.".to_string()),
+                    },
+                    _ => CommandResult::Output(format!(
+                        "Ephemeral home: {}\r\nUsage: ::ephemeral on|off",
+                        match &self.ephemeral {
+                            Some(ephemeral) => ephemeral.path().display().to_string(),
+                            None => "off".to_string(),
+                        }
+                    )),
+                },
+                "workspace" => match args {
+                    "" => match &self.workspace {
+                        Some(workspace) => CommandResult::Output(format!(
+                            "Workspace: {} ({})",
+                            workspace.path().display(),
+                            if workspace.is_mounted() {
+                                "dedicated tmpfs mount"
+                            } else {
+                                "directory on existing tmpfs"
+                            }
+                        )),
+                        None => CommandResult::Output(
+                            "No workspace. Use ::workspace create [dir].".to_string(),
+                        ),
+                    },
+                    "wipe" => match self.workspace.take() {
+                        Some(workspace) => CommandResult::Output(format!(
+                            "WORKSPACE WIPED: {} files shredded.",
+                            workspace.destroy()
+                        )),
+                        None => CommandResult::Output("No workspace to wipe.".to_string()),
+                    },
+                    _ => match args.split_once(' ').unwrap_or((args, "")) {
+                        ("create", _) if self.workspace.is_some() => CommandResult::Output(
+                            "A workspace is already active (::workspace wipe first).".to_string(),
+                        ),
+                        ("create", dir) => {
+                            let dir = dir.trim();
+                            let base = (!dir.is_empty()).then(|| Path::new(dir));
+                            match Workspace::create(base) {
+                                Ok(workspace) => {
+                                    let message = format!(
+                                        "WORKSPACE CREATED: {}\r\nTMPDIR points here; everything inside is shredded on ::workspace wipe or exit.",
+                                        workspace.path().display()
+                                    );
+                                    self.workspace = Some(workspace);
+                                    CommandResult::Output(message)
+                                }
+                                Err(e) => CommandResult::Error(e),
+                            }
+                        }
+                        _ => CommandResult::Output(
+                            "Usage: ::workspace create [dir] | ::workspace | ::workspace wipe"
+                                .to_string(),
+                        ),
+                    },
+                },
+                "split" => {
+                    // ::split <k>/<n> [--qr] [-v <vault-key> | <secret>]
+                    let usage = "Usage: ::split <k>/<n> [--qr] [-v <vault-key> | <secret>]";
+                    let (spec, rest) = args.split_once(' ').unwrap_or((args, ""));
+                    let Some((k, n)) = spec.split_once('/').and_then(|(k, n)| {
+                        Some((k.parse::<u8>().ok()?, n.parse::<u8>().ok()?))
+                    }) else {
+                        return CommandResult::Output(usage.to_string());
+                    };
+                    let (qr, rest) = match strip_flag(rest, "--qr") {
+                        Some(rest) => (true, rest),
+                        None => (false, rest),
+                    };
+
+                    let secret = match strip_flag(rest, "-v") {
+                        Some(name) if !name.is_empty() => self.vault.get(name),
+                        Some(_) => return CommandResult::Output(usage.to_string()),
+                        None if !rest.is_empty() => Ok(SecretString::new(rest.to_string())),
+                        None => match read_secret("Secret to split: ") {
+                            Ok(Some(secret)) => Ok(secret),
+                            Ok(None) => Err("Cancelled.".to_string()),
+                            Err(e) => Err(format!("Input error: {}", e)),
+                        },
+                    };
+                    let mut shares = match secret.and_then(|secret| split(secret.expose().as_bytes(), k, n)) {
+                        Ok(shares) => shares,
+                        Err(e) => return CommandResult::Error(e),
+                    };
+
+                    // Each share goes to its own encrypted slot, to be pushed to one custodian
+                    let mut output = format!(
+                        "SECRET SPLIT INTO {} SHARES (ANY {} RECOVER IT).\r\nStaged in encrypted slots share-1..share-{}; push each with ::paste -s share-<i>.",
+                        n, k, n
+                    );
+                    for (i, share) in shares.iter().enumerate() {
+                        if let Err(e) = self.clipboard_slots.store(&format!("share-{}", i + 1), share) {
+                            output = e;
+                            break;
+                        }
+                        if qr {
+                            match render_qr(share) {
+                                Ok(code) => {
+                                    output.push_str(&format!("\r\nShare {}/{}:\r\n{}", i + 1, n, code))
+                                }
+                                Err(e) => output.push_str(&format!("\r\n{}", e)),
+                            }
+                        }
+                    }
+                    shares.iter_mut().for_each(|share| share.zeroize());
+                    CommandResult::Output(output)
+                }
+                "combine" => {
+                    // ::combine [-v <vault-key>] [share...]; prompts for shares when none are given
+                    let (vault_name, rest) = match strip_flag(args, "-v") {
+                        Some(rest) => match rest.split_once(' ') {
+                            Some((name, rest)) => (Some(name), rest.trim_start()),
+                            None if !rest.is_empty() => (Some(rest), ""),
+                            None => {
+                                return CommandResult::Output(
+                                    "Usage: ::combine [-v <vault-key>] [share...]".to_string(),
+                                )
+                            }
+                        },
+                        None => (None, args),
+                    };
+
+                    let mut prompted = Vec::new();
+                    if rest.is_empty() {
+                        loop {
+                            match read_secret(&format!(
+                                "Share {} (empty line when done): ",
+                                prompted.len() + 1
+                            )) {
+                                Ok(Some(share)) if !share.expose().is_empty() => prompted.push(share),
+                                Ok(Some(_)) => break,
+                                Ok(None) => return CommandResult::Output("Cancelled.".to_string()),
+                                Err(e) => return CommandResult::Error(format!("Input error: {}", e)),
+                            }
+                        }
+                    }
+                    let shares: Vec<&str> = if rest.is_empty() {
+                        prompted.iter().map(|share| share.expose().as_str()).collect()
+                    } else {
+                        rest.split_whitespace().collect()
+                    };
+                    let count = shares.len();
+                    let recovered = combine(&shares).and_then(|bytes| {
+                        SecretBytes::new(bytes).into_text().map_err(|_| {
+                            "combine: recovered secret is not valid UTF-8.".to_string()
+                        })
+                    });
+                    drop(prompted);
+
+                    match (recovered, vault_name) {
+                        (Ok(secret), Some(name)) => vault_add(&mut self.vault, name, secret.expose()),
+                        (Ok(secret), None) => match self
+                            .secure_clipboard(false, None)
+                            .and_then(|clipboard| {
+                                clipboard.copy_with_timeout(secret, self.shell.clipboard_timeout)
+                            }) {
+                            Ok(msg) => CommandResult::Output(format!(
+                                "SECRET RECOVERED FROM {} SHARES. {}",
+                                count, msg
+                            )),
+                            Err(e) => CommandResult::Error(e),
+                        },
+                        (Err(e), _) => CommandResult::Error(e),
+                    }
+                }
+                "contacts" => {
+                    let contact_parts: Vec<&str> = args.split_whitespace().collect();
+                    match contact_parts.as_slice() {
+                        ["add", name, public_key] => match self.contacts.add(name, public_key) {
+                            Ok(true) => {
+                                CommandResult::Output(format!("CONTACT '{}' REPLACED.", name))
+                            }
+                            Ok(false) => CommandResult::Output(format!(
+                                "CONTACT '{}' ADDED. Use ::cp --to {} <text> to seal for them.",
+                                name, name
+                            )),
+                            Err(e) => CommandResult::Error(e),
+                        },
+                        ["rm", name] => match self.contacts.remove(name) {
+                            Ok(()) => CommandResult::Output(format!("CONTACT '{}' REMOVED.", name)),
+                            Err(e) => CommandResult::Error(e),
+                        },
+                        [] | ["list"] => {
+                            let contacts = self.contacts.list();
+                            if contacts.is_empty() {
+                                CommandResult::Output("No contacts.".to_string())
+                            } else {
+                                CommandResult::Output(format!(
+                                    "Contacts (X25519):\r\n{}",
+                                    contacts.join("\r\n")
+                                ))
+                            }
+                        }
+                        _ => CommandResult::Output(
+                            "Usage: ::contacts [list] | ::contacts add <name> <age1...|base64 key> | ::contacts rm <name>"
+                                .to_string(),
+                        ),
+                    }
+                }
+                "age-keygen" => {
+                    // New X25519 identity, kept in the vault for ::paste / ::decrypt-file.
+                    // -o also writes it as an age-keygen style identity file (0600).
+                    let output_path = match strip_flag(args, "-o") {
+                        Some(path) if !path.is_empty() => Some(Path::new(path)),
+                        None if args.is_empty() => None,
+                        _ => return CommandResult::Output("Usage: ::age-keygen [-o <file>]".to_string()),
+                    };
+
+                    let identity = AgeIdentity::generate();
+                    let recipient = identity.recipient();
+                    let mut encoded = identity.encode();
+                    if let Some(path) = output_path {
+                        let mut contents = format!("# public key: {}\n{}\n", recipient, encoded);
+                        let written = write_private_file(path, contents.as_bytes());
+                        contents.zeroize();
+                        if let Err(e) = written {
+                            encoded.zeroize();
+                            return CommandResult::Error(e);
+                        }
+                    }
+                    let stored = self.vault.add(AGE_IDENTITY_ENTRY, &encoded);
+                    encoded.zeroize();
+
+                    match stored {
+                        Ok(replaced) => CommandResult::Output(format!(
+                            "AGE IDENTITY STORED IN VAULT AS '{}'{}.\r\nRecipient: {}",
+                            AGE_IDENTITY_ENTRY,
+                            if replaced { " (PREVIOUS ONE REPLACED)" } else { "" },
+                            recipient
+                        )),
+                        Err(e) => CommandResult::Error(e),
+                    }
+                }
+                "type" => {
+                    if args.is_empty() {
+                        CommandResult::Output("Usage: ::type <slot|text>".to_string())
+                    } else {
+                        match detect_keystroke_backend() {
+                            Some(backend) => {
+                                // A staged slot by that name wins over literal text
+                                let text = self
+                                    .clipboard_slots
+                                    .get(args)
+                                    .unwrap_or_else(|_| SecretString::new(args.to_string()));
+                                let mut stdout = term::stdout();
+                                let _ = write!(
+                                    stdout,
+                                    "Focus the target window. Typing via {} in {}s...\r\n",
+                                    backend.name(),
+                                    TYPE_DELAY_SECS
+                                );
+                                let _ = stdout.flush();
+                                match type_text(backend, text.expose()) {
+                                    Ok(()) => CommandResult::Output(
+                                        "SECRET TYPED. Clipboard untouched.".to_string(),
+                                    ),
+                                    Err(e) => CommandResult::Error(e),
+                                }
+                            }
+                            None => CommandResult::Output(
+                                "type: needs wtype (Wayland), xdotool (X11) or ydotool (uinput)."
+                                    .to_string(),
+                            ),
+                        }
+                    }
+                }
+                "share" => {
+                    let share_parts: Vec<&str> = args.split_whitespace().collect();
+                    match share_parts.as_slice() {
+                        ["send", target, slot @ ..] if slot.len() <= 1 => {
+                            // A staged slot, or whatever is on the clipboard now
+                            let payload = match slot.first() {
+                                Some(name) => self.clipboard_slots.get(name),
+                                None => self
+                                    .shell
+                                    .clipboard()
+                                    .and_then(|clipboard| clipboard.get_text())
+                                    .map(SecretString::new),
+                            };
+                            match payload {
+                                Ok(payload) => {
+                                    match crate::share::send(target, payload.expose()) {
+                                        Ok(code) => CommandResult::Output(format!(
+                                            "PAYLOAD SHARED WITH {}.\r\nVerification code: {} (must match the receiver)",
+                                            target, code
+                                        )),
+                                        Err(e) => CommandResult::Error(e),
+                                    }
+                                }
+                                Err(e) => CommandResult::Error(e),
+                            }
+                        }
+                        ["recv", bind @ ..] if bind.len() <= 1 => {
+                            let bind = bind
+                                .first()
+                                .map(|b| b.to_string())
+                                .unwrap_or_else(|| format!(":{}", crate::share::SHARE_PORT));
+                            let mut stdout = term::stdout();
+                            let _ = write!(
+                                stdout,
+                                "Waiting for ::share send on {} ({}s)...\r\n",
+                                bind,
+                                crate::share::ACCEPT_TIMEOUT.as_secs()
+                            );
+                            let _ = stdout.flush();
+                            match crate::share::receive(&bind) {
+                                Ok((payload, code)) => {
+                                    // Staged, not pushed: the clipboard is only touched on request
+                                    match self.clipboard_slots.store("shared", payload.expose()) {
+                                        Ok(()) => CommandResult::Output(format!(
+                                            "RECEIVED INTO ENCRYPTED SLOT 'shared'. Use ::paste -s shared to push it.\r\n\
+                                            Verification code: {} (must match the sender)",
+                                            code
+                                        )),
+                                        Err(e) => CommandResult::Error(e),
+                                    }
+                                }
+                                Err(e) => CommandResult::Error(e),
+                            }
+                        }
+                        _ => CommandResult::Output(
+                            "Usage: ::share send <host[:port]|socket> [slot] | ::share recv [addr:port|:port|socket]"
+                                .to_string(),
+                        ),
+                    }
+                }
+                "send" => {
+                    if args.is_empty() {
+                        CommandResult::Output("Usage: ::send <file>".to_string())
+                    } else {
+                        let code = crate::wormhole::generate_code();
+                        let mut stdout = term::stdout();
+                        let _ = write!(
+                            stdout,
+                            "Transfer code: {}\r\nOn the other machine: ::recv {}\r\nWaiting for the receiver ({}s, Esc cancels)...\r\n",
+                            code,
+                            code,
+                            crate::wormhole::SEND_TIMEOUT.as_secs()
+                        );
+                        let _ = stdout.flush();
+                        match crate::wormhole::send_file(
+                            Path::new(args),
+                            &code,
+                            crate::wormhole::WORMHOLE_PORT,
+                            cancel_pressed,
+                        ) {
+                            Ok(bytes) => {
+                                CommandResult::Output(format!("FILE SENT ({} bytes).", bytes))
+                            }
+                            Err(e) => CommandResult::Error(e),
+                        }
+                    }
+                }
+                "recv" => {
+                    let recv_parts: Vec<&str> = args.split_whitespace().collect();
+                    match recv_parts.as_slice() {
+                        [code, peer @ ..] if peer.len() <= 1 => {
+                            let dir = env::current_dir().unwrap_or_else(|_| Path::new(".").to_path_buf());
+                            match crate::wormhole::receive_file(code, peer.first().copied(), &dir)
+                            {
+                                Ok((path, bytes)) => CommandResult::Output(format!(
+                                    "FILE RECEIVED: {} ({} bytes).",
+                                    path.display(),
+                                    bytes
+                                )),
+                                Err(e) => CommandResult::Error(e),
+                            }
+                        }
+                        _ => CommandResult::Output(
+                            "Usage: ::recv <code> [host[:port]]".to_string(),
+                        ),
+                    }
+                }
+                "resolve" => {
+                    let resolve_parts: Vec<&str> = args.split_whitespace().collect();
+                    let (name, url) = match resolve_parts.as_slice() {
+                        [name] => (*name, crate::dns::DEFAULT_SERVER),
+                        [name, "--doh", url] | ["--doh", url, name] => (*name, *url),
+                        _ => {
+                            return CommandResult::Output(
+                                "Usage: ::resolve <name|ip> [--doh https://host/dns-query|tls://host[:853]]"
+                                    .to_string(),
+                            )
+                        }
+                    };
+                    let server = match crate::dns::Server::parse(url) {
+                        Ok(server) => server,
+                        Err(e) => return CommandResult::Error(e),
+                    };
+                    match crate::dns::resolve(name, &server, self.proxy.as_ref()) {
+                        Ok(records) if records.is_empty() => CommandResult::Output(format!(
+                            "{}: no records ({} via {}).",
+                            name,
+                            server.protocol(),
+                            url
+                        )),
+                        Ok(records) => {
+                            let mut output =
+                                format!("{} ({} via {}):", name, server.protocol(), url);
+                            for record in records {
+                                output.push_str(&format!(
+                                    "\r\n  {:<6} {}  (TTL {}s)",
+                                    record.kind, record.value, record.ttl
+                                ));
+                            }
+                            CommandResult::Output(output)
+                        }
+                        Err(e) => CommandResult::Error(e),
+                    }
+                }
+                "decrypt" => {
+                    if args.is_empty() {
+                        CommandResult::Output("Usage: ::decrypt <key> | ::decrypt -p".to_string())
+                    } else if self.refuse_remote_plaintext
+                        && ssh_session().is_some()
+                        && !args.contains(" -o ")
+                    {
+                        CommandResult::Error(
+                            "REFUSED OVER SSH: ::decrypt would print the plaintext to a remote display. \
+                            Write it to a file with ::decrypt <key> -o <file>, or set ssh.refuse_plaintext = false."
+                                .to_string(),
+                        )
+                    } else if args == "-p" {
+                        match read_secret("Passphrase: ") {
+                            Ok(Some(passphrase)) => {
+                                match self.shell.clipboard().and_then(|clipboard| {
+                                    clipboard.decrypt_clipboard_with_passphrase(passphrase.expose())
+                                }) {
+                                    Ok(plaintext) => CommandResult::Output(format!(
+                                        "Decrypted: {}",
+                                        plaintext.expose()
+                                    )),
+                                    Err(e) => CommandResult::Error(e),
+                                }
+                            }
+                            Ok(None) => CommandResult::Output("Cancelled.".to_string()),
+                            Err(e) => CommandResult::Error(format!("Input error: {}", e)),
+                        }
+                    } else {
+                        // ::decrypt <key> [-o <file>]; -o is for binary payloads
+                        let (key, output_path) = match args.split_once(" -o ") {
+                            Some((key, path)) => (key.trim(), Some(Path::new(path.trim()))),
+                            None => (args, None),
+                        };
+                        match self.shell.clipboard() {
+                            Ok(clipboard) => match clipboard.inspect() {
+                                Ok(ClipboardPayload::Binary) => {
+                                    self.decrypt_binary(&clipboard, key, output_path)
+                                }
+                                _ => match clipboard.decrypt_clipboard(key) {
+                                    Ok(plaintext) => CommandResult::Output(format!(
+                                        "Decrypted: {}",
+                                        plaintext.expose()
+                                    )),
+                                    Err(e) => CommandResult::Error(e),
+                                },
+                            },
+                            Err(e) => CommandResult::Error(e),
+                        }
+                    }
+                }
+                "encrypt-file" => {
+                    // ::encrypt-file [--shred] [--age <age1...[,age1...]|-p> | --gpg <recipient>] <path>
+                    let mut shred = false;
+                    let mut age_spec = None;
+                    let mut gpg_recipient = None;
+                    let mut path = args;
+                    loop {
+                        if let Some(rest) = strip_flag(path, "--shred") {
+                            shred = true;
+                            path = rest;
+                        } else if let Some(rest) = strip_flag(path, "--age") {
+                            let (spec, rest) = rest.split_once(' ').unwrap_or((rest, ""));
+                            age_spec = Some(spec);
+                            path = rest.trim_start();
+                        } else if let Some(rest) = strip_flag(path, "--gpg") {
+                            let (recipient, rest) = rest.split_once(' ').unwrap_or((rest, ""));
+                            gpg_recipient = Some(recipient);
+                            path = rest.trim_start();
+                        } else {
+                            break;
+                        }
+                    }
+                    if path.is_empty()
+                        || age_spec == Some("")
+                        || gpg_recipient == Some("")
+                        || (age_spec.is_some() && gpg_recipient.is_some())
+                    {
+                        return CommandResult::Output(
+                            "Usage: ::encrypt-file [--shred] [--age <age1...[,age1...]|-p> | --gpg <recipient>] <path>"
+                                .to_string(),
+                        );
+                    }
+
+                    let path = Path::new(path);
+                    let result = match (age_spec, gpg_recipient) {
+                        (_, Some(recipient)) => encrypt_file_gpg(path, recipient),
+                        (Some(spec), _) if spec != "-p" => parse_age_recipients(spec)
+                            .and_then(|recipients| encrypt_file_age(path, &recipients)),
+                        _ => prompt_new_passphrase().and_then(|passphrase| {
+                            if age_spec.is_some() {
+                                encrypt_file_age(path, &Recipients::Passphrase(passphrase.expose()))
+                            } else {
+                                encrypt_file(path, passphrase.expose())
+                            }
+                        }),
+                    };
+                    match result {
+                        Ok(output) if shred => match shred_file(path) {
+                            Ok(()) => CommandResult::Output(format!(
+                                "FILE ENCRYPTED: {}\r\nORIGINAL SHREDDED.",
+                                output.display()
+                            )),
+                            Err(e) => CommandResult::Output(format!(
+                                "FILE ENCRYPTED: {}\r\nShred failed: {}",
+                                output.display(),
+                                e
+                            )),
+                        },
+                        Ok(output) => CommandResult::Output(format!(
+                            "FILE ENCRYPTED: {}\r\nOriginal kept; use --shred to remove it.",
+                            output.display()
+                        )),
+                        Err(e) => CommandResult::Error(e),
+                    }
+                }
+                "decrypt-file" => {
+                    // ::decrypt-file [-i <identity file>] <path>; the format is detected
+                    let (identity_file, path) = match strip_flag(args, "-i") {
+                        Some(rest) => match rest.split_once(' ') {
+                            Some((file, path)) => (Some(Path::new(file)), path.trim_start()),
+                            None => (None, ""),
+                        },
+                        None => (None, args),
+                    };
+                    if path.is_empty() {
+                        return CommandResult::Output(
+                            "Usage: ::decrypt-file [-i <identity file>] <path>".to_string(),
+                        );
+                    }
+
+                    let path = Path::new(path);
+                    let result = match detect_format(path) {
+                        Ok(FileFormat::Age { passphrase: false }) => {
+                            age_identities(&self.vault, identity_file).and_then(|identities| {
+                                decrypt_file_age(path, &Unlock::Identities(&identities))
+                            })
+                        }
+                        // gpg-agent asks for the passphrase itself
+                        Ok(FileFormat::Gpg) => decrypt_file_gpg(path),
+                        Ok(format) => match read_secret("Passphrase: ") {
+                            Ok(Some(passphrase)) => match format {
+                                    FileFormat::Ghost => decrypt_file(path, passphrase.expose()),
+                                    _ => decrypt_file_age(path, &Unlock::Passphrase(passphrase.expose())),
+                                },
+                            Ok(None) => Err("Cancelled.".to_string()),
+                            Err(e) => Err(format!("Input error: {}", e)),
+                        },
+                        Err(e) => Err(e),
+                    };
+                    match result {
+                        Ok(output) => {
+                            CommandResult::Output(format!("FILE DECRYPTED: {}", output.display()))
+                        }
+                        Err(e) => CommandResult::Error(e),
+                    }
+                }
+                "hash" => {
+                    // ::hash <algo> <file|text> | ::hash verify <algo> <expected> <file|text>
+                    let (expected, rest) = match strip_flag(args, "verify") {
+                        Some(rest) => match rest.split_once(' ') {
+                            Some((algorithm, rest)) => match rest.split_once(' ') {
+                                Some((expected, target)) => {
+                                    (Some(expected), format!("{} {}", algorithm, target))
+                                }
+                                None => (Some(""), String::new()),
+                            },
+                            None => (Some(""), String::new()),
+                        },
+                        None => (None, args.to_string()),
+                    };
+                    let parsed = rest.split_once(' ').and_then(|(algorithm, target)| {
+                        HashAlgorithm::parse(algorithm).map(|algorithm| (algorithm, target))
+                    });
+                    let (algorithm, target) = match parsed {
+                        Some((algorithm, target)) if !target.is_empty() && expected != Some("") => {
+                            (algorithm, target)
+                        }
+                        _ => {
+                            return CommandResult::Output(
+                                "Usage: ::hash sha256|sha512|blake3 <file|text> | ::hash verify <algo> <expected> <file|text>"
+                                    .to_string(),
+                            )
+                        }
+                    };
+
+                    match digest_target(algorithm, target) {
+                        Ok((digest, label)) => match expected {
+                            None => CommandResult::Output(format!("{}  {}", to_hex(&digest), label)),
+                            Some(expected) => match digest_matches(&digest, expected) {
+                                Ok(true) => CommandResult::Output(format!(
+                                    "✓ {} MATCHES: {}",
+                                    algorithm.name(),
+                                    label
+                                )),
+                                Ok(false) => CommandResult::Output(format!(
+                                    "✗ {} MISMATCH: {}",
+                                    algorithm.name(),
+                                    label
+                                )),
+                                Err(e) => CommandResult::Error(e),
+                            },
+                        },
+                        Err(e) => CommandResult::Error(e),
+                    }
+                }
+                "hmac" => {
+                    // ::hmac <vault-key> <file|text> | ::hmac verify <vault-key> <expected> <file|text>
+                    let usage = "Usage: ::hmac <vault-key> <file|text> | ::hmac verify <vault-key> <expected> <file|text>";
+                    if let Some(rest) = strip_flag(args, "verify") {
+                        let mut parts = rest.splitn(3, ' ');
+                        let (Some(key_name), Some(expected), Some(target)) =
+                            (parts.next(), parts.next(), parts.next())
+                        else {
+                            return CommandResult::Output(usage.to_string());
+                        };
+                        if target.is_empty() {
+                            return CommandResult::Output(usage.to_string());
+                        }
+                        return match hmac_verify(&self.vault, key_name, expected, target) {
+                            Ok((true, label)) => {
+                                CommandResult::Output(format!("✓ HMAC MATCHES: {}", label))
+                            }
+                            Ok((false, label)) => {
+                                CommandResult::Output(format!("✗ HMAC MISMATCH: {}", label))
+                            }
+                            Err(e) => CommandResult::Error(e),
+                        };
+                    }
+
+                    match args.split_once(' ') {
+                        Some((key_name, target)) if !target.is_empty() => {
+                            match hmac_target(&self.vault, key_name, target) {
+                                Ok((tag, label)) => {
+                                    CommandResult::Output(format!("{}  {}", to_hex(&tag), label))
+                                }
+                                Err(e) => CommandResult::Error(e),
+                            }
+                        }
+                        _ => CommandResult::Output(usage.to_string()),
+                    }
+                }
+                "sign" => {
+                    // ::sign keygen <name> | ::sign pubkey <name> | ::sign <name> <file|text>
+                    let usage = "Usage: ::sign keygen <name> | ::sign pubkey <name> | ::sign <name> <file|text>";
+                    if let Some(name) = strip_flag(args, "keygen") {
+                        if name.is_empty() || name.contains(' ') {
+                            return CommandResult::Output(usage.to_string());
+                        }
+                        let name = name.to_string();
+                        return match generate_signing_key(&mut self.vault, &name) {
+                            Ok((public_key, replaced)) => CommandResult::Output(format!(
+                                "SIGNING KEY {} '{}'\r\nPublic key: {}",
+                                if replaced { "REPLACED" } else { "STORED" },
+                                name,
+                                public_key
+                            )),
+                            Err(e) => CommandResult::Error(e),
+                        };
+                    }
+                    if let Some(name) = strip_flag(args, "pubkey") {
+                        if name.is_empty() {
+                            return CommandResult::Output(usage.to_string());
+                        }
+                        return match public_key(&self.vault, name) {
+                            Ok(public_key) => CommandResult::Output(public_key),
+                            Err(e) => CommandResult::Error(e),
+                        };
+                    }
+
+                    match args.split_once(' ') {
+                        Some((name, target)) if !target.is_empty() => {
+                            match sign_target(&self.vault, name, target) {
+                                Ok((signature, label)) => {
+                                    CommandResult::Output(format!("{}  {}", signature, label))
+                                }
+                                Err(e) => CommandResult::Error(e),
+                            }
+                        }
+                        _ => CommandResult::Output(usage.to_string()),
+                    }
+                }
+                "verify" => {
+                    // ::verify <pubkey|vault-key> <signature> <file|text>
+                    let mut parts = args.splitn(3, ' ');
+                    match (parts.next(), parts.next(), parts.next()) {
+                        (Some(signer), Some(signature), Some(target)) if !target.is_empty() => {
+                            match verify_target(&self.vault, signer, signature, target) {
+                                Ok((true, label)) => {
+                                    CommandResult::Output(format!("✓ SIGNATURE VALID: {}", label))
+                                }
+                                Ok((false, label)) => {
+                                    CommandResult::Output(format!("✗ SIGNATURE INVALID: {}", label))
+                                }
+                                Err(e) => CommandResult::Error(e),
+                            }
+                        }
+                        _ => CommandResult::Output(
+                            "Usage: ::verify <pubkey|vault-key> <signature> <file|text>".to_string(),
+                        ),
+                    }
+                }
+                "anti-debug" => {
+                    if is_debugger_present() {
+                        if self.paranoid_mode {
+                            // Auto-panic in paranoid mode
+                            emergency_shutdown(
+                                self.theme,
+                                &["DEBUGGER DETECTED - PARANOID MODE ACTIVE".to_string()],
+                            );
+                        } else {
+                            CommandResult::Output("⚠ WARNING: DEBUGGER DETECTED!".to_string())
+                        }
+                    } else {
+                        CommandResult::Output("✓ No debugger detected.".to_string())
+                    }
+                }
+                "paranoid" => {
+                    if args == "on" {
+                        self.set_paranoid(true);
+                        CommandResult::Output(format!(
+                            "⚠ PARANOID MODE ENABLED\r\n\
+                            - Auto-panic on debugger detection\r\n\
+                            - Periodic security checks every {} commands\r\n\
+                            - Own sockets watched for unexpected connections\r\n\
+                            - Enhanced threat monitoring",
+                            self.check_interval
+                        ))
+                    } else if args == "off" {
+                        self.set_paranoid(false);
+                        CommandResult::Output("PARANOID MODE DISABLED".to_string())
+                    } else {
+                        CommandResult::Output(format!(
+                            "Paranoid mode: {}\r\nUsage: ::paranoid on|off",
+                            if self.paranoid_mode {
+                                "ENABLED"
+                            } else {
+                                "DISABLED"
+                            }
+                        ))
+                    }
+                }
+                "readonly" => match args {
+                    "on" => {
+                        self.readonly = true;
+                        CommandResult::Output(
+                            "READ-ONLY MODE ENABLED\r\n\
+                            - Writes, deletes, installs and network pushes are refused\r\n\
+                            - Inspection commands run as usual\r\n\
+                            - ::readonly off allows changes again"
+                                .to_string(),
+                        )
+                    }
+                    "off" => {
+                        self.readonly = false;
+                        CommandResult::Output("READ-ONLY MODE DISABLED".to_string())
+                    }
+                    _ => CommandResult::Output(format!(
+                        "Read-only mode: {}\r\nUsage: ::readonly on|off",
+                        if self.readonly { "ENABLED" } else { "DISABLED" }
+                    )),
+                },
+                "direct" => match args {
+                    "on" => {
+                        self.direct_exec = true;
+                        CommandResult::Output(
+                            "DIRECT EXEC ENABLED\r\n\
+                            - Commands are parsed here and exec'd without $SHELL -c\r\n\
+                            - Quotes, ~, $NAME and globs work; pipes, redirections and ; are refused\r\n\
+                            - ::direct off runs them through the shell again"
+                                .to_string(),
+                        )
+                    }
+                    "off" => {
+                        self.direct_exec = false;
+                        CommandResult::Output("DIRECT EXEC DISABLED".to_string())
+                    }
+                    _ => CommandResult::Output(format!(
+                        "Direct exec: {}\r\nUsage: ::direct on|off",
+                        if self.direct_exec { "ENABLED" } else { "DISABLED" }
+                    )),
+                },
+                "profile" => match SecurityProfile::parse(args) {
+                    Some(profile) => CommandResult::Output(self.apply_profile(profile)),
+                    None => CommandResult::Output(format!(
+                        "Security profile: {}\r\n{}\r\nUsage: ::profile standard|paranoid|stealth",
+                        self.profile.name(),
+                        SecurityProfile::ALL
+                            .iter()
+                            .map(|profile| format!("  {:<9} {}", profile.name(), profile.description()))
+                            .collect::<Vec<_>>()
+                            .join("\r\n")
+                    )),
+                },
+                "theme" => match Theme::parse(args) {
+                    Some(theme) => {
+                        self.theme = theme;
+                        CommandResult::Output(format!("THEME SET: {}.", theme.name()))
+                    }
+                    None => CommandResult::Output(format!(
+                        "Theme: {}\r\n{}\r\nUsage: ::theme default|matrix|mono|high-contrast",
+                        self.theme.name(),
+                        Theme::ALL
+                            .iter()
+                            .map(|theme| format!(
+                                "  {:<13} {}",
+                                theme.name(),
+                                theme.description()
+                            ))
+                            .collect::<Vec<_>>()
+                            .join("\r\n")
+                    )),
+                },
+                "isolate" => {
+                    if args.is_empty() {
+                        CommandResult::Output("Usage: ::isolate <command>".to_string())
+                    } else {
+                        let shell = env::var("SHELL").unwrap_or_else(|_| "/bin/sh".to_string());
+                        match isolated_command(&shell, args) {
+                            Ok(mut command) => self.child_output_result(command.output()),
+                            Err(e) => CommandResult::Error(format!("isolate: {}", e)),
+                        }
+                    }
+                }
+                "trace" if args.is_empty() => {
+                    CommandResult::Output("Usage: ::trace <command>".to_string())
+                }
+                "trace" => self.trace_command(args),
+                "torcheck" => {
+                    let address = (!args.is_empty()).then_some(args);
+                    CommandResult::Output(self.tor_check(address))
+                }
+                "proxy" => {
+                    let (sub, sub_args) = args.split_once(' ').unwrap_or((args, ""));
+                    let sub_args = sub_args.trim();
+                    let proxy = match sub {
+                        "set" => Proxy::parse(sub_args),
+                        "tor" => Proxy::tor(Some(sub_args).filter(|a| !a.is_empty())),
+                        "off" => {
+                            self.proxy = None;
+                            return CommandResult::Output(
+                                "PROXY DISABLED. Commands connect directly.".to_string(),
+                            );
+                        }
+                        "check" => {
+                            return CommandResult::Output(match &self.proxy {
+                                Some(proxy) => self.proxy_leak_check(proxy),
+                                None => "No proxy set. Use ::proxy set <url> or ::proxy tor."
+                                    .to_string(),
+                            })
+                        }
+                        "" => {
+                            return CommandResult::Output(match &self.proxy {
+                                Some(proxy) => format!(
+                                    "Proxy: {}\r\nPreload: {}\r\n\
+                                    Usage: ::proxy set <url> | ::proxy tor [host:port] | ::proxy check | ::proxy off",
+                                    proxy.url(),
+                                    proxy
+                                        .preload()
+                                        .map(|(preload, _)| preload.name())
+                                        .unwrap_or("none (proxy variables only)")
+                                ),
+                                None => "Proxy: off\r\n\
+                                    Usage: ::proxy set <url> | ::proxy tor [host:port] | ::proxy check | ::proxy off"
+                                    .to_string(),
+                            })
+                        }
+                        _ => Err(
+                            "Usage: ::proxy set <url> | ::proxy tor [host:port] | ::proxy check | ::proxy off"
+                                .to_string(),
+                        ),
+                    };
+                    match proxy {
+                        Ok(proxy) => {
+                            let message = match proxy.preload() {
+                                Some((preload, _)) => format!(
+                                    "PROXY SET: {} (via {}). Run ::proxy check to test for leaks.",
+                                    proxy.url(),
+                                    preload.name()
+                                ),
+                                None => format!(
+                                    "PROXY SET: {}\r\n⚠ torsocks/proxychains-ng not found: only tools \
+                                    honoring ALL_PROXY and HTTP(S)_PROXY are routed.",
+                                    proxy.url()
+                                ),
+                            };
+                            self.proxy = Some(proxy);
+                            CommandResult::Output(message)
+                        }
+                        Err(e) => CommandResult::Error(e),
+                    }
+                }
+                "sandbox" => {
+                    let sub_parts: Vec<&str> = args.splitn(2, ' ').collect();
+                    let sub_args = sub_parts.get(1).map(|a| a.trim()).unwrap_or("");
+                    let backend = detect_backend();
+                    match sub_parts[0] {
+                        "profile" => match SandboxProfile::parse(sub_args) {
+                            Some(profile) => {
+                                self.sandbox_profile = profile;
+                                match (profile, backend) {
+                                    (SandboxProfile::Off, _) => CommandResult::Output(
+                                        "SANDBOX DISABLED. Commands run directly.".to_string(),
+                                    ),
+                                    (_, Some(backend)) => CommandResult::Output(format!(
+                                        "SANDBOX PROFILE '{}' ACTIVE via {}.",
+                                        profile.name(),
+                                        backend.name()
+                                    )),
+                                    (_, None) => CommandResult::Output(format!(
+                                        "⚠ SANDBOX PROFILE '{}' SET, but neither bwrap nor firejail is installed.\r\n\
+                                        Commands will run unsandboxed until one is available.",
+                                        profile.name()
+                                    )),
+                                }
+                            }
+                            None => CommandResult::Output(
+                                "Usage: ::sandbox profile off|standard|strict".to_string(),
+                            ),
+                        },
+                        "run" if !sub_args.is_empty() => match backend {
+                            Some(_) => {
+                                let profile = match self.sandbox_profile {
+                                    SandboxProfile::Off => SandboxProfile::Standard,
+                                    profile => profile,
+                                };
+                                match self.child_command(sub_args, profile) {
+                                    Ok(mut command) => self.child_output_result(command.output()),
+                                    Err(e) => CommandResult::Error(e),
+                                }
+                            }
+                            None => CommandResult::Output(
+                                "sandbox: neither bwrap nor firejail is installed.".to_string(),
+                            ),
+                        },
+                        "" => CommandResult::Output(format!(
+                            "Sandbox profile: {}\r\nBackend: {}\r\n\
+                            Usage: ::sandbox profile off|standard|strict | ::sandbox run <command>",
+                            self.sandbox_profile.name(),
+                            backend.map(|b| b.name()).unwrap_or("none (install bwrap or firejail)")
+                        )),
+                        _ => CommandResult::Output(
+                            "Usage: ::sandbox profile off|standard|strict | ::sandbox run <command>"
+                                .to_string(),
+                        ),
+                    }
+                }
+                "run" if !args.is_empty() => {
+                    let path = args.trim().to_string();
+                    match run_script(Path::new(&path), self) {
+                        Ok(()) => CommandResult::Output(format!("SCRIPT {} COMPLETE.", path)),
+                        Err(e) => CommandResult::Error(format!("Script error: {}", e)),
+                    }
+                }
+                "run" => CommandResult::Output("Usage: ::run <script.rhai>".to_string()),
+                "plugins" => {
+                    let plugins = list_plugins();
+                    if plugins.is_empty() {
+                        CommandResult::Output(format!(
+                            "No plugins installed. Plugins are executables named {}<name> \
+                            in ~/.config/gsh/plugins or on PATH.",
+                            crate::plugin::PLUGIN_PREFIX
+                        ))
+                    } else {
+                        let mut output = String::from("Plugins:\r\n");
+                        for (name, path) in plugins {
+                            output.push_str(&format!("  ::{:<16} {}\r\n", name, path.display()));
+                        }
+                        CommandResult::Output(output.trim_end().to_string())
+                    }
+                }
+                _ => match find_plugin(cmd) {
+                    // `cmd` and `args` borrow the input line; the plugin host needs &mut self
+                    Some(path) => {
+                        let (name, args) = (cmd.to_string(), args.to_string());
+                        match run_plugin(&name, &path, &args, self) {
+                            Ok(output) if output.is_empty() => CommandResult::NoOp,
+                            Ok(output) => CommandResult::Output(output),
+                            Err(e) => CommandResult::Error(format!("Plugin error: {}", e)),
+                        }
+                    }
+                    None => {
+                        let plugins: Vec<String> =
+                            list_plugins().into_iter().map(|(name, _)| name).collect();
+                        let hint = did_you_mean(&suggest(cmd, &plugins));
+                        CommandResult::Output(if hint.is_empty() {
+                            format!("Unknown GHOST command: '{}'. Type ::help for the list.", cmd)
+                        } else {
+                            format!("Unknown GHOST command: '{}'. {}", cmd, hint)
+                        })
+                    }
+                },
+            }
+        } else {
+            // `<command> ::> <file>`: keep only an encrypted copy of the output
+            if let Some((command_line, target)) = trimmed_command.rsplit_once(" ::> ") {
+                return self.capture_encrypted(command_line.trim(), target);
+            }
+
+            // Built-in: cd
+            let parts: Vec<&str> = trimmed_command.splitn(2, ' ').collect();
+            if parts[0] == "cd" {
+                let path_str = parts.get(1).unwrap_or(&"~");
+                let path = match *path_str {
+                    "~" => env::var("HOME").unwrap_or_else(|_| "/".to_string()),
+                    _ => path_str.to_string(),
+                };
+                match env::set_current_dir(&path) {
+                    Ok(_) => return CommandResult::NoOp,
+                    Err(e) => return CommandResult::Output(format!("cd: {}", e)),
+                }
+            }
+
+            // Built-in: clear (standard shell alias)
+            if parts[0] == "clear" {
+                let _ = sanitize_screen(&mut term::stdout());
+                let _ = execute!(term::stdout(), SetTitle(&self.title));
+                return CommandResult::NoOp;
+            }
+
+            // Built-in: plain ls, cat, head and grep, unless a sandbox profile confines them
+            #[cfg(unix)]
+            if self.sandbox_profile == SandboxProfile::Off {
+                if let Some(captured) = builtins::run(trimmed_command) {
+                    self.last_status.set(Some(captured.status));
+                    return CommandResult::Output(child_output(&captured.stdout, &captured.stderr));
+                }
+            }
+
+            match self.external_command(trimmed_command) {
+                // Without a shell nobody else says so
+                Ok(mut command) if self.direct_exec => {
+                    let program = command.get_program().to_string_lossy().into_owned();
+                    match command.output() {
+                        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                            self.last_status.set(Some(127));
+                            CommandResult::Output(format!("{}: command not found", program))
+                        }
+                        output => self.child_output_result(output),
+                    }
+                }
+                Ok(mut command) => self.child_output_result(command.output()),
+                Err(e) => {
+                    self.last_status.set(Some(2));
+                    CommandResult::Error(e)
+                }
+            }
+        }
+    }
+
+    /// ::trace: run `command_line` and list the files it changed outside ::workspace,
+    /// ::ephemeral and the config's `trace.allow` patterns
+    fn trace_command(&self, command_line: &str) -> CommandResult {
+        let Some(backend) = trace::detect_backend() else {
+            return CommandResult::Output(
+                "trace: needs strace installed, or root for fanotify.".to_string(),
+            );
+        };
+        let command = match self.external_command(command_line) {
+            Ok(command) => command,
+            Err(e) => {
+                self.last_status.set(Some(2));
+                return CommandResult::Error(e);
+            }
+        };
+        let (output, trace) = match trace::run(backend, command) {
+            Ok(traced) => traced,
+            Err(e) => return CommandResult::Error(e),
+        };
+        self.last_status.set(Some(exit_code(output.status)));
+
+        let mut allowed = self.trace_allow.clone();
+        let scratch = [
+            self.workspace.as_ref().map(|workspace| workspace.path()),
+            self.ephemeral.as_ref().map(|ephemeral| ephemeral.path()),
+        ];
+        allowed.extend(
+            scratch
+                .iter()
+                .flatten()
+                .map(|dir| format!("{}/*", dir.display())),
+        );
+        let (changes, hidden) = trace.outside(&allowed);
+        let mut report = vec![if changes.is_empty() {
+            format!(
+                "✓ TRACE ({}): no files changed outside the allowed paths.",
+                backend.name()
+            )
+        } else {
+            format!(
+                "⚠ TRACE ({}): {} paths changed outside the allowed paths:",
+                backend.name(),
+                changes.len()
+            )
+        }];
+        for (path, change) in changes {
+            report.push(match change {
+                Change::Renamed(from) => format!(
+                    "  {:<11} {} (from {})",
+                    change.label(),
+                    path.display(),
+                    from.display()
+                ),
+                change => format!("  {:<11} {}", change.label(), path.display()),
+            });
+        }
+        if hidden > 0 {
+            report.push(format!("  ({} more inside allowed paths)", hidden));
+        }
+        if backend == TraceBackend::Fanotify {
+            report.push(
+                "  fanotify only sees writes; install strace to see removals and renames too."
+                    .to_string(),
+            );
+        }
+
+        let mut result = child_output(
+            &String::from_utf8_lossy(&output.stdout),
+            &String::from_utf8_lossy(&output.stderr),
+        );
+        if !result.is_empty() {
+            result.push_str("\r\n");
+        }
+        result.push_str(&report.join("\r\n"));
+        CommandResult::Output(result)
+    }
+
+    /// Decrypt a binary clipboard container: images go back on the clipboard,
+    /// other bytes are written to `output_path`
+    fn decrypt_binary(
+        &self,
+        clipboard: &SecureClipboard,
+        key: &str,
+        output_path: Option<&Path>,
+    ) -> CommandResult {
+        let data = match clipboard.decrypt_clipboard_data(key) {
+            Ok(data) => data,
+            Err(e) => return CommandResult::Error(e),
+        };
+        let result = match (&data, output_path) {
+            (ClipboardData::Image { .. }, None) => {
+                clipboard.restore_image(&data, self.shell.clipboard_timeout)
+            }
+            (ClipboardData::Bytes(bytes), Some(path)) => {
+                write_private_file(path, bytes).map(|()| {
+                    format!(
+                        "{} WRITTEN TO {} (mode 600).",
+                        data.describe(),
+                        path.display()
+                    )
+                })
+            }
+            (ClipboardData::Bytes(_), None) => Err(format!(
+                "Binary payload ({}). Usage: ::decrypt <key> -o <file>",
+                data.describe()
+            )),
+            (ClipboardData::Image { .. }, Some(_)) => Err(
+                "Image payload: use ::decrypt <key> to restore it to the clipboard.".to_string(),
+            ),
+        };
+        match result {
+            Ok(msg) => CommandResult::Output(msg),
+            Err(e) => CommandResult::Error(e),
+        }
+    }
+
+    /// Build the command that runs `command_line`, wrapped by the active sandbox
+    /// profile when one is set, with the config's `env.<command>` changes for the
+    /// programs it runs, and routed through the ::proxy
+    fn external_command(&self, command_line: &str) -> Result<Command, String> {
+        let mut command = self.child_command(command_line, self.sandbox_profile)?;
+        let programs = programs(command_line);
+        for (program, entry) in &self.env_overrides {
+            if programs.contains(program) {
+                entry.apply(&mut command);
+            }
+        }
+        if let Some(proxy) = &self.proxy {
+            proxy.apply(&mut command);
+        }
+        Ok(command)
+    }
+
+    /// `command_line` run through $SHELL, or parsed and exec'd directly under
+    /// ::direct, inside the `profile` sandbox when a backend is installed
+    fn child_command(
+        &self,
+        command_line: &str,
+        profile: SandboxProfile,
+    ) -> Result<Command, String> {
+        let (assignments, argv) = if self.direct_exec {
+            let parsed = parse_direct(command_line)?;
+            (parsed.env, parsed.args)
+        } else {
+            let shell = env::var("SHELL").unwrap_or_else(|_| "/bin/sh".to_string());
+            (
+                Vec::new(),
+                vec![shell, "-c".to_string(), command_line.to_string()],
+            )
+        };
+        let mut command = match (profile, detect_backend()) {
+            (SandboxProfile::Off, _) | (_, None) => {
+                let mut command = Command::new(&argv[0]);
+                command.args(&argv[1..]);
+                // Sandboxes mount a throwaway HOME of their own
+                if let Some(ephemeral) = &self.ephemeral {
+                    command.envs(ephemeral.variables());
+                }
+                command
+            }
+            (profile, Some(backend)) => {
+                let argv: Vec<&str> = argv.iter().map(String::as_str).collect();
+                sandboxed_command(backend, profile, &argv)
+            }
+        };
+        command.envs(assignments);
+        Ok(command)
+    }
+
+    /// ::proxy check: the proxy's exit address against the one a child reaches the net as
+    fn proxy_leak_check(&self, proxy: &Proxy) -> String {
+        let mut lines = Vec::new();
+        let exit = match proxy.exit_address() {
+            Ok((ip, tor)) => {
+                lines.push(format!(
+                    "✓ Proxy exit address: {}{}",
+                    ip,
+                    if tor { " (Tor)" } else { "" }
+                ));
+                if proxy.tor && !tor {
+                    lines.push("✗ Exit is not a Tor relay.".to_string());
+                }
+                ip
+            }
+            Err(e) => return format!("✗ Proxy unreachable: {}", e),
+        };
+
+        match proxy.preload() {
+            Some((preload, path)) => lines.push(format!(
+                "✓ {} preloaded into children ({})",
+                preload.name(),
+                path.display()
+            )),
+            None => lines.push(
+                "⚠ No torsocks/proxychains-ng library found: only tools honoring ALL_PROXY \
+                and HTTP(S)_PROXY are covered."
+                    .to_string(),
+            ),
+        }
+
+        let Some(check) = check_command() else {
+            lines.push("⚠ curl not installed; child traffic not tested.".to_string());
+            return lines.join("\r\n");
+        };
+        let child = self
+            .external_command(&check)
+            .and_then(|mut command| command.output().map_err(|e| e.to_string()))
+            .and_then(|output| {
+                parse_check_response(&String::from_utf8_lossy(&output.stdout)).map(|(ip, _)| ip)
+            });
+        lines.push(match child {
+            Ok(ip) if ip == exit => format!("✓ Child traffic exits at {} too. NO LEAK.", ip),
+            Ok(ip) => format!("✗ LEAK DETECTED: child traffic exits at {}.", ip),
+            Err(e) => format!("⚠ Child check failed: {}", e),
+        });
+        lines.join("\r\n")
+    }
+
+    /// ::torcheck: find a Tor SOCKS port, then ask check.torproject.org through it
+    fn tor_check(&self, address: Option<&str>) -> String {
+        let candidates: Vec<String> = match (address, &self.proxy) {
+            (Some(address), _) => vec![address.to_string()],
+            (None, Some(proxy)) if proxy.tor => vec![format!("{}:{}", proxy.host, proxy.port)],
+            (None, _) => vec![TOR_SOCKS.to_string(), TOR_BROWSER_SOCKS.to_string()],
+        };
+        let mut lines = Vec::new();
+        let mut tor = None;
+        for candidate in &candidates {
+            let proxy = match Proxy::tor(Some(candidate)) {
+                Ok(proxy) => proxy,
+                Err(e) => return e,
+            };
+            match proxy.handshake() {
+                Ok(elapsed) => {
+                    lines.push(format!(
+                        "✓ Tor SOCKS port {} is up ({}ms)",
+                        candidate,
+                        elapsed.as_millis()
+                    ));
+                    tor = Some(proxy);
+                    break;
+                }
+                Err(e) => lines.push(format!("✗ {}: {}", candidate, e)),
+            }
+        }
+        let Some(tor) = tor else {
+            lines.push("No Tor SOCKS port reachable. Is tor running?".to_string());
+            return lines.join("\r\n");
+        };
+
+        match tor.exit_address() {
+            Ok((ip, true)) => {
+                lines.push(format!("✓ Exit IP: {}", ip));
+                lines.push("✓ check.torproject.org: this traffic is using Tor.".to_string());
+            }
+            Ok((ip, false)) => {
+                lines.push(format!("✗ Exit IP: {}", ip));
+                lines.push("✗ check.torproject.org: this traffic is NOT using Tor.".to_string());
+            }
+            Err(e) => lines.push(format!("✗ check.torproject.org unreachable: {}", e)),
+        }
+        lines.push(match &self.proxy {
+            Some(proxy) if proxy.tor && proxy.host == tor.host && proxy.port == tor.port => {
+                "✓ ::proxy routes child commands through this port.".to_string()
+            }
+            _ => format!(
+                "⚠ Child commands are not routed through Tor. Use ::proxy tor {}:{}",
+                tor.host, tor.port
+            ),
+        });
+        lines.join("\r\n")
+    }
+
+    /// Run `command_line` and write its stdout/stderr only as ciphertext to the file in
+    /// `target` (`[--age <age1...|-p>] <file>`); the output is never shown. Without
+    /// `--age` the file uses the `.ghost` passphrase format of ::encrypt-file.
+    fn capture_encrypted(&self, command_line: &str, target: &str) -> CommandResult {
+        let target = target.trim();
+        let (age_spec, path) = match strip_flag(target, "--age") {
+            Some(rest) => match rest.split_once(' ') {
+                Some((spec, path)) => (Some(spec), path.trim()),
+                None => (Some(rest), ""),
+            },
+            None => (None, target),
+        };
+        if path.is_empty() || command_line.is_empty() {
+            return CommandResult::Output(
+                "Usage: <command> ::> [--age <age1...[,age1...]|-p>] <file>".to_string(),
+            );
+        }
+        let path = Path::new(path);
+        if path.exists() {
+            return CommandResult::Output(format!("{}: already exists.", path.display()));
+        }
+        let mut command = match self.external_command(command_line) {
+            Ok(command) => command,
+            Err(e) => return CommandResult::Error(e),
+        };
+
+        // Ask for keys before running anything, so a typo doesn't run the command twice
+        let recipients = match age_spec {
+            Some(spec) if spec != "-p" => match parse_age_recipients(spec) {
+                Ok(recipients) => Some(recipients),
+                Err(e) => return CommandResult::Error(e),
+            },
+            _ => None,
+        };
+        let passphrase = match recipients {
+            Some(_) => SecretString::default(),
+            None => match prompt_new_passphrase() {
+                Ok(passphrase) => passphrase,
+                Err(e) => return CommandResult::Error(e),
+            },
+        };
+
+        let output = command.stdin(Stdio::null()).output();
+        let result = match output {
+            Ok(mut output) => {
+                let mut captured = std::mem::take(&mut output.stdout);
+                if !output.stderr.is_empty() {
+                    captured.extend_from_slice(b"STDERR:\n");
+                    captured.extend_from_slice(&output.stderr);
+                    output.stderr.zeroize();
+                }
+                let written = match (&recipients, age_spec) {
+                    (Some(recipients), _) => write_age_file(path, recipients, &captured),
+                    (None, Some(_)) => write_age_file(
+                        path,
+                        &Recipients::Passphrase(passphrase.expose()),
+                        &captured,
+                    ),
+                    (None, None) => write_ghost_file(path, passphrase.expose(), &captured),
+                };
+                let size = captured.len();
+                captured.zeroize();
+                written.map(|()| {
+                    format!(
+                        "OUTPUT ENCRYPTED: {} ({} bytes, {})\r\nRead it with ::decrypt-file {}",
+                        path.display(),
+                        size,
+                        output.status,
+                        path.display()
+                    )
+                })
+            }
+            Err(e) => Err(format!("Failed to execute process: {}", e)),
+        };
+        match result {
+            Ok(msg) => CommandResult::Output(msg),
+            Err(e) => CommandResult::Error(e),
+        }
+    }
+
+    /// Run `command_line` with `input` fed to its stdin
+    fn run_with_stdin(&self, command_line: &str, input: &[u8]) -> CommandResult {
+        let mut command = match self.external_command(command_line) {
+            Ok(command) => command,
+            Err(e) => return CommandResult::Error(e),
+        };
+        let child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn();
+
+        match child {
+            Ok(mut child) => {
+                if let Some(mut stdin) = child.stdin.take() {
+                    let _ = stdin.write_all(input);
+                }
+                self.child_output_result(child.wait_with_output())
+            }
+            Err(e) => CommandResult::Error(format!("Failed to execute process: {}\r\n", e)),
+        }
+    }
+
+    /// Turn a finished child into output, recording its exit status for the history
+    fn child_output_result(&self, output: io::Result<Output>) -> CommandResult {
+        match output {
+            Ok(output) => {
+                self.last_status.set(Some(exit_code(output.status)));
+                CommandResult::Output(child_output(
+                    &String::from_utf8_lossy(&output.stdout),
+                    &String::from_utf8_lossy(&output.stderr),
+                ))
+            }
+            Err(e) => CommandResult::Error(format!("Failed to execute process: {}\r\n", e)),
+        }
+    }
+}
+
+/// The host interface plugins get: the auto-clearing clipboard and the vault
+impl PluginHost for SecureBuffer {
+    fn copy(&mut self, plugin: &str, text: String) -> Result<String, String> {
+        let msg = self
+            .secure_clipboard(false, None)?
+            .copy_with_timeout(text, self.shell.clipboard_timeout)?;
+        Ok(format!("{} COPIED. {}", plugin.to_uppercase(), msg))
+    }
+
+    fn vault_get(&mut self, plugin: &str, name: &str) -> Result<String, String> {
+        let prompt = format!(
+            "Plugin '{}' wants vault entry '{}'. Allow? [y/N] ",
+            plugin, name
+        );
+        match confirm(&prompt) {
+            // The plugin protocol carries it as plain text from here
+            Ok(true) => self.vault.get(name).map(SecretString::into_exposed),
+            Ok(false) => Err("denied by user".to_string()),
+            Err(e) => Err(format!("input error: {}", e)),
+        }
+    }
+
+    fn vault_put(&mut self, _plugin: &str, name: &str, secret: &str) -> Result<(), String> {
+        self.vault.add(name, secret).map(|_| ())
+    }
+}
+
+/// The shell state ::run scripts can drive
+impl ScriptHost for SecureBuffer {
+    fn run_line(&mut self, line: &str) -> Result<String, String> {
+        let command = line.trim();
+        if command == "::run" || command.starts_with("::run ") {
+            return Err("scripts cannot start other scripts".to_string());
+        }
+        let mut typed = std::mem::replace(&mut self.content, command.to_string());
+        let result = self.process_command();
+        self.content.zeroize();
+        self.content = std::mem::take(&mut typed);
+        match result {
+            CommandResult::Output(output)
+            | CommandResult::Error(output)
+            | CommandResult::Insert(output) => Ok(output),
+            CommandResult::NoOp => Ok(String::new()),
+            CommandResult::Exit => Err("::exit is not available in scripts".to_string()),
+        }
+    }
+
+    fn copy(&mut self, text: String) -> Result<String, String> {
+        self.secure_clipboard(false, None)?
+            .copy_with_timeout(text, self.shell.clipboard_timeout)
+    }
+
+    fn vault_get(&mut self, name: &str) -> Result<String, String> {
+        // Script values are plain strings, scrubbed when the script thread ends
+        self.vault.get(name).map(SecretString::into_exposed)
+    }
+
+    fn vault_put(&mut self, name: &str, secret: &str) -> Result<(), String> {
+        self.vault.add(name, secret).map(|_| ())
+    }
+
+    fn print(&mut self, text: &str) {
+        let mut stdout = term::stdout();
+        let _ = write!(stdout, "{}\r\n", text.replace('\n', "\r\n"));
+        let _ = stdout.flush();
+    }
+}
+
+/// Store a secret for ::vault add
+fn vault_add(vault: &mut Vault, name: &str, secret: &str) -> CommandResult {
+    match vault.add(name, secret) {
+        Ok(true) => CommandResult::Output(format!("VAULT ENTRY '{}' REPLACED.", name)),
+        Ok(false) => CommandResult::Output(format!(
+            "STORED IN VAULT AS '{}'. Use ::vault get {} to copy it.",
+            name, name
+        )),
+        Err(e) => CommandResult::Error(e),
+    }
+}
+
+/// Read the clipboard for ::paste and decrypt it: with `key` for key-protected
+/// payloads, prompting for the passphrase when needed, or as-is when plain
+fn paste_plaintext(
+    clipboard: Result<SecureClipboard, String>,
+    key: Option<&str>,
+    vault: &Vault,
+) -> Result<SecretString, String> {
+    let clipboard = clipboard?;
+
+    if let Some(key) = key {
+        return clipboard.decrypt_clipboard(key);
+    }
+
+    match clipboard.inspect()? {
+        ClipboardPayload::Plain(text) => Ok(SecretString::new(text)),
+        ClipboardPayload::KeyProtected => {
+            Err("Clipboard payload is key-protected. Usage: ::paste <key>".to_string())
+        }
+        ClipboardPayload::Binary => {
+            Err("Clipboard holds an encrypted binary payload. Use ::decrypt <key>.".to_string())
+        }
+        ClipboardPayload::PassphraseProtected => match read_secret("Passphrase: ") {
+            Ok(Some(passphrase)) => {
+                clipboard.decrypt_clipboard_with_passphrase(passphrase.expose())
+            }
+            Ok(None) => Err("Cancelled.".to_string()),
+            Err(e) => Err(format!("Input error: {}", e)),
+        },
+        ClipboardPayload::Age { passphrase: true } => match read_secret("Passphrase: ") {
+            Ok(Some(passphrase)) => {
+                clipboard.decrypt_clipboard_age(&Unlock::Passphrase(passphrase.expose()))
+            }
+            Ok(None) => Err("Cancelled.".to_string()),
+            Err(e) => Err(format!("Input error: {}", e)),
+        },
+        ClipboardPayload::Age { passphrase: false } => {
+            let identities = age_identities(vault, None)?;
+            clipboard.decrypt_clipboard_age(&Unlock::Identities(&identities))
+        }
+        ClipboardPayload::Gpg => clipboard.decrypt_clipboard_gpg(),
+    }
+}
+
+/// Parse a comma-separated list of `age1...` recipients
+fn parse_age_recipients(spec: &str) -> Result<Recipients<'static>, String> {
+    spec.split(',')
+        .map(parse_recipient)
+        .collect::<Result<Vec<_>, _>>()
+        .map(Recipients::Keys)
+}
+
+/// age identities from an identity file, or the one `::age-keygen` stored in the vault
+fn age_identities(vault: &Vault, identity_file: Option<&Path>) -> Result<Vec<AgeIdentity>, String> {
+    let text = match identity_file {
+        Some(path) => SecretString::new(
+            fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?,
+        ),
+        None => vault.get(AGE_IDENTITY_ENTRY).map_err(|_| {
+            "No age identity in the vault. Run ::age-keygen or pass -i <identity file>.".to_string()
+        })?,
+    };
+    parse_identities(text.expose())
+}
+
+/// A child's stdout, then its stderr under a `STDERR:` heading
+fn child_output(stdout: &str, stderr: &str) -> String {
+    let mut result = String::new();
+    if !stdout.is_empty() {
+        result.push_str(stdout);
+    }
+    if !stderr.is_empty() {
+        if !result.is_empty() {
+            result.push_str("\r\n");
+        }
+        result.push_str("STDERR:\r\n");
+        result.push_str(stderr);
+    }
+    result.replace("\n", "\r\n")
+}
+
+/// Exit code, or 128 + the signal number for a killed child, as shells report it
+fn exit_code(status: ExitStatus) -> i32 {
+    #[cfg(unix)]
+    if let Some(signal) = std::os::unix::process::ExitStatusExt::signal(&status) {
+        return 128 + signal;
+    }
+    status.code().unwrap_or(-1)
+}
+
+/// One `::history` listing line: number, time, exit status and the command
+fn history_line(index: usize, entry: &HistoryEntry) -> String {
+    let time = entry.time.map_or_else(|| "-".to_string(), format_datetime);
+    let status = match entry.status {
+        Some(0) => "✓".to_string(),
+        Some(code) => format!("✗ {}", code),
+        None => "-".to_string(),
+    };
+    entry.command.expose(|command| {
+        format!(
+            "  {:>4}  {}  {:<5} {}\r\n",
+            index + 1,
+            time,
+            status,
+            command
+        )
+    })
+}
+
+// --- UTILS ---
+
+/// Read a secret from the keyboard without echoing anything.
+/// Returns None if the user cancels with Esc or Ctrl+C.
+pub fn read_secret(prompt: &str) -> io::Result<Option<SecretString>> {
+    let mut stdout = term::stdout();
+    write!(stdout, "{}", prompt)?;
+    stdout.flush()?;
+
+    // Pre-allocate so typing doesn't leave reallocated copies behind
+    let mut typed = SecretString::new(String::with_capacity(256));
+    let secret = typed.expose_mut();
+    loop {
+        match term::read_event()? {
+            // A pasted passphrase ends at its first line break, which is not Enter
+            Event::Paste(mut pasted) => {
+                secret.extend(pasted.chars().take_while(|c| !matches!(c, '\r' | '\n')));
+                pasted.zeroize();
+            }
+            Event::Key(KeyEvent {
+                code, modifiers, ..
+            }) => match code {
+                KeyCode::Enter => break,
+                KeyCode::Esc => {
+                    write!(stdout, "\r\n")?;
+                    return Ok(None);
+                }
+                KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => {
+                    write!(stdout, "^C\r\n")?;
+                    return Ok(None);
+                }
+                KeyCode::Backspace => {
+                    secret.pop();
+                }
+                KeyCode::Char(c) => secret.push(c),
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+
+    write!(stdout, "\r\n")?;
+    Ok(Some(typed))
+}
+
+/// Whether Esc or Ctrl+C is waiting in the input queue (for long-running commands)
+fn cancel_pressed() -> bool {
+    while term::poll_event(std::time::Duration::ZERO).unwrap_or(false) {
+        if let Ok(Event::Key(KeyEvent {
+            code, modifiers, ..
+        })) = term::read_event()
+        {
+            if code == KeyCode::Esc
+                || (code == KeyCode::Char('c') && modifiers.contains(KeyModifiers::CONTROL))
+            {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+/// Ask a yes/no question; only `y` answers yes
+pub fn confirm(prompt: &str) -> io::Result<bool> {
+    let mut stdout = term::stdout();
+    write!(stdout, "{}", prompt)?;
+    stdout.flush()?;
+
+    loop {
+        if let Event::Key(KeyEvent { code, .. }) = term::read_event()? {
+            let allowed = matches!(code, KeyCode::Char('y') | KeyCode::Char('Y'));
+            write!(stdout, "{}\r\n", if allowed { "y" } else { "n" })?;
+            return Ok(allowed);
+        }
+    }
+}
+
+/// Wait for one key; only Ctrl+O answers yes, so a reflexive `y` never overrides
+fn override_pressed(prompt: &str) -> io::Result<bool> {
+    let mut stdout = term::stdout();
+    write!(stdout, "{}", prompt)?;
+    stdout.flush()?;
+
+    loop {
+        if let Event::Key(KeyEvent {
+            code, modifiers, ..
+        }) = term::read_event()?
+        {
+            let allowed = code == KeyCode::Char('o') && modifiers.contains(KeyModifiers::CONTROL);
+            write!(stdout, "{}\r\n", if allowed { "^O" } else { "n" })?;
+            return Ok(allowed);
+        }
+    }
+}
+
+/// Apply the policy rule matching a command line: a deny rule refuses it unless the
+/// operator presses Ctrl+O, a confirm rule asks first. None lets the line run.
+fn enforce_policy(rule: &PolicyRule) -> Option<CommandResult> {
+    let allowed = match rule.action {
+        PolicyAction::Deny => override_pressed(&format!(
+            "✗ DENIED BY POLICY ({}). Ctrl+O runs it anyway, any other key cancels: ",
+            rule.pattern
+        )),
+        PolicyAction::Confirm => confirm(&format!(
+            "⚠ POLICY ({}) asks before running this. Run it? [y/N] ",
+            rule.pattern
+        )),
+    };
+    match allowed {
+        Ok(true) => None,
+        Ok(false) => Some(CommandResult::Error(format!(
+            "BLOCKED BY POLICY: {}",
+            rule.pattern
+        ))),
+        Err(e) => Some(CommandResult::Error(format!("Policy prompt failed: {}", e))),
+    }
+}
+
+/// Multi-line input with echo for ::note: Enter starts a new line, Ctrl+D saves,
+/// Esc or Ctrl+C discards
+fn read_note() -> io::Result<Option<String>> {
+    let mut stdout = term::stdout();
+    write!(stdout, "NOTE (Ctrl+D to save, Esc to discard)\r\n| ")?;
+    stdout.flush()?;
+
+    // Pre-allocate so typing doesn't leave reallocated copies behind
+    let mut text = String::with_capacity(4096);
+    loop {
+        match term::read_event()? {
+            Event::Paste(mut pasted) => {
+                let mut chars = pasted.chars().peekable();
+                while let Some(c) = chars.next() {
+                    match c {
+                        '\r' if chars.peek() == Some(&'\n') => {}
+                        '\r' | '\n' => {
+                            text.push('\n');
+                            write!(stdout, "\r\n| ")?;
+                        }
+                        c => {
+                            text.push(c);
+                            write!(stdout, "{}", c)?;
+                        }
+                    }
+                }
+                pasted.zeroize();
+            }
+            Event::Key(KeyEvent {
+                code, modifiers, ..
+            }) => match code {
+                KeyCode::Char('d') if modifiers.contains(KeyModifiers::CONTROL) => break,
+                KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => {
+                    text.zeroize();
+                    write!(stdout, "^C\r\n")?;
+                    return Ok(None);
+                }
+                KeyCode::Esc => {
+                    text.zeroize();
+                    write!(stdout, "\r\n")?;
+                    return Ok(None);
+                }
+                KeyCode::Enter => {
+                    text.push('\n');
+                    write!(stdout, "\r\n| ")?;
+                }
+                // Only edit within the current line
+                KeyCode::Backspace if !text.is_empty() && !text.ends_with('\n') => {
+                    text.pop();
+                    write!(stdout, "\x08 \x08")?;
+                }
+                KeyCode::Char(c) => {
+                    text.push(c);
+                    write!(stdout, "{}", c)?;
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+        stdout.flush()?;
+    }
+
+    write!(stdout, "\r\n")?;
+    // Drop trailing empty lines
+    let kept = text.trim_end_matches('\n').len();
+    text[kept..].zeroize();
+    text.truncate(kept);
+    if text.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(text))
+}
+
+/// If `args` starts with option `flag`, return what follows it
+fn strip_flag<'a>(args: &'a str, flag: &str) -> Option<&'a str> {
+    match args.strip_prefix(flag) {
+        Some(rest) if rest.is_empty() || rest.starts_with(' ') => Some(rest.trim_start()),
+        _ => None,
+    }
+}
+
+/// Ask for a new passphrase twice and make sure both entries match
+fn prompt_new_passphrase() -> Result<SecretString, String> {
+    let first = match read_secret("New passphrase: ") {
+        Ok(Some(p)) if !p.expose().is_empty() => p,
+        Ok(Some(_)) => return Err("Error: Empty passphrase.".to_string()),
+        Ok(None) => return Err("Cancelled.".to_string()),
+        Err(e) => return Err(format!("Input error: {}", e)),
+    };
+
+    let second = match read_secret("Confirm passphrase: ") {
+        Ok(Some(p)) => p,
+        Ok(None) => return Err("Cancelled.".to_string()),
+        Err(e) => return Err(format!("Input error: {}", e)),
+    };
+
+    if first.expose() == second.expose() {
+        Ok(first)
+    } else {
+        Err("Error: Passphrases do not match.".to_string())
+    }
+}
+
+/// Kill the background jobs the shell's commands left in its process group and every
+/// process descended from the shell, deepest first so none is re-parented away before
+/// its turn (Linux, read from /proc). The group is only touched when the shell leads
+/// it, so a parent that shares it, like a script running gsh, is never signalled.
+fn kill_children() {
+    #[cfg(target_os = "linux")]
+    {
+        let own = std::process::id() as i32;
+        let leader = unsafe { libc::getpgrp() } == own;
+        // (pid, parent, process group) of every process
+        let mut processes: Vec<(i32, i32, i32)> = Vec::new();
+        if let Ok(entries) = fs::read_dir("/proc") {
+            for entry in entries.flatten() {
+                let Some(pid) = entry.file_name().to_str().and_then(|pid| pid.parse().ok()) else {
+                    continue;
+                };
+                // State, parent and group follow the command name, which may contain spaces
+                let ids = fs::read_to_string(entry.path().join("stat"))
+                    .ok()
+                    .and_then(|stat| {
+                        let (_, rest) = stat.rsplit_once(')')?;
+                        let mut fields = rest.split_whitespace().skip(1);
+                        Some((fields.next()?.parse().ok()?, fields.next()?.parse().ok()?))
+                    });
+                if let Some((parent, group)) = ids {
+                    processes.push((pid, parent, group));
+                }
+            }
+        }
+        let mut targets = vec![own];
+        let mut next = 0;
+        while next < targets.len() {
+            let parent = targets[next];
+            targets.extend(
+                processes
+                    .iter()
+                    .filter(|(_, ppid, _)| *ppid == parent)
+                    .map(|(pid, _, _)| *pid),
+            );
+            next += 1;
+        }
+        if leader {
+            for (pid, _, group) in &processes {
+                if *group == own && !targets.contains(pid) {
+                    targets.push(*pid);
+                }
+            }
+        }
+        for pid in targets.into_iter().skip(1).rev() {
+            unsafe { libc::kill(pid, libc::SIGKILL) };
+        }
+    }
+}
+
+/// Paranoid-mode response to a detected threat: wipe the screen, name the threats and
+/// exit with 137 as if killed
+pub fn emergency_shutdown(theme: Theme, alerts: &[String]) -> ! {
+    let mut stdout = term::stdout();
+    let _ = execute!(
+        stdout,
+        DisableBracketedPaste,
+        Print(RESET_SCROLL_REGION),
+        Clear(ClearType::All),
+        MoveTo(0, 0),
+        SetTitle(""),
+        Print(POP_TITLE)
+    );
+    for alert in alerts {
+        let _ = write!(stdout, "{}\r\n", theme.alert(alert));
+    }
+    let _ = write!(
+        stdout,
+        "{}\r\n",
+        theme.paint(
+            Role::Alert,
+            "PARANOID MODE - INITIATING EMERGENCY SHUTDOWN..."
+        )
+    );
+    let _ = stdout.flush();
+    std::thread::sleep(std::time::Duration::from_millis(500));
+    term::exit(137);
+}
+
+/// Save the terminal's window title on its title stack and show `title` instead, so
+/// screenshots and window-manager logs see something routine
+pub fn mask_title(stdout: &mut TermOutput, title: &str) -> io::Result<()> {
+    execute!(stdout, Print(PUSH_TITLE), SetTitle(title))
+}
+
+/// Bring back the title saved by `mask_title`. Terminals without a title stack keep
+/// the blank title `sanitize_screen` leaves.
+pub fn restore_title(stdout: &mut TermOutput) -> io::Result<()> {
+    execute!(stdout, Print(POP_TITLE))
+}
+
+/// Wipe the visible screen, the terminal scrollback buffer (CSI 3 J) and the
+/// window title so previous output can't be scrolled back to. Also releases the
+/// status bar row so the terminal is left scrolling normally.
+pub fn sanitize_screen(stdout: &mut TermOutput) -> io::Result<()> {
+    execute!(
+        stdout,
+        Print(RESET_SCROLL_REGION),
+        Clear(ClearType::All),
+        Clear(ClearType::Purge),
+        SetTitle(""),
+        MoveTo(0, 0)
+    )
+}
@@ -0,0 +1,162 @@
+/// Message authentication for ::hmac, ::sign and ::verify
+/// HMAC-SHA256 and Ed25519 detached signatures over a file or text. Secret keys only
+/// ever live in the session vault and are zeroized right after each use.
+use base64::{engine::general_purpose, Engine as _};
+use chacha20poly1305::aead::OsRng;
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use sha2::Sha256;
+use std::path::Path;
+use zeroize::Zeroize;
+
+use crate::hash::{feed_target, from_hex};
+use crate::vault::Vault;
+
+/// Prefix marking an Ed25519 signing key stored in the vault
+const SIGNING_KEY_PREFIX: &str = "ed25519:";
+
+/// Largest file ::sign / ::verify will read (Ed25519 needs the whole message)
+const MAX_SIGNED_SIZE: u64 = 64 * 1024 * 1024;
+
+/// HMAC-SHA256 of `target` under the vault secret `key_name`. Returns the tag and a label.
+pub fn hmac_target(
+    vault: &Vault,
+    key_name: &str,
+    target: &str,
+) -> Result<(Vec<u8>, String), String> {
+    let key = vault.get(key_name)?;
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(key.expose().as_bytes())
+        .map_err(|_| "Invalid HMAC key.".to_string())?;
+
+    let label = feed_target(target, |data| mac.update(data))?;
+    Ok((mac.finalize().into_bytes().to_vec(), label))
+}
+
+/// Check `target` against an expected hex HMAC tag in constant time
+pub fn hmac_verify(
+    vault: &Vault,
+    key_name: &str,
+    expected_hex: &str,
+    target: &str,
+) -> Result<(bool, String), String> {
+    let expected = from_hex(expected_hex).ok_or("Expected tag is not valid hex.")?;
+    let key = vault.get(key_name)?;
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(key.expose().as_bytes())
+        .map_err(|_| "Invalid HMAC key.".to_string())?;
+
+    let label = feed_target(target, |data| mac.update(data))?;
+    Ok((mac.verify_slice(&expected).is_ok(), label))
+}
+
+/// Generate an Ed25519 key, store it in the vault as `name` and return the base64 public key.
+/// The bool is true if an existing entry was replaced.
+pub fn generate_signing_key(vault: &mut Vault, name: &str) -> Result<(String, bool), String> {
+    let mut seed = [0u8; 32];
+    OsRng.fill_bytes(&mut seed);
+    let signing_key = SigningKey::from_bytes(&seed);
+    let mut encoded = format!(
+        "{}{}",
+        SIGNING_KEY_PREFIX,
+        general_purpose::STANDARD.encode(seed)
+    );
+    seed.zeroize();
+
+    let replaced = vault.add(name, &encoded);
+    encoded.zeroize();
+    Ok((encode_public_key(&signing_key.verifying_key()), replaced?))
+}
+
+/// Base64 public key of the signing key stored as `name`
+pub fn public_key(vault: &Vault, name: &str) -> Result<String, String> {
+    signing_key(vault, name).map(|key| encode_public_key(&key.verifying_key()))
+}
+
+/// Detached Ed25519 signature (base64) of `target` with the vault key `name`
+pub fn sign_target(vault: &Vault, name: &str, target: &str) -> Result<(String, String), String> {
+    let signing_key = signing_key(vault, name)?;
+    let (mut message, label) = message_bytes(target)?;
+    let signature = signing_key.sign(&message);
+    message.zeroize();
+    Ok((
+        general_purpose::STANDARD.encode(signature.to_bytes()),
+        label,
+    ))
+}
+
+/// Verify a detached signature of `target`. `signer` is a base64 public key, or the
+/// name of a signing key in the vault.
+pub fn verify_target(
+    vault: &Vault,
+    signer: &str,
+    signature_b64: &str,
+    target: &str,
+) -> Result<(bool, String), String> {
+    let verifying_key = match decode_public_key(signer) {
+        Some(key) => key,
+        None => signing_key(vault, signer)
+            .map(|key| key.verifying_key())
+            .map_err(|_| {
+                format!(
+                    "'{}' is neither a public key nor a vault signing key.",
+                    signer
+                )
+            })?,
+    };
+    let signature = general_purpose::STANDARD
+        .decode(signature_b64)
+        .ok()
+        .and_then(|bytes| Signature::from_slice(&bytes).ok())
+        .ok_or("Invalid signature format.")?;
+
+    let (mut message, label) = message_bytes(target)?;
+    let valid = verifying_key.verify_strict(&message, &signature).is_ok();
+    message.zeroize();
+    Ok((valid, label))
+}
+
+/// The Ed25519 signing key stored in the vault as `name`
+pub fn signing_key(vault: &Vault, name: &str) -> Result<SigningKey, String> {
+    let seed = vault
+        .get(name)?
+        .expose()
+        .strip_prefix(SIGNING_KEY_PREFIX)
+        .and_then(|b64| general_purpose::STANDARD.decode(b64).ok());
+
+    let mut seed = seed.ok_or_else(|| format!("Vault entry '{}' is not a signing key.", name))?;
+    let key = <[u8; 32]>::try_from(seed.as_slice()).map(|bytes| SigningKey::from_bytes(&bytes));
+    seed.zeroize();
+    key.map_err(|_| format!("Vault entry '{}' is not a signing key.", name))
+}
+
+fn encode_public_key(key: &VerifyingKey) -> String {
+    general_purpose::STANDARD.encode(key.as_bytes())
+}
+
+fn decode_public_key(encoded: &str) -> Option<VerifyingKey> {
+    let bytes = general_purpose::STANDARD.decode(encoded).ok()?;
+    VerifyingKey::from_bytes(&<[u8; 32]>::try_from(bytes).ok()?).ok()
+}
+
+/// The whole message to sign: a file (up to `MAX_SIGNED_SIZE`) or the text itself
+fn message_bytes(target: &str) -> Result<(Vec<u8>, String), String> {
+    let path = Path::new(target);
+    if path.is_file() {
+        let size = path
+            .metadata()
+            .map_err(|e| format!("{}: {}", path.display(), e))?
+            .len();
+        if size > MAX_SIGNED_SIZE {
+            return Err(format!(
+                "{}: file too large to sign ({} bytes, limit {}).",
+                path.display(),
+                size,
+                MAX_SIGNED_SIZE
+            ));
+        }
+    }
+
+    let mut message = Vec::new();
+    let label = feed_target(target, |data| message.extend_from_slice(data))?;
+    Ok((message, label))
+}
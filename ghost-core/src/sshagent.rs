@@ -0,0 +1,317 @@
+/// Minimal in-memory SSH agent for ::ssh-add
+/// Ed25519 keys are held only in mlocked, zeroized memory and served over a socket in a
+/// private 0700 directory. SSH_AUTH_SOCK is exported so child `ssh` commands use it.
+/// Only identity listing and signing are implemented; any other request is refused.
+use base64::{engine::general_purpose, Engine as _};
+use chacha20poly1305::aead::OsRng;
+use ed25519_dalek::{Signer, SigningKey};
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use ssh_key::private::KeypairData;
+use ssh_key::PrivateKey;
+use std::env;
+use std::fs;
+use std::io::{Read, Write};
+#[cfg(unix)]
+use std::os::unix::fs::DirBuilderExt;
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use zeroize::Zeroize;
+
+use crate::secret::SecretString;
+use crate::security::{disable_core_dump, lock_memory};
+
+// Agent protocol message numbers (draft-miller-ssh-agent)
+const SSH_AGENT_FAILURE: u8 = 5;
+const SSH_AGENTC_REQUEST_IDENTITIES: u8 = 11;
+const SSH_AGENT_IDENTITIES_ANSWER: u8 = 12;
+const SSH_AGENTC_SIGN_REQUEST: u8 = 13;
+const SSH_AGENT_SIGN_RESPONSE: u8 = 14;
+
+/// Largest request read from a client
+const MAX_MESSAGE_SIZE: usize = 256 * 1024;
+
+const KEY_TYPE: &str = "ssh-ed25519";
+
+/// One identity; the secret half sits in its own locked allocation
+pub struct SshKey {
+    secret: Box<[u8; 32]>,
+    public: [u8; 32],
+    comment: String,
+}
+
+impl SshKey {
+    /// Take ownership of an Ed25519 seed, zeroizing the caller's copy
+    pub fn from_seed(seed: &mut [u8; 32], comment: &str) -> Self {
+        let mut secret = Box::new([0u8; 32]);
+        // Best effort: keep the key out of swap and core dumps
+        let _ = lock_memory(secret.as_ptr(), secret.len());
+        let _ = disable_core_dump(secret.as_ptr(), secret.len());
+        secret.copy_from_slice(seed);
+        seed.zeroize();
+
+        let public = SigningKey::from_bytes(&secret).verifying_key().to_bytes();
+        SshKey {
+            secret,
+            public,
+            comment: comment.to_string(),
+        }
+    }
+
+    /// Read an OpenSSH private key file; `passphrase` is only asked for if it is encrypted
+    pub fn from_file(
+        path: &Path,
+        passphrase: impl FnOnce() -> Result<SecretString, String>,
+    ) -> Result<Self, String> {
+        let mut key = PrivateKey::read_openssh_file(path)
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+        if key.is_encrypted() {
+            key = key
+                .decrypt(passphrase()?.expose())
+                .map_err(|_| format!("{}: wrong passphrase.", path.display()))?;
+        }
+
+        match key.key_data() {
+            KeypairData::Ed25519(keypair) => {
+                let mut seed = keypair.private.to_bytes();
+                Ok(SshKey::from_seed(&mut seed, key.comment()))
+            }
+            _ => Err(format!(
+                "{}: only ed25519 keys are supported.",
+                path.display()
+            )),
+        }
+    }
+
+    /// Wire encoding of the public key: string "ssh-ed25519", string key
+    fn blob(&self) -> Vec<u8> {
+        let mut blob = Vec::with_capacity(51);
+        put_string(&mut blob, KEY_TYPE.as_bytes());
+        put_string(&mut blob, &self.public);
+        blob
+    }
+
+    /// `SHA256:...` fingerprint as shown by ssh-add -l
+    pub fn fingerprint(&self) -> String {
+        let digest = Sha256::digest(self.blob());
+        format!("SHA256:{}", general_purpose::STANDARD_NO_PAD.encode(digest))
+    }
+
+    /// authorized_keys line for this identity
+    pub fn public_line(&self) -> String {
+        format!(
+            "{} {} {}",
+            KEY_TYPE,
+            general_purpose::STANDARD.encode(self.blob()),
+            self.comment
+        )
+    }
+
+    fn sign(&self, data: &[u8]) -> Vec<u8> {
+        let signature = SigningKey::from_bytes(&self.secret).sign(data);
+        let mut encoded = Vec::with_capacity(83);
+        put_string(&mut encoded, KEY_TYPE.as_bytes());
+        put_string(&mut encoded, &signature.to_bytes());
+        encoded
+    }
+}
+
+impl Drop for SshKey {
+    fn drop(&mut self) {
+        self.secret.zeroize();
+    }
+}
+
+/// A running agent. Dropping it stops the listener, zeroizes every key and removes
+/// the socket.
+pub struct SshAgent {
+    socket_dir: PathBuf,
+    socket_path: PathBuf,
+    keys: Arc<Mutex<Vec<SshKey>>>,
+    stopping: Arc<AtomicBool>,
+}
+
+impl SshAgent {
+    /// Bind the agent socket and export SSH_AUTH_SOCK
+    #[cfg(unix)]
+    pub fn start() -> Result<Self, String> {
+        let mut suffix = [0u8; 8];
+        OsRng.fill_bytes(&mut suffix);
+        let base = env::var_os("XDG_RUNTIME_DIR")
+            .map(PathBuf::from)
+            .filter(|dir| dir.is_dir())
+            .unwrap_or_else(env::temp_dir);
+        let socket_dir = base.join(format!(
+            "ghost-agent-{}",
+            suffix
+                .iter()
+                .map(|b| format!("{:02x}", b))
+                .collect::<String>()
+        ));
+        fs::DirBuilder::new()
+            .mode(0o700)
+            .create(&socket_dir)
+            .map_err(|e| format!("ssh-agent: {}: {}", socket_dir.display(), e))?;
+
+        let socket_path = socket_dir.join("agent.sock");
+        let listener = match UnixListener::bind(&socket_path) {
+            Ok(listener) => listener,
+            Err(e) => {
+                let _ = fs::remove_dir(&socket_dir);
+                return Err(format!("ssh-agent: {}: {}", socket_path.display(), e));
+            }
+        };
+
+        let keys = Arc::new(Mutex::new(Vec::new()));
+        let stopping = Arc::new(AtomicBool::new(false));
+        {
+            let keys = Arc::clone(&keys);
+            let stopping = Arc::clone(&stopping);
+            thread::spawn(move || {
+                for stream in listener.incoming() {
+                    if stopping.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    if let Ok(stream) = stream {
+                        let keys = Arc::clone(&keys);
+                        thread::spawn(move || serve(stream, &keys));
+                    }
+                }
+            });
+        }
+
+        env::set_var("SSH_AUTH_SOCK", &socket_path);
+        Ok(SshAgent {
+            socket_dir,
+            socket_path,
+            keys,
+            stopping,
+        })
+    }
+
+    #[cfg(not(unix))]
+    pub fn start() -> Result<Self, String> {
+        Err("ssh-agent: not supported on this platform.".to_string())
+    }
+
+    pub fn socket_path(&self) -> &Path {
+        &self.socket_path
+    }
+
+    /// Add an identity, replacing one with the same public key.
+    /// Returns its fingerprint.
+    pub fn add(&self, key: SshKey) -> String {
+        let fingerprint = key.fingerprint();
+        let mut keys = self.keys.lock().unwrap_or_else(|e| e.into_inner());
+        keys.retain(|existing| existing.public != key.public);
+        keys.push(key);
+        fingerprint
+    }
+
+    /// `<fingerprint> <comment>` for each loaded identity
+    pub fn list(&self) -> Vec<String> {
+        self.keys
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .map(|key| format!("  {} {} (ED25519)", key.fingerprint(), key.comment))
+            .collect()
+    }
+
+    /// Zeroize and drop every identity, returning how many there were
+    pub fn remove_all(&self) -> usize {
+        let mut keys = self.keys.lock().unwrap_or_else(|e| e.into_inner());
+        let count = keys.len();
+        keys.clear();
+        count
+    }
+}
+
+impl Drop for SshAgent {
+    fn drop(&mut self) {
+        self.remove_all();
+        self.stopping.store(true, Ordering::SeqCst);
+        // Wake the accept loop so it sees the flag
+        #[cfg(unix)]
+        let _ = UnixStream::connect(&self.socket_path);
+        let _ = fs::remove_file(&self.socket_path);
+        let _ = fs::remove_dir(&self.socket_dir);
+        if env::var_os("SSH_AUTH_SOCK").is_some_and(|sock| sock == self.socket_path.as_os_str()) {
+            env::remove_var("SSH_AUTH_SOCK");
+        }
+    }
+}
+
+/// Answer requests on one client connection until it closes
+#[cfg(unix)]
+fn serve(mut stream: UnixStream, keys: &Mutex<Vec<SshKey>>) {
+    loop {
+        let mut length = [0u8; 4];
+        if stream.read_exact(&mut length).is_err() {
+            return;
+        }
+        let length = u32::from_be_bytes(length) as usize;
+        if length == 0 || length > MAX_MESSAGE_SIZE {
+            return;
+        }
+        let mut request = vec![0u8; length];
+        if stream.read_exact(&mut request).is_err() {
+            return;
+        }
+
+        let response = handle(&request, keys).unwrap_or_else(|| vec![SSH_AGENT_FAILURE]);
+        let mut framed = Vec::with_capacity(4 + response.len());
+        put_string(&mut framed, &response);
+        if stream.write_all(&framed).is_err() {
+            return;
+        }
+    }
+}
+
+/// Response to one request, or None for SSH_AGENT_FAILURE
+fn handle(request: &[u8], keys: &Mutex<Vec<SshKey>>) -> Option<Vec<u8>> {
+    let keys = keys.lock().unwrap_or_else(|e| e.into_inner());
+    let (&kind, mut body) = request.split_first()?;
+    match kind {
+        SSH_AGENTC_REQUEST_IDENTITIES => {
+            let mut response = vec![SSH_AGENT_IDENTITIES_ANSWER];
+            response.extend_from_slice(&(keys.len() as u32).to_be_bytes());
+            for key in keys.iter() {
+                put_string(&mut response, &key.blob());
+                put_string(&mut response, key.comment.as_bytes());
+            }
+            Some(response)
+        }
+        SSH_AGENTC_SIGN_REQUEST => {
+            let blob = take_string(&mut body)?;
+            let data = take_string(&mut body)?;
+            let key = keys.iter().find(|key| key.blob() == blob)?;
+            let mut response = vec![SSH_AGENT_SIGN_RESPONSE];
+            put_string(&mut response, &key.sign(data));
+            Some(response)
+        }
+        _ => None,
+    }
+}
+
+/// Append an SSH `string` (u32 BE length + bytes)
+fn put_string(buffer: &mut Vec<u8>, data: &[u8]) {
+    buffer.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    buffer.extend_from_slice(data);
+}
+
+/// Split an SSH `string` off the front of `input`
+fn take_string<'a>(input: &mut &'a [u8]) -> Option<&'a [u8]> {
+    let (length, rest) = input.split_first_chunk::<4>()?;
+    let length = u32::from_be_bytes(*length) as usize;
+    if rest.len() < length {
+        return None;
+    }
+    let (data, rest) = rest.split_at(length);
+    *input = rest;
+    Some(data)
+}
@@ -0,0 +1,679 @@
+/// Terminal input and output, with a headless backend for scripted runs
+/// Everything the shell reads from or draws on the terminal goes through here:
+/// `read_event` and `poll_event` for keys, `stdout` for output and `size`. Normally
+/// that is crossterm on the real TTY. After `start_headless`, key events come from a
+/// script and output lands on an in-memory `Screen` instead, so the event loop, the
+/// prompts and the panic flows run without a terminal (`ghost-shell --headless`).
+/// After `start_relay` (a `::detach`ed session) both go through the session socket to
+/// whichever `ghost-shell --attach` client is connected.
+use crossterm::{
+    event::{self, Event, KeyCode, KeyEvent, KeyModifiers},
+    terminal,
+};
+use std::collections::VecDeque;
+use std::io::{self, Write};
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+
+#[cfg(unix)]
+use crate::detach::Relay;
+
+/// Screen size of a headless run until the script resizes it
+pub const HEADLESS_SIZE: (u16, u16) = (80, 24);
+
+/// The installed script and screen, when running headless
+static HEADLESS: Mutex<Option<Headless>> = Mutex::new(None);
+
+/// The session socket, once detached
+#[cfg(unix)]
+static RELAY: Mutex<Option<Relay>> = Mutex::new(None);
+
+/// One line of a headless script
+#[derive(Debug, Clone, PartialEq)]
+pub enum Step {
+    /// Deliver an input event
+    Event(Event),
+    /// Print the screen as it is at this point to the real stdout
+    Snapshot,
+}
+
+struct Headless {
+    steps: VecDeque<Step>,
+    screen: Screen,
+}
+
+fn headless() -> MutexGuard<'static, Option<Headless>> {
+    HEADLESS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+#[cfg(unix)]
+fn relay() -> MutexGuard<'static, Option<Relay>> {
+    RELAY
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Parse a headless script. One step per line; blank lines and `#` comments are
+/// skipped:
+///
+/// ```text
+/// type <text>       each character as a key press
+/// line <text>       the same, then Enter
+/// key <key> ...     Enter Tab Backspace Delete Esc Up Down Left Right Home End, Ctrl+<letter>,
+///                   Alt+<digit>
+/// paste <text>      a bracketed paste; `\n` stands for a newline
+/// resize <cols> <rows>
+/// screen            print the screen so far
+/// ```
+pub fn parse_script(script: &str) -> Result<Vec<Step>, String> {
+    let mut steps = Vec::new();
+    for (number, line) in script.lines().enumerate() {
+        let invalid = |what: &str| format!("Script line {}: {}", number + 1, what);
+        let trimmed = line.trim_start();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let (word, rest) = trimmed.split_once(' ').unwrap_or((trimmed, ""));
+        match word {
+            "type" | "line" => {
+                steps.extend(
+                    rest.chars()
+                        .map(|c| key(KeyCode::Char(c), KeyModifiers::NONE)),
+                );
+                if word == "line" {
+                    steps.push(key(KeyCode::Enter, KeyModifiers::NONE));
+                }
+            }
+            "key" => {
+                for name in rest.split_whitespace() {
+                    steps.push(
+                        parse_key(name)
+                            .ok_or_else(|| invalid(&format!("unknown key '{}'", name)))?,
+                    );
+                }
+            }
+            "paste" => steps.push(Step::Event(Event::Paste(rest.replace("\\n", "\n")))),
+            "resize" => {
+                let size: Vec<u16> = rest
+                    .split_whitespace()
+                    .map(str::parse)
+                    .collect::<Result<_, _>>()
+                    .map_err(|_| invalid("resize takes <cols> <rows>"))?;
+                match size.as_slice() {
+                    [cols, rows] if *cols > 0 && *rows > 0 => {
+                        steps.push(Step::Event(Event::Resize(*cols, *rows)))
+                    }
+                    _ => return Err(invalid("resize takes <cols> <rows>")),
+                }
+            }
+            "screen" => steps.push(Step::Snapshot),
+            _ => return Err(invalid(&format!("unknown step '{}'", word))),
+        }
+    }
+    Ok(steps)
+}
+
+/// One event as a script line, the way `ghost-shell --attach` sends it; None for
+/// events the shell never reads
+pub fn encode_event(event: &Event) -> Option<String> {
+    match event {
+        Event::Key(KeyEvent {
+            code, modifiers, ..
+        }) => {
+            let name = match code {
+                KeyCode::Char(c)
+                    if modifiers.contains(KeyModifiers::CONTROL) && c.is_ascii_alphabetic() =>
+                {
+                    return Some(format!("key Ctrl+{}", c.to_ascii_lowercase()));
+                }
+                KeyCode::Char(c) if modifiers.contains(KeyModifiers::ALT) && c.is_ascii_digit() => {
+                    return Some(format!("key Alt+{}", c));
+                }
+                KeyCode::Char(c) => return Some(format!("type {}", c)),
+                KeyCode::Enter => "Enter",
+                KeyCode::Tab => "Tab",
+                KeyCode::Backspace => "Backspace",
+                KeyCode::Delete => "Delete",
+                KeyCode::Esc => "Esc",
+                KeyCode::Up => "Up",
+                KeyCode::Down => "Down",
+                KeyCode::Left => "Left",
+                KeyCode::Right => "Right",
+                KeyCode::Home => "Home",
+                KeyCode::End => "End",
+                _ => return None,
+            };
+            Some(format!("key {}", name))
+        }
+        Event::Paste(text) => Some(format!(
+            "paste {}",
+            text.replace("\r\n", "\n")
+                .replace('\r', "\n")
+                .replace('\n', "\\n")
+        )),
+        Event::Resize(cols, rows) => Some(format!("resize {} {}", cols, rows)),
+        _ => None,
+    }
+}
+
+fn key(code: KeyCode, modifiers: KeyModifiers) -> Step {
+    Step::Event(Event::Key(KeyEvent::new(code, modifiers)))
+}
+
+/// `Enter`, `Up`, `Ctrl+T`, `Alt+2`, ... as a key press
+fn parse_key(name: &str) -> Option<Step> {
+    if let Some(digit) = name
+        .strip_prefix("Alt+")
+        .or_else(|| name.strip_prefix("alt+"))
+    {
+        let mut chars = digit.chars();
+        return match (chars.next(), chars.next()) {
+            (Some(c), None) if c.is_ascii_digit() => Some(key(KeyCode::Char(c), KeyModifiers::ALT)),
+            _ => None,
+        };
+    }
+    if let Some(letter) = name
+        .strip_prefix("Ctrl+")
+        .or_else(|| name.strip_prefix("ctrl+"))
+    {
+        let mut chars = letter.chars();
+        return match (chars.next(), chars.next()) {
+            (Some(c), None) if c.is_ascii_alphabetic() => Some(key(
+                KeyCode::Char(c.to_ascii_lowercase()),
+                KeyModifiers::CONTROL,
+            )),
+            _ => None,
+        };
+    }
+    let code = match name {
+        "Enter" => KeyCode::Enter,
+        "Tab" => KeyCode::Tab,
+        "Backspace" => KeyCode::Backspace,
+        "Delete" => KeyCode::Delete,
+        "Esc" => KeyCode::Esc,
+        "Up" => KeyCode::Up,
+        "Down" => KeyCode::Down,
+        "Left" => KeyCode::Left,
+        "Right" => KeyCode::Right,
+        "Home" => KeyCode::Home,
+        "End" => KeyCode::End,
+        _ => return None,
+    };
+    Some(key(code, KeyModifiers::NONE))
+}
+
+/// Run headless from now on: input comes from `steps`, output goes to a `cols` x
+/// `rows` in-memory screen
+pub fn start_headless(steps: Vec<Step>, (cols, rows): (u16, u16)) {
+    *headless() = Some(Headless {
+        steps: steps.into(),
+        screen: Screen::new(cols as usize, rows as usize),
+    });
+}
+
+/// Whether a headless script is installed
+pub fn is_headless() -> bool {
+    headless().is_some()
+}
+
+/// Serve the session socket instead of the terminal from now on
+#[cfg(unix)]
+pub fn start_relay(session: Relay) {
+    *relay() = Some(session);
+}
+
+/// Whether the session is detached from its terminal
+pub fn is_relayed() -> bool {
+    #[cfg(unix)]
+    return relay().is_some();
+    #[cfg(not(unix))]
+    false
+}
+
+/// Whether a client is attached to the detached session
+pub fn is_attached() -> bool {
+    #[cfg(unix)]
+    return relay().as_ref().is_some_and(Relay::is_attached);
+    #[cfg(not(unix))]
+    false
+}
+
+/// Disconnect the attached client; the session waits for the next one
+pub fn drop_client() {
+    #[cfg(unix)]
+    if let Some(relay) = relay().as_mut() {
+        relay.drop_client();
+    }
+}
+
+/// Next input event. A finished headless script reads as `UnexpectedEof`.
+pub fn read_event() -> io::Result<Event> {
+    // The lock is let go between waits so other threads can still write
+    #[cfg(unix)]
+    while let Some(relay) = relay().as_mut() {
+        if let Some(event) = relay.next_event(Duration::from_millis(100))? {
+            return Ok(event);
+        }
+    }
+    let mut guard = headless();
+    let Some(headless) = guard.as_mut() else {
+        drop(guard);
+        return event::read();
+    };
+    loop {
+        match headless.steps.pop_front() {
+            Some(Step::Event(event)) => {
+                if let Event::Resize(cols, rows) = event {
+                    headless.screen.resize(cols as usize, rows as usize);
+                }
+                return Ok(event);
+            }
+            Some(Step::Snapshot) => print_screen(&headless.screen)?,
+            None => return Err(end_of_script()),
+        }
+    }
+}
+
+/// Whether an event arrives within `timeout`. Headless, script steps only go to
+/// reads that wait: a zero timeout (checking for keys typed ahead) sees nothing, and
+/// a finished script reads as `UnexpectedEof`.
+pub fn poll_event(timeout: Duration) -> io::Result<bool> {
+    #[cfg(unix)]
+    if let Some(relay) = relay().as_mut() {
+        return relay.poll(timeout);
+    }
+    match headless().as_ref() {
+        None => event::poll(timeout),
+        Some(_) if timeout.is_zero() => Ok(false),
+        Some(headless) if headless.steps.is_empty() => Err(end_of_script()),
+        Some(_) => Ok(true),
+    }
+}
+
+fn end_of_script() -> io::Error {
+    io::Error::new(io::ErrorKind::UnexpectedEof, "end of headless script")
+}
+
+/// Terminal size as (columns, rows)
+pub fn size() -> io::Result<(u16, u16)> {
+    #[cfg(unix)]
+    if let Some(relay) = relay().as_ref() {
+        return Ok(relay.size());
+    }
+    match headless().as_ref() {
+        None => terminal::size(),
+        Some(headless) => Ok((headless.screen.cols as u16, headless.screen.rows as u16)),
+    }
+}
+
+/// Where the shell draws: the real stdout, or the headless screen
+pub fn stdout() -> Output {
+    Output(io::stdout())
+}
+
+/// Writer for `stdout()`
+pub struct Output(io::Stdout);
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        #[cfg(unix)]
+        if let Some(relay) = relay().as_mut() {
+            relay.write(buf);
+            return Ok(buf.len());
+        }
+        match headless().as_mut() {
+            Some(headless) => {
+                headless.screen.feed(buf);
+                Ok(buf.len())
+            }
+            None => self.0.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        #[cfg(unix)]
+        if relay().is_some() {
+            return Ok(());
+        }
+        match headless().as_ref() {
+            Some(_) => Ok(()),
+            None => self.0.flush(),
+        }
+    }
+}
+
+/// End a headless run by printing the final screen, or a detached one by closing its
+/// socket; nothing on a real terminal
+pub fn finish() -> io::Result<()> {
+    #[cfg(unix)]
+    drop(relay().take());
+    match headless().take() {
+        Some(headless) => print_screen(&headless.screen),
+        None => Ok(()),
+    }
+}
+
+/// Exit the process, printing the final headless screen first
+pub fn exit(code: i32) -> ! {
+    let _ = finish();
+    std::process::exit(code);
+}
+
+fn print_screen(screen: &Screen) -> io::Result<()> {
+    let mut out = io::stdout();
+    writeln!(out, "=== SCREEN ===")?;
+    for line in screen.lines() {
+        writeln!(out, "{}", line)?;
+    }
+    writeln!(out, "=== END ===")?;
+    out.flush()
+}
+
+/// Escape sequence parser state
+#[derive(Debug, Clone, PartialEq)]
+enum Parse {
+    Ground,
+    Escape,
+    Csi(String),
+    Osc(String),
+    /// ESC inside an OSC: `\` ends it
+    OscEscape(String),
+    /// DCS (tmux passthrough): skipped up to ESC `\`
+    Dcs,
+    DcsEscape,
+}
+
+/// In-memory terminal for headless runs. Understands the cursor movement, erase,
+/// scroll region, save/restore, alternate screen and title sequences the shell
+/// emits, in raw mode (LF does not return the cursor). Colors and modes are dropped.
+pub struct Screen {
+    cols: usize,
+    rows: usize,
+    grid: Vec<Vec<char>>,
+    scrollback: Vec<String>,
+    row: usize,
+    col: usize,
+    top: usize,
+    bottom: usize,
+    saved: (usize, usize),
+    /// The main screen and its cursor while the alternate screen is shown
+    main: Option<(Vec<Vec<char>>, usize, usize)>,
+    title: String,
+    /// Titles saved with CSI 22 t
+    titles: Vec<String>,
+    parse: Parse,
+    /// Bytes of a UTF-8 character split across writes
+    partial: Vec<u8>,
+}
+
+impl Screen {
+    pub fn new(cols: usize, rows: usize) -> Self {
+        let (cols, rows) = (cols.max(1), rows.max(1));
+        Screen {
+            cols,
+            rows,
+            grid: vec![vec![' '; cols]; rows],
+            scrollback: Vec::new(),
+            row: 0,
+            col: 0,
+            top: 0,
+            bottom: rows - 1,
+            saved: (0, 0),
+            main: None,
+            title: String::new(),
+            titles: Vec::new(),
+            parse: Parse::Ground,
+            partial: Vec::new(),
+        }
+    }
+
+    /// Lines that scrolled off the top, then the visible rows, without trailing
+    /// blanks. The alternate screen has no scrollback.
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines: Vec<String> = if self.main.is_some() {
+            Vec::new()
+        } else {
+            self.scrollback.clone()
+        };
+        lines.extend(
+            self.grid
+                .iter()
+                .map(|row| row.iter().collect::<String>().trim_end().to_string()),
+        );
+        while lines.last().is_some_and(String::is_empty) {
+            lines.pop();
+        }
+        lines
+    }
+
+    /// The window title last set
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    /// (columns, rows)
+    pub fn size(&self) -> (usize, usize) {
+        (self.cols, self.rows)
+    }
+
+    /// Output that paints the visible rows on a cleared terminal of the same size
+    /// and puts the cursor back
+    pub fn repaint(&self) -> String {
+        let mut output = String::from("\x1b[r\x1b[H\x1b[2J");
+        for (row, line) in self.grid.iter().enumerate() {
+            let text = line.iter().collect::<String>();
+            if !text.trim_end().is_empty() {
+                output.push_str(&format!("\x1b[{};1H{}", row + 1, text.trim_end()));
+            }
+        }
+        output.push_str(&format!("\x1b[{};{}H", self.row + 1, self.col + 1));
+        output
+    }
+
+    /// Interpret output bytes
+    pub fn feed(&mut self, bytes: &[u8]) {
+        self.partial.extend_from_slice(bytes);
+        let valid = match std::str::from_utf8(&self.partial) {
+            Ok(text) => text.len(),
+            Err(e) if e.error_len().is_none() => e.valid_up_to(), // Split character
+            Err(_) => {
+                // Not UTF-8 at all: show it as replacement characters
+                let text = String::from_utf8_lossy(&self.partial).into_owned();
+                self.partial.clear();
+                text.chars().for_each(|c| self.input(c));
+                return;
+            }
+        };
+        let rest = self.partial.split_off(valid);
+        let text =
+            String::from_utf8(std::mem::replace(&mut self.partial, rest)).unwrap_or_default();
+        text.chars().for_each(|c| self.input(c));
+    }
+
+    fn input(&mut self, c: char) {
+        match std::mem::replace(&mut self.parse, Parse::Ground) {
+            Parse::Ground => self.ground(c),
+            Parse::Escape => match c {
+                '[' => self.parse = Parse::Csi(String::new()),
+                ']' => self.parse = Parse::Osc(String::new()),
+                'P' => self.parse = Parse::Dcs,
+                '7' => self.saved = (self.row, self.col),
+                '8' => (self.row, self.col) = self.saved,
+                _ => {}
+            },
+            Parse::Csi(mut params) => {
+                if ('\x40'..='\x7e').contains(&c) {
+                    self.csi(&params, c);
+                } else {
+                    params.push(c);
+                    self.parse = Parse::Csi(params);
+                }
+            }
+            Parse::Osc(mut text) => match c {
+                '\x07' => self.osc(&text),
+                '\x1b' => self.parse = Parse::OscEscape(text),
+                c => {
+                    text.push(c);
+                    self.parse = Parse::Osc(text);
+                }
+            },
+            Parse::OscEscape(text) => self.osc(&text),
+            Parse::Dcs => {
+                if c == '\x1b' {
+                    self.parse = Parse::DcsEscape;
+                } else {
+                    self.parse = Parse::Dcs;
+                }
+            }
+            Parse::DcsEscape => {
+                if c != '\\' {
+                    self.parse = Parse::Dcs;
+                }
+            }
+        }
+    }
+
+    fn ground(&mut self, c: char) {
+        match c {
+            '\x1b' => self.parse = Parse::Escape,
+            '\r' => self.col = 0,
+            '\n' => self.line_feed(),
+            '\x08' => self.col = self.col.saturating_sub(1),
+            '\t' => self.col = ((self.col / 8 + 1) * 8).min(self.cols - 1),
+            c if c.is_control() => {}
+            c => {
+                if self.col >= self.cols {
+                    self.col = 0;
+                    self.line_feed();
+                }
+                self.grid[self.row][self.col] = c;
+                self.col += 1;
+            }
+        }
+    }
+
+    fn line_feed(&mut self) {
+        if self.row == self.bottom {
+            self.scroll_up();
+        } else if self.row + 1 < self.rows {
+            self.row += 1;
+        }
+    }
+
+    /// Scroll the region up a line; lines leaving the top of the main screen are kept
+    fn scroll_up(&mut self) {
+        let line = self.grid.remove(self.top);
+        if self.top == 0 && self.main.is_none() {
+            self.scrollback
+                .push(line.iter().collect::<String>().trim_end().to_string());
+        }
+        self.grid.insert(self.bottom, vec![' '; self.cols]);
+    }
+
+    fn csi(&mut self, params: &str, command: char) {
+        if let Some(mode) = params.strip_prefix('?') {
+            match (mode, command) {
+                ("1049", 'h') if self.main.is_none() => {
+                    let blank = vec![vec![' '; self.cols]; self.rows];
+                    let main = std::mem::replace(&mut self.grid, blank);
+                    self.main = Some((main, self.row, self.col));
+                }
+                ("1049", 'l') => {
+                    if let Some((grid, row, col)) = self.main.take() {
+                        self.grid = grid;
+                        (self.row, self.col) = (row, col);
+                    }
+                }
+                _ => {} // Bracketed paste, cursor visibility and other modes
+            }
+            return;
+        }
+        let numbers: Vec<usize> = params.split(';').map(|n| n.parse().unwrap_or(0)).collect();
+        let n = |i: usize| numbers.get(i).copied().unwrap_or(0);
+        let count = n(0).max(1);
+        match command {
+            'A' => self.row = self.row.saturating_sub(count),
+            'B' => self.row = (self.row + count).min(self.rows - 1),
+            'C' => self.col = (self.col + count).min(self.cols - 1),
+            'D' => self.col = self.col.saturating_sub(count),
+            'G' => self.col = (count - 1).min(self.cols - 1),
+            'd' => self.row = (count - 1).min(self.rows - 1),
+            'H' | 'f' => {
+                self.row = (n(0).max(1) - 1).min(self.rows - 1);
+                self.col = (n(1).max(1) - 1).min(self.cols - 1);
+            }
+            'J' => match n(0) {
+                0 => {
+                    self.clear_line(self.col..self.cols);
+                    self.grid[self.row + 1..]
+                        .iter_mut()
+                        .for_each(|line| line.fill(' '));
+                }
+                1 => {
+                    self.grid[..self.row]
+                        .iter_mut()
+                        .for_each(|line| line.fill(' '));
+                    self.clear_line(0..self.col + 1);
+                }
+                2 => self.grid.iter_mut().for_each(|line| line.fill(' ')),
+                3 => self.scrollback.clear(),
+                _ => {}
+            },
+            'K' => match n(0) {
+                0 => self.clear_line(self.col..self.cols),
+                1 => self.clear_line(0..self.col + 1),
+                2 => self.clear_line(0..self.cols),
+                _ => {}
+            },
+            'r' => {
+                let top = n(0).max(1) - 1;
+                let bottom = if n(1) == 0 { self.rows } else { n(1) }.min(self.rows) - 1;
+                if top < bottom {
+                    (self.top, self.bottom) = (top, bottom);
+                    (self.row, self.col) = (0, 0);
+                }
+            }
+            'S' => (0..count).for_each(|_| self.scroll_up()),
+            't' if n(0) == 22 => self.titles.push(self.title.clone()),
+            't' if n(0) == 23 => {
+                if let Some(title) = self.titles.pop() {
+                    self.title = title;
+                }
+            }
+            _ => {} // Colors and anything else the shell doesn't use
+        }
+    }
+
+    fn clear_line(&mut self, columns: std::ops::Range<usize>) {
+        let end = columns.end.min(self.cols);
+        let start = columns.start.min(end);
+        self.grid[self.row][start..end].fill(' ');
+    }
+
+    /// OSC 0 and 2 set the title; the rest (OSC 52 clipboard writes) are ignored
+    fn osc(&mut self, text: &str) {
+        if let Some(title) = text.strip_prefix("0;").or_else(|| text.strip_prefix("2;")) {
+            self.title = title.to_string();
+        }
+    }
+
+    /// A resized terminal keeps the top-left of the screen and resets the region
+    pub(crate) fn resize(&mut self, cols: usize, rows: usize) {
+        let (cols, rows) = (cols.max(1), rows.max(1));
+        let fit = |grid: &mut Vec<Vec<char>>| {
+            grid.iter_mut().for_each(|line| line.resize(cols, ' '));
+            grid.resize(rows, vec![' '; cols]);
+        };
+        fit(&mut self.grid);
+        if let Some((grid, row, col)) = self.main.as_mut() {
+            fit(grid);
+            (*row, *col) = ((*row).min(rows - 1), (*col).min(cols - 1));
+        }
+        (self.cols, self.rows) = (cols, rows);
+        (self.top, self.bottom) = (0, rows - 1);
+        self.row = self.row.min(rows - 1);
+        self.col = self.col.min(cols - 1);
+    }
+}
@@ -0,0 +1,166 @@
+/// Color themes for ::theme and the config `theme` key
+/// A theme styles six things: the prompt, good results like the posture grade,
+/// warnings, errors, threat alerts and the clipboard countdown. Command output is classified line by line, so alerts raised
+/// anywhere (periodic checks, ::security-status, clipboard tampering) pick up the theme.
+/// Security alerts also get their own `‼` prefix, so they stand out without color.
+use crossterm::style::{Color, ContentStyle, Stylize};
+
+/// What a piece of text is, for styling
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    Prompt,
+    /// A good result, such as a high posture grade
+    Success,
+    Warning,
+    Error,
+    Alert,
+    Countdown,
+}
+
+/// Marks security alerts in place of the `⚠` that warnings use
+pub const ALERT_PREFIX: &str = "‼ ";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Theme {
+    Default,
+    Matrix,
+    Mono,
+    HighContrast,
+}
+
+impl Theme {
+    pub const ALL: [Theme; 4] = [
+        Theme::Default,
+        Theme::Matrix,
+        Theme::Mono,
+        Theme::HighContrast,
+    ];
+
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "default" => Some(Theme::Default),
+            "matrix" => Some(Theme::Matrix),
+            "mono" => Some(Theme::Mono),
+            "high-contrast" => Some(Theme::HighContrast),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Theme::Default => "default",
+            Theme::Matrix => "matrix",
+            Theme::Mono => "mono",
+            Theme::HighContrast => "high-contrast",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Theme::Default => "plain prompt, yellow warnings, red errors, bold red alerts",
+            Theme::Matrix => "green prompt and warnings, alerts in reverse video",
+            Theme::Mono => "no colors, bold, italics and reverse video only",
+            Theme::HighContrast => "white prompt, alerts, errors and warnings on solid backgrounds",
+        }
+    }
+
+    /// The startup theme: mono when `NO_COLOR` is set (https://no-color.org)
+    pub fn initial() -> Self {
+        match std::env::var_os("NO_COLOR") {
+            Some(value) if !value.is_empty() => Theme::Mono,
+            _ => Theme::Default,
+        }
+    }
+
+    pub fn style(&self, role: Role) -> ContentStyle {
+        let plain = ContentStyle::new();
+        match (self, role) {
+            (Theme::Default, Role::Prompt) => plain,
+            (Theme::Default, Role::Success) => plain.green(),
+            (Theme::Default, Role::Warning) => plain.yellow(),
+            (Theme::Default, Role::Error) => plain.red(),
+            (Theme::Default, Role::Alert) => plain.red().bold(),
+            (Theme::Default, Role::Countdown) => plain.cyan(),
+            (Theme::Matrix, Role::Prompt) => plain.green().bold(),
+            (Theme::Matrix, Role::Success) => plain.green().bold(),
+            (Theme::Matrix, Role::Warning) => plain.green(),
+            (Theme::Matrix, Role::Error) => plain.green().underlined(),
+            (Theme::Matrix, Role::Alert) => plain.green().bold().reverse(),
+            (Theme::Matrix, Role::Countdown) => plain.dark_green(),
+            (Theme::Mono, Role::Prompt) => plain.bold(),
+            (Theme::Mono, Role::Success) => plain,
+            (Theme::Mono, Role::Warning) => plain.bold(),
+            (Theme::Mono, Role::Error) => plain.italic(),
+            (Theme::Mono, Role::Alert) => plain.bold().reverse(),
+            (Theme::Mono, Role::Countdown) => plain.underlined(),
+            (Theme::HighContrast, Role::Prompt) => plain.white().bold(),
+            (Theme::HighContrast, Role::Success) => plain.black().on(Color::Green),
+            (Theme::HighContrast, Role::Warning) => plain.black().on(Color::Yellow),
+            (Theme::HighContrast, Role::Error) => plain.white().on(Color::DarkRed),
+            (Theme::HighContrast, Role::Alert) => plain.white().on(Color::Red).bold(),
+            (Theme::HighContrast, Role::Countdown) => plain.black().on(Color::Cyan),
+        }
+    }
+
+    /// `text` wrapped in the escape sequences for `role`
+    pub fn paint(&self, role: Role, text: &str) -> String {
+        self.style(role).apply(text).to_string()
+    }
+
+    /// A security alert: `ALERT_PREFIX` in place of any leading `⚠`, styled as an alert
+    pub fn alert(&self, text: &str) -> String {
+        let text = text.trim_start_matches('⚠').trim_start();
+        let text = text.strip_prefix(ALERT_PREFIX).unwrap_or(text);
+        self.paint(Role::Alert, &format!("{}{}", ALERT_PREFIX, text))
+    }
+
+    /// Command output with warning, error and alert lines styled
+    pub fn paint_output(&self, output: &str) -> String {
+        output
+            .split("\r\n")
+            .map(|line| match classify(line) {
+                Some(Role::Alert) => self.alert(line),
+                Some(role) => self.paint(role, line),
+                None => line.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join("\r\n")
+    }
+
+    /// A failed command's message, every line styled as an error except alerts
+    pub fn paint_error(&self, message: &str) -> String {
+        message
+            .split("\r\n")
+            .map(|line| match classify(line) {
+                Some(Role::Alert) => self.alert(line),
+                _ => self.paint(Role::Error, line),
+            })
+            .collect::<Vec<_>>()
+            .join("\r\n")
+    }
+}
+
+/// Threat alerts, errors and warnings by their wording; everything else stays unstyled
+fn classify(line: &str) -> Option<Role> {
+    if ["ALERT", "DETECTED", "CRITICAL"]
+        .iter()
+        .any(|word| line.contains(word))
+    {
+        Some(Role::Alert)
+    } else if line.starts_with("Failed")
+        // "Error: ...", "Input error: ...", "Hook error: ..."
+        || line
+            .split_once(':')
+            .is_some_and(|(head, _)| head.ends_with("error") || head.ends_with("Error"))
+    {
+        Some(Role::Error)
+    } else if line.starts_with('⚠')
+        || line.contains('✗')
+        || line.starts_with("Warning")
+        || line.starts_with("WARNING")
+    {
+        Some(Role::Warning)
+    } else {
+        None
+    }
+}
@@ -0,0 +1,210 @@
+/// File timestamp control for ::touch-match and ::timestomp
+/// Access and modification times are set on every platform; the birth (creation) time
+/// only where the OS allows it (macOS, Windows). The inode change time (ctime) cannot be
+/// set from userspace and always moves to "now".
+use std::fs::{self, File, FileTimes};
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Timestamps applied to a file
+pub struct Timestamps {
+    pub accessed: SystemTime,
+    pub modified: SystemTime,
+    pub created: Option<SystemTime>,
+}
+
+impl Timestamps {
+    /// All three set to the same instant
+    pub fn at(time: SystemTime) -> Self {
+        Timestamps {
+            accessed: time,
+            modified: time,
+            created: Some(time),
+        }
+    }
+
+    /// The timestamps `reference` currently carries
+    pub fn of(reference: &Path) -> Result<Self, String> {
+        let metadata =
+            fs::metadata(reference).map_err(|e| format!("{}: {}", reference.display(), e))?;
+        let read = |time: std::io::Result<SystemTime>| {
+            time.map_err(|e| format!("{}: {}", reference.display(), e))
+        };
+        Ok(Timestamps {
+            accessed: read(metadata.accessed())?,
+            modified: read(metadata.modified())?,
+            created: metadata.created().ok(),
+        })
+    }
+}
+
+/// Apply `times` to `target`. Returns whether the birth time was set as well.
+pub fn apply(target: &Path, times: &Timestamps) -> Result<bool, String> {
+    let file = open_for_times(target).map_err(|e| format!("{}: {}", target.display(), e))?;
+    let mut file_times = FileTimes::new()
+        .set_accessed(times.accessed)
+        .set_modified(times.modified);
+    let birth_time = set_created(&mut file_times, times.created);
+    file.set_times(file_times)
+        .map_err(|e| format!("{}: {}", target.display(), e))?;
+    Ok(birth_time)
+}
+
+#[cfg(target_os = "macos")]
+fn set_created(file_times: &mut FileTimes, created: Option<SystemTime>) -> bool {
+    use std::os::macos::fs::FileTimesExt;
+
+    match created {
+        Some(created) => {
+            *file_times = file_times.set_created(created);
+            true
+        }
+        None => false,
+    }
+}
+
+#[cfg(windows)]
+fn set_created(file_times: &mut FileTimes, created: Option<SystemTime>) -> bool {
+    use std::os::windows::fs::FileTimesExt;
+
+    match created {
+        Some(created) => {
+            *file_times = file_times.set_created(created);
+            true
+        }
+        None => false,
+    }
+}
+
+#[cfg(not(any(target_os = "macos", windows)))]
+fn set_created(_file_times: &mut FileTimes, _created: Option<SystemTime>) -> bool {
+    false
+}
+
+/// Handle that may change timestamps; directories need read access on Unix
+fn open_for_times(target: &Path) -> std::io::Result<File> {
+    if target.is_dir() {
+        File::open(target)
+    } else {
+        File::options().write(true).open(target)
+    }
+}
+
+/// Parse `YYYY-MM-DD[ HH:MM[:SS]]` (also with `T` and an optional `Z`) as UTC, or `@<unix seconds>`
+pub fn parse_datetime(input: &str) -> Result<SystemTime, String> {
+    let invalid = || {
+        format!(
+            "Invalid date/time: {} (expected YYYY-MM-DD[ HH:MM[:SS]] UTC or @<unix seconds>)",
+            input
+        )
+    };
+    let input = input.trim();
+    if let Some(seconds) = input.strip_prefix('@') {
+        let seconds: u64 = seconds.parse().map_err(|_| invalid())?;
+        return Ok(UNIX_EPOCH + Duration::from_secs(seconds));
+    }
+
+    let input = input.strip_suffix('Z').unwrap_or(input);
+    let (date, time) = input
+        .split_once(['T', ' '])
+        .map(|(date, time)| (date, time.trim()))
+        .unwrap_or((input, ""));
+    let date: Vec<u32> = date
+        .split('-')
+        .map(|field| field.parse().ok())
+        .collect::<Option<_>>()
+        .ok_or_else(invalid)?;
+    let time: Vec<u32> = if time.is_empty() {
+        Vec::new()
+    } else {
+        time.split(':')
+            .map(|field| field.parse().ok())
+            .collect::<Option<_>>()
+            .ok_or_else(invalid)?
+    };
+    let (&[year, month, day], hour, minute, second) = (
+        date.as_slice(),
+        time.first().copied().unwrap_or(0),
+        time.get(1).copied().unwrap_or(0),
+        time.get(2).copied().unwrap_or(0),
+    ) else {
+        return Err(invalid());
+    };
+    if time.len() == 1
+        || time.len() > 3
+        || year < 1970
+        || !(1..=12).contains(&month)
+        || day == 0
+        || day > days_in_month(year, month)
+        || hour > 23
+        || minute > 59
+        || second > 59
+    {
+        return Err(invalid());
+    }
+
+    let days = days_from_civil(year, month, day);
+    let seconds = days * 86_400 + hour as u64 * 3600 + minute as u64 * 60 + second as u64;
+    Ok(UNIX_EPOCH + Duration::from_secs(seconds))
+}
+
+/// `YYYY-MM-DD HH:MM:SS UTC`
+pub fn format_datetime(time: SystemTime) -> String {
+    let Ok(since_epoch) = time.duration_since(UNIX_EPOCH) else {
+        return "before 1970".to_string();
+    };
+    let seconds = since_epoch.as_secs();
+    let (year, month, day) = civil_from_days(seconds / 86_400);
+    let time_of_day = seconds % 86_400;
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        time_of_day / 3600,
+        time_of_day / 60 % 60,
+        time_of_day % 60
+    )
+}
+
+fn days_in_month(year: u32, month: u32) -> u32 {
+    match month {
+        2 if year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400)) => {
+            29
+        }
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Days since 1970-01-01 (Howard Hinnant's algorithm, years >= 1970)
+fn days_from_civil(year: u32, month: u32, day: u32) -> u64 {
+    let year = u64::from(if month <= 2 { year - 1 } else { year });
+    let era = year / 400;
+    let year_of_era = year - era * 400;
+    let month = month as u64;
+    let day_of_year =
+        (153 * if month > 2 { month - 3 } else { month + 9 } + 2) / 5 + day as u64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Inverse of `days_from_civil`
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+    (year, month, day)
+}
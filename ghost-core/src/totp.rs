@@ -0,0 +1,202 @@
+/// TOTP (RFC 6238) codes for ::totp, computed from secrets held in the session vault
+/// Secrets are stored as `totp:<algorithm>:<digits>:<period>:<base32>` vault entries.
+use hmac::{Hmac, Mac};
+use sha1::Sha1;
+use sha2::{Sha256, Sha512};
+use std::time::{SystemTime, UNIX_EPOCH};
+use zeroize::Zeroize;
+
+/// Prefix marking a TOTP secret stored in the vault
+const TOTP_PREFIX: &str = "totp:";
+
+const DEFAULT_DIGITS: u32 = 6;
+const DEFAULT_PERIOD: u64 = 30; // seconds
+
+const BASE32_ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TotpAlgorithm {
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+impl TotpAlgorithm {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_uppercase().as_str() {
+            "SHA1" => Some(TotpAlgorithm::Sha1),
+            "SHA256" => Some(TotpAlgorithm::Sha256),
+            "SHA512" => Some(TotpAlgorithm::Sha512),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            TotpAlgorithm::Sha1 => "SHA1",
+            TotpAlgorithm::Sha256 => "SHA256",
+            TotpAlgorithm::Sha512 => "SHA512",
+        }
+    }
+}
+
+/// Turn a base32 secret or an `otpauth://totp/...` URI into a vault entry
+pub fn encode_entry(input: &str) -> Result<String, String> {
+    let input = input.trim();
+    let (mut secret, algorithm, digits, period) = match input.strip_prefix("otpauth://") {
+        Some(uri) => parse_otpauth(uri)?,
+        None => (
+            input.to_string(),
+            TotpAlgorithm::Sha1,
+            DEFAULT_DIGITS,
+            DEFAULT_PERIOD,
+        ),
+    };
+
+    let mut normalized: String = secret
+        .chars()
+        .filter(|c| !matches!(c, ' ' | '-' | '='))
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    secret.zeroize();
+
+    match base32_decode(&normalized) {
+        Some(mut bytes) if !bytes.is_empty() => bytes.zeroize(),
+        _ => {
+            normalized.zeroize();
+            return Err("Invalid TOTP secret (expected base32 or an otpauth:// URI).".to_string());
+        }
+    }
+
+    let entry = format!(
+        "{}{}:{}:{}:{}",
+        TOTP_PREFIX,
+        algorithm.name(),
+        digits,
+        period,
+        normalized
+    );
+    normalized.zeroize();
+    Ok(entry)
+}
+
+/// Current code for a vault entry and the seconds it stays valid
+pub fn current_code(entry: &str) -> Result<(String, u64), String> {
+    let not_totp = || "Vault entry is not a TOTP secret.".to_string();
+    let mut fields = entry
+        .strip_prefix(TOTP_PREFIX)
+        .ok_or_else(not_totp)?
+        .split(':');
+    let (Some(algorithm), Some(digits), Some(period), Some(secret), None) = (
+        fields.next().and_then(TotpAlgorithm::parse),
+        fields.next().and_then(|d| d.parse::<u32>().ok()),
+        fields.next().and_then(|p| p.parse::<u64>().ok()),
+        fields.next(),
+        fields.next(),
+    ) else {
+        return Err(not_totp());
+    };
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|e| format!("System clock error: {}", e))?
+        .as_secs();
+    let mut key = base32_decode(secret).ok_or_else(not_totp)?;
+    let code = hotp(algorithm, &key, now / period, digits);
+    key.zeroize();
+    Ok((code?, period - now % period))
+}
+
+/// HOTP (RFC 4226) with dynamic truncation
+fn hotp(algorithm: TotpAlgorithm, key: &[u8], counter: u64, digits: u32) -> Result<String, String> {
+    let counter = counter.to_be_bytes();
+    let mut digest = match algorithm {
+        TotpAlgorithm::Sha1 => hmac_digest::<Hmac<Sha1>>(key, &counter),
+        TotpAlgorithm::Sha256 => hmac_digest::<Hmac<Sha256>>(key, &counter),
+        TotpAlgorithm::Sha512 => hmac_digest::<Hmac<Sha512>>(key, &counter),
+    }?;
+
+    let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+    let value = u32::from_be_bytes([
+        digest[offset] & 0x7f,
+        digest[offset + 1],
+        digest[offset + 2],
+        digest[offset + 3],
+    ]);
+    digest.zeroize();
+
+    let modulus = 10u64.pow(digits);
+    Ok(format!(
+        "{:0width$}",
+        value as u64 % modulus,
+        width = digits as usize
+    ))
+}
+
+fn hmac_digest<M: Mac + hmac::digest::KeyInit>(
+    key: &[u8],
+    message: &[u8],
+) -> Result<Vec<u8>, String> {
+    let mut mac = <M as Mac>::new_from_slice(key).map_err(|_| "Invalid TOTP key.".to_string())?;
+    mac.update(message);
+    Ok(mac.finalize().into_bytes().to_vec())
+}
+
+/// Secret, algorithm, digits and period from an otpauth URI (without the scheme)
+fn parse_otpauth(uri: &str) -> Result<(String, TotpAlgorithm, u32, u64), String> {
+    let query = uri
+        .strip_prefix("totp/")
+        .and_then(|rest| rest.split_once('?'))
+        .map(|(_, query)| query)
+        .ok_or("Only otpauth://totp/ URIs are supported.")?;
+
+    let mut secret = None;
+    let mut algorithm = TotpAlgorithm::Sha1;
+    let mut digits = DEFAULT_DIGITS;
+    let mut period = DEFAULT_PERIOD;
+    for (name, value) in query.split('&').filter_map(|pair| pair.split_once('=')) {
+        match name.to_ascii_lowercase().as_str() {
+            "secret" => secret = Some(value.to_string()),
+            "algorithm" => {
+                algorithm = TotpAlgorithm::parse(value)
+                    .ok_or_else(|| format!("Unsupported TOTP algorithm: {}", value))?
+            }
+            "digits" => {
+                digits = value
+                    .parse()
+                    .ok()
+                    .filter(|d| (6..=8).contains(d))
+                    .ok_or("TOTP digits must be 6, 7 or 8.")?
+            }
+            "period" => {
+                period = value
+                    .parse()
+                    .ok()
+                    .filter(|p| *p > 0)
+                    .ok_or("Invalid TOTP period.")?
+            }
+            _ => {}
+        }
+    }
+
+    let secret = secret.ok_or("otpauth URI has no secret.")?;
+    Ok((secret, algorithm, digits, period))
+}
+
+/// Decode unpadded RFC 4648 base32 (uppercase)
+fn base32_decode(encoded: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(encoded.len() * 5 / 8);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for c in encoded.bytes() {
+        let value = BASE32_ALPHABET.iter().position(|&a| a == c)? as u32;
+        buffer = (buffer << 5) | value;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+    Some(bytes)
+}
@@ -0,0 +1,565 @@
+/// File-change tracing of child commands, for ::trace
+/// Runs a command and lists the files it and everything it started wrote, created,
+/// removed or renamed, so the traces a tool leaves behind (caches, logs, history and
+/// lock files) can be seen and cleaned up. Two backends:
+///
+/// - strace, when installed: every path-based system call of the process tree is
+///   logged and the successful ones that change something are kept. Exact, and works
+///   unprivileged wherever ptrace is allowed. The log goes to a private tmpfs
+///   directory that is shredded afterwards.
+/// - fanotify, as root: every mount is watched for files modified or closed after
+///   writing while the command runs. Only writes are seen, not removals or renames,
+///   and a write by a process that exited before its event was read cannot be told
+///   apart from one by an unrelated process, so it is counted.
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::process::{Command, Output};
+
+use crate::config::matches_pattern;
+use crate::sandbox::find_in_path;
+use crate::workspace::ScratchDir;
+
+/// Paths written by nearly every command that never count as traces
+pub const ALWAYS_ALLOWED: &[&str] = &["/dev/null", "/dev/tty", "/dev/pts/*", "/proc/*", "/sys/*"];
+
+/// How poll() waits between checks for the end of the command (fanotify)
+#[cfg(target_os = "linux")]
+const POLL_MS: libc::c_int = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceBackend {
+    Strace,
+    Fanotify,
+}
+
+impl TraceBackend {
+    pub fn name(&self) -> &'static str {
+        match self {
+            TraceBackend::Strace => "strace",
+            TraceBackend::Fanotify => "fanotify",
+        }
+    }
+}
+
+/// strace when installed, else fanotify when running as root
+pub fn detect_backend() -> Option<TraceBackend> {
+    if find_in_path("strace").is_some() {
+        return Some(TraceBackend::Strace);
+    }
+    #[cfg(target_os = "linux")]
+    if unsafe { libc::geteuid() } == 0 {
+        return Some(TraceBackend::Fanotify);
+    }
+    None
+}
+
+/// What happened to a path
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    /// Opened for writing or truncated
+    Written,
+    /// A directory, link or device node was made
+    Created,
+    /// Mode, owner, timestamps or extended attributes changed
+    Attributes,
+    Removed,
+    /// Written or created, then removed again: the data may still be on the disk
+    Transient,
+    /// Moved here from the given path
+    Renamed(PathBuf),
+}
+
+impl Change {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Change::Written => "written",
+            Change::Created => "created",
+            Change::Attributes => "attributes",
+            Change::Removed => "removed",
+            Change::Transient => "transient",
+            Change::Renamed(_) => "renamed",
+        }
+    }
+}
+
+/// Every path a traced command changed, with what happened to it last
+pub struct Trace {
+    pub backend: TraceBackend,
+    changes: BTreeMap<PathBuf, Change>,
+}
+
+impl Trace {
+    fn new(backend: TraceBackend) -> Self {
+        Trace {
+            backend,
+            changes: BTreeMap::new(),
+        }
+    }
+
+    fn record(&mut self, path: PathBuf, change: Change) {
+        let existing = self.changes.get(&path);
+        let change = match (existing, change) {
+            (None | Some(Change::Removed | Change::Attributes), change) => change,
+            (Some(_), Change::Removed) => Change::Transient,
+            // Writes to something this command made or moved are part of making it
+            (Some(_), Change::Written | Change::Attributes) => return,
+            (Some(_), change) => change,
+        };
+        self.changes.insert(path, change);
+    }
+
+    fn rename(&mut self, from: PathBuf, to: PathBuf) {
+        // Write-then-rename is how most tools save a file: report the result only
+        let change = match self.changes.remove(&from) {
+            Some(Change::Written | Change::Created | Change::Transient) => Change::Written,
+            Some(Change::Renamed(original)) => Change::Renamed(original),
+            _ => Change::Renamed(from),
+        };
+        self.changes.insert(to, change);
+    }
+
+    /// Changes to paths matching none of `allowed`, and how many others were left out
+    pub fn outside(&self, allowed: &[String]) -> (Vec<(&Path, &Change)>, usize) {
+        let mut outside = Vec::new();
+        let mut hidden = 0;
+        for (path, change) in &self.changes {
+            let name = path.to_string_lossy();
+            let is_allowed = ALWAYS_ALLOWED
+                .iter()
+                .copied()
+                .chain(allowed.iter().map(String::as_str))
+                .any(|pattern| matches_pattern(pattern, &name));
+            if is_allowed {
+                hidden += 1;
+            } else {
+                outside.push((path.as_path(), change));
+            }
+        }
+        (outside, hidden)
+    }
+}
+
+/// Run `command` to completion under `backend`
+pub fn run(backend: TraceBackend, command: Command) -> Result<(Output, Trace), String> {
+    match backend {
+        TraceBackend::Strace => run_strace(command),
+        #[cfg(target_os = "linux")]
+        TraceBackend::Fanotify => run_fanotify(command),
+        #[cfg(not(target_os = "linux"))]
+        TraceBackend::Fanotify => Err("trace: fanotify is only available on Linux.".to_string()),
+    }
+}
+
+/// `command` run under `strace -f`, one timestamped log per process
+fn run_strace(command: Command) -> Result<(Output, Trace), String> {
+    let log = ScratchDir::create("ghost-trace").map_err(|e| format!("trace: {}", e))?;
+    let mut strace = Command::new("strace");
+    strace
+        .args(["-f", "-ff", "-qq", "-y", "-ttt", "-e", "trace=%file", "-o"])
+        .arg(log.path().join("t"))
+        .arg("--")
+        .arg(command.get_program())
+        .args(command.get_args());
+    for (name, value) in command.get_envs() {
+        match value {
+            Some(value) => strace.env(name, value),
+            None => strace.env_remove(name),
+        };
+    }
+    if let Some(dir) = command.get_current_dir() {
+        strace.current_dir(dir);
+    }
+    let output = strace
+        .output()
+        .map_err(|e| format!("trace: strace: {}", e))?;
+
+    let mut lines = Vec::new();
+    for entry in fs::read_dir(log.path()).into_iter().flatten().flatten() {
+        if let Ok(text) = fs::read_to_string(entry.path()) {
+            lines.extend(text.lines().map(str::to_string));
+        }
+    }
+    if lines.is_empty() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!(
+            "trace: strace could not run the command: {}",
+            stderr.lines().last().unwrap_or("no log written")
+        ));
+    }
+    // Fixed-width `seconds.microseconds` stamps: a stable sort on them merges the
+    // processes back into the order the calls happened in
+    lines.sort_by(|a, b| a.split(' ').next().cmp(&b.split(' ').next()));
+
+    let cwd = command
+        .get_current_dir()
+        .map(Path::to_path_buf)
+        .or_else(|| env::current_dir().ok())
+        .unwrap_or_else(|| PathBuf::from("/"));
+    let mut trace = Trace::new(TraceBackend::Strace);
+    for line in &lines {
+        if let Some((name, args, result)) = parse_call(line) {
+            record_call(&mut trace, name, &args, result, &cwd);
+        }
+    }
+    Ok((output, trace))
+}
+
+/// `1700000000.123456 name(arg, arg) = result` → name, arguments, result. Signals and
+/// other notes strace writes between calls are skipped.
+fn parse_call(line: &str) -> Option<(&str, Vec<&str>, &str)> {
+    let (_, call) = line.split_once(' ')?;
+    let open = call.find('(')?;
+    let name = &call[..open];
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return None;
+    }
+    let mut args = Vec::new();
+    let mut depth = 0;
+    let mut start = open + 1;
+    let mut chars = call.char_indices().skip(open + 1);
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => {
+                while let Some((_, c)) = chars.next() {
+                    match c {
+                        '\\' => {
+                            chars.next();
+                        }
+                        '"' => break,
+                        _ => {}
+                    }
+                }
+            }
+            // fd decorations: `3</path>` may hold anything but `>`
+            '<' => {
+                for (_, c) in chars.by_ref() {
+                    if c == '>' {
+                        break;
+                    }
+                }
+            }
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' if depth > 0 => depth -= 1,
+            ',' if depth == 0 => {
+                args.push(call[start..i].trim());
+                start = i + 1;
+            }
+            ')' => {
+                let last = call[start..i].trim();
+                if !last.is_empty() {
+                    args.push(last);
+                }
+                let result = call[i + 1..].trim_start().strip_prefix('=')?.trim();
+                return Some((name, args, result));
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Add what one successful call changed to `trace`
+fn record_call(trace: &mut Trace, name: &str, args: &[&str], result: &str, cwd: &Path) {
+    if result.starts_with('-') || result.starts_with('?') {
+        return;
+    }
+    let path = |index: usize| resolve(None, args.get(index)?, cwd);
+    let at = |dirfd: usize, index: usize| resolve(args.get(dirfd).copied(), args.get(index)?, cwd);
+    let changed = match name {
+        "open" | "openat" | "openat2" | "creat" => {
+            let flags = match name {
+                "open" => args.get(1),
+                "openat" | "openat2" => args.get(2),
+                _ => Some(&"O_WRONLY|O_CREAT|O_TRUNC"),
+            };
+            if !flags.is_some_and(|flags| opens_for_writing(flags)) {
+                return;
+            }
+            let opened = fd_path(result).or_else(|| match name {
+                "openat" | "openat2" => at(0, 1),
+                _ => path(0),
+            });
+            opened.map(|opened| (opened, Change::Written))
+        }
+        "truncate" => path(0).map(|p| (p, Change::Written)),
+        "unlink" | "rmdir" => path(0).map(|p| (p, Change::Removed)),
+        "unlinkat" => at(0, 1).map(|p| (p, Change::Removed)),
+        "mkdir" | "mknod" => path(0).map(|p| (p, Change::Created)),
+        "mkdirat" | "mknodat" => at(0, 1).map(|p| (p, Change::Created)),
+        "symlink" | "link" => path(1).map(|p| (p, Change::Created)),
+        "symlinkat" => at(1, 2).map(|p| (p, Change::Created)),
+        "linkat" => at(2, 3).map(|p| (p, Change::Created)),
+        "chmod" | "chown" | "lchown" | "utime" | "utimes" | "setxattr" | "lsetxattr"
+        | "removexattr" | "lremovexattr" => path(0).map(|p| (p, Change::Attributes)),
+        "fchmodat" | "fchmodat2" | "fchownat" | "futimesat" => {
+            at(0, 1).map(|p| (p, Change::Attributes))
+        }
+        // A NULL path means the file behind the descriptor itself
+        "utimensat" if args.get(1) == Some(&"NULL") => args
+            .first()
+            .and_then(|fd| fd_path(fd))
+            .map(|p| (p, Change::Attributes)),
+        "utimensat" => at(0, 1).map(|p| (p, Change::Attributes)),
+        "rename" => {
+            if let (Some(from), Some(to)) = (path(0), path(1)) {
+                trace.rename(from, to);
+            }
+            None
+        }
+        "renameat" | "renameat2" => {
+            if let (Some(from), Some(to)) = (at(0, 1), at(2, 3)) {
+                trace.rename(from, to);
+            }
+            None
+        }
+        _ => None,
+    };
+    if let Some((path, change)) = changed {
+        trace.record(path, change);
+    }
+}
+
+/// Whether open flags as strace prints them can change the file. O_TMPFILE files have
+/// no name and vanish on close, so they are left out.
+fn opens_for_writing(flags: &str) -> bool {
+    !flags.contains("O_TMPFILE")
+        && ["O_WRONLY", "O_RDWR", "O_CREAT", "O_TRUNC", "O_APPEND"]
+            .iter()
+            .any(|flag| flags.contains(flag))
+}
+
+/// The path strace's `-y` shows for a descriptor: `3</etc/hosts>`, `AT_FDCWD</home/x>`
+fn fd_path(arg: &str) -> Option<PathBuf> {
+    let start = arg.find('<')?;
+    let path = arg[start + 1..].strip_suffix('>')?;
+    path.starts_with('/')
+        .then(|| path_from_bytes(unescape(path)))
+}
+
+/// A quoted path argument made absolute against its directory descriptor or `cwd`
+fn resolve(dirfd: Option<&str>, arg: &str, cwd: &Path) -> Option<PathBuf> {
+    let path = path_from_bytes(unescape(arg.strip_prefix('"')?.strip_suffix('"')?));
+    if path.is_absolute() {
+        return Some(normalize(&path));
+    }
+    let base = dirfd.and_then(fd_path).unwrap_or_else(|| cwd.to_path_buf());
+    Some(normalize(&base.join(path)))
+}
+
+/// Drop `.` and fold `..` without touching the filesystem
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// C-style escapes as strace and /proc/self/mounts print them: `\n`, `\"`, `\\`, octal
+/// `\303` and hex `\xc3`
+fn unescape(text: &str) -> Vec<u8> {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] != b'\\' || i + 1 == bytes.len() {
+            out.push(bytes[i]);
+            i += 1;
+            continue;
+        }
+        let escape = bytes[i + 1];
+        i += 2;
+        let digits = |i: usize, radix: u32, max: usize| {
+            bytes[i..]
+                .iter()
+                .take(max)
+                .take_while(|b| (**b as char).is_digit(radix))
+                .count()
+        };
+        match escape {
+            b'0'..=b'7' => {
+                let len = 1 + digits(i, 8, 2);
+                let octal = std::str::from_utf8(&bytes[i - 1..i - 1 + len]).unwrap_or("0");
+                out.push(u8::from_str_radix(octal, 8).unwrap_or(0));
+                i += len - 1;
+            }
+            b'x' if digits(i, 16, 2) > 0 => {
+                let len = digits(i, 16, 2);
+                let hex = std::str::from_utf8(&bytes[i..i + len]).unwrap_or("0");
+                out.push(u8::from_str_radix(hex, 16).unwrap_or(0));
+                i += len;
+            }
+            b'n' => out.push(b'\n'),
+            b't' => out.push(b'\t'),
+            b'r' => out.push(b'\r'),
+            b'v' => out.push(0x0b),
+            b'f' => out.push(0x0c),
+            other => out.push(other),
+        }
+    }
+    out
+}
+
+#[cfg(unix)]
+fn path_from_bytes(bytes: Vec<u8>) -> PathBuf {
+    use std::os::unix::ffi::OsStringExt;
+
+    PathBuf::from(std::ffi::OsString::from_vec(bytes))
+}
+
+#[cfg(not(unix))]
+fn path_from_bytes(bytes: Vec<u8>) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// `command` run while every mount is watched for writes
+#[cfg(target_os = "linux")]
+fn run_fanotify(mut command: Command) -> Result<(Output, Trace), String> {
+    use std::ffi::CString;
+    use std::os::fd::{FromRawFd, OwnedFd};
+    use std::os::unix::ffi::OsStrExt;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    let fd = unsafe {
+        libc::fanotify_init(
+            libc::FAN_CLASS_NOTIF | libc::FAN_CLOEXEC | libc::FAN_NONBLOCK,
+            (libc::O_RDONLY | libc::O_LARGEFILE | libc::O_CLOEXEC) as libc::c_uint,
+        )
+    };
+    if fd < 0 {
+        return Err(format!(
+            "trace: fanotify: {}",
+            std::io::Error::last_os_error()
+        ));
+    }
+    let group = unsafe { OwnedFd::from_raw_fd(fd) };
+    let mut marked = 0;
+    for mount in mount_points() {
+        let Ok(c_path) = CString::new(mount.as_os_str().as_bytes()) else {
+            continue;
+        };
+        let result = unsafe {
+            libc::fanotify_mark(
+                fd,
+                libc::FAN_MARK_ADD | libc::FAN_MARK_MOUNT,
+                libc::FAN_MODIFY | libc::FAN_CLOSE_WRITE,
+                libc::AT_FDCWD,
+                c_path.as_ptr(),
+            )
+        };
+        if result == 0 {
+            marked += 1;
+        }
+    }
+    if marked == 0 {
+        return Err("trace: fanotify: no mount could be watched.".to_string());
+    }
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let stop_flag = Arc::clone(&stop);
+    let reader = std::thread::spawn(move || read_events(group, &stop_flag));
+    let output = command.output();
+    stop.store(true, Ordering::SeqCst);
+    let written = reader.join().unwrap_or_default();
+    let output = output.map_err(|e| format!("Failed to execute process: {}", e))?;
+
+    let mut trace = Trace::new(TraceBackend::Fanotify);
+    for path in written {
+        trace.record(path, Change::Written);
+    }
+    Ok((output, trace))
+}
+
+/// Mount points from /proc/self/mounts
+#[cfg(target_os = "linux")]
+fn mount_points() -> Vec<PathBuf> {
+    fs::read_to_string("/proc/self/mounts")
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| line.split_whitespace().nth(1))
+        .map(|mount| path_from_bytes(unescape(mount)))
+        .collect()
+}
+
+/// Paths of the files written by other processes until `stop` is set and the queue is
+/// empty
+#[cfg(target_os = "linux")]
+fn read_events(group: std::os::fd::OwnedFd, stop: &std::sync::atomic::AtomicBool) -> Vec<PathBuf> {
+    use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+    use std::sync::atomic::Ordering;
+
+    let own = std::process::id() as i32;
+    let header = std::mem::size_of::<libc::fanotify_event_metadata>();
+    let mut buffer = [0u8; 8192];
+    let mut paths = Vec::new();
+    loop {
+        let mut poll = libc::pollfd {
+            fd: group.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        if unsafe { libc::poll(&mut poll, 1, POLL_MS) } <= 0 {
+            if stop.load(Ordering::SeqCst) {
+                break;
+            }
+            continue;
+        }
+        let read =
+            unsafe { libc::read(group.as_raw_fd(), buffer.as_mut_ptr().cast(), buffer.len()) };
+        let mut offset = 0;
+        while read > 0 && offset + header <= read as usize {
+            let event: libc::fanotify_event_metadata =
+                unsafe { std::ptr::read_unaligned(buffer[offset..].as_ptr().cast()) };
+            if (event.event_len as usize) < header {
+                break;
+            }
+            offset += event.event_len as usize;
+            // No descriptor when the queue overflowed
+            if event.fd < 0 {
+                continue;
+            }
+            let file = unsafe { OwnedFd::from_raw_fd(event.fd) };
+            if event.pid == own || !started_here(event.pid as u32) {
+                continue;
+            }
+            if let Ok(path) = fs::read_link(format!("/proc/self/fd/{}", file.as_raw_fd())) {
+                paths.push(path);
+            }
+        }
+    }
+    paths
+}
+
+/// Whether `pid` descends from this process. One that already exited cannot be looked
+/// up and is assumed to.
+#[cfg(target_os = "linux")]
+fn started_here(pid: u32) -> bool {
+    let own = std::process::id();
+    let mut pid = pid;
+    let mut first = true;
+    loop {
+        let Ok(stat) = fs::read_to_string(format!("/proc/{}/stat", pid)) else {
+            return first;
+        };
+        first = false;
+        // `pid (comm) state ppid ...`; comm may itself contain spaces or parentheses
+        let parent = stat
+            .rsplit_once(')')
+            .and_then(|(_, rest)| rest.split_whitespace().nth(1))
+            .and_then(|ppid| ppid.parse::<u32>().ok());
+        match parent {
+            Some(parent) if parent == own => return true,
+            Some(parent) if parent > 1 => pid = parent,
+            _ => return false,
+        }
+    }
+}
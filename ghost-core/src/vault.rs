@@ -0,0 +1,118 @@
+/// Session vault: named secrets kept encrypted in RAM for the lifetime of the shell
+/// Secrets are only ever decrypted to be pushed to the clipboard, never printed. Each
+/// ciphertext sits in a guarded allocation of its own.
+use std::collections::BTreeMap;
+use std::ops::Range;
+use std::time::Instant;
+use zeroize::Zeroize;
+
+use crate::clipboard::{open_bytes, seal_bytes};
+use crate::guarded::GuardedBytes;
+use crate::keys::SubKey;
+use crate::secret::SecretString;
+
+/// One encrypted vault entry
+struct VaultEntry {
+    nonce: [u8; 12],
+    ciphertext: GuardedBytes,
+    added_at: Instant,
+}
+
+/// name → secret store encrypted under the session's vault subkey
+pub struct Vault {
+    key: SubKey,
+    entries: BTreeMap<String, VaultEntry>,
+}
+
+impl Vault {
+    pub fn new(key: SubKey) -> Self {
+        Vault {
+            key,
+            entries: BTreeMap::new(),
+        }
+    }
+
+    /// Encrypt `secret` under `name`, replacing (and zeroizing) any previous one.
+    /// Returns true if an existing entry was replaced.
+    pub fn add(&mut self, name: &str, secret: &str) -> Result<bool, String> {
+        let (nonce, mut ciphertext) = self.key.expose(|key| seal_bytes(key, secret.as_bytes()))?;
+        let entry = VaultEntry {
+            nonce,
+            ciphertext: GuardedBytes::new(&ciphertext),
+            added_at: Instant::now(),
+        };
+        ciphertext.zeroize();
+        match self.entries.insert(name.to_string(), entry) {
+            Some(mut previous) => {
+                previous.nonce.zeroize();
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Decrypt the secret stored under `name`
+    pub fn get(&self, name: &str) -> Result<SecretString, String> {
+        let entry = self
+            .entries
+            .get(name)
+            .ok_or_else(|| format!("No vault entry named '{}'.", name))?;
+
+        self.key
+            .expose(|key| {
+                entry
+                    .ciphertext
+                    .expose(|ciphertext| open_bytes(key, &entry.nonce, ciphertext))
+            })?
+            .into_text()
+    }
+
+    /// Describe the stored entries without decrypting them
+    pub fn list(&self) -> Vec<String> {
+        self.entries
+            .iter()
+            .map(|(name, entry)| {
+                format!(
+                    "  {}: added {}s ago",
+                    name,
+                    entry.added_at.elapsed().as_secs()
+                )
+            })
+            .collect()
+    }
+
+    /// Where the encrypted entries sit in memory, for ::meminfo
+    pub fn regions(&self) -> Vec<Range<usize>> {
+        self.entries
+            .values()
+            .map(|entry| entry.ciphertext.region())
+            .collect()
+    }
+
+    /// Zeroize and drop the entry `name`
+    pub fn remove(&mut self, name: &str) -> Result<(), String> {
+        match self.entries.remove(name) {
+            Some(mut entry) => {
+                entry.nonce.zeroize();
+                Ok(())
+            }
+            None => Err(format!("No vault entry named '{}'.", name)),
+        }
+    }
+
+    /// Zeroize and drop every entry
+    pub fn purge(&mut self) -> usize {
+        let count = self.entries.len();
+        for entry in self.entries.values_mut() {
+            entry.nonce.zeroize();
+        }
+        self.entries.clear();
+        count
+    }
+}
+
+impl Drop for Vault {
+    fn drop(&mut self) {
+        self.purge();
+    }
+}
@@ -0,0 +1,65 @@
+/// Disconnect watchdog: notices a closed terminal or a dead parent process
+/// Closing the terminal window sends SIGHUP, and on Linux the death of the parent
+/// process is turned into one with PR_SET_PDEATHSIG. The default action would end the
+/// shell on the spot without running any destructor, so the handler only raises a
+/// flag that the prompt loop checks between events. A parent dying on systems without
+/// PR_SET_PDEATHSIG shows up as the shell being re-parented.
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+
+/// Set by the SIGHUP handler
+static HANGUP: AtomicBool = AtomicBool::new(false);
+
+/// Parent process id at install time, 0 before
+static PARENT: AtomicI32 = AtomicI32::new(0);
+
+#[cfg(unix)]
+extern "C" fn on_hangup(_signal: libc::c_int) {
+    HANGUP.store(true, Ordering::SeqCst);
+}
+
+/// Catch SIGHUP and ask for one when the parent process dies
+pub fn install() {
+    #[cfg(unix)]
+    {
+        PARENT.store(unsafe { libc::getppid() }, Ordering::SeqCst);
+        unsafe {
+            libc::signal(
+                libc::SIGHUP,
+                on_hangup as extern "C" fn(libc::c_int) as libc::sighandler_t,
+            );
+        }
+        #[cfg(target_os = "linux")]
+        unsafe {
+            libc::prctl(libc::PR_SET_PDEATHSIG, libc::SIGHUP as libc::c_ulong);
+        }
+        // The parent may have gone before PR_SET_PDEATHSIG was in place
+        if unsafe { libc::getppid() } != PARENT.load(Ordering::SeqCst) {
+            HANGUP.store(true, Ordering::SeqCst);
+        }
+    }
+}
+
+/// Whether the session lost its terminal or its parent
+pub fn disconnected() -> bool {
+    if HANGUP.load(Ordering::SeqCst) {
+        return true;
+    }
+    #[cfg(unix)]
+    {
+        let parent = PARENT.load(Ordering::SeqCst);
+        if parent != 0 && unsafe { libc::getppid() } != parent {
+            return true;
+        }
+    }
+    false
+}
+
+/// Stop watching: a detached session outlives its terminal and its parent
+pub fn release() {
+    HANGUP.store(false, Ordering::SeqCst);
+    PARENT.store(0, Ordering::SeqCst);
+    #[cfg(target_os = "linux")]
+    unsafe {
+        libc::prctl(libc::PR_SET_PDEATHSIG, 0 as libc::c_ulong);
+    }
+}
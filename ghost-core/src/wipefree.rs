@@ -0,0 +1,164 @@
+/// Free-space wiping for ::wipe-free
+/// Filler files are written into the filesystem until it reports no space left, synced,
+/// and deleted again, so blocks released by earlier deletions are overwritten with
+/// random data. Best effort: SSDs (wear levelling), copy-on-write filesystems and
+/// snapshots can keep old blocks out of reach.
+use chacha20poly1305::aead::OsRng;
+use rand::rngs::StdRng;
+use rand::{RngCore, SeedableRng};
+use std::fs::{self, File, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Bytes written per call; also the granularity of progress and throttling
+const WIPE_CHUNK: usize = 1024 * 1024;
+
+/// Filler files are split at this size to stay under per-file limits (FAT32)
+const MAX_FILLER_SIZE: u64 = 2 * 1024 * 1024 * 1024;
+
+/// Outcome of a wipe
+pub struct WipeSummary {
+    pub written: u64,
+    pub elapsed: Duration,
+    pub cancelled: bool,
+}
+
+/// Free bytes on the filesystem holding `path`
+#[cfg(unix)]
+pub fn free_space(path: &Path) -> Result<u64, String> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = std::ffi::CString::new(path.as_os_str().as_bytes())
+        .map_err(|_| format!("{}: invalid path.", path.display()))?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) } != 0 {
+        return Err(format!(
+            "{}: {}",
+            path.display(),
+            std::io::Error::last_os_error()
+        ));
+    }
+    Ok(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+#[cfg(not(unix))]
+pub fn free_space(path: &Path) -> Result<u64, String> {
+    Err(format!(
+        "{}: free-space wiping is not supported on this platform.",
+        path.display()
+    ))
+}
+
+/// Fill the free space under `dir` with random data, then delete the filler.
+/// `rate` caps throughput in bytes per second. `progress(written, total)` is called
+/// after every chunk; returning false cancels the wipe.
+pub fn wipe_free(
+    dir: &Path,
+    rate: Option<u64>,
+    mut progress: impl FnMut(u64, u64) -> bool,
+) -> Result<WipeSummary, String> {
+    if !dir.is_dir() {
+        return Err(format!("{}: not a directory.", dir.display()));
+    }
+    let total = free_space(dir)?;
+
+    let mut seed = [0u8; 32];
+    OsRng.fill_bytes(&mut seed);
+    let mut rng = StdRng::from_seed(seed);
+    let mut chunk = vec![0u8; WIPE_CHUNK];
+
+    let started = Instant::now();
+    let mut fillers: Vec<PathBuf> = Vec::new();
+    let mut file: Option<File> = None;
+    let mut file_size = 0u64;
+    let mut written = 0u64;
+    let mut cancelled = false;
+    let result = loop {
+        if file.is_none() || file_size >= MAX_FILLER_SIZE {
+            if let Some(previous) = file.take() {
+                let _ = previous.sync_all();
+            }
+            match create_filler(dir) {
+                Ok((path, created)) => {
+                    fillers.push(path);
+                    file = Some(created);
+                    file_size = 0;
+                }
+                Err(e) if is_full(&e) => break Ok(()),
+                Err(e) => break Err(format!("{}: {}", dir.display(), e)),
+            }
+        }
+        let Some(current) = file.as_mut() else {
+            break Ok(());
+        };
+
+        rng.fill_bytes(&mut chunk);
+        match current.write(&chunk) {
+            Ok(0) => break Ok(()),
+            Ok(n) => {
+                file_size += n as u64;
+                written += n as u64;
+            }
+            Err(e) if is_full(&e) => break Ok(()),
+            // Per-file size limit: continue in a new filler
+            Err(e) if e.kind() == ErrorKind::FileTooLarge => file_size = MAX_FILLER_SIZE,
+            Err(e) => break Err(format!("{}: {}", dir.display(), e)),
+        }
+
+        if !progress(written, total.max(written)) {
+            cancelled = true;
+            break Ok(());
+        }
+        if let Some(rate) = rate.filter(|rate| *rate > 0) {
+            let due = Duration::from_secs_f64(written as f64 / rate as f64);
+            if let Some(ahead) = due.checked_sub(started.elapsed()) {
+                thread::sleep(ahead);
+            }
+        }
+    };
+
+    if let Some(last) = file.take() {
+        let _ = last.sync_all();
+    }
+    chunk.fill(0);
+    for filler in &fillers {
+        let _ = fs::remove_file(filler);
+    }
+    result.map(|_| WipeSummary {
+        written,
+        elapsed: started.elapsed(),
+        cancelled,
+    })
+}
+
+/// Out of space, or out of quota
+fn is_full(error: &std::io::Error) -> bool {
+    matches!(
+        error.kind(),
+        ErrorKind::StorageFull | ErrorKind::QuotaExceeded
+    )
+}
+
+/// New private filler file under `dir`
+fn create_filler(dir: &Path) -> std::io::Result<(PathBuf, File)> {
+    let mut suffix = [0u8; 6];
+    OsRng.fill_bytes(&mut suffix);
+    let path = dir.join(format!(
+        ".ghost-wipe-{}",
+        suffix
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect::<String>()
+    ));
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let file = options.open(&path)?;
+    Ok((path, file))
+}
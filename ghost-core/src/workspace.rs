@@ -0,0 +1,335 @@
+/// RAM-backed scratch directories for ::workspace, ::ephemeral and ::trace
+/// The workspace is a private 0700 directory on an existing tmpfs ($XDG_RUNTIME_DIR or
+/// /dev/shm); when the chosen location is not RAM-backed, a fresh tmpfs is mounted on it
+/// (root only). While active the shell works inside it and TMPDIR points at it; on
+/// teardown every file is overwritten and removed, and the mount (if any) is detached.
+///
+/// The ephemeral home is the same kind of directory handed to child processes as HOME
+/// and their XDG cache, data and state directories, so shell, pager, editor and REPL
+/// history files (`.python_history`, `.viminfo`, `.lesshst`, ...) land in RAM and are
+/// shredded with it. gsh itself keeps the real HOME.
+use chacha20poly1305::aead::OsRng;
+use rand::RngCore;
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::filecrypt::shred_file;
+
+/// Size limit of a tmpfs mounted by ::workspace
+#[cfg(target_os = "linux")]
+const TMPFS_OPTIONS: &str = "mode=0700,size=512m";
+
+/// statfs f_type of tmpfs
+#[cfg(target_os = "linux")]
+const TMPFS_MAGIC: libc::c_long = 0x0102_1994;
+
+/// Existing tmpfs locations tried before mounting a new one
+#[cfg(target_os = "linux")]
+const TMPFS_CANDIDATES: &[&str] = &["XDG_RUNTIME_DIR", "/dev/shm"];
+
+pub struct Workspace {
+    path: PathBuf,
+    mounted: bool,
+    previous_dir: Option<PathBuf>,
+    previous_tmpdir: Option<OsString>,
+    active: bool,
+}
+
+impl Workspace {
+    /// Create the workspace under `base` (or a default tmpfs), cd into it and export TMPDIR
+    #[cfg(target_os = "linux")]
+    pub fn create(base: Option<&Path>) -> Result<Self, String> {
+        let base = match base {
+            Some(base) => base.to_path_buf(),
+            None => default_tmpfs().unwrap_or_else(env::temp_dir),
+        };
+        // Already RAM-backed: a private subdirectory is enough. Otherwise mount one.
+        let mounted = !is_tmpfs(&base);
+        if mounted && unsafe { libc::geteuid() } != 0 {
+            return Err(format!(
+                "workspace: {} is not on tmpfs and mounting one needs root.",
+                base.display()
+            ));
+        }
+
+        let path = base.join(random_name("ghost-workspace"));
+        create_private_dir(&path).map_err(|e| format!("workspace: {}", e))?;
+        if mounted {
+            if let Err(e) = mount_tmpfs(&path) {
+                let _ = fs::remove_dir(&path);
+                return Err(e);
+            }
+        }
+
+        let previous_dir = env::current_dir().ok();
+        let previous_tmpdir = env::var_os("TMPDIR");
+        let mut workspace = Workspace {
+            path,
+            mounted,
+            previous_dir,
+            previous_tmpdir,
+            active: true,
+        };
+        if let Err(e) = env::set_current_dir(&workspace.path) {
+            workspace.teardown();
+            return Err(format!("workspace: {}", e));
+        }
+        env::set_var("TMPDIR", &workspace.path);
+        Ok(workspace)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn create(_base: Option<&Path>) -> Result<Self, String> {
+        Err("workspace: only supported on Linux.".to_string())
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// True if the workspace is its own tmpfs mount
+    pub fn is_mounted(&self) -> bool {
+        self.mounted
+    }
+
+    /// Wipe and remove the workspace now. Returns how many files were destroyed.
+    pub fn destroy(mut self) -> usize {
+        self.teardown()
+    }
+
+    fn teardown(&mut self) -> usize {
+        if !self.active {
+            return 0;
+        }
+        self.active = false;
+
+        // Step out before removing it, and give children their old TMPDIR back
+        if env::current_dir().is_ok_and(|dir| dir.starts_with(&self.path)) {
+            let fallback = self.previous_dir.clone().unwrap_or_else(env::temp_dir);
+            let _ = env::set_current_dir(fallback);
+        }
+        match self.previous_tmpdir.take() {
+            Some(tmpdir) => env::set_var("TMPDIR", tmpdir),
+            None => env::remove_var("TMPDIR"),
+        }
+
+        let destroyed = wipe_dir(&self.path);
+        #[cfg(target_os = "linux")]
+        if self.mounted {
+            unmount(&self.path);
+        }
+        let _ = fs::remove_dir(&self.path);
+        destroyed
+    }
+}
+
+impl Drop for Workspace {
+    fn drop(&mut self) {
+        self.teardown();
+    }
+}
+
+/// Directories below the ephemeral home and the variables pointing at them
+const EPHEMERAL_DIRS: &[(&str, &str)] = &[
+    ("HOME", ""),
+    ("XDG_CACHE_HOME", ".cache"),
+    ("XDG_DATA_HOME", ".local/share"),
+    ("XDG_STATE_HOME", ".local/state"),
+];
+
+/// Private directory on the first writable tmpfs, shredded when dropped
+pub struct ScratchDir {
+    path: PathBuf,
+}
+
+impl ScratchDir {
+    /// Create `prefix-<random>` on $XDG_RUNTIME_DIR or /dev/shm
+    #[cfg(target_os = "linux")]
+    pub fn create(prefix: &str) -> Result<Self, String> {
+        let base = default_tmpfs()
+            .ok_or_else(|| "no writable tmpfs ($XDG_RUNTIME_DIR or /dev/shm).".to_string())?;
+        let path = base.join(random_name(prefix));
+        create_private_dir(&path)?;
+        Ok(ScratchDir { path })
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn create(_prefix: &str) -> Result<Self, String> {
+        Err("only supported on Linux.".to_string())
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Wipe and remove the directory now. Returns how many files were destroyed.
+    pub fn destroy(mut self) -> usize {
+        self.teardown()
+    }
+
+    fn teardown(&mut self) -> usize {
+        let destroyed = wipe_dir(&self.path);
+        let _ = fs::remove_dir(&self.path);
+        destroyed
+    }
+}
+
+impl Drop for ScratchDir {
+    fn drop(&mut self) {
+        self.teardown();
+    }
+}
+
+/// Throwaway HOME and XDG directories for child processes (::ephemeral)
+pub struct EphemeralHome {
+    dir: ScratchDir,
+}
+
+impl EphemeralHome {
+    /// Create the directory tree on the first writable tmpfs
+    pub fn create() -> Result<Self, String> {
+        let dir = ScratchDir::create("ghost-home").map_err(|e| format!("ephemeral: {}", e))?;
+        for (_, subdir) in EPHEMERAL_DIRS.iter().filter(|(_, dir)| !dir.is_empty()) {
+            fs::create_dir_all(dir.path().join(subdir)).map_err(|e| format!("ephemeral: {}", e))?;
+        }
+        Ok(EphemeralHome { dir })
+    }
+
+    pub fn path(&self) -> &Path {
+        self.dir.path()
+    }
+
+    /// `(variable, directory)` for each variable a child gets
+    pub fn variables(&self) -> impl Iterator<Item = (&'static str, PathBuf)> + '_ {
+        EPHEMERAL_DIRS.iter().map(|(name, dir)| match *dir {
+            "" => (*name, self.path().to_path_buf()),
+            dir => (*name, self.path().join(dir)),
+        })
+    }
+
+    /// Wipe and remove the directory now. Returns how many files were destroyed.
+    pub fn destroy(self) -> usize {
+        self.dir.destroy()
+    }
+}
+
+/// `prefix-` and 12 random hex digits
+#[cfg(target_os = "linux")]
+fn random_name(prefix: &str) -> String {
+    let mut suffix = [0u8; 6];
+    OsRng.fill_bytes(&mut suffix);
+    format!(
+        "{}-{}",
+        prefix,
+        suffix
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect::<String>()
+    )
+}
+
+/// Overwrite and remove everything below `dir`, returning the number of files destroyed
+fn wipe_dir(dir: &Path) -> usize {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return 0;
+    };
+    let mut destroyed = 0;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        match fs::symlink_metadata(&path) {
+            Ok(metadata) if metadata.is_dir() => {
+                destroyed += wipe_dir(&path);
+                let _ = fs::remove_dir(&path);
+            }
+            Ok(metadata) if metadata.is_file() => {
+                if shred_file(&path).is_err() {
+                    let _ = fs::remove_file(&path);
+                }
+                destroyed += 1;
+            }
+            // Symlinks, sockets, FIFOs: never follow, just unlink
+            _ => {
+                let _ = fs::remove_file(&path);
+            }
+        }
+    }
+    destroyed
+}
+
+/// First writable tmpfs among $XDG_RUNTIME_DIR and /dev/shm
+#[cfg(target_os = "linux")]
+fn default_tmpfs() -> Option<PathBuf> {
+    TMPFS_CANDIDATES
+        .iter()
+        .filter_map(|candidate| {
+            if candidate.starts_with('/') {
+                Some(PathBuf::from(candidate))
+            } else {
+                env::var_os(candidate).map(PathBuf::from)
+            }
+        })
+        .find(|dir| is_tmpfs(dir) && fs::metadata(dir).is_ok_and(|m| !m.permissions().readonly()))
+}
+
+#[cfg(target_os = "linux")]
+fn create_private_dir(path: &Path) -> Result<(), String> {
+    use std::os::unix::fs::DirBuilderExt;
+
+    fs::DirBuilder::new()
+        .mode(0o700)
+        .create(path)
+        .map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+#[cfg(target_os = "linux")]
+fn is_tmpfs(path: &Path) -> bool {
+    use std::os::unix::ffi::OsStrExt;
+
+    let Ok(c_path) = std::ffi::CString::new(path.as_os_str().as_bytes()) else {
+        return false;
+    };
+    let mut stat: libc::statfs = unsafe { std::mem::zeroed() };
+    unsafe { libc::statfs(c_path.as_ptr(), &mut stat) == 0 && stat.f_type == TMPFS_MAGIC }
+}
+
+#[cfg(target_os = "linux")]
+fn mount_tmpfs(path: &Path) -> Result<(), String> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let target = CString::new(path.as_os_str().as_bytes())
+        .map_err(|_| "workspace: invalid path.".to_string())?;
+    let options = CString::new(TMPFS_OPTIONS).map_err(|_| "workspace: invalid options.")?;
+    let result = unsafe {
+        libc::mount(
+            c"tmpfs".as_ptr(),
+            target.as_ptr(),
+            c"tmpfs".as_ptr(),
+            libc::MS_NOSUID | libc::MS_NODEV,
+            options.as_ptr() as *const libc::c_void,
+        )
+    };
+    if result == 0 {
+        Ok(())
+    } else {
+        Err(format!(
+            "workspace: mount tmpfs on {}: {}",
+            path.display(),
+            std::io::Error::last_os_error()
+        ))
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn unmount(path: &Path) {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    if let Ok(target) = CString::new(path.as_os_str().as_bytes()) {
+        unsafe {
+            libc::umount2(target.as_ptr(), libc::MNT_DETACH);
+        }
+    }
+}
@@ -0,0 +1,569 @@
+/// Password-authenticated file transfer for ::send / ::recv
+/// The sender shows a short code (`417-guitar-raven`: a nameplate and two words); the
+/// receiver types it in. SPAKE2 over Ristretto255 turns the code into a strong shared
+/// key, so there are no pre-shared keys, and a wrong guess aborts the transfer instead
+/// of allowing an offline attack. The receiver finds the sender by UDP broadcast on the
+/// local network, or connects to `host[:port]` directly.
+///
+///   receiver -> sender   "GSHW1" | nameplate (u16 BE) | Y
+///   sender -> receiver   X | confirmation A
+///   receiver -> sender   confirmation B
+///   sender -> receiver   nonce prefix (7) | frames: last flag (1) | length (u32 BE) | ciphertext
+///   receiver -> sender   "OK"
+///
+/// The first frame carries `file name \0 size`; the rest are the file in 64 KiB chunks
+/// (ChaCha20-Poly1305 STREAM, as in `.ghost` files).
+use chacha20poly1305::aead::stream::{DecryptorBE32, EncryptorBE32};
+use chacha20poly1305::aead::{KeyInit, OsRng};
+use chacha20poly1305::ChaCha20Poly1305;
+use curve25519_dalek::constants::RISTRETTO_BASEPOINT_POINT;
+use curve25519_dalek::ristretto::{CompressedRistretto, RistrettoPoint};
+use curve25519_dalek::scalar::Scalar;
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac};
+use rand::{Rng, RngCore};
+use sha2::{Digest, Sha256, Sha512};
+use std::fs::{File, OpenOptions};
+use std::io::{ErrorKind, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs, UdpSocket};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+use zeroize::Zeroize;
+
+use crate::filecrypt::read_full;
+use crate::genpass::{usable_words, EMBEDDED_WORDLIST};
+
+/// TCP port the sender listens on, and UDP port for discovery
+pub const WORMHOLE_PORT: u16 = 47291;
+
+/// How long ::send waits for the receiver
+pub const SEND_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// How long ::recv looks for the sender on the local network
+const DISCOVERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+const IO_TIMEOUT: Duration = Duration::from_secs(30);
+
+const MAGIC: &[u8; 5] = b"GSHW1";
+const DISCOVER: &[u8; 6] = b"GSHW1?";
+const ANNOUNCE: &[u8; 6] = b"GSHW1!";
+
+const CHUNK_SIZE: usize = 64 * 1024;
+const TAG_LEN: usize = 16;
+
+/// Domain separation for the SPAKE2 points and the derived keys
+const M_LABEL: &[u8] = b"ghost-shell wormhole v1 M";
+const N_LABEL: &[u8] = b"ghost-shell wormhole v1 N";
+const KEY_INFO: &[u8] = b"ghost-shell wormhole v1 keys";
+
+/// Words in a code after the nameplate (2 x 12.9 bits)
+const CODE_WORDS: usize = 2;
+
+/// Keys agreed for one transfer
+struct TransferKeys {
+    data: [u8; 32],
+    confirm_sender: [u8; 32],
+    confirm_receiver: [u8; 32],
+    transcript: [u8; 32],
+}
+
+impl Drop for TransferKeys {
+    fn drop(&mut self) {
+        self.data.zeroize();
+        self.confirm_sender.zeroize();
+        self.confirm_receiver.zeroize();
+    }
+}
+
+/// A fresh transfer code
+pub fn generate_code() -> String {
+    let words = usable_words(EMBEDDED_WORDLIST);
+    let mut rng = OsRng;
+    let mut code = rng.gen_range(1..1000u16).to_string();
+    for _ in 0..CODE_WORDS {
+        code.push('-');
+        code.push_str(words[rng.gen_range(0..words.len())]);
+    }
+    code
+}
+
+/// Nameplate of `code`, after checking its shape
+fn nameplate(code: &str) -> Result<u16, String> {
+    let invalid = || format!("Invalid code '{}' (expected e.g. 417-guitar-raven).", code);
+    let (number, words) = code.split_once('-').ok_or_else(invalid)?;
+    if words.split('-').count() != CODE_WORDS || words.split('-').any(str::is_empty) {
+        return Err(invalid());
+    }
+    number.parse().map_err(|_| invalid())
+}
+
+/// Offer `path` under `code`: waits for the receiver, runs the exchange and streams the
+/// file. `cancelled` is polled while waiting. Returns the bytes sent.
+pub fn send_file(
+    path: &Path,
+    code: &str,
+    port: u16,
+    mut cancelled: impl FnMut() -> bool,
+) -> Result<u64, String> {
+    let expected = nameplate(code)?;
+    let mut file = File::open(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let size = file
+        .metadata()
+        .map_err(|e| format!("{}: {}", path.display(), e))?
+        .len();
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .ok_or_else(|| format!("{}: not a file.", path.display()))?;
+
+    let listener =
+        TcpListener::bind(("0.0.0.0", port)).map_err(|e| format!("send: port {}: {}", port, e))?;
+    listener
+        .set_nonblocking(true)
+        .map_err(|e| format!("send: {}", e))?;
+    // Discovery is best effort: without it the receiver has to name this host
+    let discovery = UdpSocket::bind(("0.0.0.0", WORMHOLE_PORT))
+        .and_then(|socket| socket.set_nonblocking(true).map(|_| socket))
+        .ok();
+
+    let deadline = Instant::now() + SEND_TIMEOUT;
+    loop {
+        if cancelled() {
+            return Err("Cancelled.".to_string());
+        }
+        if Instant::now() >= deadline {
+            return Err("send: nobody received the file in time.".to_string());
+        }
+        if let Some(socket) = &discovery {
+            answer_discovery(socket, expected, port);
+        }
+        let mut stream = match listener.accept() {
+            Ok((stream, _)) => stream,
+            Err(e) if e.kind() == ErrorKind::WouldBlock => {
+                thread::sleep(Duration::from_millis(50));
+                continue;
+            }
+            Err(e) => return Err(format!("send: {}", e)),
+        };
+        stream
+            .set_nonblocking(false)
+            .and_then(|_| stream.set_read_timeout(Some(IO_TIMEOUT)))
+            .and_then(|_| stream.set_write_timeout(Some(IO_TIMEOUT)))
+            .map_err(|e| format!("send: {}", e))?;
+
+        let mut hello = [0u8; MAGIC.len() + 2 + 32];
+        if stream.read_exact(&mut hello).is_err()
+            || &hello[..MAGIC.len()] != MAGIC
+            || u16::from_be_bytes([hello[5], hello[6]]) != expected
+        {
+            // Someone else's transfer or a stray connection; keep waiting
+            continue;
+        }
+        let mut peer = [0u8; 32];
+        peer.copy_from_slice(&hello[7..]);
+
+        // One attempt per code: a wrong guess ends the transfer
+        let keys = sender_exchange(&mut stream, code, &peer)?;
+        return stream_file(&mut stream, &keys, &mut file, &name, size);
+    }
+}
+
+/// Reply to a receiver looking for `expected` with our TCP port
+fn answer_discovery(socket: &UdpSocket, expected: u16, port: u16) {
+    let mut buffer = [0u8; 16];
+    while let Ok((n, from)) = socket.recv_from(&mut buffer) {
+        if n == DISCOVER.len() + 2
+            && &buffer[..DISCOVER.len()] == DISCOVER
+            && u16::from_be_bytes([buffer[6], buffer[7]]) == expected
+        {
+            let mut reply = ANNOUNCE.to_vec();
+            reply.extend_from_slice(&port.to_be_bytes());
+            let _ = socket.send_to(&reply, from);
+        }
+    }
+}
+
+/// Receive the file offered under `code` into `dir`, from `peer` (`host[:port]`) or
+/// whoever answers on the local network. Returns the file written and its size.
+pub fn receive_file(code: &str, peer: Option<&str>, dir: &Path) -> Result<(PathBuf, u64), String> {
+    let plate = nameplate(code)?;
+    let address = match peer {
+        Some(peer) => resolve(peer)?,
+        None => discover(plate)?,
+    };
+    let mut stream = TcpStream::connect_timeout(&address, IO_TIMEOUT)
+        .map_err(|e| format!("recv: {}: {}", address, e))?;
+    stream
+        .set_read_timeout(Some(IO_TIMEOUT))
+        .and_then(|_| stream.set_write_timeout(Some(IO_TIMEOUT)))
+        .map_err(|e| format!("recv: {}", e))?;
+
+    let keys = receiver_exchange(&mut stream, code, plate)?;
+    receive_stream(&mut stream, &keys, dir)
+}
+
+fn resolve(peer: &str) -> Result<SocketAddr, String> {
+    let target = if peer
+        .rsplit_once(':')
+        .is_some_and(|(_, port)| port.parse::<u16>().is_ok())
+    {
+        peer.to_string()
+    } else {
+        format!("{}:{}", peer, WORMHOLE_PORT)
+    };
+    target
+        .to_socket_addrs()
+        .ok()
+        .and_then(|mut addresses| addresses.next())
+        .ok_or_else(|| format!("recv: cannot resolve {}", peer))
+}
+
+/// Broadcast for the sender holding `plate` and wait for its answer
+fn discover(plate: u16) -> Result<SocketAddr, String> {
+    let socket = UdpSocket::bind(("0.0.0.0", 0))
+        .and_then(|socket| socket.set_broadcast(true).map(|_| socket))
+        .and_then(|socket| {
+            socket
+                .set_read_timeout(Some(Duration::from_secs(1)))
+                .map(|_| socket)
+        })
+        .map_err(|e| format!("recv: {}", e))?;
+    let mut query = DISCOVER.to_vec();
+    query.extend_from_slice(&plate.to_be_bytes());
+
+    let deadline = Instant::now() + DISCOVERY_TIMEOUT;
+    let mut buffer = [0u8; 16];
+    while Instant::now() < deadline {
+        let _ = socket.send_to(&query, ("255.255.255.255", WORMHOLE_PORT));
+        let _ = socket.send_to(&query, ("127.0.0.1", WORMHOLE_PORT));
+        while let Ok((n, from)) = socket.recv_from(&mut buffer) {
+            if n == ANNOUNCE.len() + 2 && &buffer[..ANNOUNCE.len()] == ANNOUNCE {
+                let port = u16::from_be_bytes([buffer[6], buffer[7]]);
+                return Ok(SocketAddr::new(from.ip(), port));
+            }
+        }
+    }
+    Err(
+        "recv: no sender found on the local network. Pass its address: ::recv <code> <host[:port]>"
+            .to_string(),
+    )
+}
+
+// --- SPAKE2 ---
+
+fn label_point(label: &[u8]) -> RistrettoPoint {
+    let mut wide = [0u8; 64];
+    wide.copy_from_slice(&Sha512::digest(label));
+    RistrettoPoint::from_uniform_bytes(&wide)
+}
+
+fn password_scalar(code: &str) -> Scalar {
+    let mut wide = [0u8; 64];
+    wide.copy_from_slice(
+        &Sha512::new()
+            .chain_update(KEY_INFO)
+            .chain_update(code)
+            .finalize(),
+    );
+    let scalar = Scalar::from_bytes_mod_order_wide(&wide);
+    wide.zeroize();
+    scalar
+}
+
+fn random_scalar() -> Scalar {
+    let mut wide = [0u8; 64];
+    OsRng.fill_bytes(&mut wide);
+    let scalar = Scalar::from_bytes_mod_order_wide(&wide);
+    wide.zeroize();
+    scalar
+}
+
+fn decode_point(bytes: &[u8; 32]) -> Result<RistrettoPoint, String> {
+    CompressedRistretto(*bytes)
+        .decompress()
+        .filter(|point| *point != RistrettoPoint::default())
+        .ok_or_else(|| "Handshake failed: invalid key from peer.".to_string())
+}
+
+/// Derive the transfer keys from the SPAKE2 transcript
+fn derive_keys(
+    code: &str,
+    x_msg: &[u8; 32],
+    y_msg: &[u8; 32],
+    shared: &RistrettoPoint,
+) -> TransferKeys {
+    let mut transcript: [u8; 32] = Sha256::new()
+        .chain_update(KEY_INFO)
+        .chain_update(Sha256::digest(code))
+        .chain_update(x_msg)
+        .chain_update(y_msg)
+        .chain_update(shared.compress().as_bytes())
+        .finalize()
+        .into();
+    let hkdf = Hkdf::<Sha256>::new(None, &transcript);
+    let mut okm = [0u8; 96];
+    hkdf.expand(KEY_INFO, &mut okm)
+        .expect("96 bytes is a valid HKDF-SHA256 length");
+    let mut keys = TransferKeys {
+        data: [0u8; 32],
+        confirm_sender: [0u8; 32],
+        confirm_receiver: [0u8; 32],
+        transcript: [0u8; 32],
+    };
+    keys.data.copy_from_slice(&okm[..32]);
+    keys.confirm_sender.copy_from_slice(&okm[32..64]);
+    keys.confirm_receiver.copy_from_slice(&okm[64..]);
+    keys.transcript = Sha256::digest(transcript).into();
+    okm.zeroize();
+    transcript.zeroize();
+    keys
+}
+
+fn confirmation(key: &[u8; 32], transcript: &[u8; 32]) -> [u8; 32] {
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(key).expect("HMAC takes any key length");
+    mac.update(transcript);
+    mac.finalize().into_bytes().into()
+}
+
+fn verify_confirmation(key: &[u8; 32], transcript: &[u8; 32], tag: &[u8]) -> bool {
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(key).expect("HMAC takes any key length");
+    mac.update(transcript);
+    mac.verify_slice(tag).is_ok()
+}
+
+/// Sender side (SPAKE2 party A, blinded with M)
+fn sender_exchange(
+    stream: &mut TcpStream,
+    code: &str,
+    y_msg: &[u8; 32],
+) -> Result<TransferKeys, String> {
+    let w = password_scalar(code);
+    let x = random_scalar();
+    let x_msg = (x * RISTRETTO_BASEPOINT_POINT + w * label_point(M_LABEL))
+        .compress()
+        .to_bytes();
+    let y = decode_point(y_msg)?;
+    let shared = x * (y - w * label_point(N_LABEL));
+    let keys = derive_keys(code, &x_msg, y_msg, &shared);
+
+    let mut reply = x_msg.to_vec();
+    reply.extend_from_slice(&confirmation(&keys.confirm_sender, &keys.transcript));
+    stream
+        .write_all(&reply)
+        .map_err(|e| format!("send: {}", e))?;
+
+    let mut tag = [0u8; 32];
+    stream
+        .read_exact(&mut tag)
+        .map_err(|_| "send: the receiver used a wrong code. Transfer aborted.".to_string())?;
+    if !verify_confirmation(&keys.confirm_receiver, &keys.transcript, &tag) {
+        return Err("send: the receiver used a wrong code. Transfer aborted.".to_string());
+    }
+    Ok(keys)
+}
+
+/// Receiver side (SPAKE2 party B, blinded with N)
+fn receiver_exchange(
+    stream: &mut TcpStream,
+    code: &str,
+    plate: u16,
+) -> Result<TransferKeys, String> {
+    let w = password_scalar(code);
+    let y = random_scalar();
+    let y_msg = (y * RISTRETTO_BASEPOINT_POINT + w * label_point(N_LABEL))
+        .compress()
+        .to_bytes();
+
+    let mut hello = MAGIC.to_vec();
+    hello.extend_from_slice(&plate.to_be_bytes());
+    hello.extend_from_slice(&y_msg);
+    stream
+        .write_all(&hello)
+        .map_err(|e| format!("recv: {}", e))?;
+
+    let mut reply = [0u8; 64];
+    stream
+        .read_exact(&mut reply)
+        .map_err(|e| format!("recv: {}", e))?;
+    let mut x_msg = [0u8; 32];
+    x_msg.copy_from_slice(&reply[..32]);
+    let x = decode_point(&x_msg)?;
+    let shared = y * (x - w * label_point(M_LABEL));
+    let keys = derive_keys(code, &x_msg, &y_msg, &shared);
+
+    if !verify_confirmation(&keys.confirm_sender, &keys.transcript, &reply[32..]) {
+        return Err("recv: wrong code (or someone is interfering). Nothing received.".to_string());
+    }
+    stream
+        .write_all(&confirmation(&keys.confirm_receiver, &keys.transcript))
+        .map_err(|e| format!("recv: {}", e))?;
+    Ok(keys)
+}
+
+// --- DATA ---
+
+fn write_frame(stream: &mut TcpStream, last: bool, ciphertext: &[u8]) -> Result<(), String> {
+    let mut frame = Vec::with_capacity(5 + ciphertext.len());
+    frame.push(u8::from(last));
+    frame.extend_from_slice(&(ciphertext.len() as u32).to_be_bytes());
+    frame.extend_from_slice(ciphertext);
+    stream.write_all(&frame).map_err(|e| format!("send: {}", e))
+}
+
+fn read_frame(stream: &mut TcpStream) -> Result<(bool, Vec<u8>), String> {
+    let mut header = [0u8; 5];
+    stream
+        .read_exact(&mut header)
+        .map_err(|e| format!("recv: {}", e))?;
+    let length = u32::from_be_bytes([header[1], header[2], header[3], header[4]]) as usize;
+    if length > CHUNK_SIZE + TAG_LEN {
+        return Err("recv: sender sent an oversized frame.".to_string());
+    }
+    let mut ciphertext = vec![0u8; length];
+    stream
+        .read_exact(&mut ciphertext)
+        .map_err(|e| format!("recv: {}", e))?;
+    Ok((header[0] == 1, ciphertext))
+}
+
+fn stream_file(
+    stream: &mut TcpStream,
+    keys: &TransferKeys,
+    file: &mut File,
+    name: &str,
+    size: u64,
+) -> Result<u64, String> {
+    let encryption_failed = |_| "send: encryption failed.".to_string();
+    let mut nonce_prefix = [0u8; 7];
+    OsRng.fill_bytes(&mut nonce_prefix);
+    stream
+        .write_all(&nonce_prefix)
+        .map_err(|e| format!("send: {}", e))?;
+    let cipher = ChaCha20Poly1305::new((&keys.data).into());
+    let mut encryptor = EncryptorBE32::from_aead(cipher, (&nonce_prefix).into());
+
+    let header = format!("{}\0{}", name, size);
+    let sealed = encryptor
+        .encrypt_next(header.as_bytes())
+        .map_err(encryption_failed)?;
+    write_frame(stream, false, &sealed)?;
+
+    let mut buffer = vec![0u8; CHUNK_SIZE];
+    let mut sent = 0u64;
+    let result = loop {
+        let n = match read_full(file, &mut buffer) {
+            Ok(n) => n,
+            Err(e) => break Err(format!("send: {}", e)),
+        };
+        sent += n as u64;
+        if n < CHUNK_SIZE {
+            break encryptor
+                .encrypt_last(&buffer[..n])
+                .map_err(encryption_failed)
+                .and_then(|sealed| write_frame(stream, true, &sealed));
+        }
+        if let Err(e) = encryptor
+            .encrypt_next(&buffer[..n])
+            .map_err(encryption_failed)
+            .and_then(|sealed| write_frame(stream, false, &sealed))
+        {
+            break Err(e);
+        }
+    };
+    buffer.zeroize();
+    result?;
+
+    let mut ack = [0u8; 2];
+    match stream.read_exact(&mut ack) {
+        Ok(()) if &ack == b"OK" => Ok(sent),
+        _ => Err("send: the receiver did not confirm the transfer.".to_string()),
+    }
+}
+
+fn receive_stream(
+    stream: &mut TcpStream,
+    keys: &TransferKeys,
+    dir: &Path,
+) -> Result<(PathBuf, u64), String> {
+    let tampered = |_| "recv: transfer corrupted or tampered with.".to_string();
+    let mut nonce_prefix = [0u8; 7];
+    stream
+        .read_exact(&mut nonce_prefix)
+        .map_err(|e| format!("recv: {}", e))?;
+    let cipher = ChaCha20Poly1305::new((&keys.data).into());
+    let mut decryptor = DecryptorBE32::from_aead(cipher, (&nonce_prefix).into());
+
+    let (_, sealed) = read_frame(stream)?;
+    let header = decryptor
+        .decrypt_next(sealed.as_slice())
+        .map_err(tampered)?;
+    let header = String::from_utf8_lossy(&header).into_owned();
+    let (name, size) = header
+        .split_once('\0')
+        .and_then(|(name, size)| Some((name, size.parse::<u64>().ok()?)))
+        .ok_or("recv: malformed transfer header.")?;
+    // Only the final component: the sender doesn't choose where the file lands
+    let name = Path::new(name)
+        .file_name()
+        .filter(|name| !name.is_empty())
+        .ok_or("recv: invalid file name from sender.")?;
+    let path = dir.join(name);
+
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options
+        .open(&path)
+        .map_err(|e| format!("{}: {}", path.display(), e))?;
+
+    let mut decryptor = Some(decryptor);
+    let result = (|| -> Result<u64, String> {
+        let mut received = 0u64;
+        loop {
+            let (last, sealed) = read_frame(stream)?;
+            let mut chunk = match decryptor.take() {
+                Some(final_chunk) if last => final_chunk.decrypt_last(sealed.as_slice()),
+                Some(mut next) => {
+                    let chunk = next.decrypt_next(sealed.as_slice());
+                    decryptor = Some(next);
+                    chunk
+                }
+                None => unreachable!("the loop ends at the last frame"),
+            }
+            .map_err(tampered)?;
+            received += chunk.len() as u64;
+            let written = file.write_all(&chunk);
+            chunk.zeroize();
+            written.map_err(|e| format!("{}: {}", path.display(), e))?;
+            if received > size {
+                return Err("recv: sender sent more data than announced.".to_string());
+            }
+            if last {
+                break;
+            }
+        }
+        if received != size {
+            return Err("recv: transfer ended early.".to_string());
+        }
+        file.sync_all()
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+        Ok(received)
+    })();
+
+    match result {
+        Ok(received) => {
+            let _ = stream.write_all(b"OK");
+            Ok((path, received))
+        }
+        Err(e) => {
+            drop(file);
+            let _ = std::fs::remove_file(&path);
+            Err(e)
+        }
+    }
+}
@@ -0,0 +1,97 @@
+/// X11 keystroke interception checks for `::security-status`
+/// Any X client can read the keyboard: an active keyboard grab routes every key to the
+/// grabbing client, and XInput2 raw events or the RECORD extension let a client watch
+/// keys without taking them. The grab is found by trying one: XGrabKeyboard fails with
+/// AlreadyGrabbed while another client holds it. The X server has no request that lists
+/// who selected raw events or enabled a RECORD context, so those listeners are looked
+/// for as known sniffer processes instead. Nothing is checked without `$DISPLAY`.
+use std::fs;
+use x11rb::connection::Connection;
+use x11rb::protocol::xproto::{ConnectionExt, GrabMode, GrabStatus};
+use x11rb::CURRENT_TIME;
+
+/// Programs that log keys through XInput, RECORD or X events: (name, arguments that
+/// make it a listener; empty when the program always is)
+const SNIFFERS: &[(&str, &[&str])] = &[
+    ("xinput", &["test", "test-xi2"]),
+    ("xev", &["-root"]),
+    ("cnee", &["--record", "-rec"]),
+    ("xmacrorec2", &[]),
+    ("xspy", &[]),
+    ("logkeys", &[]),
+    ("xkeylogger", &[]),
+    ("xkeylog", &[]),
+];
+
+/// Threats found on the X display named by `$DISPLAY`, if there is one
+pub fn detect_keyboard_interception() -> Vec<String> {
+    if std::env::var_os("DISPLAY").is_none_or(|display| display.is_empty()) {
+        return Vec::new();
+    }
+    let mut threats = Vec::new();
+    if keyboard_grabbed() == Some(true) {
+        threats.push(
+            "X11 keyboard grab held by another client (keystrokes may be intercepted)".to_string(),
+        );
+    }
+    threats.extend(sniffer_processes());
+    threats
+}
+
+/// Whether another client holds an active keyboard grab; None when the display is
+/// unavailable. A free keyboard is grabbed and released at once.
+fn keyboard_grabbed() -> Option<bool> {
+    let (connection, screen) = x11rb::connect(None).ok()?;
+    let root = connection.setup().roots.get(screen)?.root;
+    let status = connection
+        .grab_keyboard(false, root, CURRENT_TIME, GrabMode::ASYNC, GrabMode::ASYNC)
+        .ok()?
+        .reply()
+        .ok()?
+        .status;
+    if status == GrabStatus::SUCCESS {
+        let _ = connection.ungrab_keyboard(CURRENT_TIME);
+        let _ = connection.flush();
+    }
+    Some(status == GrabStatus::ALREADY_GRABBED)
+}
+
+/// Running processes that match `SNIFFERS`
+fn sniffer_processes() -> Vec<String> {
+    let mut threats = Vec::new();
+    let Ok(processes) = fs::read_dir("/proc") else {
+        return threats;
+    };
+    for entry in processes.flatten() {
+        let pid = match entry.file_name().into_string() {
+            Ok(pid) if pid.chars().all(|c| c.is_ascii_digit()) => pid,
+            _ => continue,
+        };
+        let Ok(cmdline) = fs::read(format!("/proc/{}/cmdline", pid)) else {
+            continue;
+        };
+        let args: Vec<String> = cmdline
+            .split(|&b| b == 0)
+            .filter(|arg| !arg.is_empty())
+            .map(|arg| String::from_utf8_lossy(arg).into_owned())
+            .collect();
+        let Some(program) = args
+            .first()
+            .map(|arg| arg.rsplit('/').next().unwrap_or(arg))
+        else {
+            continue;
+        };
+        let listening = SNIFFERS.iter().any(|(name, flags)| {
+            *name == program
+                && (flags.is_empty() || args[1..].iter().any(|arg| flags.contains(&arg.as_str())))
+        });
+        if listening {
+            threats.push(format!(
+                "X11 keystroke sniffer running: {} (PID: {})",
+                args.join(" "),
+                pid
+            ));
+        }
+    }
+    threats
+}
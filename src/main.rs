@@ -1,528 +1,615 @@
-mod clipboard;
-mod security;
-
 use crossterm::{
-    cursor::MoveToColumn,
-    event::{self, Event, KeyCode, KeyEvent, KeyModifiers},
+    cursor::{MoveTo, MoveToColumn, MoveUp, RestorePosition, SavePosition},
+    event::{DisableBracketedPaste, EnableBracketedPaste, Event, KeyCode, KeyEvent, KeyModifiers},
     execute, queue,
     style::Print,
-    terminal::{disable_raw_mode, enable_raw_mode, Clear, ClearType},
+    terminal::{disable_raw_mode, enable_raw_mode, Clear, ClearType, SetTitle},
 };
 use std::env;
-use std::ffi::CString;
-use std::fs;
-use std::io::{self, Write};
-use std::path::Path;
-use std::process::Command;
+use std::io::{self, Read, Write};
+use std::time::Duration;
 use zeroize::Zeroize;
 
-use crate::clipboard::SecureClipboard;
-use crate::security::{initialize_security, is_debugger_present, SecurityStatus};
+use ghost_core::config::{config_path, load_config, PROMPT_DIR};
+#[cfg(unix)]
+use ghost_core::detach::{self, release_terminal, Relay, Verifier};
+use ghost_core::honeypot::{self, Honeypot};
+use ghost_core::keys::SessionKeys;
+use ghost_core::posture::PostureMonitor;
+use ghost_core::profile::{mask_process, SecurityProfile};
+use ghost_core::remote::ssh_session;
+use ghost_core::script::ScrubbingAllocator;
+use ghost_core::secret::SecretString;
+use ghost_core::security::{apply_platform_sandbox, block_ptrace_attach, record_maps_baseline};
+use ghost_core::shell::{
+    confirm, mask_title, read_secret, restore_title, sanitize_screen, CommandResult, GhostShell,
+    SecureBuffer, DEFAULT_TITLE, RESET_SCROLL_REGION,
+};
+use ghost_core::term::{self, parse_script, start_headless, Output, HEADLESS_SIZE};
+use ghost_core::theme::Role;
+use ghost_core::watchdog;
 
 // --- CONSTANTS ---
-const GHOST_COMMAND_PREFIX: &str = "::";
-
-// --- ENUMS ---
-
-/// Result of command execution
-enum CommandResult {
-    /// No output, continue normally
-    NoOp,
-    /// Command produced output
-    Output(String),
-    /// Exit the shell
-    Exit,
+const MASTER_PASSPHRASE_FLAG: &str = "--master-passphrase"; // derive session keys from a passphrase
+const PROFILE_FLAG: &str = "--profile"; // security profile to start with
+const HEADLESS_FLAG: &str = "--headless"; // run a key script from stdin on an in-memory screen
+const ATTACH_FLAG: &str = "--attach"; // connect to a ::detach'ed session
+const STATUS_BAR_MIN_ROWS: u16 = 3; // smaller terminals get no status bar
+const SECRET_WARNING_MIN_WIDTH: usize = 16; // narrower room shows no inline warning
+
+/// Zeroes memory freed by ::run scripts and config hooks
+#[global_allocator]
+static ALLOCATOR: ScrubbingAllocator = ScrubbingAllocator;
+
+/// `--profile <name>` (or `--profile=<name>`) from the command line, standard by default
+fn profile_from_args() -> Result<SecurityProfile, String> {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let name = args.iter().enumerate().find_map(|(i, arg)| {
+        if arg == PROFILE_FLAG {
+            Some(args.get(i + 1).map(String::as_str).unwrap_or_default())
+        } else {
+            arg.strip_prefix(PROFILE_FLAG)?.strip_prefix('=')
+        }
+    });
+    match name {
+        None => Ok(SecurityProfile::Standard),
+        Some(name) => SecurityProfile::parse(name).ok_or_else(|| {
+            format!(
+                "Unknown profile '{}'. Choose standard, paranoid or stealth.",
+                name
+            )
+        }),
+    }
 }
 
-// --- STRUCTURES ---
-
-/// Main Ghost Shell state (reserved for future refactoring)
-#[allow(dead_code)]
-struct GhostShell {
-    buffer: SecureBuffer,
-    security_status: SecurityStatus,
-    clipboard: SecureClipboard,
-    clipboard_timeout: u64, // seconds
-    encryption_enabled: bool,
+/// Session keys from the master passphrase, falling back to a random key if none was given
+fn session_keys_from_passphrase(passphrase: Option<&str>) -> io::Result<SessionKeys> {
+    let keys = match passphrase {
+        Some(p) if !p.is_empty() => SessionKeys::from_passphrase(p),
+        _ => Err("No passphrase given.".to_string()),
+    };
+    match keys {
+        Ok(keys) => Ok(keys),
+        Err(e) => {
+            write!(term::stdout(), "{} Using a random session key.\r\n", e)?;
+            Ok(SessionKeys::random())
+        }
+    }
 }
 
-#[allow(dead_code)]
-impl GhostShell {
-    fn new() -> Result<Self, String> {
-        let security_status = initialize_security();
-        let encryption_enabled = true; // Default to encrypted clipboard
-        let clipboard_timeout = 30; // 30 seconds default
-
-        let clipboard = SecureClipboard::new(encryption_enabled)?;
-
-        Ok(GhostShell {
-            buffer: SecureBuffer::new(),
-            security_status,
-            clipboard,
-            clipboard_timeout,
-            encryption_enabled,
-        })
+/// With the honeypot armed, the master passphrase gets its allowed attempts; after the
+/// last wrong one the launch is left in the fake shell, which never returns
+fn unlock_or_trap(honeypot: &Honeypot) -> io::Result<(Option<SecretString>, SessionKeys)> {
+    for attempt in 1..=honeypot.attempts() {
+        let passphrase = read_secret("Master passphrase: ")?;
+        let keys = passphrase
+            .as_ref()
+            .map(|p| p.expose().as_str())
+            .filter(|p| !p.is_empty())
+            .and_then(|p| SessionKeys::from_passphrase(p).ok())
+            .filter(|keys| honeypot.accepts(keys));
+        if let Some(keys) = keys {
+            return Ok((passphrase, keys));
+        }
+        drop(passphrase);
+        if attempt < honeypot.attempts() {
+            write!(term::stdout(), "Wrong passphrase.\r\n")?;
+        }
     }
+    honeypot::trap(honeypot)
 }
 
-/// SecureBuffer holds command input and history
-/// Note: We implement Drop manually to ensure history is zeroized
-struct SecureBuffer {
-    content: String,
-    history: Vec<String>,
-    history_index: usize, // Points to index in history. history.len() = new line.
-    cursor_pos: usize,    // Cursor position within 'content' (chars)
-    command_count: usize, // Track number of commands executed
-    paranoid_mode: bool,  // Auto-panic on threat detection
+fn get_current_prompt(template: &str) -> String {
+    let current_dir = env::current_dir()
+        .unwrap_or_else(|_| "/".into())
+        .file_name()
+        .unwrap_or_else(|| "gsh".as_ref())
+        .to_string_lossy()
+        .to_string();
+    template.replace(PROMPT_DIR, &current_dir)
 }
 
-/// Custom Drop implementation to securely zeroize all sensitive data
-impl Drop for SecureBuffer {
-    fn drop(&mut self) {
-        // Zeroize the current command buffer
-        self.content.zeroize();
-
-        // Zeroize each command in history
-        for cmd in self.history.iter_mut() {
-            cmd.zeroize();
-        }
-        self.history.clear();
+/// Draw the whole input line: grade badge, prompt and the part of the line that fits
+fn redraw_line(stdout: &mut Output, buffer: &mut SecureBuffer) -> io::Result<()> {
+    draw_line(stdout, buffer, true)
+}
 
-        // Reset counters (not sensitive, but good hygiene)
-        self.history_index = 0;
-        self.cursor_pos = 0;
-        self.command_count = 0;
-        self.paranoid_mode = false;
-    }
+/// Bring the input line up to date after an edit, repainting only from the first
+/// changed character, or just moving the cursor when nothing changed
+fn update_line(stdout: &mut Output, buffer: &mut SecureBuffer) -> io::Result<()> {
+    draw_line(stdout, buffer, false)
 }
 
-impl SecureBuffer {
-    fn new() -> Self {
-        SecureBuffer {
-            content: String::new(),
-            history: Vec::new(),
-            history_index: 0,
-            cursor_pos: 0,
-            command_count: 0,
-            paranoid_mode: false, // Can be enabled with ::paranoid command
-        }
+fn draw_line(stdout: &mut Output, buffer: &mut SecureBuffer, full: bool) -> io::Result<()> {
+    let prompt = get_current_prompt(buffer.prompt());
+    let (badge, badge_width) = buffer.grade_badge();
+    let start = badge_width + prompt.chars().count();
+    let columns = term::size().map_or(80, |(cols, _)| cols as usize);
+    // A line wider than the screen scrolls sideways; the last column stays free so the
+    // cursor never wraps onto the next row
+    let width = columns.saturating_sub(start + 1).max(1);
+    let line = buffer.line_mut();
+    let (scroll, scrolled) = line.scroll(width);
+    let changed = line.take_changed();
+    let repaint_from = if full || scrolled {
+        Some(scroll)
+    } else {
+        changed.map(|changed| changed.max(scroll))
+    };
+
+    if full {
+        queue!(
+            stdout,
+            MoveToColumn(0),
+            Print(badge),
+            Print(buffer.theme().paint(Role::Prompt, &prompt))
+        )?;
     }
-
-    // --- MANIPULATION ---
-
-    fn insert(&mut self, c: char) {
-        if self.cursor_pos >= self.content.len() {
-            self.content.push(c);
-        } else {
-            self.content.insert(self.cursor_pos, c);
+    if let Some(from) = repaint_from {
+        let line = buffer.line();
+        let (head, tail) = line.span(from, scroll + width - from);
+        queue!(
+            stdout,
+            MoveToColumn((start + from - scroll) as u16),
+            Clear(ClearType::UntilNewLine),
+            Print(head),
+            Print(tail)
+        )?;
+        // A secret typed as an argument gets a warning after the line, where it fits
+        // (checked only then, so a long paste is not scanned on every key)
+        let line_width = start + (line.len() - scroll).min(width);
+        let room = columns.saturating_sub(line_width + 3);
+        if room >= SECRET_WARNING_MIN_WIDTH {
+            if let Some(warning) = buffer.secret_warning() {
+                let warning: String = if warning.chars().count() > room {
+                    warning.chars().take(room - 1).chain(['…']).collect()
+                } else {
+                    warning
+                };
+                queue!(
+                    stdout,
+                    Print("  "),
+                    Print(buffer.theme().paint(Role::Warning, &warning))
+                )?;
+            }
         }
-        self.cursor_pos += 1;
     }
+    queue!(
+        stdout,
+        MoveToColumn((start + buffer.line().cursor() - scroll) as u16)
+    )?;
+    stdout.flush()?;
+    Ok(())
+}
 
-    fn backspace(&mut self) {
-        if self.cursor_pos > 0 {
-            self.content.remove(self.cursor_pos - 1);
-            self.cursor_pos -= 1;
-        }
+/// Keep the last row out of the scroll region so output scrolls above the status bar.
+/// Re-applied after every command, since full-screen children reset the region.
+fn reserve_status_row(stdout: &mut Output) -> io::Result<()> {
+    let rows = term::size().map(|(_, rows)| rows).unwrap_or(0);
+    if rows < STATUS_BAR_MIN_ROWS {
+        return execute!(stdout, Print(RESET_SCROLL_REGION));
     }
+    // Step off the last row first (scrolling if we are on it), since setting the
+    // region leaves the cursor where it was
+    execute!(
+        stdout,
+        Print("\n"),
+        MoveUp(1),
+        SavePosition,
+        Print(format!("\x1b[1;{}r", rows - 1)),
+        RestorePosition
+    )
+}
 
-    fn move_left(&mut self) {
-        if self.cursor_pos > 0 {
-            self.cursor_pos -= 1;
-        }
+/// Draw the status bar on the reserved last row without moving the input cursor
+fn draw_status_bar(stdout: &mut Output, status: &str) -> io::Result<()> {
+    let rows = term::size().map(|(_, rows)| rows).unwrap_or(0);
+    if rows < STATUS_BAR_MIN_ROWS {
+        return Ok(());
     }
+    queue!(
+        stdout,
+        SavePosition,
+        MoveTo(0, rows - 1),
+        Clear(ClearType::CurrentLine),
+        Print(status),
+        RestorePosition
+    )?;
+    stdout.flush()
+}
 
-    fn move_right(&mut self) {
-        if self.cursor_pos < self.content.len() {
-            self.cursor_pos += 1;
-        }
-    }
+/// Run the current input line and show its result. Returns false when the shell should exit.
+fn submit_line(stdout: &mut Output, buffer: &mut SecureBuffer) -> io::Result<bool> {
+    write!(stdout, "\r\n")?;
 
-    // --- HISTORY ---
+    // Process command and handle result
+    let result = buffer.execute_line();
+    reserve_status_row(stdout)?;
 
-    fn history_up(&mut self) {
-        if self.history_index > 0 {
-            self.history_index -= 1;
-            if let Some(cmd) = self.history.get(self.history_index) {
-                self.content = cmd.clone();
-                self.cursor_pos = self.content.len();
-            }
+    match result {
+        CommandResult::Exit => return Ok(false),
+        CommandResult::Output(output) => {
+            write!(stdout, "{}\r\n", buffer.theme().paint_output(&output))?;
+            buffer.commit_history();
+            buffer.clear_state();
         }
-    }
-
-    fn history_down(&mut self) {
-        if self.history_index < self.history.len() {
-            self.history_index += 1;
-            if self.history_index == self.history.len() {
-                self.content.clear();
-                self.cursor_pos = 0;
-            } else if let Some(cmd) = self.history.get(self.history_index) {
-                self.content = cmd.clone();
-                self.cursor_pos = self.content.len();
+        CommandResult::Error(message) => {
+            write!(stdout, "{}\r\n", buffer.theme().paint_error(&message))?;
+            buffer.commit_history();
+            buffer.clear_state();
+        }
+        CommandResult::NoOp => {
+            buffer.commit_history();
+            buffer.clear_state();
+        }
+        CommandResult::Insert(mut text) => {
+            buffer.commit_history();
+            buffer.clear_state();
+            for c in text.chars() {
+                buffer.insert(c);
             }
+            text.zeroize();
         }
     }
+    #[cfg(unix)]
+    if let Some(verifier) = buffer.take_detach(false) {
+        detach(stdout, verifier)?;
+    }
+    match buffer.take_tab_request() {
+        Some(Ok(banner)) => write!(stdout, "{}\r\n", banner)?,
+        Some(Err(e)) => write!(stdout, "{}\r\n", buffer.theme().paint_error(&e))?,
+        None => {}
+    }
+    // Children like ssh or vim, or their output, may have retitled the window
+    execute!(stdout, SetTitle(buffer.title()))?;
+    redraw_line(stdout, buffer)?;
+    Ok(true)
+}
 
-    fn commit_history(&mut self) {
-        if !self.content.trim().is_empty() {
-            // Avoid duplicates at the end
-            if self.history.last() != Some(&self.content) {
-                self.history.push(self.content.clone());
-            }
-        }
-        self.history_index = self.history.len();
+/// Hand the session over to its socket (see `ghost_core::detach`). The terminal is
+/// wiped and given its modes back; the prompt loop carries on for attached clients.
+#[cfg(unix)]
+fn detach(stdout: &mut Output, verifier: Verifier) -> io::Result<()> {
+    let relay = match Relay::listen(verifier, term::size().unwrap_or(HEADLESS_SIZE)) {
+        Ok(relay) => relay,
+        Err(e) => return write!(stdout, "Detach failed: {}\r\n", e),
+    };
+    // A terminal that hung up takes none of this
+    let _ = execute!(stdout, DisableBracketedPaste);
+    let _ = sanitize_screen(stdout);
+    let _ = restore_title(stdout);
+    let _ = write!(
+        stdout,
+        "Ghost Shell session {} detached. Reattach with: ghost-shell --attach {}\r\n",
+        std::process::id(),
+        std::process::id()
+    );
+    let _ = stdout.flush();
+    let _ = disable_raw_mode();
+    release_terminal();
+    watchdog::release();
+    term::start_relay(relay);
+    Ok(())
+}
+
+/// The terminal or the parent went away: a session armed with `::detach on-hangup`
+/// detaches, any other is wiped
+fn hang_up(stdout: &mut Output, buffer: &mut SecureBuffer) -> io::Result<()> {
+    #[cfg(unix)]
+    if let Some(verifier) = buffer.take_detach(true) {
+        return detach(stdout, verifier);
     }
+    let _ = stdout;
+    buffer.disconnect()
+}
 
-    // --- AUTOCOMPLETE ---
-    fn autocomplete(&mut self) {
-        // Very basic implementation: complete files in current dir based on last word
-        let parts: Vec<&str> = self.content.split_whitespace().collect();
-        if let Some(last_word) = parts.last() {
-            let path_to_check = if last_word.contains('/') {
-                Path::new(last_word).parent().unwrap_or(Path::new("."))
-            } else {
-                Path::new(".")
-            };
+/// `--attach [pid]`: the pid, when one follows the flag
+fn attach_from_args() -> Option<Option<u32>> {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let position = args.iter().position(|arg| arg == ATTACH_FLAG)?;
+    Some(args.get(position + 1).and_then(|pid| pid.parse().ok()))
+}
 
-            let prefix = Path::new(last_word)
-                .file_name()
-                .and_then(|s| s.to_str())
-                .unwrap_or("");
-
-            if let Ok(entries) = fs::read_dir(path_to_check) {
-                let matches: Vec<String> = entries
-                    .filter_map(|e| e.ok())
-                    .map(|e| e.file_name().to_string_lossy().to_string())
-                    .filter(|name| name.starts_with(prefix))
-                    .collect();
-
-                if matches.len() == 1 {
-                    let completion = &matches[0][prefix.len()..];
-                    for c in completion.chars() {
-                        self.insert(c);
-                    }
-                } else if matches.len() > 1 {
-                    // TODO: Show possibilities? For now, just cycle or do nothing.
+/// A bracketed paste lands on the input line as one insertion and is never run by
+/// itself; a multi-line paste is joined into one line, after confirmation
+fn paste(stdout: &mut Output, buffer: &mut SecureBuffer, mut text: String) -> io::Result<()> {
+    let lines = text.trim_end_matches(['\r', '\n']).lines().count();
+    let accepted = lines <= 1 || {
+        write!(stdout, "\r\n")?;
+        confirm(&format!(
+            "Pasted text spans {} lines. Insert it as one line? [y/N] ",
+            lines
+        ))?
+    };
+    if accepted {
+        for (i, line) in text.trim_end_matches(['\r', '\n']).lines().enumerate() {
+            if i > 0 {
+                buffer.insert(' ');
+            }
+            // Tabs become spaces; escape sequences and other controls are dropped
+            for c in line.chars() {
+                match c {
+                    '\t' => buffer.insert(' '),
+                    c if c.is_control() => {}
+                    c => buffer.insert(c),
                 }
             }
         }
     }
-
-    fn clear_state(&mut self) {
-        self.content.clear();
-        self.cursor_pos = 0;
-        self.history_index = self.history.len();
+    text.zeroize();
+    if lines > 1 {
+        redraw_line(stdout, buffer)
+    } else {
+        update_line(stdout, buffer)
     }
+}
 
-    /// Securely purge command history from memory
-    fn purge_history(&mut self) {
-        // Zeroize each string in history before clearing
-        for cmd in self.history.iter_mut() {
-            cmd.zeroize();
+fn main() -> io::Result<()> {
+    let profile = match profile_from_args() {
+        Ok(profile) => profile,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(2);
         }
-        self.history.clear();
-        self.history_index = 0;
-    }
-
-    // --- EXECUTION ---
-
-    fn process_command(&mut self) -> CommandResult {
-        let trimmed_command = self.content.trim();
-
-        if trimmed_command.is_empty() {
-            return CommandResult::NoOp;
+    };
+
+    // Attaching only relays this terminal to a detached session's socket
+    if let Some(pid) = attach_from_args() {
+        #[cfg(unix)]
+        let attached = detach::attach(pid);
+        #[cfg(not(unix))]
+        let attached: Result<(), String> = {
+            let _ = pid;
+            Err("Detached sessions are not supported on this platform.".to_string())
+        };
+        if let Err(e) = attached {
+            eprintln!("{}", e);
+            std::process::exit(1);
         }
+        return Ok(());
+    }
 
-        // Increment command counter
-        self.command_count += 1;
-
-        // Periodic security check in paranoid mode (every 5 commands)
-        if self.paranoid_mode && self.command_count.is_multiple_of(5) && is_debugger_present() {
-            let _ = execute!(io::stdout(), Clear(ClearType::All), MoveToColumn(0));
-            println!("⚠ PERIODIC CHECK: DEBUGGER DETECTED");
-            println!("PARANOID MODE - INITIATING EMERGENCY SHUTDOWN...");
-            std::thread::sleep(std::time::Duration::from_millis(500));
-            std::process::exit(137);
+    // Headless: keys come from a script on stdin and the screen lives in memory
+    let headless = env::args().any(|arg| arg == HEADLESS_FLAG);
+    if headless {
+        let mut script = String::new();
+        io::stdin().read_to_string(&mut script)?;
+        match parse_script(&script) {
+            Ok(steps) => start_headless(steps, HEADLESS_SIZE),
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(2);
+            }
         }
+    }
 
-        if let Some(ghost_cmd) = trimmed_command.strip_prefix(GHOST_COMMAND_PREFIX) {
-            let parts: Vec<&str> = ghost_cmd.splitn(2, ' ').collect();
-            let cmd = parts[0];
-            let args = if parts.len() > 1 { parts[1] } else { "" };
-
-            match cmd {
-                "panic" => {
-                    // NUCLEAR OPTION
-                    let _ = execute!(io::stdout(), Clear(ClearType::All), MoveToColumn(0));
-                    println!("KERNEL PANIC - MEMORY CORRUPTION DETECTED at 0xDEADBEEF");
-                    println!("Dumping core to /dev/null...");
-                    std::thread::sleep(std::time::Duration::from_millis(1500));
-                    std::process::exit(137); // Simulated crash
-                }
-                "status" => CommandResult::Output(
-                    "GHOST MODE ACTIVE. MEMORY SECURE. TRACE: NONE.".to_string(),
-                ),
-                "security-status" => {
-                    let status = initialize_security();
-                    CommandResult::Output(status.report())
-                }
-                "exit" => CommandResult::Exit,
-                "clear" => {
-                    let _ = execute!(io::stdout(), Clear(ClearType::All), MoveToColumn(0));
-                    CommandResult::NoOp
-                }
-                "history" => {
-                    if self.history.is_empty() {
-                        CommandResult::Output("No commands in history.".to_string())
-                    } else {
-                        let mut output = String::from("Command History (RAM only):\r\n");
-                        for (i, cmd) in self.history.iter().enumerate() {
-                            output.push_str(&format!("  {}: {}\r\n", i + 1, cmd));
-                        }
-                        CommandResult::Output(output)
-                    }
-                }
-                "purge-history" => {
-                    let count = self.history.len();
-                    self.purge_history();
-                    CommandResult::Output(format!(
-                        "HISTORY PURGED. {} COMMANDS ZEROIZED FROM MEMORY.",
-                        count
-                    ))
-                }
-                "cp" => {
-                    if args.is_empty() {
-                        CommandResult::Output("Error: No content to copy.".to_string())
-                    } else {
-                        match SecureClipboard::new(true) {
-                            Ok(clipboard) => {
-                                match clipboard.copy_with_timeout(args.to_string(), 30) {
-                                    Ok(msg) => CommandResult::Output(msg),
-                                    Err(e) => CommandResult::Output(e),
-                                }
-                            }
-                            Err(e) => CommandResult::Output(e),
-                        }
-                    }
-                }
-                "decrypt" => {
-                    if args.is_empty() {
-                        CommandResult::Output("Usage: ::decrypt <key>".to_string())
-                    } else {
-                        match SecureClipboard::new(false) {
-                            Ok(clipboard) => match clipboard.decrypt_clipboard(args) {
-                                Ok(plaintext) => {
-                                    CommandResult::Output(format!("Decrypted: {}", plaintext))
-                                }
-                                Err(e) => CommandResult::Output(e),
-                            },
-                            Err(e) => CommandResult::Output(e),
-                        }
-                    }
-                }
-                "anti-debug" => {
-                    if is_debugger_present() {
-                        if self.paranoid_mode {
-                            // Auto-panic in paranoid mode
-                            let _ = execute!(io::stdout(), Clear(ClearType::All), MoveToColumn(0));
-                            println!("⚠ DEBUGGER DETECTED - PARANOID MODE ACTIVE");
-                            println!("INITIATING EMERGENCY SHUTDOWN...");
-                            std::thread::sleep(std::time::Duration::from_millis(500));
-                            std::process::exit(137);
-                        } else {
-                            CommandResult::Output("⚠ WARNING: DEBUGGER DETECTED!".to_string())
-                        }
-                    } else {
-                        CommandResult::Output("✓ No debugger detected.".to_string())
-                    }
-                }
-                "paranoid" => {
-                    if args == "on" {
-                        self.paranoid_mode = true;
-                        CommandResult::Output(
-                            "⚠ PARANOID MODE ENABLED\r\n\
-                            - Auto-panic on debugger detection\r\n\
-                            - Periodic security checks every 5 commands\r\n\
-                            - Enhanced threat monitoring"
-                                .to_string(),
-                        )
-                    } else if args == "off" {
-                        self.paranoid_mode = false;
-                        CommandResult::Output("PARANOID MODE DISABLED".to_string())
-                    } else {
-                        CommandResult::Output(format!(
-                            "Paranoid mode: {}\r\nUsage: ::paranoid on|off",
-                            if self.paranoid_mode {
-                                "ENABLED"
-                            } else {
-                                "DISABLED"
-                            }
-                        ))
-                    }
-                }
-                _ => CommandResult::Output(format!("Unknown GHOST command: '{}'", cmd)),
-            }
-        } else {
-            // Built-in: cd
-            let parts: Vec<&str> = trimmed_command.splitn(2, ' ').collect();
-            if parts[0] == "cd" {
-                let path_str = parts.get(1).unwrap_or(&"~");
-                let path = match *path_str {
-                    "~" => env::var("HOME").unwrap_or_else(|_| "/".to_string()),
-                    _ => path_str.to_string(),
-                };
-                match env::set_current_dir(&path) {
-                    Ok(_) => return CommandResult::NoOp,
-                    Err(e) => return CommandResult::Output(format!("cd: {}", e)),
-                }
-            }
+    // 1. PROCESS MASKING
+    mask_process(&profile.settings());
 
-            // Built-in: clear (standard shell alias)
-            if parts[0] == "clear" {
-                let _ = execute!(io::stdout(), Clear(ClearType::All), MoveToColumn(0));
-                return CommandResult::NoOp;
-            }
+    let mut stdout = term::stdout();
+    write!(stdout, "Initializing Ghost Shell protocol...\r\n")?;
 
-            let shell = env::var("SHELL").unwrap_or_else(|_| "/bin/sh".to_string());
-            match Command::new(shell).arg("-c").arg(trimmed_command).output() {
-                Ok(output) => {
-                    let stdout = String::from_utf8_lossy(&output.stdout);
-                    let stderr = String::from_utf8_lossy(&output.stderr);
-                    let mut result = String::new();
-                    if !stdout.is_empty() {
-                        result.push_str(&stdout);
-                    }
-                    if !stderr.is_empty() {
-                        if !result.is_empty() {
-                            result.push_str("\r\n");
-                        }
-                        result.push_str("STDERR:\r\n");
-                        result.push_str(&stderr);
-                    }
-                    CommandResult::Output(result.replace("\n", "\r\n"))
-                }
-                Err(e) => CommandResult::Output(format!("Failed to execute process: {}\r\n", e)),
-            }
-        }
-    }
-}
+    // Baseline for detecting code injected after startup
+    record_maps_baseline();
 
-// --- UTILS ---
+    // OS-level self-sandboxing (OpenBSD pledge/unveil, FreeBSD capsicum)
+    apply_platform_sandbox();
 
-fn get_current_prompt() -> String {
-    let current_dir = env::current_dir()
-        .unwrap_or_else(|_| "/".into())
-        .file_name()
-        .unwrap_or_else(|| "gsh".as_ref())
-        .to_string_lossy()
-        .to_string();
-    format!("gsh {}>> ", current_dir)
-}
+    // Debuggers attaching from here on are refused by the kernel, not just detected
+    block_ptrace_attach();
 
-fn redraw_line(stdout: &mut io::Stdout, buffer: &SecureBuffer) -> io::Result<()> {
-    let prompt = get_current_prompt();
-    queue!(
+    // 2. RAW MODE ACQUISITION
+    if !headless {
+        enable_raw_mode()?;
+    }
+    // A closed terminal or dead parent wipes the session instead of killing it outright
+    watchdog::install();
+    // Pastes arrive as one event instead of keystrokes that could press Enter
+    execute!(
         stdout,
-        MoveToColumn(0),
-        Clear(ClearType::UntilNewLine),
-        Print(&prompt),
-        Print(&buffer.content),
-        MoveToColumn((prompt.len() + buffer.cursor_pos) as u16)
+        EnableBracketedPaste,
+        Clear(ClearType::All),
+        MoveToColumn(0)
     )?;
-    stdout.flush()?;
-    Ok(())
-}
-
-fn main() -> io::Result<()> {
-    // 1. PROCESS MASKING
-    #[cfg(target_os = "linux")]
-    {
-        if let Ok(fake_name) = CString::new("systemd-journald") {
-            let _ = prctl::set_name(fake_name.to_str().unwrap());
+    mask_title(&mut stdout, DEFAULT_TITLE)?;
+
+    // The session passphrase derives the master key and unlocks the config file.
+    // An armed honeypot makes the master passphrase mandatory.
+    let honeypot = Honeypot::load().unwrap_or_else(|e| {
+        let _ = write!(stdout, "{} Honeypot not armed.\r\n", e);
+        None
+    });
+    let master_passphrase = env::args().any(|arg| arg == MASTER_PASSPHRASE_FLAG);
+    let config_file = config_path().filter(|path| path.is_file());
+    let (passphrase, keys) = match &honeypot {
+        Some(honeypot) => unlock_or_trap(honeypot)?,
+        None => {
+            let passphrase = match (master_passphrase, &config_file) {
+                (true, _) => read_secret("Master passphrase: ")?,
+                (false, Some(_)) => read_secret("Config passphrase (Esc for defaults): ")?,
+                (false, None) => None,
+            };
+            let keys = if master_passphrase {
+                session_keys_from_passphrase(passphrase.as_ref().map(|p| p.expose().as_str()))?
+            } else {
+                SessionKeys::random()
+            };
+            (passphrase, keys)
         }
+    };
+    // One clipboard and one security scan for the whole session
+    let shell = GhostShell::new();
+    let threats = shell.security_status().threats_detected.len();
+    let mut buffer = SecureBuffer::new(keys, shell);
+    if threats > 0 {
+        let alert = format!(
+            "STARTUP SCAN: {} THREAT(S) DETECTED. Run ::security-status for details.",
+            threats
+        );
+        write!(stdout, "{}\r\n", buffer.theme().alert(&alert))?;
+    }
+    if let Some(session) = ssh_session() {
+        let warning = format!(
+            "⚠ {}: everything shown here crosses the network to the client. Clipboard copies go to the client's terminal (OSC 52).",
+            session.describe()
+        );
+        write!(
+            stdout,
+            "{}\r\n",
+            buffer.theme().paint(Role::Warning, &warning)
+        )?;
+    }
+    if profile != SecurityProfile::Standard {
+        write!(stdout, "{}\r\n", buffer.apply_profile(profile))?;
+    }
+    if let (Some(path), Some(p)) = (&config_file, &passphrase) {
+        match load_config(path, p.expose()) {
+            Ok(config) => buffer.apply_config(path, config),
+            Err(e) => write!(stdout, "{} Using defaults.\r\n", e)?,
+        }
+    }
+    drop(passphrase);
+    if let Some(message) = buffer.restore_history() {
+        write!(stdout, "{}\r\n", message)?;
     }
-
-    println!("Initializing Ghost Shell protocol...");
-
-    // 2. RAW MODE ACQUISITION
-    enable_raw_mode()?;
-    let mut stdout = io::stdout();
-    execute!(stdout, Clear(ClearType::All), MoveToColumn(0))?;
-
-    let mut buffer = SecureBuffer::new();
     let mut running = true;
+    let posture = PostureMonitor::start();
+    let mut status = String::new(); // Last status bar drawn; emptied to force a redraw
+    let mut size = term::size().unwrap_or_default();
 
     // Initial draw
-    redraw_line(&mut stdout, &buffer)?;
+    reserve_status_row(&mut stdout)?;
+    redraw_line(&mut stdout, &mut buffer)?;
 
     while running {
-        if event::poll(std::time::Duration::from_millis(100))? {
-            if let Event::Key(KeyEvent {
+        if watchdog::disconnected() {
+            hang_up(&mut stdout, &mut buffer)?;
+        }
+        if let Some(alert) = buffer.check_clipboard_tamper() {
+            queue!(stdout, MoveToColumn(0), Clear(ClearType::CurrentLine))?;
+            write!(stdout, "{}\r\n", buffer.theme().alert(&alert))?;
+            redraw_line(&mut stdout, &mut buffer)?;
+        }
+
+        // The region and the bar row move with the terminal size
+        let current_size = term::size().unwrap_or_default();
+        if current_size != size {
+            size = current_size;
+            reserve_status_row(&mut stdout)?;
+            status.clear();
+            redraw_line(&mut stdout, &mut buffer)?;
+        }
+
+        // Posture changes from the monitor thread, and the clipboard countdown ticking
+        let current_status = buffer.status_bar(posture.posture(), size.0 as usize);
+        if current_status != status {
+            draw_status_bar(&mut stdout, &current_status)?;
+            status = current_status;
+        }
+
+        let ready = match term::poll_event(Duration::from_millis(100)) {
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break, // Script finished
+            Err(_) if watchdog::disconnected() => {
+                hang_up(&mut stdout, &mut buffer)?;
+                continue;
+            }
+            ready => ready?,
+        };
+        if ready {
+            let event = match term::read_event() {
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+                Err(_) if watchdog::disconnected() => {
+                    hang_up(&mut stdout, &mut buffer)?;
+                    continue;
+                }
+                event => event?,
+            };
+            if let Event::Paste(text) = event {
+                paste(&mut stdout, &mut buffer, text)?;
+            } else if let Event::Resize(..) = event {
+                // A client attaching to a detached session needs the whole prompt
+                reserve_status_row(&mut stdout)?;
+                status.clear();
+                redraw_line(&mut stdout, &mut buffer)?;
+            } else if let Event::Key(KeyEvent {
                 code, modifiers, ..
-            }) = event::read()?
+            }) = event
             {
                 match code {
                     KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => {
-                        buffer.content.clear();
-                        buffer.cursor_pos = 0;
+                        buffer.discard_line();
                         write!(stdout, "^C\r\n")?;
-                        redraw_line(&mut stdout, &buffer)?;
+                        redraw_line(&mut stdout, &mut buffer)?;
                     }
                     KeyCode::Char('l') if modifiers.contains(KeyModifiers::CONTROL) => {
                         // Ctrl+L to clear screen
                         execute!(stdout, Clear(ClearType::All), MoveToColumn(0))?;
-                        redraw_line(&mut stdout, &buffer)?;
+                        status.clear();
+                        redraw_line(&mut stdout, &mut buffer)?;
                     }
-                    KeyCode::Enter => {
-                        write!(stdout, "\r\n")?;
-
-                        // Process command and handle result
-                        let result = buffer.process_command();
-
-                        match result {
-                            CommandResult::Exit => {
-                                running = false;
-                            }
-                            CommandResult::Output(output) => {
-                                write!(stdout, "{}\r\n", output)?;
-                                buffer.commit_history();
-                                buffer.clear_state();
-                                redraw_line(&mut stdout, &buffer)?;
-                            }
-                            CommandResult::NoOp => {
-                                buffer.commit_history();
-                                buffer.clear_state();
-                                redraw_line(&mut stdout, &buffer)?;
-                            }
+                    KeyCode::Char('t') if modifiers.contains(KeyModifiers::CONTROL) => {
+                        // Ctrl+T: fuzzy finder over history and ghost commands
+                        buffer.fuzzy_pick()?;
+                        reserve_status_row(&mut stdout)?;
+                        status.clear();
+                        redraw_line(&mut stdout, &mut buffer)?;
+                    }
+                    KeyCode::Char(c @ '1'..='9') if modifiers.contains(KeyModifiers::ALT) => {
+                        // Alt+<digit>: show that tab, opening it if need be
+                        queue!(stdout, MoveToColumn(0), Clear(ClearType::CurrentLine))?;
+                        match buffer.switch_tab(c as usize - '0' as usize) {
+                            Ok(()) => write!(stdout, "{}\r\n", buffer.tab_banner())?,
+                            Err(e) => write!(stdout, "{}\r\n", buffer.theme().paint_error(&e))?,
                         }
+                        execute!(stdout, SetTitle(buffer.title()))?;
+                        status.clear();
+                        redraw_line(&mut stdout, &mut buffer)?;
+                    }
+                    KeyCode::Char(c)
+                        if modifiers.contains(KeyModifiers::CONTROL)
+                            && buffer.binding(c).is_some() =>
+                    {
+                        // Config key binding: run the bound command as if typed
+                        let command = buffer.binding(c).unwrap_or_default().to_string();
+                        buffer.clear_state();
+                        for c in command.chars() {
+                            buffer.insert(c);
+                        }
+                        redraw_line(&mut stdout, &mut buffer)?;
+                        running = submit_line(&mut stdout, &mut buffer)?;
+                        status.clear();
+                    }
+                    KeyCode::Enter => {
+                        running = submit_line(&mut stdout, &mut buffer)?;
+                        status.clear();
                     }
                     KeyCode::Char(c) => {
                         buffer.insert(c);
-                        redraw_line(&mut stdout, &buffer)?;
+                        update_line(&mut stdout, &mut buffer)?;
                     }
                     KeyCode::Backspace => {
                         buffer.backspace();
-                        redraw_line(&mut stdout, &buffer)?;
+                        update_line(&mut stdout, &mut buffer)?;
                     }
                     KeyCode::Left => {
                         buffer.move_left();
-                        redraw_line(&mut stdout, &buffer)?;
+                        update_line(&mut stdout, &mut buffer)?;
                     }
                     KeyCode::Right => {
                         buffer.move_right();
-                        redraw_line(&mut stdout, &buffer)?;
+                        update_line(&mut stdout, &mut buffer)?;
                     }
                     KeyCode::Up => {
                         buffer.history_up();
-                        redraw_line(&mut stdout, &buffer)?;
+                        update_line(&mut stdout, &mut buffer)?;
                     }
                     KeyCode::Down => {
                         buffer.history_down();
-                        redraw_line(&mut stdout, &buffer)?;
+                        update_line(&mut stdout, &mut buffer)?;
                     }
                     KeyCode::Tab => {
                         buffer.autocomplete();
-                        redraw_line(&mut stdout, &buffer)?;
+                        update_line(&mut stdout, &mut buffer)?;
                     }
                     _ => {} // Ignore other keys
                 }
@@ -531,9 +618,18 @@ fn main() -> io::Result<()> {
     }
 
     // 3. CLEANUP & EXIT
-    disable_raw_mode()?;
-    println!("\n[!] INITIATING SECURE SHUTDOWN...");
-    println!("[*] Overwriting memory buffers... DONE.");
-    println!("[*] All systems clear. Ghost Shell terminated.");
-    Ok(())
+    execute!(stdout, DisableBracketedPaste)?;
+    sanitize_screen(&mut stdout)?;
+    restore_title(&mut stdout)?;
+    if !headless {
+        disable_raw_mode()?;
+    }
+    write!(
+        stdout,
+        "\r\n[!] INITIATING SECURE SHUTDOWN...\r\n\
+        [*] Overwriting memory buffers... DONE.\r\n\
+        [*] All systems clear. Ghost Shell terminated.\r\n"
+    )?;
+    stdout.flush()?;
+    term::finish()
 }
@@ -14,6 +14,31 @@ echo ""
 echo "Running clippy..."
 cargo clippy --all-targets --all-features -- -D warnings 2>&1 | tail -n 2
 
+echo ""
+echo "Headless run (scripted keys, in-memory screen)..."
+scratch=$(mktemp -d)
+output=$(XDG_CONFIG_HOME="$scratch" XDG_DATA_HOME="$scratch" ./target/release/ghost-shell --headless <<'SCRIPT'
+line ::status
+type echo helo
+key Left
+type l
+key Enter
+key Up
+key Enter
+line ::history
+screen
+SCRIPT
+)
+rm -rf "$scratch"
+for expected in "GHOST MODE ACTIVE" "gsh .*>> echo hello" "2 .* echo hello" "Ghost Shell terminated"; do
+    if ! grep -q "$expected" <<< "$output"; then
+        echo "❌ Headless run: expected '$expected'"
+        echo "$output"
+        exit 1
+    fi
+done
+echo "Headless run OK"
+
 echo ""
 echo "✅ All checks passed!"
 echo ""